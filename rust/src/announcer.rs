@@ -0,0 +1,221 @@
+//! 候選頁數提示（無障礙播報）
+//!
+//! 習慣全鍵盤操作的使用者不一定會看狀態窗，候選超過一頁時可以用短嗶聲或
+//! 語音（SAPI）提示「還有下一頁」。播報在獨立執行緒上進行，鍵盤鉤子只負責
+//! 把要說的話丟進通道，不會被 Beep 或語音引擎卡住。
+//! `Announcer::speak` 也是其他無障礙播報共用的入口。
+//!
+//! 按了無效的鍵（打錯字根、沒有候選可送出或選）時的提示音也由播報執行緒發出（見 InvalidKeyFeedback），
+//! 音調比頁數提示低，聽得出差別。
+
+use crate::input_method::InputMethodState;
+use anyhow::{bail, Result};
+use tracing::{debug, warn};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::Media::Speech::{ISpVoice, SpVoice, SPF_ASYNC, SPF_PURGEBEFORESPEAK};
+use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_APARTMENTTHREADED};
+use windows::Win32::System::Diagnostics::Debug::Beep;
+
+/// 提示音頻率（Hz）與長度（毫秒），短到不干擾打字
+const BEEP_FREQUENCY: u32 = 880;
+const BEEP_DURATION_MS: u32 = 60;
+/// 無效按鍵的提示音頻率（Hz）
+const INVALID_KEY_BEEP_FREQUENCY: u32 = 330;
+
+/// 按了無效的鍵時的回饋（設定 play_sound_enable、invalid_key_flash）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InvalidKeyFeedback {
+    /// 發出短促的提示音
+    pub beep: bool,
+    /// 閃一下狀態窗
+    pub flash: bool,
+}
+
+impl InvalidKeyFeedback {
+    pub fn is_enabled(&self) -> bool {
+        self.beep || self.flash
+    }
+}
+
+/// 候選頁數提示方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnnounceMode {
+    /// 不提示（預設）
+    #[default]
+    Off,
+    /// 候選超過一頁時發出短嗶聲
+    Beep,
+    /// 以語音唸出頁數（語音引擎無法使用時退回嗶聲）
+    Speech,
+}
+
+impl AnnounceMode {
+    /// 從設定值解析（"off" / "beep" / "speech"，不分大小寫，空字串為預設值）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "off" => Ok(Self::Off),
+            "beep" => Ok(Self::Beep),
+            "speech" => Ok(Self::Speech),
+            other => bail!("未知的候選頁數提示方式 '{}'（可用: off、beep、speech）", other),
+        }
+    }
+}
+
+/// 候選跨頁時的 (目前頁, 總頁數)，頁數從 1 起算；只有一頁或沒有候選時為 None
+pub fn page_summary(candidates: usize, per_page: usize, index: usize) -> Option<(usize, usize)> {
+    if per_page == 0 {
+        return None;
+    }
+    let total = candidates.div_ceil(per_page);
+    (total > 1).then(|| ((index / per_page + 1).min(total), total))
+}
+
+/// 語音播報的頁數文字
+pub fn page_text(page: usize, total: usize) -> String {
+    if page <= 1 {
+        format!("共 {} 頁候選", total)
+    } else {
+        format!("第 {} 頁，共 {} 頁", page, total)
+    }
+}
+
+/// 播報執行緒的工作項目
+enum Announcement {
+    Beep,
+    /// 無效按鍵的提示音
+    InvalidKey,
+    Speak(String),
+}
+
+/// 無障礙播報器
+pub struct Announcer {
+    mode: Mutex<AnnounceMode>,
+    sender: Option<Sender<Announcement>>,
+    /// 上一次提示的 (字根, 目前頁, 總頁數)，同一狀態不重複提示
+    last: Mutex<Option<(String, usize, usize)>>,
+}
+
+impl Announcer {
+    /// 建立播報器並啟動播報執行緒（執行緒建立失敗時只是不播報）
+    pub fn new(mode: AnnounceMode) -> Self {
+        let (sender, receiver) = mpsc::channel::<Announcement>();
+        let sender = thread::Builder::new()
+            .name("announcer".to_string())
+            .spawn(move || {
+                let mut voice: Option<ISpVoice> = None;
+                let mut voice_failed = false;
+                for item in receiver {
+                    match item {
+                        Announcement::Beep => beep(),
+                        Announcement::InvalidKey => {
+                            let _ = unsafe { Beep(INVALID_KEY_BEEP_FREQUENCY, BEEP_DURATION_MS) };
+                        }
+                        Announcement::Speak(text) => {
+                            if voice.is_none() && !voice_failed {
+                                match create_voice() {
+                                    Ok(v) => voice = Some(v),
+                                    Err(e) => {
+                                        warn!("無法啟用語音播報，改用提示音: {}", e);
+                                        voice_failed = true;
+                                    }
+                                }
+                            }
+                            match &voice {
+                                Some(voice) => {
+                                    let flags = (SPF_ASYNC.0 | SPF_PURGEBEFORESPEAK.0) as u32;
+                                    if let Err(e) = unsafe { voice.Speak(PCWSTR(HSTRING::from(text.as_str()).as_ptr()), flags, None) } {
+                                        warn!("語音播報失敗: {}", e);
+                                        beep();
+                                    }
+                                }
+                                None => beep(),
+                            }
+                        }
+                    }
+                }
+            })
+            .map_err(|e| warn!("無法啟動播報執行緒: {}", e))
+            .ok()
+            .map(|_| sender);
+        Self { mode: Mutex::new(mode), sender, last: Mutex::new(None) }
+    }
+
+    pub fn set_mode(&self, mode: AnnounceMode) {
+        *self.mode.lock().unwrap() = mode;
+    }
+
+    /// 唸出一段文字；提示方式為嗶聲時只發出提示音，關閉時不動作
+    pub fn speak(&self, text: &str) {
+        let item = match *self.mode.lock().unwrap() {
+            AnnounceMode::Off => return,
+            AnnounceMode::Beep => Announcement::Beep,
+            AnnounceMode::Speech => Announcement::Speak(text.to_string()),
+        };
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(item);
+        }
+    }
+
+    /// 無效按鍵的提示音（不受頁數提示方式影響，是否發出由呼叫端依 InvalidKeyFeedback 決定）
+    pub fn invalid_key(&self) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(Announcement::InvalidKey);
+        }
+    }
+
+    /// 候選跨頁時提示頁數（字根或頁數沒變就不重複提示）
+    pub fn announce_pages(&self, state: &InputMethodState) {
+        if *self.mode.lock().unwrap() == AnnounceMode::Off {
+            return;
+        }
+        let summary = page_summary(state.candidates.len(), state.candidates_per_page, state.candidate_index);
+        let key = summary.map(|(page, total)| (state.current_code.clone(), page, total));
+        {
+            let mut last = self.last.lock().unwrap();
+            if *last == key {
+                return;
+            }
+            *last = key;
+        }
+        if let Some((page, total)) = summary {
+            debug!("候選頁數提示: '{}' {}/{}", state.current_code, page, total);
+            self.speak(&page_text(page, total));
+        }
+    }
+}
+
+fn beep() {
+    let _ = unsafe { Beep(BEEP_FREQUENCY, BEEP_DURATION_MS) };
+}
+
+/// 在播報執行緒上建立 SAPI 語音物件
+fn create_voice() -> Result<ISpVoice> {
+    unsafe {
+        // 已初始化過（S_FALSE）也算成功；模式不同時 CoCreateInstance 仍可能成功，錯誤交給它回報
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        Ok(CoCreateInstance(&SpVoice, None, CLSCTX_ALL)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_summary_and_text() {
+        assert_eq!(page_summary(0, 6, 0), None);
+        assert_eq!(page_summary(6, 6, 0), None);
+        assert_eq!(page_summary(7, 6, 0), Some((1, 2)));
+        assert_eq!(page_summary(13, 6, 6), Some((2, 3)));
+        assert_eq!(page_text(1, 2), "共 2 頁候選");
+        assert_eq!(page_text(2, 3), "第 2 頁，共 3 頁");
+        assert_eq!(AnnounceMode::parse("Speech").unwrap(), AnnounceMode::Speech);
+        assert_eq!(AnnounceMode::parse("").unwrap(), AnnounceMode::Off);
+        assert!(AnnounceMode::parse("loud").is_err());
+        assert!(!InvalidKeyFeedback::default().is_enabled());
+        assert!(InvalidKeyFeedback { beep: false, flash: true }.is_enabled());
+    }
+}
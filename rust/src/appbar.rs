@@ -0,0 +1,134 @@
+//! 停駐模式（AppBar）
+//!
+//! 設定 gui_dock 為 "top" 或 "bottom" 時，狀態窗不再浮動，而是以 Windows AppBar 註冊，
+//! 像工具列一樣佔據螢幕頂端或底部一整條，其他視窗最大化時不會蓋住它。
+//! 每次顯示時向系統註冊並取得位置，隱藏時取消註冊把空間還給桌面。
+//! 沒有處理 ABN_POSCHANGED 通知（fltk 視窗收不到自訂訊息），工作列移動後下次顯示時才會重新排位置。
+
+use anyhow::{bail, Result};
+use tracing::{debug, info};
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::Graphics::Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST};
+use windows::Win32::UI::Shell::{
+    SHAppBarMessage, ABE_BOTTOM, ABE_TOP, ABM_GETTASKBARPOS, ABM_NEW, ABM_QUERYPOS, ABM_REMOVE, ABM_SETPOS,
+    APPBARDATA,
+};
+
+/// 狀態窗的停駐位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DockEdge {
+    /// 浮動視窗（預設）
+    #[default]
+    Floating,
+    /// 停駐在螢幕頂端
+    Top,
+    /// 停駐在螢幕底部
+    Bottom,
+}
+
+impl DockEdge {
+    /// 從設定值解析（"floating"、"top"、"bottom"，不分大小寫）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "floating" => Ok(Self::Floating),
+            "top" => Ok(Self::Top),
+            "bottom" => Ok(Self::Bottom),
+            other => bail!("未知的停駐位置 '{}'（可用: floating、top、bottom）", other),
+        }
+    }
+
+    fn abe(self) -> Option<u32> {
+        match self {
+            Self::Floating => None,
+            Self::Top => Some(ABE_TOP),
+            Self::Bottom => Some(ABE_BOTTOM),
+        }
+    }
+}
+
+/// 在 area 內貼著停駐邊、高度為 thickness 的一條（area 為整個螢幕或系統調整過的建議位置）
+fn edge_rect(edge: DockEdge, area: &RECT, thickness: i32) -> RECT {
+    match edge {
+        DockEdge::Top | DockEdge::Floating => RECT { bottom: area.top + thickness, ..*area },
+        DockEdge::Bottom => RECT { top: area.bottom - thickness, ..*area },
+    }
+}
+
+fn appbar_data(hwnd: HWND) -> APPBARDATA {
+    APPBARDATA { cbSize: std::mem::size_of::<APPBARDATA>() as u32, hWnd: hwnd, ..Default::default() }
+}
+
+/// 把視窗註冊為停駐在 edge 的 AppBar（已註冊時重新排位置），回傳視窗應放的位置
+pub fn dock(hwnd: HWND, edge: DockEdge, thickness: i32) -> Result<RECT> {
+    let Some(abe) = edge.abe() else {
+        bail!("浮動模式不需要停駐");
+    };
+    if hwnd.0 == 0 {
+        bail!("視窗尚未建立");
+    }
+    unsafe {
+        let mut data = appbar_data(hwnd);
+        // 已註冊過時 ABM_NEW 回傳 0，照樣重新排位置
+        if SHAppBarMessage(ABM_NEW, &mut data) != 0 {
+            info!("狀態窗已註冊為 AppBar（{:?}）", edge);
+        }
+
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO { cbSize: std::mem::size_of::<MONITORINFO>() as u32, ..Default::default() };
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            undock(hwnd);
+            bail!("無法取得螢幕範圍");
+        }
+
+        data.uEdge = abe;
+        data.rc = edge_rect(edge, &info.rcMonitor, thickness);
+        // 系統依其他 AppBar（例如工作列）縮小建議位置，再依停駐邊套回高度
+        SHAppBarMessage(ABM_QUERYPOS, &mut data);
+        data.rc = edge_rect(edge, &data.rc, thickness);
+        SHAppBarMessage(ABM_SETPOS, &mut data);
+        debug!("AppBar 位置: {:?}", data.rc);
+        Ok(data.rc)
+    }
+}
+
+/// 取消 AppBar 註冊，把佔用的空間還給桌面（沒有註冊時不影響）
+pub fn undock(hwnd: HWND) {
+    if hwnd.0 == 0 {
+        return;
+    }
+    unsafe {
+        let mut data = appbar_data(hwnd);
+        SHAppBarMessage(ABM_REMOVE, &mut data);
+    }
+}
+
+/// 系統工作列的範圍與所在的邊（ABE_TOP、ABE_BOTTOM 等），取不到時回傳 None
+pub fn taskbar() -> Option<(RECT, u32)> {
+    unsafe {
+        let mut data = appbar_data(HWND(0));
+        if SHAppBarMessage(ABM_GETTASKBARPOS, &mut data) == 0 {
+            return None;
+        }
+        Some((data.rc, data.uEdge))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dock_rect_spans_edge() {
+        assert_eq!(DockEdge::parse(" Bottom ").unwrap(), DockEdge::Bottom);
+        assert_eq!(DockEdge::parse("").unwrap(), DockEdge::Floating);
+        assert!(DockEdge::parse("left").is_err());
+
+        let monitor = RECT { left: 0, top: 0, right: 1920, bottom: 1080 };
+        assert_eq!(edge_rect(DockEdge::Top, &monitor, 100), RECT { left: 0, top: 0, right: 1920, bottom: 100 });
+        let bottom = edge_rect(DockEdge::Bottom, &monitor, 100);
+        assert_eq!(bottom, RECT { left: 0, top: 980, right: 1920, bottom: 1080 });
+        // 底部有 40 像素高的工作列時，系統把建議位置的下緣往上移，再套回高度
+        let proposed = RECT { bottom: 1040, ..bottom };
+        assert_eq!(edge_rect(DockEdge::Bottom, &proposed, 100), RECT { left: 0, top: 940, right: 1920, bottom: 1040 });
+    }
+}
@@ -0,0 +1,190 @@
+//! 簡易 zip 打包模組
+//!
+//! 只支援寫入（deflate 壓縮、UTF-8 檔名），足夠產生回報包這類小型壓縮檔
+
+use anyhow::Result;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// zip 壓縮方式：deflate
+const METHOD_DEFLATE: u16 = 8;
+/// 一般用途旗標：檔名使用 UTF-8
+const FLAG_UTF8: u16 = 0x0800;
+/// 解壓所需版本（2.0）
+const VERSION: u16 = 20;
+
+struct Entry {
+    name: String,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// zip 寫入器，所有內容先放在記憶體，finish 時回傳完整的 zip 位元組
+pub struct ZipWriter {
+    buffer: Vec<u8>,
+    entries: Vec<Entry>,
+    dos_time: u16,
+    dos_date: u16,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        let (dos_date, dos_time) = dos_date_time(SystemTime::now());
+        Self { buffer: Vec::new(), entries: Vec::new(), dos_time, dos_date }
+    }
+
+    /// 加入一個檔案
+    pub fn add_file(&mut self, name: &str, content: &[u8]) -> Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content)?;
+        let compressed = encoder.finish()?;
+
+        let entry = Entry {
+            name: name.replace('\\', "/"),
+            crc: crc32fast::hash(content),
+            compressed_size: u32::try_from(compressed.len())?,
+            size: u32::try_from(content.len())?,
+            offset: u32::try_from(self.buffer.len())?,
+        };
+
+        // 本地檔案標頭
+        let buf = &mut self.buffer;
+        put_u32(buf, 0x0403_4b50);
+        put_u16(buf, VERSION);
+        put_u16(buf, FLAG_UTF8);
+        put_u16(buf, METHOD_DEFLATE);
+        put_u16(buf, self.dos_time);
+        put_u16(buf, self.dos_date);
+        put_u32(buf, entry.crc);
+        put_u32(buf, entry.compressed_size);
+        put_u32(buf, entry.size);
+        put_u16(buf, entry.name.len() as u16);
+        put_u16(buf, 0);
+        buf.extend_from_slice(entry.name.as_bytes());
+        buf.extend_from_slice(&compressed);
+
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// 寫入中央目錄並回傳完整的 zip 內容
+    pub fn finish(mut self) -> Vec<u8> {
+        let central_start = self.buffer.len() as u32;
+        let buf = &mut self.buffer;
+        for entry in &self.entries {
+            put_u32(buf, 0x0201_4b50);
+            put_u16(buf, VERSION);
+            put_u16(buf, VERSION);
+            put_u16(buf, FLAG_UTF8);
+            put_u16(buf, METHOD_DEFLATE);
+            put_u16(buf, self.dos_time);
+            put_u16(buf, self.dos_date);
+            put_u32(buf, entry.crc);
+            put_u32(buf, entry.compressed_size);
+            put_u32(buf, entry.size);
+            put_u16(buf, entry.name.len() as u16);
+            put_u16(buf, 0); // extra 長度
+            put_u16(buf, 0); // 註解長度
+            put_u16(buf, 0); // 磁碟編號
+            put_u16(buf, 0); // 內部屬性
+            put_u32(buf, 0); // 外部屬性
+            put_u32(buf, entry.offset);
+            buf.extend_from_slice(entry.name.as_bytes());
+        }
+        let central_size = buf.len() as u32 - central_start;
+
+        // 中央目錄結尾
+        put_u32(buf, 0x0605_4b50);
+        put_u16(buf, 0);
+        put_u16(buf, 0);
+        put_u16(buf, self.entries.len() as u16);
+        put_u16(buf, self.entries.len() as u16);
+        put_u32(buf, central_size);
+        put_u32(buf, central_start);
+        put_u16(buf, 0);
+
+        self.buffer
+    }
+}
+
+/// 將 UTC 時間轉成 (年, 月, 日, 時, 分, 秒)
+pub fn civil_time(time: SystemTime) -> (i64, u32, u32, u32, u32, u32) {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    // Howard Hinnant 的 civil_from_days 演算法
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day, (rem / 3_600) as u32, (rem % 3_600 / 60) as u32, (rem % 60) as u32)
+}
+
+/// zip 使用的 DOS 日期與時間
+fn dos_date_time(time: SystemTime) -> (u16, u16) {
+    let (year, month, day, hour, minute, second) = civil_time(time);
+    let year = year.clamp(1980, 2107) as u16;
+    let date = ((year - 1980) << 9) | ((month as u16) << 5) | day as u16;
+    let time = ((hour as u16) << 11) | ((minute as u16) << 5) | (second as u16 / 2);
+    (date, time)
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+    use std::time::Duration;
+
+    #[test]
+    fn test_civil_time() {
+        assert_eq!(civil_time(UNIX_EPOCH), (1970, 1, 1, 0, 0, 0));
+        // 2024-02-29 12:34:56 UTC
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(civil_time(time), (2024, 2, 29, 12, 34, 56));
+    }
+
+    #[test]
+    fn test_zip_layout() {
+        let mut zip = ZipWriter::new();
+        zip.add_file("report.txt", "字典統計".as_bytes()).unwrap();
+        zip.add_file("logs\\uclliu.log", b"hello hello hello").unwrap();
+        let bytes = zip.finish();
+
+        // 開頭為本地檔案標頭，結尾為 22 位元組的中央目錄結尾
+        assert_eq!(&bytes[0..4], &[0x50, 0x4b, 0x03, 0x04]);
+        let eocd = &bytes[bytes.len() - 22..];
+        assert_eq!(&eocd[0..4], &[0x50, 0x4b, 0x05, 0x06]);
+        assert_eq!(u16::from_le_bytes([eocd[10], eocd[11]]), 2);
+
+        // 第一個檔案可以解壓回原內容
+        let name_len = u16::from_le_bytes([bytes[26], bytes[27]]) as usize;
+        let compressed_size = u32::from_le_bytes([bytes[18], bytes[19], bytes[20], bytes[21]]) as usize;
+        assert_eq!(&bytes[30..30 + name_len], b"report.txt");
+        let data = &bytes[30 + name_len..30 + name_len + compressed_size];
+        let mut content = String::new();
+        DeflateDecoder::new(data).read_to_string(&mut content).unwrap();
+        assert_eq!(content, "字典統計");
+
+        // 檔名統一使用 /
+        assert!(bytes.windows(14).any(|w| w == b"logs/uclliu.log"[..14].as_ref()));
+    }
+}
@@ -0,0 +1,77 @@
+//! 聯想詞
+//!
+//! 送出一個字後，從字碼表中以這個字開頭的詞組找出接下來可能要打的字詞
+//! （送出「輸」後提示「入」「出」），在輸入下一個字根前直接按選字鍵選用。
+//! 聯想表由字碼表（含額外層與使用者字典）中的詞組建立，不需要另外的檔案。
+
+use crate::candidate::clusters;
+use crate::dictionary::Dictionary;
+use std::collections::HashMap;
+
+/// 以「第一個字」查「後面接的字詞」的聯想表
+#[derive(Debug, Clone, Default)]
+pub struct AssociationTable {
+    map: HashMap<String, Vec<String>>,
+}
+
+impl AssociationTable {
+    /// 由詞組建立聯想表（單字略過，同一個聯想只留第一次出現的位置）
+    pub fn from_phrases<'a>(phrases: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut map: HashMap<String, Vec<String>> = HashMap::new();
+        for phrase in phrases {
+            let Some(&first) = clusters(phrase).first() else {
+                continue;
+            };
+            let rest = &phrase[first.len()..];
+            // 符號開頭的是顏文字之類的符號串，不適合當聯想
+            if rest.is_empty() || !first.starts_with(char::is_alphanumeric) {
+                continue;
+            }
+            let follows = map.entry(first.to_string()).or_default();
+            if !follows.iter().any(|f| f == rest) {
+                follows.push(rest.to_string());
+            }
+        }
+        Self { map }
+    }
+
+    /// 由字碼表中的詞組建立聯想表
+    pub fn from_dictionary(dictionary: &Dictionary) -> Self {
+        let entries = dictionary.entries();
+        Self::from_phrases(entries.iter().flat_map(|(_, chars)| chars.iter().map(String::as_str)))
+    }
+
+    /// 剛送出的文字之後可以接的字詞（以最後一個字查詢）
+    pub fn suggest(&self, committed: &str) -> &[String] {
+        clusters(committed)
+            .last()
+            .and_then(|last| self.map.get(*last))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// 有聯想的字數
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_follow_ups() {
+        let table = AssociationTable::from_phrases(["輸入", "輸出", "輸入", "輸入法", "入口", "一", "(╯ °□°)"]);
+        assert_eq!(table.suggest("輸"), ["入", "出", "入法"]);
+        // 以送出文字的最後一個字查詢
+        assert_eq!(table.suggest("輸入"), ["口"]);
+        assert!(table.suggest("一").is_empty());
+        assert!(table.suggest("").is_empty());
+        assert_eq!(table.len(), 2);
+    }
+}
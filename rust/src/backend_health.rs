@@ -0,0 +1,155 @@
+//! 送字後端的健康檢查
+//!
+//! 剪貼簿被其他程式鎖住、SendInput 被 UIPI 或 BlockInput 擋下時，打字只會默默沒有反應。
+//! InputSimulator 建立時對每個後端做一次不會影響前景程式的自測，結果記在 BackendHealth，
+//! 之後每次實際送字再以結果更新（第一次使用時才知道的問題也會反映出來）：
+//! - 剪貼簿：能取得剪貼簿序號（WINSTA_ACCESSCLIPBOARD 權限）並讀取目前內容，不寫入；
+//! - SendInput：送出一個位移為 0 的滑鼠移動，游標不會動；
+//! - UI Automation：能建立 CUIAutomation 物件（目前沒有以 UIA 送字，只供診斷參考）。
+//!
+//! 結果列在說明視窗與回報包的診斷報告。
+
+use tracing::{info, warn};
+use windows::core::{IUnknown, GUID};
+use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED};
+use windows::Win32::System::DataExchange::GetClipboardSequenceNumber;
+use windows::Win32::UI::Input::KeyboardAndMouse::{SendInput, INPUT, INPUT_0, INPUT_MOUSE, MOUSEEVENTF_MOVE, MOUSEINPUT};
+
+/// CUIAutomation 的 CLSID
+const CLSID_CUI_AUTOMATION: GUID = GUID::from_u128(0xff48dba4_60ef_4201_aa87_54103eef594e);
+
+/// 單一後端的狀態
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Health {
+    /// 還沒檢測
+    #[default]
+    Untested,
+    Available,
+    /// 不可用，附上原因
+    Unavailable(String),
+}
+
+impl Health {
+    fn from_result(result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => Self::Available,
+            Err(reason) => Self::Unavailable(reason),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Self::Untested => "未檢測".to_string(),
+            Self::Available => "可用".to_string(),
+            Self::Unavailable(reason) => format!("不可用（{}）", reason),
+        }
+    }
+}
+
+/// 各送字後端的狀態
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackendHealth {
+    pub clipboard: Health,
+    pub send_input: Health,
+    pub uia: Health,
+}
+
+impl BackendHealth {
+    /// 對每個後端做一次自測並記錄結果
+    pub fn probe() -> Self {
+        let health = Self {
+            clipboard: Health::from_result(probe_clipboard()),
+            send_input: Health::from_result(probe_send_input()),
+            uia: Health::from_result(probe_uia()),
+        };
+        for line in health.lines() {
+            if line.contains("不可用") {
+                warn!("送字後端自測: {}", line);
+            } else {
+                info!("送字後端自測: {}", line);
+            }
+        }
+        health
+    }
+
+    /// 以實際送字的結果更新剪貼簿的狀態（狀態改變時記一筆日誌）
+    pub fn record_clipboard(&mut self, result: Result<(), String>) {
+        Self::record("剪貼簿", &mut self.clipboard, result);
+    }
+
+    /// 以實際送字的結果更新 SendInput 的狀態
+    pub fn record_send_input(&mut self, result: Result<(), String>) {
+        Self::record("SendInput", &mut self.send_input, result);
+    }
+
+    fn record(name: &str, health: &mut Health, result: Result<(), String>) {
+        let new = Health::from_result(result);
+        if *health != new {
+            info!("送字後端 {} 的狀態: {} -> {}", name, health.label(), new.label());
+            *health = new;
+        }
+    }
+
+    /// 每個後端一行，例如「剪貼簿: 可用」
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("剪貼簿: {}", self.clipboard.label()),
+            format!("SendInput: {}", self.send_input.label()),
+            format!("UI Automation: {}（診斷參考，目前不以 UIA 送字）", self.uia.label()),
+        ]
+    }
+}
+
+fn probe_clipboard() -> Result<(), String> {
+    if unsafe { GetClipboardSequenceNumber() } == 0 {
+        return Err("沒有剪貼簿存取權限".to_string());
+    }
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    // 剪貼簿不是文字（或是空的）也代表讀得到
+    match clipboard.get_text() {
+        Ok(_) | Err(arboard::Error::ContentNotAvailable) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn probe_send_input() -> Result<(), String> {
+    let input = INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT { dx: 0, dy: 0, mouseData: 0, dwFlags: MOUSEEVENTF_MOVE, time: 0, dwExtraInfo: 0 },
+        },
+    };
+    let sent = unsafe { SendInput(&[input], std::mem::size_of::<INPUT>() as i32) };
+    if sent == 1 {
+        Ok(())
+    } else {
+        Err(format!("SendInput 回傳 {}（輸入可能被 BlockInput 或安全桌面擋下）", sent))
+    }
+}
+
+fn probe_uia() -> Result<(), String> {
+    unsafe {
+        // 已初始化過（S_FALSE）也算成功；模式不同時 CoCreateInstance 仍可能成功，錯誤交給它回報
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        CoCreateInstance::<_, IUnknown>(&CLSID_CUI_AUTOMATION, None, CLSCTX_INPROC_SERVER)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_health_lines_and_record() {
+        let mut health = BackendHealth::default();
+        assert_eq!(health.lines()[0], "剪貼簿: 未檢測");
+        health.record_send_input(Err("SendInput 只送出 0/2 個事件".to_string()));
+        assert_eq!(health.lines()[1], "SendInput: 不可用（SendInput 只送出 0/2 個事件）");
+        health.record_send_input(Ok(()));
+        health.record_clipboard(Ok(()));
+        assert_eq!((&health.clipboard, &health.send_input), (&Health::Available, &Health::Available));
+        assert!(health.lines()[2].starts_with("UI Automation: 未檢測"));
+    }
+}
@@ -0,0 +1,222 @@
+//! 使用者資料自動備份
+//!
+//! 鍵盤鉤子執行緒的訊息循環每隔 backup_interval_minutes 分鐘（啟動後第一輪也會）把目前使用者的
+//! 自訂詞、字頻、停用字、巨集、學習曲線與設定檔複製到使用者資料目錄下的備份資料夾：
+//!
+//! ```text
+//! profiles/alice/backups/
+//!   20240301-093000/
+//!   20240301-103000/
+//! ```
+//!
+//! 只保留最近 backup_keep 份，內容與上一份相同或有檔案損毀時不另建。
+//! 載入使用者資料前先檢查檔案是否損毀（JSON 無法解析、設定檔不是文字，例如斷電後被填滿 0），
+//! 損毀的檔案改名為 *.corrupt 保留，再從最近一份完好的備份還原。
+//! 設定檔在載入使用者資料前就已讀取，還原後下次啟動才會生效。
+
+use crate::blocklist::BLOCKLIST_FILE;
+use crate::config::Config;
+use crate::frequency::FREQUENCY_FILE;
+use crate::learning::LEARNING_FILE;
+use crate::macros::MACROS_FILE;
+use crate::profile::Profile;
+use crate::snippets::SNIPPETS_FILE;
+use crate::user_dictionary::USER_DICTIONARY_FILE;
+use anyhow::{Context, Result};
+use tracing::{debug, info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 備份資料夾名稱（放在使用者資料目錄，見 Profile::data_path）
+pub const BACKUP_DIR: &str = "backups";
+/// 預設保留的備份份數
+pub const DEFAULT_BACKUP_KEEP: usize = 10;
+
+/// 使用者資料目錄中要備份的檔案
+const PROFILE_FILES: [&str; 6] =
+    [USER_DICTIONARY_FILE, SNIPPETS_FILE, FREQUENCY_FILE, BLOCKLIST_FILE, MACROS_FILE, LEARNING_FILE];
+
+/// 備份設定（backup_interval_minutes 與 backup_keep）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupPolicy {
+    /// 兩次備份的間隔
+    pub interval: Duration,
+    /// 保留的份數
+    pub keep: usize,
+}
+
+impl BackupPolicy {
+    /// 從設定值建立，任一項為 0 時表示不備份
+    pub fn new(interval_minutes: u64, keep: usize) -> Option<Self> {
+        (interval_minutes > 0 && keep > 0).then(|| Self { interval: Duration::from_secs(interval_minutes * 60), keep })
+    }
+}
+
+/// 目前使用者要備份的檔案（使用者資料與設定檔）
+fn backup_files(profile: &Profile) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = PROFILE_FILES.iter().map(|name| profile.data_path(name)).collect();
+    match Config::path() {
+        Ok(path) => files.push(path),
+        Err(e) => debug!("取不到設定檔路徑，不備份設定檔: {:#}", e),
+    }
+    files
+}
+
+/// 檔案內容是否完好：JSON 檔必須能解析，其他檔案必須是不含 NUL 的 UTF-8 文字
+fn is_intact(path: &Path, content: &[u8]) -> bool {
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+        serde_json::from_slice::<serde_json::Value>(content).is_ok()
+    } else {
+        std::str::from_utf8(content).is_ok_and(|text| !text.contains('\0'))
+    }
+}
+
+/// 備份資料夾中的各份備份（由舊到新）
+fn list_backups(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect();
+    dirs.sort();
+    dirs
+}
+
+/// 備份目前使用者的資料，回傳新備份的位置；內容與上一份相同時回傳 None
+pub fn backup(profile: &Profile, keep: usize) -> Result<Option<PathBuf>> {
+    backup_in(&profile.data_path(BACKUP_DIR), &backup_files(profile), keep, &timestamp())
+}
+
+fn backup_in(root: &Path, files: &[PathBuf], keep: usize, stamp: &str) -> Result<Option<PathBuf>> {
+    let mut contents = Vec::new();
+    for path in files {
+        let (Some(name), Ok(content)) = (path.file_name(), fs::read(path)) else {
+            continue;
+        };
+        // 有檔案損毀時整份不備份，以免輪替後把完好的備份擠掉（下次載入時會從備份還原）
+        if !is_intact(path, &content) {
+            warn!("{:?} 已損毀，這次不備份", path);
+            return Ok(None);
+        }
+        contents.push((name.to_owned(), content));
+    }
+    if contents.is_empty() {
+        return Ok(None);
+    }
+
+    let backups = list_backups(root);
+    if let Some(latest) = backups.last() {
+        let file_count = fs::read_dir(latest).map(|entries| entries.count()).unwrap_or(0);
+        let unchanged = file_count == contents.len()
+            && contents.iter().all(|(name, content)| fs::read(latest.join(name)).is_ok_and(|old| old == *content));
+        if unchanged {
+            debug!("使用者資料與上一份備份相同，不另建備份");
+            return Ok(None);
+        }
+    }
+
+    let dir = root.join(stamp);
+    fs::create_dir_all(&dir).with_context(|| format!("無法建立備份資料夾: {:?}", dir))?;
+    for (name, content) in &contents {
+        let path = dir.join(name);
+        fs::write(&path, content).with_context(|| format!("無法寫入備份: {:?}", path))?;
+    }
+
+    // 只保留最近 keep 份
+    let backups = list_backups(root);
+    for old in &backups[..backups.len().saturating_sub(keep)] {
+        if let Err(e) = fs::remove_dir_all(old) {
+            warn!("無法刪除舊備份 {:?}: {}", old, e);
+        }
+    }
+    Ok(Some(dir))
+}
+
+/// 檢查目前使用者的資料，損毀的檔案從最近一份完好的備份還原，回傳還原的檔案
+/// 檔案不存在不算損毀（可能是使用者刪除的）
+pub fn restore_corrupt(profile: &Profile) -> Vec<PathBuf> {
+    restore_corrupt_in(&profile.data_path(BACKUP_DIR), &backup_files(profile))
+}
+
+fn restore_corrupt_in(root: &Path, files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut restored = Vec::new();
+    for path in files {
+        let (Some(name), Ok(content)) = (path.file_name(), fs::read(path)) else {
+            continue;
+        };
+        if is_intact(path, &content) {
+            continue;
+        }
+        let backup = list_backups(root).into_iter().rev().map(|dir| dir.join(name)).find(|candidate| {
+            fs::read(candidate).is_ok_and(|content| is_intact(path, &content))
+        });
+        let Some(backup) = backup else {
+            warn!("{:?} 已損毀，但找不到可用的備份", path);
+            continue;
+        };
+        // 損毀的檔案留著，使用者還能自己救回部分內容
+        let mut corrupt_name = name.to_owned();
+        corrupt_name.push(".corrupt");
+        let corrupt_path = path.with_file_name(corrupt_name);
+        let result = fs::rename(path, &corrupt_path).and_then(|_| fs::copy(&backup, path));
+        match result {
+            Ok(_) => {
+                warn!("{:?} 已損毀，已從備份 {:?} 還原（損毀的檔案改名為 {:?}）", path, backup, corrupt_path);
+                restored.push(path.clone());
+            }
+            Err(e) => warn!("{:?} 已損毀，從備份 {:?} 還原失敗: {}", path, backup, e),
+        }
+    }
+    if !restored.is_empty() {
+        info!("已從備份還原 {} 個檔案", restored.len());
+    }
+    restored
+}
+
+/// 備份資料夾名稱用的時間（本地時間，YYYYMMDD-HHMMSS，依名稱排序即依時間排序）
+fn timestamp() -> String {
+    let time = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        time.wYear, time.wMonth, time.wDay, time.wHour, time.wMinute, time.wSecond
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_rotates_and_restores() {
+        let dir = std::env::temp_dir().join(format!("uclliu_backup_test_{}", std::process::id()));
+        let root = dir.join(BACKUP_DIR);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let user = dir.join("user.json");
+        let config = dir.join("UCLLIU.ini");
+        let files = [user.clone(), config.clone(), dir.join("frequency.json")];
+
+        fs::write(&user, r#"{ "chardefs": { "a": ["一"] } }"#).unwrap();
+        fs::write(&config, "[Config]\n").unwrap();
+        let first = backup_in(&root, &files, 2, "20240301-090000").unwrap().unwrap();
+        assert_eq!(fs::read(first.join("user.json")).unwrap(), fs::read(&user).unwrap());
+        // 內容沒變不另建
+        assert!(backup_in(&root, &files, 2, "20240301-100000").unwrap().is_none());
+
+        fs::write(&user, r#"{ "chardefs": { "a": ["乙"] } }"#).unwrap();
+        backup_in(&root, &files, 2, "20240301-110000").unwrap().unwrap();
+        fs::write(&user, r#"{ "chardefs": { "a": ["丙"] } }"#).unwrap();
+        backup_in(&root, &files, 2, "20240301-120000").unwrap().unwrap();
+        assert_eq!(list_backups(&root), vec![root.join("20240301-110000"), root.join("20240301-120000")]);
+
+        // 斷電後被填滿 0：不備份，從最近一份完好的備份還原
+        fs::write(&user, [0u8; 16]).unwrap();
+        assert!(backup_in(&root, &files, 2, "20240301-130000").unwrap().is_none());
+        assert_eq!(restore_corrupt_in(&root, &files), vec![user.clone()]);
+        assert_eq!(fs::read_to_string(&user).unwrap(), r#"{ "chardefs": { "a": ["丙"] } }"#);
+        assert!(dir.join("user.json.corrupt").exists());
+        assert!(restore_corrupt_in(&root, &files).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,15 +1,74 @@
 //! 遊戲輸入測試工具（獨立可執行文件）
 //! 
 //! 使用方法：
-//!   cargo run --bin game_input_test
+//!   cargo run --bin game_input_test -- [--dict <liu.json>] [--count <數量>] [--seed <種子>]
 //!
-//! 這個工具會模擬遊戲輸入場景，測試遊戲模式窗口功能
+//! 這個工具會模擬遊戲輸入場景，測試遊戲模式窗口功能。
+//! 測試字根由字典隨機抽取，每個案例送出後讀取剪貼簿，和字典中的期望候選比對。
 
 mod game_input_test {
+    use anyhow::{Context, Result};
     use enigo::*;
+    use serde_json::Value;
     use std::thread;
     use std::time::Duration;
-    use log::{info, debug};
+    use tracing::{info, debug};
+
+    /// 測試案例：在遊戲模式窗口輸入字根後按 Space，應該送出 expected
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct TestCase {
+        pub code: String,
+        pub expected: String,
+        /// 同一字根的其他候選（依字頻排序時可能排到第一個）
+        pub alternatives: Vec<String>,
+    }
+
+    /// 從 chardefs 格式的字典隨機抽取 count 個字根產生測試案例（同一個 seed 抽到的案例相同）
+    /// 只抽取能以字母鍵打出、長度不超過 max_code_length 的字根
+    pub fn sample_cases(json: &str, count: usize, max_code_length: usize, seed: u64) -> Result<Vec<TestCase>> {
+        let root: Value = serde_json::from_str(json).context("字典不是有效的 JSON")?;
+        let chardefs = root
+            .get("chardefs")
+            .and_then(Value::as_object)
+            .context("字典缺少 chardefs")?;
+
+        let mut cases: Vec<TestCase> = chardefs
+            .iter()
+            .filter(|(code, _)| code.len() <= max_code_length && code.chars().all(|c| c.is_ascii_lowercase()))
+            .filter_map(|(code, value)| {
+                let mut outputs = value.as_array()?.iter().filter_map(candidate_output);
+                let expected = outputs.next()?;
+                Some(TestCase { code: code.clone(), expected, alternatives: outputs.collect() })
+            })
+            .collect();
+        cases.sort_by(|a, b| a.code.cmp(&b.code));
+
+        // xorshift 亂數做部分 Fisher-Yates 洗牌，不需要額外的亂數套件
+        let mut state = seed | 1;
+        let count = count.min(cases.len());
+        for i in 0..count {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let j = i + (state % (cases.len() - i) as u64) as usize;
+            cases.swap(i, j);
+        }
+        cases.truncate(count);
+        Ok(cases)
+    }
+
+    /// 候選實際送出的文字：字串本身，或 { "display", "output" } 的 output（沒有時為 display）
+    fn candidate_output(value: &Value) -> Option<String> {
+        match value {
+            Value::String(text) => Some(text.clone()),
+            Value::Object(entry) => entry
+                .get("output")
+                .or_else(|| entry.get("display"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            _ => None,
+        }
+    }
 
     /// 遊戲輸入測試器
     pub struct GameInputTester {
@@ -122,6 +181,12 @@ mod game_input_test {
             self.key_click(Key::Space);
         }
 
+        /// 模擬 Enter 鍵清除字根與累積文字（每個測試案例前呼叫，剪貼簿只會留下這次的結果）
+        pub fn clear_input(&mut self) {
+            info!("模擬 Enter 鍵清除字根與累積文字");
+            self.key_click(Key::Return);
+        }
+
         /// 模擬 ESC 鍵關閉窗口
         pub fn close_window(&mut self) {
             info!("模擬 ESC 鍵關閉窗口");
@@ -168,18 +233,111 @@ mod game_input_test {
     }
 }
 
-use game_input_test::GameInputTester;
+use game_input_test::{sample_cases, GameInputTester, TestCase};
 use std::io::{self, Write};
+use std::path::PathBuf;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 預設抽取的測試案例數
+const DEFAULT_CASE_COUNT: usize = 10;
+
+/// 嘸蝦米字根最長 5 碼
+const MAX_CODE_LENGTH: usize = 5;
+
+/// 命令列參數
+struct Options {
+    dict: PathBuf,
+    count: usize,
+    seed: u64,
+}
+
+impl Options {
+    fn parse() -> Self {
+        let mut options = Self {
+            dict: default_dictionary_path(),
+            count: DEFAULT_CASE_COUNT,
+            seed: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(1),
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--dict" => options.dict = args.next().map(PathBuf::from).unwrap_or(options.dict),
+                "--count" => options.count = args.next().and_then(|v| v.parse().ok()).unwrap_or(options.count),
+                "--seed" => options.seed = args.next().and_then(|v| v.parse().ok()).unwrap_or(options.seed),
+                other => println!("忽略未知的參數: {}", other),
+            }
+        }
+        options
+    }
+}
+
+/// 和輸入法相同：執行檔目錄下的 liu.json，找不到時用目前目錄的
+fn default_dictionary_path() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join("liu.json")))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from("liu.json"))
+}
+
+/// 單一案例的結果
+enum Outcome {
+    /// 送出的就是字典中的第一個候選
+    Pass,
+    /// 送出同一字根的其他候選（多半是依字頻排序的結果）
+    Reordered(String),
+    Fail(String),
+}
+
+/// 輸入字根、按 Space，再從剪貼簿讀回遊戲模式窗口累積的文字
+fn run_case(tester: &mut GameInputTester, case: &TestCase) -> Outcome {
+    tester.clear_input();
+    thread::sleep(Duration::from_millis(200));
+    tester.input_code(&case.code);
+    thread::sleep(Duration::from_millis(300));
+    tester.select_first_candidate();
+    thread::sleep(Duration::from_millis(300));
+
+    let actual = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+        Ok(text) => text,
+        Err(e) => return Outcome::Fail(format!("無法讀取剪貼簿: {}", e)),
+    };
+    if actual == case.expected {
+        Outcome::Pass
+    } else if case.alternatives.contains(&actual) {
+        Outcome::Reordered(actual)
+    } else {
+        Outcome::Fail(actual)
+    }
+}
 
 fn main() {
+    let options = Options::parse();
     println!("遊戲輸入測試工具");
     println!("==================");
     println!();
+
+    let cases = match std::fs::read_to_string(&options.dict)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| sample_cases(&json, options.count, MAX_CODE_LENGTH, options.seed))
+    {
+        Ok(cases) if !cases.is_empty() => cases,
+        Ok(_) => {
+            println!("字典 {:?} 中沒有可以用字母鍵打出的字根", options.dict);
+            return;
+        }
+        Err(e) => {
+            println!("無法從字典 {:?} 產生測試案例: {:#}", options.dict, e);
+            return;
+        }
+    };
+    println!("字典: {:?}", options.dict);
+    println!("抽取 {} 個字根（--seed {} 可重現同一組案例）", cases.len(), options.seed);
+    println!();
     println!("注意：這個工具會實際發送按鍵到系統，請確保：");
-    println!("1. 輸入法程序正在運行");
-    println!("2. 準備好接收輸入的應用程序（例如記事本）");
+    println!("1. 輸入法程序正在運行，且使用同一份字典");
+    println!("2. 遊戲模式窗口打開後點擊窗口給予焦點");
     println!("3. 5 秒後開始測試...");
     println!();
     
@@ -194,28 +352,74 @@ fn main() {
     
     let mut tester = GameInputTester::new();
     
-    // 測試 1: 觸發遊戲模式窗口
-    println!("測試 1: 觸發遊戲模式窗口 (Ctrl+Space)");
+    // 觸發遊戲模式窗口
+    println!("觸發遊戲模式窗口 (Ctrl+Space)");
     tester.trigger_input_window();
     thread::sleep(Duration::from_millis(500));
     
-    // 測試 2: 輸入字根
-    println!("測試 2: 輸入字根 'ucl'");
-    tester.input_code("ucl");
-    thread::sleep(Duration::from_millis(500));
-    
-    // 測試 3: 選擇第一個候選字
-    println!("測試 3: 選擇第一個候選字 (Space)");
-    tester.select_first_candidate();
-    thread::sleep(Duration::from_millis(500));
+    // 逐一輸入抽到的字根，和字典比對送出的結果
+    let (mut passed, mut reordered, mut failed) = (0, 0, 0);
+    for (i, case) in cases.iter().enumerate() {
+        match run_case(&mut tester, case) {
+            Outcome::Pass => {
+                passed += 1;
+                println!("[{}/{}] {} → {} ✅", i + 1, cases.len(), case.code, case.expected);
+            }
+            Outcome::Reordered(actual) => {
+                reordered += 1;
+                println!(
+                    "[{}/{}] {} → {}（字典第一個候選為 {}，可能是字頻排序）",
+                    i + 1,
+                    cases.len(),
+                    case.code,
+                    actual,
+                    case.expected
+                );
+            }
+            Outcome::Fail(actual) => {
+                failed += 1;
+                println!("[{}/{}] {} → 期望 {}，實際 '{}' ❌", i + 1, cases.len(), case.code, case.expected, actual);
+            }
+        }
+    }
+    tester.clear_input();
     
     println!();
-    println!("測試完成！");
-    println!();
-    println!("如果遊戲模式窗口正常顯示並輸入了候選字，測試成功。");
-    println!("如果沒有，請檢查：");
-    println!("1. 輸入法程序是否正在運行");
-    println!("2. 日誌輸出是否有錯誤");
-    println!("3. 遊戲模式窗口是否正確顯示");
+    println!("測試完成：{} 通過、{} 順序不同、{} 失敗", passed, reordered, failed);
+    if failed > 0 {
+        println!();
+        println!("有失敗的案例，請檢查：");
+        println!("1. 輸入法程序是否正在運行、是否載入同一份字典");
+        println!("2. 日誌輸出是否有錯誤");
+        println!("3. 遊戲模式窗口是否正確顯示並有焦點");
+        std::process::exit(1);
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_cases_from_dictionary() {
+        let json = r#"{ "chardefs": {
+            "a": ["一", "乙"], "ab": ["測試"], "xo": [{ "display": "笑", "output": "😂" }],
+            ",,": ["，"], "abcdef": ["太長"], "b": []
+        } }"#;
+        let cases = sample_cases(json, 10, 5, 42).unwrap();
+        let codes: Vec<&str> = {
+            let mut codes: Vec<&str> = cases.iter().map(|c| c.code.as_str()).collect();
+            codes.sort();
+            codes
+        };
+        assert_eq!(codes, vec!["a", "ab", "xo"]);
+        let a = cases.iter().find(|c| c.code == "a").unwrap();
+        assert_eq!((a.expected.as_str(), a.alternatives.clone()), ("一", vec!["乙".to_string()]));
+        assert_eq!(cases.iter().find(|c| c.code == "xo").unwrap().expected, "😂");
+
+        // 同一個 seed 抽到相同的案例
+        assert_eq!(sample_cases(json, 2, 5, 7).unwrap(), sample_cases(json, 2, 5, 7).unwrap());
+        assert_eq!(sample_cases(json, 2, 5, 7).unwrap().len(), 2);
+        assert!(sample_cases("{}", 2, 5, 7).is_err());
+    }
+}
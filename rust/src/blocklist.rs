@@ -0,0 +1,146 @@
+//! 停用字清單
+//!
+//! 生僻、異體等永遠不想看到的候選放在使用者資料目錄的 blocklist.json，
+//! 查詢候選時（主字碼表與使用者字典合併後）一律濾掉，見 Dictionary::lookup。
+//! 檔案是單純的字串陣列，可以手動編輯：
+//!
+//! ```json
+//! ["丌", "乂"]
+//! ```
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// 停用字清單檔名（放在使用者資料目錄，見 Profile::data_path）
+pub const BLOCKLIST_FILE: &str = "blocklist.json";
+
+/// 停用字清單
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Blocklist {
+    /// 停用的候選文字（排序後存檔，方便手動編輯）
+    entries: BTreeSet<String>,
+    /// 上次儲存後是否有修改
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Blocklist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 讀取停用字清單，檔案不存在或格式有誤時視為空清單
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<Self>(&content) {
+            Ok(blocklist) => {
+                info!("載入停用字清單: {:?}（{} 個）", path, blocklist.len());
+                blocklist
+            }
+            Err(e) => {
+                warn!("停用字清單 {:?} 格式有誤，暫不停用任何候選: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// 寫入停用字清單（先寫暫存檔再改名，中斷時不會弄壞原本的檔案）
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).with_context(|| format!("無法寫入停用字清單: {:?}", tmp_path))?;
+        fs::rename(&tmp_path, path).with_context(|| format!("無法寫入停用字清單: {:?}", path))?;
+        Ok(())
+    }
+
+    /// 候選是否已停用
+    pub fn contains(&self, text: &str) -> bool {
+        self.entries.contains(text)
+    }
+
+    /// 停用候選，原本就已停用時回傳 false
+    pub fn insert(&mut self, text: &str) -> bool {
+        let text = text.trim();
+        if text.is_empty() || !self.entries.insert(text.to_string()) {
+            return false;
+        }
+        self.dirty = true;
+        true
+    }
+
+    /// 恢復候選，原本沒有停用時回傳 false
+    pub fn remove(&mut self, text: &str) -> bool {
+        if !self.entries.remove(text) {
+            return false;
+        }
+        self.dirty = true;
+        true
+    }
+
+    /// 依排序列出停用的候選
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 上次儲存後是否有修改
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// 標記為已儲存
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_remove_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("uclliu-blocklist-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(BLOCKLIST_FILE);
+        let _ = fs::remove_file(&path);
+        assert!(Blocklist::load(&path).is_empty());
+
+        let mut blocklist = Blocklist::new();
+        assert!(blocklist.insert("乂"));
+        assert!(blocklist.insert(" 丌 "));
+        assert!(!blocklist.insert("乂"));
+        assert!(!blocklist.insert(""));
+        assert!(blocklist.is_dirty());
+        blocklist.save(&path).unwrap();
+        blocklist.mark_saved();
+
+        let loaded = Blocklist::load(&path);
+        assert_eq!(loaded.iter().collect::<Vec<_>>(), vec!["丌", "乂"]);
+        assert!(!loaded.is_dirty());
+
+        assert!(blocklist.remove("丌"));
+        assert!(!blocklist.remove("丌"));
+        assert!(!blocklist.contains("丌"));
+        assert!(blocklist.contains("乂"));
+
+        fs::write(&path, r#"{ "not": "a list" }"#).unwrap();
+        assert!(Blocklist::load(&path).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
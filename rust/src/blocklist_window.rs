@@ -0,0 +1,90 @@
+//! 停用字管理視窗
+//!
+//! 列出目前使用者停用的候選，可以新增或恢復。修改直接套用到輸入處理器的字典，
+//! 由鍵盤鉤子執行緒定期寫回 blocklist.json（見 AppState::save_profile_data）。
+
+use crate::input_method::InputMethodProcessor;
+use fltk::{
+    browser::HoldBrowser,
+    button::Button,
+    input::Input,
+    prelude::*,
+    window::Window,
+};
+use tracing::debug;
+use std::sync::{Arc, Mutex};
+
+/// 停用字管理視窗
+pub struct BlocklistWindow {
+    window: Window,
+    input: Input,
+    entries: HoldBrowser,
+    processor: Arc<Mutex<InputMethodProcessor>>,
+}
+
+impl BlocklistWindow {
+    pub fn new(processor: Arc<Mutex<InputMethodProcessor>>) -> Self {
+        let window = Window::default().with_size(320, 360).with_label("停用字管理").center_screen();
+
+        let mut input = Input::new(10, 10, 190, 30, "");
+        input.set_tooltip("輸入要停用的候選文字");
+        let mut add_button = Button::new(210, 10, 100, 30, "停用");
+
+        let mut entries = HoldBrowser::new(10, 50, 300, 260, "");
+        entries.set_text_size(16);
+        // 停用的可能是 @ 開頭的顏文字，不使用列表的格式碼（見 LookupWindow）
+        entries.set_format_char('\u{1}');
+
+        let mut remove_button = Button::new(10, 320, 120, 30, "恢復選取");
+        window.end();
+
+        // 停用字清單變動後更新列表；正在輸入時候選也會變，由輸入處理器通知狀態窗重繪
+        {
+            let processor = processor.clone();
+            let mut input = input.clone();
+            let mut entries = entries.clone();
+            add_button.set_callback(move |_| {
+                let text = input.value();
+                if processor.lock().map(|mut p| p.block_candidate(&text)).unwrap_or(false) {
+                    debug!("停用字管理: 停用 '{}'", text);
+                    input.set_value("");
+                    fill_entries(&mut entries, &processor);
+                }
+            });
+        }
+        {
+            let processor = processor.clone();
+            let mut entries = entries.clone();
+            remove_button.set_callback(move |_| {
+                // 瀏覽器的行號從 1 開始，0 表示沒有選取
+                let line = entries.value();
+                let Some(text) = (line > 0).then(|| entries.text(line)).flatten() else {
+                    return;
+                };
+                if processor.lock().map(|mut p| p.unblock_candidate(&text)).unwrap_or(false) {
+                    debug!("停用字管理: 恢復 '{}'", text);
+                    fill_entries(&mut entries, &processor);
+                }
+            });
+        }
+
+        Self { window, input, entries, processor }
+    }
+
+    /// 重新整理列表後顯示視窗（狀態窗右鍵隱藏的字也會出現在列表中）
+    pub fn show(&mut self) {
+        fill_entries(&mut self.entries, &self.processor);
+        self.window.show();
+        let _ = self.input.take_focus();
+    }
+}
+
+/// 以目前的停用字清單重新填入列表
+fn fill_entries(entries: &mut HoldBrowser, processor: &Arc<Mutex<InputMethodProcessor>>) {
+    entries.clear();
+    if let Ok(processor) = processor.lock() {
+        for text in processor.dictionary().blocklist.iter() {
+            entries.add(text);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! 候選
+//!
+//! 查詢結果原本只是候選文字的 Vec<String>，候選所屬的字根、來源另外放在平行的 Vec，
+//! 別名輸出送出時才回頭查字典，要再加權重、註記之類的資訊就塞不進去。
+//! 現在字典查詢（見 Dictionary::candidates）、輸入處理器的狀態與 GUI 都改用 Candidate：
+//! 一個候選帶著顯示文字、實際送出的文字、所屬字根、來源、排序權重與註記。
+//! 字根加上顯示文字即可識別一個候選（字頻、停用字、撤銷都以這兩者為準）。
+//!
+//! 字碼表檔案、匯出與反查只需要文字，仍然使用 Dictionary::lookup。
+
+use std::fmt;
+
+/// 候選的來源（GUI 據此標註，方便維護字碼表與除錯）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CandidateSource {
+    /// 主字碼表
+    Main,
+    /// 使用者字典（user.json）
+    User,
+    /// 額外的字碼表層（層名稱）
+    Layer(String),
+    /// 依使用次數排到前面的候選（字頻學習）
+    Learned,
+}
+
+impl CandidateSource {
+    /// 接在候選後面的標註，主字碼表不標註
+    pub fn mark(&self) -> Option<String> {
+        match self {
+            Self::Main => None,
+            Self::User => Some("〔自〕".to_string()),
+            Self::Layer(name) => Some(format!("〔{}〕", name)),
+            Self::Learned => Some("〔學〕".to_string()),
+        }
+    }
+}
+
+/// 一個候選
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// 候選列顯示的文字
+    pub text: String,
+    /// 實際送出的文字（字碼表的別名輸出、emoji 短碼表的輸出），None 表示送出 text 本身
+    pub output: Option<String>,
+    /// 候選所屬的字根（萬用字元與 emoji 短碼查詢時為實際符合的字根；同音字與符號選單沒有字根，為空字串）
+    pub code: String,
+    /// 來源（規則、巨集、emoji、同音字與符號選單等即時產生的候選為 None）
+    pub source: Option<CandidateSource>,
+    /// 排序權重：使用者選用的次數（見 FrequencyStats），沒有紀錄或沒有開啟字頻時為 0
+    pub weight: u32,
+    /// 接在候選後面的註記（GUI 以括號標出），沒有時為 None
+    pub note: Option<String>,
+}
+
+impl Candidate {
+    /// 只有文字的候選（規則、巨集、同音字、符號選單等即時產生的候選）
+    pub fn plain(code: &str, text: impl Into<String>) -> Self {
+        Self { text: text.into(), output: None, code: code.to_string(), source: None, weight: 0, note: None }
+    }
+}
+
+impl AsRef<str> for Candidate {
+    fn as_ref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// 把文字切成一個個顯示上的字（組合字元、emoji ZWJ 序列算一個字）
+pub fn clusters(text: &str) -> Vec<&str> {
+    let mut result: Vec<&str> = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<char> = None;
+    for (i, ch) in text.char_indices() {
+        if i > 0 && !prev.is_some_and(|prev| joins_previous(prev, ch)) {
+            result.push(&text[start..i]);
+            start = i;
+        }
+        prev = Some(ch);
+    }
+    if !text.is_empty() {
+        result.push(&text[start..]);
+    }
+    result
+}
+
+/// 候選是否為詞組（超過一個字；單一個 emoji ZWJ 序列仍算一個字）
+pub fn is_phrase(text: &str) -> bool {
+    clusters(text).len() > 1
+}
+
+/// 字元是否要和前一個字元顯示成同一個字：組合附加符號、變體選擇符、膚色修飾、
+/// 旗幟標籤，以及 ZWJ 本身與緊接在 ZWJ 之後的字元（emoji ZWJ 序列）
+fn joins_previous(prev: char, ch: char) -> bool {
+    prev == '\u{200D}'
+        || matches!(
+            ch as u32,
+            0x0300..=0x036F
+                | 0x1AB0..=0x1AFF
+                | 0x1DC0..=0x1DFF
+                | 0x20D0..=0x20FF
+                | 0xFE20..=0xFE2F
+                | 0x200D
+                | 0xFE00..=0xFE0F
+                | 0x1F3FB..=0x1F3FF
+                | 0xE0020..=0xE007F
+                | 0xE0100..=0xE01EF
+        )
+}
@@ -0,0 +1,178 @@
+//! 候選窗
+//!
+//! 狀態窗固定在角落（或停駐），打字時視線卻在文字游標附近。設定 candidate_window 後，
+//! 字根與候選從狀態窗分離到這個獨立的候選窗：跟著前景程式的文字游標（"caret"，取不到游標時跟滑鼠）、
+//! 跟著滑鼠（"mouse"）或固定在畫面中央上方（"center"）；狀態窗只留字根與累積文字。
+//!
+//! 兩個視窗由 GuiWindowManager 協調：狀態窗顯示期間有候選（或聯想詞、暫時英文提示）時才顯示候選窗，
+//! 內容來自同一個輸入處理器。候選窗有自己的版面設定（candidate_window_vertical、candidate_window_zoom、
+//! candidate_window_high_contrast），展示熱鍵只調整狀態窗。
+//!
+//! 候選窗以 override 工具視窗建立並加上 WS_EX_NOACTIVATE，不會搶走狀態窗或遊戲的焦點。
+
+use crate::gui_backend::StatusView;
+use crate::gui_window::escape_label;
+use crate::overlay;
+use crate::presentation::Presentation;
+use anyhow::{bail, Result};
+use fltk::{app, enums::{Align, Color, FrameType}, frame::Frame, prelude::*, window::Window};
+use windows::Win32::Foundation::{HWND, POINT};
+use windows::Win32::Graphics::Gdi::ClientToScreen;
+use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetGUIThreadInfo, GetWindowThreadProcessId, GUITHREADINFO};
+
+/// 候選窗左上角與游標（文字游標的底端或滑鼠）的距離
+const ANCHOR_OFFSET: (i32, i32) = (0, 8);
+
+/// 候選窗的位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CandidateWindowPosition {
+    /// 不分離，候選顯示在狀態窗裡（預設）
+    #[default]
+    Off,
+    /// 跟著前景程式的文字游標，取不到游標時跟著滑鼠
+    Caret,
+    /// 跟著滑鼠游標
+    Mouse,
+    /// 固定在畫面中央上方
+    Center,
+}
+
+impl CandidateWindowPosition {
+    /// 從設定值解析（"off"、"caret"、"mouse"、"center"，不分大小寫）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "off" => Ok(Self::Off),
+            "caret" => Ok(Self::Caret),
+            "mouse" => Ok(Self::Mouse),
+            "center" => Ok(Self::Center),
+            other => bail!("未知的候選窗位置 '{}'（可用: off、caret、mouse、center）", other),
+        }
+    }
+
+    /// 大小 size 的候選窗在 screen（寬, 高）中的左上角；anchor 為文字游標底端或滑鼠位置
+    /// 下方放不下時改放在游標上方
+    fn origin(self, size: (i32, i32), screen: (i32, i32), anchor: (i32, i32)) -> (i32, i32) {
+        let (x, y) = match self {
+            Self::Off | Self::Center => ((screen.0 - size.0) / 2, screen.1 / 3),
+            Self::Caret | Self::Mouse => {
+                let below = anchor.1 + ANCHOR_OFFSET.1;
+                let y = if below + size.1 > screen.1 { anchor.1 - ANCHOR_OFFSET.1 * 3 - size.1 } else { below };
+                (anchor.0 + ANCHOR_OFFSET.0, y)
+            }
+        };
+        (x.clamp(0, (screen.0 - size.0).max(0)), y.clamp(0, (screen.1 - size.1).max(0)))
+    }
+}
+
+/// 前景程式文字游標的底端（螢幕座標）；程式沒有使用系統游標（多數遊戲、瀏覽器）時為 None
+fn caret_anchor() -> Option<(i32, i32)> {
+    unsafe {
+        let thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+        let mut info = GUITHREADINFO { cbSize: std::mem::size_of::<GUITHREADINFO>() as u32, ..Default::default() };
+        GetGUIThreadInfo(thread, &mut info).ok()?;
+        if info.hwndCaret.0 == 0 {
+            return None;
+        }
+        let mut point = POINT { x: info.rcCaret.left, y: info.rcCaret.bottom };
+        ClientToScreen(info.hwndCaret, &mut point).as_bool().then_some((point.x, point.y))
+    }
+}
+
+/// 獨立的候選窗
+pub struct CandidateWindow {
+    window: Window,
+    code_frame: Frame,
+    word_frame: Frame,
+    /// 補全提示（狀態窗的第二行）
+    hint_frame: Frame,
+    position: CandidateWindowPosition,
+}
+
+impl CandidateWindow {
+    pub fn new(position: CandidateWindowPosition, presentation: Presentation) -> Self {
+        let layout = presentation.layout();
+        let theme = presentation.theme();
+        let rgb = |(r, g, b): (u8, u8, u8)| Color::from_rgb(r, g, b);
+
+        let mut window = Window::new(0, 0, layout.width, layout.height, "");
+        window.set_border(false);
+        // override 視窗在 Windows 上是不出現在工作列、顯示時不取得焦點的工具視窗
+        window.set_override();
+        window.set_color(rgb(theme.background));
+
+        // 版面與狀態窗相同：左邊字根、右邊候選、下方一行提示
+        let (x, y, w, h) = layout.code;
+        let mut code_frame = Frame::new(x, y, w, h, "");
+        code_frame.set_label_size(layout.code_size);
+        code_frame.set_label_color(rgb(theme.text));
+        code_frame.set_align(Align::Left | Align::Inside);
+
+        let (x, y, w, h) = layout.word;
+        let mut word_frame = Frame::new(x, y, w, h, "");
+        word_frame.set_label_size(layout.word_size);
+        word_frame.set_label_color(rgb(theme.text));
+        // 直向時一行一個候選，從上方開始排
+        word_frame.set_align(if presentation.vertical {
+            Align::Left | Align::Inside | Align::Top
+        } else {
+            Align::Left | Align::Inside
+        });
+
+        let (x, y, w, h) = layout.accumulated;
+        let mut hint_frame = Frame::new(x, y, w, h, "");
+        hint_frame.set_frame(FrameType::FlatBox);
+        hint_frame.set_label_size(layout.accumulated_size);
+        hint_frame.set_label_color(rgb(theme.accumulated_text));
+        hint_frame.set_color(rgb(theme.accumulated_background));
+        hint_frame.set_align(Align::Left | Align::Inside);
+        window.end();
+
+        Self { window, code_frame, word_frame, hint_frame, position }
+    }
+
+    /// 顯示新的內容；None 表示沒有候選，隱藏候選窗
+    pub fn update(&mut self, view: Option<&StatusView>) {
+        let Some(view) = view else {
+            if self.window.shown() {
+                self.window.hide();
+            }
+            return;
+        };
+        self.code_frame.set_label(&escape_label(&view.code));
+        self.word_frame.set_label(&escape_label(&view.candidates));
+        self.hint_frame.set_label(&escape_label(&view.hints));
+
+        let anchor = match self.position {
+            CandidateWindowPosition::Caret => caret_anchor().unwrap_or_else(app::get_mouse),
+            _ => app::get_mouse(),
+        };
+        let (screen_w, screen_h) = app::screen_size();
+        let size = (self.window.w(), self.window.h());
+        let (x, y) = self.position.origin(size, (screen_w as i32, screen_h as i32), anchor);
+        self.window.set_pos(x, y);
+        if !self.window.shown() {
+            self.window.show();
+            app::flush();
+            overlay::keep_on_top_without_focus(HWND(self.window.raw_handle() as isize));
+        }
+        self.window.redraw();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidate_window_position() {
+        assert_eq!(CandidateWindowPosition::parse(" Caret ").unwrap(), CandidateWindowPosition::Caret);
+        assert_eq!(CandidateWindowPosition::parse("").unwrap(), CandidateWindowPosition::Off);
+        assert!(CandidateWindowPosition::parse("corner").is_err());
+
+        let screen = (1920, 1080);
+        assert_eq!(CandidateWindowPosition::Center.origin((500, 100), screen, (0, 0)), (710, 360));
+        assert_eq!(CandidateWindowPosition::Caret.origin((500, 100), screen, (300, 400)), (300, 408));
+        // 游標在畫面下方時放到游標上方，靠右時往內縮
+        assert_eq!(CandidateWindowPosition::Mouse.origin((500, 100), screen, (1800, 1000)), (1420, 876));
+    }
+}
@@ -0,0 +1,208 @@
+//! 字碼表（chardefs）格式的解析與字根比對
+//!
+//! 只依賴 serde，不碰檔案系統或 Windows API：輸入法本體的 Dictionary 與
+//! 網頁查碼元件（wasm/）共用這一份程式，兩邊讀同一份 liu.json 的結果一致。
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// 字碼表中單一候選的定義
+/// 可以是純字串，或是 { "display": 顯示文字, "output": 實際送出文字 } 的別名格式
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum CharDefEntry {
+    Text(String),
+    Alias { display: String, output: String },
+}
+
+/// 字碼表檔案格式
+#[derive(Deserialize)]
+pub struct ChardefsFile {
+    pub chardefs: HashMap<String, Vec<CharDefEntry>>,
+    /// 候選權重（可省略）：字根 → 候選（顯示文字）→ 權重，權重高的排前面，沒列出的視為 0
+    #[serde(default)]
+    pub weights: HashMap<String, HashMap<String, i64>>,
+}
+
+/// 整理後的字碼表內容
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CharDefs {
+    /// 字根（小寫）→ 候選（顯示文字）
+    pub code_to_chars: HashMap<String, Vec<String>>,
+    /// 字根 → 顯示文字 → 實際送出文字（display/output 別名）
+    pub candidate_outputs: HashMap<String, HashMap<String, String>>,
+}
+
+impl CharDefs {
+    /// 從 JSON 字串解析（不做 schema 驗證，錯誤說明由呼叫端補充）
+    pub fn parse(content: &str) -> serde_json::Result<Self> {
+        Ok(serde_json::from_str::<ChardefsFile>(content)?.into())
+    }
+
+    /// 從 .cin 文字解析（gcin / OpenVanilla 格式，只讀 %chardef 區段），沒有 %chardef 區段時回傳 None
+    /// 每行為「字根 候選」，字根轉為小寫，同一字根的候選依出現順序排列並去除重複
+    pub fn parse_cin(content: &str) -> Option<Self> {
+        let mut code_to_chars: HashMap<String, Vec<String>> = HashMap::new();
+        let mut in_chardef = false;
+        let mut found = false;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(directive) = line.strip_prefix('%') {
+                match directive.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    ["chardef", "begin"] => {
+                        in_chardef = true;
+                        found = true;
+                    }
+                    ["chardef", "end"] => in_chardef = false,
+                    _ => {}
+                }
+                continue;
+            }
+            if !in_chardef {
+                continue;
+            }
+            let Some((code, text)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+            let text = text.trim();
+            let chars = code_to_chars.entry(code.to_lowercase()).or_default();
+            if !text.is_empty() && !chars.iter().any(|c| c == text) {
+                chars.push(text.to_string());
+            }
+        }
+        found.then(|| Self { code_to_chars, candidate_outputs: HashMap::new() })
+    }
+}
+
+impl From<ChardefsFile> for CharDefs {
+    /// 所有字根轉為小寫，大小寫不同的同一字根合併候選
+    /// 參考：uclliu.pyw 第 1180-1189 行
+    /// 有 weights 時同一字根的候選依權重排序（權重相同時維持原本順序）
+    fn from(file: ChardefsFile) -> Self {
+        let mut code_to_chars: HashMap<String, Vec<String>> = HashMap::new();
+        let mut candidate_outputs: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for (key, entries) in file.chardefs {
+            let lower_key = key.to_lowercase();
+            let mut value = Vec::with_capacity(entries.len());
+            for entry in entries {
+                match entry {
+                    CharDefEntry::Text(text) => value.push(text),
+                    CharDefEntry::Alias { display, output } => {
+                        candidate_outputs
+                            .entry(lower_key.clone())
+                            .or_default()
+                            .insert(display.clone(), output);
+                        value.push(display);
+                    }
+                }
+            }
+            // 如果已經存在小寫鍵，合併候選字列表
+            code_to_chars.entry(lower_key)
+                .and_modify(|v| {
+                    // 合併候選字，避免重複
+                    for char in &value {
+                        if !v.contains(char) {
+                            v.push(char.clone());
+                        }
+                    }
+                })
+                .or_insert_with(|| value);
+        }
+        for (key, weights) in file.weights {
+            if let Some(chars) = code_to_chars.get_mut(&key.to_lowercase()) {
+                sort_by_weight(chars, &weights);
+            }
+        }
+        Self { code_to_chars, candidate_outputs }
+    }
+}
+
+/// 依權重排序候選（權重高的在前，沒有權重的視為 0，權重相同時維持原本順序）
+pub fn sort_by_weight(candidates: &mut [String], weights: &HashMap<String, i64>) {
+    candidates.sort_by_key(|c| std::cmp::Reverse(weights.get(c).copied().unwrap_or(0)));
+}
+
+/// 候選中包含指定文字的字根（未排序）
+pub fn codes_containing(code_to_chars: &HashMap<String, Vec<String>>, text: &str) -> Vec<String> {
+    code_to_chars
+        .iter()
+        .filter(|(_, chars)| chars.iter().any(|c| c == text))
+        .map(|(code, _)| code.clone())
+        .collect()
+}
+
+/// 字根排序：短的字根在前，同長度依字母排序，並去除重複
+pub fn sort_codes(codes: &mut Vec<String>) {
+    codes.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    codes.dedup();
+}
+
+/// 字根是否含萬用字元（* 或 ?）
+pub fn is_wildcard(code: &str) -> bool {
+    code.contains(['*', '?'])
+}
+
+/// 字根是否符合含萬用字元的樣式（* 代表任意長度的字根，包含空字串；? 代表剛好一個字根）
+pub fn wildcard_match(pattern: &str, code: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let code: Vec<char> = code.chars().collect();
+    let (mut p, mut c) = (0, 0);
+    // 最近一個 * 的位置與它目前吃到的字根位置，比對失敗時讓 * 多吃一個字根再試
+    let mut star: Option<(usize, usize)> = None;
+    while c < code.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, c));
+                p += 1;
+            }
+            Some(&ch) if ch == '?' || ch == code[c] => {
+                p += 1;
+                c += 1;
+            }
+            _ => match star {
+                Some((star_p, star_c)) => {
+                    star = Some((star_p, star_c + 1));
+                    p = star_p + 1;
+                    c = star_c + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_merges_case_and_aliases() {
+        let defs = CharDefs::parse(
+            r#"{ "chardefs": { "A": ["一"], "a": ["乙", "一"], "xo": [{ "display": "笑", "output": "😂" }], "yi": ["一"] } }"#,
+        )
+        .unwrap();
+        assert_eq!(defs.code_to_chars["a"].len(), 2);
+        assert_eq!(defs.code_to_chars["xo"], vec!["笑"]);
+        assert_eq!(defs.candidate_outputs["xo"]["笑"], "😂");
+        let mut codes = codes_containing(&defs.code_to_chars, "一");
+        sort_codes(&mut codes);
+        assert_eq!(codes, vec!["a", "yi"]);
+        assert!(CharDefs::parse("{}").is_err());
+    }
+
+    #[test]
+    fn test_parse_cin() {
+        let defs = CharDefs::parse_cin(
+            "%gen_inp\n%keyname begin\na A\n%keyname end\n%chardef begin\nA 一\na 乙\na 一\n# 註解\nxo (^_^)\n%chardef end\nb 不算\n",
+        )
+        .unwrap();
+        assert_eq!(defs.code_to_chars["a"], vec!["一", "乙"]);
+        assert_eq!(defs.code_to_chars["xo"], vec!["(^_^)"]);
+        assert!(!defs.code_to_chars.contains_key("b"));
+        assert!(CharDefs::parse_cin("a 一\n").is_none());
+    }
+}
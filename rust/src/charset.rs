@@ -0,0 +1,120 @@
+//! 候選字的字集篩選
+//!
+//! 不少字根對應到 CJK 擴充 B 之後的罕用字，在遊戲裡常顯示成方框（豆腐字）。
+//! 設定字集後，查詢候選時把字集以外的漢字藏起來，或移到候選最後面：
+//!
+//! - big5：Big5 編碼得出來的字
+//! - common：Big5 的常用字區（A440–C67E，即教育部常用國字）
+//!
+//! 只檢查漢字，符號、英數、emoji 與顏文字不受影響；詞組要每個漢字都在字集內才算符合。
+
+use anyhow::{bail, Result};
+use encoding_rs::BIG5;
+
+/// Big5 常用字區的範圍
+const BIG5_COMMON: std::ops::RangeInclusive<u16> = 0xA440..=0xC67E;
+
+/// 候選字集
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Charset {
+    /// 不篩選
+    #[default]
+    All,
+    /// Big5 編碼得出來的字
+    Big5,
+    /// Big5 常用字
+    Common,
+}
+
+impl Charset {
+    /// 從設定值解析："all"、"big5" 或 "common"
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "all" => Ok(Self::All),
+            "big5" => Ok(Self::Big5),
+            "common" => Ok(Self::Common),
+            other => bail!("不支援的候選字集: {}（可用 all、big5、common）", other),
+        }
+    }
+
+    /// 這個漢字是否在字集內
+    fn contains(self, ch: char) -> bool {
+        if self == Self::All {
+            return true;
+        }
+        let mut buf = [0u8; 4];
+        let (bytes, _, had_errors) = BIG5.encode(ch.encode_utf8(&mut buf));
+        if had_errors {
+            return false;
+        }
+        match (self, bytes.as_ref()) {
+            (Self::Common, &[high, low]) => BIG5_COMMON.contains(&u16::from_be_bytes([high, low])),
+            _ => true,
+        }
+    }
+}
+
+/// 查詢候選時套用的字集篩選
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CharsetFilter {
+    pub charset: Charset,
+    /// true 時字集以外的候選移到最後面，false 時直接藏起來
+    pub demote: bool,
+}
+
+impl CharsetFilter {
+    /// 候選中的漢字是否都在字集內
+    pub fn accepts(&self, candidate: &str) -> bool {
+        candidate.chars().filter(|&ch| is_han(ch)).all(|ch| self.charset.contains(ch))
+    }
+
+    /// 篩選候選（保持原本的順序）
+    pub fn apply<T: AsRef<str>>(&self, candidates: &mut Vec<T>) {
+        if self.charset == Charset::All {
+            return;
+        }
+        if self.demote {
+            // sort_by_key 是穩定排序，字集內外各自保持原本的順序
+            candidates.sort_by_key(|candidate| !self.accepts(candidate.as_ref()));
+        } else {
+            candidates.retain(|candidate| self.accepts(candidate.as_ref()));
+        }
+    }
+}
+
+/// 是否為漢字（CJK 統一漢字、擴充 A 之後各區與相容漢字）
+fn is_han(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x3FFFF
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_by_charset() {
+        // 𠀾（擴充 B）不在 Big5；堃 在 Big5 次常用字區；肥 在常用字區
+        let candidates = || vec!["𠀾".to_string(), "堃".to_string(), "肥".to_string(), "①".to_string()];
+
+        let mut big5 = candidates();
+        CharsetFilter { charset: Charset::Big5, demote: false }.apply(&mut big5);
+        assert_eq!(big5, ["堃", "肥", "①"]);
+
+        let mut common = candidates();
+        CharsetFilter { charset: Charset::Common, demote: false }.apply(&mut common);
+        assert_eq!(common, ["肥", "①"]);
+
+        let mut demoted = candidates();
+        CharsetFilter { charset: Charset::Common, demote: true }.apply(&mut demoted);
+        assert_eq!(demoted, ["肥", "①", "𠀾", "堃"]);
+
+        let filter = CharsetFilter { charset: Charset::Common, demote: false };
+        assert!(filter.accepts("肥米 :)"));
+        assert!(!filter.accepts("肥𠀾"));
+        assert_eq!(Charset::parse("BIG5").unwrap(), Charset::Big5);
+        assert!(Charset::parse("gbk").is_err());
+    }
+}
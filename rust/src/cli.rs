@@ -0,0 +1,164 @@
+//! 命令列參數模組
+//!
+//! 支援的參數：
+//!   --scheme <名稱>   使用 schemes/<名稱>/ 下的輸入法方案（預設為內建嘸蝦米）
+//!   --profile <名稱>  使用 profiles/<名稱>/ 下的使用者資料（預設為目前的 Windows 使用者）
+//!   --strict          依內建 schema 嚴格驗證字典與方案描述（給表格作者除錯用）
+//!   --obs-output <檔案> 將組字狀態寫到文字檔，給 OBS 文字來源讀取
+//!   --minimized       啟動時只建立托盤，不顯示狀態窗（覆蓋設定檔）
+//!   --export-dict <檔案> 把合併後的字碼表匯出成 .cin 或 CSV（依副檔名）後結束，不啟動輸入法
+//!   --dict <檔案>     使用指定的字碼表取代方案的字碼表（覆蓋設定檔，可用 %APPDATA% 等環境變數）
+//!   --type <文字>     以輸入法流程把文字打到前景程式後結束，不啟動輸入法（見 type_sentence 模組）
+
+use tracing::warn;
+
+/// 命令列參數
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CliArgs {
+    /// 指定的輸入法方案名稱（覆蓋設定檔）
+    pub scheme: Option<String>,
+    /// 指定的使用者設定檔名稱（覆蓋設定檔）
+    pub profile: Option<String>,
+    /// 嚴格驗證字典與方案描述
+    pub strict: bool,
+    /// OBS 字幕輸出檔案（覆蓋設定檔）
+    pub obs_output: Option<String>,
+    /// 啟動時最小化到托盤
+    pub minimized: bool,
+    /// 匯出字碼表的檔案路徑（有指定時只匯出、不啟動）
+    pub export_dict: Option<String>,
+    /// 主字碼表路徑（覆蓋設定檔）
+    pub dict: Option<String>,
+    /// 模擬輸入的文字（有指定時只打字、不啟動）
+    pub type_text: Option<String>,
+}
+
+impl CliArgs {
+    /// 解析目前程序的命令列參數
+    pub fn parse() -> Self {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    /// 解析命令列參數（不含程式名稱）
+    pub fn parse_from<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut result = Self::default();
+        let mut args = args.into_iter().map(Into::into);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scheme" => match args.next() {
+                    Some(name) => result.scheme = Some(name),
+                    None => warn!("--scheme 缺少方案名稱，忽略"),
+                },
+                "--profile" => match args.next() {
+                    Some(name) => result.profile = Some(name),
+                    None => warn!("--profile 缺少名稱，忽略"),
+                },
+                "--strict" => result.strict = true,
+                "--minimized" => result.minimized = true,
+                "--obs-output" => match args.next() {
+                    Some(path) => result.obs_output = Some(path),
+                    None => warn!("--obs-output 缺少檔案路徑，忽略"),
+                },
+                "--export-dict" => match args.next() {
+                    Some(path) => result.export_dict = Some(path),
+                    None => warn!("--export-dict 缺少檔案路徑，忽略"),
+                },
+                "--dict" => match args.next() {
+                    Some(path) => result.dict = Some(path),
+                    None => warn!("--dict 缺少檔案路徑，忽略"),
+                },
+                "--type" => match args.next() {
+                    Some(text) => result.type_text = Some(text),
+                    None => warn!("--type 缺少文字，忽略"),
+                },
+                _ => {
+                    if let Some(name) = arg.strip_prefix("--scheme=") {
+                        result.scheme = Some(name.to_string());
+                    } else if let Some(name) = arg.strip_prefix("--profile=") {
+                        result.profile = Some(name.to_string());
+                    } else if let Some(path) = arg.strip_prefix("--obs-output=") {
+                        result.obs_output = Some(path.to_string());
+                    } else if let Some(path) = arg.strip_prefix("--export-dict=") {
+                        result.export_dict = Some(path.to_string());
+                    } else if let Some(path) = arg.strip_prefix("--dict=") {
+                        result.dict = Some(path.to_string());
+                    } else if let Some(text) = arg.strip_prefix("--type=") {
+                        result.type_text = Some(text.to_string());
+                    } else {
+                        warn!("未知的命令列參數: {}", arg);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scheme() {
+        assert_eq!(CliArgs::parse_from(["--scheme", "array30"]).scheme, Some("array30".to_string()));
+        assert_eq!(CliArgs::parse_from(["--scheme=dayi"]).scheme, Some("dayi".to_string()));
+        assert_eq!(CliArgs::parse_from(Vec::<String>::new()).scheme, None);
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        let args = CliArgs::parse_from(["--strict", "--scheme", "array30"]);
+        assert!(args.strict);
+        assert_eq!(args.scheme, Some("array30".to_string()));
+        assert!(!CliArgs::parse_from(["--scheme", "array30"]).strict);
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        assert_eq!(CliArgs::parse_from(["--profile", "小明"]).profile, Some("小明".to_string()));
+        assert_eq!(CliArgs::parse_from(["--profile=bob"]).profile, Some("bob".to_string()));
+    }
+
+    #[test]
+    fn test_parse_obs_output() {
+        assert_eq!(CliArgs::parse_from(["--obs-output", "obs.txt"]).obs_output, Some("obs.txt".to_string()));
+        assert_eq!(CliArgs::parse_from(["--obs-output=C:\\obs.txt"]).obs_output, Some("C:\\obs.txt".to_string()));
+    }
+
+    #[test]
+    fn test_parse_minimized() {
+        assert!(CliArgs::parse_from(["--minimized"]).minimized);
+        assert!(!CliArgs::parse_from(["--strict"]).minimized);
+    }
+
+    #[test]
+    fn test_parse_export_dict() {
+        assert_eq!(CliArgs::parse_from(["--export-dict", "out.cin"]).export_dict, Some("out.cin".to_string()));
+        assert_eq!(CliArgs::parse_from(["--export-dict=my.csv"]).export_dict, Some("my.csv".to_string()));
+        assert_eq!(CliArgs::parse_from(["--strict"]).export_dict, None);
+    }
+
+    #[test]
+    fn test_parse_dict() {
+        assert_eq!(CliArgs::parse_from(["--dict", "%APPDATA%\\liu.json"]).dict, Some("%APPDATA%\\liu.json".to_string()));
+        assert_eq!(CliArgs::parse_from(["--dict=D:\\liu.json"]).dict, Some("D:\\liu.json".to_string()));
+        assert_eq!(CliArgs::parse_from(["--export-dict", "out.cin"]).dict, None);
+    }
+
+    #[test]
+    fn test_parse_type() {
+        assert_eq!(CliArgs::parse_from(["--type", "肥米 輸入法"]).type_text, Some("肥米 輸入法".to_string()));
+        assert_eq!(CliArgs::parse_from(["--type=你好"]).type_text, Some("你好".to_string()));
+    }
+
+    #[test]
+    fn test_parse_ignores_unknown() {
+        assert_eq!(CliArgs::parse_from(["--foo", "--scheme"]), CliArgs::default());
+    }
+}
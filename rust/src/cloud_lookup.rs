@@ -0,0 +1,179 @@
+//! 線上備援查詢（離線優先）
+//!
+//! 設定 cloud_lookup_url 後，字根在本地字碼表（含使用者字典與額外層）查不到候選、也沒有以它開頭的
+//! 更長字根時，在背景執行緒向公開的字碼資料庫查詢；查到的候選寫入使用者字典（user.json），
+//! 之後離線也查得到，還在打同一個字根時立即列出（見 AppState::apply_cloud_candidates）。
+//!
+//! 網址中的 {code} 換成字根（非英數字元以 %XX 編碼），回應為候選字串的 JSON 陣列，例如 `["𠀀", "𠀁"]`。
+//! 預設關閉；查詢時不持有任何鎖，不阻塞鍵盤鉤子。同一個字根在這次執行中只查一次（查不到也記下），
+//! 同時最多 MAX_IN_FLIGHT 個查詢，連線失敗後暫停 FAILURE_BACKOFF 再試。
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// 查詢逾時
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+/// 同時進行的查詢上限
+const MAX_IN_FLIGHT: usize = 2;
+/// 連線失敗後暫停查詢的時間
+const FAILURE_BACKOFF: Duration = Duration::from_secs(60);
+/// 一個字根最多收下幾個候選
+const MAX_CANDIDATES: usize = 20;
+/// 回應大小上限
+const MAX_RESPONSE_BYTES: u64 = 64 * 1024;
+
+/// 線上備援查詢
+#[derive(Debug)]
+pub struct CloudLookup {
+    /// 查詢網址，{code} 換成字根
+    url: String,
+    /// 這次執行中查過（或正在查）的字根
+    queried: Mutex<HashSet<String>>,
+    in_flight: AtomicUsize,
+    /// 上次連線失敗的時間
+    failed_at: Mutex<Option<Instant>>,
+}
+
+impl CloudLookup {
+    /// 從設定建立，沒有設定網址時回傳 None（不查詢）
+    pub fn from_config(url: &str) -> Option<Self> {
+        let url = url.trim();
+        if url.is_empty() {
+            return None;
+        }
+        if !url.contains("{code}") {
+            warn!("線上備援查詢網址沒有 {{code}}，字根會接在網址後面: {}", url);
+        }
+        Some(Self {
+            url: url.to_string(),
+            queried: Mutex::new(HashSet::new()),
+            in_flight: AtomicUsize::new(0),
+            failed_at: Mutex::new(None),
+        })
+    }
+
+    /// 字根的查詢網址
+    fn url_for(&self, code: &str) -> String {
+        let mut encoded = String::new();
+        for byte in code.bytes() {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        if self.url.contains("{code}") {
+            self.url.replace("{code}", &encoded)
+        } else {
+            format!("{}{}", self.url, encoded)
+        }
+    }
+
+    /// 在背景執行緒查詢字根，查到候選時以 (字根, 候選) 呼叫 on_found
+    /// 查過的字根、查詢太多或剛連線失敗時直接略過
+    pub fn request<F>(self: &Arc<Self>, code: &str, on_found: F)
+    where
+        F: FnOnce(String, Vec<String>) + Send + 'static,
+    {
+        if self.failed_at.lock().unwrap().is_some_and(|at| at.elapsed() < FAILURE_BACKOFF) {
+            return;
+        }
+        if self.in_flight.load(Ordering::Relaxed) >= MAX_IN_FLIGHT {
+            debug!("線上備援查詢進行中的太多，略過字根 '{}'", code);
+            return;
+        }
+        if !self.queried.lock().unwrap().insert(code.to_string()) {
+            return;
+        }
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        let lookup = self.clone();
+        let owned = code.to_string();
+        let spawned = std::thread::Builder::new().name("cloud-lookup".to_string()).spawn(move || {
+            let code = owned;
+            let result = lookup.fetch(&code);
+            lookup.in_flight.fetch_sub(1, Ordering::Relaxed);
+            match result {
+                Ok(candidates) if candidates.is_empty() => debug!("線上備援查詢: 字根 '{}' 沒有候選", code),
+                Ok(candidates) => {
+                    // 字根等於打過的內容，只記在 debug（不寫入日誌檔）
+                    info!("線上備援查詢到 {} 個候選", candidates.len());
+                    debug!("線上備援查詢: 字根 '{}' 查到 {:?}", code, candidates);
+                    on_found(code, candidates);
+                }
+                Err(e) => {
+                    warn!("線上備援查詢失敗，{} 秒內不再查詢: {:#}", FAILURE_BACKOFF.as_secs(), e);
+                    // 連線問題不算查過，恢復後可以再查
+                    lookup.queried.lock().unwrap().remove(&code);
+                    *lookup.failed_at.lock().unwrap() = Some(Instant::now());
+                }
+            }
+        });
+        if let Err(e) = spawned {
+            warn!("無法啟動線上備援查詢執行緒: {}", e);
+            self.in_flight.fetch_sub(1, Ordering::Relaxed);
+            self.queried.lock().unwrap().remove(code);
+        }
+    }
+
+    /// 下載並解析字根的候選
+    fn fetch(&self, code: &str) -> Result<Vec<String>> {
+        use std::io::Read;
+        let url = self.url_for(code);
+        // ureq 的錯誤訊息帶有網址（含字根），只留錯誤種類
+        let response = match ureq::get(&url).timeout(LOOKUP_TIMEOUT).call() {
+            Ok(response) => response,
+            // 資料庫沒有這個字根
+            Err(ureq::Error::Status(404, _)) => return Ok(Vec::new()),
+            Err(ureq::Error::Status(status, _)) => bail!("{} 回應 HTTP {}", self.url, status),
+            Err(e) => bail!("無法查詢 {}: {}", self.url, e.kind()),
+        };
+        let mut body = String::new();
+        response
+            .into_reader()
+            .take(MAX_RESPONSE_BYTES)
+            .read_to_string(&mut body)
+            .with_context(|| format!("讀取 {} 的回應時中斷", self.url))?;
+        parse_response(&body)
+    }
+}
+
+/// 解析查詢結果：候選字串的 JSON 陣列，略過空白與重複的候選
+pub fn parse_response(body: &str) -> Result<Vec<String>> {
+    let value: serde_json::Value = serde_json::from_str(body.trim_start_matches('\u{feff}')).context("查詢結果不是 JSON")?;
+    let Some(items) = value.as_array() else {
+        bail!("查詢結果應為候選字串的陣列");
+    };
+    let mut candidates: Vec<String> = Vec::new();
+    for item in items {
+        let Some(text) = item.as_str().map(str::trim) else {
+            bail!("查詢結果的候選應為字串: {}", item);
+        };
+        if !text.is_empty() && !candidates.iter().any(|c| c == text) {
+            candidates.push(text.to_string());
+        }
+    }
+    candidates.truncate(MAX_CANDIDATES);
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cloud_lookup_url_and_response() {
+        assert!(CloudLookup::from_config("  ").is_none());
+        let lookup = CloudLookup::from_config("https://example.com/liu/{code}.json").unwrap();
+        assert_eq!(lookup.url_for("ab,"), "https://example.com/liu/ab%2C.json");
+        let lookup = CloudLookup::from_config("https://example.com/q?code=").unwrap();
+        assert_eq!(lookup.url_for("xyz"), "https://example.com/q?code=xyz");
+
+        assert_eq!(parse_response(r#"["𠀀", " ", "𠀁", "𠀀"]"#).unwrap(), ["𠀀", "𠀁"]);
+        assert!(parse_response(r#"{ "a": ["一"] }"#).is_err());
+        assert!(parse_response("[1]").is_err());
+    }
+}
@@ -1,9 +1,72 @@
 //! 配置管理模組
 
-use anyhow::Result;
+use crate::announcer::AnnounceMode;
+use crate::appbar::DockEdge;
+use crate::candidate_window::CandidateWindowPosition;
+use crate::charset::Charset;
+use crate::dictionary::{DictionaryBackend, MergePolicy};
+use crate::input_method::ShiftLetter;
+use crate::input_simulator::{SendMode, TerminalMode};
+use crate::long_paste;
+use crate::mini_float::MiniFloatPosition;
+use crate::mode_badge::BadgePosition;
+use crate::mode_indicator::IndicatorTarget;
+use crate::release_gate;
+use crate::schedule::{Schedule, ScheduleEntry};
+use crate::sorter;
+use crate::time_macros::TimeMacro;
+use crate::validation::{check_value, ValidationError};
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// 翻頁鍵設定可用的組合：名稱與（上一頁, 下一頁）的虛擬鍵碼
+pub const PAGE_KEY_PAIRS: &[(&str, (u32, u32))] = &[
+    ("page_up_down", (0x21, 0x22)),
+    ("up_down", (0x26, 0x28)),
+    ("left_right", (0x25, 0x27)),
+    ("comma_period", (0xBC, 0xBE)),
+    ("minus_equal", (0xBD, 0xBB)),
+];
+
+/// 按鍵設定可用的鍵名（字母、數字與 F1～F24 另外處理）與虛擬鍵碼
+pub const KEY_NAMES: &[(&str, u32)] = &[
+    ("backspace", 0x08),
+    ("tab", 0x09),
+    ("enter", 0x0D),
+    ("esc", 0x1B),
+    ("page_up", 0x21),
+    ("page_down", 0x22),
+    ("end", 0x23),
+    ("home", 0x24),
+    ("left", 0x25),
+    ("up", 0x26),
+    ("right", 0x27),
+    ("down", 0x28),
+    ("insert", 0x2D),
+    ("delete", 0x2E),
+];
+
+/// 鍵名對應的虛擬鍵碼（不分大小寫）：a～z、0～9、F1～F24 或 KEY_NAMES 中的名稱
+pub fn key_vk(name: &str) -> Option<u32> {
+    let name = name.to_ascii_lowercase();
+    if let [byte] = name.as_bytes() {
+        if byte.is_ascii_alphanumeric() {
+            // 字母與數字鍵的虛擬鍵碼就是大寫 ASCII
+            return Some(byte.to_ascii_uppercase() as u32);
+        }
+    }
+    if let Some(number) = name.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        // VK_F1 = 0x70
+        return (1..=24).contains(&number).then_some(0x6F + number);
+    }
+    KEY_NAMES.iter().find(|(key_name, _)| *key_name == name).map(|&(_, vk)| vk)
+}
+
+/// 預設放行的 Ctrl 組合鍵：複製、貼上、剪下、復原、全選等常用的系統與編輯組合
+pub const DEFAULT_CTRL_PASSTHROUGH: &str =
+    "a,c,v,x,z,y,s,f,n,o,p,w,t,r,l,h,tab,esc,enter,backspace,delete,insert,home,end,left,right,up,down,page_up,page_down";
 
 /// 應用程式配置
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,12 +83,151 @@ pub struct Config {
     pub y: i32,
     /// 是否顯示短根
     pub sp: bool,
-    /// 是否有打字音
+    /// 按了無效的鍵（打錯字根、沒有候選可送出、選字鍵沒有對應的候選）時發出短促的提示音
     pub play_sound_enable: bool,
+    /// 按了無效的鍵時閃一下狀態窗（可與提示音同時使用）
+    pub invalid_key_flash: bool,
     /// 啟動時預設模式（0=英模式，1=肥模式）
     pub startup_default_ucl: bool,
     /// 允許使用 Shift+Space 切換全形/半形
     pub enable_half_full: bool,
+    /// 肥模式下按 Shift+字母："pass"（交給系統打出大寫字母）、"commit"（直接送出大寫字母，正在組字時先送出第一個候選）
+    /// 或 "word"（以大寫字母開始暫時英文單字，Space 送出）
+    pub shift_letter: String,
+    /// 輸入法方案名稱（schemes/<名稱>/，空字串表示內建嘸蝦米）
+    pub scheme: String,
+    /// 主字碼表路徑（取代方案目錄中的字碼表），可用絕對路徑與 %APPDATA% 之類的環境變數，
+    /// 相對路徑以執行檔目錄為準；空字串表示使用方案的字碼表
+    pub dictionary_path: String,
+    /// 使用者設定檔名稱（profiles/<名稱>/，空字串表示目前的 Windows 使用者）
+    pub profile: String,
+    /// 遊戲模式窗口顯示/隱藏時是否使用淡入淡出效果
+    pub gui_fade: bool,
+    /// OBS 字幕輸出檔案路徑（空字串表示不輸出）
+    pub obs_output_path: String,
+    /// OBS 字幕更新間隔（毫秒）
+    pub obs_update_interval_ms: u64,
+    /// 字典後端："memory"（整份載入記憶體）或 "sqlite"（大型字碼表用，啟動較快）
+    pub dictionary_backend: String,
+    /// 字根查詢視窗預設是否顯示 Unicode 字元名稱
+    pub lookup_unicode_names: bool,
+    /// 遊戲模式窗口使用 overlay 模式（無邊框全螢幕遊戲下仍可見，不支援時退回一般視窗）
+    pub gui_overlay: bool,
+    /// 狀態窗停駐位置："floating"（浮動視窗）、"top" 或 "bottom"（以 AppBar 佔據螢幕頂端或底部）
+    pub gui_dock: String,
+    /// 迷你候選浮窗："off"（不使用）、"mouse"（跟隨滑鼠）或 "center"（畫面中央上方），遊戲模式窗口顯示期間組字時出現
+    pub gui_mini_float: String,
+    /// 候選窗分離："off"（候選顯示在狀態窗裡）、"caret"（跟隨文字游標，取不到時跟隨滑鼠）、"mouse" 或 "center"；
+    /// 分離後狀態窗只顯示字根與累積文字
+    pub candidate_window: String,
+    /// 候選窗直向排列候選
+    pub candidate_window_vertical: bool,
+    /// 候選窗縮放百分比（60-300）
+    pub candidate_window_zoom: u32,
+    /// 候選窗使用高對比配色
+    pub candidate_window_high_contrast: bool,
+    /// 模式浮標："off"（不使用）、"taskbar"（固定在工作列末端旁）或 "floating"（可拖曳，靠近工作列時貼齊），顯示肥/英，點擊切換
+    pub gui_mode_badge: String,
+    /// 螢幕鍵盤的佈局檔（JSON，相對於執行檔目錄，可用環境變數）；空字串表示內建的標準 60% 佈局
+    pub keyboard_layout: String,
+    /// 遊戲模式窗口沒有焦點時偵測到 Ctrl+V（已回到遊戲貼上），自動清除累積文字
+    pub gui_clear_after_paste: bool,
+    /// 遊戲模式窗口累積文字最多可撤回幾步（Ctrl+Z 撤回、Ctrl+Y 重做；0 表示停用）
+    pub gui_undo_limit: usize,
+    /// 候選後標註來源：〔自〕使用者字典、〔學〕依使用次數排前；關閉時只標註額外字碼表層的名稱
+    pub gui_candidate_source: bool,
+    /// 送出文字的方式："auto"（依內容自動挑選，擴充平面字元用貼上）、"paste" 或 "unicode"
+    pub send_mode: String,
+    /// 單次送出的字數上限，超過時分段送出（0 表示不限，預設）
+    pub send_max_chars: usize,
+    /// 每分鐘送出的字數上限，超過的文字延後送出，避免被遊戲判定洗頻（0 表示不限，預設）
+    pub send_max_chars_per_minute: usize,
+    /// 長文分段送出（Ctrl+Alt+V）每段的字數
+    pub long_paste_chunk_chars: usize,
+    /// 長文分段送出兩段之間的延遲（毫秒）
+    pub long_paste_delay_ms: u64,
+    /// 抬手才上屏：選字後等所有實體按鍵都放開才送出文字（給對按鍵時序敏感的遊戲）
+    pub commit_on_release: bool,
+    /// 抬手才上屏最多等待的毫秒數，超過就照常送出
+    pub commit_on_release_timeout_ms: u64,
+    /// 啟動時只建立托盤、不顯示狀態窗（之後以 Ctrl+Space 或托盤選單叫出）
+    pub start_minimized: bool,
+    /// 啟動時把系統預設輸入法切到英文鍵盤，退出時還原（異常結束時下次啟動補還原）
+    pub switch_to_english_layout: bool,
+    /// 依使用紀錄排序候選（字頻存在使用者資料目錄的 frequency.json）；false 時一律使用字碼表順序
+    pub candidate_frequency: bool,
+    /// 候選排序方式："table"（字碼表順序）、"frequency"（選用次數）或 "recency"（最近選用），見 sorter 模組
+    pub candidate_sort: String,
+    /// 前景是終端機時的送字相容模式："unicode"（去除結尾換行、逐字輸入）、"strip_newline"、"confirm" 或 "off"
+    pub terminal_mode: String,
+    /// 候選超過一頁時的提示方式："off"、"beep"（短嗶聲）或 "speech"（語音唸出頁數）
+    pub page_announce: String,
+    /// 以鍵盤 LED 或序列埠顯示英/肥模式："off"、"scroll_lock"、"num_lock"、"caps_lock" 或序列埠名稱（例如 "COM3"）
+    pub mode_indicator: String,
+    /// 模式輸出到序列埠時的鮑率
+    pub mode_indicator_baud: u32,
+    /// 額外載入的字碼表（相對於方案目錄），依優先順序排列；"main" 代表方案的主字碼表，未列出時主字碼表最優先
+    pub dictionary_layers: Vec<String>,
+    /// 同一字根出現在多個字碼表時的候選合併方式："dedup"（依序接起來並去掉重複）、"keep_first_layer"（只用優先順序最高的字碼表）或 "interleave"（輪流取）
+    pub dictionary_merge_policy: String,
+    /// 選字確認模式：大於 0 時第一次按選字鍵只高亮，在這段時間（毫秒）內再按一次或按 Space 才送出
+    pub selection_confirm_ms: u64,
+    /// 送出後依字碼表中的詞組提示聯想詞，輸入下一個字根前可以按選字鍵選用
+    pub associated_phrases: bool,
+    /// 字根打滿（嘸蝦米為 5 碼）、只有一個候選且沒有更長的字根時自動送出，不必按 Space
+    pub auto_commit_unique: bool,
+    /// 補全提示：組字時在狀態窗第二行列出接著輸入哪些字根還能組成更長的字根（例如 a → +b, +d, +x）
+    pub completion_hints: bool,
+    /// 以較長的字根送出、字碼表裡還有更短的字根時，在狀態窗提示簡碼（例如「「的」可用 2 碼: xx」）
+    pub shorter_code_hints: bool,
+    /// 整句組字：選字送出的文字先累積在狀態窗，沒有字根時按 Enter 才一次送出（避免在遊戲中每個字都貼上一次）
+    pub compose_sentence: bool,
+    /// emoji 短碼表："emoji" 為內建表，也可以是方案目錄中與字典同格式的檔案；空字串表示不使用
+    pub emoji_layer: String,
+    /// emoji 短碼的前綴鍵（取第一個字元），以它開頭的輸入改查 emoji 短碼表，例如設為 ":" 後輸入 :smile:
+    /// 預設為空字串（不使用），避免攔下平常要打的符號
+    pub emoji_prefix: String,
+    /// 重複送出的觸發鍵（取第一個字元）：有候選時按它再輸入數字，送出時重複送出該次數；空字串表示不使用
+    pub repeat_key: String,
+    /// 符號選單鍵（取第一個字元，例如 "="）：沒有輸入字根時按它在候選區列出符號分類；空字串表示不使用
+    pub symbol_menu_key: String,
+    /// 符號選單的符號表（JSON，相對於執行檔目錄，可用環境變數）；空字串表示內建的表（標點、數學、箭頭、框線）
+    pub symbol_table: String,
+    /// 重送鍵："F1"～"F24"（F4 為退出鍵，不能使用）：沒有在組字時按它重送最近一次送出的文字；"none" 表示不使用
+    pub repeat_output_key: String,
+    /// 候選字集："all"（不篩選）、"big5"（Big5 編碼得出來的字）或 "common"（Big5 常用字）
+    pub candidate_charset: String,
+    /// 字集以外的候選移到最後面而不是藏起來
+    pub candidate_charset_demote: bool,
+    /// 線上更新字碼表的網址（liu.json 或 .cin，托盤選單「更新字碼表」下載）；空字串表示不使用
+    pub dictionary_update_url: String,
+    /// 更新字碼表的 SHA-256 校驗碼；空字串表示下載網址加上 .sha256 的校驗碼檔
+    pub dictionary_update_sha256: String,
+    /// 線上備援查詢的網址，{code} 換成字根，回應為候選的 JSON 陣列；空字串表示不查詢（預設）
+    /// 本地字碼表查不到的字根才在背景查詢，查到的候選寫入使用者字典
+    pub cloud_lookup_url: String,
+    /// 肥模式下按住 Ctrl 時放行給前景程式的組合鍵（鍵名以逗號分隔，見 key_vk）；"all" 表示 Ctrl 組合一律放行
+    /// 清單以外的 Ctrl 組合留給輸入法使用：組字中攔截，沒有組字時放行
+    pub ctrl_passthrough: String,
+    /// 肥模式下也原樣放行的按鍵（每個字元一個鍵，例如 "/;" 讓遊戲的技能鍵不被攔截）；空字串表示不使用
+    pub passthrough_keys: String,
+    /// 其他軟體以 VK_PACKET 送出的字元（巨集軟體、螢幕小鍵盤）是否加入遊戲模式窗口的累積文字；按鍵本身一律放行
+    pub accumulate_packet_input: bool,
+    /// 依時段自動切換模式與使用者（見 schedule 模組）；空清單表示不使用
+    pub schedule: Vec<ScheduleEntry>,
+    /// 日期/時間巨集碼：字根完全符合 code 時送出依 format 展開的目前日期/時間（見 time_macros 模組）；預設空清單，不使用
+    pub time_macros: Vec<TimeMacro>,
+    /// 主控台的日誌過濾規則（EnvFilter 語法，例如 "info,uclliu::keyboard_hook=trace"）；空字串表示使用 RUST_LOG 或預設的 debug
+    pub log_filter: String,
+    /// 組字時翻頁候選的按鍵組合（見 PAGE_KEY_PAIRS，可用逗號列出多組）；"none" 表示不使用
+    /// 沒有組字時這些鍵照常輸入；comma_period 會讓 , . 不能再當字根的結尾，請斟酌使用
+    pub page_keys: String,
+    /// 按鍵回放保留的按鍵數（托盤「按鍵回放」）；0 表示不記錄
+    pub key_trace_len: usize,
+    /// 自動備份使用者資料與設定檔的間隔（分鐘）；0 表示不備份
+    pub backup_interval_minutes: u64,
+    /// 自動備份保留的份數（見 backup 模組）；0 表示不備份
+    pub backup_keep: usize,
 }
 
 impl Default for Config {
@@ -38,8 +240,74 @@ impl Default for Config {
             y: 950,
             sp: false,
             play_sound_enable: false,
+            invalid_key_flash: false,
             startup_default_ucl: true,
             enable_half_full: true,
+            shift_letter: "pass".to_string(),
+            scheme: String::new(),
+            dictionary_path: String::new(),
+            profile: String::new(),
+            gui_fade: true,
+            obs_output_path: String::new(),
+            obs_update_interval_ms: crate::obs_output::DEFAULT_INTERVAL.as_millis() as u64,
+            dictionary_backend: "memory".to_string(),
+            lookup_unicode_names: true,
+            gui_overlay: false,
+            gui_dock: "floating".to_string(),
+            gui_mini_float: "off".to_string(),
+            candidate_window: "off".to_string(),
+            candidate_window_vertical: false,
+            candidate_window_zoom: 100,
+            candidate_window_high_contrast: false,
+            gui_mode_badge: "off".to_string(),
+            keyboard_layout: String::new(),
+            gui_clear_after_paste: true,
+            gui_undo_limit: crate::gui_backend::DEFAULT_UNDO_LIMIT,
+            gui_candidate_source: false,
+            send_mode: "auto".to_string(),
+            send_max_chars: 0,
+            send_max_chars_per_minute: 0,
+            long_paste_chunk_chars: long_paste::DEFAULT_CHUNK_CHARS,
+            long_paste_delay_ms: long_paste::DEFAULT_DELAY.as_millis() as u64,
+            commit_on_release: false,
+            commit_on_release_timeout_ms: release_gate::DEFAULT_TIMEOUT.as_millis() as u64,
+            start_minimized: true,
+            switch_to_english_layout: false,
+            candidate_frequency: true,
+            candidate_sort: "table".to_string(),
+            terminal_mode: "unicode".to_string(),
+            page_announce: "off".to_string(),
+            mode_indicator: "off".to_string(),
+            mode_indicator_baud: 9600,
+            dictionary_layers: Vec::new(),
+            dictionary_merge_policy: "dedup".to_string(),
+            selection_confirm_ms: 0,
+            associated_phrases: true,
+            auto_commit_unique: false,
+            completion_hints: false,
+            shorter_code_hints: false,
+            compose_sentence: false,
+            emoji_layer: "emoji".to_string(),
+            emoji_prefix: String::new(),
+            repeat_key: "\\".to_string(),
+            symbol_menu_key: String::new(),
+            symbol_table: String::new(),
+            repeat_output_key: "none".to_string(),
+            candidate_charset: "all".to_string(),
+            candidate_charset_demote: false,
+            dictionary_update_url: String::new(),
+            dictionary_update_sha256: String::new(),
+            cloud_lookup_url: String::new(),
+            ctrl_passthrough: DEFAULT_CTRL_PASSTHROUGH.to_string(),
+            passthrough_keys: String::new(),
+            accumulate_packet_input: false,
+            schedule: Vec::new(),
+            time_macros: Vec::new(),
+            log_filter: String::new(),
+            page_keys: "page_up_down".to_string(),
+            key_trace_len: crate::key_trace::DEFAULT_TRACE_LEN,
+            backup_interval_minutes: 60,
+            backup_keep: crate::backup::DEFAULT_BACKUP_KEEP,
         }
     }
 }
@@ -47,14 +315,7 @@ impl Default for Config {
 impl Config {
     /// 載入配置檔案
     pub fn load() -> Result<Self> {
-        let exe_path = std::env::current_exe()?;
-        let exe_dir = exe_path.parent()
-            .ok_or_else(|| std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "無法取得執行檔目錄"
-            ))?;
-        
-        let config_path = exe_dir.join("UCLLIU.ini");
+        let config_path = Self::path()?;
         
         if !config_path.exists() {
             // 如果配置檔案不存在，使用預設值並創建檔案
@@ -69,19 +330,230 @@ impl Config {
     
     /// 儲存配置檔案
     pub fn save(&self) -> Result<()> {
+        let _config_path = Self::path()?;
+        
+        // TODO: 寫入 INI 格式
+        // 目前先不實作
+        
+        Ok(())
+    }
+    
+    /// 設定檔路徑（執行檔目錄下的 UCLLIU.ini）
+    pub fn path() -> Result<PathBuf> {
         let exe_path = std::env::current_exe()?;
         let exe_dir = exe_path.parent()
             .ok_or_else(|| std::io::Error::new(
                 std::io::ErrorKind::NotFound,
                 "無法取得執行檔目錄"
             ))?;
-        
-        let _config_path = exe_dir.join("UCLLIU.ini");
-        
-        // TODO: 寫入 INI 格式
-        // 目前先不實作
-        
-        Ok(())
+        Ok(exe_dir.join("UCLLIU.ini"))
+    }
+
+    /// 候選翻頁鍵（以逗號或空白分隔多組），回傳每組上一頁與下一頁的虛擬鍵碼
+    pub fn page_keys(&self) -> Result<Vec<(u32, u32)>> {
+        let mut keys = Vec::new();
+        for name in self.page_keys.split([',', ' ']).map(str::trim).filter(|name| !name.is_empty()) {
+            let name = name.to_ascii_lowercase();
+            if name == "none" {
+                continue;
+            }
+            let Some(&(_, pair)) = PAGE_KEY_PAIRS.iter().find(|(pair_name, _)| *pair_name == name) else {
+                let names: Vec<&str> = PAGE_KEY_PAIRS.iter().map(|(pair_name, _)| *pair_name).collect();
+                bail!("未知的翻頁鍵 '{}'（可用: none、{}）", name, names.join("、"));
+            };
+            if !keys.contains(&pair) {
+                keys.push(pair);
+            }
+        }
+        Ok(keys)
     }
+
+    /// 重送鍵的虛擬鍵碼，"none" 或空字串時回傳 None
+    pub fn repeat_output_key(&self) -> Result<Option<u32>> {
+        let name = self.repeat_output_key.trim();
+        if name.is_empty() || name.eq_ignore_ascii_case("none") {
+            return Ok(None);
+        }
+        let number = name
+            .strip_prefix(['F', 'f'])
+            .and_then(|n| n.parse::<u32>().ok())
+            .filter(|n| (1..=24).contains(n) && *n != 4);
+        let Some(number) = number else {
+            bail!("未知的重送鍵 '{}'（可用: none、F1～F24，F4 除外）", name);
+        };
+        // VK_F1 = 0x70
+        Ok(Some(0x6F + number))
+    }
+
+    /// 放行的 Ctrl 組合鍵（虛擬鍵碼），"all" 時回傳 None（一律放行）
+    pub fn ctrl_passthrough(&self) -> Result<Option<Vec<u32>>> {
+        if self.ctrl_passthrough.trim().eq_ignore_ascii_case("all") {
+            return Ok(None);
+        }
+        let mut keys = Vec::new();
+        for name in self.ctrl_passthrough.split([',', ' ']).map(str::trim).filter(|name| !name.is_empty()) {
+            let Some(vk) = key_vk(name) else {
+                let names: Vec<&str> = KEY_NAMES.iter().map(|(key_name, _)| *key_name).collect();
+                bail!("未知的 Ctrl 放行鍵 '{}'（可用: all、a～z、0～9、F1～F24、{}）", name, names.join("、"));
+            };
+            if !keys.contains(&vk) {
+                keys.push(vk);
+            }
+        }
+        Ok(Some(keys))
+    }
+
+    /// 檢查所有需要解析的設定值（選項名稱、按鍵名稱、排程等），回傳所有無效的設定
+    /// 啟動時以 validation::into_result 一次列出，不必改一個錯再啟動一次才看到下一個
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        check_value(&mut errors, "candidate_sort", sorter::parse(&self.candidate_sort));
+        check_value(&mut errors, "candidate_charset", Charset::parse(&self.candidate_charset));
+        check_value(&mut errors, "shift_letter", ShiftLetter::parse(&self.shift_letter));
+        check_value(&mut errors, "dictionary_backend", DictionaryBackend::parse(&self.dictionary_backend));
+        check_value(&mut errors, "dictionary_merge_policy", MergePolicy::parse(&self.dictionary_merge_policy));
+        check_value(&mut errors, "send_mode", SendMode::parse(&self.send_mode));
+        check_value(&mut errors, "terminal_mode", TerminalMode::parse(&self.terminal_mode));
+        check_value(&mut errors, "page_announce", AnnounceMode::parse(&self.page_announce));
+        check_value(&mut errors, "mode_indicator", IndicatorTarget::parse(&self.mode_indicator));
+        check_value(&mut errors, "gui_dock", DockEdge::parse(&self.gui_dock));
+        check_value(&mut errors, "gui_mini_float", MiniFloatPosition::parse(&self.gui_mini_float));
+        check_value(&mut errors, "candidate_window", CandidateWindowPosition::parse(&self.candidate_window));
+        check_value(&mut errors, "gui_mode_badge", BadgePosition::parse(&self.gui_mode_badge));
+        check_value(&mut errors, "schedule", Schedule::parse(&self.schedule));
+        check_value(&mut errors, "page_keys", self.page_keys());
+        check_value(&mut errors, "repeat_output_key", self.repeat_output_key());
+        check_value(&mut errors, "ctrl_passthrough", self.ctrl_passthrough());
+        // 這幾個按鍵設定只取第一個字元，多打的字元會被默默忽略
+        for (name, value) in [
+            ("emoji_prefix", &self.emoji_prefix),
+            ("repeat_key", &self.repeat_key),
+            ("symbol_menu_key", &self.symbol_menu_key),
+        ] {
+            if value.chars().count() > 1 {
+                errors.push(ValidationError { path: name.to_string(), message: format!("應為單一字元或空字串，而不是 '{}'", value) });
+            }
+        }
+        errors
+    }
+
+    /// 原樣放行的按鍵字元（略過空白與重複）
+    pub fn passthrough_keys(&self) -> Vec<char> {
+        let mut keys: Vec<char> = Vec::new();
+        for ch in self.passthrough_keys.chars().filter(|ch| !ch.is_whitespace()) {
+            if !keys.contains(&ch) {
+                keys.push(ch);
+            }
+        }
+        keys
+    }
+}
+
+
+/// 解析設定中的路徑：展開 %VAR% 環境變數（找不到的變數原樣保留），相對路徑以 base 為準
+pub fn resolve_path(path: &str, base: &Path) -> PathBuf {
+    base.join(expand_env_vars(path.trim(), |name| std::env::var(name).ok()))
+}
+
+/// 展開 Windows 風格的 %VAR% 環境變數，%% 代表 % 本身
+fn expand_env_vars(text: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('%') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('%') else {
+            // 沒有成對的 %，原樣保留
+            result.push_str(&rest[start..]);
+            return result;
+        };
+        let name = &after[..end];
+        match lookup(name).filter(|_| !name.is_empty()) {
+            Some(value) => result.push_str(&value),
+            None if name.is_empty() => result.push('%'),
+            None => {
+                result.push('%');
+                result.push_str(name);
+                result.push('%');
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+    result
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_env_vars() {
+        let lookup = |name: &str| (name == "APPDATA").then(|| "C:\\Users\\me\\AppData\\Roaming".to_string());
+        assert_eq!(
+            expand_env_vars("%APPDATA%\\uclliu\\liu.json", lookup),
+            "C:\\Users\\me\\AppData\\Roaming\\uclliu\\liu.json"
+        );
+        assert_eq!(expand_env_vars("%NOPE%\\liu.json", lookup), "%NOPE%\\liu.json");
+        assert_eq!(expand_env_vars("100%% %APPDATA", lookup), "100% %APPDATA");
+        assert_eq!(resolve_path("liu.json", Path::new("base")), Path::new("base").join("liu.json"));
+    }
+
+    #[test]
+    fn test_passthrough_keys() {
+        let config = Config { passthrough_keys: "/ ; /".to_string(), ..Default::default() };
+        assert_eq!(config.passthrough_keys(), vec!['/', ';']);
+        assert!(Config::default().passthrough_keys().is_empty());
+    }
+
+    #[test]
+    fn test_page_keys() {
+        assert_eq!(Config::default().page_keys().unwrap(), vec![(0x21, 0x22)]);
+        let config = Config { page_keys: "Minus_Equal, up_down minus_equal".to_string(), ..Default::default() };
+        assert_eq!(config.page_keys().unwrap(), vec![(0xBD, 0xBB), (0x26, 0x28)]);
+        let config = Config { page_keys: "none".to_string(), ..Default::default() };
+        assert!(config.page_keys().unwrap().is_empty());
+        let config = Config { page_keys: "tab".to_string(), ..Default::default() };
+        assert!(config.page_keys().is_err());
+    }
+
+    #[test]
+    fn test_ctrl_passthrough() {
+        let keys = Config::default().ctrl_passthrough().unwrap().unwrap();
+        assert!(keys.contains(&0x43) && keys.contains(&0x56) && keys.contains(&0x09));
+        assert!(!keys.contains(&0x42));
+        let config = Config { ctrl_passthrough: "C, v F5 Page_Up c".to_string(), ..Default::default() };
+        assert_eq!(config.ctrl_passthrough().unwrap(), Some(vec![0x43, 0x56, 0x74, 0x21]));
+        let config = Config { ctrl_passthrough: "ALL".to_string(), ..Default::default() };
+        assert_eq!(config.ctrl_passthrough().unwrap(), None);
+        let config = Config { ctrl_passthrough: "ctrl".to_string(), ..Default::default() };
+        assert!(config.ctrl_passthrough().is_err());
+    }
+
+    #[test]
+    fn test_validate_reports_every_invalid_value() {
+        assert!(Config::default().validate().is_empty());
+        let config = Config {
+            candidate_sort: "random".to_string(),
+            shift_letter: "shout".to_string(),
+            ctrl_passthrough: "c, ctrl".to_string(),
+            repeat_key: "''".to_string(),
+            ..Default::default()
+        };
+        let paths: Vec<String> = config.validate().into_iter().map(|error| error.path).collect();
+        assert_eq!(paths, ["candidate_sort", "shift_letter", "ctrl_passthrough", "repeat_key"]);
+        let message = crate::validation::into_result(config.validate(), "設定檔").unwrap_err().to_string();
+        assert!(message.contains("candidate_sort: 未知的候選排序方式 'random'"));
+    }
+
+    #[test]
+    fn test_repeat_output_key() {
+        assert_eq!(Config::default().repeat_output_key().unwrap(), None);
+        let config = Config { repeat_output_key: "f9".to_string(), ..Default::default() };
+        assert_eq!(config.repeat_output_key().unwrap(), Some(0x78));
+        for name in ["F4", "F25", "Tab"] {
+            let config = Config { repeat_output_key: name.to_string(), ..Default::default() };
+            assert!(config.repeat_output_key().is_err());
+        }
+    }
+}
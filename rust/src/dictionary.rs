@@ -1,24 +1,189 @@
 //! 字碼表字典模組
 
-use anyhow::{Context, Result};
-use log::{info, warn};
-use serde::Deserialize;
+use crate::blocklist::Blocklist;
+use crate::candidate::{Candidate, CandidateSource};
+use crate::chardefs::{codes_containing, is_wildcard, sort_codes, wildcard_match, CharDefs, ChardefsFile};
+use crate::dictionary_cache;
+use crate::pinyi::HomophoneTable;
+use crate::scheme::InputScheme;
+use crate::sqlite_dictionary::SqliteStore;
+use crate::user_dictionary;
+use crate::validation;
+use anyhow::{bail, Context, Result};
+use tracing::{debug, info, warn};
+use serde_json::Value;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 
-/// 字碼表字典
+/// gcin .gtab 檔頭中 QUICK_KEYS 的大小（quick1[46][10][4] 與 quick2[46][46][10][4]）
+const GTAB_QUICK_KEYS_SIZE: usize = 46 * 10 * 4 + 46 * 46 * 10 * 4;
+/// 檔頭中 QUICK_KEYS 之後的保留區（endkey[99]、keybits、selkey2[10] 等）
+const GTAB_RESERVED_OFFSET: usize = 68 + GTAB_QUICK_KEYS_SIZE;
+/// 檔頭大小
+const GTAB_HEADER_SIZE: usize = GTAB_RESERVED_OFFSET + 128;
+/// 沒有記錄 keybits 的舊表格每個按鍵佔的位元數
+const GTAB_DEFAULT_KEYBITS: u32 = 6;
+
+/// 字典統計（給診斷報告使用）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DictionaryStats {
+    /// 字根數
+    pub codes: usize,
+    /// 候選總數
+    pub candidates: usize,
+    /// 使用 display/output 別名的候選數
+    pub aliases: usize,
+    /// 同音字表收錄的字數（沒有載入同音字表時為 None）
+    pub pinyi_chars: Option<usize>,
+    /// 使用者字典（user.json）的候選數
+    pub user_entries: usize,
+}
+
+/// 萬用字元查詢最多列出的字根數（* 太寬鬆時避免列出整份字碼表）
+pub const WILDCARD_MAX_CODES: usize = 100;
+
+/// 字典後端
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DictionaryBackend {
+    /// 整份字碼表載入記憶體（預設）
+    #[default]
+    Memory,
+    /// 字碼表轉成 SQLite 資料庫，查詢走索引（適合大型字碼表）
+    Sqlite,
+}
+
+impl DictionaryBackend {
+    /// 從設定值解析（"memory" / "sqlite"，不分大小寫，空字串為預設值）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "memory" => Ok(Self::Memory),
+            "sqlite" => Ok(Self::Sqlite),
+            other => Err(anyhow::anyhow!("未知的字典後端 '{}'（可用: memory、sqlite）", other)),
+        }
+    }
+}
+
+/// 同一字根出現在多個字碼表（主字碼表與額外層）時的合併方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// 依優先順序接在一起，重複的候選只留第一個（預設）
+    #[default]
+    Dedup,
+    /// 只用優先順序最高、含有該字根的那一個字碼表
+    KeepFirstLayer,
+    /// 各字碼表輪流取一個候選，重複的略過
+    Interleave,
+}
+
+impl MergePolicy {
+    /// 從設定值解析（"dedup" / "keep_first_layer" / "interleave"，不分大小寫，空字串為預設值）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "dedup" => Ok(Self::Dedup),
+            "keep_first_layer" => Ok(Self::KeepFirstLayer),
+            "interleave" => Ok(Self::Interleave),
+            other => Err(anyhow::anyhow!(
+                "未知的候選合併方式 '{}'（可用: dedup、keep_first_layer、interleave）",
+                other
+            )),
+        }
+    }
+
+    /// 合併各來源的候選（依優先順序排列，沒有該字根的來源不列入）
+    pub fn merge(self, sources: &[&[String]]) -> Vec<String> {
+        let mut chars: Vec<String> = Vec::new();
+        let mut push = |ch: &String| {
+            if !chars.contains(ch) {
+                chars.push(ch.clone());
+            }
+        };
+        match self {
+            Self::Dedup => sources.iter().flat_map(|source| source.iter()).for_each(&mut push),
+            Self::KeepFirstLayer => sources.first().into_iter().flat_map(|source| source.iter()).for_each(&mut push),
+            Self::Interleave => {
+                let longest = sources.iter().map(|source| source.len()).max().unwrap_or(0);
+                for i in 0..longest {
+                    sources.iter().filter_map(|source| source.get(i)).for_each(&mut push);
+                }
+            }
+        }
+        chars
+    }
+}
+
+/// 設定中代表方案主字碼表（含符號表）的層名稱
+pub const MAIN_LAYER: &str = "main";
+
+/// 設定中代表內建顏文字表的層名稱
+pub const KAOMOJI_LAYER: &str = "kaomoji";
+
+/// 內建顏文字表（字典格式的示例，候選為多字元字串，含組合字元與 emoji ZWJ 序列）
+const KAOMOJI_TABLE: &str = include_str!("../kaomoji.json");
+
+/// 設定中代表內建 emoji 短碼表的名稱
+pub const EMOJI_LAYER: &str = "emoji";
+
+/// 內建 emoji 短碼表（smile、thumbsup 之類的英文短碼，以前綴鍵開頭輸入，例如 :smile:）
+const EMOJI_TABLE: &str = include_str!("../emoji.json");
+
+/// 使用者文字片段（snippets.json）的層名稱，見 snippets 模組
+pub const SNIPPETS_LAYER: &str = "snippets";
+
+/// 額外載入的字碼表層（例如公司術語表），見 Dictionary::load_layers
 #[derive(Clone)]
+pub struct DictionaryLayer {
+    /// 層名稱（檔名去掉副檔名，GUI 以此標示候選來源）
+    pub name: String,
+    pub dictionary: Dictionary,
+}
+
+/// 字碼表字典
+#[derive(Clone, Default)]
 pub struct Dictionary {
     /// 字根 -> 候選字列表的映射
+    /// 使用 SQLite 後端時只存放額外合併進來的字根（例如符號表）
     pub code_to_chars: HashMap<String, Vec<String>>,
-    /// 同音字表（可選）
-    pub pinyi_data: Option<Vec<String>>,
+    /// 同音字表（可選，見 pinyi 模組）
+    pub pinyi_data: Option<HomophoneTable>,
+    /// 別名輸出：字根 -> (顯示文字 -> 實際送出文字)
+    /// 只有使用 display/output 格式的候選才會出現在這裡
+    pub candidate_outputs: HashMap<String, HashMap<String, String>>,
+    /// SQLite 後端（主字碼表），None 表示全部在記憶體
+    pub sqlite: Option<Arc<SqliteStore>>,
+    /// 使用者字典（user.json），同一字根的候選排在主字碼表之前
+    pub user: Option<Box<Dictionary>>,
+    /// 使用者字典檔（add_entry/remove_entry 寫回的 user.json）
+    pub user_path: Option<PathBuf>,
+    /// 停用字清單（blocklist.json），查詢時濾掉
+    pub blocklist: Blocklist,
+    /// 額外的字碼表層，依優先順序排列
+    pub layers: Vec<DictionaryLayer>,
+    /// 主字碼表在各層之間的位置：前 main_priority 層的候選排在主字碼表之前
+    pub main_priority: usize,
+    /// 同一字根出現在多個字碼表時的合併方式
+    pub merge_policy: MergePolicy,
+    /// emoji 短碼表層：只在以前綴鍵開頭的輸入中查詢，不混入一般候選
+    pub emoji: Option<Box<DictionaryLayer>>,
+    /// 字碼表無法解析、改用上次載入成功的快取版本（啟動後提示使用者）
+    pub restored_from_cache: bool,
+    /// 記憶體字碼表的反查索引：文字 -> 最短的字根（第一次 shortest_code 時建立，主字碼表換掉時重建）
+    pub(crate) shortest_codes: OnceLock<Arc<HashMap<String, String>>>,
 }
 
 impl Dictionary {
     /// 載入字碼表
-    /// 字典檔必須與執行檔放在同一目錄
-    pub fn load() -> Result<Self> {
+    /// 沒有指定路徑（設定 dictionary_path 或 --dict）時使用執行檔目錄中的 liu.json
+    pub fn load(dictionary_path: Option<&Path>) -> Result<Self> {
+        if let Some(path) = dictionary_path {
+            if !path.exists() {
+                return Err(anyhow::anyhow!("找不到指定的字碼表檔案: {:?}", path));
+            }
+            return Self::load_from(path, false);
+        }
+        
         let exe_path = std::env::current_exe()?;
         let exe_dir = exe_path.parent()
             .ok_or_else(|| std::io::Error::new(
@@ -26,7 +191,7 @@ impl Dictionary {
                 "無法取得執行檔目錄"
             ))?;
         
-        // 字典檔必須與執行檔放在同一目錄
+        // 預設字典檔與執行檔放在同一目錄
         let json_path = exe_dir.join("liu.json");
         
         if !json_path.exists() {
@@ -36,65 +201,856 @@ impl Dictionary {
             ));
         }
         
+        Self::load_from(&json_path, false)
+    }
+    
+    /// 從指定路徑載入字碼表
+    /// 同音字表 pinyi.txt 從字碼表所在目錄讀取（可選）
+    /// strict 為 true 時依內建 schema 嚴格驗證（見 validation 模組），此時一律解析 JSON、不使用快取
+    pub fn load_from(json_path: &Path, strict: bool) -> Result<Self> {
         info!("載入字碼表: {:?}", json_path);
         
-        let content = fs::read_to_string(&json_path)
-            .with_context(|| format!("無法讀取字碼表: {:?}", json_path))?;
-        
-        // JSON 檔案格式：{ "chardefs": { "字根": ["候選字1", "候選字2", ...], ... } }
-        #[derive(Deserialize)]
-        struct LiuJsonFile {
-            chardefs: HashMap<String, Vec<String>>,
-        }
-        
-        let json_file: LiuJsonFile = serde_json::from_str(&content)
-            .with_context(|| "無法解析 JSON 格式")?;
-        
-        // 提取 chardefs 並將所有鍵轉為小寫（根據 Python 版本的處理邏輯）
-        // 參考：uclliu.pyw 第 1180-1189 行
-        let mut code_map: HashMap<String, Vec<String>> = HashMap::new();
-        for (key, value) in json_file.chardefs {
-            let lower_key = key.to_lowercase();
-            // 如果已經存在小寫鍵，合併候選字列表
-            code_map.entry(lower_key)
-                .and_modify(|v| {
-                    // 合併候選字，避免重複
-                    for char in &value {
-                        if !v.contains(char) {
-                            v.push(char.clone());
-                        }
+        let cached = if strict { None } else { dictionary_cache::load(json_path) };
+        let mut dictionary = match cached {
+            Some(dictionary) => dictionary,
+            None => match Self::read_file(json_path, strict) {
+                Ok(dictionary) => {
+                    // 快取寫不進去（例如唯讀目錄）只影響下次啟動速度
+                    if let Err(e) = dictionary_cache::save(json_path, &dictionary) {
+                        warn!("無法建立字碼表快取: {:#}", e);
                     }
-                })
-                .or_insert_with(|| value);
+                    dictionary
+                }
+                // 字碼表被其他程式或中斷的下載改壞時，退回上次載入成功的版本（嚴格模式照常報錯）
+                Err(e) => match dictionary_cache::load_last_good(json_path).filter(|_| !strict) {
+                    Some(mut dictionary) => {
+                        warn!("{:#}，改用上次載入成功的快取版本", e);
+                        dictionary.restored_from_cache = true;
+                        dictionary
+                    }
+                    None => return Err(e),
+                },
+            },
+        };
+        
+        info!("已載入 {} 個字根", dictionary.code_to_chars.len());
+        
+        dictionary.pinyi_data = Self::load_pinyi(json_path);
+        
+        Ok(dictionary)
+    }
+    
+    /// 以 SQLite 後端載入字碼表（第一次會從 JSON 建立資料庫，之後直接開啟）
+    pub fn load_sqlite(json_path: &Path, strict: bool) -> Result<Self> {
+        let store = SqliteStore::open_or_build(json_path, strict)?;
+        Ok(Self {
+            pinyi_data: Self::load_pinyi(json_path),
+            sqlite: Some(Arc::new(store)),
+            ..Default::default()
+        })
+    }
+    
+    /// 載入同音字表（可選）
+    /// 同音字表必須與字碼表放在同一目錄
+    fn load_pinyi(json_path: &Path) -> Option<HomophoneTable> {
+        let pinyi_path = json_path
+            .parent()
+            .map(|dir| dir.join("pinyi.txt"))
+            .unwrap_or_else(|| Path::new("pinyi.txt").to_path_buf());
+        
+        if !pinyi_path.exists() {
+            return None;
+        }
+        match fs::read_to_string(&pinyi_path) {
+            Ok(content) => {
+                let table = HomophoneTable::parse(&content);
+                if table.is_empty() {
+                    warn!("同音字表 {:?} 沒有任何同音字", pinyi_path);
+                    return None;
+                }
+                info!("載入同音字表: {:?}（{} 個字）", pinyi_path, table.len());
+                Some(table)
+            }
+            Err(e) => {
+                warn!("無法讀取同音字表 {:?}: {}", pinyi_path, e);
+                None
+            }
+        }
+    }
+    
+    /// 這個字的同音字（沒有同音字表或沒有收錄時為空）
+    pub fn homophones(&self, ch: char) -> &[char] {
+        self.pinyi_data.as_ref().map(|table| table.get(ch)).unwrap_or(&[])
+    }
+    
+    /// 載入輸入法方案的字典與符號表（符號表一律放在記憶體）
+    /// 指定 dictionary_path 時以它取代方案的字碼表，符號表仍從方案目錄讀取
+    pub fn load_scheme(
+        scheme: &InputScheme,
+        scheme_dir: &Path,
+        dictionary_path: Option<&Path>,
+        strict: bool,
+        backend: DictionaryBackend,
+    ) -> Result<Self> {
+        if let Some(path) = dictionary_path.filter(|path| !path.exists()) {
+            return Err(anyhow::anyhow!("找不到指定的字碼表檔案: {:?}", path));
+        }
+        let json_path = dictionary_path
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| scheme_dir.join(&scheme.dictionary));
+        if !json_path.exists() {
+            return Err(anyhow::anyhow!(
+                "找不到方案 '{}' 的字碼表檔案 {}\n方案目錄: {:?}",
+                scheme.name,
+                scheme.dictionary,
+                scheme_dir
+            ));
         }
         
-        info!("已載入 {} 個字根", code_map.len());
+        let mut dictionary = match backend {
+            DictionaryBackend::Memory => Self::load_from(&json_path, strict)?,
+            DictionaryBackend::Sqlite => Self::load_sqlite(&json_path, strict)?,
+        };
         
-        // 載入同音字表（可選）
-        // 同音字表必須與執行檔放在同一目錄
-        let pinyi_path = exe_dir.join("pinyi.txt");
+        // 符號表與字典同格式，合併在字典之後
+        if let Some(symbols) = &scheme.symbols {
+            let symbols_path = scheme_dir.join(symbols);
+            match fs::read_to_string(&symbols_path) {
+                Ok(content) => {
+                    let symbols_dict = Self::parse(&content, strict)
+                        .with_context(|| format!("無法解析符號表: {:?}", symbols_path))?;
+                    info!("載入符號表: {:?}（{} 個字根）", symbols_path, symbols_dict.code_to_chars.len());
+                    dictionary.merge(symbols_dict);
+                }
+                Err(e) => warn!("無法讀取符號表 {:?}: {}", symbols_path, e),
+            }
+        }
         
-        let pinyi_data = if pinyi_path.exists() {
-            info!("載入同音字表: {:?}", pinyi_path);
-            Some(
-                fs::read_to_string(&pinyi_path)
-                    .ok()
-                    .map(|s| s.lines().map(|l| l.to_string()).collect())
-                    .unwrap_or_default()
-            )
-        } else {
+        Ok(dictionary)
+    }
+    
+    /// 依優先順序載入額外的字碼表層（相對於方案目錄、與字典同格式的 JSON）
+    /// 清單中的 "main" 標出主字碼表的位置，沒有列出時主字碼表優先於所有額外層；"kaomoji" 為內建顏文字表、"emoji" 為內建 emoji 短碼表
+    /// 單一層讀取或解析失敗只警告並略過，不影響啟動
+    pub fn load_layers(&mut self, scheme_dir: &Path, names: &[String], strict: bool) {
+        self.layers.clear();
+        self.main_priority = 0;
+        for name in names {
+            let name = name.trim();
+            if name.eq_ignore_ascii_case(MAIN_LAYER) {
+                self.main_priority = self.layers.len();
+                continue;
+            }
+            if let Some(layer) = Self::read_layer(scheme_dir, name, strict) {
+                self.layers.push(layer);
+            }
+        }
+    }
+    
+    /// 讀取單一字碼表層："kaomoji"、"emoji" 為內建表，其他為相對於方案目錄的檔案
+    /// 讀取或解析失敗時警告並回傳 None
+    fn read_layer(scheme_dir: &Path, name: &str, strict: bool) -> Option<DictionaryLayer> {
+        let builtin = [(KAOMOJI_LAYER, KAOMOJI_TABLE), (EMOJI_LAYER, EMOJI_TABLE)]
+            .into_iter()
+            .find(|(builtin, _)| name.eq_ignore_ascii_case(builtin));
+        if let Some((builtin, table)) = builtin {
+            return match Self::parse(table, strict) {
+                Ok(dictionary) => Some(DictionaryLayer { name: builtin.to_string(), dictionary }),
+                Err(e) => {
+                    warn!("內建字碼表 {} 有誤: {:#}", builtin, e);
+                    None
+                }
+            };
+        }
+        let path = scheme_dir.join(name);
+        let result = Self::read_file(&path, strict);
+        match result {
+            Ok(dictionary) => {
+                info!("載入字碼表層: {:?}（{} 個字根）", path, dictionary.code_to_chars.len());
+                let layer_name = Path::new(name)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| name.to_string());
+                Some(DictionaryLayer { name: layer_name, dictionary })
+            }
+            Err(e) => {
+                warn!("無法載入字碼表層 {:?}: {:#}", path, e);
+                None
+            }
+        }
+    }
+    
+    /// 載入 emoji 短碼表層（"emoji" 為內建表，也可以是方案目錄中同格式的檔案），空字串表示不使用
+    pub fn load_emoji_layer(&mut self, scheme_dir: &Path, name: &str, strict: bool) {
+        let name = name.trim();
+        self.emoji = if name.is_empty() {
             None
+        } else {
+            Self::read_layer(scheme_dir, name, strict).map(Box::new)
+        };
+    }
+    
+    /// 以 emoji 短碼的開頭查詢（短的短碼在前，完全符合的自然排第一），依短碼分組回傳
+    /// 沒有載入 emoji 表或還沒輸入短碼時為空
+    pub fn lookup_emoji(&self, partial: &str) -> Vec<(String, Vec<String>)> {
+        let Some(layer) = self.emoji.as_deref().filter(|_| !partial.is_empty()) else {
+            return Vec::new();
+        };
+        let chars = &layer.dictionary.code_to_chars;
+        let mut codes: Vec<String> = chars.keys().filter(|code| code.starts_with(partial)).cloned().collect();
+        sort_codes(&mut codes);
+        codes
+            .into_iter()
+            .take(WILDCARD_MAX_CODES)
+            .map(|code| {
+                let candidates = chars[&code].clone();
+                (code, candidates)
+            })
+            .collect()
+    }
+    
+    /// emoji 短碼表中候選的別名輸出
+    pub fn emoji_output_for(&self, code: &str, display: &str) -> Option<&str> {
+        let outputs = self.emoji.as_ref()?.dictionary.candidate_outputs.get(code)?;
+        outputs.get(display).map(String::as_str)
+    }
+    
+    /// 在最低優先順序加入一層字碼表
+    pub fn add_layer(&mut self, name: impl Into<String>, dictionary: Dictionary) {
+        self.layers.push(DictionaryLayer { name: name.into(), dictionary });
+    }
+    
+    /// 套用使用者文字片段：放在最高優先順序（排在所有額外層與主字碼表之前，僅次於使用者字典）
+    /// 先移除舊的片段層，None 表示不使用
+    pub fn set_snippets(&mut self, snippets: Option<Dictionary>) {
+        if let Some(i) = self.layers.iter().position(|layer| layer.name == SNIPPETS_LAYER) {
+            self.layers.remove(i);
+            if i < self.main_priority {
+                self.main_priority -= 1;
+            }
+        }
+        if let Some(dictionary) = snippets {
+            self.layers.insert(0, DictionaryLayer { name: SNIPPETS_LAYER.to_string(), dictionary });
+            self.main_priority += 1;
+        }
+    }
+    
+    /// 從 JSON 字串解析字碼表
+    /// JSON 檔案格式：{ "chardefs": { "字根": ["候選字1", "候選字2", ...], ... } }
+    /// 候選也可以寫成 { "display": "笑", "output": "😂" }，GUI 顯示 display、送出時改送 output
+    /// 可選的 "weights": { "字根": { "候選": 權重 } } 讓表格作者預先排定候選順序（權重高的在前）
+    pub fn from_json(content: &str) -> Result<Self> {
+        Self::parse(content, false)
+    }
+    
+    /// 讀取字碼表檔：副檔名為 .gtab 時當成 gcin 的二進位表格（見 from_gtab），其他一律當成 JSON
+    pub fn read_file(path: &Path, strict: bool) -> Result<Self> {
+        let is_gtab = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gtab"));
+        if is_gtab {
+            let bytes = fs::read(path).with_context(|| format!("無法讀取字碼表: {:?}", path))?;
+            return Self::from_gtab(&bytes).with_context(|| format!("gtab 表格 {:?} 有誤", path));
+        }
+        let content = fs::read_to_string(path).with_context(|| format!("無法讀取字碼表: {:?}", path))?;
+        Self::parse(&content, strict).with_context(|| format!("字碼表 {:?} 有誤", path))
+    }
+    
+    /// 解析 gcin 的 .gtab 二進位表格（gcin2tab 產生，整數為 little-endian）
+    /// 結構：檔頭、keymap[KeyS]、keyname[KeyS][4]、idx1[KeyS + 1]、DefC 個項目、詞組區
+    /// 每個項目是打包的字根（每鍵 keybits 位元，第一鍵在最高位，值為 keymap 的索引，0 表示結束）
+    /// 與 4 位元組的字；字的第一個位元組小於 0x80 時是詞組區的索引（3 個位元組，big-endian）
+    /// 字根轉為小寫，同一字根的候選依表格中的順序排列
+    pub fn from_gtab(bytes: &[u8]) -> Result<Self> {
+        let header = bytes.get(..GTAB_HEADER_SIZE).context("檔頭不完整，不是 gtab 表格")?;
+        let read_count = |offset: usize, name: &str| -> Result<usize> {
+            let value = i32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+            usize::try_from(value).with_context(|| format!("檔頭的 {} 不合理: {}", name, value))
+        };
+        let key_count = read_count(52, "KeyS")?;
+        let max_press = read_count(56, "MaxPress")?;
+        let defined = read_count(64, "DefC")?;
+        let keybits = match header[GTAB_RESERVED_OFFSET + 99] {
+            0 => GTAB_DEFAULT_KEYBITS,
+            bits => bits as u32,
+        };
+        if !(1..=8).contains(&keybits) {
+            bail!("檔頭的 keybits 不合理: {}", keybits);
+        }
+        // 字根總位元數超過 32 時項目改用 8 位元組的字根
+        let key_width: u32 = if keybits as usize * max_press > 32 { 64 } else { 32 };
+        let item_size = key_width as usize / 8 + 4;
+        
+        let mut pos = GTAB_HEADER_SIZE;
+        let mut take = |len: usize| -> Result<&[u8]> {
+            let slice = pos.checked_add(len).and_then(|end| bytes.get(pos..end)).context("檔案不完整")?;
+            pos += len;
+            Ok(slice)
+        };
+        let keymap = take(key_count)?;
+        take(key_count * 4)?; // keyname：只給 gcin 顯示用
+        take((key_count + 1) * 4)?; // idx1：依第一鍵的索引，查詢用
+        let items = take(defined.checked_mul(item_size).context("檔頭的 DefC 不合理")?)?;
+        
+        // 詞組區（可省略）：phrnum、phridx[phrnum]（各詞組的結束位置）、phrbuf
+        let mut phrases: Vec<&[u8]> = Vec::new();
+        if let Ok(count) = take(4) {
+            let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+            let ends: Vec<usize> = take(count.checked_mul(4).context("詞組數不合理")?)?
+                .chunks_exact(4)
+                .map(|end| u32::from_le_bytes(end.try_into().unwrap()) as usize)
+                .collect();
+            let buffer = take(ends.last().copied().unwrap_or(0))?;
+            let mut start = 0;
+            for end in ends {
+                phrases.push(buffer.get(start..end).context("詞組索引超出範圍")?);
+                start = end;
+            }
+        }
+        
+        let max_keys = key_width / keybits;
+        let mask = (1u64 << keybits) - 1;
+        let mut code_to_chars: HashMap<String, Vec<String>> = HashMap::new();
+        for item in items.chunks_exact(item_size) {
+            let (key, ch) = item.split_at(key_width as usize / 8);
+            let mut packed = [0u8; 8];
+            packed[..key.len()].copy_from_slice(key);
+            let packed = u64::from_le_bytes(packed);
+            let mut code = String::new();
+            for i in 0..max_keys {
+                let index = (packed >> ((max_keys - 1 - i) * keybits)) & mask;
+                if index == 0 {
+                    break;
+                }
+                let key = keymap.get(index as usize).with_context(|| format!("按鍵索引超出範圍: {}", index))?;
+                code.push((*key as char).to_ascii_lowercase());
+            }
+            let text = if ch[0] >= 0x80 {
+                let len = ch.iter().position(|&b| b == 0).unwrap_or(ch.len());
+                String::from_utf8_lossy(&ch[..len]).into_owned()
+            } else {
+                let index = (ch[0] as usize) << 16 | (ch[1] as usize) << 8 | ch[2] as usize;
+                let phrase = phrases.get(index).with_context(|| format!("詞組索引超出範圍: {}", index))?;
+                String::from_utf8_lossy(phrase).into_owned()
+            };
+            if code.is_empty() || text.is_empty() {
+                continue;
+            }
+            let chars = code_to_chars.entry(code).or_default();
+            if !chars.contains(&text) {
+                chars.push(text);
+            }
+        }
+        
+        Ok(Self { code_to_chars, ..Default::default() })
+    }
+    
+    /// 從 JSON 字串解析字碼表，strict 為 true 時先依內建 schema 嚴格驗證
+    pub fn parse(content: &str, strict: bool) -> Result<Self> {
+        // 提取 chardefs 並將所有鍵轉為小寫（見 CharDefs）
+        let CharDefs { code_to_chars, candidate_outputs } = if strict {
+            let value: Value = serde_json::from_str(content)
+                .with_context(|| "無法解析 JSON 格式")?;
+            validation::into_result(validation::validate_dictionary(&value, true), "字碼表")?;
+            serde_json::from_value::<ChardefsFile>(value)
+                .with_context(|| "無法解析字碼表")?
+                .into()
+        } else {
+            CharDefs::parse(content)
+                .map_err(|e| Self::explain_parse_error(content, e))?
         };
         
         Ok(Self {
-            code_to_chars: code_map,
-            pinyi_data,
+            code_to_chars,
+            pinyi_data: None,
+            candidate_outputs,
+            sqlite: None,
+            user: None,
+            user_path: None,
+            blocklist: Blocklist::new(),
+            layers: Vec::new(),
+            main_priority: 0,
+            merge_policy: MergePolicy::Dedup,
+            emoji: None,
+            restored_from_cache: false,
+            shortest_codes: OnceLock::new(),
         })
     }
     
+    /// 解析失敗時以 schema 驗證找出出錯的 JSON 路徑（例如 chardefs.xxx[2]）
+    /// 語法錯誤或驗證不出問題時回傳原本的錯誤
+    fn explain_parse_error(content: &str, error: serde_json::Error) -> anyhow::Error {
+        let error = anyhow::Error::new(error).context("無法解析 JSON 格式");
+        let Ok(value) = serde_json::from_str::<Value>(content) else {
+            return error;
+        };
+        match validation::into_result(validation::validate_dictionary(&value, false), "字碼表") {
+            Err(e) => e,
+            Ok(()) => error,
+        }
+    }
+    
     /// 根據字根查詢候選字
-    pub fn lookup(&self, code: &str) -> Option<&Vec<String>> {
-        self.code_to_chars.get(code)
+    /// 記憶體後端直接借用字典內容；SQLite 後端回傳查詢結果（接上記憶體中合併進來的候選）
+    /// 有使用者字典時，使用者的候選排在最前面（重複的候選略過）
+    /// 停用字清單中的候選一律濾掉，全部被濾掉時回傳 None
+    /// 字根含 * 或 ? 時為萬用字元查詢，依字根分組串接所有符合字根的候選（見 lookup_wildcard）
+    pub fn lookup(&self, code: &str) -> Option<Cow<'_, [String]>> {
+        if is_wildcard(code) {
+            let mut chars: Vec<String> = Vec::new();
+            for (_, group) in self.lookup_wildcard(code) {
+                for ch in group {
+                    if !chars.contains(&ch) {
+                        chars.push(ch);
+                    }
+                }
+            }
+            return (!chars.is_empty()).then_some(Cow::Owned(chars));
+        }
+        self.lookup_exact(code)
+    }
+    
+    /// 一次查出候選與是否還有以該字根開頭的更長字根（lookup 加上 has_prefix）
+    /// 補碼與自動送出都需要兩者，集中在這裡，日後換成前綴樹時只需一次走訪
+    /// 每個按鍵都會查詢，只做精確查詢、不展開萬用字元（含萬用字元的字根查不到候選，has_longer 為 false）
+    pub fn lookup_ex(&self, code: &str) -> (Option<Cow<'_, [String]>>, bool) {
+        if is_wildcard(code) {
+            return (None, false);
+        }
+        (self.lookup_exact(code), self.has_prefix(code))
+    }
+    
+    /// 萬用字元查詢：字根中的 * 代表任意長度的字根，例如 a*d 符合 ad、abd、acxd；
+    /// ? 代表剛好一個字根，例如 a?c 符合 abc、axc，不符合 ac、abbc
+    /// 依字根分組回傳（短的字根在前，同長度依字母排序），最多 WILDCARD_MAX_CODES 組
+    /// 候選已套用使用者字典與停用字清單，樣式只有萬用字元時不查詢
+    pub fn lookup_wildcard(&self, pattern: &str) -> Vec<(String, Vec<String>)> {
+        if pattern.chars().all(|c| c == '*' || c == '?') {
+            return Vec::new();
+        }
+        let mut codes = self.matching_codes(pattern);
+        codes.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        codes.dedup();
+        codes
+            .into_iter()
+            .filter_map(|code| {
+                let chars = self.lookup_exact(&code)?.into_owned();
+                Some((code, chars))
+            })
+            .take(WILDCARD_MAX_CODES)
+            .collect()
+    }
+    
+    /// 列出符合萬用字元樣式的字根（未排序，可能重複）
+    fn matching_codes(&self, pattern: &str) -> Vec<String> {
+        let mut codes: Vec<String> = self
+            .code_to_chars
+            .keys()
+            .filter(|code| wildcard_match(pattern, code))
+            .cloned()
+            .collect();
+        if let Some(user) = &self.user {
+            codes.extend(user.matching_codes(pattern));
+        }
+        for layer in &self.layers {
+            codes.extend(layer.dictionary.matching_codes(pattern));
+        }
+        if let Some(store) = &self.sqlite {
+            match store.codes_matching(pattern, WILDCARD_MAX_CODES) {
+                Ok(found) => codes.extend(found),
+                Err(e) => warn!("萬用字元查詢字典資料庫失敗 '{}': {}", pattern, e),
+            }
+        }
+        codes
+    }
+    
+    /// 查詢單一字根（不含萬用字元）
+    fn lookup_exact(&self, code: &str) -> Option<Cow<'_, [String]>> {
+        let chars = self.lookup_merged(code)?;
+        if self.blocklist.is_empty() || !chars.iter().any(|ch| self.blocklist.contains(ch)) {
+            return Some(chars);
+        }
+        let kept: Vec<String> = chars.iter().filter(|ch| !self.blocklist.contains(ch)).cloned().collect();
+        (!kept.is_empty()).then_some(Cow::Owned(kept))
+    }
+    
+    /// 查詢使用者字典與主字碼表合併後的候選（未套用停用字清單）
+    fn lookup_merged(&self, code: &str) -> Option<Cow<'_, [String]>> {
+        let base = self.lookup_base(code);
+        let Some(user_chars) = self.user.as_ref().and_then(|user| user.code_to_chars.get(code)) else {
+            return base;
+        };
+        let mut chars = user_chars.clone();
+        for ch in base.iter().flat_map(|base| base.iter()) {
+            if !chars.contains(ch) {
+                chars.push(ch.clone());
+            }
+        }
+        Some(Cow::Owned(chars))
+    }
+    
+    /// 依層的優先順序與 merge_policy 合併主字碼表與各額外層的候選（不含使用者字典）
+    fn lookup_base(&self, code: &str) -> Option<Cow<'_, [String]>> {
+        if self.layers.is_empty() {
+            return self.lookup_main(code);
+        }
+        let main = self.lookup_main(code);
+        let mut sources: Vec<&[String]> = Vec::with_capacity(self.layers.len() + 1);
+        for i in 0..=self.layers.len() {
+            if i == self.main_priority {
+                sources.extend(main.as_deref());
+            }
+            let layer_chars = self.layers.get(i).and_then(|layer| layer.dictionary.code_to_chars.get(code));
+            sources.extend(layer_chars.map(Vec::as_slice));
+        }
+        let chars = self.merge_policy.merge(&sources);
+        (!chars.is_empty()).then_some(Cow::Owned(chars))
+    }
+    
+    /// 候選來自哪一個額外的字碼表層（依優先順序找第一個含有該候選的來源）
+    /// 來自使用者字典或主字碼表、或沒有額外層時回傳 None
+    pub fn layer_of(&self, code: &str, candidate: &str) -> Option<&str> {
+        if self.layers.is_empty() {
+            return None;
+        }
+        let has = |chars: Option<&Vec<String>>| chars.is_some_and(|chars| chars.iter().any(|c| c == candidate));
+        if has(self.user.as_ref().and_then(|user| user.code_to_chars.get(code))) {
+            return None;
+        }
+        for (i, layer) in self.layers.iter().enumerate() {
+            if i == self.main_priority
+                && self.lookup_main(code).is_some_and(|main| main.iter().any(|c| c == candidate))
+            {
+                return None;
+            }
+            if has(layer.dictionary.code_to_chars.get(code)) {
+                return Some(&layer.name);
+            }
+        }
+        None
+    }
+    
+    /// 候選來自哪一個字碼表（使用者字典、額外層或主字碼表），與 lookup 的合併順序一致
+    pub fn source_of(&self, code: &str, candidate: &str) -> CandidateSource {
+        let in_user = self
+            .user
+            .as_ref()
+            .and_then(|user| user.code_to_chars.get(code))
+            .is_some_and(|chars| chars.iter().any(|c| c == candidate));
+        if in_user {
+            return CandidateSource::User;
+        }
+        match self.layer_of(code, candidate) {
+            Some(layer) => CandidateSource::Layer(layer.to_string()),
+            None => CandidateSource::Main,
+        }
+    }
+    
+    /// 查詢字根的候選，附上別名輸出與來源（組字與狀態窗使用；只需要文字時用 lookup）
+    pub fn candidates(&self, code: &str) -> Option<Vec<Candidate>> {
+        let chars = self.lookup(code)?;
+        Some(self.make_candidates(code, chars.iter().cloned()))
+    }
+    
+    /// 把字根 code 下的候選文字包成 Candidate（查出別名輸出與來源）
+    pub fn make_candidates(&self, code: &str, texts: impl IntoIterator<Item = String>) -> Vec<Candidate> {
+        texts
+            .into_iter()
+            .map(|text| Candidate {
+                output: self.output_for(code, &text).map(Cow::into_owned),
+                source: Some(self.source_of(code, &text)),
+                code: code.to_string(),
+                text,
+                weight: 0,
+                note: None,
+            })
+            .collect()
+    }
+    
+    /// 查詢主字碼表（不含使用者字典與額外層）
+    fn lookup_main(&self, code: &str) -> Option<Cow<'_, [String]>> {
+        let extra = self.code_to_chars.get(code);
+        let Some(store) = &self.sqlite else {
+            return extra.map(|chars| Cow::Borrowed(chars.as_slice()));
+        };
+        
+        let mut chars = store.lookup(code).unwrap_or_else(|e| {
+            warn!("查詢字典資料庫失敗 '{}': {}", code, e);
+            Vec::new()
+        });
+        if chars.is_empty() {
+            return extra.map(|chars| Cow::Borrowed(chars.as_slice()));
+        }
+        for ch in extra.into_iter().flatten() {
+            if !chars.contains(ch) {
+                chars.push(ch.clone());
+            }
+        }
+        Some(Cow::Owned(chars))
+    }
+    
+    /// 合併後的完整字碼表：所有來源（主字碼表、額外層、使用者字典）的字根依字母排序，
+    /// 候選與 lookup 相同（已套用使用者字典、層的優先順序與停用字清單）
+    pub fn entries(&self) -> Vec<(String, Vec<String>)> {
+        let mut codes: Vec<String> = self.code_to_chars.keys().cloned().collect();
+        if let Some(user) = &self.user {
+            codes.extend(user.code_to_chars.keys().cloned());
+        }
+        for layer in &self.layers {
+            codes.extend(layer.dictionary.code_to_chars.keys().cloned());
+        }
+        if let Some(store) = &self.sqlite {
+            match store.codes() {
+                Ok(found) => codes.extend(found),
+                Err(e) => warn!("列舉字典資料庫字根失敗: {}", e),
+            }
+        }
+        codes.sort();
+        codes.dedup();
+        codes
+            .into_iter()
+            .filter_map(|code| {
+                let chars = self.lookup_exact(&code)?.into_owned();
+                Some((code, chars))
+            })
+            .collect()
+    }
+    
+    /// 批次查詢多個字根，依輸入順序回傳 (字根, 候選字)
+    /// 記憶體後端的候選字直接借用字典內容，呼叫端只需鎖一次字典、不必複製
+    pub fn lookup_many<'a, 'c>(
+        &'a self,
+        codes: &'c [&'c str],
+    ) -> impl Iterator<Item = (&'c str, Option<Cow<'a, [String]>>)> {
+        codes.iter().map(move |&code| (code, self.lookup(code)))
+    }
+    
+    /// 列舉所有以指定字根開頭的字根（包含字根本身）與其候選字
+    /// 順序不固定，需要排序時由呼叫端處理
+    /// 只列舉記憶體中的字根，SQLite 後端的主字碼表不在其中
+    pub fn iterate_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a [String])> {
+        self.code_to_chars
+            .iter()
+            .filter(move |(code, _)| code.starts_with(prefix))
+            .map(|(code, chars)| (code.as_str(), chars.as_slice()))
+    }
+    
+    /// 反查：列出候選中包含指定文字的所有字根（短的字根在前，同長度依字母排序）
+    pub fn codes_for(&self, text: &str) -> Vec<String> {
+        let mut codes = codes_containing(&self.code_to_chars, text);
+        if let Some(store) = &self.sqlite {
+            match store.codes_for(text) {
+                Ok(found) => codes.extend(found),
+                Err(e) => warn!("反查字典資料庫失敗 '{}': {}", text, e),
+            }
+        }
+        if let Some(user) = &self.user {
+            codes.extend(user.codes_for(text));
+        }
+        for layer in &self.layers {
+            codes.extend(layer.dictionary.codes_for(text));
+        }
+        sort_codes(&mut codes);
+        codes
+    }
+    
+    /// 文字最短的字根（同長度依字母排序），查不到時為 None
+    /// 記憶體字碼表走反查索引，不必每次掃過整份字碼表；SQLite、使用者字典與額外層照 codes_for 查
+    pub fn shortest_code(&self, text: &str) -> Option<String> {
+        let mut codes: Vec<String> = self.reverse_index().get(text).cloned().into_iter().collect();
+        if let Some(store) = &self.sqlite {
+            match store.codes_for(text) {
+                Ok(found) => codes.extend(found),
+                Err(e) => warn!("反查字典資料庫失敗 '{}': {}", text, e),
+            }
+        }
+        if let Some(user) = &self.user {
+            codes.extend(user.shortest_code(text));
+        }
+        for layer in &self.layers {
+            codes.extend(layer.dictionary.shortest_code(text));
+        }
+        sort_codes(&mut codes);
+        codes.into_iter().next()
+    }
+
+    /// 先建好反查索引（開啟簡碼提示時呼叫，避免第一次送字時才花時間建立）
+    pub fn warm_reverse_index(&self) {
+        self.reverse_index();
+        for layer in &self.layers {
+            layer.dictionary.warm_reverse_index();
+        }
+    }
+
+    fn reverse_index(&self) -> &HashMap<String, String> {
+        self.shortest_codes.get_or_init(|| {
+            let mut index: HashMap<String, String> = HashMap::new();
+            for (code, chars) in &self.code_to_chars {
+                for text in chars {
+                    let shorter = |current: &String| (code.len(), code) < (current.len(), current);
+                    if index.get(text).is_none_or(shorter) {
+                        index.insert(text.clone(), code.clone());
+                    }
+                }
+            }
+            debug!("已建立反查索引（{} 個候選）", index.len());
+            Arc::new(index)
+        })
+    }
+
+    /// 合併另一份字典（另一份的候選接在現有候選之後，重複的候選略過）
+    pub fn merge(&mut self, other: Dictionary) {
+        self.shortest_codes = OnceLock::new();
+        for (code, chars) in other.code_to_chars {
+            let entry = self.code_to_chars.entry(code).or_default();
+            for ch in chars {
+                if !entry.contains(&ch) {
+                    entry.push(ch);
+                }
+            }
+        }
+        for (code, outputs) in other.candidate_outputs {
+            let entry = self.candidate_outputs.entry(code).or_default();
+            for (display, output) in outputs {
+                entry.entry(display).or_insert(output);
+            }
+        }
+    }
+    
+    /// 換掉主字碼表（含合併進來的符號表與同音字表），使用者字典、額外層、emoji 表與停用字不變
+    /// 線上更新字碼表後用來熱替換（見 dictionary_update 模組）
+    pub fn replace_main(&mut self, main: Dictionary) {
+        self.code_to_chars = main.code_to_chars;
+        self.shortest_codes = OnceLock::new();
+        self.candidate_outputs = main.candidate_outputs;
+        self.sqlite = main.sqlite;
+        self.pinyi_data = main.pinyi_data;
+    }
+    
+    /// 套用使用者字典，None 表示不使用（例如切換到沒有 user.json 的使用者）
+    pub fn set_user(&mut self, user: Option<Dictionary>) {
+        self.user = user.map(Box::new);
+    }
+    
+    /// 設定使用者字典檔（切換使用者時一併更新），add_entry/remove_entry 寫回這個檔案
+    pub fn set_user_path(&mut self, path: Option<PathBuf>) {
+        self.user_path = path;
+    }
+    
+    /// 新增一筆使用者字典條目：附加到 user.json 並立即生效，不必重新啟動
+    /// 條目已存在時回傳 false；沒有設定使用者字典檔時回傳錯誤
+    pub fn add_entry(&mut self, code: &str, text: &str) -> Result<bool> {
+        let Some(path) = &self.user_path else {
+            bail!("沒有設定使用者字典檔，無法新增 {} → {}", code, text);
+        };
+        if !user_dictionary::add_entry(path, code, text)? {
+            return Ok(false);
+        }
+        self.add_user_entry(code, text);
+        Ok(true)
+    }
+    
+    /// 刪除一筆使用者字典條目：從 user.json 移除並立即生效
+    /// 只能刪除使用者字典的條目（主字碼表的字請用停用字清單），條目不存在時回傳 false
+    pub fn remove_entry(&mut self, code: &str, text: &str) -> Result<bool> {
+        let Some(path) = &self.user_path else {
+            bail!("沒有設定使用者字典檔，無法刪除 {} → {}", code, text);
+        };
+        let removed = user_dictionary::remove_entry(path, code, text)?;
+        Ok(self.remove_user_entry(code, text) || removed)
+    }
+    
+    /// 套用停用字清單（啟動或切換使用者時從檔案載入）
+    pub fn set_blocklist(&mut self, blocklist: Blocklist) {
+        self.blocklist = blocklist;
+    }
+    
+    /// 在記憶體中新增一筆使用者字典條目，立即生效（寫回檔案見 user_dictionary::add_entry）
+    pub fn add_user_entry(&mut self, code: &str, text: &str) {
+        let user = self.user.get_or_insert_with(Default::default);
+        user.shortest_codes = OnceLock::new();
+        let chars = user.code_to_chars.entry(code.trim().to_lowercase()).or_default();
+        if !chars.iter().any(|c| c == text) {
+            chars.push(text.to_string());
+        }
+    }
+    
+    /// 在記憶體中刪除一筆使用者字典條目（連同別名輸出），字根沒有候選時整個移除
+    pub fn remove_user_entry(&mut self, code: &str, text: &str) -> bool {
+        let Some(user) = &mut self.user else {
+            return false;
+        };
+        let code = code.trim().to_lowercase();
+        user.shortest_codes = OnceLock::new();
+        let Some(chars) = user.code_to_chars.get_mut(&code) else {
+            return false;
+        };
+        let before = chars.len();
+        chars.retain(|c| c != text);
+        let removed = chars.len() != before;
+        if chars.is_empty() {
+            user.code_to_chars.remove(&code);
+        }
+        if let Some(outputs) = user.candidate_outputs.get_mut(&code) {
+            outputs.remove(text);
+            if outputs.is_empty() {
+                user.candidate_outputs.remove(&code);
+            }
+        }
+        removed
+    }
+    
+    /// 取得候選字實際要送出的文字
+    /// 有設定別名輸出時回傳 output，否則回傳 None（送出顯示文字本身）
+    /// 使用者字典的別名優先
+    pub fn output_for(&self, code: &str, display: &str) -> Option<Cow<'_, str>> {
+        if let Some(output) = self
+            .user
+            .as_ref()
+            .and_then(|user| user.candidate_outputs.get(code))
+            .and_then(|outputs| outputs.get(display))
+        {
+            return Some(Cow::Borrowed(output.as_str()));
+        }
+        if let Some(output) = self.candidate_outputs.get(code).and_then(|outputs| outputs.get(display)) {
+            return Some(Cow::Borrowed(output.as_str()));
+        }
+        if let Some(store) = &self.sqlite {
+            match store.output_for(code, display) {
+                Ok(Some(output)) => return Some(Cow::Owned(output)),
+                Ok(None) => {}
+                Err(e) => warn!("查詢字典資料庫失敗 '{}': {}", code, e),
+            }
+        }
+        self.layers.iter().find_map(|layer| layer.dictionary.output_for(code, display))
+    }
+    
+    /// 統計字典內容
+    /// SQLite 後端的數字為資料庫與記憶體中字根的合計
+    pub fn stats(&self) -> DictionaryStats {
+        let (db_codes, db_candidates, db_aliases) = match &self.sqlite {
+            Some(store) => store.counts().unwrap_or_else(|e| {
+                warn!("統計字典資料庫失敗: {}", e);
+                (0, 0, 0)
+            }),
+            None => (0, 0, 0),
+        };
+        let layers = self.layers.iter().map(|layer| layer.dictionary.stats());
+        let (layer_codes, layer_candidates, layer_aliases) = layers.fold((0, 0, 0), |(c, n, a), stats| {
+            (c + stats.codes, n + stats.candidates, a + stats.aliases)
+        });
+        DictionaryStats {
+            codes: db_codes + layer_codes + self.code_to_chars.len(),
+            candidates: db_candidates + layer_candidates + self.code_to_chars.values().map(|v| v.len()).sum::<usize>(),
+            aliases: db_aliases + layer_aliases + self.candidate_outputs.values().map(|m| m.len()).sum::<usize>(),
+            pinyi_chars: self.pinyi_data.as_ref().map(HomophoneTable::len),
+            user_entries: self
+                .user
+                .as_ref()
+                .map(|user| user.code_to_chars.values().map(|v| v.len()).sum())
+                .unwrap_or(0),
+        }
     }
     
     /// 取得候選字數量
@@ -105,7 +1061,383 @@ impl Dictionary {
     /// 檢查是否存在以指定字根開頭的字根（用於補碼機制判斷）
     /// 例如：檢查是否存在以 "sis" 開頭的字根（如 "sisp"）
     pub fn has_prefix(&self, prefix: &str) -> bool {
-        self.code_to_chars.keys().any(|key| key.starts_with(prefix) && key != prefix)
+        if self.iterate_prefix(prefix).any(|(code, _)| code != prefix) {
+            return true;
+        }
+        if self.user.as_ref().is_some_and(|user| user.has_prefix(prefix)) {
+            return true;
+        }
+        if self.layers.iter().any(|layer| layer.dictionary.has_prefix(prefix)) {
+            return true;
+        }
+        match &self.sqlite {
+            Some(store) => store.has_longer_code(prefix).unwrap_or_else(|e| {
+                warn!("查詢字典資料庫失敗 '{}': {}", prefix, e);
+                false
+            }),
+            None => false,
+        }
+    }
+    
+    /// 補全提示：以 prefix 開頭的更長字根中，緊接在 prefix 後面的字根（依字母排序、不重複）
+    /// 例如有 ab、ad、adx 時，a 的結果為 b、d
+    pub fn next_letters(&self, prefix: &str) -> Vec<char> {
+        let mut letters: Vec<char> = self
+            .iterate_prefix(prefix)
+            .filter_map(|(code, _)| code[prefix.len()..].chars().next())
+            .collect();
+        if let Some(user) = &self.user {
+            letters.extend(user.next_letters(prefix));
+        }
+        for layer in &self.layers {
+            letters.extend(layer.dictionary.next_letters(prefix));
+        }
+        if let Some(store) = &self.sqlite {
+            match store.next_letters(prefix) {
+                Ok(found) => letters.extend(found),
+                Err(e) => warn!("查詢字典資料庫失敗 '{}': {}", prefix, e),
+            }
+        }
+        letters.sort_unstable();
+        letters.dedup();
+        letters
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json_plain_entries() {
+        let dictionary = Dictionary::from_json(r#"{ "chardefs": { "A": ["一", "乙"], "ab": ["二"] } }"#).unwrap();
+        assert_eq!(dictionary.lookup("a").as_deref(), Some(&["一".to_string(), "乙".to_string()][..]));
+        assert_eq!(dictionary.lookup("ab").as_deref(), Some(&["二".to_string()][..]));
+        assert!(dictionary.candidate_outputs.is_empty());
+    }
+
+    #[test]
+    fn test_from_json_alias_entries() {
+        let dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "xo": ["哈", { "display": "笑", "output": "😂" }] } }"#,
+        ).unwrap();
+        // GUI 顯示 display
+        assert_eq!(dictionary.lookup("xo").as_deref(), Some(&["哈".to_string(), "笑".to_string()][..]));
+        // 送出時改送 output
+        assert_eq!(dictionary.output_for("xo", "笑").as_deref(), Some("😂"));
+        assert_eq!(dictionary.output_for("xo", "哈").as_deref(), None);
+        assert_eq!(dictionary.output_for("a", "笑").as_deref(), None);
+        let stats = dictionary.stats();
+        assert_eq!((stats.codes, stats.candidates, stats.aliases, stats.pinyi_chars), (1, 2, 1, None));
+    }
+
+    #[test]
+    fn test_merge_appends_without_duplicates() {
+        let mut dictionary = Dictionary::from_json(r#"{ "chardefs": { ".": ["。"], "a": ["一"] } }"#).unwrap();
+        let symbols = Dictionary::from_json(r#"{ "chardefs": { ".": ["。", "．"], ",": ["，"] } }"#).unwrap();
+        dictionary.merge(symbols);
+        assert_eq!(dictionary.lookup(".").as_deref(), Some(&["。".to_string(), "．".to_string()][..]));
+        assert_eq!(dictionary.lookup(",").as_deref(), Some(&["，".to_string()][..]));
+        assert_eq!(dictionary.lookup("a").as_deref(), Some(&["一".to_string()][..]));
+    }
+
+    #[test]
+    fn test_lookup_many_keeps_order() {
+        let dictionary = Dictionary::from_json(r#"{ "chardefs": { "a": ["一"], "b": ["二"] } }"#).unwrap();
+        let results: Vec<_> = dictionary.lookup_many(&["b", "x", "a"]).collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!((results[0].0, results[0].1.as_deref()), ("b", Some(&["二".to_string()][..])));
+        assert_eq!((results[1].0, results[1].1.as_deref()), ("x", None));
+        assert_eq!((results[2].0, results[2].1.as_deref()), ("a", Some(&["一".to_string()][..])));
+    }
+
+    #[test]
+    fn test_iterate_prefix() {
+        let dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "si": ["四"], "sis": ["想"], "sisp": ["塞"], "a": ["一"] } }"#,
+        ).unwrap();
+        let mut codes: Vec<&str> = dictionary.iterate_prefix("sis").map(|(code, _)| code).collect();
+        codes.sort();
+        assert_eq!(codes, vec!["sis", "sisp"]);
+        assert_eq!(dictionary.iterate_prefix("z").count(), 0);
+        assert!(dictionary.has_prefix("sis"));
+        assert!(!dictionary.has_prefix("sisp"));
+        assert_eq!(dictionary.next_letters("si"), ['s']);
+        assert_eq!(dictionary.shortest_code("一").as_deref(), Some("a"));
+        assert_eq!(dictionary.shortest_code("二"), None);
+        assert!(dictionary.next_letters("sisp").is_empty());
+
+        let (candidates, has_longer) = dictionary.lookup_ex("sis");
+        assert_eq!(candidates.as_deref(), Some(&["想".to_string()][..]));
+        assert!(has_longer);
+        assert!(!dictionary.lookup_ex("sisp").1);
+        // 沒有這個字根但有更長的
+        assert_eq!(dictionary.lookup_ex("s"), (None, true));
+        assert!(!dictionary.lookup_ex("s*").1);
+    }
+
+    #[test]
+    fn test_parse_error_reports_path() {
+        let error = Dictionary::from_json(r#"{ "chardefs": { "xxx": ["一", "二", 3] } }"#).err().unwrap();
+        assert!(error.to_string().contains("chardefs.xxx[2]"));
+
+        // 嚴格模式拒絕空字串候選
+        let content = r#"{ "chardefs": { "a": ["一", ""] } }"#;
+        assert!(Dictionary::parse(content, false).is_ok());
+        let error = Dictionary::parse(content, true).err().unwrap();
+        assert!(error.to_string().contains("chardefs.a[1]"));
+    }
+
+    #[test]
+    #[cfg(feature = "app")]
+    fn test_sqlite_backend_with_merged_symbols() {
+        let main = Dictionary::from_json(
+            r#"{ "chardefs": { "a": ["一", "乙"], "sisp": ["塞"], "xo": [{ "display": "笑", "output": "😂" }] } }"#,
+        ).unwrap();
+        let mut dictionary = Dictionary {
+            sqlite: Some(Arc::new(SqliteStore::in_memory(&main).unwrap())),
+            ..Default::default()
+        };
+        dictionary.merge(Dictionary::from_json(r#"{ "chardefs": { "a": ["乙", "ㄧ"], ".": ["。"] } }"#).unwrap());
+
+        // 資料庫的候選在前，記憶體合併進來的候選接在後面
+        assert_eq!(dictionary.lookup("a").as_deref(), Some(&["一".to_string(), "乙".to_string(), "ㄧ".to_string()][..]));
+        assert_eq!(dictionary.lookup(".").as_deref(), Some(&["。".to_string()][..]));
+        assert!(dictionary.lookup("zz").is_none());
+        assert!(dictionary.has_prefix("sis"));
+        assert!(!dictionary.has_prefix("sisp"));
+        assert_eq!(dictionary.output_for("xo", "笑").as_deref(), Some("😂"));
+        assert_eq!(dictionary.codes_for("乙"), vec!["a".to_string()]);
+        assert_eq!(dictionary.stats().codes, 5);
+    }
+
+    #[test]
+    fn test_user_dictionary_takes_precedence() {
+        let mut dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "a": ["一", "乙"], "xo": [{ "display": "笑", "output": "😂" }] } }"#,
+        ).unwrap();
+        let user = Dictionary::from_json(
+            r#"{ "chardefs": { "a": ["乙", "丫"], "xo": [{ "display": "笑", "output": "🤣" }], "ucl": ["肥米"] } }"#,
+        ).unwrap();
+        dictionary.set_user(Some(user));
+        
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), ["乙", "丫", "一"]);
+        assert_eq!(dictionary.output_for("xo", "笑").as_deref(), Some("🤣"));
+        assert_eq!(dictionary.lookup("ucl").unwrap().as_ref(), ["肥米"]);
+        assert_eq!(dictionary.source_of("a", "丫"), CandidateSource::User);
+        assert_eq!(dictionary.source_of("a", "一"), CandidateSource::Main);
+        // 候選帶著別名輸出與來源
+        let xo = dictionary.candidates("xo").unwrap();
+        assert_eq!((xo[0].text.as_str(), xo[0].output.as_deref().unwrap(), xo[0].code.as_str()), ("笑", "🤣", "xo"));
+        assert_eq!(xo[0].source, Some(CandidateSource::User));
+        assert!(dictionary.has_prefix("uc"));
+        assert_eq!(dictionary.codes_for("肥米"), vec!["ucl".to_string()]);
+        
+        dictionary.add_user_entry("UCL", "肥米輸入法");
+        assert_eq!(dictionary.lookup("ucl").unwrap().as_ref(), ["肥米", "肥米輸入法"]);
+        assert_eq!(dictionary.stats().user_entries, 5);
+        
+        // 使用者字典改變後反查索引跟著更新
+        assert_eq!(dictionary.shortest_code("肥米").as_deref(), Some("ucl"));
+        dictionary.add_user_entry("um", "肥米");
+        assert_eq!(dictionary.shortest_code("肥米").as_deref(), Some("um"));
+        
+        dictionary.set_user(None);
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), ["一", "乙"]);
+        assert!(dictionary.lookup("ucl").is_none());
+    }
+    
+    #[test]
+    fn test_add_and_remove_entry_persist() {
+        let dir = std::env::temp_dir().join(format!("uclliu-dict-entry-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(user_dictionary::USER_DICTIONARY_FILE);
+        let _ = fs::remove_file(&path);
+
+        let mut dictionary = Dictionary::from_json(r#"{ "chardefs": { "a": ["一"] } }"#).unwrap();
+        assert!(dictionary.add_entry("ucl", "肥米").is_err());
+        dictionary.set_user_path(Some(path.clone()));
+
+        assert!(dictionary.add_entry("UCL", "肥米").unwrap());
+        assert!(!dictionary.add_entry("ucl", "肥米").unwrap());
+        assert!(dictionary.add_entry("a", "丫").unwrap());
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), ["丫", "一"]);
+        assert!(dictionary.has_prefix("uc"));
+        let reloaded = user_dictionary::load(&path).unwrap().unwrap();
+        assert_eq!(reloaded.lookup("ucl").unwrap().as_ref(), ["肥米"]);
+
+        // 主字碼表的字不會被刪除
+        assert!(!dictionary.remove_entry("a", "一").unwrap());
+        assert!(dictionary.remove_entry("ucl", "肥米").unwrap());
+        assert!(dictionary.lookup("ucl").is_none());
+        assert!(!dictionary.has_prefix("uc"));
+        assert!(user_dictionary::load(&path).unwrap().unwrap().lookup("ucl").is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn test_layers_merge_in_priority_order() {
+        let mut dictionary = Dictionary::from_json(r#"{ "chardefs": { "a": ["一", "乙"] } }"#).unwrap();
+        dictionary.add_layer("jargon", Dictionary::from_json(r#"{ "chardefs": { "a": ["甲", "一"], "ax": ["案"] } }"#).unwrap());
+        dictionary.add_layer("symbols", Dictionary::from_json(r#"{ "chardefs": { "a": ["＠"] } }"#).unwrap());
+
+        // 預設主字碼表優先
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), &["一", "乙", "甲", "＠"]);
+        assert_eq!(dictionary.layer_of("a", "一"), None);
+        assert_eq!(dictionary.layer_of("a", "甲"), Some("jargon"));
+        assert_eq!(dictionary.source_of("a", "甲"), CandidateSource::Layer("jargon".to_string()));
+        assert!(dictionary.has_prefix("a"));
+
+        // 術語表排在主字碼表之前
+        dictionary.main_priority = 1;
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), &["甲", "一", "乙", "＠"]);
+        assert_eq!(dictionary.layer_of("a", "一"), Some("jargon"));
+        assert_eq!(dictionary.layer_of("a", "＠"), Some("symbols"));
+        assert_eq!(dictionary.lookup("ax").unwrap().as_ref(), &["案"]);
+    }
+
+    #[test]
+    fn test_merge_policies() {
+        let mut dictionary = Dictionary::from_json(r#"{ "chardefs": { "a": ["一", "乙"] } }"#).unwrap();
+        dictionary.add_layer("jargon", Dictionary::from_json(r#"{ "chardefs": { "a": ["甲", "一", "丙"], "ax": ["案"] } }"#).unwrap());
+        dictionary.add_layer("symbols", Dictionary::from_json(r#"{ "chardefs": { "a": ["＠"] } }"#).unwrap());
+
+        assert_eq!(MergePolicy::parse("").unwrap(), MergePolicy::Dedup);
+        assert_eq!(MergePolicy::parse(" Keep_First_Layer ").unwrap(), MergePolicy::KeepFirstLayer);
+        assert!(MergePolicy::parse("first").is_err());
+
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), &["一", "乙", "甲", "丙", "＠"]);
+
+        dictionary.merge_policy = MergePolicy::KeepFirstLayer;
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), &["一", "乙"]);
+        // 優先的字碼表沒有這個字根時用下一個
+        assert_eq!(dictionary.lookup("ax").unwrap().as_ref(), &["案"]);
+        dictionary.main_priority = 1;
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), &["甲", "一", "丙"]);
+        assert_eq!(dictionary.source_of("a", "一"), CandidateSource::Layer("jargon".to_string()));
+
+        dictionary.merge_policy = MergePolicy::Interleave;
+        dictionary.main_priority = 0;
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), &["一", "甲", "＠", "乙", "丙"]);
+    }
+
+    #[test]
+    fn test_builtin_kaomoji_layer() {
+        let mut dictionary = Dictionary::from_json(r#"{ "chardefs": { "a": ["一"] } }"#).unwrap();
+        dictionary.load_layers(Path::new("."), &["kaomoji".to_string()], true);
+        let flips = dictionary.lookup("flip").unwrap();
+        assert_eq!(flips[0], "(╯°□°）╯︵ ┻━┻");
+        // 組合字元與 ZWJ 序列原樣保留，不會被拆開或正規化
+        assert_eq!(dictionary.lookup("lenny").unwrap()[0], "( \u{361}° \u{35C}ʖ \u{361}°)");
+        assert_eq!(dictionary.lookup("fam").unwrap()[0], "👨\u{200D}👩\u{200D}👧");
+        assert_eq!(dictionary.layer_of("fam", "👨\u{200D}👩\u{200D}👧"), Some("kaomoji"));
+    }
+
+    #[test]
+    fn test_emoji_layer_prefix_lookup() {
+        let mut dictionary = Dictionary::from_json(r#"{ "chardefs": { "a": ["一"] } }"#).unwrap();
+        assert!(dictionary.lookup_emoji("smile").is_empty());
+        dictionary.load_emoji_layer(Path::new("."), "emoji", true);
+        let groups = dictionary.lookup_emoji("s");
+        assert!(groups.iter().all(|(code, _)| code.starts_with('s')));
+        assert_eq!(dictionary.lookup_emoji("smile")[0], ("smile".to_string(), vec!["😄".to_string(), "😊".to_string(), "🙂".to_string()]));
+        let codes: Vec<String> = dictionary.lookup_emoji("thumbs").into_iter().map(|(code, _)| code).collect();
+        assert_eq!(codes, ["thumbsup", "thumbsdown"]);
+        // emoji 表不混入一般查詢
+        assert!(dictionary.lookup("smile").is_none());
+        assert!(dictionary.lookup_emoji("").is_empty());
+    }
+
+    #[test]
+    fn test_blocklist_filters_merged_candidates() {
+        let mut dictionary = Dictionary::from_json(r#"{ "chardefs": { "a": ["一", "乙", "乂"], "b": ["丌"] } }"#).unwrap();
+        dictionary.set_user(Some(Dictionary::from_json(r#"{ "chardefs": { "a": ["丫"] } }"#).unwrap()));
+        let mut blocklist = Blocklist::new();
+        blocklist.insert("乂");
+        blocklist.insert("丫");
+        blocklist.insert("丌");
+        dictionary.set_blocklist(blocklist);
+        
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), ["一", "乙"]);
+        assert!(dictionary.lookup("b").is_none());
+        dictionary.set_blocklist(Blocklist::new());
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), ["丫", "一", "乙", "乂"]);
+    }
+    
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("a*d", "ad"));
+        assert!(wildcard_match("a*d", "abcd"));
+        assert!(!wildcard_match("a*d", "adx"));
+        assert!(wildcard_match("*o", "xo"));
+        assert!(wildcard_match("a*b*c", "axbyc"));
+        assert!(!wildcard_match("ab*b", "ab"));
+        assert!(wildcard_match("ab", "ab"));
+        assert!(!wildcard_match("ab", "abc"));
+        assert!(wildcard_match("a?c", "abc"));
+        assert!(!wildcard_match("a?c", "ac"));
+        assert!(!wildcard_match("a?c", "abbc"));
+        assert!(wildcard_match("?*c", "xyc"));
+        assert!(!wildcard_match("?*c", "c"));
+        assert!(wildcard_match("a*?", "ab"));
+    }
+    
+    #[test]
+    fn test_lookup_wildcard_groups_by_code() {
+        let mut dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "ad": ["二"], "abd": ["三", "二"], "acxd": ["四"], "ae": ["五"] } }"#,
+        ).unwrap();
+        dictionary.set_user(Some(Dictionary::from_json(r#"{ "chardefs": { "azd": ["六"] } }"#).unwrap()));
+        
+        let groups = dictionary.lookup_wildcard("a*d");
+        let codes: Vec<&str> = groups.iter().map(|(code, _)| code.as_str()).collect();
+        assert_eq!(codes, vec!["ad", "abd", "azd", "acxd"]);
+        assert_eq!(groups[1].1, vec!["三", "二"]);
+        
+        // lookup 依字根分組串接候選，重複的候選只留第一次出現
+        assert_eq!(dictionary.lookup("a*d").unwrap().as_ref(), ["二", "三", "六", "四"]);
+        assert!(dictionary.lookup("z*").is_none());
+        assert!(dictionary.lookup_wildcard("**").is_empty());
+
+        // ? 代表剛好一個字根
+        assert_eq!(dictionary.lookup("a?d").unwrap().as_ref(), ["三", "二", "六"]);
+        assert!(dictionary.lookup_wildcard("?*").is_empty());
+        assert!(!dictionary.lookup_ex("a?").1);
+        // 補碼判斷用的 lookup_ex 不展開萬用字元
+        assert_eq!(dictionary.lookup_ex("a*d"), (None, false));
+    }
+    
+    #[test]
+    fn test_from_gtab() {
+        // 兩個按鍵（a、b），keybits 6：字根 "a" 為一、"ab" 為詞組「一二」
+        let mut bytes = vec![0u8; GTAB_HEADER_SIZE];
+        bytes[52..56].copy_from_slice(&3i32.to_le_bytes()); // KeyS（含索引 0）
+        bytes[56..60].copy_from_slice(&5i32.to_le_bytes()); // MaxPress
+        bytes[64..68].copy_from_slice(&2i32.to_le_bytes()); // DefC
+        bytes[GTAB_RESERVED_OFFSET + 99] = 6;
+        bytes.extend_from_slice(&[0, b'A', b'b']);
+        bytes.extend_from_slice(&[0; 3 * 4 + 4 * 4]);
+        bytes.extend_from_slice(&(1u32 << 24).to_le_bytes());
+        bytes.extend_from_slice(&[0xE4, 0xB8, 0x80, 0]);
+        bytes.extend_from_slice(&(1u32 << 24 | 2 << 18).to_le_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&6u32.to_le_bytes());
+        bytes.extend_from_slice("一二".as_bytes());
+        
+        let dictionary = Dictionary::from_gtab(&bytes).unwrap();
+        assert_eq!(dictionary.lookup("a").unwrap().as_ref(), ["一"]);
+        assert_eq!(dictionary.lookup("ab").unwrap().as_ref(), ["一二"]);
+        assert!(Dictionary::from_gtab(&bytes[..bytes.len() - 2]).is_err());
+        assert!(Dictionary::from_gtab(b"{}").is_err());
+    }
+    
+    #[test]
+    fn test_backend_parse() {
+        assert_eq!(DictionaryBackend::parse("").unwrap(), DictionaryBackend::Memory);
+        assert_eq!(DictionaryBackend::parse("SQLite").unwrap(), DictionaryBackend::Sqlite);
+        assert!(DictionaryBackend::parse("redis").is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! 字碼表二進位快取
+//!
+//! 每次啟動都解析整份 liu.json 要花上明顯的時間。第一次解析後把結果以 bincode
+//! 寫成同目錄下的 .cache 檔（例如 liu.json → liu.cache），之後啟動直接反序列化。
+//! 快取裡記錄字碼表的大小、修改時間與內容的 SHA-256，JSON 變了就視為過期並重建
+//! （只有修改時間變了、內容相同時沿用快取）。
+//!
+//! 快取同時是「上次載入成功的版本」：字碼表被其他程式或中斷的下載改壞、無法解析時，
+//! 改用快取裡的版本啟動並提示使用者（見 load_last_good）。
+
+use crate::dictionary::Dictionary;
+use anyhow::{Context, Result};
+use tracing::{debug, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// 快取格式版本，格式改變時遞增以觸發重建
+const FORMAT_VERSION: u32 = 2;
+
+/// 寫入快取時借用字典內容，避免整份複製
+#[derive(Serialize)]
+struct CacheRef<'a> {
+    format: u32,
+    source: &'a str,
+    sha256: &'a str,
+    code_to_chars: &'a HashMap<String, Vec<String>>,
+    candidate_outputs: &'a HashMap<String, HashMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+struct CacheFile {
+    format: u32,
+    source: String,
+    sha256: String,
+    code_to_chars: HashMap<String, Vec<String>>,
+    candidate_outputs: HashMap<String, HashMap<String, String>>,
+}
+
+/// 字碼表對應的快取路徑（同目錄、副檔名改為 .cache）
+pub fn cache_path(json_path: &Path) -> PathBuf {
+    json_path.with_extension("cache")
+}
+
+/// 字碼表檔案的大小與修改時間，用來判斷快取是否過期
+pub fn source_stamp(json_path: &Path) -> Result<String> {
+    let metadata = fs::metadata(json_path)
+        .with_context(|| format!("找不到字碼表: {:?}", json_path))?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok(format!("{}:{}", metadata.len(), modified))
+}
+
+/// 字碼表檔案內容的 SHA-256（十六進位小寫）
+pub fn file_sha256(json_path: &Path) -> Result<String> {
+    let content = fs::read(json_path).with_context(|| format!("無法讀取字碼表: {:?}", json_path))?;
+    Ok(format!("{:x}", Sha256::digest(&content)))
+}
+
+/// 讀取快取檔，不存在或格式不符時回傳 None
+fn read_cache(json_path: &Path) -> Option<CacheFile> {
+    let path = cache_path(json_path);
+    let bytes = fs::read(&path).ok()?;
+    match bincode::deserialize::<CacheFile>(&bytes) {
+        Ok(cache) if cache.format == FORMAT_VERSION => Some(cache),
+        Ok(_) => {
+            debug!("字碼表快取 {:?} 是舊格式，將重建", path);
+            None
+        }
+        Err(e) => {
+            debug!("字碼表快取 {:?} 無法讀取，將重建: {}", path, e);
+            None
+        }
+    }
+}
+
+impl From<CacheFile> for Dictionary {
+    fn from(cache: CacheFile) -> Self {
+        Dictionary {
+            code_to_chars: cache.code_to_chars,
+            candidate_outputs: cache.candidate_outputs,
+            ..Default::default()
+        }
+    }
+}
+
+/// 讀取快取，不存在、過期或格式不符時回傳 None（呼叫端改為解析 JSON）
+pub fn load(json_path: &Path) -> Option<Dictionary> {
+    let path = cache_path(json_path);
+    let source = source_stamp(json_path).ok()?;
+    let cache = read_cache(json_path)?;
+    if cache.source != source {
+        // 大小或修改時間變了才比對內容，只有修改時間變了（例如複製、還原備份）時沿用快取
+        if file_sha256(json_path).ok()? != cache.sha256 {
+            info!("字碼表已變更，重建快取: {:?}", path);
+            return None;
+        }
+        debug!("字碼表內容與上次載入時相同，沿用快取: {:?}", path);
+    }
+
+    info!("從快取載入字碼表: {:?}", path);
+    Some(cache.into())
+}
+
+/// 讀取上次載入成功的字碼表（不管字碼表檔案是否已變更），字碼表無法解析時退回這個版本
+pub fn load_last_good(json_path: &Path) -> Option<Dictionary> {
+    read_cache(json_path).map(Dictionary::from)
+}
+
+/// 把解析好的字碼表寫入快取（先寫暫存檔再改名，中斷時不會留下不完整的快取）
+pub fn save(json_path: &Path, dictionary: &Dictionary) -> Result<()> {
+    let path = cache_path(json_path);
+    let source = source_stamp(json_path)?;
+    let sha256 = file_sha256(json_path)?;
+    let bytes = bincode::serialize(&CacheRef {
+        format: FORMAT_VERSION,
+        source: &source,
+        sha256: &sha256,
+        code_to_chars: &dictionary.code_to_chars,
+        candidate_outputs: &dictionary.candidate_outputs,
+    })
+    .context("無法序列化字碼表快取")?;
+
+    let tmp_path = path.with_extension("cache.tmp");
+    fs::write(&tmp_path, bytes).with_context(|| format!("無法寫入字碼表快取: {:?}", tmp_path))?;
+    fs::rename(&tmp_path, &path).with_context(|| format!("無法寫入字碼表快取: {:?}", path))?;
+    info!("已建立字碼表快取: {:?}", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_roundtrip_and_invalidation() {
+        let dir = std::env::temp_dir().join(format!("uclliu-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let json_path = dir.join("liu.json");
+
+        fs::write(&json_path, r#"{ "chardefs": { "a": ["一", "乙"], "xo": [{ "display": "笑", "output": "😂" }] } }"#).unwrap();
+        assert!(load(&json_path).is_none());
+
+        let dictionary = Dictionary::load_from(&json_path, false).unwrap();
+        assert!(cache_path(&json_path).exists());
+        let cached = load(&json_path).unwrap();
+        assert_eq!(cached.code_to_chars, dictionary.code_to_chars);
+        assert_eq!(cached.output_for("xo", "笑").as_deref(), Some("😂"));
+
+        // 字碼表內容（大小）改變後快取失效
+        fs::write(&json_path, r#"{ "chardefs": { "a": ["一"] } }"#).unwrap();
+        assert!(load(&json_path).is_none());
+        assert_eq!(Dictionary::load_from(&json_path, false).unwrap().lookup("a").unwrap().len(), 1);
+
+        // 字碼表被改壞時退回上次載入成功的版本
+        fs::write(&json_path, r#"{ "chardefs": { "a": ["#).unwrap();
+        assert!(load(&json_path).is_none());
+        let restored = Dictionary::load_from(&json_path, false).unwrap();
+        assert!(restored.restored_from_cache);
+        assert_eq!(restored.lookup("a").unwrap().len(), 1);
+        // 嚴格模式不退回
+        assert!(Dictionary::load_from(&json_path, true).is_err());
+
+        // 損壞的快取視為不存在
+        fs::write(cache_path(&json_path), b"broken").unwrap();
+        assert!(load(&json_path).is_none());
+        assert!(load_last_good(&json_path).is_none());
+        assert!(Dictionary::load_from(&json_path, false).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
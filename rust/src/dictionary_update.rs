@@ -0,0 +1,224 @@
+//! 線上更新字碼表
+//!
+//! 設定 dictionary_update_url 後，托盤選單「更新字碼表」從網址下載新版的 liu.json 或 .cin，
+//! 核對 SHA-256 後取代目前的主字碼表（舊檔留一份 .bak），再直接換進執行中的輸入處理器，
+//! 不必重新啟動（見 AppState::update_dictionary）。
+//!
+//! 校驗碼取自設定 dictionary_update_sha256；沒有填時下載網址加上 .sha256 的檔案
+//! （sha256sum 的輸出格式，取第一個欄位）。.cin 轉成 chardefs 格式的 JSON 後再存檔，
+//! 字碼表快取與 SQLite 資料庫依檔案的修改時間在下次啟動時自動重建。
+
+use crate::chardefs::CharDefs;
+use crate::dictionary::{Dictionary, DictionaryBackend};
+use crate::scheme::InputScheme;
+use anyhow::{bail, Context, Result};
+use tracing::{info, warn};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 下載逾時
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+/// 下載大小上限（liu.json 約 10MB，預留空間給較大的表格）
+const MAX_DOWNLOAD_BYTES: u64 = 64 * 1024 * 1024;
+
+/// 更新來源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateSource {
+    /// 字碼表網址（.json 或 .cin）
+    pub url: String,
+    /// 預期的 SHA-256（十六進位），None 表示從 url + ".sha256" 下載
+    pub sha256: Option<String>,
+}
+
+impl UpdateSource {
+    /// 從設定建立，沒有設定網址時回傳 None
+    pub fn from_config(url: &str, sha256: &str) -> Option<Self> {
+        let url = url.trim();
+        if url.is_empty() {
+            return None;
+        }
+        let sha256 = sha256.trim();
+        Some(Self { url: url.to_string(), sha256: (!sha256.is_empty()).then(|| sha256.to_string()) })
+    }
+
+    /// 網址指向 .cin 檔（忽略查詢字串與大小寫）
+    fn is_cin(&self) -> bool {
+        let path = self.url.split(['?', '#']).next().unwrap_or_default();
+        path.to_ascii_lowercase().ends_with(".cin")
+    }
+}
+
+/// 主字碼表的位置與載入方式（更新後依此重新載入）
+#[derive(Debug, Clone)]
+pub struct MainDictionary {
+    pub scheme_dir: PathBuf,
+    /// 取代方案字碼表的檔案（設定 dictionary_path 或 --dict）
+    pub dictionary_path: Option<PathBuf>,
+    pub strict: bool,
+    pub backend: DictionaryBackend,
+}
+
+impl MainDictionary {
+    /// 主字碼表檔案
+    pub fn path(&self, scheme: &InputScheme) -> PathBuf {
+        self.dictionary_path.clone().unwrap_or_else(|| self.scheme_dir.join(&scheme.dictionary))
+    }
+
+    /// 重新載入主字碼表（含符號表與同音字表）
+    pub fn load(&self, scheme: &InputScheme) -> Result<Dictionary> {
+        Dictionary::load_scheme(scheme, &self.scheme_dir, self.dictionary_path.as_deref(), self.strict, self.backend)
+    }
+}
+
+/// 下載字碼表並核對校驗碼，回傳 chardefs 格式的 JSON 與字根數
+pub fn fetch(source: &UpdateSource) -> Result<(String, usize)> {
+    info!("下載字碼表: {}", source.url);
+    let content = download(&source.url)?;
+    let expected = match &source.sha256 {
+        Some(sha256) => sha256.clone(),
+        None => {
+            let checksum_url = format!("{}.sha256", source.url);
+            let text = String::from_utf8(download(&checksum_url)?)
+                .with_context(|| format!("校驗碼檔 {} 不是文字檔", checksum_url))?;
+            text.split_whitespace()
+                .next()
+                .with_context(|| format!("校驗碼檔 {} 是空的", checksum_url))?
+                .to_string()
+        }
+    };
+    verify_sha256(&content, &expected)?;
+    to_chardefs_json(&content, source.is_cin())
+}
+
+/// 下載網址的內容
+fn download(url: &str) -> Result<Vec<u8>> {
+    let response = ureq::get(url)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .call()
+        .with_context(|| format!("無法下載 {}", url))?;
+    let mut content = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD_BYTES + 1)
+        .read_to_end(&mut content)
+        .with_context(|| format!("下載 {} 時中斷", url))?;
+    if content.len() as u64 > MAX_DOWNLOAD_BYTES {
+        bail!("{} 超過 {} MB，不像是字碼表", url, MAX_DOWNLOAD_BYTES / 1024 / 1024);
+    }
+    Ok(content)
+}
+
+/// 核對 SHA-256（十六進位，不分大小寫）
+pub fn verify_sha256(content: &[u8], expected: &str) -> Result<()> {
+    let actual = format!("{:x}", Sha256::digest(content));
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        bail!("字碼表校驗碼不符（預期 {}，實際 {}），沒有更新", expected.trim(), actual);
+    }
+    Ok(())
+}
+
+/// 把下載的內容轉成要存檔的 chardefs JSON，順便確認可以解析；回傳 JSON 與字根數
+pub fn to_chardefs_json(content: &[u8], is_cin: bool) -> Result<(String, usize)> {
+    let text = std::str::from_utf8(content).context("字碼表不是 UTF-8 文字檔")?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    if is_cin {
+        let defs = CharDefs::parse_cin(text).context(".cin 檔中沒有 %chardef 區段")?;
+        let codes = defs.code_to_chars.len();
+        // 依字根排序輸出，方便比對新舊版本
+        let chardefs: BTreeMap<_, _> = defs.code_to_chars.into_iter().collect();
+        let json = serde_json::to_string_pretty(&serde_json::json!({ "chardefs": chardefs }))?;
+        return Ok((json, codes));
+    }
+    let dictionary = Dictionary::from_json(text).context("下載的字碼表有誤")?;
+    Ok((text.to_string(), dictionary.code_to_chars.len()))
+}
+
+/// 字碼表的備份檔：原本的副檔名加上 .bak（liu.json → liu.json.bak）
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// 以新的內容取代字碼表檔：只取代 .json 字碼表（下載的內容一律存成 chardefs JSON，
+/// .gtab 等依副檔名解析的表格存進去會讀不回來）。新內容先寫暫存檔並以 read_file 完整解析一次，
+/// 解析成功才備份原本的檔案（liu.json.bak）再改名取代
+pub fn install(path: &Path, json: &str, strict: bool) -> Result<()> {
+    let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if !is_json {
+        bail!("主字碼表 {:?} 不是 .json 檔，線上更新只能取代 JSON 字碼表，沒有更新", path);
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, json).with_context(|| format!("無法寫入字碼表: {:?}", tmp_path))?;
+    if let Err(e) = Dictionary::read_file(&tmp_path, strict) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e.context("下載的字碼表無法載入，沒有更新"));
+    }
+    if path.exists() {
+        let backup_path = backup_path(path);
+        fs::copy(path, &backup_path).with_context(|| format!("無法備份字碼表: {:?}", backup_path))?;
+    }
+    fs::rename(&tmp_path, path).with_context(|| format!("無法寫入字碼表: {:?}", path))?;
+    info!("已更新字碼表: {:?}", path);
+    Ok(())
+}
+
+/// 取代後重新載入失敗時，從備份還原原本的字碼表
+pub fn restore(path: &Path) -> Result<()> {
+    let backup_path = backup_path(path);
+    fs::copy(&backup_path, path).with_context(|| format!("無法從 {:?} 還原字碼表", backup_path))?;
+    warn!("已從備份還原字碼表: {:?}", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_and_convert() {
+        // "abc" 的 SHA-256
+        let sha = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD";
+        assert!(verify_sha256(b"abc", sha).is_ok());
+        assert!(verify_sha256(b"abd", sha).is_err());
+
+        let (json, codes) = to_chardefs_json("%chardef begin\nA 一\nab 二\n%chardef end\n".as_bytes(), true).unwrap();
+        assert_eq!(codes, 2);
+        assert_eq!(Dictionary::from_json(&json).unwrap().lookup("a").unwrap().as_ref(), ["一"]);
+        assert!(to_chardefs_json(b"a 1\n", true).is_err());
+        assert!(to_chardefs_json(br#"{ "chardefs": { "a": [1] } }"#, false).is_err());
+
+        let source = UpdateSource::from_config(" https://example.com/liu.CIN?v=2 ", "").unwrap();
+        assert!(source.is_cin());
+        assert_eq!(source.sha256, None);
+        assert!(UpdateSource::from_config("", "abc").is_none());
+    }
+
+    #[test]
+    fn test_install_validates_and_backs_up() {
+        let dir = std::env::temp_dir().join(format!("uclliu-dict-update-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("liu.json");
+        fs::write(&path, r#"{ "chardefs": { "a": ["一"] } }"#).unwrap();
+
+        // 解析不了的內容不取代原檔
+        assert!(install(&path, r#"{ "chardefs": { "a": [1] } }"#, false).is_err());
+        assert_eq!(Dictionary::read_file(&path, false).unwrap().lookup("a").unwrap().as_ref(), ["一"]);
+
+        install(&path, r#"{ "chardefs": { "a": ["乙"] } }"#, false).unwrap();
+        assert_eq!(Dictionary::read_file(&path, false).unwrap().lookup("a").unwrap().as_ref(), ["乙"]);
+        assert!(dir.join("liu.json.bak").exists());
+        restore(&path).unwrap();
+        assert_eq!(Dictionary::read_file(&path, false).unwrap().lookup("a").unwrap().as_ref(), ["一"]);
+
+        // .gtab 主字碼表不取代
+        let gtab = dir.join("liu.gtab");
+        assert!(install(&gtab, r#"{ "chardefs": { "a": ["乙"] } }"#, false).is_err());
+        assert!(!gtab.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,173 @@
+//! 匯出字碼表
+//!
+//! `uclliu.exe --export-dict out.cin` 把目前合併後的字碼表（主字碼表、額外層、
+//! 使用者字典，已濾掉停用字）寫成 .cin 或 CSV，方便備份或分享自訂的表格。
+//! 副檔名為 .csv 時輸出 CSV（字根,候選,別名輸出），其餘一律輸出 .cin。
+
+use crate::dictionary::Dictionary;
+use crate::scheme::InputScheme;
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// 匯出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// gcin / OpenVanilla 等使用的 .cin 文字格式
+    Cin,
+    /// 字根,候選,別名輸出
+    Csv,
+}
+
+impl ExportFormat {
+    /// 依副檔名決定格式（.csv 以外都視為 .cin）
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Self::Csv,
+            _ => Self::Cin,
+        }
+    }
+}
+
+/// 匯出結果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    /// 寫出的字根數
+    pub codes: usize,
+    /// 寫出的候選數
+    pub candidates: usize,
+    /// .cin 無法表示而略過的候選數（含空白的候選，例如部分顏文字）
+    pub skipped: usize,
+}
+
+/// 匯出字碼表到檔案
+pub fn export(path: &Path, dictionary: &Dictionary, scheme: &InputScheme) -> Result<ExportSummary> {
+    let (content, summary) = match ExportFormat::from_path(path) {
+        ExportFormat::Cin => to_cin(dictionary, scheme),
+        ExportFormat::Csv => to_csv(dictionary),
+    };
+    fs::write(path, content).with_context(|| format!("無法寫入匯出檔案: {:?}", path))?;
+    Ok(summary)
+}
+
+/// 產生 .cin 內容；.cin 以空白分隔字根與候選，含空白的候選無法表示，略過並計數
+pub fn to_cin(dictionary: &Dictionary, scheme: &InputScheme) -> (String, ExportSummary) {
+    let entries = dictionary.entries();
+    let mut summary = ExportSummary::default();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "%gen_inp");
+    let _ = writeln!(out, "%ename uclliu");
+    let _ = writeln!(out, "%cname {}", scheme.name);
+    let _ = writeln!(out, "%selkey {}", scheme.selection_keys);
+
+    // 字根用到的按鍵（英文字母顯示為大寫）
+    let mut keys: Vec<char> = entries.iter().flat_map(|(code, _)| code.chars()).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    let _ = writeln!(out, "%keyname begin");
+    for key in keys.into_iter().filter(|key| !key.is_whitespace()) {
+        let _ = writeln!(out, "{} {}", key, key.to_ascii_uppercase());
+    }
+    let _ = writeln!(out, "%keyname end");
+
+    let _ = writeln!(out, "%chardef begin");
+    for (code, chars) in &entries {
+        let mut written = false;
+        for ch in chars {
+            if ch.chars().any(char::is_whitespace) {
+                summary.skipped += 1;
+                continue;
+            }
+            let _ = writeln!(out, "{} {}", code, ch);
+            summary.candidates += 1;
+            written = true;
+        }
+        if written {
+            summary.codes += 1;
+        }
+    }
+    let _ = writeln!(out, "%chardef end");
+    (out, summary)
+}
+
+/// 產生 CSV 內容（有標題列；沒有別名輸出時第三欄留空）
+pub fn to_csv(dictionary: &Dictionary) -> (String, ExportSummary) {
+    let mut summary = ExportSummary::default();
+    let mut out = String::from("code,text,output\n");
+    for (code, chars) in dictionary.entries() {
+        for ch in &chars {
+            let output = dictionary.output_for(&code, ch);
+            let _ = writeln!(
+                out,
+                "{},{},{}",
+                csv_field(&code),
+                csv_field(ch),
+                csv_field(output.as_deref().unwrap_or(""))
+            );
+        }
+        summary.codes += 1;
+        summary.candidates += chars.len();
+    }
+    (out, summary)
+}
+
+/// CSV 欄位：含逗號、引號或換行時加上引號，引號寫成兩個
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_dictionary() -> Dictionary {
+        let mut dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "b": ["二"], "a": ["一", "乙"], "xo": [{ "display": "笑", "output": "😂" }] } }"#,
+        )
+        .unwrap();
+        dictionary.add_user_entry("a", "壹");
+        dictionary.add_user_entry("flip", "(╯°□°）╯︵ ┻━┻");
+        dictionary.add_user_entry("q", "a,\"b\"");
+        dictionary
+    }
+
+    #[test]
+    fn test_export_format_from_path() {
+        assert_eq!(ExportFormat::from_path(Path::new("out.CSV")), ExportFormat::Csv);
+        assert_eq!(ExportFormat::from_path(Path::new("out.cin")), ExportFormat::Cin);
+        assert_eq!(ExportFormat::from_path(Path::new("out")), ExportFormat::Cin);
+    }
+
+    #[test]
+    fn test_to_cin_includes_user_entries() {
+        let (content, summary) = to_cin(&create_test_dictionary(), &InputScheme::default());
+        let chardefs: Vec<&str> = content
+            .lines()
+            .skip_while(|line| *line != "%chardef begin")
+            .skip(1)
+            .take_while(|line| *line != "%chardef end")
+            .collect();
+        assert_eq!(chardefs, vec!["a 壹", "a 一", "a 乙", "b 二", "q a,\"b\"", "xo 笑"]);
+        assert!(content.contains("%selkey 1234567890\n"));
+        assert!(content.contains("\nx X\n"));
+        assert_eq!(summary, ExportSummary { codes: 4, candidates: 6, skipped: 1 });
+    }
+
+    #[test]
+    fn test_to_csv_quotes_fields() {
+        let (content, summary) = to_csv(&create_test_dictionary());
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[0], "code,text,output");
+        assert_eq!(lines[1], "a,壹,");
+        assert!(lines.contains(&"flip,(╯°□°）╯︵ ┻━┻,"));
+        assert!(lines.contains(&"q,\"a,\"\"b\"\"\","));
+        assert!(lines.contains(&"xo,笑,😂"));
+        assert_eq!(summary, ExportSummary { codes: 5, candidates: 7, skipped: 0 });
+    }
+}
@@ -0,0 +1,194 @@
+//! 字頻統計模組
+//!
+//! 記錄每個 (字根, 候選字) 被選用的次數與最後一次選用的先後，查詢候選時把常用（或最近用過）的字往前排，
+//! 常用字不會永遠停在第 4、5 個位置（排序策略見 sorter 模組）。統計依使用者存放在使用者資料目錄的
+//! frequency.json，跨次啟動保留。
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// 字頻統計檔名（放在使用者資料目錄，見 Profile::data_path）
+pub const FREQUENCY_FILE: &str = "frequency.json";
+
+/// 字頻統計
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrequencyStats {
+    /// 字根 -> (候選字 -> 選用次數)
+    counts: HashMap<String, HashMap<String, u32>>,
+    /// 字根 -> (候選字 -> 最後一次選用的序號)，序號越大越近（舊版的檔案沒有這一項）
+    #[serde(default)]
+    last_used: HashMap<String, HashMap<String, u64>>,
+    /// 下一次選用的序號
+    #[serde(default)]
+    clock: u64,
+    /// 上次儲存後是否有新紀錄
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl FrequencyStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 讀取字頻統計，檔案不存在或格式有誤時從空白開始
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<Self>(&content) {
+            Ok(stats) => {
+                info!("載入字頻統計: {:?}（{} 個字根）", path, stats.counts.len());
+                stats
+            }
+            Err(e) => {
+                warn!("字頻統計 {:?} 格式有誤，重新開始統計: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// 寫入字頻統計（先寫暫存檔再改名，中斷時不會弄壞原本的檔案）
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string(self)?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).with_context(|| format!("無法寫入字頻統計: {:?}", tmp_path))?;
+        fs::rename(&tmp_path, path).with_context(|| format!("無法寫入字頻統計: {:?}", path))?;
+        Ok(())
+    }
+
+    /// 記錄一次選用
+    pub fn record(&mut self, code: &str, candidate: &str) {
+        if code.is_empty() || candidate.is_empty() {
+            return;
+        }
+        let count = self
+            .counts
+            .entry(code.to_string())
+            .or_default()
+            .entry(candidate.to_string())
+            .or_insert(0);
+        *count = count.saturating_add(1);
+        self.clock += 1;
+        self.last_used.entry(code.to_string()).or_default().insert(candidate.to_string(), self.clock);
+        self.dirty = true;
+    }
+
+    /// 取消一次選用（撤銷送出時），次數歸零時移除紀錄；最後選用的先後無法還原，維持原狀
+    pub fn unrecord(&mut self, code: &str, candidate: &str) {
+        let Some(candidates) = self.counts.get_mut(code) else {
+            return;
+        };
+        let Some(count) = candidates.get_mut(candidate) else {
+            return;
+        };
+        *count = count.saturating_sub(1);
+        if *count == 0 {
+            candidates.remove(candidate);
+            if candidates.is_empty() {
+                self.counts.remove(code);
+            }
+            if let Some(last_used) = self.last_used.get_mut(code) {
+                last_used.remove(candidate);
+                if last_used.is_empty() {
+                    self.last_used.remove(code);
+                }
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// 候選字的選用次數
+    pub fn count(&self, code: &str, candidate: &str) -> u32 {
+        self.counts
+            .get(code)
+            .and_then(|counts| counts.get(candidate))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// 候選字最後一次選用的序號（越大越近），沒有選用過時為 0
+    pub fn last_used(&self, code: &str, candidate: &str) -> u64 {
+        self.last_used
+            .get(code)
+            .and_then(|last_used| last_used.get(candidate))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// 依選用次數由多到少排序候選（次數相同時保持字碼表順序）
+    pub fn sort<T: AsRef<str>>(&self, code: &str, candidates: &mut [T]) {
+        let Some(counts) = self.counts.get(code) else {
+            return;
+        };
+        candidates.sort_by_key(|c| std::cmp::Reverse(counts.get(c.as_ref()).copied().unwrap_or(0)));
+    }
+
+    /// 上次儲存後是否有新紀錄
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// 標記為已儲存
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_sort_by_usage_keeps_table_order_for_ties() {
+        let mut stats = FrequencyStats::new();
+        let mut list = candidates(&["一", "乙", "丫", "以"]);
+        stats.sort("a", &mut list);
+        assert_eq!(list, candidates(&["一", "乙", "丫", "以"]));
+
+        stats.record("a", "丫");
+        stats.record("a", "丫");
+        stats.record("a", "以");
+        stats.record("b", "一");
+        stats.sort("a", &mut list);
+        assert_eq!(list, candidates(&["丫", "以", "一", "乙"]));
+        assert_eq!(stats.count("a", "丫"), 2);
+        assert_eq!(stats.count("a", "一"), 0);
+        // 最後選用的先後
+        assert!(stats.last_used("a", "以") > stats.last_used("a", "丫"));
+        assert_eq!(stats.last_used("a", "一"), 0);
+        stats.unrecord("a", "以");
+        assert_eq!(stats.last_used("a", "以"), 0);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("uclliu-frequency-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(FREQUENCY_FILE);
+
+        let mut stats = FrequencyStats::new();
+        assert!(!stats.is_dirty());
+        stats.record("a", "乙");
+        assert!(stats.is_dirty());
+        stats.save(&path).unwrap();
+        stats.mark_saved();
+
+        let loaded = FrequencyStats::load(&path);
+        assert_eq!(loaded.count("a", "乙"), 1);
+        assert!(!loaded.is_dirty());
+
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(FrequencyStats::load(&path), FrequencyStats::new());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
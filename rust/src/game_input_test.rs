@@ -3,7 +3,7 @@
 use enigo::*;
 use std::thread;
 use std::time::Duration;
-use log::{info, debug};
+use tracing::{info, debug};
 
 /// 遊戲輸入測試器
 pub struct GameInputTester {
@@ -116,6 +116,12 @@ impl GameInputTester {
         self.key_click(Key::Space);
     }
 
+    /// 模擬 Enter 鍵清除字根與累積文字（每個測試案例前呼叫，剪貼簿只會留下這次的結果）
+    pub fn clear_input(&mut self) {
+        info!("模擬 Enter 鍵清除字根與累積文字");
+        self.key_click(Key::Return);
+    }
+
     /// 模擬 ESC 鍵關閉窗口
     pub fn close_window(&mut self) {
         info!("模擬 ESC 鍵關閉窗口");
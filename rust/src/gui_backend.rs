@@ -0,0 +1,329 @@
+//! GUI 後端抽象
+//!
+//! 核心（鍵盤鉤子、托盤、輸入處理器）只透過這裡的型別與 GUI 溝通，不直接使用 fltk 型別：
+//! - 其他執行緒以 GuiCommand 送命令給 GUI 執行緒；
+//! - 狀態窗要顯示的文字由 StatusView 從輸入處理器的狀態算好，後端只負責畫出來；
+//! - 狀態窗實作 GuiBackend（目前只有 fltk 的 StatusWindow，見 gui_window 模組），
+//!   之後換成 egui/winit 或 TSF 原生候選窗時只需要新的實作，GuiWindowManager 不必改。
+
+use crate::appbar::DockEdge;
+use crate::candidate::{is_phrase, CandidateSource};
+use crate::chardefs::is_wildcard;
+use crate::input_method::InputMethodProcessor;
+use crate::macros::MacroAction;
+use crate::mode::InputMode;
+use crate::presentation::{Presentation, PresentationAction, CANDIDATES_PER_PAGE};
+use anyhow::Result;
+use std::borrow::Cow;
+
+/// 迷你候選浮窗最多列出幾個候選（見 mini_float 模組）
+pub const MINI_FLOAT_CANDIDATES: usize = 5;
+
+/// 累積文字預設最多可撤回幾步
+pub const DEFAULT_UNDO_LIMIT: usize = 100;
+
+/// 由其他執行緒（例如鍵盤鉤子）送給 GUI 執行緒的命令
+/// GUI 元件只在 GUI 執行緒上操作，其他執行緒一律透過 channel 送命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuiCommand {
+    /// 切換遊戲模式窗口顯示/隱藏
+    Toggle,
+    /// 顯示訊息視窗（標題, 內容），例如托盤的統計面板
+    ShowMessage(String, String),
+    /// 顯示字根查詢視窗
+    ShowLookup,
+    /// 查碼：以剪貼簿的文字開啟字根查詢視窗，逐字列出字根
+    LookupClipboard,
+    /// 選取反查：複製前景程式選取的文字（之後還原剪貼簿），以它開啟字根查詢視窗
+    LookupSelection,
+    /// 長文分段送出：把剪貼簿的文字分段送到前景程式並顯示進度
+    LongPaste,
+    /// 顯示停用字管理視窗
+    ShowBlocklist,
+    /// 顯示螢幕鍵盤
+    ShowKeyboard,
+    /// 顯示符號面板
+    ShowSymbolPanel,
+    /// 顯示說明視窗（快捷鍵一覽）
+    ShowHelp,
+    /// 顯示學習曲線
+    ShowLearning,
+    /// 顯示按鍵回放
+    ShowKeyTrace,
+    /// 顯示開發者用的狀態檢視
+    ShowInspector,
+    /// 展示熱鍵：調整遊戲模式窗口版面
+    Presentation(PresentationAction),
+    /// 詢問是否把多行文字送到終端機（見 input_simulator::TerminalMode）
+    ConfirmTerminalSend(String),
+    /// 巨集錄製完成，詢問名稱與熱鍵後存起來（見 macros 模組）
+    NameMacro(Vec<MacroAction>),
+    /// 遊戲模式窗口可見但沒有焦點時按了 Ctrl+V（使用者回到遊戲貼上累積文字）
+    PastedElsewhere,
+    /// 複製文字到剪貼簿（Alt + 選字鍵複製候選，不送出）
+    CopyToClipboard(String),
+    /// 把其他軟體送出的文字加入累積文字（見 accumulate_packet_input）
+    AppendAccumulated(String),
+    /// 詢問新的日誌過濾規則（托盤「日誌等級 → 自訂…」）
+    EditLogFilter,
+    /// 按了無效的鍵，閃一下狀態窗（見設定 invalid_key_flash）
+    FlashStatus,
+}
+
+/// 狀態窗的視窗行為設定（後端不支援的項目可以忽略）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowOptions {
+    /// 顯示/隱藏時淡入淡出
+    pub fade: bool,
+    /// 以 overlay 模式顯示（無邊框全螢幕遊戲下仍可見）
+    pub overlay: bool,
+    /// 停駐位置
+    pub dock: DockEdge,
+    /// 累積文字最多可撤回幾步（0 表示不記錄）
+    pub undo_limit: usize,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self { fade: true, overlay: false, dock: DockEdge::Floating, undo_limit: DEFAULT_UNDO_LIMIT }
+    }
+}
+
+/// 狀態窗要顯示的內容（與後端無關的純文字）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatusView {
+    /// 字根欄（字根或提示文字）
+    pub code: String,
+    /// 候選欄（一頁的候選、聯想詞或補碼選字；直向版面時以換行分隔）
+    pub candidates: String,
+    /// 整句組字累積、還沒送出的句子（沒有開啟整句組字或還沒累積時為空）
+    pub composition: String,
+    /// 補全提示：接著輸入哪些字根還能組成更長的字根，例如「a → +b, +d, +x」（沒有提示時為空）
+    pub hints: String,
+    /// 迷你候選浮窗的一行：字根欄加上目前頁的前幾個候選（沒有在組字時為 None）
+    pub mini: Option<String>,
+    /// 簡碼提示：上一次送出的字有更短的字根，例如「「的」可用 2 碼: xx」（組字中或沒有提示時為空）
+    pub tip: String,
+}
+
+impl StatusView {
+    /// 依輸入處理器目前的狀態產生顯示內容
+    /// show_candidate_source 為 true 時標註所有候選來源，否則只標註額外字碼表層
+    pub fn build(
+        processor: &InputMethodProcessor,
+        mode: InputMode,
+        presentation: &Presentation,
+        show_candidate_source: bool,
+    ) -> Self {
+        let state = processor.get_state();
+        let separator = presentation.candidate_separator();
+
+        // 按過重複鍵時在字根後標出重複次數
+        let repeat_mark = match state.repeat_count {
+            Some(0) => " ×".to_string(),
+            Some(count) => format!(" ×{}", count),
+            None => String::new(),
+        };
+
+        // 字根顯示（類似 Python 的 type_label_set_text）
+        let code = if let Some(text) = &state.temp_english {
+            format!("英「{}」", text)
+        } else if let Some(ch) = state.homophone_of {
+            // 同音字熱鍵列出的候選沒有字根，標出是哪個字的同音字
+            format!("同音「{}」{}", ch, repeat_mark)
+        } else if state.symbol_menu.is_some() {
+            // 符號選單先列出分類，選了分類後標出分類名稱
+            match processor.symbol_category() {
+                Some(name) => format!("符號「{}」", name),
+                None => "符號".to_string(),
+            }
+        } else if state.current_code.is_empty() && mode == InputMode::English {
+            "英模式".to_string()
+        } else if state.current_code.is_empty() {
+            // 沒有字根時顯示提示文字，避免視覺上像是「什麼都沒出現」
+            if processor.is_half_width() { "輸入字根...（半形）" } else { "輸入字根..." }.to_string()
+        } else {
+            format!("{}{}", state.current_code, repeat_mark)
+        };
+
+        // 候選字顯示（類似 Python 的 word_label_set_text）
+        let candidates = &state.candidates;
+        let candidates = if state.temp_english.is_some() {
+            "暫時英文：Space/Enter 送出，Esc 取消".to_string()
+        } else if candidates.is_empty() && !state.suggestions.is_empty() {
+            // 送出後的聯想詞，以選字鍵編號列出
+            let numbered: Vec<String> = state
+                .suggestions
+                .iter()
+                .zip(processor.scheme().selection_keys.chars())
+                .map(|(suggestion, key)| format!("{}.{}", key, suggestion))
+                .collect();
+            format!("聯想：{}", numbered.join(separator))
+        } else if candidates.is_empty() {
+            String::new()
+        } else {
+            let start_idx = state.candidate_index;
+            let end_idx = (start_idx + CANDIDATES_PER_PAGE).min(candidates.len());
+
+            // 萬用字元與 emoji 短碼查詢時候選的字根各不相同，在每組的第一個候選前標出字根
+            let label_codes = is_wildcard(&state.current_code) || state.is_emoji_composition();
+            let mut labels = Vec::new();
+            for i in start_idx..end_idx {
+                let entry = &candidates[i];
+                // 詞組以「」框起，和單字區分
+                let candidate = if is_phrase(&entry.text) {
+                    Cow::Owned(format!("「{}」", entry.text))
+                } else {
+                    Cow::Borrowed(entry.text.as_str())
+                };
+                let candidate = if label_codes && (i == start_idx || candidates[i - 1].code != entry.code) {
+                    format!("[{}] {}", entry.code, candidate)
+                } else {
+                    candidate.into_owned()
+                };
+                // 來自額外字碼表層的候選標出層名稱；開啟來源標註時也標出使用者字典與學習
+                let mark = entry
+                    .source
+                    .as_ref()
+                    .filter(|source| show_candidate_source || matches!(source, CandidateSource::Layer(_)))
+                    .and_then(CandidateSource::mark);
+                let mut candidate = match mark {
+                    Some(mark) => format!("{}{}", candidate, mark),
+                    None => candidate,
+                };
+                if let Some(note) = &entry.note {
+                    candidate = format!("{}（{}）", candidate, note);
+                }
+                if i == start_idx && state.complement_selected.is_none() {
+                    labels.push(format!("{} (Space)", candidate));
+                } else {
+                    labels.push(candidate);
+                }
+            }
+
+            // 補碼鍵選出的字標明是第幾個候選，並以選字鍵編號列出同一頁的候選供改選
+            if let Some(label) = state.complement_label() {
+                let numbered: Vec<String> = candidates[start_idx..end_idx]
+                    .iter()
+                    .zip(processor.scheme().selection_keys.chars())
+                    .map(|(candidate, key)| format!("{}.{}", key, candidate))
+                    .collect();
+                format!("{} (Space){}改選：{}", label, separator, numbered.join(separator))
+            } else if let Some(ref selected) = state.complement_selected {
+                format!("{} (Space)", selected)
+            } else {
+                labels.join(separator)
+            }
+        };
+
+        // 迷你浮窗只列出候選本身，補碼與聯想詞的提示留給狀態窗
+        let composing = !state.current_code.is_empty() || state.is_listing() || state.temp_english.is_some();
+        let mini = composing.then(|| {
+            let numbered: Vec<String> = state
+                .get_current_page_candidates()
+                .iter()
+                .take(MINI_FLOAT_CANDIDATES)
+                .zip(processor.scheme().selection_keys.chars())
+                .map(|(candidate, key)| format!("{}.{}", key, candidate))
+                .collect();
+            format!("{}  {}", code, numbered.join(" ")).trim_end().to_string()
+        });
+
+        let letters = processor.completion_letters();
+        let hints = if letters.is_empty() {
+            String::new()
+        } else {
+            let letters: Vec<String> = letters.iter().map(|letter| format!("+{}", letter)).collect();
+            format!("{} → {}", state.current_code, letters.join(", "))
+        };
+
+        // 簡碼提示等到組字結束才顯示，不和候選搶位置
+        let tip = if composing { String::new() } else { processor.shorter_code_hint().unwrap_or_default() };
+
+        Self { code, candidates, composition: processor.composition(), hints, mini, tip }
+    }
+
+    /// 候選分離到候選窗時狀態窗顯示的內容：只留字根與整句組字
+    pub fn without_candidates(&self) -> Self {
+        Self { candidates: String::new(), hints: String::new(), ..self.clone() }
+    }
+}
+
+/// 狀態窗的 GUI 後端
+/// 所有方法都在 GUI 執行緒上呼叫；只有 show、hide、update、set_theme 是必要的，
+/// 其他功能（動畫、遊戲模式的累積文字）不支援的後端使用預設實作即可
+/// 焦點狀態由後端寫入共用的 gui_has_focus 旗標，鍵盤鉤子直接讀旗標
+/// 管理器放在 AppState 裡與其他執行緒共用，所以後端必須是 Send
+pub trait GuiBackend: Send {
+    /// 顯示狀態窗
+    fn show(&mut self) -> Result<()>;
+
+    /// 隱藏狀態窗
+    fn hide(&mut self);
+
+    /// 顯示新的內容（只標記有變化的部分，實際繪製交給後端的事件循環）
+    fn update(&mut self, view: &StatusView);
+
+    /// 套用展示用版面與配色（見 presentation 模組）
+    fn set_theme(&mut self, presentation: Presentation);
+
+    /// 套用視窗行為設定（下次顯示時生效）
+    fn configure(&mut self, _options: WindowOptions) {}
+
+    /// 推進動畫等定期工作，回傳動畫是否仍在進行
+    fn tick(&mut self) -> bool {
+        false
+    }
+
+    /// 短暫閃爍提示（例如按了無效的鍵），由 tick 結束閃爍
+    fn flash(&mut self) {}
+
+    /// 加入一段遊戲模式的累積文字
+    fn append_text(&mut self, _text: &str) {}
+
+    /// 清除遊戲模式的累積文字，原本就是空的時回傳 false
+    fn clear_text(&mut self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::Dictionary;
+
+    #[test]
+    fn test_status_view_from_state() {
+        let dictionary = Dictionary::from_json(r#"{ "chardefs": { "a": ["一", "乙"], "ab": ["二"], "abc": ["乙"] } }"#).unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+        let presentation = Presentation::default();
+
+        let view = StatusView::build(&processor, InputMode::English, &presentation, false);
+        assert_eq!(view, StatusView { code: "英模式".to_string(), ..Default::default() });
+
+        let (_, _) = processor.handle_code_input('a');
+        let view = StatusView::build(&processor, InputMode::Ucl, &presentation, false);
+        assert_eq!(view.code, "a");
+        assert_eq!(view.candidates, format!("一 (Space){}乙", presentation.candidate_separator()));
+        assert_eq!(view.mini.as_deref(), Some("a  1.一 2.乙"));
+        assert!(view.hints.is_empty());
+        processor.set_completion_hints(true);
+        let view = StatusView::build(&processor, InputMode::Ucl, &presentation, false);
+        assert_eq!(view.hints, "a → +b");
+        let status = view.without_candidates();
+        assert_eq!((status.code.as_str(), status.candidates.as_str(), status.hints.as_str()), ("a", "", ""));
+
+        processor.set_shorter_code_hints(true);
+        processor.handle_code_input('b');
+        processor.handle_code_input('c');
+        assert_eq!(processor.handle_selection_key('1').as_deref(), Some("乙"));
+        let view = StatusView::build(&processor, InputMode::Ucl, &presentation, false);
+        assert_eq!(view.tip, "「乙」可用 1 碼: a");
+        assert_eq!(view.without_candidates().tip, view.tip);
+
+        processor.set_compose_sentence(true);
+        processor.push_composition("一");
+        let view = StatusView::build(&processor, InputMode::Ucl, &presentation, false);
+        assert_eq!(view.composition, "一");
+    }
+}
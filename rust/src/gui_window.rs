@@ -1,20 +1,58 @@
 //! GUI 主窗口模組
 //! 用於顯示字根和候選字（類似 Python 版本的 type_label 和 word_label）
 //! 同時作為遊戲模式窗口，能夠接收鍵盤輸入（用於 Raw Input 遊戲）
-
+//! StatusWindow 是 GuiBackend 的 fltk 實作（見 gui_backend 模組）；設定 candidate_window 時
+//! 候選改由獨立的 CandidateWindow 顯示，兩者由 GuiWindowManager 協調
+//!
+//! 狀態窗、候選窗、迷你浮窗、模式浮標、符號面板與各工具視窗（*_window 模組）都只在 GUI 執行緒上建立與操作；
+//! 其他執行緒以 GuiCommand 通知 GUI 執行緒（見 gui_backend 模組）
+
+use crate::appbar::{self, DockEdge};
+use crate::candidate_window::{CandidateWindow, CandidateWindowPosition};
+use crate::blocklist_window::BlocklistWindow;
+use crate::help_window::HelpWindow;
+use crate::input_events::{FlagObserver, InputObserver};
+use crate::key_trace::KeyTrace;
+use crate::key_trace_window::KeyTraceWindow;
+use crate::inspector::Inspector;
+use crate::inspector_window::InspectorWindow;
+use crate::learning::LearningTracker;
+use crate::learning_window::LearningWindow;
+use crate::keyboard_layout::KeyboardLayout;
+use crate::keyboard_window::KeyboardWindow;
 use crate::input_method::InputMethodProcessor;
+use crate::gui_backend::{GuiBackend, GuiCommand, StatusView, WindowOptions, DEFAULT_UNDO_LIMIT};
 use crate::input_simulator::InputSimulator;
+use crate::logging;
+use crate::long_paste_window::LongPasteWindow;
+use crate::lookup_window::LookupWindow;
+use crate::macros::{Macro, MacroAction, MacroBook, MACRO_SLOTS};
+use crate::mini_float::{MiniFloat, MiniFloatPosition};
+use crate::mode_badge::{BadgePosition, ModeBadge};
+use crate::mode::{InputMode, ModeState};
+use crate::overlay::{self, OverlaySupport};
+use crate::presentation::{Presentation, PresentationAction, CANDIDATES_PER_PAGE};
+use crate::selection;
+use crate::symbol_panel::SymbolPanel;
+use crate::symbols::SymbolTable;
 use anyhow::Result;
 use fltk::{
     app,
+    dialog,
     enums::{Align, Color, Event, Key},
     frame::Frame,
+    menu::MenuItem,
     prelude::*,
-    window::Window,
+    window::DoubleWindow,
 };
-use log::{debug, info, warn};
-use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{debug, info, warn};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::mpsc::{Receiver, TryRecvError};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use windows::{
     Win32::Foundation::{COLORREF, HWND},
     Win32::UI::WindowsAndMessaging::{
@@ -24,9 +62,171 @@ use windows::{
     },
 };
 
+/// 聚焦時的窗口透明度（0 = 完全透明, 255 = 完全不透明）
+const ALPHA_FOCUSED: u8 = 100;
+/// 失焦時的窗口透明度（幾乎完全透明，避免誤會它有焦點）
+const ALPHA_UNFOCUSED: u8 = 10;
+/// 淡入淡出動畫時間
+const FADE_DURATION: Duration = Duration::from_millis(150);
+/// 按了無效的鍵時狀態窗閃爍的時間與底色
+const FLASH_DURATION: Duration = Duration::from_millis(120);
+const FLASH_COLOR: (u8, u8, u8) = (255, 170, 170);
+/// 確認對話框關閉後，等待終端機重新取得焦點再送字的時間
+const CONFIRM_REFOCUS_DELAY: Duration = Duration::from_millis(150);
+/// 查碼熱鍵最多反查剪貼簿開頭的幾個字
+const LOOKUP_CLIPBOARD_MAX_CHARS: usize = 50;
+
+/// 進行中的淡入淡出動畫
+struct Fade {
+    from: u8,
+    started: Instant,
+    /// 淡出完成後隱藏窗口
+    hide_when_done: bool,
+}
+
+/// 計算淡入淡出在 elapsed 時的透明度（線性插值）
+fn fade_alpha(from: u8, to: u8, elapsed: Duration, duration: Duration) -> u8 {
+    if duration.is_zero() || elapsed >= duration {
+        return to;
+    }
+    let t = elapsed.as_secs_f64() / duration.as_secs_f64();
+    (from as f64 + (to as f64 - from as f64) * t).round() as u8
+}
+
+/// 轉成 fltk 標籤文字：標籤中的 @ 是符號標記（例如 @-> 會畫成箭頭），
+/// 顏文字裡的 @ 要寫成 @@ 才會顯示原字
+pub(crate) fn escape_label(text: &str) -> Cow<'_, str> {
+    if text.contains('@') {
+        Cow::Owned(text.replace('@', "@@"))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// 設定窗口整體透明度（窗口必須已套用 WS_EX_LAYERED）
+/// 遊戲模式累積的文字，記住每次選字的邊界：Backspace 一次退回一整個詞組，
+/// 不會把「測試」退成「測」
+/// 每次修改前保存一份快照，Ctrl+Z / Ctrl+Y 逐步撤回與重做（誤按 Enter 清掉整句也能救回）
+#[derive(Debug)]
+struct AccumulatedText {
+    text: String,
+    /// 每次送進來的文字在 text 中的起點（位元組位置）
+    starts: Vec<usize>,
+    /// 可撤回的快照（最後一個是最近一次修改前的內容）
+    undo: VecDeque<(String, Vec<usize>)>,
+    /// 撤回後可重做的快照，有新的修改時清空
+    redo: Vec<(String, Vec<usize>)>,
+    /// 最多保留幾步撤回（0 表示不記錄）
+    undo_limit: usize,
+}
+
+impl Default for AccumulatedText {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            starts: Vec::new(),
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            undo_limit: DEFAULT_UNDO_LIMIT,
+        }
+    }
+}
+
+impl AccumulatedText {
+    /// 加入一次選字（或直接輸入）的結果
+    fn push_commit(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.record();
+        self.starts.push(self.text.len());
+        self.text.push_str(text);
+    }
+
+    /// 退回最後一次加入的文字
+    fn pop_commit(&mut self) -> Option<String> {
+        if self.starts.is_empty() {
+            return None;
+        }
+        self.record();
+        let start = self.starts.pop()?;
+        Some(self.text.split_off(start))
+    }
+
+    fn clear(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+        self.record();
+        self.text.clear();
+        self.starts.clear();
+    }
+
+    /// 設定撤回上限，超過的舊步驟直接丟掉
+    fn set_undo_limit(&mut self, limit: usize) {
+        self.undo_limit = limit;
+        while self.undo.len() > limit {
+            self.undo.pop_front();
+        }
+        self.redo.truncate(limit);
+    }
+
+    /// 修改前保存目前內容，並清空重做
+    fn record(&mut self) {
+        self.redo.clear();
+        if self.undo_limit == 0 {
+            return;
+        }
+        if self.undo.len() >= self.undo_limit {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(self.snapshot());
+    }
+
+    fn snapshot(&self) -> (String, Vec<usize>) {
+        (self.text.clone(), self.starts.clone())
+    }
+
+    /// 撤回上一次修改，沒有可撤回的步驟時回傳 false
+    fn undo(&mut self) -> bool {
+        let Some((text, starts)) = self.undo.pop_back() else {
+            return false;
+        };
+        self.redo.push(self.snapshot());
+        (self.text, self.starts) = (text, starts);
+        true
+    }
+
+    /// 重做上一次撤回的修改，沒有可重做的步驟時回傳 false
+    fn redo(&mut self) -> bool {
+        let Some((text, starts)) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push_back(self.snapshot());
+        (self.text, self.starts) = (text, starts);
+        true
+    }
+
+    fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+fn set_window_alpha(window: &DoubleWindow, alpha: u8) {
+    unsafe {
+        let raw = window.raw_handle();
+        let hwnd = HWND(raw as isize);
+        let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA);
+    }
+}
+
 /// GUI 主窗口
-pub struct GuiWindow {
-    window: Window,
+pub struct StatusWindow {
+    window: DoubleWindow,
     code_frame: Frame,             // 字根顯示框（類似 Python 的 type_label）
     word_frame: Frame,             // 候選字顯示框（類似 Python 的 word_label）
     accumulated_text_frame: Frame, // 累積文字顯示框（顯示待貼上的完整句子）
@@ -34,14 +234,32 @@ pub struct GuiWindow {
     input_simulator: Arc<Mutex<InputSimulator>>,
     gui_needs_update: Arc<AtomicBool>,
     is_input_mode: bool, // 是否為輸入模式（窗口有焦點時接收鍵盤輸入）
-    accumulated_text: Arc<Mutex<String>>, // 累積的文字（待貼上到遊戲）
+    accumulated_text: Arc<Mutex<AccumulatedText>>, // 累積的文字（待貼上到遊戲）
     /// 與全域狀態共享的可見旗標（給鍵盤鉤子查詢，不再在鉤子裡鎖 GUI 管理器）
     gui_visible_flag: Arc<AtomicBool>,
     /// 與全域狀態共享的焦點旗標
     gui_has_focus_flag: Arc<AtomicBool>,
+    /// 視窗行為設定（淡入淡出、overlay、停駐位置）
+    options: WindowOptions,
+    /// 動畫最後套用的透明度
+    alpha: u8,
+    /// 目標透明度（依焦點狀態變化，由事件處理器更新）
+    target_alpha: Arc<AtomicU8>,
+    /// 進行中的淡入淡出動畫
+    fade: Option<Fade>,
+    /// 這次顯示是否真的以 overlay 模式顯示（不支援時退回一般視窗）
+    overlay_active: bool,
+    /// 目前是否已註冊為 AppBar（隱藏時取消註冊）
+    docked: bool,
+    /// 上次檢查前景視窗、重新置頂 overlay 的時間
+    last_overlay_check: Instant,
+    /// 無效按鍵閃爍的結束時間（閃爍中才有值）
+    flash_until: Option<Instant>,
+    /// 展示用版面設定（直橫排列、縮放、高對比）
+    presentation: Presentation,
 }
 
-impl GuiWindow {
+impl StatusWindow {
     /// 創建新的 GUI 主窗口
     pub fn new(
         processor: Arc<Mutex<InputMethodProcessor>>,
@@ -49,6 +267,7 @@ impl GuiWindow {
         gui_needs_update: Arc<AtomicBool>,
         gui_visible_flag: Arc<AtomicBool>,
         gui_has_focus_flag: Arc<AtomicBool>,
+        mode: Arc<ModeState>,
     ) -> Result<Self> {
         // 獲取屏幕尺寸，將窗口放在屏幕右下角
         let screen_w = app::screen_size().0 as i32;
@@ -58,7 +277,8 @@ impl GuiWindow {
         let win_x = screen_w - win_w - 10; // 距離右邊 10 像素
         let win_y = screen_h - win_h - 50; // 距離底部 50 像素（避免被任務欄遮擋）
 
-        let mut window = Window::new(win_x, win_y, win_w, win_h, "");
+        // 使用雙緩衝窗口，重繪時不會先清成背景色而閃爍
+        let mut window = DoubleWindow::new(win_x, win_y, win_w, win_h, "");
         // 顯示邊框，讓使用者更容易看到窗口位置
         window.set_border(true);
         window.set_color(Color::from_rgb(222, 222, 222)); // 淺灰色背景，類似 Python 版本
@@ -99,10 +319,13 @@ impl GuiWindow {
         let processor_clone = processor.clone();
         let input_simulator_clone = input_simulator.clone();
         let gui_needs_update_clone = gui_needs_update.clone();
-        let accumulated_text_clone = Arc::new(Mutex::new(String::new()));
+        let accumulated_text_clone = Arc::new(Mutex::new(AccumulatedText::default()));
         let accumulated_text_for_handler = accumulated_text_clone.clone();
 
         let gui_has_focus_for_handler = gui_has_focus_flag.clone();
+        let mode_for_handler = mode.clone();
+        let target_alpha = Arc::new(AtomicU8::new(ALPHA_FOCUSED));
+        let target_alpha_for_handler = target_alpha.clone();
 
         window.handle(move |w, ev| {
             // 讓 FLTK 處理 Focus/Unfocus，並在鍵盤事件時直接詢問窗口是否有焦點
@@ -111,16 +334,8 @@ impl GuiWindow {
                     debug!("遊戲模式窗口獲得焦點");
                     gui_has_focus_for_handler.store(true, Ordering::Relaxed);
                     // 窗口獲得焦點時，提高透明度，讓使用者明顯感覺「現在可以打字」
-                    unsafe {
-                        let raw = w.raw_handle();
-                        let hwnd = HWND(raw as isize);
-                        let _ = SetLayeredWindowAttributes(
-                            hwnd,
-                            COLORREF(0),
-                            100, // 聚焦時半透明（或可改成 255 完全不透明）
-                            LWA_ALPHA,
-                        );
-                    }
+                    target_alpha_for_handler.store(ALPHA_FOCUSED, Ordering::Relaxed);
+                    set_window_alpha(w, ALPHA_FOCUSED);
                     // 不在這裡處理鍵盤邏輯，讓事件繼續傳遞
                     return false;
                 }
@@ -128,21 +343,22 @@ impl GuiWindow {
                     debug!("遊戲模式窗口失去焦點");
                     gui_has_focus_for_handler.store(false, Ordering::Relaxed);
                     // 窗口失去焦點時，幾乎完全透明，避免誤會它有焦點
-                    unsafe {
-                        let raw = w.raw_handle();
-                        let hwnd = HWND(raw as isize);
-                        let _ = SetLayeredWindowAttributes(
-                            hwnd,
-                            COLORREF(0),
-                            10, // 失焦時幾乎完全透明（0~255）
-                            LWA_ALPHA,
-                        );
-                    }
+                    target_alpha_for_handler.store(ALPHA_UNFOCUSED, Ordering::Relaxed);
+                    set_window_alpha(w, ALPHA_UNFOCUSED);
                     return false;
                 }
+                Event::Push if app::event_mouse_button() == app::MouseButton::Right => {
+                    Self::show_block_menu(&processor_clone, &gui_needs_update_clone);
+                    return true;
+                }
                 _ => {}
             }
 
+            // 遊戲模式窗口送出的字累積在窗口裡，不在前景程式，不能用退格撤銷
+            if ev == Event::KeyDown && w.has_focus() {
+                processor_clone.lock().unwrap().forget_undo();
+            }
+
             // 處理鍵盤事件（只在窗口有焦點時處理）
             Self::handle_keyboard_event(
                 w,
@@ -151,6 +367,7 @@ impl GuiWindow {
                 &input_simulator_clone,
                 &gui_needs_update_clone,
                 &accumulated_text_for_handler,
+                &mode_for_handler,
             )
         });
 
@@ -166,9 +383,56 @@ impl GuiWindow {
             accumulated_text: accumulated_text_clone, // 使用同一個 Arc，這樣 handler 和窗口可以共享
             gui_visible_flag,
             gui_has_focus_flag,
+            options: WindowOptions::default(),
+            alpha: ALPHA_FOCUSED,
+            target_alpha,
+            fade: None,
+            overlay_active: false,
+            docked: false,
+            last_overlay_check: Instant::now(),
+            flash_until: None,
+            presentation: Presentation::default(),
         })
     }
 
+    /// 右鍵選單：把目前這一頁的某個候選加入停用字清單（隱藏此字）
+    fn show_block_menu(processor: &Arc<Mutex<InputMethodProcessor>>, gui_needs_update: &AtomicBool) {
+        // 與狀態窗顯示的候選一致（見 StatusView::build）：補碼選字時只顯示選中的那個
+        let shown: Vec<String> = {
+            let processor = processor.lock().unwrap();
+            let state = processor.get_state();
+            match &state.complement_selected {
+                Some(selected) => vec![selected.clone()],
+                None => state
+                    .candidates
+                    .iter()
+                    .skip(state.candidate_index)
+                    .take(CANDIDATES_PER_PAGE)
+                    .map(|candidate| candidate.text.clone())
+                    .collect(),
+            }
+        };
+        if shown.is_empty() {
+            return;
+        }
+
+        // 選單標籤中的 & 是快捷鍵標記，要寫成 && 才會顯示原字（@ 見 escape_label）
+        let labels: Vec<String> = shown
+            .iter()
+            .map(|c| format!("隱藏「{}」", escape_label(c).replace('&', "&&")))
+            .collect();
+        let choices: Vec<&str> = labels.iter().map(String::as_str).collect();
+        let Some(chosen) = MenuItem::new(&choices).popup(app::event_x(), app::event_y()) else {
+            return;
+        };
+        let Some(index) = chosen.label().and_then(|label| labels.iter().position(|l| *l == label)) else {
+            return;
+        };
+        if processor.lock().unwrap().block_candidate(&shown[index]) {
+            gui_needs_update.store(true, Ordering::Relaxed);
+        }
+    }
+
     /// 複製文字到剪貼簿（輔助函數）
     fn copy_to_clipboard(text: &str) {
         if text.is_empty() {
@@ -194,12 +458,13 @@ impl GuiWindow {
     /// 用戶只需要切換回遊戲，按 Ctrl+V 貼上全部文字
     /// 這樣可以避免頻繁切換焦點，更可靠
     fn handle_keyboard_event(
-        w: &mut Window,
+        w: &mut DoubleWindow,
         ev: Event,
         processor: &Arc<Mutex<InputMethodProcessor>>,
         _input_simulator: &Arc<Mutex<InputSimulator>>,
         gui_needs_update: &Arc<AtomicBool>,
-        accumulated_text: &Arc<Mutex<String>>,
+        accumulated_text: &Arc<Mutex<AccumulatedText>>,
+        mode: &ModeState,
     ) -> bool {
         match ev {
             Event::KeyDown => {
@@ -225,45 +490,48 @@ impl GuiWindow {
                     return true; // 已處理
                 }
 
-                // 處理字母鍵（字根輸入）
-                if !key_char.is_empty() {
-                    let ch = key_char.chars().next().unwrap();
-                    if ch.is_ascii_alphabetic() {
-                        let ch_lower = ch.to_ascii_lowercase();
-                        let (success, complement_selected) = {
-                            let mut proc = processor.lock().unwrap();
-                            proc.handle_code_input(ch_lower)
+                // 英模式：可見字元直接累積，不組字（Enter、Backspace、Ctrl 組合鍵照下面處理）
+                if mode.get() == InputMode::English && !app::event_state().contains(fltk::enums::Shortcut::Ctrl) {
+                    if let Some(ch) = key_char.chars().next().filter(|c| !c.is_control()) {
+                        let text_to_copy = {
+                            let mut acc_text = accumulated_text.lock().unwrap();
+                            acc_text.push_commit(ch.encode_utf8(&mut [0; 4]));
+                            acc_text.as_str().to_string()
                         };
-
-                        if success {
-                            if complement_selected.is_some() {
-                                // 補碼選擇，等待 Space 鍵
-                                info!("✅ 補碼選擇候選字（等待 Space 鍵送出）");
-                            }
-                            gui_needs_update.store(true, Ordering::Relaxed);
-                            return true; // 已處理
-                        }
+                        Self::copy_to_clipboard(&text_to_copy);
+                        gui_needs_update.store(true, Ordering::Relaxed);
+                        return true;
                     }
                 }
 
-                // 處理數字鍵（候選字選擇）
+                // 處理選字鍵（候選字選擇）
                 // 使用 event_text() 來檢查字符，因為 FLTK 的 Key 枚舉不直接支持數字鍵
+                // 數字鍵一律當選字鍵；方案的選字鍵不是數字（例如 "asd"）時，組字中有候選才當選字鍵，否則是字根
                 if !key_char.is_empty() {
                     if let Some(ch) = key_char.chars().next() {
-                        // ASCII 數字鍵 → 用來選擇候選字
-                        if ch.is_ascii_digit() {
-                            let num = ch.to_digit(10).unwrap() as u8;
-                            let num_u8 = if num == 0 { 0 } else { num as u8 };
+                        let is_selection = ch.is_ascii_digit() || {
+                            let proc = processor.lock().unwrap();
+                            let state = proc.get_state();
+                            proc.scheme().selection_index(ch).is_some()
+                                && !state.current_code.is_empty()
+                                && !state.get_current_page_candidates().is_empty()
+                        };
+                        if is_selection {
+                            // 補碼選字後按選字鍵只是改選，等 Space 再送出
+                            if processor.lock().unwrap().handle_complement_reselect(ch) {
+                                gui_needs_update.store(true, Ordering::Relaxed);
+                                return true;
+                            }
                             if let Some(text) = {
                                 let mut proc = processor.lock().unwrap();
-                                proc.handle_number_selection(num_u8)
+                                proc.handle_selection_key(ch)
                             } {
                                 // 選擇了候選字，累積到文字緩衝區並自動複製到剪貼簿
                                 let text_to_copy = {
                                     let mut acc_text = accumulated_text.lock().unwrap();
-                                    acc_text.push_str(&text);
-                                    let result = acc_text.clone();
-                                    info!("✅ 選擇候選字 {}: {}，累積文字: {}", num, text, result);
+                                    acc_text.push_commit(&text);
+                                    let result = acc_text.as_str().to_string();
+                                    debug!("✅ 選擇候選字 {}: {}，累積文字: {}", ch, text, result);
                                     result
                                 };
 
@@ -274,13 +542,49 @@ impl GuiWindow {
                                 return true; // 已處理
                             } else {
                                 // 沒有對應的候選字，攔截並忽略該按鍵
-                                debug!("數字鍵 {} 沒有對應的候選字，攔截並忽略", num);
+                                debug!("選字鍵 {} 沒有對應的候選字，攔截並忽略", ch);
                                 return true; // 已處理（攔截）
                             }
                         }
                     }
                 }
 
+                // 處理字母鍵（字根輸入）
+                if !key_char.is_empty() {
+                    let ch = key_char.chars().next().unwrap();
+                    if ch.is_ascii_alphabetic() {
+                        let ch_lower = ch.to_ascii_lowercase();
+                        let (success, complement_selected, auto_committed) = {
+                            let mut proc = processor.lock().unwrap();
+                            let (success, complement_selected) = proc.handle_code_input(ch_lower);
+                            (success, complement_selected, proc.take_auto_commit())
+                        };
+
+                        // 字根打滿且只有一個候選，已自動送出：累積到文字緩衝區並自動複製到剪貼簿
+                        if let Some(text) = auto_committed {
+                            let text_to_copy = {
+                                let mut acc_text = accumulated_text.lock().unwrap();
+                                acc_text.push_commit(&text);
+                                let result = acc_text.as_str().to_string();
+                                debug!("自動送出唯一候選: {}，累積文字: {}", text, result);
+                                result
+                            };
+                            Self::copy_to_clipboard(&text_to_copy);
+                            gui_needs_update.store(true, Ordering::Relaxed);
+                            return true;
+                        }
+
+                        if success {
+                            if complement_selected.is_some() {
+                                // 補碼選擇，等待 Space 鍵
+                                info!("✅ 補碼選擇候選字（等待 Space 鍵送出）");
+                            }
+                            gui_needs_update.store(true, Ordering::Relaxed);
+                            return true; // 已處理
+                        }
+                    }
+                }
+
                 // 處理 Space 鍵（選擇第一個候選字，或清除查不到字的字根）
                 if key == Key::from_char(' ') || key_char == " " {
                     let result = {
@@ -292,9 +596,9 @@ impl GuiWindow {
                         // 有候選字，累積到文字緩衝區並自動複製到剪貼簿
                         let text_to_copy = {
                             let mut acc_text = accumulated_text.lock().unwrap();
-                            acc_text.push_str(&text);
-                            let result = acc_text.clone();
-                            info!("Space: 選擇候選字: {}，累積文字: {}", text, result);
+                            acc_text.push_commit(&text);
+                            let result = acc_text.as_str().to_string();
+                            debug!("Space: 選擇候選字: {}，累積文字: {}", text, result);
                             result
                         };
 
@@ -321,7 +625,7 @@ impl GuiWindow {
                             || !state.candidates.is_empty()
                             || state.complement_selected.is_some()
                         {
-                            info!(
+                            debug!(
                                 "Enter: 清除當前字根與候選: code='{}', candidates={}",
                                 state.current_code,
                                 state.candidates.len()
@@ -355,14 +659,14 @@ impl GuiWindow {
                         gui_needs_update.store(true, Ordering::Relaxed);
                         return true; // 已處理
                     }
-                    // 沒有字根可刪除時，改為刪除「打字區」最後一個字
+                    // 沒有字根可刪除時，改為刪除「打字區」最後一次選的字（詞組整個刪除）
                     {
                         let mut acc_text = accumulated_text.lock().unwrap();
-                        if let Some(ch) = acc_text.pop() {
-                            let remaining = acc_text.clone();
-                            info!(
-                                "Backspace: 刪除累積文字最後一字 '{}', 剩餘: {}",
-                                ch, remaining
+                        if let Some(removed) = acc_text.pop_commit() {
+                            let remaining = acc_text.as_str().to_string();
+                            debug!(
+                                "Backspace: 刪除累積文字最後一次選字 '{}', 剩餘: {}",
+                                removed, remaining
                             );
 
                             // 更新剪貼簿為新的累積文字（如果還有內容）
@@ -384,7 +688,7 @@ impl GuiWindow {
                 {
                     let text_to_copy = {
                         let acc_text = accumulated_text.lock().unwrap();
-                        acc_text.clone()
+                        acc_text.as_str().to_string()
                     };
 
                     if !text_to_copy.is_empty() {
@@ -417,6 +721,28 @@ impl GuiWindow {
                     return false;
                 }
 
+                // 處理 Ctrl+Z / Ctrl+Y（撤回與重做累積文字的修改）
+                if app::event_state().contains(fltk::enums::Shortcut::Ctrl)
+                    && (key == Key::from_char('z') || key == Key::from_char('y'))
+                {
+                    let text_to_copy = {
+                        let mut acc_text = accumulated_text.lock().unwrap();
+                        let changed = if key == Key::from_char('z') { acc_text.undo() } else { acc_text.redo() };
+                        if !changed {
+                            debug!("累積文字沒有可撤回或重做的步驟");
+                            return true; // 已處理（攔截，避免傳到遊戲）
+                        }
+                        acc_text.as_str().to_string()
+                    };
+                    debug!("{}累積文字: {}", if key == Key::from_char('z') { "撤回，" } else { "重做，" }, text_to_copy);
+                    // 剪貼簿跟著更新（與 Backspace 相同，變成空的時不動剪貼簿）
+                    if !text_to_copy.is_empty() {
+                        Self::copy_to_clipboard(&text_to_copy);
+                    }
+                    gui_needs_update.store(true, Ordering::Relaxed);
+                    return true;
+                }
+
                 // 其他 Ctrl 組合鍵，讓它通過
                 if app::event_state().contains(fltk::enums::Shortcut::Ctrl) {
                     return false;
@@ -425,6 +751,32 @@ impl GuiWindow {
                 // 先處理與肥模式一致的符號輸入（例如點號、逗號）
                 if !key_char.is_empty() {
                     if let Some(ch) = key_char.chars().next() {
+                        // 萬用字元 * 與 ?（只記得部分字根時使用），沒有字根時當成一般字元
+                        if matches!(ch, '*' | '?') && processor.lock().unwrap().handle_wildcard_input(ch) {
+                            gui_needs_update.store(true, Ordering::Relaxed);
+                            return true;
+                        }
+                        // 同音字熱鍵 '：沒有字根時列出上一個送出字的同音字
+                        if ch == '\'' && processor.lock().unwrap().show_homophones() {
+                            gui_needs_update.store(true, Ordering::Relaxed);
+                            return true;
+                        }
+                        // emoji 短碼前綴鍵（:smile: 的 :），結尾再按一次時送出第一個候選
+                        let (handled, emoji) = processor.lock().unwrap().handle_emoji_prefix(ch);
+                        if handled {
+                            if let Some(text) = emoji {
+                                let text_to_copy = {
+                                    let mut acc_text = accumulated_text.lock().unwrap();
+                                    acc_text.push_commit(&text);
+                                    let result = acc_text.as_str().to_string();
+                                    debug!("emoji 短碼: 選擇 {}，累積文字: {}", text, result);
+                                    result
+                                };
+                                Self::copy_to_clipboard(&text_to_copy);
+                            }
+                            gui_needs_update.store(true, Ordering::Relaxed);
+                            return true;
+                        }
                         // 只處理 ASCII 符號，避免誤吃已組好的中文字
                         if ch == '.' || ch == ',' {
                             let (success, symbol_selected) = {
@@ -436,7 +788,7 @@ impl GuiWindow {
                                 if let Some(symbol) = symbol_selected {
                                     // 符號映射找到候選，但與肥模式一致：只設定狀態，等待 Space 送出
                                     // 這裡不直接累積文字，避免按一次 '.' 就出現兩次符號
-                                    info!(
+                                    debug!(
                                         "符號輸入 '{}', 映射為 '{}', 等待 Space 送出",
                                         ch, symbol
                                     );
@@ -461,9 +813,9 @@ impl GuiWindow {
                         if !ch.is_control() {
                             let text_to_copy = {
                                 let mut acc_text = accumulated_text.lock().unwrap();
-                                acc_text.push(ch);
-                                let result = acc_text.clone();
-                                info!("直接輸入字元 '{}', 累積文字: {}", ch, result);
+                                acc_text.push_commit(ch.encode_utf8(&mut [0; 4]));
+                                let result = acc_text.as_str().to_string();
+                                debug!("直接輸入字元 '{}', 累積文字: {}", ch, result);
                                 result
                             };
 
@@ -484,8 +836,109 @@ impl GuiWindow {
         }
     }
 
+    /// 嘗試以 overlay 模式顯示，不支援或設定失敗時回傳 false（退回一般視窗）
+    fn try_overlay(hwnd: HWND) -> bool {
+        let support = overlay::check_support();
+        if support != OverlaySupport::Supported {
+            warn!("無法使用 overlay 模式（{}），改用一般視窗", support.reason());
+            return false;
+        }
+        match overlay::apply(hwnd) {
+            Ok(()) => {
+                info!("遊戲模式窗口以 overlay 模式顯示");
+                true
+            }
+            Err(e) => {
+                warn!("設定 overlay 失敗，改用一般視窗: {:#}", e);
+                false
+            }
+        }
+    }
+
+    /// 註冊為 AppBar 並把窗口移到系統給的位置（橫跨整個螢幕寬度）；失敗時維持浮動
+    fn apply_dock(&mut self) {
+        let hwnd = HWND(self.window.raw_handle() as isize);
+        match appbar::dock(hwnd, self.options.dock, self.window.h()) {
+            Ok(rect) => {
+                self.window.resize(rect.left, rect.top, rect.right - rect.left, rect.bottom - rect.top);
+                self.docked = true;
+            }
+            Err(e) => warn!("無法停駐狀態窗，維持浮動: {:#}", e),
+        }
+    }
+
+    /// overlay 顯示期間定期檢查前景視窗，被無邊框全螢幕遊戲蓋住時重新置頂
+    fn tick_overlay(&mut self) {
+        if !self.overlay_active || !self.is_input_mode || self.last_overlay_check.elapsed() < overlay::REASSERT_INTERVAL {
+            return;
+        }
+        self.last_overlay_check = Instant::now();
+        overlay::reassert_topmost(HWND(self.window.raw_handle() as isize));
+    }
+
+    /// 時間到了就把閃爍的底色換回主題底色，回傳是否仍在閃爍
+    fn tick_flash(&mut self) -> bool {
+        let Some(until) = self.flash_until else {
+            return false;
+        };
+        if Instant::now() < until {
+            return true;
+        }
+        self.flash_until = None;
+        let (r, g, b) = self.presentation.theme().background;
+        self.window.set_color(Color::from_rgb(r, g, b));
+        self.window.redraw();
+        false
+    }
+
+    /// 推進淡入淡出動畫，回傳動畫是否仍在進行
+    fn tick_fade(&mut self) -> bool {
+        let Some(fade) = &self.fade else {
+            return false;
+        };
+
+        let to = if fade.hide_when_done {
+            0
+        } else {
+            self.target_alpha.load(Ordering::Relaxed)
+        };
+        let elapsed = fade.started.elapsed();
+        let hide_when_done = fade.hide_when_done;
+        let alpha = fade_alpha(fade.from, to, elapsed, FADE_DURATION);
+        self.apply_alpha(alpha);
+
+        if elapsed < FADE_DURATION {
+            return true;
+        }
+
+        self.fade = None;
+        if hide_when_done {
+            self.window.hide();
+            // 下次顯示時恢復聚焦透明度
+            self.target_alpha.store(ALPHA_FOCUSED, Ordering::Relaxed);
+        }
+        false
+    }
+
+    /// 套用透明度並記錄目前值（動畫中斷時從這裡接續）
+    fn apply_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+        set_window_alpha(&self.window, alpha);
+    }
+
+    /// 只在文字有變化時更新標籤並重繪該元件，避免整窗重繪造成閃爍
+    fn set_frame_label(frame: &mut Frame, label: &str) {
+        let label = escape_label(label);
+        if frame.label() != label {
+            frame.set_label(&label);
+            frame.redraw();
+        }
+    }
+}
+
+impl GuiBackend for StatusWindow {
     /// 顯示窗口
-    pub fn show(&mut self) {
+    fn show(&mut self) -> Result<()> {
         debug!("顯示 GUI 視窗（遊戲模式）");
 
         // 確保窗口可見
@@ -511,49 +964,52 @@ impl GuiWindow {
         info!("💡 提示：選擇候選字後，文字會累積在窗口中，並自動複製到剪貼簿");
         info!("💡 提示：輸入完成後，切換回遊戲按 Ctrl+V 貼上全部文字");
 
-        // 設定透明度與最上層屬性
-        unsafe {
-            let raw = self.window.raw_handle();
-            let hwnd = HWND(raw as isize);
-
-            // 開啟 WS_EX_LAYERED 擴充樣式，才能套用透明度
-            let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
-            let new_ex_style = ex_style | WS_EX_LAYERED.0 as isize;
-            let _ = SetWindowLongPtrW(hwnd, GWL_EXSTYLE, new_ex_style);
-
-            // 將整個窗口 alpha 設為 100（半透明）
-            // 若想要更透明或更不透明，可調整第三個參數 0~255
-            let _ = SetLayeredWindowAttributes(
-                hwnd,
-                COLORREF(0),
-                100, // 0 = 完全透明, 255 = 完全不透明
-                LWA_ALPHA,
-            );
+        let hwnd = HWND(self.window.raw_handle() as isize);
+        self.overlay_active = self.options.overlay && Self::try_overlay(hwnd);
+
+        // 設定透明度與最上層屬性（overlay 模式已在 try_overlay 設定過）
+        if !self.overlay_active {
+            unsafe {
+                // 開啟 WS_EX_LAYERED 擴充樣式，才能套用透明度
+                let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+                let new_ex_style = ex_style | WS_EX_LAYERED.0 as isize;
+                let _ = SetWindowLongPtrW(hwnd, GWL_EXSTYLE, new_ex_style);
+
+                // 嘗試將窗口設為最上層，避免被其他窗口（例如遊戲）遮住
+                let _ = SetWindowPos(
+                    hwnd,
+                    HWND_TOPMOST,
+                    0,
+                    0,
+                    0,
+                    0,
+                    SWP_NOMOVE | SWP_NOSIZE | SWP_SHOWWINDOW,
+                );
+            }
+        }
 
-            // 嘗試將窗口設為最上層，避免被其他窗口（例如遊戲）遮住
-            let _ = SetWindowPos(
-                hwnd,
-                HWND_TOPMOST,
-                0,
-                0,
-                0,
-                0,
-                SWP_NOMOVE | SWP_NOSIZE | SWP_SHOWWINDOW,
-            );
+        if self.options.dock != DockEdge::Floating {
+            self.apply_dock();
         }
 
-        // 更新顯示內容
-        self.update_display();
+        // 淡入到半透明（或直接套用），若正在淡出則從目前透明度接著淡入
+        // 注意：套用 WS_EX_LAYERED 後必須設定一次透明度，否則窗口不會顯示
+        let start = if !self.options.fade {
+            self.target_alpha.load(Ordering::Relaxed)
+        } else if self.fade.is_some() {
+            self.alpha
+        } else {
+            0
+        };
+        self.apply_alpha(start);
+        self.fade = self.options.fade.then(|| Fade {
+            from: start,
+            started: Instant::now(),
+            hide_when_done: false,
+        });
 
-        // 強制重繪
+        // 顯示內容由呼叫端接著以 update 更新（只重繪有變化的元件，由事件循環統一繪製）
         self.window.redraw();
-        self.code_frame.redraw();
-        self.word_frame.redraw();
-
-        // 強制刷新窗口
-        app::flush();
-        let _ = app::check();
-        app::flush();
 
         debug!(
             "GUI 視窗已顯示，位置: ({}, {}), 大小: {}x{}, shown: {}",
@@ -563,10 +1019,11 @@ impl GuiWindow {
             self.window.h(),
             self.window.shown()
         );
+        Ok(())
     }
 
     /// 隱藏窗口
-    pub fn hide(&mut self) {
+    fn hide(&mut self) {
         if self.window.shown() {
             // 清除輸入狀態
             let mut proc = self.processor.lock().unwrap();
@@ -576,105 +1033,239 @@ impl GuiWindow {
             // 用戶可以手動按 Enter 清除，或下次打開窗口時自動清除
             let acc_text = self.accumulated_text.lock().unwrap();
             if !acc_text.is_empty() {
-                info!(
+                debug!(
                     "💡 提示：累積的文字 '{}' 仍在剪貼簿中，可以在遊戲中按 Ctrl+V 貼上",
-                    acc_text
+                    acc_text.as_str()
                 );
             }
             drop(acc_text);
 
             self.gui_needs_update.store(true, Ordering::Relaxed);
 
-            self.window.hide();
+            // 把停駐佔用的空間還給桌面
+            if self.docked {
+                appbar::undock(HWND(self.window.raw_handle() as isize));
+                self.docked = false;
+            }
+
             self.is_input_mode = false;
+            if self.options.fade {
+                // 沒有動畫時，窗口的透明度就是焦點事件設定的目標透明度
+                let from = if self.fade.is_some() {
+                    self.alpha
+                } else {
+                    self.target_alpha.load(Ordering::Relaxed)
+                };
+                // 淡出完成後才真正隱藏（見 tick_fade）
+                self.fade = Some(Fade { from, started: Instant::now(), hide_when_done: true });
+            } else {
+                self.fade = None;
+                self.window.hide();
+            }
             info!("遊戲模式窗口已隱藏，停止接收鍵盤輸入");
         }
     }
 
-    /// 更新顯示（根據處理器狀態更新字根和候選字顯示）
-    pub fn update_display(&mut self) {
-        let processor = self.processor.lock().unwrap();
-        let state = processor.get_state();
-
-        // 更新字根顯示（類似 Python 的 type_label_set_text）
-        if state.current_code.is_empty() {
-            // 沒有字根時顯示提示文字，避免視覺上像是「什麼都沒出現」
-            self.code_frame.set_label("輸入字根...");
-        } else {
-            self.code_frame.set_label(&state.current_code);
-        }
-
-        // 更新候選字顯示（類似 Python 的 word_label_set_text）
-        let candidates = &state.candidates;
-        if candidates.is_empty() {
-            self.word_frame.set_label("");
-        } else {
-            let start_idx = state.candidate_index;
-            let end_idx = (start_idx + 6).min(candidates.len());
-
-            let mut labels = Vec::new();
-            for i in start_idx..end_idx {
-                let candidate = &candidates[i];
-                if i == start_idx && state.complement_selected.is_none() {
-                    labels.push(format!("{} (Space)", candidate));
-                } else {
-                    labels.push(format!("{}", candidate));
-                }
-            }
 
-            // 如果有補碼選擇的候選字，顯示在第一個位置
-            if let Some(ref selected) = state.complement_selected {
-                self.word_frame.set_label(&format!("{} (Space)", selected));
-            } else {
-                self.word_frame.set_label(&labels.join(" "));
-            }
-        }
+    /// 更新字根、候選字與累積文字的顯示
+    fn update(&mut self, view: &StatusView) {
+        // 字根顯示（類似 Python 的 type_label_set_text）
+        Self::set_frame_label(&mut self.code_frame, &view.code);
+        // 候選字顯示（類似 Python 的 word_label_set_text），沒有候選時借用來顯示簡碼提示
+        let words = if view.candidates.is_empty() { &view.tip } else { &view.candidates };
+        Self::set_frame_label(&mut self.word_frame, words);
 
         // 更新累積文字顯示
         let acc_text = self.accumulated_text.lock().unwrap();
-        let acc_text_str = acc_text.clone();
+        let acc_text_str = acc_text.as_str().to_string();
         drop(acc_text);
 
-        if acc_text_str.is_empty() {
-            self.accumulated_text_frame
-                .set_label("待貼上文字將顯示在這裡... (已自動複製到剪貼簿，Enter 清除)");
+        if !view.composition.is_empty() {
+            // 鍵盤鉤子的整句組字（compose_sentence）借用同一欄顯示
+            Self::set_frame_label(
+                &mut self.accumulated_text_frame,
+                &format!("整句: {} (Enter 送出，Backspace 刪除，Esc 清除)", view.composition),
+            );
+        } else if !view.hints.is_empty() {
+            // 組字中的補全提示也借用這一欄，送出後恢復顯示累積文字
+            Self::set_frame_label(&mut self.accumulated_text_frame, &format!("延伸: {}", view.hints));
+        } else if acc_text_str.is_empty() {
+            Self::set_frame_label(
+                &mut self.accumulated_text_frame,
+                "待貼上文字將顯示在這裡... (已自動複製到剪貼簿，Enter 清除)",
+            );
         } else {
-            self.accumulated_text_frame.set_label(&format!(
-                "待貼上: {} (已自動複製到剪貼簿，切換回遊戲按 Ctrl+V 貼上，Enter 清除)",
-                acc_text_str
-            ));
+            Self::set_frame_label(
+                &mut self.accumulated_text_frame,
+                &format!(
+                    "待貼上: {} (已自動複製到剪貼簿，切換回遊戲按 Ctrl+V 貼上，Enter 清除)",
+                    acc_text_str
+                ),
+            );
         }
 
-        // 強制重繪累積文字顯示框
-        self.accumulated_text_frame.redraw();
+        debug!("GUI 窗口更新：字根='{}', 候選='{}', 累積文字='{}'", view.code, view.candidates, acc_text_str);
+    }
 
-        debug!(
-            "GUI 窗口更新：字根='{}', 候選字數量={}, 累積文字='{}'",
-            state.current_code,
-            candidates.len(),
-            acc_text_str
-        );
+    /// 套用展示用版面設定（立即生效），窗口以右下角為基準縮放
+    fn set_theme(&mut self, presentation: Presentation) {
+        if self.presentation == presentation {
+            return;
+        }
+        self.presentation = presentation;
+        let layout = presentation.layout();
+        let theme = presentation.theme();
+        let rgb = |(r, g, b): (u8, u8, u8)| Color::from_rgb(r, g, b);
+
+        let right = self.window.x() + self.window.w();
+        let bottom = self.window.y() + self.window.h();
+        self.window.resize(right - layout.width, bottom - layout.height, layout.width, layout.height);
+        // 停駐中時依新的高度重新排位置
+        if self.docked {
+            self.apply_dock();
+        }
+        self.window.set_color(rgb(theme.background));
+
+        let (x, y, w, h) = layout.code;
+        self.code_frame.resize(x, y, w, h);
+        self.code_frame.set_label_size(layout.code_size);
+        self.code_frame.set_label_color(rgb(theme.text));
+        self.code_frame.set_color(rgb(theme.background));
+
+        let (x, y, w, h) = layout.word;
+        self.word_frame.resize(x, y, w, h);
+        self.word_frame.set_label_size(layout.word_size);
+        self.word_frame.set_label_color(rgb(theme.text));
+        self.word_frame.set_color(rgb(theme.background));
+        // 直向時一行一個候選，從上方開始排
+        let word_align = if presentation.vertical {
+            Align::Left | Align::Inside | Align::Top
+        } else {
+            Align::Left | Align::Inside
+        };
+        self.word_frame.set_align(word_align);
+
+        let (x, y, w, h) = layout.accumulated;
+        self.accumulated_text_frame.resize(x, y, w, h);
+        self.accumulated_text_frame.set_label_size(layout.accumulated_size);
+        self.accumulated_text_frame.set_label_color(rgb(theme.accumulated_text));
+        self.accumulated_text_frame.set_color(rgb(theme.accumulated_background));
+
+        // 候選分隔改變時由呼叫端以新的版面重新產生內容再 update
+        self.window.redraw();
     }
 
-    /// 強制刷新顯示（不立即 flush，讓事件循環處理）
-    pub fn redraw(&mut self) {
+    /// 設定淡入淡出、overlay 與停駐位置（下次顯示時生效）
+    fn configure(&mut self, options: WindowOptions) {
+        self.options = options;
+        self.accumulated_text.lock().unwrap().set_undo_limit(options.undo_limit);
+    }
+
+    /// 推進淡入淡出與閃爍動畫並在 overlay 模式下定期重新置頂，回傳動畫是否仍在進行
+    fn tick(&mut self) -> bool {
+        self.tick_overlay();
+        let flashing = self.tick_flash();
+        self.tick_fade() || flashing
+    }
+
+    /// 把底色短暫換成警示色，由 tick_flash 換回
+    fn flash(&mut self) {
+        let (r, g, b) = FLASH_COLOR;
+        self.window.set_color(Color::from_rgb(r, g, b));
         self.window.redraw();
-        self.code_frame.redraw();
-        self.word_frame.redraw();
-        self.accumulated_text_frame.redraw();
-        // 不立即 flush，讓事件循環統一處理，避免頻繁刷新導致延遲
+        self.flash_until = Some(Instant::now() + FLASH_DURATION);
+    }
+
+    /// 加入一段累積文字並複製整段到剪貼簿（與選字相同）
+    fn append_text(&mut self, text: &str) {
+        let text_to_copy = {
+            let mut acc_text = self.accumulated_text.lock().unwrap();
+            acc_text.push_commit(text);
+            acc_text.as_str().to_string()
+        };
+        Self::copy_to_clipboard(&text_to_copy);
+    }
+
+    /// 清除累積文字（剪貼簿不動），原本就是空的時回傳 false
+    fn clear_text(&mut self) -> bool {
+        let mut acc_text = self.accumulated_text.lock().unwrap();
+        if acc_text.is_empty() {
+            return false;
+        }
+        acc_text.clear();
+        true
     }
 }
 
 /// GUI 窗口管理器
 pub struct GuiWindowManager {
-    window: Option<GuiWindow>,
+    /// 狀態窗（第一次顯示時建立；核心只透過 GuiBackend 操作）
+    window: Option<Box<dyn GuiBackend>>,
     processor: Arc<Mutex<InputMethodProcessor>>,
     input_simulator: Arc<Mutex<InputSimulator>>,
     gui_needs_update: Arc<AtomicBool>,
     visible: bool, // 自行追蹤可見狀態，避免依賴底層 shown() 行為
     gui_visible_flag: Arc<AtomicBool>,
     gui_has_focus_flag: Arc<AtomicBool>,
+    /// 狀態窗的淡入淡出、overlay 與停駐位置設定（窗口重建時沿用）
+    options: WindowOptions,
+    /// 字根查詢視窗（第一次開啟時建立）
+    lookup_window: Option<LookupWindow>,
+    /// 停用字管理視窗（第一次開啟時建立）
+    blocklist_window: Option<BlocklistWindow>,
+    keyboard_window: Option<KeyboardWindow>,
+    /// 符號面板（第一次開啟時建立）
+    symbol_panel: Option<SymbolPanel>,
+    /// 符號面板列出的符號表（與符號選單相同）
+    symbol_table: SymbolTable,
+    /// 說明視窗（第一次開啟時建立）
+    help_window: Option<HelpWindow>,
+    /// 學習曲線視窗（第一次開啟時建立）
+    learning_window: Option<LearningWindow>,
+    /// 螢幕鍵盤的佈局檔（None 表示內建佈局）
+    keyboard_layout: Option<PathBuf>,
+    /// 查詢視窗預設是否顯示 Unicode 名稱
+    lookup_unicode_names: bool,
+    /// 候選後標註所有來源
+    show_candidate_source: bool,
+    /// 在遊戲中按 Ctrl+V 貼上後自動清除累積文字
+    clear_after_paste: bool,
+    /// 英/肥模式（與 AppState 共用）
+    mode: Arc<ModeState>,
+    /// 展示用版面設定（窗口重建時沿用）
+    presentation: Presentation,
+    /// 目前使用者的巨集（命名新巨集時寫入，由鍵盤鉤子執行緒存檔）
+    macros: Arc<Mutex<MacroBook>>,
+    /// 學習曲線紀錄（與 AppState 共用）
+    learning: Arc<LearningTracker>,
+    /// 按鍵回放視窗
+    key_trace_window: Option<KeyTraceWindow>,
+    /// 最近按鍵的處理紀錄（與 AppState 共用）
+    key_trace: Arc<KeyTrace>,
+    /// 開發者用的狀態檢視視窗
+    inspector_window: Option<InspectorWindow>,
+    /// 狀態檢視的資料來源（第一次開啟視窗時交給視窗）
+    inspector: Option<Inspector>,
+    /// 長文分段送出的進度視窗（第一次分段送出時建立）
+    long_paste_window: Option<LongPasteWindow>,
+    /// 迷你候選浮窗（第一次組字時建立）
+    mini_float: Option<MiniFloat>,
+    /// 迷你候選浮窗的位置（Off 表示不使用）
+    mini_float_position: MiniFloatPosition,
+    /// 分離的候選窗（第一次有候選時建立）
+    candidate_window: Option<CandidateWindow>,
+    /// 候選窗的位置（Off 表示候選顯示在狀態窗裡）
+    candidate_window_position: CandidateWindowPosition,
+    /// 候選窗的版面設定（展示熱鍵只調整狀態窗）
+    candidate_presentation: Presentation,
+    /// 工作列旁的模式浮標（第一次更新時建立）
+    mode_badge: Option<ModeBadge>,
+    /// 模式浮標的位置（Off 表示不使用）
+    mode_badge_position: BadgePosition,
+    /// 點擊模式浮標時設定，由鍵盤鉤子執行緒切換模式（與 AppState 共用）
+    mode_toggle_request: Arc<AtomicBool>,
+    /// 訂閱輸入事件，組字狀態有變化時要求重繪（輸入處理器只保留弱引用）
+    _input_observer: Arc<dyn InputObserver>,
 }
 
 impl GuiWindowManager {
@@ -686,6 +1277,8 @@ impl GuiWindowManager {
         gui_visible_flag: Arc<AtomicBool>,
         gui_has_focus_flag: Arc<AtomicBool>,
     ) -> Self {
+        let input_observer: Arc<dyn InputObserver> = Arc::new(FlagObserver(gui_needs_update.clone()));
+        processor.lock().unwrap().subscribe(&input_observer);
         Self {
             window: None,
             processor,
@@ -694,26 +1287,404 @@ impl GuiWindowManager {
             visible: false,
             gui_visible_flag,
             gui_has_focus_flag,
+            options: WindowOptions::default(),
+            lookup_window: None,
+            blocklist_window: None,
+            keyboard_window: None,
+            symbol_panel: None,
+            symbol_table: SymbolTable::builtin(),
+            help_window: None,
+            learning_window: None,
+            keyboard_layout: None,
+            lookup_unicode_names: true,
+            show_candidate_source: false,
+            clear_after_paste: true,
+            mode: Arc::new(ModeState::default()),
+            presentation: Presentation::default(),
+            macros: Arc::new(Mutex::new(MacroBook::default())),
+            learning: Arc::new(LearningTracker::default()),
+            key_trace_window: None,
+            key_trace: Arc::new(KeyTrace::new(0)),
+            inspector_window: None,
+            inspector: None,
+            long_paste_window: None,
+            mini_float: None,
+            mini_float_position: MiniFloatPosition::Off,
+            candidate_window: None,
+            candidate_window_position: CandidateWindowPosition::Off,
+            candidate_presentation: Presentation::default(),
+            mode_badge: None,
+            mode_badge_position: BadgePosition::Off,
+            mode_toggle_request: Arc::new(AtomicBool::new(false)),
+            _input_observer: input_observer,
+        }
+    }
+
+    /// 設定命名新巨集時要寫入的巨集集合（與 AppState 共用）
+    pub fn set_macros(&mut self, macros: Arc<Mutex<MacroBook>>) {
+        self.macros = macros;
+    }
+
+    /// 設定學習曲線紀錄（與 AppState 共用）
+    pub fn set_learning(&mut self, learning: Arc<LearningTracker>) {
+        self.learning = learning;
+    }
+
+    /// 設定按鍵回放紀錄（與 AppState 共用）
+    pub fn set_key_trace(&mut self, key_trace: Arc<KeyTrace>) {
+        self.key_trace = key_trace;
+    }
+
+    /// 設定狀態檢視的資料來源（AppState 建好後設定）
+    pub fn set_inspector(&mut self, inspector: Inspector) {
+        self.inspector = Some(inspector);
+    }
+
+    /// 設定英/肥模式的共用狀態（與 AppState 共用，需在第一次顯示窗口前設定）
+    pub fn set_mode_state(&mut self, mode: Arc<ModeState>) {
+        self.mode = mode;
+    }
+
+    /// 設定點擊模式浮標時要設定的旗標（與 AppState 共用）
+    pub fn set_mode_toggle_request(&mut self, request: Arc<AtomicBool>) {
+        self.mode_toggle_request = request;
+    }
+
+    /// 設定查詢視窗預設是否顯示 Unicode 名稱
+    pub fn set_lookup_unicode_names(&mut self, enabled: bool) {
+        self.lookup_unicode_names = enabled;
+    }
+
+    /// 顯示字根查詢視窗
+    pub fn show_lookup(&mut self) {
+        let processor = self.processor.clone();
+        let with_name = self.lookup_unicode_names;
+        self.lookup_window
+            .get_or_insert_with(|| LookupWindow::new(processor, with_name))
+            .show();
+    }
+
+    /// 查碼：讀取剪貼簿的文字，在字根查詢視窗逐字列出字根（剪貼簿沒有文字時只開啟視窗）
+    pub fn lookup_clipboard(&mut self) {
+        let text = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("無法讀取剪貼簿，只開啟查詢視窗: {}", e);
+                String::new()
+            }
+        };
+        self.lookup_text(&text);
+    }
+
+    /// 選取反查：模擬 Ctrl+C 取得前景程式選取的文字（之後還原剪貼簿），在字根查詢視窗逐字列出字根
+    /// 沒有選取文字時不開啟視窗
+    pub fn lookup_selection(&mut self) {
+        match selection::copy_selection() {
+            Ok(Some(text)) => self.lookup_text(&text),
+            Ok(None) => info!("前景程式沒有選取文字，略過選取反查"),
+            Err(e) => warn!("無法取得選取的文字: {:#}", e),
+        }
+    }
+
+    /// 以 text 開啟字根查詢視窗
+    fn lookup_text(&mut self, text: &str) {
+        // 不小心選取了一整篇文章時只查開頭
+        let text: String = text.trim().chars().take(LOOKUP_CLIPBOARD_MAX_CHARS).collect();
+        let processor = self.processor.clone();
+        let with_name = self.lookup_unicode_names;
+        self.lookup_window
+            .get_or_insert_with(|| LookupWindow::new(processor, with_name))
+            .show_query(&text);
+    }
+
+    /// 長文分段送出：把剪貼簿的文字交給輸入模擬器分段送到前景程式，並顯示進度視窗
+    pub fn start_long_paste(&mut self) {
+        let text = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("無法讀取剪貼簿，略過分段送出: {}", e);
+                return;
+            }
+        };
+        if text.trim().is_empty() {
+            info!("剪貼簿沒有文字，略過分段送出");
+            return;
+        }
+        self.input_simulator.lock().unwrap().start_long_paste(&text);
+        let simulator = self.input_simulator.clone();
+        self.long_paste_window
+            .get_or_insert_with(|| LongPasteWindow::new(simulator))
+            .show();
+    }
+
+    /// 分段送出期間更新進度，送完時關閉視窗
+    pub fn tick_long_paste(&mut self) {
+        if let Some(window) = &mut self.long_paste_window {
+            window.refresh();
+        }
+    }
+
+    /// 顯示停用字管理視窗
+    pub fn show_blocklist(&mut self) {
+        let processor = self.processor.clone();
+        self.blocklist_window
+            .get_or_insert_with(|| BlocklistWindow::new(processor))
+            .show();
+    }
+
+    /// 設定螢幕鍵盤的佈局檔（None 表示內建佈局），下次開啟時生效
+    pub fn set_keyboard_layout(&mut self, path: Option<PathBuf>) {
+        self.keyboard_layout = path;
+        self.keyboard_window = None;
+    }
+
+    /// 顯示螢幕鍵盤（佈局檔讀取失敗時警告並改用內建佈局）
+    pub fn show_keyboard(&mut self) {
+        if self.keyboard_window.is_none() {
+            let layout = match &self.keyboard_layout {
+                Some(path) => KeyboardLayout::load(path).unwrap_or_else(|e| {
+                    warn!("{:#}，改用內建鍵盤佈局", e);
+                    KeyboardLayout::builtin()
+                }),
+                None => KeyboardLayout::builtin(),
+            };
+            self.keyboard_window = Some(KeyboardWindow::new(layout, self.processor.clone()));
+        }
+        if let Some(window) = &mut self.keyboard_window {
+            window.show();
+        }
+    }
+
+    /// 設定符號面板的符號表，下次開啟時生效
+    pub fn set_symbol_table(&mut self, table: SymbolTable) {
+        self.symbol_table = table;
+        self.symbol_panel = None;
+    }
+
+    /// 顯示符號面板
+    pub fn show_symbol_panel(&mut self) {
+        let simulator = self.input_simulator.clone();
+        self.symbol_panel
+            .get_or_insert_with(|| SymbolPanel::new(&self.symbol_table, simulator))
+            .show();
+    }
+
+    /// 顯示說明視窗
+    pub fn show_help(&mut self) {
+        let processor = self.processor.clone();
+        let (macros, input_simulator) = (self.macros.clone(), self.input_simulator.clone());
+        self.help_window
+            .get_or_insert_with(|| HelpWindow::new(processor, macros, input_simulator))
+            .show();
+    }
+
+    /// 顯示學習曲線視窗
+    pub fn show_learning(&mut self) {
+        let learning = self.learning.clone();
+        self.learning_window
+            .get_or_insert_with(|| LearningWindow::new(learning))
+            .show();
+    }
+
+    /// 顯示按鍵回放視窗
+    pub fn show_key_trace(&mut self) {
+        let key_trace = self.key_trace.clone();
+        self.key_trace_window
+            .get_or_insert_with(|| KeyTraceWindow::new(key_trace))
+            .show();
+    }
+
+    /// 按鍵回放視窗開著時更新列表
+    pub fn tick_key_trace(&mut self) {
+        if let Some(window) = &mut self.key_trace_window {
+            window.refresh();
+        }
+    }
+
+    /// 顯示狀態檢視視窗
+    pub fn show_inspector(&mut self) {
+        if self.inspector_window.is_none() {
+            let Some(inspector) = self.inspector.take() else {
+                warn!("狀態檢視還沒有資料來源，無法開啟");
+                return;
+            };
+            self.inspector_window = Some(InspectorWindow::new(inspector));
+        }
+        if let Some(window) = &mut self.inspector_window {
+            window.show();
+        }
+    }
+
+    /// 狀態檢視視窗開著時每秒更新
+    pub fn tick_inspector(&mut self) {
+        if let Some(window) = &mut self.inspector_window {
+            window.refresh();
         }
     }
 
+    /// 設定是否使用淡入淡出效果
+    pub fn set_fade_enabled(&mut self, enabled: bool) {
+        self.options.fade = enabled;
+        self.configure_window();
+    }
+
+    /// 設定累積文字最多可撤回幾步（0 表示停用 Ctrl+Z / Ctrl+Y）
+    pub fn set_undo_limit(&mut self, limit: usize) {
+        self.options.undo_limit = limit;
+        self.configure_window();
+    }
+
+    /// 設定是否使用 overlay 模式
+    pub fn set_overlay_enabled(&mut self, enabled: bool) {
+        self.options.overlay = enabled;
+        self.configure_window();
+    }
+
+    /// 設定狀態窗停駐位置（浮動或螢幕頂端、底部）
+    pub fn set_dock(&mut self, dock: DockEdge) {
+        self.options.dock = dock;
+        self.configure_window();
+    }
+
+    /// 把視窗行為設定套用到已建立的狀態窗
+    fn configure_window(&mut self) {
+        if let Some(ref mut window) = self.window {
+            window.configure(self.options);
+        }
+    }
+
+    /// 設定迷你候選浮窗的位置（Off 表示不使用）
+    pub fn set_mini_float(&mut self, position: MiniFloatPosition) {
+        self.mini_float_position = position;
+        self.mini_float = None;
+    }
+
+    /// 設定候選窗的位置與版面（Off 表示候選顯示在狀態窗裡）
+    pub fn set_candidate_window(&mut self, position: CandidateWindowPosition, presentation: Presentation) {
+        self.candidate_window_position = position;
+        self.candidate_presentation = presentation;
+        self.candidate_window = None;
+        self.gui_needs_update.store(true, Ordering::Relaxed);
+    }
+
+    /// 候選窗顯示 view（None 時隱藏），候選沒有分離時略過
+    fn update_candidate_window(&mut self, view: Option<&StatusView>) {
+        if self.candidate_window_position == CandidateWindowPosition::Off {
+            return;
+        }
+        if view.is_none() && self.candidate_window.is_none() {
+            return;
+        }
+        let (position, presentation) = (self.candidate_window_position, self.candidate_presentation);
+        self.candidate_window
+            .get_or_insert_with(|| CandidateWindow::new(position, presentation))
+            .update(view);
+    }
+
+    /// 設定模式浮標的位置（Off 表示不使用）
+    pub fn set_mode_badge(&mut self, position: BadgePosition) {
+        self.mode_badge_position = position;
+        self.mode_badge = None;
+    }
+
+    /// 讓模式浮標跟上目前的英/肥模式（托盤、Shift 或浮標本身切換後都會反映），不使用浮標時略過
+    pub fn tick_mode_badge(&mut self) {
+        if self.mode_badge_position == BadgePosition::Off {
+            return;
+        }
+        let (position, request) = (self.mode_badge_position, self.mode_toggle_request.clone());
+        self.mode_badge
+            .get_or_insert_with(|| ModeBadge::new(position, request))
+            .update(self.mode.get());
+    }
+
+    /// 迷你浮窗顯示 text（None 時隱藏），不使用浮窗時略過
+    fn update_mini_float(&mut self, text: Option<&str>) {
+        if self.mini_float_position == MiniFloatPosition::Off {
+            return;
+        }
+        if text.is_none() && self.mini_float.is_none() {
+            return;
+        }
+        let position = self.mini_float_position;
+        self.mini_float.get_or_insert_with(|| MiniFloat::new(position)).update(text);
+    }
+
+    /// 設定是否在候選後標註所有來源（主字碼表以外的使用者字典、學習、額外層）
+    pub fn set_show_candidate_source(&mut self, enabled: bool) {
+        self.show_candidate_source = enabled;
+        self.gui_needs_update.store(true, Ordering::Relaxed);
+    }
+
+    /// 設定在遊戲中按 Ctrl+V 貼上後是否自動清除累積文字
+    pub fn set_clear_after_paste(&mut self, enabled: bool) {
+        self.clear_after_paste = enabled;
+    }
+
+    /// 使用者在其他視窗按了 Ctrl+V：已貼上累積文字，清掉避免下次連舊句子一起貼上
+    pub fn on_pasted_elsewhere(&mut self) {
+        if !self.clear_after_paste {
+            return;
+        }
+        if let Some(ref mut window) = self.window {
+            if window.clear_text() {
+                info!("已在遊戲中貼上，清除累積文字");
+                self.gui_needs_update.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// 把其他軟體送出的文字加入累積文字（窗口還沒建立時略過）
+    pub fn append_accumulated_text(&mut self, text: &str) {
+        if let Some(ref mut window) = self.window {
+            window.append_text(text);
+            self.gui_needs_update.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// 按了無效的鍵：狀態窗顯示中才閃一下
+    pub fn flash(&mut self) {
+        if !self.visible {
+            return;
+        }
+        if let Some(ref mut window) = self.window {
+            window.flash();
+        }
+    }
+
+    /// 套用展示熱鍵動作
+    pub fn apply_presentation(&mut self, action: PresentationAction) {
+        if !self.presentation.apply(action) {
+            return;
+        }
+        info!("展示版面: {:?}", self.presentation);
+        if let Some(ref mut window) = self.window {
+            window.set_theme(self.presentation);
+        }
+        self.update_display();
+    }
+
     /// 顯示 GUI 窗口
     pub fn show(&mut self) -> Result<()> {
         if self.window.is_none() {
-            let window = GuiWindow::new(
+            let window = StatusWindow::new(
                 self.processor.clone(),
                 self.input_simulator.clone(),
                 self.gui_needs_update.clone(),
                 self.gui_visible_flag.clone(),
                 self.gui_has_focus_flag.clone(),
+                self.mode.clone(),
             )?;
-            self.window = Some(window);
+            self.window = Some(Box::new(window));
         }
 
         if let Some(ref mut window) = self.window {
-            window.show();
+            window.configure(self.options);
+            window.set_theme(self.presentation);
+            window.show()?;
             // 注意：焦點狀態由 FLTK 自動管理，不需要手動設置
         }
+        self.update_display();
         // 標記為可見
         self.visible = true;
         self.gui_visible_flag.store(true, Ordering::Relaxed);
@@ -727,6 +1698,8 @@ impl GuiWindowManager {
             window.hide();
             // 注意：焦點狀態由 FLTK 自動管理，窗口隱藏時會自動失去焦點
         }
+        self.update_mini_float(None);
+        self.update_candidate_window(None);
         // 標記為不可見
         self.visible = false;
         self.gui_visible_flag.store(false, Ordering::Relaxed);
@@ -735,14 +1708,34 @@ impl GuiWindowManager {
 
     /// 更新顯示
     pub fn update_display(&mut self) {
-        if let Some(ref mut window) = self.window {
-            window.update_display();
-            window.redraw();
-            // 觸發一次 flush，確保顯示更新
-            fltk::app::flush();
-            // 再次檢查並處理事件，確保重繪完成
-            let _ = fltk::app::check();
-            fltk::app::flush();
+        let Some(ref mut window) = self.window else {
+            return;
+        };
+        let view = {
+            let processor = self.processor.lock().unwrap();
+            StatusView::build(&processor, self.mode.get(), &self.presentation, self.show_candidate_source)
+        };
+        // 候選分離時狀態窗只留字根與整句組字，候選窗以自己的版面重新產生內容
+        let candidate_view = if self.candidate_window_position == CandidateWindowPosition::Off {
+            window.update(&view);
+            None
+        } else {
+            window.update(&view.without_candidates());
+            let processor = self.processor.lock().unwrap();
+            let split = StatusView::build(&processor, self.mode.get(), &self.candidate_presentation, self.show_candidate_source);
+            Some(split).filter(|split| self.visible && !(split.candidates.is_empty() && split.hints.is_empty()))
+        };
+        // 迷你浮窗與候選窗和狀態窗同步，狀態窗隱藏時不顯示
+        let mini = view.mini.as_deref().filter(|_| self.visible);
+        self.update_mini_float(mini);
+        self.update_candidate_window(candidate_view.as_ref());
+    }
+
+    /// 推進淡入淡出動畫（overlay 模式下也定期重新置頂），回傳動畫是否仍在進行
+    pub fn tick_animation(&mut self) -> bool {
+        match self.window {
+            Some(ref mut window) => window.tick(),
+            None => false,
         }
     }
 
@@ -750,17 +1743,217 @@ impl GuiWindowManager {
     pub fn is_visible(&self) -> bool {
         self.visible
     }
+}
 
-    /// 檢查窗口是否有焦點（從實際窗口讀取，確保準確）
-    pub fn has_focus(&self) -> bool {
-        // 從實際窗口讀取焦點狀態，直接調用 GuiWindow 的方法
-        // 這樣可以確保焦點狀態是準確的，不會有緩存不同步的問題
-        if let Some(ref window) = self.window {
-            window.window.has_focus()
-        } else {
-            false
+/// 詢問是否把多行文字送到終端機，確認後等終端機重新取得焦點再送出
+fn confirm_terminal_send(simulator: &Mutex<InputSimulator>, text: &str) -> Result<()> {
+    let lines = text.lines().count();
+    let message = format!("要把 {} 行文字送到終端機嗎？終端機可能會逐行執行。\n\n{}", lines, text);
+    if dialog::choice2_default(&message, "取消", "送出", "") != Some(1) {
+        info!("使用者取消送出多行文字到終端機");
+        return Ok(());
+    }
+    std::thread::sleep(CONFIRM_REFOCUS_DELAY);
+    simulator.lock().unwrap().send_text(text)?;
+    info!("已送出多行文字到終端機（{} 行）", lines);
+    Ok(())
+}
+
+/// 詢問新巨集的名稱與熱鍵後存入巨集集合（取消或名稱空白時不儲存）
+fn name_macro(macros: &Mutex<MacroBook>, actions: &[MacroAction]) -> Result<()> {
+    let prompt = format!("巨集已錄製（{} 個動作），請輸入名稱：", actions.len());
+    let Some(name) = dialog::input_default(&prompt, "").filter(|name| !name.trim().is_empty()) else {
+        info!("取消儲存巨集");
+        return Ok(());
+    };
+    let default_slot = macros.lock().unwrap().free_slot().map(|slot| slot.to_string()).unwrap_or_default();
+    let slot = dialog::input_default("綁定熱鍵 Ctrl+Alt+數字（1-9，留空不綁定）：", &default_slot)
+        .and_then(|text| text.trim().parse::<u8>().ok())
+        .filter(|slot| MACRO_SLOTS.contains(slot));
+    let name = name.trim().to_string();
+    info!("儲存巨集 '{}'（熱鍵 {:?}）", name, slot);
+    macros.lock().unwrap().insert(Macro { name, slot, actions: actions.to_vec() });
+    Ok(())
+}
+
+/// 詢問新的日誌過濾規則並套用（取消或空白時不變，規則有誤時提示）
+fn edit_log_filter() -> Result<()> {
+    let prompt = "日誌過濾規則（例如 info,uclliu::keyboard_hook=trace）：";
+    let Some(directives) = dialog::input_default(prompt, &logging::current_filter()) else {
+        return Ok(());
+    };
+    if directives.trim().is_empty() {
+        return Ok(());
+    }
+    if let Err(e) = logging::set_filter(&directives) {
+        dialog::alert_default(&format!("{:#}", e));
+    }
+    Ok(())
+}
+
+/// 運行 GUI 執行緒的 fltk 事件循環（在主執行緒上呼叫）
+/// 處理 fltk 事件、其他執行緒送來的 GuiCommand，以及輸入變化時的重繪
+/// should_quit 設定後返回
+pub fn run_event_loop(
+    manager: &Arc<Mutex<GuiWindowManager>>,
+    commands: &Receiver<GuiCommand>,
+    gui_needs_update: &AtomicBool,
+    should_quit: &AtomicBool,
+) -> Result<()> {
+    // 初始化 fltk（GUI 只在主執行緒上操作）
+    let _app = app::App::default();
+
+    loop {
+        if should_quit.load(Ordering::Relaxed) {
+            info!("GUI 執行緒收到退出信號");
+            break;
+        }
+
+        // 等待 fltk 事件（最多 10ms），沒有事件時也會定期返回處理命令與重繪
+        let _ = app::wait_for(0.01);
+
+        // 處理其他執行緒送來的命令
+        loop {
+            match commands.try_recv() {
+                Ok(command) => {
+                    debug!("GUI 執行緒收到命令: {:?}", command);
+                    let mut manager = manager.lock().unwrap();
+                    let result = match &command {
+                        GuiCommand::Toggle if manager.is_visible() => {
+                            info!("隱藏遊戲模式窗口");
+                            manager.hide();
+                            Ok(())
+                        }
+                        GuiCommand::Toggle => {
+                            info!("顯示遊戲模式窗口");
+                            manager.show()
+                        }
+                        GuiCommand::ShowMessage(title, text) => {
+                            // 訊息視窗會阻塞 GUI 執行緒直到關閉，先放開 manager 的鎖
+                            drop(manager);
+                            dialog::message_title(title);
+                            dialog::message_default(text);
+                            Ok(())
+                        }
+                        GuiCommand::ShowLookup => {
+                            info!("顯示字根查詢視窗");
+                            manager.show_lookup();
+                            Ok(())
+                        }
+                        GuiCommand::LookupClipboard => {
+                            info!("查碼：反查剪貼簿的文字");
+                            manager.lookup_clipboard();
+                            Ok(())
+                        }
+                        GuiCommand::LookupSelection => {
+                            info!("選取反查：反查前景程式選取的文字");
+                            manager.lookup_selection();
+                            Ok(())
+                        }
+                        GuiCommand::LongPaste => {
+                            info!("分段送出剪貼簿的文字");
+                            manager.start_long_paste();
+                            Ok(())
+                        }
+                        GuiCommand::ShowBlocklist => {
+                            info!("顯示停用字管理視窗");
+                            manager.show_blocklist();
+                            Ok(())
+                        }
+                        GuiCommand::ShowKeyboard => {
+                            info!("顯示螢幕鍵盤");
+                            manager.show_keyboard();
+                            Ok(())
+                        }
+                        GuiCommand::ShowSymbolPanel => {
+                            info!("顯示符號面板");
+                            manager.show_symbol_panel();
+                            Ok(())
+                        }
+                        GuiCommand::ShowHelp => {
+                            info!("顯示說明視窗");
+                            manager.show_help();
+                            Ok(())
+                        }
+                        GuiCommand::ShowLearning => {
+                            info!("顯示學習曲線");
+                            manager.show_learning();
+                            Ok(())
+                        }
+                        GuiCommand::ShowKeyTrace => {
+                            info!("顯示按鍵回放");
+                            manager.show_key_trace();
+                            Ok(())
+                        }
+                        GuiCommand::ShowInspector => {
+                            info!("顯示狀態檢視");
+                            manager.show_inspector();
+                            Ok(())
+                        }
+                        GuiCommand::Presentation(action) => {
+                            manager.apply_presentation(*action);
+                            Ok(())
+                        }
+                        GuiCommand::ConfirmTerminalSend(text) => {
+                            // 對話框會阻塞 GUI 執行緒直到關閉，先放開 manager 的鎖
+                            let simulator = manager.input_simulator.clone();
+                            drop(manager);
+                            confirm_terminal_send(&simulator, text)
+                        }
+                        GuiCommand::NameMacro(actions) => {
+                            // 對話框會阻塞 GUI 執行緒直到關閉，先放開 manager 的鎖
+                            let macros = manager.macros.clone();
+                            drop(manager);
+                            name_macro(&macros, actions)
+                        }
+                        GuiCommand::PastedElsewhere => {
+                            manager.on_pasted_elsewhere();
+                            Ok(())
+                        }
+                        GuiCommand::CopyToClipboard(text) => {
+                            StatusWindow::copy_to_clipboard(text);
+                            Ok(())
+                        }
+                        GuiCommand::AppendAccumulated(text) => {
+                            manager.append_accumulated_text(text);
+                            Ok(())
+                        }
+                        GuiCommand::FlashStatus => {
+                            manager.flash();
+                            Ok(())
+                        }
+                        GuiCommand::EditLogFilter => {
+                            // 對話框會阻塞 GUI 執行緒直到關閉，先放開 manager 的鎖
+                            drop(manager);
+                            edit_log_filter()
+                        }
+                    };
+                    if let Err(e) = result {
+                        warn!("處理 GUI 命令 {:?} 失敗: {}", command, e);
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    info!("GUI 命令通道已關閉，結束 GUI 事件循環");
+                    return Ok(());
+                }
+            }
+        }
+
+        if let Ok(mut manager) = manager.lock() {
+            // 只在有輸入變化時才更新 GUI 主窗口顯示
+            if gui_needs_update.swap(false, Ordering::Relaxed) {
+                manager.update_display();
+            }
+            manager.tick_animation();
+            manager.tick_key_trace();
+            manager.tick_inspector();
+            manager.tick_long_paste();
+            manager.tick_mode_badge();
         }
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -782,10 +1975,9 @@ mod tests {
         code_map.insert("abc".to_string(), vec!["三".to_string(), "參".to_string()]);
         code_map.insert("test".to_string(), vec!["測試".to_string()]);
 
-        Dictionary {
-            code_to_chars: code_map,
-            pinyi_data: None,
-        }
+        let mut dictionary = Dictionary::default();
+        dictionary.code_to_chars = code_map;
+        dictionary
     }
 
     /// 創建測試用的組件
@@ -810,12 +2002,13 @@ mod tests {
         let gui_has_focus_flag = Arc::new(AtomicBool::new(false));
 
         // 創建窗口應該成功
-        let window_result = GuiWindow::new(
+        let window_result = StatusWindow::new(
             processor.clone(),
             input_simulator.clone(),
             gui_needs_update.clone(),
             gui_visible_flag,
             gui_has_focus_flag,
+            Arc::new(ModeState::default()),
         );
 
         assert!(window_result.is_ok(), "窗口創建應該成功");
@@ -928,6 +2121,51 @@ mod tests {
         }
     }
 
+    /// 測試：累積文字按 Backspace 整個退回最後一次選的詞組
+    #[test]
+    fn test_accumulated_text_backspace_removes_whole_phrase() {
+        let mut acc_text = AccumulatedText::default();
+        acc_text.push_commit("我");
+        acc_text.push_commit("測試");
+        acc_text.push_commit("");
+        assert_eq!(acc_text.as_str(), "我測試");
+        assert_eq!(acc_text.pop_commit().as_deref(), Some("測試"));
+        assert_eq!(acc_text.as_str(), "我");
+        acc_text.clear();
+        assert!(acc_text.is_empty());
+        assert_eq!(acc_text.pop_commit(), None);
+    }
+
+    /// 測試：累積文字逐步撤回與重做，新的修改清空重做，超過上限的舊步驟丟掉
+    #[test]
+    fn test_accumulated_text_undo_redo() {
+        let mut acc_text = AccumulatedText::default();
+        acc_text.push_commit("我");
+        acc_text.push_commit("測試");
+        acc_text.clear();
+        assert!(acc_text.undo());
+        assert_eq!(acc_text.as_str(), "我測試");
+        assert!(acc_text.undo());
+        assert_eq!(acc_text.as_str(), "我");
+        assert!(acc_text.redo());
+        assert_eq!(acc_text.as_str(), "我測試");
+        // 撤回後還原的詞組邊界不變
+        assert_eq!(acc_text.pop_commit().as_deref(), Some("測試"));
+        assert!(!acc_text.redo());
+
+        acc_text.set_undo_limit(2);
+        for text in ["一", "二", "三"] {
+            acc_text.push_commit(text);
+        }
+        assert!(acc_text.undo() && acc_text.undo());
+        assert_eq!(acc_text.as_str(), "我一");
+        assert!(!acc_text.undo());
+
+        acc_text.set_undo_limit(0);
+        acc_text.push_commit("四");
+        assert!(!acc_text.undo());
+    }
+
     /// 測試：鍵盤事件處理 - Backspace 鍵刪除字根
     ///
     /// 驗證窗口能夠處理 Backspace 鍵刪除字根
@@ -1025,7 +2263,7 @@ mod tests {
             let state = proc.get_state();
             assert_eq!(state.current_code, "test", "字根應該是 'test'");
             assert_eq!(state.candidates.len(), 1, "應該找到 1 個候選字");
-            assert_eq!(state.candidates[0], "測試", "候選字應該是 '測試'");
+            assert_eq!(state.candidates[0].text, "測試", "候選字應該是 '測試'");
         }
 
         // 3. 選擇候選字（模擬 Space 鍵）
@@ -1133,4 +2371,21 @@ mod tests {
         // 結論：窗口能夠獨立處理鍵盤輸入，不依賴鍵盤鉤子
         // 這使得輸入法能夠支援使用 Raw Input 的遊戲
     }
+
+    #[test]
+    fn test_fade_alpha_interpolation() {
+        let duration = Duration::from_millis(100);
+        assert_eq!(fade_alpha(0, 100, Duration::ZERO, duration), 0);
+        assert_eq!(fade_alpha(0, 100, Duration::from_millis(50), duration), 50);
+        assert_eq!(fade_alpha(100, 0, Duration::from_millis(25), duration), 75);
+        // 超過動畫時間直接回傳目標值
+        assert_eq!(fade_alpha(0, 100, Duration::from_millis(500), duration), 100);
+        assert_eq!(fade_alpha(10, 100, Duration::ZERO, Duration::ZERO), 100);
+    }
+
+    #[test]
+    fn test_escape_label_keeps_kaomoji() {
+        assert_eq!(escape_label("(╯°□°）╯︵ ┻━┻"), "(╯°□°）╯︵ ┻━┻");
+        assert_eq!(escape_label("(@_@)"), "(@@_@@)");
+    }
 }
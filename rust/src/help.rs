@@ -0,0 +1,142 @@
+//! 說明視窗的內容（快捷鍵一覽）
+//!
+//! 選字鍵、補碼鍵、字根長度與特殊碼規則來自目前的輸入法方案，emoji 前綴鍵、選字確認與
+//! 聯想詞來自輸入處理器的設定，巨集熱鍵來自目前使用者的巨集；每次開啟說明視窗時重新產生，
+//! 換了方案、使用者或設定後內容跟著變。
+
+use crate::input_method::InputMethodProcessor;
+use crate::macros::{MacroBook, MACRO_SLOTS};
+use std::fmt::Write;
+
+/// 產生說明文字
+pub fn help_text(processor: &InputMethodProcessor, macros: &MacroBook) -> String {
+    let scheme = processor.scheme();
+    let mut text = String::new();
+
+    let _ = writeln!(text, "【輸入法方案】{}", scheme.name);
+    let _ = writeln!(text, "字根最長 {} 碼", scheme.max_code_length);
+    let _ = writeln!(text);
+
+    let _ = writeln!(text, "【基本操作】");
+    let _ = writeln!(text, "  單按 Shift        切換英/肥模式");
+    let _ = writeln!(text, "  Space / Enter     送出第一個候選（補碼選字時送出選到的字）");
+    let _ = writeln!(text, "  Backspace         刪除最後一碼");
+    let _ = writeln!(text, "  Esc               清除輸入中的字根");
+    let selection_keys: Vec<char> = scheme.selection_keys.chars().collect();
+    if let (Some(first), Some(last)) = (selection_keys.first(), selection_keys.last()) {
+        let _ = writeln!(
+            text,
+            "  選字鍵 {}～{}      選目前頁的第 1～{} 個候選",
+            first,
+            last,
+            selection_keys.len()
+        );
+    }
+    if let Some(timeout) = processor.selection_confirm() {
+        let _ = writeln!(
+            text,
+            "  選字確認模式：第一次按選字鍵只高亮，{} 毫秒內再按一次或按 Space 才送出",
+            timeout.as_millis()
+        );
+    }
+    let _ = writeln!(text);
+
+    if !scheme.complement_keys.is_empty() {
+        let _ = writeln!(text, "【補碼】字根打完後按補碼鍵直接選字，再按 Space 送出（補碼後仍可按選字鍵改選）");
+        for (i, key) in scheme.complement_keys.chars().enumerate() {
+            let _ = writeln!(text, "  {}  第 {} 個候選", key, i + 2);
+        }
+        let _ = writeln!(text);
+    }
+
+    let _ = writeln!(text, "【特殊輸入】");
+    let _ = writeln!(text, "  *                 萬用字元，只記得部分字根時使用（例如 a*d）");
+    let _ = writeln!(text, "  ?                 單一萬用字元，代表剛好一碼（例如 a?c）");
+    let _ = writeln!(text, "  '                 沒有字根時列出上一個送出字的同音字");
+    if let Some(prefix) = processor.emoji_prefix() {
+        let _ = writeln!(text, "  {}短碼{}            輸入 emoji 短碼，例如 {}smile{}", prefix, prefix, prefix, prefix);
+    }
+    if processor.associated_phrases_enabled() {
+        let _ = writeln!(text, "  送出後提示聯想詞，輸入下一個字根前按選字鍵選用");
+    }
+    let _ = writeln!(text);
+
+    if !scheme.rules.is_empty() {
+        let _ = writeln!(text, "【特殊碼規則】字根符合時優先於字碼表");
+        for rule in &scheme.rules {
+            let _ = writeln!(text, "  {}  →  {}", rule.pattern, rule.generator);
+        }
+        let _ = writeln!(text);
+    }
+
+    let _ = writeln!(text, "【熱鍵】");
+    let _ = writeln!(text, "  Ctrl+Space        顯示/隱藏狀態窗（遊戲模式）");
+    let _ = writeln!(text, "  F4                退出");
+    let _ = writeln!(text, "  連按三次 Esc      緊急放行：清空輸入並切到英模式");
+    let _ = writeln!(text, "  Ctrl+Alt+V        候選直向/橫向排列");
+    let _ = writeln!(text, "  Ctrl+Alt+= / -    放大/縮小狀態窗");
+    let _ = writeln!(text, "  Ctrl+Alt+H        高對比主題");
+    let _ = writeln!(text, "  Ctrl+Alt+0        還原預設版面");
+    let _ = writeln!(text, "  Ctrl+Alt+R        開始/停止錄製巨集");
+    let _ = writeln!(text, "  Ctrl+Alt+L        查碼：列出剪貼簿中每個字的字根");
+    let _ = writeln!(text, "  Ctrl+Alt+K        選取反查：列出目前選取的每個字的字根");
+    let _ = writeln!(text, "  Ctrl+Alt+V        長文分段送出：把剪貼簿的文字一段一段送出");
+    let _ = writeln!(text);
+
+    let _ = writeln!(text, "【巨集】");
+    let bound: Vec<_> = MACRO_SLOTS.filter_map(|slot| macros.for_slot(slot).map(|m| (slot, m))).collect();
+    if bound.is_empty() {
+        let _ = writeln!(text, "  還沒有綁定熱鍵的巨集（Ctrl+Alt+R 錄製）");
+    }
+    for (slot, found) in bound {
+        let _ = writeln!(text, "  Ctrl+Alt+{}        {}", slot, found.name);
+    }
+    let _ = writeln!(text);
+
+    let _ = writeln!(text, "【工作流程】");
+    let _ = writeln!(text, "  1. 在肥模式下打字根，候選出現在狀態窗");
+    let _ = writeln!(text, "  2. 按 Space 或選字鍵（或補碼鍵後按 Space）送出到目前的視窗");
+    let _ = writeln!(text, "  3. 遊戲擋住輸入時按 Ctrl+Space 開啟狀態窗，在窗內打字累積，");
+    let _ = writeln!(text, "     回到遊戲按 Ctrl+V 貼上");
+    let _ = write!(text, "  4. 要打英文時單按 Shift 切到英模式");
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::Dictionary;
+    use crate::macros::{Macro, MacroAction};
+    use crate::rules::RuleDef;
+    use crate::scheme::InputScheme;
+    use std::time::Duration;
+
+    #[test]
+    fn test_help_text_follows_settings() {
+        let scheme = InputScheme {
+            name: "行列".to_string(),
+            complement_keys: String::new(),
+            selection_keys: "123".to_string(),
+            rules: vec![RuleDef { pattern: "^zd$".to_string(), generator: "date".to_string(), template: None }],
+            ..Default::default()
+        };
+        let mut processor = InputMethodProcessor::with_scheme(Dictionary::default(), scheme);
+        processor.set_selection_confirm(Some(Duration::from_millis(800)));
+        let mut macros = MacroBook::default();
+        macros.insert(Macro { name: "打招呼".to_string(), slot: Some(2), actions: vec![MacroAction::ToggleWindow] });
+
+        let text = help_text(&processor, &macros);
+        assert!(text.contains("【輸入法方案】行列"));
+        assert!(text.contains("選字鍵 1～3"));
+        assert!(text.contains("800 毫秒"));
+        assert!(!text.contains("【補碼】"));
+        assert!(text.contains("^zd$  →  date"));
+        assert!(text.contains("Ctrl+Alt+2        打招呼"));
+
+        let text = help_text(&InputMethodProcessor::new(Dictionary::default()), &MacroBook::default());
+        assert!(text.contains("  v  第 2 個候選"));
+        assert!(text.contains("  w  第 6 個候選"));
+        assert!(text.contains("還沒有綁定熱鍵的巨集"));
+    }
+}
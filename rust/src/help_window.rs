@@ -0,0 +1,61 @@
+//! 說明視窗
+//!
+//! 列出目前設定的熱鍵、選字鍵、補碼鍵與工作流程（內容見 help 模組），以及各送字後端的自測結果
+//! （見 backend_health 模組），每次開啟時重新產生。
+
+use crate::help;
+use crate::input_method::InputMethodProcessor;
+use crate::input_simulator::InputSimulator;
+use crate::macros::MacroBook;
+use fltk::{
+    enums::Font,
+    prelude::*,
+    text::{TextBuffer, TextDisplay},
+    window::Window,
+};
+use std::sync::{Arc, Mutex};
+
+/// 說明視窗
+pub struct HelpWindow {
+    window: Window,
+    buffer: TextBuffer,
+    processor: Arc<Mutex<InputMethodProcessor>>,
+    macros: Arc<Mutex<MacroBook>>,
+    input_simulator: Arc<Mutex<InputSimulator>>,
+}
+
+impl HelpWindow {
+    pub fn new(
+        processor: Arc<Mutex<InputMethodProcessor>>,
+        macros: Arc<Mutex<MacroBook>>,
+        input_simulator: Arc<Mutex<InputSimulator>>,
+    ) -> Self {
+        let window = Window::default().with_size(560, 520).with_label("說明").center_screen();
+
+        let buffer = TextBuffer::default();
+        let mut display = TextDisplay::new(0, 0, 560, 520, "");
+        display.set_buffer(buffer.clone());
+        // 等寬字型讓按鍵與說明對齊
+        display.set_text_font(Font::Courier);
+        display.set_text_size(15);
+        window.end();
+
+        Self { window, buffer, processor, macros, input_simulator }
+    }
+
+    /// 以目前的設定重新產生內容後顯示視窗
+    pub fn show(&mut self) {
+        let text = {
+            let processor = self.processor.lock().unwrap();
+            let macros = self.macros.lock().unwrap();
+            let mut text = help::help_text(&processor, &macros);
+            text.push_str("\n\n【送字後端】\n");
+            for line in self.input_simulator.lock().unwrap().health().lines() {
+                text.push_str(&format!("  {}\n", line));
+            }
+            text
+        };
+        self.buffer.set_text(&text);
+        self.window.show();
+    }
+}
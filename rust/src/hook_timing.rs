@@ -0,0 +1,196 @@
+//! 鍵盤鉤子回呼耗時監控模組
+//!
+//! 低階鍵盤鉤子的回呼超過系統的 LowLevelHooksTimeout 時，Windows 會直接移除鉤子，
+//! 之後按鍵完全不經過輸入法。這裡量測每次回呼的耗時並統計分佈：
+//! 連續多次超過預算時進入降級模式，鉤子執行緒只輸出 warn 以上的日誌
+//! （主控台與日誌檔寫入是回呼裡最耗時的非必要工作），回呼恢復正常一段時間後自動解除。
+//!
+//! 統計只由鉤子執行緒寫入，欄位都是原子變數，托盤統計面板可以在任何執行緒讀取。
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// 單次回呼的耗時預算
+pub const CALLBACK_BUDGET: Duration = Duration::from_millis(5);
+/// 連續超過預算幾次後進入降級模式
+const DEGRADE_AFTER: u32 = 3;
+/// 降級後連續幾次在預算內才解除
+const RECOVER_AFTER: u32 = 200;
+/// 分佈統計的區間上限（微秒），最後一格為超過最大上限的回呼
+const BUCKET_BOUNDS_US: [u64; 6] = [500, 1_000, 2_000, 5_000, 10_000, 50_000];
+
+/// 記錄一次回呼後的狀態變化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingEvent {
+    /// 在預算內
+    Normal,
+    /// 超過預算
+    OverBudget,
+    /// 超過預算且剛進入降級模式
+    Degraded,
+    /// 剛解除降級模式
+    Recovered,
+}
+
+/// 鍵盤鉤子回呼耗時統計
+#[derive(Default)]
+pub struct HookTiming {
+    buckets: [AtomicU64; BUCKET_BOUNDS_US.len() + 1],
+    count: AtomicU64,
+    total_us: AtomicU64,
+    max_us: AtomicU64,
+    over_budget: AtomicU64,
+    /// 已回報過警告的超時次數（訊息循環用來判斷是否有新的超時）
+    reported_over_budget: AtomicU64,
+    /// 最近一次超時的耗時
+    last_over_budget_us: AtomicU64,
+    degraded: AtomicBool,
+    degrade_count: AtomicU64,
+    consecutive_slow: AtomicU32,
+    consecutive_fast: AtomicU32,
+}
+
+impl HookTiming {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 記錄一次回呼的耗時（只在鉤子執行緒呼叫）
+    /// 回呼內只更新原子變數，警告留給訊息循環輸出，避免在回呼裡做 I/O
+    pub fn record(&self, elapsed: Duration) -> TimingEvent {
+        let us = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = BUCKET_BOUNDS_US
+            .iter()
+            .position(|&bound| us < bound)
+            .unwrap_or(BUCKET_BOUNDS_US.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_us.fetch_add(us, Ordering::Relaxed);
+        self.max_us.fetch_max(us, Ordering::Relaxed);
+
+        if elapsed > CALLBACK_BUDGET {
+            self.over_budget.fetch_add(1, Ordering::Relaxed);
+            self.last_over_budget_us.store(us, Ordering::Relaxed);
+            self.consecutive_fast.store(0, Ordering::Relaxed);
+            let slow = self.consecutive_slow.fetch_add(1, Ordering::Relaxed) + 1;
+            if slow >= DEGRADE_AFTER && !self.degraded.swap(true, Ordering::Relaxed) {
+                self.degrade_count.fetch_add(1, Ordering::Relaxed);
+                return TimingEvent::Degraded;
+            }
+            return TimingEvent::OverBudget;
+        }
+
+        self.consecutive_slow.store(0, Ordering::Relaxed);
+        if self.degraded.load(Ordering::Relaxed) {
+            let fast = self.consecutive_fast.fetch_add(1, Ordering::Relaxed) + 1;
+            if fast >= RECOVER_AFTER {
+                self.consecutive_fast.store(0, Ordering::Relaxed);
+                self.degraded.store(false, Ordering::Relaxed);
+                return TimingEvent::Recovered;
+            }
+        }
+        TimingEvent::Normal
+    }
+
+    /// 是否處於降級模式
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Relaxed)
+    }
+
+    /// 自上次呼叫後新增的超時次數與最近一次超時的耗時（給訊息循環輸出警告）
+    pub fn take_new_over_budget(&self) -> Option<(u64, Duration)> {
+        let total = self.over_budget.load(Ordering::Relaxed);
+        let reported = self.reported_over_budget.swap(total, Ordering::Relaxed);
+        (total > reported).then(|| {
+            (total - reported, Duration::from_micros(self.last_over_budget_us.load(Ordering::Relaxed)))
+        })
+    }
+
+    /// 統計面板的文字
+    pub fn summary(&self) -> String {
+        let count = self.count.load(Ordering::Relaxed);
+        if count == 0 {
+            return "尚未收到任何按鍵".to_string();
+        }
+
+        let total_us = self.total_us.load(Ordering::Relaxed);
+        let mut text = format!(
+            "回呼次數: {}\n平均耗時: {:.2} ms\n最長耗時: {:.2} ms\n超過預算（{} ms）: {} 次\n降級模式: {}（共進入 {} 次）\n\n[耗時分佈]\n",
+            count,
+            total_us as f64 / count as f64 / 1000.0,
+            self.max_us.load(Ordering::Relaxed) as f64 / 1000.0,
+            CALLBACK_BUDGET.as_millis(),
+            self.over_budget.load(Ordering::Relaxed),
+            if self.is_degraded() { "是" } else { "否" },
+            self.degrade_count.load(Ordering::Relaxed),
+        );
+
+        let mut lower = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            let n = bucket.load(Ordering::Relaxed);
+            let label = match BUCKET_BOUNDS_US.get(i) {
+                Some(&upper) => format!("{} - {} ms", format_ms(lower), format_ms(upper)),
+                None => format!("≥ {} ms", format_ms(lower)),
+            };
+            text.push_str(&format!("{:<14} {:>6} ({:>5.1}%)\n", label, n, n as f64 * 100.0 / count as f64));
+            lower = BUCKET_BOUNDS_US.get(i).copied().unwrap_or(lower);
+        }
+        text
+    }
+}
+
+fn format_ms(us: u64) -> String {
+    if us.is_multiple_of(1000) {
+        (us / 1000).to_string()
+    } else {
+        format!("{}", us as f64 / 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_degrade_and_recover() {
+        let timing = HookTiming::new();
+        let slow = Duration::from_millis(8);
+        let fast = Duration::from_micros(300);
+
+        assert_eq!(timing.record(slow), TimingEvent::OverBudget);
+        assert_eq!(timing.record(fast), TimingEvent::Normal);
+        assert_eq!(timing.record(slow), TimingEvent::OverBudget);
+        assert_eq!(timing.record(slow), TimingEvent::OverBudget);
+        assert_eq!(timing.record(slow), TimingEvent::Degraded);
+        assert!(timing.is_degraded());
+
+        for _ in 1..RECOVER_AFTER {
+            assert_eq!(timing.record(fast), TimingEvent::Normal);
+        }
+        assert_eq!(timing.record(fast), TimingEvent::Recovered);
+        assert!(!timing.is_degraded());
+    }
+
+    #[test]
+    fn test_over_budget_reported_once() {
+        let timing = HookTiming::new();
+        assert_eq!(timing.take_new_over_budget(), None);
+        timing.record(Duration::from_millis(6));
+        timing.record(Duration::from_millis(12));
+        assert_eq!(timing.take_new_over_budget(), Some((2, Duration::from_millis(12))));
+        assert_eq!(timing.take_new_over_budget(), None);
+    }
+
+    #[test]
+    fn test_summary_distribution() {
+        let timing = HookTiming::new();
+        timing.record(Duration::from_micros(100));
+        timing.record(Duration::from_micros(700));
+        timing.record(Duration::from_millis(60));
+        let text = timing.summary();
+        assert!(text.contains("回呼次數: 3"));
+        assert!(text.contains("0 - 0.5 ms"));
+        assert!(text.contains("≥ 50 ms"));
+        assert!(text.contains("超過預算（5 ms）: 1 次"));
+    }
+}
@@ -0,0 +1,359 @@
+//! 托盤圖示影像模組
+//!
+//! 解析 icon.ico（支援 PNG 與 24/32 位元 BMP 兩種內嵌格式），依目前 DPI 挑選最適合的尺寸，
+//! 並在右下角疊加顯示英/肥模式的小徽章。找不到 icon.ico 時使用內建的純色圖示。
+
+use crate::mode::InputMode;
+use anyhow::{Context, Result};
+use std::io::Cursor;
+
+/// RGBA 影像（每像素 4 位元組，由上而下逐列排列）
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    fn new(width: u32, height: u32) -> Self {
+        Self { width, height, pixels: vec![0; (width * height * 4) as usize] }
+    }
+
+    fn put(&mut self, x: u32, y: u32, color: [u8; 4]) {
+        if x < self.width && y < self.height {
+            let i = ((y * self.width + x) * 4) as usize;
+            self.pixels[i..i + 4].copy_from_slice(&color);
+        }
+    }
+
+    fn get(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+}
+
+/// 模式徽章
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Badge {
+    /// 英模式（不攔截）
+    English,
+    /// 肥模式（攔截輸入）
+    Ucl,
+}
+
+impl Badge {
+    /// 目前模式對應的徽章
+    pub fn for_mode(mode: InputMode) -> Self {
+        match mode {
+            InputMode::English => Self::English,
+            InputMode::Ucl => Self::Ucl,
+        }
+    }
+
+    /// 徽章底色（RGBA），托盤圖示與模式浮標共用
+    pub fn background(self) -> [u8; 4] {
+        match self {
+            Self::English => [60, 110, 200, 255],
+            Self::Ucl => [220, 90, 30, 255],
+        }
+    }
+}
+
+/// 徽章字樣（5x5 點陣）：英模式顯示 A，肥模式顯示 米
+const GLYPH_ENGLISH: [&str; 5] = [".###.", "#...#", "#####", "#...#", "#...#"];
+const GLYPH_UCL: [&str; 5] = ["#.#.#", ".###.", "#####", ".###.", "#.#.#"];
+
+/// BMP 圖示的最大邊長（ICO 規格最大 256，寬鬆一些；超過的視為損毀，不配置記憶體）
+const MAX_BMP_SIZE: u32 = 1024;
+
+/// 解析 ICO 檔案，回傳其中所有可解碼的圖示
+pub fn parse_ico(bytes: &[u8]) -> Result<Vec<RgbaImage>> {
+    if bytes.len() < 6 || read_u16(bytes, 0) != 0 || read_u16(bytes, 2) != 1 {
+        return Err(anyhow::anyhow!("不是有效的 ICO 檔案"));
+    }
+
+    let count = read_u16(bytes, 4) as usize;
+    let mut images = Vec::with_capacity(count);
+    for i in 0..count {
+        let entry = 6 + i * 16;
+        if entry + 16 > bytes.len() {
+            break;
+        }
+        let size = read_u32(bytes, entry + 8) as usize;
+        let offset = read_u32(bytes, entry + 12) as usize;
+        let Some(data) = bytes.get(offset..offset.saturating_add(size)) else {
+            continue;
+        };
+
+        let image = if data.starts_with(b"\x89PNG") {
+            decode_png(data)
+        } else {
+            decode_bmp(data)
+        };
+        // 不支援的格式（例如 8 位元調色盤）直接略過，改用其他尺寸
+        if let Ok(image) = image {
+            images.push(image);
+        }
+    }
+
+    if images.is_empty() {
+        return Err(anyhow::anyhow!("ICO 檔案中沒有可用的圖示"));
+    }
+    Ok(images)
+}
+
+/// 挑選最適合指定尺寸的圖示：優先取不小於 size 的最小圖示，否則取最大的，再縮放到 size
+pub fn select_size(images: &[RgbaImage], size: u32) -> Option<RgbaImage> {
+    let best = images
+        .iter()
+        .filter(|img| img.width >= size)
+        .min_by_key(|img| img.width)
+        .or_else(|| images.iter().max_by_key(|img| img.width))?;
+    Some(resize(best, size))
+}
+
+/// 找不到 icon.ico 時使用的內建圖示（淺灰底的方塊）
+pub fn fallback_icon(size: u32) -> RgbaImage {
+    let mut image = RgbaImage::new(size, size);
+    for y in 0..size {
+        for x in 0..size {
+            let border = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+            image.put(x, y, if border { [80, 80, 80, 255] } else { [222, 222, 222, 255] });
+        }
+    }
+    image
+}
+
+/// 在圖示右下角疊加模式徽章（大小隨圖示尺寸等比放大，高 DPI 下仍清楚）
+pub fn draw_badge(image: &mut RgbaImage, badge: Badge) {
+    let glyph = match badge {
+        Badge::English => &GLYPH_ENGLISH,
+        Badge::Ucl => &GLYPH_UCL,
+    };
+    let background = badge.background();
+
+    // 16px 圖示使用 7x7 徽章（5x5 字樣加 1px 邊距），更大的圖示以整數倍放大
+    let scale = (image.width.min(image.height) / 16).max(1);
+    let badge_size = 7 * scale;
+    let left = image.width.saturating_sub(badge_size);
+    let top = image.height.saturating_sub(badge_size);
+
+    for y in 0..badge_size {
+        for x in 0..badge_size {
+            image.put(left + x, top + y, background);
+        }
+    }
+    for (row, line) in glyph.iter().enumerate() {
+        for (col, ch) in line.chars().enumerate() {
+            if ch != '#' {
+                continue;
+            }
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let x = left + (col as u32 + 1) * scale + dx;
+                    let y = top + (row as u32 + 1) * scale + dy;
+                    image.put(x, y, [255, 255, 255, 255]);
+                }
+            }
+        }
+    }
+}
+
+/// 縮放影像（縮小時取區塊平均，放大時取最近像素）
+fn resize(image: &RgbaImage, size: u32) -> RgbaImage {
+    if image.width == size && image.height == size {
+        return image.clone();
+    }
+
+    let mut result = RgbaImage::new(size, size);
+    for y in 0..size {
+        for x in 0..size {
+            let x0 = x * image.width / size;
+            let y0 = y * image.height / size;
+            let x1 = ((x + 1) * image.width / size).max(x0 + 1);
+            let y1 = ((y + 1) * image.height / size).max(y0 + 1);
+
+            let mut sum = [0u32; 4];
+            for sy in y0..y1 {
+                for sx in x0..x1 {
+                    for (s, v) in sum.iter_mut().zip(image.get(sx, sy)) {
+                        *s += v as u32;
+                    }
+                }
+            }
+            let n = (x1 - x0) * (y1 - y0);
+            result.put(x, y, sum.map(|s| (s / n) as u8));
+        }
+    }
+    result
+}
+
+fn decode_png(data: &[u8]) -> Result<RgbaImage> {
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().context("無法解析 PNG 圖示")?;
+    let mut buf = vec![0; reader.output_buffer_size().unwrap_or(0)];
+    let info = reader.next_frame(&mut buf).context("無法解碼 PNG 圖示")?;
+    let buf = &buf[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buf.to_vec(),
+        png::ColorType::Rgb => buf.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return Err(anyhow::anyhow!("不支援的 PNG 色彩格式")),
+    };
+
+    Ok(RgbaImage { width: info.width, height: info.height, pixels })
+}
+
+/// 解碼 ICO 內嵌的 BMP（BITMAPINFOHEADER，高度為實際高度的兩倍：色彩資料 + AND 遮罩）
+fn decode_bmp(data: &[u8]) -> Result<RgbaImage> {
+    if data.len() < 40 {
+        return Err(anyhow::anyhow!("BMP 圖示資料不完整"));
+    }
+    let header_size = read_u32(data, 0) as usize;
+    let width = read_u32(data, 4) as i32;
+    let height = read_u32(data, 8) as i32 / 2;
+    let bit_count = read_u16(data, 14);
+    if width <= 0 || height <= 0 || !matches!(bit_count, 24 | 32) {
+        return Err(anyhow::anyhow!("不支援的 BMP 圖示格式（{} 位元）", bit_count));
+    }
+
+    let (width, height) = (width as u32, height as u32);
+    if width > MAX_BMP_SIZE || height > MAX_BMP_SIZE {
+        return Err(anyhow::anyhow!("BMP 圖示尺寸 {}x{} 超過上限", width, height));
+    }
+    // 寬高已限制在 MAX_BMP_SIZE 內，位移量來自檔案內容，一律以 checked 運算避免溢位
+    let bytes_per_pixel = bit_count as usize / 8;
+    let stride = (width as usize * bytes_per_pixel).div_ceil(4) * 4;
+    let mask_stride = (width as usize).div_ceil(32) * 4;
+    let color_start = header_size;
+    let mask_start = stride
+        .checked_mul(height as usize)
+        .and_then(|color_size| color_start.checked_add(color_size))
+        .filter(|&mask_start| mask_start <= data.len())
+        .ok_or_else(|| anyhow::anyhow!("BMP 圖示資料不完整"))?;
+    let has_mask = mask_stride
+        .checked_mul(height as usize)
+        .and_then(|mask_size| mask_start.checked_add(mask_size))
+        .is_some_and(|mask_end| mask_end <= data.len());
+
+    let mut image = RgbaImage::new(width, height);
+    let mut any_alpha = false;
+    for y in 0..height {
+        // BMP 由下而上存放
+        let row = color_start + (height - 1 - y) as usize * stride;
+        for x in 0..width {
+            let p = row + x as usize * bytes_per_pixel;
+            let alpha = if bit_count == 32 { data[p + 3] } else { 255 };
+            any_alpha |= bit_count == 32 && alpha != 0;
+            image.put(x, y, [data[p + 2], data[p + 1], data[p], alpha]);
+        }
+    }
+
+    // 沒有 alpha 通道資訊時，以 AND 遮罩決定透明度（遮罩位元為 1 表示透明）
+    if !any_alpha && has_mask {
+        for y in 0..height {
+            let row = mask_start + (height - 1 - y) as usize * mask_stride;
+            for x in 0..width {
+                let transparent = data[row + x as usize / 8] & (0x80 >> (x % 8)) != 0;
+                let i = ((y * width + x) * 4 + 3) as usize;
+                image.pixels[i] = if transparent { 0 } else { 255 };
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 組出只含一個 32 位元 BMP 圖示的 ICO
+    fn build_bmp_ico(size: u32, color: [u8; 4]) -> Vec<u8> {
+        let mut bmp = Vec::new();
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        bmp.extend_from_slice(&size.to_le_bytes());
+        bmp.extend_from_slice(&(size * 2).to_le_bytes());
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&32u16.to_le_bytes());
+        bmp.extend_from_slice(&[0; 24]);
+        for _ in 0..size * size {
+            bmp.extend_from_slice(&[color[2], color[1], color[0], color[3]]);
+        }
+        bmp.extend(std::iter::repeat_n(0, (size as usize).div_ceil(32) * 4 * size as usize));
+
+        let mut ico = Vec::new();
+        ico.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+        ico.extend_from_slice(&[size as u8, size as u8, 0, 0]);
+        ico.extend_from_slice(&1u16.to_le_bytes());
+        ico.extend_from_slice(&32u16.to_le_bytes());
+        ico.extend_from_slice(&(bmp.len() as u32).to_le_bytes());
+        ico.extend_from_slice(&22u32.to_le_bytes());
+        ico.extend_from_slice(&bmp);
+        ico
+    }
+
+    #[test]
+    fn test_parse_bmp_ico() {
+        let images = parse_ico(&build_bmp_ico(4, [10, 20, 30, 255])).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!((images[0].width, images[0].height), (4, 4));
+        assert_eq!(images[0].get(3, 3), [10, 20, 30, 255]);
+        assert!(parse_ico(b"not an icon").is_err());
+    }
+
+    #[test]
+    fn test_decode_bmp_rejects_oversize_headers() {
+        let mut bmp = build_bmp_ico(4, [10, 20, 30, 255])[22..].to_vec();
+        assert!(decode_bmp(&bmp).is_ok());
+        // 超大的寬度或標頭長度不可溢位，也不可配置巨大的影像
+        bmp[4..8].copy_from_slice(&0x7fff_ffffu32.to_le_bytes());
+        assert!(decode_bmp(&bmp).is_err());
+        bmp[4..8].copy_from_slice(&4u32.to_le_bytes());
+        bmp[0..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(decode_bmp(&bmp).is_err());
+    }
+
+    #[test]
+    fn test_select_size_prefers_larger() {
+        let small = RgbaImage::new(16, 16);
+        let mut large = RgbaImage::new(32, 32);
+        large.put(0, 0, [255, 255, 255, 255]);
+        let images = vec![small, large];
+
+        // 24px 從 32px 縮小
+        assert_eq!(select_size(&images, 24).unwrap().width, 24);
+        // 16px 直接取 16px 的圖示
+        assert_eq!(select_size(&images, 16).unwrap(), images[0]);
+        // 48px 沒有更大的，從 32px 放大
+        assert_eq!(select_size(&images, 48).unwrap().width, 48);
+        assert!(select_size(&[], 16).is_none());
+    }
+
+    #[test]
+    fn test_draw_badge_scales_with_size() {
+        let mut image = fallback_icon(16);
+        draw_badge(&mut image, Badge::Ucl);
+        // 米 字樣左上角的點
+        assert_eq!(image.get(10, 10), [255, 255, 255, 255]);
+        assert_eq!(image.get(9, 9), [220, 90, 30, 255]);
+
+        let mut image = fallback_icon(32);
+        draw_badge(&mut image, Badge::English);
+        // 32px 時徽章為 14x14
+        assert_eq!(image.get(18, 18), [60, 110, 200, 255]);
+        assert_eq!(image.get(17, 17), [222, 222, 222, 255]);
+    }
+}
@@ -0,0 +1,85 @@
+//! 輸入事件的觀察者介面
+//!
+//! 統計、OBS 字幕輸出、GUI 等子系統想知道組字狀態何時改變時，實作 InputObserver 並向
+//! InputMethodProcessor::subscribe 註冊，取代定期輪詢或到處設定旗標。處理器只保留弱引用，
+//! 訂閱者丟掉自己的 Arc 就自動取消訂閱。
+//!
+//! 事件在持有輸入處理器的鎖時發出（多半在鍵盤鉤子回呼裡），觀察者只能做設定旗標、
+//! 喚醒執行緒這類很快的事，不可以再鎖輸入處理器。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::Duration;
+
+/// 組字狀態的變化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    /// 開始輸入字根（原本沒有字根）
+    CompositionStarted,
+    /// 字根、候選或補碼選中的候選有變化
+    CandidatesUpdated,
+    /// 送出文字（送出後組字結束）
+    Committed(String),
+    /// 沒有送出就清除了字根（Esc、切換模式、刪光字根等）
+    Cleared,
+}
+
+/// 輸入事件的觀察者
+pub trait InputObserver: Send + Sync {
+    fn on_input_event(&self, event: &InputEvent);
+}
+
+/// 已註冊的觀察者（弱引用）
+#[derive(Default)]
+pub struct ObserverList {
+    observers: Vec<Weak<dyn InputObserver>>,
+}
+
+impl ObserverList {
+    pub fn subscribe(&mut self, observer: &Arc<dyn InputObserver>) {
+        self.observers.push(Arc::downgrade(observer));
+    }
+
+    /// 通知所有還在的觀察者，順便移除已經不在的
+    pub fn notify(&mut self, event: &InputEvent) {
+        self.observers.retain(|observer| match observer.upgrade() {
+            Some(observer) => {
+                observer.on_input_event(event);
+                true
+            }
+            None => false,
+        });
+    }
+}
+
+/// 收到任何事件時設定旗標（例如 GUI 的 gui_needs_update）
+pub struct FlagObserver(pub Arc<AtomicBool>);
+
+impl InputObserver for FlagObserver {
+    fn on_input_event(&self, _event: &InputEvent) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 收到任何事件時喚醒等待中的執行緒（例如 OBS 字幕輸出）
+#[derive(Default)]
+pub struct ChangeSignal {
+    changed: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl ChangeSignal {
+    /// 等到有事件或逾時，回傳期間是否有事件（並清除）
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let changed = self.changed.lock().unwrap();
+        let (mut changed, _) = self.condvar.wait_timeout_while(changed, timeout, |changed| !*changed).unwrap();
+        std::mem::take(&mut *changed)
+    }
+}
+
+impl InputObserver for ChangeSignal {
+    fn on_input_event(&self, _event: &InputEvent) {
+        *self.changed.lock().unwrap() = true;
+        self.condvar.notify_all();
+    }
+}
@@ -1,21 +1,118 @@
 //! 輸入法邏輯模組
 
-use crate::dictionary::Dictionary;
-use log::debug;
+use crate::associations::AssociationTable;
+use crate::blocklist::Blocklist;
+use crate::charset::CharsetFilter;
+use crate::candidate::{clusters, Candidate};
+use crate::chardefs::is_wildcard;
+use crate::dictionary::{Dictionary, SNIPPETS_LAYER};
+use crate::frequency::FrequencyStats;
+use crate::input_events::{InputEvent, InputObserver, ObserverList};
+use crate::rules::{LocalTime, RuleSet};
+use crate::scheme::InputScheme;
+use crate::snippets;
+use crate::sorter::{self, CandidateSorter, TableOrder};
+use crate::symbols::{SymbolMenu, SymbolTable};
+use crate::time_macros::{self, TimeMacro};
+use anyhow::{bail, Result};
+use tracing::{debug, info};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 重複送出的次數上限（見 start_repeat_count）
+pub const MAX_REPEAT_COUNT: usize = 99;
+
+/// emoji 短碼最多幾個字元（含前綴鍵；短碼是英文單字，比一般字根長）
+pub const EMOJI_MAX_CODE_LENGTH: usize = 32;
+
+/// 保留最近幾次送出的文字（見 recent_commits）
+pub const RECENT_COMMITS_LEN: usize = 10;
+
+/// 肥模式下按 Shift+字母的行為（設定 shift_letter）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShiftLetter {
+    /// 交給系統處理，打出大寫字母，不影響目前的字根（預設）
+    #[default]
+    Pass,
+    /// 直接送出大寫字母；正在組字時先送出第一個候選
+    Commit,
+    /// 以大寫字母開始暫時英文緩衝區（見 start_temp_english），之後的字母接在後面，Space 送出整個單字
+    Word,
+}
+
+impl ShiftLetter {
+    /// 從設定值解析（"pass"、"commit"、"word"，不分大小寫）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "pass" => Ok(Self::Pass),
+            "commit" => Ok(Self::Commit),
+            "word" => Ok(Self::Word),
+            other => bail!("未知的 Shift+字母行為 '{}'（可用: pass、commit、word）", other),
+        }
+    }
+}
+
+/// 全形標點轉成對應的半形 ASCII 標點（沒有對應的字元保持不變）
+fn to_half_width(text: &str) -> String {
+    let mut half = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            // 全形 ASCII 區（！到～）
+            '\u{FF01}'..='\u{FF5E}' => half.push(char::from_u32(ch as u32 - 0xFEE0).unwrap_or(ch)),
+            '\u{3000}' => half.push(' '),
+            '。' => half.push('.'),
+            '、' => half.push(','),
+            '‘' | '’' => half.push('\''),
+            '“' | '”' => half.push('"'),
+            '…' => half.push_str("..."),
+            _ => half.push(ch),
+        }
+    }
+    half
+}
+
+/// 沒有字根的候選（同音字、符號選單）
+fn listing(texts: impl IntoIterator<Item = String>) -> Vec<Candidate> {
+    texts.into_iter().map(|text| Candidate::plain("", text)).collect()
+}
 
 /// 輸入法狀態
 #[derive(Debug, Clone, PartialEq)]
 pub struct InputMethodState {
     /// 當前輸入的字根
     pub current_code: String,
-    /// 候選字列表
-    pub candidates: Vec<String>,
+    /// 候選列表（各自帶著所屬字根、來源與別名輸出，見 candidate 模組）
+    pub candidates: Vec<Candidate>,
     /// 當前候選字索引（用於分頁）
     pub candidate_index: usize,
     /// 每頁顯示的候選字數量
     pub candidates_per_page: usize,
+    /// 字根最大長度（由輸入法方案決定）
+    pub max_code_length: usize,
     /// 補碼選擇的候選字（等待 Space 鍵送出）
     pub complement_selected: Option<String>,
+    /// 補碼/符號選擇的候選字所屬的字根（用於查詢別名輸出）
+    pub complement_code: Option<String>,
+    /// 觸發補碼的按鍵（以選字鍵改選後為 None）
+    pub complement_key: Option<char>,
+    /// 補碼選中的是候選列表中的第幾個（從 0 起算；符號選擇時為 None）
+    pub complement_position: Option<usize>,
+    /// 送出後的聯想詞（輸入下一個字根前可以按選字鍵選用）
+    pub suggestions: Vec<String>,
+    /// emoji 短碼的前綴鍵（字根以它開頭時改查 emoji 表；None 表示不使用）
+    pub emoji_prefix: Option<char>,
+    /// 目前列出的是哪個字的同音字（按同音字熱鍵叫出，候選即為同音字）
+    pub homophone_of: Option<char>,
+    /// 符號選單目前列出的層級（按符號選單鍵叫出，候選為分類名稱或符號；None 表示沒有開啟）
+    pub symbol_menu: Option<SymbolMenu>,
+    /// 候選字集篩選（見 charset 模組）
+    pub charset_filter: CharsetFilter,
+    /// 暫時英文模式的緩衝區（按 ` 進入，Space/Enter 原樣送出；None 表示不在暫時英文模式）
+    pub temp_english: Option<String>,
+    /// 按下重複鍵後輸入的重複次數（0 表示還沒輸入數字；None 表示沒有按重複鍵）
+    pub repeat_count: Option<usize>,
 }
 
 impl Default for InputMethodState {
@@ -25,7 +122,18 @@ impl Default for InputMethodState {
             candidates: Vec::new(),
             candidate_index: 0,
             candidates_per_page: 6,
+            max_code_length: 5,
             complement_selected: None,
+            complement_code: None,
+            complement_key: None,
+            complement_position: None,
+            suggestions: Vec::new(),
+            emoji_prefix: None,
+            charset_filter: CharsetFilter::default(),
+            homophone_of: None,
+            symbol_menu: None,
+            temp_english: None,
+            repeat_count: None,
         }
     }
 }
@@ -41,15 +149,41 @@ impl InputMethodState {
         self.candidates.clear();
         self.candidate_index = 0;
         self.complement_selected = None;
+        self.complement_code = None;
+        self.complement_key = None;
+        self.complement_position = None;
+        self.suggestions.clear();
+        self.homophone_of = None;
+        self.symbol_menu = None;
+        self.temp_english = None;
+        self.repeat_count = None;
+    }
+
+    /// 是否列出沒有字根的候選（同音字或符號選單）
+    pub fn is_listing(&self) -> bool {
+        self.homophone_of.is_some() || self.symbol_menu.is_some()
+    }
+
+    /// 是否正在輸入 emoji 短碼（字根以前綴鍵開頭）
+    pub fn is_emoji_composition(&self) -> bool {
+        self.emoji_prefix.is_some_and(|prefix| self.current_code.starts_with(prefix))
     }
 
     /// 添加字根
     pub fn append_code(&mut self, ch: char) {
-        // 字根最多 5 碼
-        if self.current_code.len() < 5 {
+        // 字根最多 max_code_length 碼（嘸蝦米為 5 碼），emoji 短碼另有上限
+        let max_length = if self.is_emoji_composition() { EMOJI_MAX_CODE_LENGTH } else { self.max_code_length };
+        if self.current_code.len() < max_length {
             self.current_code.push(ch);
-            // 每次添加字根時，清除之前的補碼/符號選擇（因為開始輸入新字根）
+            // 每次添加字根時，清除之前的補碼/符號選擇、重複次數、聯想詞、同音字與符號選單（因為開始輸入新字根）
+            self.suggestions.clear();
+            self.homophone_of = None;
+            self.symbol_menu = None;
+            self.repeat_count = None;
             self.complement_selected = None;
+            self.complement_code = None;
+            self.complement_key = None;
+            self.complement_position = None;
         }
     }
 
@@ -61,15 +195,18 @@ impl InputMethodState {
     }
 
     /// 查詢候選字
-    pub fn lookup_candidates(&mut self, dictionary: &Dictionary) {
+    /// 字碼表的候選在載入時已依 weights 排好（見 CharDefs），使用者字頻的排序在這之後才套用
+    pub fn lookup_candidates(&mut self, dictionary: &Dictionary, sorter: &dyn CandidateSorter, usage: &FrequencyStats) {
         if self.current_code.is_empty() {
             self.candidates.clear();
             self.candidate_index = 0;
             return;
         }
 
-        if let Some(chars) = dictionary.lookup(&self.current_code) {
-            self.candidates = chars.clone();
+        if let Some(candidates) = dictionary.candidates(&self.current_code) {
+            self.candidates = candidates;
+            self.charset_filter.apply(&mut self.candidates);
+            sorter.arrange(&self.current_code, &mut self.candidates, usage);
             self.candidate_index = 0;
             debug!(
                 "查詢字根 '{}' 找到 {} 個候選字",
@@ -89,15 +226,21 @@ impl InputMethodState {
     }
 
     /// 取得當前頁的候選字
-    pub fn get_current_page_candidates(&self) -> Vec<String> {
+    pub fn get_current_page_candidates(&self) -> &[Candidate] {
         let start = self.candidate_index;
         let end = (start + self.candidates_per_page).min(self.candidates.len());
         
         if start >= self.candidates.len() {
-            return Vec::new();
+            return &[];
         }
         
-        self.candidates[start..end].to_vec()
+        &self.candidates[start..end]
+    }
+
+    /// 所有候選的顯示文字（測試用）
+    #[cfg(test)]
+    pub fn candidate_texts(&self) -> Vec<&str> {
+        self.candidates.iter().map(|candidate| candidate.text.as_str()).collect()
     }
 
     /// 是否有下一頁
@@ -124,34 +267,521 @@ impl InputMethodState {
         }
     }
 
-    /// 根據數字鍵選擇候選字（0-9）
-    /// 返回選中的字，如果無效返回 None
-    pub fn select_candidate(&self, index: usize) -> Option<String> {
-        let page_candidates = self.get_current_page_candidates();
-        if index < page_candidates.len() {
-            Some(page_candidates[index].clone())
-        } else {
-            None
-        }
+    /// 補碼選字的說明，例如「補碼 v → 第 2 候選：乙」，讓使用者確認選到的是第幾個候選
+    /// 以選字鍵改選後省略補碼鍵；符號選擇（沒有候選位置）時回傳 None
+    pub fn complement_label(&self) -> Option<String> {
+        let position = self.complement_position?;
+        let selected = self.complement_selected.as_ref()?;
+        Some(match self.complement_key {
+            Some(key) => format!("補碼 {} → 第 {} 候選：{}", key, position + 1, selected),
+            None => format!("第 {} 候選：{}", position + 1, selected),
+        })
+    }
+
+    /// 目前頁第 index 個候選（從 0 起算），沒有時回傳 None
+    pub fn select_candidate(&self, index: usize) -> Option<&Candidate> {
+        self.get_current_page_candidates().get(index)
     }
 }
 
+/// 上一次送出的紀錄（撤銷用）
+#[derive(Debug, Clone)]
+struct UndoEntry {
+    /// 撤銷後還原的字根
+    code: String,
+    /// 記錄字頻用的字根與候選（沒有計入字頻時為 None）
+    counted: Option<(String, String)>,
+    /// 實際送出的文字
+    output: String,
+    /// 送出時已放行的按鍵數（見 passed_keys）
+    passed_keys: u64,
+}
+
 /// 輸入法處理器
 pub struct InputMethodProcessor {
     state: InputMethodState,
     dictionary: Dictionary,
+    scheme: InputScheme,
+    /// 特殊碼規則（優先於字典查詢）
+    rules: RuleSet,
+    /// 日期/時間巨集碼（優先於特殊碼規則，送出時才展開）
+    time_macros: Vec<TimeMacro>,
+    /// 最近送出的文字（由舊到新，最多 RECENT_COMMITS_LEN 筆；給 repeat 規則、同音字與重送鍵使用）
+    recent_commits: VecDeque<String>,
+    /// 上一次送出的紀錄，撤銷熱鍵用（送出後按了其他鍵就作廢，見 forget_undo）
+    undo: Option<UndoEntry>,
+    /// 鍵盤鉤子放行的按鍵計數，送出後有變化就不能撤銷
+    /// 鉤子不取處理器的鎖，只增加這個計數（緊急放行時處理器可能卡住）
+    passed_keys: Arc<AtomicU64>,
+    /// 字頻統計（選用次數與先後，排序策略據此排序候選）
+    frequency: FrequencyStats,
+    /// 候選排序策略（見 sorter 模組）
+    sorter: Box<dyn CandidateSorter>,
+    /// 目前的候選是否由特殊碼規則產生（規則產生的候選不計入字頻）
+    rule_candidates: bool,
+    /// 選字確認模式的逾時時間（None 表示按選字鍵直接送出）
+    selection_confirm_timeout: Option<Duration>,
+    /// 確認模式下已高亮、等待再按一次的候選（候選位置, 高亮時間）
+    pending_selection: Option<(usize, Instant)>,
+    /// 聯想表（None 表示不提示聯想詞）
+    associations: Option<AssociationTable>,
+    /// 字根打滿且只有一個候選時自動送出
+    auto_commit: bool,
+    /// 組字時提示可以接著輸入哪些字根（見 completion_letters）
+    completion_hints: bool,
+    /// 以較長的字根送出、字碼表裡還有更短的字根時提示簡碼（見 shorter_code_hint）
+    shorter_code_hints: bool,
+    /// 最近一次送出的字與它更短的字根，下一次送出時更新
+    shorter_code: Option<(String, String)>,
+    /// 自動送出的文字，等呼叫端以 take_auto_commit 取走
+    auto_committed: Option<String>,
+    /// 重複送出的觸發鍵（None 表示不使用，見 start_repeat_count）
+    repeat_key: Option<char>,
+    /// 符號選單鍵（None 表示不使用，見 open_symbol_menu）
+    symbol_menu_key: Option<char>,
+    /// 符號選單的符號表
+    symbols: SymbolTable,
+    /// 半形模式：符號改送出半形 ASCII 標點（Shift+Space 切換）
+    half_width: bool,
+    /// 整句組字模式下累積的送出文字（每次送出一段；None 表示選字後直接送出）
+    composition: Option<Vec<String>>,
+    /// 輸入事件的觀察者
+    observers: ObserverList,
+    /// 上次通知觀察者時是否正在組字
+    composing: bool,
 }
 
 impl InputMethodProcessor {
     pub fn new(dictionary: Dictionary) -> Self {
+        Self::with_scheme(dictionary, InputScheme::default())
+    }
+
+    /// 以指定的輸入法方案創建處理器（補碼鍵、選字鍵、字根長度由方案決定）
+    pub fn with_scheme(dictionary: Dictionary, scheme: InputScheme) -> Self {
+        let mut state = InputMethodState::new();
+        state.max_code_length = scheme.max_code_length;
+        let rules = RuleSet::compile(&scheme.rules);
         Self {
-            state: InputMethodState::new(),
+            state,
             dictionary,
+            scheme,
+            rules,
+            time_macros: Vec::new(),
+            recent_commits: VecDeque::new(),
+            undo: None,
+            passed_keys: Arc::new(AtomicU64::new(0)),
+            frequency: FrequencyStats::new(),
+            sorter: Box::new(TableOrder),
+            rule_candidates: false,
+            selection_confirm_timeout: None,
+            pending_selection: None,
+            associations: None,
+            auto_commit: false,
+            completion_hints: false,
+            shorter_code_hints: false,
+            shorter_code: None,
+            auto_committed: None,
+            repeat_key: None,
+            symbol_menu_key: None,
+            symbols: SymbolTable::default(),
+            half_width: false,
+            composition: None,
+            observers: ObserverList::default(),
+            composing: false,
+        }
+    }
+
+    /// 註冊輸入事件的觀察者（只保留弱引用，見 input_events 模組）
+    pub fn subscribe(&mut self, observer: &Arc<dyn InputObserver>) {
+        self.observers.subscribe(observer);
+    }
+
+    /// 組字狀態有變化後通知觀察者：開始組字、候選更新，或沒有送出就清空了
+    fn notify_changed(&mut self) {
+        let composing = !self.state.current_code.is_empty() || self.state.is_listing();
+        if composing && !self.composing {
+            self.observers.notify(&InputEvent::CompositionStarted);
+        }
+        if composing {
+            self.observers.notify(&InputEvent::CandidatesUpdated);
+        } else if self.composing {
+            self.observers.notify(&InputEvent::Cleared);
+        }
+        self.composing = composing;
+    }
+
+    /// 取得目前使用的輸入法方案
+    pub fn scheme(&self) -> &InputScheme {
+        &self.scheme
+    }
+
+    /// 取得目前使用的字典
+    pub fn dictionary(&self) -> &Dictionary {
+        &self.dictionary
+    }
+
+    /// 取得目前使用的字典（可修改，例如套用使用者字典）
+    pub fn dictionary_mut(&mut self) -> &mut Dictionary {
+        &mut self.dictionary
+    }
+
+    /// 設定字頻統計（啟動或切換使用者時從檔案載入）
+    pub fn set_frequency(&mut self, frequency: FrequencyStats) {
+        self.frequency = frequency;
+    }
+
+    /// 設定候選字集篩選（字集以外的漢字藏起來或移到最後面）
+    pub fn set_charset_filter(&mut self, filter: CharsetFilter) {
+        self.state.charset_filter = filter;
+        self.refresh_current_candidates();
+    }
+
+    /// 設定候選排序策略
+    pub fn set_sorter(&mut self, sorter: Box<dyn CandidateSorter>) {
+        info!("候選排序方式: {}", sorter.name());
+        self.sorter = sorter;
+        self.refresh_current_candidates();
+    }
+
+    /// 有新紀錄時取出字頻統計的複本並標記為已儲存（呼叫端在鎖外寫檔）
+    pub fn take_frequency_snapshot(&mut self) -> Option<FrequencyStats> {
+        if !self.frequency.is_dirty() {
+            return None;
         }
+        self.frequency.mark_saved();
+        Some(self.frequency.clone())
+    }
+
+    /// 設定選字確認模式：第一次按選字鍵只高亮，再按一次同一鍵或按 Space 才送出，
+    /// 超過 timeout 沒有確認就取消高亮；None 表示關閉
+    pub fn set_selection_confirm(&mut self, timeout: Option<Duration>) {
+        self.selection_confirm_timeout = timeout;
+        self.pending_selection = None;
+    }
+
+    /// 選字確認模式的逾時時間（None 表示按選字鍵直接送出）
+    pub fn selection_confirm(&self) -> Option<Duration> {
+        self.selection_confirm_timeout
+    }
+
+    /// 設定字根達到最長碼長、只有一個候選且沒有更長的字根時是否自動送出
+    pub fn set_auto_commit(&mut self, enabled: bool) {
+        self.auto_commit = enabled;
+        self.auto_committed = None;
+    }
+
+    /// 設定組字時是否提示可以接著輸入的字根
+    pub fn set_completion_hints(&mut self, enabled: bool) {
+        self.completion_hints = enabled;
+    }
+
+    /// 設定是否提示簡碼；開啟時先建好反查索引，第一次送字不必等
+    pub fn set_shorter_code_hints(&mut self, enabled: bool) {
+        self.shorter_code_hints = enabled;
+        self.shorter_code = None;
+        if enabled {
+            self.dictionary.warm_reverse_index();
+        }
+    }
+
+    /// 簡碼提示：上一次送出的字有更短的字根時，例如「「的」可用 2 碼: xx」
+    pub fn shorter_code_hint(&self) -> Option<String> {
+        let (text, code) = self.shorter_code.as_ref()?;
+        Some(format!("「{}」可用 {} 碼: {}", text, code.chars().count(), code))
+    }
+
+    /// 補全提示：目前的字根接著輸入哪些字根還能組成更長的字根（例如 a → b、d、x）
+    /// 沒有開啟提示、沒有在輸入一般字根（萬用字元、emoji 短碼、列表、暫時英文）時為空
+    pub fn completion_letters(&self) -> Vec<char> {
+        let code = &self.state.current_code;
+        if !self.completion_hints
+            || code.is_empty()
+            || is_wildcard(code)
+            || self.state.is_emoji_composition()
+            || self.state.is_listing()
+            || self.state.temp_english.is_some()
+        {
+            return Vec::new();
+        }
+        self.dictionary.next_letters(code)
+    }
+
+    /// 目前的字根打錯了：查不到候選，也不是更長字根的開頭（萬用字元、emoji 短碼、暫時英文不算）
+    pub fn is_dead_end(&self) -> bool {
+        let code = &self.state.current_code;
+        !code.is_empty()
+            && self.state.candidates.is_empty()
+            && !is_wildcard(code)
+            && !self.state.is_emoji_composition()
+            && self.state.temp_english.is_none()
+            && !self.dictionary.has_prefix(code)
+    }
+
+    /// 目前的字根還是 code 時重新查詢候選（例如線上備援查詢寫入使用者字典後），回傳是否重新查詢
+    pub fn refresh_code(&mut self, code: &str) -> bool {
+        if self.state.current_code != code {
+            return false;
+        }
+        self.refresh_candidates();
+        true
+    }
+
+    /// 取走 handle_code_input 自動送出的文字（呼叫端負責送到前景程式）
+    pub fn take_auto_commit(&mut self) -> Option<String> {
+        self.auto_committed.take()
+    }
+
+    /// 確認模式的高亮已逾時時取消高亮，回傳是否有變化（呼叫端據此更新顯示）
+    pub fn expire_pending_selection(&mut self) -> bool {
+        let (Some((position, since)), Some(timeout)) = (self.pending_selection, self.selection_confirm_timeout) else {
+            return false;
+        };
+        // 高亮的候選已被送出或清除（例如輸入了新字根），只需忘掉等待狀態
+        if self.state.complement_position != Some(position) {
+            self.pending_selection = None;
+            return false;
+        }
+        if since.elapsed() < timeout {
+            return false;
+        }
+        debug!("選字確認逾時，取消高亮第 {} 個候選", position + 1);
+        self.pending_selection = None;
+        self.state.complement_selected = None;
+        self.state.complement_code = None;
+        self.state.complement_key = None;
+        self.state.complement_position = None;
+        self.notify_changed();
+        true
+    }
+
+    /// 設定是否在送出後提示聯想詞（開啟時由目前的字碼表建立聯想表）
+    pub fn set_associated_phrases(&mut self, enabled: bool) {
+        self.state.suggestions.clear();
+        self.associations = enabled.then(|| self.build_associations());
+    }
+
+    /// 字碼表有變動（例如切換使用者字典）後重建聯想表；未開啟聯想時不動作
+    pub fn refresh_associations(&mut self) {
+        if self.associations.is_some() {
+            self.associations = Some(self.build_associations());
+        }
+    }
+
+    /// 是否在送出後提示聯想詞
+    pub fn associated_phrases_enabled(&self) -> bool {
+        self.associations.is_some()
+    }
+
+    fn build_associations(&self) -> AssociationTable {
+        let table = AssociationTable::from_dictionary(&self.dictionary);
+        if table.is_empty() {
+            info!("字碼表中沒有詞組，不會提示聯想詞");
+        } else {
+            info!("建立聯想表：{} 個字有聯想詞", table.len());
+        }
+        table
+    }
+
+    /// 送出後載入聯想詞（最多一頁）
+    fn suggest_after(&mut self, committed: &str) {
+        self.state.suggestions = match &self.associations {
+            Some(table) => table.suggest(committed).iter().take(self.state.candidates_per_page).cloned().collect(),
+            None => Vec::new(),
+        };
+    }
+
+    /// 設定 emoji 短碼的前綴鍵（None 表示不使用），會清除目前的輸入
+    pub fn set_emoji_prefix(&mut self, prefix: Option<char>) {
+        self.state.clear();
+        self.state.emoji_prefix = prefix;
+    }
+
+    /// 目前有效的 emoji 前綴鍵（沒有載入 emoji 表時為 None，前綴鍵照常輸入）
+    pub fn emoji_prefix(&self) -> Option<char> {
+        self.state.emoji_prefix.filter(|_| self.dictionary.emoji.is_some())
+    }
+
+    /// 切換全形/半形模式（Shift+Space），回傳切換後是否為半形
+    pub fn toggle_half_width(&mut self) -> bool {
+        self.half_width = !self.half_width;
+        info!("切換為{}模式", if self.half_width { "半形" } else { "全形" });
+        self.half_width
+    }
+
+    /// 是否為半形模式
+    pub fn is_half_width(&self) -> bool {
+        self.half_width
+    }
+
+    /// 符號候選的顯示文字：半形模式下把全形標點換成對應的半形 ASCII 標點
+    fn symbol_output(&self, symbol: &str) -> String {
+        if self.half_width {
+            to_half_width(symbol)
+        } else {
+            symbol.to_string()
+        }
+    }
+
+    /// 設定重複送出的觸發鍵（None 表示不使用）
+    pub fn set_repeat_key(&mut self, key: Option<char>) {
+        self.repeat_key = key;
+    }
+
+    /// 重複送出的觸發鍵
+    pub fn repeat_key(&self) -> Option<char> {
+        self.repeat_key
+    }
+
+    /// 設定日期/時間巨集碼（空清單表示不使用）
+    pub fn set_time_macros(&mut self, macros: Vec<TimeMacro>) {
+        self.time_macros = macros;
+    }
+
+    /// 字根對應的日期/時間巨集碼格式
+    fn time_macro_format(&self, code: &str) -> Option<&str> {
+        self.time_macros.iter().find(|m| m.code == code).map(|m| m.format.as_str())
+    }
+
+    /// 設定符號選單鍵與符號表（鍵為 None 表示不使用）
+    pub fn set_symbol_menu(&mut self, key: Option<char>, symbols: SymbolTable) {
+        self.symbol_menu_key = key;
+        self.symbols = symbols;
+    }
+
+    /// 符號選單鍵
+    pub fn symbol_menu_key(&self) -> Option<char> {
+        self.symbol_menu_key
+    }
+
+    /// 目前列出符號的分類名稱（沒有開啟符號選單或還在列出分類時為 None）
+    pub fn symbol_category(&self) -> Option<&str> {
+        match self.state.symbol_menu? {
+            SymbolMenu::Categories => None,
+            SymbolMenu::Category(index) => self.symbols.categories.get(index).map(|category| category.name.as_str()),
+        }
+    }
+
+    /// 有候選時按重複鍵：之後輸入的數字是重複次數，送出時把選中的候選重複送出（例如 a\5 再按 Space 送出五個「一」）
+    /// 沒有候選或已在輸入重複次數時回傳 false
+    pub fn start_repeat_count(&mut self) -> bool {
+        if self.state.candidates.is_empty() || self.state.repeat_count.is_some() {
+            return false;
+        }
+        self.state.repeat_count = Some(0);
+        self.pending_selection = None;
+        true
+    }
+
+    /// 輸入重複次數的一位數字（最多 MAX_REPEAT_COUNT 次）；沒有按重複鍵時回傳 false（數字照常選字）
+    pub fn push_repeat_digit(&mut self, digit: char) -> bool {
+        let (Some(count), Some(value)) = (self.state.repeat_count, digit.to_digit(10)) else {
+            return false;
+        };
+        self.state.repeat_count = Some((count * 10 + value as usize).min(MAX_REPEAT_COUNT));
+        true
+    }
+
+    /// 設定整句組字模式：開啟時選字送出的文字先累積起來，沒有字根時按 Enter 才一次送出
+    pub fn set_compose_sentence(&mut self, enabled: bool) {
+        self.composition = enabled.then(Vec::new);
+    }
+
+    /// 整句組字模式下把送出的文字加入累積的句子；沒有開啟時回傳 false（呼叫端直接送出）
+    pub fn push_composition(&mut self, text: &str) -> bool {
+        let Some(composition) = &mut self.composition else {
+            return false;
+        };
+        composition.push(text.to_string());
+        true
+    }
+
+    /// 刪掉累積的句子中最後送出的一段（詞組整段刪除），沒有累積文字時回傳 false
+    pub fn pop_composition(&mut self) -> bool {
+        self.composition.as_mut().is_some_and(|composition| composition.pop().is_some())
+    }
+
+    /// 清除累積的句子，原本就是空的時回傳 false
+    pub fn clear_composition(&mut self) -> bool {
+        match &mut self.composition {
+            Some(composition) if !composition.is_empty() => {
+                composition.clear();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 目前累積的句子
+    pub fn composition(&self) -> String {
+        self.composition.as_deref().map(|composition| composition.concat()).unwrap_or_default()
+    }
+
+    /// 取出整句送出（沒有累積文字時回傳 None）；送出後累積的各段不能再撤銷
+    pub fn take_composition(&mut self) -> Option<String> {
+        let composition = self.composition.as_mut().filter(|composition| !composition.is_empty())?;
+        let text = composition.concat();
+        composition.clear();
+        self.undo = None;
+        Some(text)
+    }
+
+    /// 設定停用字清單（啟動或切換使用者時從檔案載入）
+    pub fn set_blocklist(&mut self, blocklist: Blocklist) {
+        self.dictionary.set_blocklist(blocklist);
+    }
+    
+    /// 停用候選（例如 GUI 右鍵「隱藏此字」），目前的候選立即更新
+    /// 原本就已停用時回傳 false
+    pub fn block_candidate(&mut self, text: &str) -> bool {
+        if !self.dictionary.blocklist.insert(text) {
+            return false;
+        }
+        info!("停用候選: {}", text);
+        self.refresh_current_candidates();
+        true
+    }
+    
+    /// 恢復已停用的候選，原本沒有停用時回傳 false
+    pub fn unblock_candidate(&mut self, text: &str) -> bool {
+        if !self.dictionary.blocklist.remove(text) {
+            return false;
+        }
+        info!("恢復候選: {}", text);
+        self.refresh_current_candidates();
+        true
+    }
+    
+    /// 停用字清單有修改時取出複本並標記為已儲存（呼叫端在鎖外寫檔）
+    pub fn take_blocklist_snapshot(&mut self) -> Option<Blocklist> {
+        let blocklist = &mut self.dictionary.blocklist;
+        if !blocklist.is_dirty() {
+            return None;
+        }
+        blocklist.mark_saved();
+        Some(blocklist.clone())
+    }
+    
+    /// 正在輸入字根時重新查詢候選（停用字清單變動後呼叫）
+    fn refresh_current_candidates(&mut self) {
+        if !self.state.current_code.is_empty() {
+            self.refresh_candidates();
+        }
+    }
+    
+    /// 查詢字根的候選，依排序策略排序
+    fn lookup_ordered(&self, code: &str) -> Option<Vec<Candidate>> {
+        let mut candidates = self.dictionary.candidates(code)?;
+        self.state.charset_filter.apply(&mut candidates);
+        if candidates.is_empty() {
+            return None;
+        }
+        self.sorter.arrange(code, &mut candidates, &self.frequency);
+        Some(candidates)
     }
 
     /// 處理字根輸入
     /// 返回 (是否處理成功, 補碼選擇的候選字)
+    /// 開啟自動送出時，字根打滿且只有一個候選就直接送出，送出的文字以 take_auto_commit 取得
     pub fn handle_code_input(&mut self, ch: char) -> (bool, Option<String>) {
         // 只接受 a-z 的字根
         if !ch.is_ascii_lowercase() && !ch.is_ascii_uppercase() {
@@ -160,62 +790,68 @@ impl InputMethodProcessor {
 
         let ch_lower = ch.to_ascii_lowercase();
         
-        // 補碼機制：v/r/s/f/w 分別選擇候選2/3/4/5/6
-        // 如果輸入的是 v/r/s/f/w，且當前字根（加上補碼後）不在字典中，
+        // 補碼機制：v/r/s/f/w 分別選擇候選2/3/4/5/6（補碼鍵由輸入法方案決定）
+        // 如果輸入的是補碼鍵，且當前字根（加上補碼後）不在字典中，
         // 但當前字根（不加補碼）存在，則選擇對應的候選字
         // 
         // 補碼機制的觸發條件（參考 Python 版本的實現）：
         // 1. 加上補碼後的字根不在字典中
         // 2. 當前字根不為空
         // 3. 當前字根存在且有足夠的候選字
-        // 4. 如果加上補碼後的字根長度 < 5，檢查是否有以該組合開頭的更長字根
+        // 4. 如果加上補碼後的字根長度 < 最大長度，檢查是否有以該組合開頭的更長字根
         //    如果沒有，則觸發補碼；如果有，則不觸發（讓用戶繼續輸入）
-        // 5. 如果加上補碼後的字根長度 = 5，如果不在字典中，應該觸發補碼
+        // 5. 如果加上補碼後的字根長度 = 最大長度，如果不在字典中，應該觸發補碼
         // 
-        // 補碼對應關係（參考 Python 版本）：
+        // 補碼對應關係（嘸蝦米方案，參考 Python 版本）：
         // - v: 候選2（索引1），需要 >= 2 個候選字
         // - r: 候選3（索引2），需要 >= 3 個候選字
         // - s: 候選4（索引3），需要 >= 4 個候選字
         // - f: 候選5（索引4），需要 >= 5 個候選字
         // - w: 候選6（索引5），需要 >= 6 個候選字
-        if ch_lower == 'v' || ch_lower == 'r' || ch_lower == 's' || ch_lower == 'f' || ch_lower == 'w' {
+        if let Some(candidate_index) = self.scheme.complement_index(ch_lower) {
             let current_code = self.state.current_code.clone();
             
             // 先嘗試加上補碼後的字根
             let code_with_suffix = format!("{}{}", current_code, ch_lower);
-            let exists_with_suffix = self.dictionary.lookup(&code_with_suffix).is_some();
+            let (with_suffix, has_longer) = self.dictionary.lookup_ex(&code_with_suffix);
+            let exists_with_suffix = with_suffix.is_some();
             
-            if !exists_with_suffix && !current_code.is_empty() {
+            // 萬用字元查詢與 emoji 短碼輸入時補碼鍵一律當成字根
+            if !exists_with_suffix
+                && !current_code.is_empty()
+                && !is_wildcard(&current_code)
+                && !self.state.is_emoji_composition()
+            {
                 // 檢查當前字根（不加補碼）是否存在
-                if let Some(candidates) = self.dictionary.lookup(&current_code) {
-                    // 根據補碼字符確定候選字索引和所需的最小候選字數量
-                    let (candidate_index, min_candidates) = match ch_lower {
-                        'v' => (1, 2), // v 選擇候選2（索引1），需要 >= 2 個候選字
-                        'r' => (2, 3), // r 選擇候選3（索引2），需要 >= 3 個候選字
-                        's' => (3, 4), // s 選擇候選4（索引3），需要 >= 4 個候選字
-                        'f' => (4, 5), // f 選擇候選5（索引4），需要 >= 5 個候選字
-                        'w' => (5, 6), // w 選擇候選6（索引5），需要 >= 6 個候選字
-                        _ => return (false, None), // 不應該到達這裡
-                    };
+                if let Some(candidates) = self.lookup_ordered(&current_code) {
+                    // 補碼選擇第 candidate_index + 1 個候選字，需要至少這麼多候選字
+                    let min_candidates = candidate_index + 1;
                     
                     // 檢查候選字數量是否足夠
-                    if candidates.len() >= min_candidates && candidates.len() > candidate_index {
+                    if candidates.len() >= min_candidates {
                         // 判斷是否應該觸發補碼
-                        let should_trigger_complement = if code_with_suffix.len() < 5 {
-                            // 長度 < 5，檢查是否有以 code_with_suffix 開頭的更長字根
+                        let should_trigger_complement = if code_with_suffix.len() < self.scheme.max_code_length {
+                            // 長度 < 最大長度，檢查是否有以 code_with_suffix 開頭的更長字根
                             // 例如："si" + "s" = "sis"（3碼），檢查是否有 "sisp" 等
                             // 如果沒有，則觸發補碼；如果有，則不觸發（讓用戶繼續輸入）
-                            !self.dictionary.has_prefix(&code_with_suffix)
+                            !has_longer
                         } else {
-                            // 長度 = 5，已經達到最大長度，如果不在字典中，應該觸發補碼
+                            // 長度 = 最大長度，已經達到最大長度，如果不在字典中，應該觸發補碼
                             // 因為無法繼續輸入更長的字根
                             true
                         };
                         
                         if should_trigger_complement {
                             // 選擇對應的候選字，存儲在狀態中等待 Space 鍵送出
-                            let selected = candidates[candidate_index].clone();
+                            // 候選列表保留給 GUI 列出，使用者可以按選字鍵改選（見 handle_complement_reselect）
+                            let selected = candidates[candidate_index].text.clone();
+                            self.state.candidates = candidates;
+                            self.state.candidate_index = 0;
                             self.state.complement_selected = Some(selected.clone());
+                            self.state.complement_code = Some(current_code.clone());
+                            self.state.complement_key = Some(ch_lower);
+                            self.state.complement_position = Some(candidate_index);
+                            self.notify_changed();
                             // 不清除字根，保持當前狀態，等待 Space 鍵
                             return (true, Some(selected));
                         }
@@ -225,16 +861,205 @@ impl InputMethodProcessor {
             
             // 如果補碼機制不適用，繼續正常流程（添加補碼字符作為字根）
             self.state.append_code(ch_lower);
-            self.state.lookup_candidates(&self.dictionary);
+            self.refresh_candidates();
+            self.try_auto_commit();
             return (true, None);
         }
         
         // 正常添加字根
         self.state.append_code(ch_lower);
-        self.state.lookup_candidates(&self.dictionary);
+        self.refresh_candidates();
+        self.try_auto_commit();
         (true, None)
     }
 
+    /// 字根達到最長碼長、只有一個候選且沒有以它開頭的更長字根時，不等 Space 直接送出
+    /// 萬用字元查詢與 emoji 短碼不自動送出
+    fn try_auto_commit(&mut self) {
+        let code = &self.state.current_code;
+        if !self.auto_commit
+            || code.len() < self.scheme.max_code_length
+            || self.state.candidates.len() != 1
+            || is_wildcard(code)
+            || self.state.is_emoji_composition()
+            || self.dictionary.has_prefix(code)
+        {
+            return;
+        }
+        debug!("字根 '{}' 只有一個候選，自動送出", code);
+        self.auto_committed = self.handle_space();
+    }
+
+    /// 處理萬用字元 *（任意長度，例如 a*d）或 ?（剛好一個字根，例如 a?c），只記得部分字根時使用
+    /// 沒有字根時不處理（讓 * 與 ? 照常輸入）；連續的 * 只算一個
+    pub fn handle_wildcard_input(&mut self, wildcard: char) -> bool {
+        if self.state.current_code.is_empty() {
+            return false;
+        }
+        if wildcard == '*' && self.state.current_code.ends_with('*') {
+            return true;
+        }
+        self.state.append_code(wildcard);
+        self.refresh_candidates();
+        true
+    }
+
+    /// 處理 emoji 前綴鍵：沒有字根時開始輸入 emoji 短碼，輸入短碼後再按一次（:smile:）送出第一個候選
+    /// 返回 (是否處理成功, 送出的文字)；不是前綴鍵、沒有載入 emoji 表或正在輸入一般字根時不處理
+    pub fn handle_emoji_prefix(&mut self, ch: char) -> (bool, Option<String>) {
+        if self.emoji_prefix() != Some(ch) {
+            return (false, None);
+        }
+        if self.state.current_code.is_empty() {
+            self.state.append_code(ch);
+            self.refresh_candidates();
+            return (true, None);
+        }
+        if !self.state.is_emoji_composition() {
+            return (false, None);
+        }
+        // 只有前綴鍵、還沒輸入短碼時忽略
+        if self.state.current_code.len() == ch.len_utf8() {
+            return (true, None);
+        }
+        (true, self.handle_space())
+    }
+
+    /// 是否在暫時英文模式（見 start_temp_english）
+    pub fn is_temp_english(&self) -> bool {
+        self.state.temp_english.is_some()
+    }
+
+    /// 沒有字根時按 `：進入暫時英文模式，之後的字元原樣收進緩衝區，Space/Enter 送出，不切換英/肥模式
+    /// 正在組字或已在暫時英文模式時回傳 false
+    pub fn start_temp_english(&mut self) -> bool {
+        if !self.state.current_code.is_empty() || self.is_temp_english() {
+            return false;
+        }
+        self.state.clear();
+        self.state.temp_english = Some(String::new());
+        self.pending_selection = None;
+        debug!("進入暫時英文模式");
+        true
+    }
+
+    /// 暫時英文模式下輸入一個字元；緩衝區是空的時再按一次 ` 直接送出 `
+    pub fn push_temp_english(&mut self, ch: char) -> Option<String> {
+        let buffer = self.state.temp_english.as_mut()?;
+        buffer.push(ch);
+        if buffer.as_str() == "`" {
+            return self.commit_temp_english();
+        }
+        None
+    }
+
+    /// 暫時英文模式下刪除最後一個字元，緩衝區已經是空的時離開暫時英文模式
+    pub fn temp_english_backspace(&mut self) {
+        if let Some(buffer) = self.state.temp_english.as_mut() {
+            if buffer.pop().is_none() {
+                self.state.temp_english = None;
+            }
+        }
+    }
+
+    /// 離開暫時英文模式並送出緩衝區（Space/Enter），緩衝區是空的時只離開
+    pub fn commit_temp_english(&mut self) -> Option<String> {
+        let text = self.state.temp_english.take().filter(|text| !text.is_empty())?;
+        debug!("暫時英文送出: {}", text);
+        self.record_commit(&text);
+        // 沒有字根可以還原，不能撤銷
+        self.undo = None;
+        self.observers.notify(&InputEvent::Committed(text.clone()));
+        Some(text)
+    }
+
+    /// 肥模式下按 Shift+字母（ShiftLetter::Commit 或 Word）：不離開肥模式打出大寫字母
+    /// 正在組字或列出候選時先以 Space 的方式送出第一個候選（沒有候選時放棄字根），回傳要送出的文字
+    pub fn handle_shift_letter(&mut self, behavior: ShiftLetter, ch: char) -> Option<String> {
+        let composing = !self.state.current_code.is_empty() || self.state.is_listing();
+        let committed = if composing { self.handle_space() } else { None };
+        // 符號選單的第一層按 Space 不會送出，直接收起
+        self.state.clear();
+        self.start_temp_english();
+        self.push_temp_english(ch.to_ascii_uppercase());
+        let letter = match behavior {
+            ShiftLetter::Word => None,
+            _ => self.commit_temp_english(),
+        };
+        self.notify_changed();
+        match (committed, letter) {
+            (Some(committed), Some(letter)) => Some(committed + &letter),
+            (committed, letter) => committed.or(letter),
+        }
+    }
+
+    /// 同音字熱鍵：列出上一次送出文字最後一個字的同音字，之後以選字鍵或 Space 選用
+    /// 正在輸入字根、還沒送出過字或查不到同音字時回傳 false
+    pub fn show_homophones(&mut self) -> bool {
+        if !self.state.current_code.is_empty() {
+            return false;
+        }
+        let Some(ch) = self.recent_commits.back().and_then(|text| text.chars().last()) else {
+            return false;
+        };
+        let homophones = self.dictionary.homophones(ch);
+        if homophones.is_empty() {
+            debug!("「{}」沒有同音字", ch);
+            return false;
+        }
+        let candidates = listing(homophones.iter().map(char::to_string));
+        self.state.clear();
+        self.state.candidates = candidates;
+        self.state.homophone_of = Some(ch);
+        self.pending_selection = None;
+        self.notify_changed();
+        true
+    }
+
+    /// 符號選單鍵：候選區列出符號表的分類，選了分類再列出該分類的符號（見 symbols 模組）
+    /// 正在輸入字根或暫時英文時回傳 false，呼叫端讓按鍵照常處理
+    pub fn open_symbol_menu(&mut self) -> bool {
+        if !self.state.current_code.is_empty() || self.state.temp_english.is_some() || self.symbols.categories.is_empty() {
+            return false;
+        }
+        self.state.clear();
+        self.state.candidates = listing(self.symbols.category_names());
+        self.state.symbol_menu = Some(SymbolMenu::Categories);
+        self.pending_selection = None;
+        self.notify_changed();
+        true
+    }
+
+    /// 符號選單選了第 index 個分類（從 0 起算），改列出該分類的符號
+    fn enter_symbol_category(&mut self, index: usize) -> bool {
+        let Some(category) = self.symbols.categories.get(index) else {
+            return false;
+        };
+        debug!("符號選單: 列出「{}」", category.name);
+        self.state.candidates = listing(category.symbols.iter().cloned());
+        self.state.candidate_index = 0;
+        self.state.symbol_menu = Some(SymbolMenu::Category(index));
+        self.pending_selection = None;
+        self.notify_changed();
+        true
+    }
+
+    /// 符號選單退回上一層：列出符號時回到分類，列出分類時關閉選單；沒有開啟時回傳 false
+    pub fn close_symbol_menu(&mut self) -> bool {
+        match self.state.symbol_menu {
+            None => return false,
+            Some(SymbolMenu::Category(_)) => {
+                self.state.candidates = listing(self.symbols.category_names());
+                self.state.candidate_index = 0;
+                self.state.symbol_menu = Some(SymbolMenu::Categories);
+            }
+            Some(SymbolMenu::Categories) => self.state.clear(),
+        }
+        self.pending_selection = None;
+        self.notify_changed();
+        true
+    }
+
     /// 處理符號輸入（例如點號 `.`）
     /// 返回 (是否處理成功, 符號選擇的候選字)
     /// 
@@ -257,13 +1082,15 @@ impl InputMethodProcessor {
             let code_with_symbol = format!("{}{}", current_code, symbol);
             
             // 查詢字典中是否有這個符號組合
-            if let Some(candidates) = self.dictionary.lookup(&code_with_symbol) {
+            if let Some(candidates) = self.lookup_ordered(&code_with_symbol) {
                 if let Some(first_symbol) = candidates.first() {
                     // 找到符號映射，存儲在狀態中等待 Space 鍵送出
-                    let selected = first_symbol.clone();
+                    let selected = self.symbol_output(&first_symbol.text);
                     self.state.complement_selected = Some(selected.clone());
+                    self.state.complement_code = Some(code_with_symbol.clone());
                     // 不清除字根，保持當前狀態，等待 Space 鍵
                     debug!("✅ 從字典表找到符號映射: '{}' -> '{}'", code_with_symbol, selected);
+                    self.notify_changed();
                     return (true, Some(selected));
                 }
             }
@@ -276,25 +1103,29 @@ impl InputMethodProcessor {
             let new_code = self.state.current_code.clone();
             
             // 查找組合（例如 "." + "." = ".."）
-            if let Some(candidates) = self.dictionary.lookup(&new_code) {
+            if let Some(candidates) = self.lookup_ordered(&new_code) {
                 if let Some(first_symbol) = candidates.first() {
                     // 找到組合映射，存儲在狀態中等待 Space 鍵送出
-                    let selected = first_symbol.clone();
+                    let selected = self.symbol_output(&first_symbol.text);
                     self.state.complement_selected = Some(selected.clone());
+                    self.state.complement_code = Some(new_code.clone());
                     debug!("✅ 從字典表找到符號組合映射: '{}' -> '{}'", new_code, selected);
+                    self.notify_changed();
                     return (true, Some(selected));
                 }
             }
             
             // 如果組合不存在，查找單獨的符號（例如 "." 對應 "。"）
             let symbol_str = symbol.to_string();
-            if let Some(candidates) = self.dictionary.lookup(&symbol_str) {
+            if let Some(candidates) = self.lookup_ordered(&symbol_str) {
                 if let Some(first_symbol) = candidates.first() {
                     // 找到單獨符號映射，存儲在狀態中等待 Space 鍵送出
-                    let selected = first_symbol.clone();
+                    let selected = self.symbol_output(&first_symbol.text);
                     self.state.complement_selected = Some(selected.clone());
+                    self.state.complement_code = Some(symbol_str.clone());
                     // 字根已經包含符號，保持不變
                     debug!("✅ 從字典表找到單獨符號映射: '{}' -> '{}'", symbol_str, selected);
+                    self.notify_changed();
                     return (true, Some(selected));
                 }
             }
@@ -308,6 +1139,155 @@ impl InputMethodProcessor {
         (false, None)
     }
 
+    /// 處理選字鍵（由輸入法方案決定，嘸蝦米為數字鍵 1-9、0）
+    /// 不是選字鍵或沒有對應的候選字時返回 None
+    pub fn handle_selection_key(&mut self, key: char) -> Option<String> {
+        let index = self.scheme.selection_index(key)?;
+        
+        // 符號選單的第一層：選字鍵選的是分類，不送出
+        if self.state.symbol_menu == Some(SymbolMenu::Categories) {
+            if self.state.select_candidate(index).is_some() {
+                self.enter_symbol_category(self.state.candidate_index + index);
+            }
+            return None;
+        }
+        if let Some(selected) = self.state.select_candidate(index).cloned() {
+            let result = self.commit_output(selected);
+            self.state.clear();
+            self.suggest_after(&result);
+            Some(result)
+        } else if self.state.current_code.is_empty() && self.state.candidates.is_empty() {
+            // 還沒輸入下一個字根：選字鍵選用聯想詞
+            let selected = self.state.suggestions.get(index)?.clone();
+            debug!("選用聯想詞: {}", selected);
+            self.record_commit(&selected);
+            // 聯想詞沒有字根可以還原，不能撤銷
+            self.undo = None;
+            self.observers.notify(&InputEvent::Committed(selected.clone()));
+            self.state.clear();
+            self.suggest_after(&selected);
+            Some(selected)
+        } else {
+            None
+        }
+    }
+
+    /// 選字鍵對應的候選實際會送出的文字，不送出、不改變組字狀態（Alt + 選字鍵複製候選用）
+    pub fn peek_selection_key(&self, key: char) -> Option<String> {
+        let index = self.scheme.selection_index(key)?;
+        let selected = self.state.select_candidate(index)?.clone();
+        Some(self.resolve_output(selected))
+    }
+
+    /// 翻頁鍵：候選翻到下一頁（forward）或上一頁，已在第一頁或最後一頁時不動
+    /// 沒有在組字（也沒有列出同音字或符號選單）時回傳 false，呼叫端讓按鍵照常輸入
+    pub fn page_candidates(&mut self, forward: bool) -> bool {
+        if self.state.current_code.is_empty() && !self.state.is_listing() {
+            return false;
+        }
+        // 選字確認模式下高亮的是目前頁的位置，換頁後作廢
+        self.pending_selection = None;
+        if forward {
+            self.state.next_page();
+        } else {
+            self.state.prev_page();
+        }
+        self.notify_changed();
+        true
+    }
+
+    /// 撤銷上一次送出：把字根還原成送出前的樣子並重新列出候選，回傳要送出的退格數
+    /// 正在組字、沒有可撤銷的送出，或送出後已按過其他鍵（見 forget_undo）時回傳 None
+    pub fn undo_last_commit(&mut self) -> Option<usize> {
+        if !self.state.current_code.is_empty() {
+            return None;
+        }
+        let undo = self.undo.take()?;
+        if undo.passed_keys != self.passed_keys.load(Ordering::Relaxed) {
+            debug!("送出後按過其他鍵，不能撤銷");
+            return None;
+        }
+        // 選錯的候選不應該因此排到前面
+        if let Some((code, candidate)) = &undo.counted {
+            self.frequency.unrecord(code, candidate);
+        }
+        self.recent_commits.pop_back();
+        self.pending_selection = None;
+        self.state.clear();
+        for ch in undo.code.chars() {
+            self.state.append_code(ch);
+        }
+        self.refresh_candidates();
+        debug!("撤銷送出: '{}'，還原字根 '{}'", undo.output, undo.code);
+        Some(clusters(&undo.output).len())
+    }
+
+    /// 重送最近一次送出的文字（重送鍵，見設定 repeat_output_key）
+    /// 正在組字、暫時英文模式或還沒送出過字時回傳 None
+    pub fn repeat_last_output(&mut self) -> Option<String> {
+        if !self.state.current_code.is_empty() || self.state.temp_english.is_some() {
+            return None;
+        }
+        let text = self.recent_commits.back()?.clone();
+        debug!("重送: {}", text);
+        // 重送的文字沒有字根可以還原，不能撤銷
+        self.undo = None;
+        self.observers.notify(&InputEvent::Committed(text.clone()));
+        Some(text)
+    }
+
+    /// 記錄一次送出（超過 RECENT_COMMITS_LEN 筆時丟掉最舊的）
+    fn record_commit(&mut self, text: &str) {
+        if self.recent_commits.len() >= RECENT_COMMITS_LEN {
+            self.recent_commits.pop_front();
+        }
+        self.recent_commits.push_back(text.to_string());
+        // 簡碼提示只針對最近一次送出，聯想詞、暫時英文等沒有字根的送出也會清掉
+        self.shorter_code = None;
+    }
+
+    /// 放行按鍵的計數，交給鍵盤鉤子在放行按鍵時增加（不必取處理器的鎖）
+    pub fn passed_keys(&self) -> Arc<AtomicU64> {
+        self.passed_keys.clone()
+    }
+
+    /// 送出後按了其他鍵（游標可能已經移動），上一次送出不能再撤銷
+    pub fn forget_undo(&mut self) {
+        self.undo = None;
+    }
+
+    /// 補碼選字後按選字鍵：改選目前頁的其他候選，仍等待 Space 送出
+    /// 選字確認模式下第一次按選字鍵也走這裡，只高亮不送出；再按一次同一鍵時回傳 false
+    /// 不在補碼選字狀態或沒有對應的候選時回傳 false（呼叫端照一般選字處理）
+    pub fn handle_complement_reselect(&mut self, key: char) -> bool {
+        let Some(index) = self.scheme.selection_index(key) else {
+            return false;
+        };
+        self.expire_pending_selection();
+        let position = self.state.candidate_index + index;
+        if self.selection_confirm_timeout.is_some() {
+            // 已高亮的候選再按一次：交給 handle_selection_key 送出
+            if self.pending_selection.take().is_some_and(|(pending, _)| pending == position) {
+                return false;
+            }
+        } else if self.state.complement_position.is_none() {
+            return false;
+        }
+        let Some(selected) = self.state.select_candidate(index).cloned() else {
+            return false;
+        };
+        debug!("改選第 {} 個候選: {}", position + 1, selected);
+        self.state.complement_code = Some(selected.code);
+        self.state.complement_selected = Some(selected.text);
+        self.state.complement_key = None;
+        self.state.complement_position = Some(position);
+        if self.selection_confirm_timeout.is_some() {
+            self.pending_selection = Some((position, Instant::now()));
+        }
+        self.notify_changed();
+        true
+    }
+
     /// 處理數字鍵選擇候選字
     pub fn handle_number_selection(&mut self, num: u8) -> Option<String> {
         if num > 9 {
@@ -317,9 +1297,10 @@ impl InputMethodProcessor {
         // 數字鍵 0 對應索引 9（第 10 個候選字）
         let index = if num == 0 { 9 } else { (num - 1) as usize };
         
-        if let Some(selected) = self.state.select_candidate(index) {
-            let result = selected.clone();
+        if let Some(selected) = self.state.select_candidate(index).cloned() {
+            let result = self.commit_output(selected);
             self.state.clear();
+            self.suggest_after(&result);
             Some(result)
         } else {
             None
@@ -328,27 +1309,57 @@ impl InputMethodProcessor {
 
     /// 處理 Backspace
     pub fn handle_backspace(&mut self) -> bool {
+        // 輸入重複次數時先刪數字，沒有數字時取消重複
+        if let Some(count) = self.state.repeat_count {
+            self.state.repeat_count = (count > 0).then_some(count / 10);
+            return true;
+        }
         if self.state.current_code.is_empty() {
-            return false; // 沒有字根可刪除，讓事件通過
+            // 符號選單退回上一層（攔截 Backspace）
+            if self.close_symbol_menu() {
+                return true;
+            }
+            // 列出同音字時收起同音字（攔截 Backspace）
+            if self.state.homophone_of.is_some() {
+                self.state.clear();
+                self.notify_changed();
+                return true;
+            }
+            // 沒有字根可刪除，收起聯想詞並讓事件通過
+            self.state.suggestions.clear();
+            return false;
         }
 
         self.state.delete_last_code();
-        self.state.lookup_candidates(&self.dictionary);
+        self.refresh_candidates();
         true
     }
 
     /// 處理 Space（選擇第一個候選字或補碼選擇的候選字）
     pub fn handle_space(&mut self) -> Option<String> {
+        self.expire_pending_selection();
+        self.pending_selection = None;
         // 優先檢查是否有補碼選擇的候選字
         if let Some(complement_selected) = self.state.complement_selected.take() {
+            let code = self.state.complement_code.take().unwrap_or_default();
+            let candidate = self.pending_candidate(code, complement_selected);
+            let result = self.commit_output(candidate);
             self.state.clear();
-            return Some(complement_selected);
+            self.suggest_after(&result);
+            return Some(result);
         }
         
+        // 符號選單的第一層：Space 選第一個分類，不送出
+        if self.state.symbol_menu == Some(SymbolMenu::Categories) {
+            self.enter_symbol_category(self.state.candidate_index);
+            return None;
+        }
+
         // 否則選擇第一個候選字
-        if let Some(first) = self.state.candidates.first() {
-            let result = first.clone();
+        if let Some(first) = self.state.candidates.first().cloned() {
+            let result = self.commit_output(first);
             self.state.clear();
+            self.suggest_after(&result);
             Some(result)
         } else {
             // 沒有候選字時，如果還有字根，按 Space 代表「放棄這組字根」→ 清除
@@ -358,9 +1369,141 @@ impl InputMethodProcessor {
                     self.state.current_code
                 );
                 self.state.clear();
+                self.notify_changed();
+            }
+            None
+        }
+    }
+
+    /// 重新產生候選字並通知觀察者
+    fn refresh_candidates(&mut self) {
+        self.lookup_current_code();
+        self.notify_changed();
+    }
+
+    /// 查詢目前字根的候選：字根符合特殊碼規則時使用規則產生的候選，否則查詢字典（依字頻排序）
+    fn lookup_current_code(&mut self) {
+        if let Some(prefix) = self.state.emoji_prefix.filter(|_| self.state.is_emoji_composition()) {
+            // emoji 短碼：只查 emoji 表，記下每個候選的短碼（含前綴鍵，送出時用來查別名與記錄字頻）
+            let groups = self.dictionary.lookup_emoji(&self.state.current_code[prefix.len_utf8()..]);
+            self.rule_candidates = false;
+            self.state.candidates.clear();
+            for (short, chars) in groups {
+                let code = format!("{}{}", prefix, short);
+                for ch in chars {
+                    let output = self.dictionary.emoji_output_for(&short, &ch).map(str::to_string);
+                    self.state.candidates.push(Candidate { output, ..Candidate::plain(&code, ch) });
+                }
+            }
+            self.state.candidate_index = 0;
+            return;
+        }
+        // 日期/時間巨集碼：候選是目前時間的預覽，和規則產生的候選一樣不計入字頻
+        let preview = self.time_macro_format(&self.state.current_code).map(|format| time_macros::expand(format, LocalTime::now()));
+        if let Some(preview) = preview {
+            debug!("字根 '{}' 為日期/時間巨集碼，預覽: {}", self.state.current_code, preview);
+            self.state.candidates = vec![Candidate::plain(&self.state.current_code, preview)];
+            self.state.candidate_index = 0;
+            self.rule_candidates = true;
+            return;
+        }
+        if !self.rules.is_empty() && !self.state.current_code.is_empty() {
+            if let Some(candidates) = self.rules.generate(&self.state.current_code, self.recent_commits.back().map(String::as_str)) {
+                debug!("字根 '{}' 符合特殊碼規則，產生 {} 個候選", self.state.current_code, candidates.len());
+                let code = &self.state.current_code;
+                self.state.candidates = candidates.into_iter().map(|text| Candidate::plain(code, text)).collect();
+                self.state.candidate_index = 0;
+                self.rule_candidates = true;
+                return;
+            }
+        }
+        self.rule_candidates = false;
+        if is_wildcard(&self.state.current_code) {
+            // 萬用字元查詢：候選依字根分組，記下每個候選的字根（送出時用來查別名與記錄字頻）
+            let groups = self.dictionary.lookup_wildcard(&self.state.current_code);
+            debug!("萬用字元 '{}' 符合 {} 個字根", self.state.current_code, groups.len());
+            let mut candidates = Vec::new();
+            for (code, mut chars) in groups {
+                self.state.charset_filter.apply(&mut chars);
+                candidates.extend(self.dictionary.make_candidates(&code, chars));
+            }
+            if self.sorter.is_adaptive() {
+                sorter::tag_usage(&mut candidates, &self.frequency);
+            }
+            self.state.candidates = candidates;
+            self.state.candidate_index = 0;
+            return;
+        }
+        self.state.lookup_candidates(&self.dictionary, self.sorter.as_ref(), &self.frequency);
+    }
+
+    /// 補碼或符號選字等待送出的候選：在候選列表中時直接使用，否則（符號組合）另外查出別名輸出
+    fn pending_candidate(&self, code: String, text: String) -> Candidate {
+        if let Some(candidate) = self.state.candidates.iter().find(|c| c.code == code && c.text == text) {
+            return candidate.clone();
+        }
+        let output = self.dictionary.output_for(&code, &text).map(|output| output.into_owned());
+        Candidate { output, ..Candidate::plain(&code, text) }
+    }
+
+    /// 送出選中的候選，回傳實際送出的文字
+    fn commit_output(&mut self, candidate: Candidate) -> String {
+        let code = candidate.code.clone();
+        // 規則產生的候選（日期、流水號等）每次都不同，不計入字頻；同音字與符號選單沒有字根，也不計入
+        let from_rules = self.rule_candidates && code == self.state.current_code;
+        let counted = !from_rules && !self.state.is_listing();
+        if counted {
+            self.frequency.record(&code, &candidate.text);
+        }
+        // 萬用字元查詢時還原成含萬用字元的字根，補碼選字時還原成不含補碼的字根
+        let undo_code = if is_wildcard(&self.state.current_code) { self.state.current_code.clone() } else { code.clone() };
+        let shorter = if self.shorter_code_hints && counted {
+            self.dictionary
+                .shortest_code(&candidate.text)
+                .filter(|short| short.len() < code.len())
+                .map(|short| (candidate.text.clone(), short))
+        } else {
+            None
+        };
+        let counted = counted.then(|| (code.clone(), candidate.text.clone()));
+        let mut output = self.resolve_output(candidate);
+        if let Some(count) = self.state.repeat_count.take().filter(|&count| count > 1) {
+            debug!("重複送出 {} 次", count);
+            output = output.repeat(count);
+        }
+        self.rules.on_commit(&code);
+        self.record_commit(&output);
+        if let Some((text, short)) = &shorter {
+            debug!("'{}' 以 {} 送出，可用簡碼 {}", text, code, short);
+        }
+        self.shorter_code = shorter;
+        let passed_keys = self.passed_keys.load(Ordering::Relaxed);
+        self.undo = Some(UndoEntry { code: undo_code, counted, output: output.clone(), passed_keys });
+        self.composing = false;
+        self.observers.notify(&InputEvent::Committed(output.clone()));
+        output
+    }
+
+    /// 將選中的候選轉換為實際送出的文字
+    /// 有別名輸出（字典的 display/output 或 emoji 短碼表）時送出 output，否則送出顯示文字本身
+    fn resolve_output(&self, candidate: Candidate) -> String {
+        // 日期/時間巨集碼在送出前才以當下的時間展開
+        if let Some(format) = self.time_macro_format(&candidate.code) {
+            return time_macros::expand(format, LocalTime::now());
+        }
+        let is_snippet = self.dictionary.layer_of(&candidate.code, &candidate.text) == Some(SNIPPETS_LAYER);
+        let output = match candidate.output {
+            Some(output) => {
+                debug!("別名輸出: '{}' 的候選 '{}' -> '{}'", candidate.code, candidate.text, output);
+                output
             }
-            None
+            None => candidate.text,
+        };
+        // 文字片段的佔位符在送出前才展開
+        if is_snippet {
+            return snippets::expand(&output, LocalTime::now(), snippets::clipboard_text);
         }
+        output
     }
 
     /// 處理 Enter（送出當前字根，不清除）
@@ -380,12 +1523,16 @@ impl InputMethodProcessor {
     /// 清除狀態
     pub fn clear(&mut self) {
         self.state.clear();
+        self.notify_changed();
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::candidate::CandidateSource;
+    use crate::sorter::{ByFrequency, ByRecency, TableOrder};
+    use crate::rules::RuleDef;
     use std::collections::HashMap;
 
     fn create_test_dictionary() -> Dictionary {
@@ -398,6 +1545,7 @@ mod tests {
         Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         }
     }
 
@@ -435,10 +1583,10 @@ mod tests {
         let mut state = InputMethodState::new();
         
         state.append_code('a');
-        state.lookup_candidates(&dictionary);
+        state.lookup_candidates(&dictionary, &TableOrder, &FrequencyStats::new());
         assert_eq!(state.candidates.len(), 2);
-        assert_eq!(state.candidates[0], "一");
-        assert_eq!(state.candidates[1], "乙");
+        assert_eq!(state.candidates[0].text, "一");
+        assert_eq!(state.candidates[1].text, "乙");
     }
 
     #[test]
@@ -447,17 +1595,17 @@ mod tests {
         let mut state = InputMethodState::new();
         
         // 創建一個有 10 個候選字的測試
-        state.candidates = (0..10).map(|i| format!("候選{}", i)).collect();
+        state.candidates = (0..10).map(|i| Candidate::plain("", format!("候選{}", i))).collect();
         state.candidates_per_page = 6;
         
         let page1 = state.get_current_page_candidates();
         assert_eq!(page1.len(), 6);
-        assert_eq!(page1[0], "候選0");
+        assert_eq!(page1[0].text, "候選0");
         
         state.next_page();
         let page2 = state.get_current_page_candidates();
         assert_eq!(page2.len(), 4);
-        assert_eq!(page2[0], "候選6");
+        assert_eq!(page2[0].text, "候選6");
     }
 
     #[test]
@@ -466,19 +1614,18 @@ mod tests {
         let mut state = InputMethodState::new();
         
         state.append_code('a');
-        state.lookup_candidates(&dictionary);
+        state.lookup_candidates(&dictionary, &TableOrder, &FrequencyStats::new());
         
         // 選擇第一個候選字（數字鍵 1）
-        let selected = state.select_candidate(0);
+        let selected = state.select_candidate(0).map(|c| c.text.clone());
         assert_eq!(selected, Some("一".to_string()));
         
         // 選擇第二個候選字（數字鍵 2）
-        let selected = state.select_candidate(1);
+        let selected = state.select_candidate(1).map(|c| c.text.clone());
         assert_eq!(selected, Some("乙".to_string()));
         
         // 選擇不存在的候選字
-        let selected = state.select_candidate(2);
-        assert_eq!(selected, None);
+        assert_eq!(state.select_candidate(2), None);
     }
 
     #[test]
@@ -585,6 +1732,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -695,6 +1843,323 @@ mod tests {
         assert_eq!(space_result, Some("乙".to_string()));
         assert_eq!(processor.get_state().current_code, ""); // 現在才清除
         assert_eq!(processor.get_state().complement_selected, None); // 補碼選擇已清除
+
+        // 預設依字碼表順序，選過「乙」之後 v 仍然選到「乙」
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.handle_code_input('v'), (true, Some("乙".to_string())));
+    }
+
+    #[test]
+    fn test_complement_label_and_reselect() {
+        let dictionary = create_test_dictionary();
+        let mut processor = InputMethodProcessor::new(dictionary);
+
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.get_state().complement_label(), None);
+        let (_, _) = processor.handle_code_input('v');
+        assert_eq!(processor.get_state().complement_label().as_deref(), Some("補碼 v → 第 2 候選：乙"));
+        assert_eq!(processor.get_state().candidate_texts(), vec!["一", "乙"]); // 其它候選仍可列出
+
+        // 按選字鍵改選，不立即送出
+        assert!(processor.handle_complement_reselect('1'));
+        assert!(!processor.handle_complement_reselect('5'));
+        assert_eq!(processor.get_state().complement_label().as_deref(), Some("第 1 候選：一"));
+        assert_eq!(processor.handle_space(), Some("一".to_string()));
+
+        // 不在補碼選字狀態時照一般選字處理
+        let (_, _) = processor.handle_code_input('a');
+        assert!(!processor.handle_complement_reselect('1'));
+    }
+
+    #[test]
+    fn test_associated_phrase_suggestions() {
+        let dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "a": ["輸"], "ab": ["輸入", "輸出"], "b": ["入"], "bc": ["入口"] } }"#,
+        )
+        .unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+        processor.set_associated_phrases(true);
+
+        // 送出「輸」後提示聯想詞，按選字鍵選用並接著提示下一組
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.handle_space(), Some("輸".to_string()));
+        assert_eq!(processor.get_state().suggestions, vec!["入", "出"]);
+        assert_eq!(processor.handle_selection_key('1'), Some("入".to_string()));
+        assert_eq!(processor.get_state().suggestions, vec!["口"]);
+
+        // 開始輸入下一個字根時收起聯想詞
+        let (_, _) = processor.handle_code_input('b');
+        assert!(processor.get_state().suggestions.is_empty());
+        assert_eq!(processor.handle_selection_key('9'), None);
+
+        processor.clear();
+        processor.set_associated_phrases(false);
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.handle_space(), Some("輸".to_string()));
+        assert!(processor.get_state().suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_peek_selection_key_keeps_composition() {
+        let dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "a": ["一", { "display": "㊀", "output": "(一)" }] } }"#,
+        )
+        .unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+        let (_, _) = processor.handle_code_input('a');
+
+        // 複製的是實際送出的文字，字根與候選都留著
+        assert_eq!(processor.peek_selection_key('2'), Some("(一)".to_string()));
+        assert_eq!(processor.peek_selection_key('9'), None);
+        assert_eq!(processor.get_state().current_code, "a");
+        assert_eq!(processor.handle_selection_key('1'), Some("一".to_string()));
+    }
+
+    #[test]
+    fn test_auto_commit_unique_candidate() {
+        let dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "abcde": ["一"], "abcdf": ["乙", "丙"], "abcdg": ["丁"], "abcdgh": ["戊"] } }"#,
+        )
+        .unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+        let type_code = |processor: &mut InputMethodProcessor, code: &str| {
+            for ch in code.chars() {
+                let (_, _) = processor.handle_code_input(ch);
+            }
+            processor.take_auto_commit()
+        };
+
+        // 預設關閉
+        assert_eq!(type_code(&mut processor, "abcde"), None);
+        assert_eq!(processor.get_state().current_code, "abcde");
+        processor.clear();
+
+        processor.set_auto_commit(true);
+        assert_eq!(type_code(&mut processor, "abcde"), Some("一".to_string()));
+        assert!(processor.get_state().current_code.is_empty());
+        // 多個候選、或還有更長的字根時照常等 Space
+        assert_eq!(type_code(&mut processor, "abcdf"), None);
+        processor.clear();
+        assert_eq!(type_code(&mut processor, "abcdg"), None);
+        assert_eq!(processor.get_state().candidate_texts(), vec!["丁"]);
+    }
+
+    #[test]
+    fn test_page_candidates_while_composing() {
+        let chardefs: Vec<String> = (0..12).map(|i| format!("\"候選{}\"", i)).collect();
+        let dictionary =
+            Dictionary::from_json(&format!(r#"{{ "chardefs": {{ "a": [{}] }} }}"#, chardefs.join(","))).unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+        // 沒有組字時不攔截
+        assert!(!processor.page_candidates(true));
+
+        let (_, _) = processor.handle_code_input('a');
+        assert!(processor.page_candidates(true));
+        let per_page = processor.get_state().candidates_per_page;
+        assert_eq!(processor.get_state().candidate_index, per_page);
+        assert_eq!(processor.handle_selection_key('1'), Some(format!("候選{}", per_page)));
+
+        let (_, _) = processor.handle_code_input('a');
+        // 已在第一頁時不動，但仍攔截
+        assert!(processor.page_candidates(false));
+        assert_eq!(processor.get_state().candidate_index, 0);
+    }
+
+    #[test]
+    fn test_half_width_symbols() {
+        let dictionary = Dictionary::from_json(r#"{ "chardefs": { ".": ["。"], "s.": ["？"], "s": ["是"] } }"#).unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+        assert_eq!(processor.handle_symbol_input('.'), (true, Some("。".to_string())));
+        assert_eq!(processor.handle_space(), Some("。".to_string()));
+
+        assert!(processor.toggle_half_width());
+        assert_eq!(processor.handle_symbol_input('.'), (true, Some(".".to_string())));
+        assert_eq!(processor.handle_space(), Some(".".to_string()));
+        let (_, _) = processor.handle_code_input('s');
+        assert_eq!(processor.handle_symbol_input('.'), (true, Some("?".to_string())));
+        assert_eq!(to_half_width("「…」：　！"), "「...」: !");
+    }
+
+    #[test]
+    fn test_repeat_count_repeats_commit() {
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        // 沒有候選時不開始
+        assert!(!processor.start_repeat_count());
+        assert!(!processor.push_repeat_digit('5'));
+
+        let (_, _) = processor.handle_code_input('a');
+        assert!(processor.start_repeat_count());
+        assert!(processor.push_repeat_digit('1'));
+        assert!(processor.push_repeat_digit('2'));
+        // Backspace 先刪數字
+        assert!(processor.handle_backspace());
+        assert_eq!(processor.get_state().repeat_count, Some(1));
+        assert!(processor.push_repeat_digit('3'));
+        assert_eq!(processor.handle_space(), Some("一".repeat(13)));
+        assert_eq!(processor.get_state().repeat_count, None);
+        assert_eq!(processor.undo_last_commit(), Some(13));
+
+        // 次數有上限；沒輸入數字時只送出一次
+        processor.clear();
+        let (_, _) = processor.handle_code_input('a');
+        processor.start_repeat_count();
+        for _ in 0..3 {
+            processor.push_repeat_digit('9');
+        }
+        assert_eq!(processor.get_state().repeat_count, Some(MAX_REPEAT_COUNT));
+        processor.clear();
+        let (_, _) = processor.handle_code_input('a');
+        processor.start_repeat_count();
+        assert_eq!(processor.handle_space(), Some("一".to_string()));
+    }
+
+    #[test]
+    fn test_compose_sentence_buffers_commits() {
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        // 沒有開啟時直接送出
+        assert!(!processor.push_composition("一"));
+        assert_eq!(processor.take_composition(), None);
+
+        processor.set_compose_sentence(true);
+        assert!(processor.push_composition("一"));
+        assert!(processor.push_composition("測試"));
+        assert!(processor.push_composition("二"));
+        assert_eq!(processor.composition(), "一測試二");
+        // 退格整段刪除
+        assert!(processor.pop_composition());
+        assert!(processor.pop_composition());
+        assert_eq!(processor.composition(), "一");
+        assert_eq!(processor.take_composition(), Some("一".to_string()));
+        assert_eq!(processor.composition(), "");
+        assert!(!processor.pop_composition());
+
+        assert!(processor.push_composition("二"));
+        assert!(processor.clear_composition());
+        assert!(!processor.clear_composition());
+    }
+
+    #[test]
+    fn test_shift_letter() {
+        assert_eq!(ShiftLetter::parse("Word").unwrap(), ShiftLetter::Word);
+        assert_eq!(ShiftLetter::parse("").unwrap(), ShiftLetter::Pass);
+        assert!(ShiftLetter::parse("upper").is_err());
+
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        assert_eq!(processor.handle_shift_letter(ShiftLetter::Commit, 'a'), Some("A".to_string()));
+        // 正在組字時先送出第一個候選
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.handle_shift_letter(ShiftLetter::Commit, 'b'), Some("一B".to_string()));
+        assert!(processor.get_state().current_code.is_empty());
+
+        // word：大寫字母開始暫時英文緩衝區，Space 送出整個單字
+        assert_eq!(processor.handle_shift_letter(ShiftLetter::Word, 'a'), None);
+        for ch in "pple".chars() {
+            assert_eq!(processor.push_temp_english(ch), None);
+        }
+        assert_eq!(processor.commit_temp_english(), Some("Apple".to_string()));
+    }
+
+    #[test]
+    fn test_temp_english_commits_verbatim() {
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        assert!(processor.start_temp_english());
+        assert!(!processor.start_temp_english());
+        for ch in "iPhone!".chars() {
+            assert_eq!(processor.push_temp_english(ch), None);
+        }
+        processor.temp_english_backspace();
+        assert_eq!(processor.get_state().temp_english.as_deref(), Some("iPhone"));
+        assert_eq!(processor.commit_temp_english(), Some("iPhone".to_string()));
+        assert!(!processor.is_temp_english());
+
+        // 連按兩次 ` 送出 `；空的緩衝區按 Backspace 離開
+        assert!(processor.start_temp_english());
+        assert_eq!(processor.push_temp_english('`'), Some("`".to_string()));
+        assert!(processor.start_temp_english());
+        processor.temp_english_backspace();
+        assert!(!processor.is_temp_english());
+
+        // 正在組字時不進入
+        let (_, _) = processor.handle_code_input('a');
+        assert!(!processor.start_temp_english());
+    }
+
+    #[test]
+    fn test_undo_last_commit_restores_code() {
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        assert_eq!(processor.undo_last_commit(), None);
+
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.handle_selection_key('2'), Some("乙".to_string()));
+        assert_eq!(processor.undo_last_commit(), Some(1));
+        assert_eq!(processor.get_state().current_code, "a");
+        // 選錯的候選不計入字頻，順序不變
+        assert_eq!(processor.get_state().candidates[0].text, "一");
+        // 只能撤銷一次
+        assert_eq!(processor.undo_last_commit(), None);
+
+        assert_eq!(processor.handle_selection_key('1'), Some("一".to_string()));
+        processor.forget_undo();
+        assert_eq!(processor.undo_last_commit(), None);
+
+        // 鍵盤鉤子放行了其他按鍵
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.handle_selection_key('1'), Some("一".to_string()));
+        processor.passed_keys().fetch_add(1, Ordering::Relaxed);
+        assert_eq!(processor.undo_last_commit(), None);
+    }
+
+    #[test]
+    fn test_repeat_last_output() {
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        assert_eq!(processor.repeat_last_output(), None);
+
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.handle_selection_key('1'), Some("一".to_string()));
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.handle_selection_key('2'), Some("乙".to_string()));
+        assert_eq!(processor.repeat_last_output(), Some("乙".to_string()));
+        // 重送的不能撤銷，也不另外記一筆
+        assert_eq!(processor.undo_last_commit(), None);
+        assert_eq!(processor.repeat_last_output(), Some("乙".to_string()));
+
+        // 撤銷後重送的是再前一次送出的文字
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.handle_selection_key('1'), Some("一".to_string()));
+        assert_eq!(processor.undo_last_commit(), Some(1));
+        processor.clear();
+        assert_eq!(processor.repeat_last_output(), Some("乙".to_string()));
+
+        // 組字中不重送
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.repeat_last_output(), None);
+    }
+
+    #[test]
+    fn test_selection_confirm_mode() {
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        processor.set_selection_confirm(Some(Duration::from_secs(60)));
+
+        // 第一次按只高亮，按其他鍵改高亮，再按同一鍵才送出
+        let (_, _) = processor.handle_code_input('a');
+        assert!(processor.handle_complement_reselect('2'));
+        assert_eq!(processor.get_state().complement_label().as_deref(), Some("第 2 候選：乙"));
+        assert!(processor.handle_complement_reselect('1'));
+        assert!(!processor.handle_complement_reselect('1'));
+        assert_eq!(processor.handle_selection_key('1'), Some("一".to_string()));
+
+        // 高亮後按 Space 送出高亮的候選
+        let (_, _) = processor.handle_code_input('a');
+        assert!(processor.handle_complement_reselect('2'));
+        assert_eq!(processor.handle_space(), Some("乙".to_string()));
+
+        // 逾時取消高亮，字根保留
+        processor.set_selection_confirm(Some(Duration::ZERO));
+        let (_, _) = processor.handle_code_input('a');
+        assert!(processor.handle_complement_reselect('2'));
+        assert!(processor.expire_pending_selection());
+        assert_eq!(processor.get_state().complement_selected, None);
+        assert_eq!(processor.get_state().current_code, "a");
     }
 
     #[test]
@@ -706,6 +2171,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -740,6 +2206,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -774,6 +2241,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -808,6 +2276,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -841,6 +2310,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -871,6 +2341,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -896,6 +2367,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         let mut processor = InputMethodProcessor::new(dictionary);
         
@@ -926,6 +2398,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         let mut processor = InputMethodProcessor::new(dictionary);
         
@@ -953,6 +2426,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -991,6 +2465,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -1014,6 +2489,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -1050,6 +2526,7 @@ mod tests {
         let dictionary = Dictionary {
             code_to_chars: code_map,
             pinyi_data: None,
+            ..Default::default()
         };
         
         let mut processor = InputMethodProcessor::new(dictionary);
@@ -1074,7 +2551,388 @@ mod tests {
         assert_eq!(processor.get_state().current_code, "sisp");
         // 應該找到 "sisp" 的候選字
         assert_eq!(processor.get_state().candidates.len(), 1);
-        assert_eq!(processor.get_state().candidates[0], "目標字");
+        assert_eq!(processor.get_state().candidates[0].text, "目標字");
     }
-}
 
+    #[test]
+    fn test_alias_output() {
+        // 候選顯示「笑」但實際送出「😂」
+        let dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "xo": [{ "display": "笑", "output": "😂" }, "哈", { "display": "嘻", "output": "😆" }] } }"#,
+        ).unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+
+        let (_, _) = processor.handle_code_input('x');
+        let (_, _) = processor.handle_code_input('o');
+        // GUI 看到的是顯示文字
+        assert_eq!(processor.get_state().candidates[0].text, "笑");
+
+        // Space 送出 output
+        assert_eq!(processor.handle_space(), Some("😂".to_string()));
+
+        // 數字鍵選擇沒有別名的候選，送出顯示文字本身
+        let (_, _) = processor.handle_code_input('x');
+        let (_, _) = processor.handle_code_input('o');
+        assert_eq!(processor.handle_number_selection(2), Some("哈".to_string()));
+
+        // 補碼選擇（r → 第 3 候選）也會套用別名輸出
+        let (_, _) = processor.handle_code_input('x');
+        let (_, _) = processor.handle_code_input('o');
+        let (_, selected) = processor.handle_code_input('r');
+        assert_eq!(selected, Some("嘻".to_string()));
+        assert_eq!(processor.handle_space(), Some("😆".to_string()));
+    }
+
+    #[test]
+    fn test_custom_scheme() {
+        // 自訂方案：字根最多 3 碼、補碼鍵為 'q'（選第 2 候選）、選字鍵為 "asd"
+        let mut code_map = HashMap::new();
+        code_map.insert("ab".to_string(), vec!["甲".to_string(), "乙".to_string()]);
+        let dictionary = Dictionary {
+            code_to_chars: code_map,
+            pinyi_data: None,
+            ..Default::default()
+        };
+        let scheme = InputScheme {
+            max_code_length: 3,
+            complement_keys: "q".to_string(),
+            selection_keys: "asd".to_string(),
+            ..InputScheme::default()
+        };
+        let mut processor = InputMethodProcessor::with_scheme(dictionary, scheme);
+
+        // 字根長度限制跟隨方案
+        for ch in ['x', 'y', 'z', 'x'] {
+            let (_, _) = processor.handle_code_input(ch);
+        }
+        assert_eq!(processor.get_state().current_code, "xyz");
+        processor.clear();
+
+        // 嘸蝦米的 'v' 在此方案不是補碼鍵
+        let (_, _) = processor.handle_code_input('a');
+        let (_, _) = processor.handle_code_input('b');
+        let (_, selected) = processor.handle_code_input('v');
+        assert_eq!(selected, None);
+        assert_eq!(processor.get_state().current_code, "abv");
+        processor.clear();
+
+        // 'q' 是補碼鍵
+        let (_, _) = processor.handle_code_input('a');
+        let (_, _) = processor.handle_code_input('b');
+        let (_, selected) = processor.handle_code_input('q');
+        assert_eq!(selected, Some("乙".to_string()));
+        processor.clear();
+
+        // 選字鍵 's' 選第 2 候選，數字鍵不是選字鍵
+        let (_, _) = processor.handle_code_input('a');
+        let (_, _) = processor.handle_code_input('b');
+        assert_eq!(processor.handle_selection_key('1'), None);
+        assert_eq!(processor.handle_selection_key('s'), Some("乙".to_string()));
+    }
+
+    #[test]
+    fn test_rules_before_dictionary() {
+        let scheme = InputScheme {
+            rules: vec![
+                RuleDef { pattern: "^zz$".to_string(), generator: "repeat".to_string(), template: None },
+                RuleDef { pattern: "^sn$".to_string(), generator: "sequence".to_string(), template: None },
+            ],
+            ..InputScheme::default()
+        };
+        let mut processor = InputMethodProcessor::with_scheme(create_test_dictionary(), scheme);
+
+        // 還沒有送出過文字時 repeat 規則不產生候選，改查字典
+        let (_, _) = processor.handle_code_input('z');
+        let (_, _) = processor.handle_code_input('z');
+        assert!(processor.get_state().candidates.is_empty());
+        processor.clear();
+
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.handle_space(), Some("一".to_string()));
+
+        let (_, _) = processor.handle_code_input('z');
+        let (_, _) = processor.handle_code_input('z');
+        assert_eq!(processor.get_state().candidate_texts(), vec!["一", "一一", "一一一"]);
+        assert_eq!(processor.handle_number_selection(2), Some("一一".to_string()));
+
+        // 流水號每送出一次加一
+        for expected in ["1", "2"] {
+            let (_, _) = processor.handle_code_input('s');
+            let (_, _) = processor.handle_code_input('n');
+            assert_eq!(processor.handle_space(), Some(expected.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_frequency_reorders_candidates() {
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        processor.set_sorter(Box::new(ByFrequency));
+
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.get_state().candidate_texts(), vec!["一", "乙"]);
+        assert_eq!(processor.handle_number_selection(2), Some("乙".to_string()));
+
+        // 選過一次的「乙」排到前面，Space 直接送出
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.get_state().candidate_texts(), vec!["乙", "一"]);
+        assert_eq!(processor.handle_space(), Some("乙".to_string()));
+
+        let snapshot = processor.take_frequency_snapshot().unwrap();
+        assert_eq!(snapshot.count("a", "乙"), 2);
+        assert!(processor.take_frequency_snapshot().is_none());
+
+        // 改用字碼表順序時恢復原本的順序；最近選用排序時剛選的字排前面
+        processor.set_sorter(Box::new(TableOrder));
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.get_state().candidate_texts(), vec!["一", "乙"]);
+        assert_eq!(processor.handle_space(), Some("一".to_string()));
+        processor.set_sorter(Box::new(ByRecency));
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.get_state().candidate_texts(), vec!["一", "乙"]);
+    }
+
+    #[test]
+    fn test_weights_rank_before_frequency() {
+        let dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "A": ["一", "乙", "乂"] }, "weights": { "a": { "乂": 10, "一": -1 } } }"#,
+        )
+        .unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+        processor.set_sorter(Box::new(ByFrequency));
+
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.get_state().candidate_texts(), vec!["乂", "乙", "一"]);
+
+        // 使用者選過的字仍然排到最前面，其餘維持權重順序
+        assert_eq!(processor.handle_number_selection(3), Some("一".to_string()));
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.get_state().candidate_texts(), vec!["一", "乂", "乙"]);
+    }
+
+    #[test]
+    fn test_candidate_sources() {
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        processor.set_sorter(Box::new(ByFrequency));
+        processor.dictionary_mut().add_user_entry("a", "丫");
+
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.get_state().candidate_texts(), vec!["丫", "一", "乙"]);
+        let sources: Vec<_> = processor.get_state().candidates.iter().map(|c| c.source.clone()).collect();
+        assert_eq!(sources, vec![Some(CandidateSource::User), Some(CandidateSource::Main), Some(CandidateSource::Main)]);
+        assert_eq!(processor.handle_number_selection(3), Some("乙".to_string()));
+
+        // 選過的字標為學習，並帶著使用次數
+        let (_, _) = processor.handle_code_input('a');
+        let first = &processor.get_state().candidates[0];
+        assert_eq!((first.text.as_str(), first.source.clone(), first.weight), ("乙", Some(CandidateSource::Learned), 1));
+        assert_eq!(processor.get_state().candidates[1].source, Some(CandidateSource::User));
+        processor.clear();
+        assert!(processor.get_state().candidates.is_empty());
+    }
+
+    #[test]
+    fn test_block_candidate_updates_current_candidates() {
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+
+        let (_, _) = processor.handle_code_input('a');
+        assert!(processor.block_candidate("一"));
+        assert!(!processor.block_candidate("一"));
+        assert_eq!(processor.get_state().candidate_texts(), vec!["乙"]);
+        assert_eq!(processor.handle_space(), Some("乙".to_string()));
+
+        let snapshot = processor.take_blocklist_snapshot().unwrap();
+        assert!(snapshot.contains("一"));
+        assert!(processor.take_blocklist_snapshot().is_none());
+
+        // 恢復後重新出現在原本的位置（預設依字碼表順序，剛選過的「乙」不會排到前面）
+        assert!(processor.unblock_candidate("一"));
+        let (_, _) = processor.handle_code_input('a');
+        assert_eq!(processor.get_state().candidate_texts(), vec!["一", "乙"]);
+    }
+
+    #[test]
+    fn test_wildcard_candidates_keep_their_codes() {
+        let mut code_map = HashMap::new();
+        code_map.insert("ad".to_string(), vec!["二".to_string()]);
+        code_map.insert("abd".to_string(), vec!["三".to_string(), "參".to_string()]);
+        code_map.insert("ae".to_string(), vec!["五".to_string()]);
+        let mut processor = InputMethodProcessor::new(Dictionary { code_to_chars: code_map, ..Default::default() });
+
+        // 沒有字根時 * 不處理
+        assert!(!processor.handle_wildcard_input('*'));
+        let (_, _) = processor.handle_code_input('a');
+        assert!(processor.handle_wildcard_input('*'));
+        assert!(processor.handle_wildcard_input('*')); // 連續的 * 只算一個
+        let (_, _) = processor.handle_code_input('d');
+        assert_eq!(processor.get_state().current_code, "a*d");
+        assert_eq!(processor.get_state().candidate_texts(), vec!["二", "三", "參"]);
+        let codes: Vec<&str> = processor.get_state().candidates.iter().map(|c| c.code.as_str()).collect();
+        assert_eq!(codes, vec!["ad", "abd", "abd"]);
+
+        // 選字時以候選實際的字根記錄字頻
+        assert_eq!(processor.handle_number_selection(3), Some("參".to_string()));
+        assert_eq!(processor.take_frequency_snapshot().unwrap().count("abd", "參"), 1);
+        assert!(processor.get_state().candidates.is_empty());
+
+        // ? 代表剛好一碼，可以連續輸入
+        assert!(!processor.handle_wildcard_input('?'));
+        let (_, _) = processor.handle_code_input('a');
+        assert!(processor.handle_wildcard_input('?'));
+        let (_, _) = processor.handle_code_input('d');
+        assert_eq!(processor.get_state().current_code, "a?d");
+        assert_eq!(processor.get_state().candidate_texts(), vec!["三", "參"]);
+        assert!(processor.handle_wildcard_input('?'));
+        assert!(processor.get_state().candidates.is_empty());
+    }
+
+    #[test]
+    fn test_emoji_prefix_composition() {
+        let mut dictionary = create_test_dictionary();
+        dictionary.load_emoji_layer(std::path::Path::new("."), "emoji", true);
+        let mut processor = InputMethodProcessor::new(dictionary);
+        assert_eq!(processor.handle_emoji_prefix(':'), (false, None));
+
+        processor.set_emoji_prefix(Some(':'));
+        assert_eq!(processor.handle_emoji_prefix(':'), (true, None));
+        // 補碼鍵（s、f）在短碼中照常當成字根，短碼可以超過 5 碼
+        for ch in "thumbsu".chars() {
+            processor.handle_code_input(ch);
+        }
+        assert_eq!(processor.get_state().current_code, ":thumbsu");
+        assert_eq!(processor.get_state().candidates[0].text, "👍");
+        assert_eq!(processor.handle_space(), Some("👍".to_string()));
+        assert_eq!(processor.take_frequency_snapshot().unwrap().count(":thumbsup", "👍"), 1);
+
+        // :fire: 以結尾的前綴鍵送出
+        processor.handle_emoji_prefix(':');
+        for ch in "fire".chars() {
+            processor.handle_code_input(ch);
+        }
+        assert_eq!(processor.handle_emoji_prefix(':'), (true, Some("🔥".to_string())));
+        assert!(processor.get_state().current_code.is_empty());
+
+        // 輸入一般字根時前綴鍵不處理
+        processor.handle_code_input('a');
+        assert_eq!(processor.handle_emoji_prefix(':'), (false, None));
+    }
+
+    #[test]
+    fn test_homophones_of_last_commit() {
+        let mut dictionary = create_test_dictionary();
+        dictionary.pinyi_data = Some(crate::pinyi::HomophoneTable::parse("一衣醫\n乙以已\n"));
+        let mut processor = InputMethodProcessor::new(dictionary);
+        assert!(!processor.show_homophones());
+
+        processor.handle_code_input('a');
+        assert_eq!(processor.handle_space(), Some("一".to_string()));
+        assert!(processor.show_homophones());
+        assert_eq!(processor.get_state().homophone_of, Some('一'));
+        assert_eq!(processor.get_state().candidate_texts(), ["衣", "醫"]);
+        assert_eq!(processor.handle_selection_key('2'), Some("醫".to_string()));
+        assert_eq!(processor.get_state().homophone_of, None);
+        // 同音字沒有字根，不計入字頻
+        assert_eq!(processor.take_frequency_snapshot().unwrap().count("", "醫"), 0);
+
+        // 送出的同音字也能再叫出同音字；Backspace 收起
+        assert!(processor.show_homophones());
+        assert!(processor.handle_backspace());
+        assert!(processor.get_state().candidates.is_empty());
+    }
+
+    #[test]
+    fn test_symbol_menu() {
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        assert!(!processor.open_symbol_menu());
+        let symbols = SymbolTable::parse(
+            r#"{ "categories": [{ "name": "標點", "symbols": ["，", "。"] }, { "name": "箭頭", "symbols": ["←", "→"] }] }"#,
+        )
+        .unwrap();
+        processor.set_symbol_menu(Some('='), symbols);
+
+        assert!(processor.open_symbol_menu());
+        assert_eq!(processor.get_state().candidate_texts(), ["標點", "箭頭"]);
+        // 第一層選的是分類，不送出
+        assert_eq!(processor.handle_selection_key('2'), None);
+        assert_eq!(processor.symbol_category(), Some("箭頭"));
+        assert_eq!(processor.get_state().candidate_texts(), ["←", "→"]);
+        // Backspace 退回分類
+        assert!(processor.handle_backspace());
+        assert_eq!(processor.get_state().symbol_menu, Some(SymbolMenu::Categories));
+        assert_eq!(processor.handle_space(), None);
+        assert_eq!(processor.handle_selection_key('2'), Some("。".to_string()));
+        assert_eq!(processor.get_state().symbol_menu, None);
+        // 符號沒有字根，不計入字頻
+        assert!(processor.take_frequency_snapshot().is_none());
+
+        // 輸入字根時不開啟
+        processor.handle_code_input('a');
+        assert!(!processor.open_symbol_menu());
+    }
+
+    #[test]
+    fn test_observer_events() {
+        use crate::input_events::{InputEvent, InputObserver};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<InputEvent>>);
+        impl InputObserver for Recorder {
+            fn on_input_event(&self, event: &InputEvent) {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+
+        let mut processor = InputMethodProcessor::new(create_test_dictionary());
+        let recorder = Arc::new(Recorder::default());
+        let observer: Arc<dyn InputObserver> = recorder.clone();
+        processor.subscribe(&observer);
+
+        processor.handle_code_input('a');
+        processor.handle_code_input('b');
+        processor.handle_space();
+        processor.handle_code_input('a');
+        processor.handle_backspace();
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                InputEvent::CompositionStarted,
+                InputEvent::CandidatesUpdated,
+                InputEvent::CandidatesUpdated,
+                InputEvent::Committed("二".to_string()),
+                InputEvent::CompositionStarted,
+                InputEvent::CandidatesUpdated,
+                InputEvent::Cleared,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shorter_code_hint() {
+        let dictionary = Dictionary::from_json(r#"{ "chardefs": { "a": ["一"], "ab": ["二"], "abc": ["一", "三"] } }"#).unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+        processor.set_shorter_code_hints(true);
+        for ch in "abc".chars() {
+            processor.handle_code_input(ch);
+        }
+        assert_eq!(processor.handle_space().as_deref(), Some("一"));
+        assert_eq!(processor.shorter_code_hint().as_deref(), Some("「一」可用 1 碼: a"));
+
+        // 已經是最短的字根時不提示
+        processor.handle_code_input('a');
+        assert_eq!(processor.handle_space().as_deref(), Some("一"));
+        assert_eq!(processor.shorter_code_hint(), None);
+    }
+
+    #[test]
+    fn test_dead_end_code() {
+        let dictionary = Dictionary::from_json(r#"{ "chardefs": { "ab": ["二"], "abc": ["三"] } }"#).unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+        processor.handle_code_input('a');
+        // 還是更長字根的開頭，不算打錯
+        assert!(!processor.is_dead_end());
+        processor.handle_code_input('x');
+        assert!(processor.is_dead_end());
+        processor.handle_wildcard_input('*');
+        assert!(!processor.is_dead_end());
+    }
+}
@@ -1,19 +1,425 @@
 //! 鍵盤輸入模擬模組
 
-use anyhow::Result;
-use log::debug;
-use std::time::Duration;
+use crate::backend_health::BackendHealth;
+use crate::candidate::clusters;
+use crate::long_paste::{self, LongPaste};
+use crate::release_gate::{GateState, HeldKeys, ReleaseGate};
+use crate::send_limit::SendLimiter;
+use anyhow::{bail, Result};
+use tracing::{debug, info, warn};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::thread;
+use windows::Win32::System::DataExchange::GetClipboardSequenceNumber;
 use windows::Win32::UI::Input::KeyboardAndMouse::*;
+use windows::Win32::UI::WindowsAndMessaging::{GetClassNameW, GetForegroundWindow};
+
+/// 本程式以 Unicode SendInput 送字時放在 dwExtraInfo 的記號，鍵盤鉤子據此分辨自己送出的 VK_PACKET
+pub const UNICODE_INPUT_MARK: usize = 0x55434C;
+
+/// 等待剪貼簿更新的最長時間（超過後退回固定延遲）
+const CLIPBOARD_UPDATE_TIMEOUT: Duration = Duration::from_millis(200);
+/// 輪詢剪貼簿序號的間隔
+const CLIPBOARD_POLL_INTERVAL: Duration = Duration::from_millis(1);
+/// 無法確認剪貼簿已更新時的固定延遲（舊行為）
+const CLIPBOARD_FALLBACK_DELAY: Duration = Duration::from_millis(10);
+/// 混合句子分段送出時，貼上後等待目標程式讀走剪貼簿再改寫的時間
+const PASTE_SETTLE_DELAY: Duration = Duration::from_millis(30);
+
+/// 送出文字的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendBackend {
+    /// Unicode SendInput（KEYEVENTF_UNICODE），不動剪貼簿
+    Unicode,
+    /// 寫入剪貼簿後送 Ctrl+V
+    Paste,
+}
+
+/// 送出模式設定
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SendMode {
+    /// 依文字內容自動挑選（預設）：擴充平面字元用貼上，其餘用 Unicode SendInput
+    #[default]
+    Auto,
+    /// 一律貼上
+    Paste,
+    /// 一律 Unicode SendInput
+    Unicode,
+}
+
+impl SendMode {
+    /// 從設定值解析（"auto" / "paste" / "unicode"，不分大小寫，空字串為預設值）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "auto" => Ok(Self::Auto),
+            "paste" => Ok(Self::Paste),
+            "unicode" => Ok(Self::Unicode),
+            other => bail!("未知的送出模式 '{}'（可用: auto、paste、unicode）", other),
+        }
+    }
+}
+
+/// 前景視窗是終端機時的送字相容策略
+/// 終端機收到多行貼上會跳出貼上保護或逐行執行，結尾換行會直接執行指令
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TerminalMode {
+    /// 不特別處理
+    Off,
+    /// 去除結尾換行後改用逐字 Unicode 輸入（預設），仍有多行時先詢問
+    #[default]
+    Unicode,
+    /// 只去除結尾換行，照一般送出模式送出
+    StripNewline,
+    /// 多行文字先詢問使用者再送出
+    Confirm,
+}
+
+impl TerminalMode {
+    /// 從設定值解析（"off" / "unicode" / "strip_newline" / "confirm"，不分大小寫，空字串為預設值）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "" | "unicode" => Ok(Self::Unicode),
+            "strip_newline" => Ok(Self::StripNewline),
+            "confirm" => Ok(Self::Confirm),
+            other => bail!("未知的終端機相容模式 '{}'（可用: off、unicode、strip_newline、confirm）", other),
+        }
+    }
+}
+
+/// 視為終端機的視窗類別（ConHost、Windows Terminal、mintty、ConEmu、PuTTY）
+const TERMINAL_WINDOW_CLASSES: &[&str] = &[
+    "ConsoleWindowClass",
+    "CASCADIA_HOSTING_WINDOW_CLASS",
+    "mintty",
+    "VirtualConsoleClass",
+    "PuTTY",
+];
+
+/// 視窗類別是否為終端機
+pub fn is_terminal_class(class_name: &str) -> bool {
+    TERMINAL_WINDOW_CLASSES.contains(&class_name)
+}
+
+/// 送往終端機的文字要怎麼處理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalAction {
+    /// 直接送出（mode 為 None 時使用設定的送出模式）
+    Send { text: String, mode: Option<SendMode> },
+    /// 多行文字，先詢問使用者
+    Confirm(String),
+}
+
+/// 依終端機相容策略決定送出方式
+pub fn terminal_action(text: &str, mode: TerminalMode) -> TerminalAction {
+    let stripped = text.trim_end_matches(['\r', '\n']).to_string();
+    match mode {
+        TerminalMode::Off => TerminalAction::Send { text: text.to_string(), mode: None },
+        TerminalMode::StripNewline => TerminalAction::Send { text: stripped, mode: None },
+        TerminalMode::Unicode if stripped.contains('\n') => TerminalAction::Confirm(stripped),
+        TerminalMode::Unicode => TerminalAction::Send { text: stripped, mode: Some(SendMode::Unicode) },
+        TerminalMode::Confirm if text.contains('\n') => TerminalAction::Confirm(stripped),
+        TerminalMode::Confirm => TerminalAction::Send { text: text.to_string(), mode: None },
+    }
+}
+
+/// 目前前景視窗的類別名稱
+fn foreground_window_class() -> Option<String> {
+    let mut buffer = [0u16; 256];
+    let len = unsafe { GetClassNameW(GetForegroundWindow(), &mut buffer) };
+    (len > 0).then(|| String::from_utf16_lossy(&buffer[..len as usize]))
+}
+
+/// send_to_foreground 的結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendOutcome {
+    /// 已送出
+    Sent,
+    /// 前景是終端機且文字有多行，需要使用者確認（確認後以 send_text 送出這段文字）
+    NeedsConfirm(String),
+    /// 超過送字限制或還有實體按鍵按著（抬手才上屏），還有文字留在佇列中，稍後由 flush_backlog 送出
+    Deferred,
+}
+
+/// 字元適合的送出方式
+/// 擴充平面（U+10000 以上，例如 CJK 擴充 B 之後的字）以 SendInput 送出時要拆成
+/// surrogate pair，部分程式會收到壞字，改用剪貼簿貼上最可靠
+pub fn backend_for(ch: char) -> SendBackend {
+    if ch as u32 > 0xFFFF {
+        SendBackend::Paste
+    } else {
+        SendBackend::Unicode
+    }
+}
+
+/// 依送出模式把文字切成連續同一送出方式的片段
+pub fn plan_segments(text: &str, mode: SendMode) -> Vec<(SendBackend, &str)> {
+    let fixed = match mode {
+        SendMode::Auto => None,
+        SendMode::Paste => Some(SendBackend::Paste),
+        SendMode::Unicode => Some(SendBackend::Unicode),
+    };
+    if let Some(backend) = fixed {
+        return if text.is_empty() { Vec::new() } else { vec![(backend, text)] };
+    }
+
+    // 先切成一個個字（組合字元、ZWJ 序列不拆開），字裡有擴充平面字元時整個字都用貼上
+    let mut segments: Vec<(SendBackend, &str)> = Vec::new();
+    let mut start = 0;
+    let mut current: Option<SendBackend> = None;
+    let mut i = 0;
+    for cluster in clusters(text) {
+        let backend = if cluster.chars().any(|ch| backend_for(ch) == SendBackend::Paste) {
+            SendBackend::Paste
+        } else {
+            SendBackend::Unicode
+        };
+        match current {
+            Some(c) if c != backend => {
+                segments.push((c, &text[start..i]));
+                start = i;
+                current = Some(backend);
+            }
+            None => current = Some(backend),
+            _ => {}
+        }
+        i += cluster.len();
+    }
+    if let Some(c) = current {
+        segments.push((c, &text[start..]));
+    }
+    segments
+}
+
+/// 送出一次選字的結果：詞組要整個一起送出，才不會在目標程式裡變成好幾次
+/// 輸入、復原時只退回一半；Auto 模式下需要拆段的詞組改成整段貼上
+pub fn plan_commit(text: &str, mode: SendMode) -> Vec<(SendBackend, &str)> {
+    let segments = plan_segments(text, mode);
+    if segments.len() > 1 {
+        vec![(SendBackend::Paste, text)]
+    } else {
+        segments
+    }
+}
 
 /// 輸入模擬器
 pub struct InputSimulator {
     // 暫時不使用 enigo，改用 Windows API
+    mode: SendMode,
+    /// 前景是終端機時的相容策略
+    terminal_mode: TerminalMode,
+    /// 送字的長度與速率限制
+    limiter: SendLimiter,
+    /// 因送字限制延後送出的文字
+    backlog: String,
+    /// 這批延後的文字是否已經提示過
+    throttle_reported: bool,
+    /// 進行中的長文分段送出（見 long_paste 模組）
+    long_paste: Option<LongPaste>,
+    /// 長文分段送出的每段字數與段間延遲
+    long_paste_chunk: (usize, Duration),
+    /// 抬手才上屏：送出前等待實體按鍵放開的門檻（None 表示不等待）
+    release_gate: Option<ReleaseGate>,
+    /// 鍵盤鉤子回報的實體按鍵狀態
+    held_keys: Arc<HeldKeys>,
+    /// 各送字後端的狀態（建立時自測，之後以實際送字的結果更新）
+    health: BackendHealth,
 }
 
 impl InputSimulator {
     pub fn new() -> Result<Self> {
-        Ok(Self {})
+        Ok(Self {
+            mode: SendMode::default(),
+            terminal_mode: TerminalMode::default(),
+            limiter: SendLimiter::default(),
+            backlog: String::new(),
+            throttle_reported: false,
+            long_paste: None,
+            long_paste_chunk: (long_paste::DEFAULT_CHUNK_CHARS, long_paste::DEFAULT_DELAY),
+            release_gate: None,
+            held_keys: Arc::default(),
+            health: BackendHealth::probe(),
+        })
+    }
+
+    /// 各送字後端目前的狀態
+    pub fn health(&self) -> &BackendHealth {
+        &self.health
+    }
+    
+    /// 設定送出模式
+    pub fn set_mode(&mut self, mode: SendMode) {
+        self.mode = mode;
+    }
+    
+    /// 設定終端機相容策略
+    pub fn set_terminal_mode(&mut self, mode: TerminalMode) {
+        self.terminal_mode = mode;
+    }
+    
+    /// 設定送字限制：單次送出的字數上限與每分鐘的字數上限（0 表示不限）
+    pub fn set_limits(&mut self, max_chars: usize, max_chars_per_minute: usize) {
+        self.limiter = SendLimiter::new(max_chars, max_chars_per_minute);
+    }
+    
+    /// 設定長文分段送出的每段字數與段間延遲
+    pub fn set_long_paste(&mut self, chunk_chars: usize, delay: Duration) {
+        self.long_paste_chunk = (chunk_chars, delay);
+    }
+    
+    /// 設定抬手才上屏：held_keys 為鍵盤鉤子回報的按鍵狀態，timeout 為最多等待多久（None 表示不等待）
+    pub fn set_release_gate(&mut self, held_keys: Arc<HeldKeys>, timeout: Option<Duration>) {
+        self.held_keys = held_keys;
+        self.release_gate = timeout.map(ReleaseGate::new);
+    }
+    
+    /// 開始分段送出一段長文（取代還沒送完的上一段）
+    pub fn start_long_paste(&mut self, text: &str) {
+        let (chunk_chars, delay) = self.long_paste_chunk;
+        let paste = LongPaste::new(text, chunk_chars, delay, Instant::now());
+        info!("開始分段送出 {} 字（每段 {} 字，間隔 {} 毫秒）", paste.progress().1, chunk_chars, delay.as_millis());
+        self.long_paste = Some(paste);
+    }
+    
+    /// 時間到了送出長文的下一段（訊息循環定期呼叫），回傳送出的段落與結果；沒有要送的段落時回傳 None
+    pub fn flush_long_paste(&mut self) -> Option<(String, Result<SendOutcome>)> {
+        let paste = self.long_paste.as_mut()?;
+        let chunk = paste.next_chunk(Instant::now())?;
+        let (sent, total) = paste.progress();
+        debug!("分段送出 {}/{} 字", sent, total);
+        if paste.is_done() {
+            info!("長文分段送出完成（{} 字）", total);
+            self.long_paste = None;
+        }
+        let result = self.send_to_foreground(&chunk);
+        Some((chunk, result))
+    }
+    
+    /// 長文分段送出的進度（已送出字數, 全部字數），沒有進行中的長文時為 None
+    pub fn long_paste_progress(&self) -> Option<(usize, usize)> {
+        self.long_paste.as_ref().map(LongPaste::progress)
+    }
+    
+    /// 取消長文分段送出，回傳還沒送出的字數
+    pub fn cancel_long_paste(&mut self) -> usize {
+        self.long_paste.take().map_or(0, |paste| paste.remaining_chars())
+    }
+    
+    /// 送出選字結果到前景視窗；有設定送字限制或抬手才上屏時先排進佇列，能送多少送多少
+    pub fn send_to_foreground(&mut self, text: &str) -> Result<SendOutcome> {
+        if !self.limiter.is_enabled() && self.release_gate.is_none() {
+            return self.send_now(text);
+        }
+        self.backlog.push_str(text);
+        self.flush_backlog()
+    }
+    
+    /// 是否有因送字限制或等待按鍵放開而延後的文字
+    pub fn has_backlog(&self) -> bool {
+        !self.backlog.is_empty()
+    }
+    
+    /// 丟棄延後的文字（緊急放行時），回傳丟掉的字數
+    pub fn clear_backlog(&mut self) -> usize {
+        let count = self.backlog.chars().count();
+        self.backlog.clear();
+        self.throttle_reported = false;
+        count
+    }
+    
+    /// 在送字限制內送出佇列中的文字，送不完的留待下次（訊息循環定期呼叫）
+    /// 依字元叢集切開，不會拆開組合字元或 emoji 序列；抬手才上屏時等實體按鍵都放開才送
+    pub fn flush_backlog(&mut self) -> Result<SendOutcome> {
+        if self.backlog.is_empty() {
+            return Ok(SendOutcome::Sent);
+        }
+        let now = Instant::now();
+        if let Some(gate) = self.release_gate.as_mut() {
+            match gate.check(self.held_keys.any_held(), now) {
+                GateState::Open => {}
+                GateState::Waiting => return Ok(SendOutcome::Deferred),
+                GateState::TimedOut => {
+                    warn!("等待按鍵放開逾時，照常送出（可能漏掉了放開的事件）");
+                    self.held_keys.clear();
+                }
+            }
+        }
+        let allowance = self.limiter.allowance(now);
+        if allowance == 0 {
+            if !self.throttle_reported {
+                self.throttle_reported = true;
+                warn!("超過送字限制，剩下 {} 字稍後分段送出", self.backlog.chars().count());
+            }
+            return Ok(SendOutcome::Deferred);
+        }
+        
+        let (mut end, mut count) = (0, 0);
+        for cluster in clusters(&self.backlog) {
+            let chars = cluster.chars().count();
+            if count > 0 && count + chars > allowance {
+                break;
+            }
+            end += cluster.len();
+            count += chars;
+        }
+        let chunk: String = self.backlog.drain(..end).collect();
+        let split = !self.backlog.is_empty();
+        self.limiter.record(now, count, split);
+        if split {
+            debug!("超過送字限制，分段送出 {} 字（剩 {} 字）", count, self.backlog.chars().count());
+        } else if self.throttle_reported {
+            self.throttle_reported = false;
+            info!("延後的文字已全部送出");
+        }
+        match self.send_now(&chunk)? {
+            SendOutcome::Sent if split => Ok(SendOutcome::Deferred),
+            outcome => Ok(outcome),
+        }
+    }
+    
+    /// 立即送出選字結果（詞組整個一起送出，見 plan_commit）；
+    /// 前景是終端機時依相容策略調整（見 TerminalMode）
+    fn send_now(&mut self, text: &str) -> Result<SendOutcome> {
+        if self.terminal_mode != TerminalMode::Off {
+            if let Some(class_name) = foreground_window_class().filter(|c| is_terminal_class(c)) {
+                match terminal_action(text, self.terminal_mode) {
+                    TerminalAction::Confirm(text) => {
+                        debug!("前景為終端機（{}），多行文字等待確認", class_name);
+                        return Ok(SendOutcome::NeedsConfirm(text));
+                    }
+                    TerminalAction::Send { text, mode } => {
+                        debug!("前景為終端機（{}），以相容模式 {:?} 送出", class_name, self.terminal_mode);
+                        self.send_segments(plan_commit(&text, mode.unwrap_or(self.mode)))?;
+                        return Ok(SendOutcome::Sent);
+                    }
+                }
+            }
+        }
+        self.send_segments(plan_commit(text, self.mode))?;
+        Ok(SendOutcome::Sent)
+    }
+    
+    /// 送出文字，依送出模式挑選後端；混合句子分段送出
+    pub fn send_text(&mut self, text: &str) -> Result<()> {
+        self.send_segments(plan_segments(text, self.mode))
+    }
+    
+    /// 依序送出各片段
+    fn send_segments(&mut self, segments: Vec<(SendBackend, &str)>) -> Result<()> {
+        let count = segments.len();
+        for (i, (backend, segment)) in segments.into_iter().enumerate() {
+            match backend {
+                SendBackend::Unicode => self.send_text_unicode(segment)?,
+                SendBackend::Paste => {
+                    self.send_text_paste(segment)?;
+                    // 後面還有片段時，等目標程式處理完 Ctrl+V 再繼續，避免剪貼簿被下一段改寫
+                    if i + 1 < count {
+                        thread::sleep(PASTE_SETTLE_DELAY);
+                    }
+                }
+            }
+        }
+        Ok(())
     }
     
     /// 發送文字（使用剪貼簿貼上方式）
@@ -22,11 +428,13 @@ impl InputSimulator {
         
         debug!("發送文字（貼上模式）: {}", text);
         
-        let mut clipboard = Clipboard::new()?;
-        clipboard.set_text(text)?;
+        let seq_before = unsafe { GetClipboardSequenceNumber() };
+        let written = Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text));
+        self.health.record_clipboard(written.as_ref().map(|_| ()).map_err(|e| e.to_string()));
+        written?;
         
-        // 等待剪貼簿更新
-        thread::sleep(Duration::from_millis(10));
+        // 等待剪貼簿更新（慢機器上固定延遲可能搶拍，改為確認序號已變化）
+        Self::wait_for_clipboard_update(seq_before);
         
         // 發送 Ctrl+V (使用 Windows API)
         unsafe {
@@ -61,14 +469,160 @@ impl InputSimulator {
         Ok(())
     }
     
+    /// 等待剪貼簿序號變化，確認內容已更新後才送 Ctrl+V
+    /// 超過 CLIPBOARD_UPDATE_TIMEOUT 仍未變化時，退回固定延遲
+    fn wait_for_clipboard_update(seq_before: u32) {
+        let start = Instant::now();
+        while start.elapsed() < CLIPBOARD_UPDATE_TIMEOUT {
+            let seq_now = unsafe { GetClipboardSequenceNumber() };
+            // 序號為 0 表示無法取得（例如沒有 WINSTA_ACCESSCLIPBOARD 權限），直接退回固定延遲
+            if seq_now == 0 {
+                break;
+            }
+            if seq_now != seq_before {
+                debug!("剪貼簿已更新（序號 {} -> {}），耗時 {:?}", seq_before, seq_now, start.elapsed());
+                return;
+            }
+            thread::sleep(CLIPBOARD_POLL_INTERVAL);
+        }
+        
+        warn!("無法確認剪貼簿已更新，退回固定延遲 {:?}", CLIPBOARD_FALLBACK_DELAY);
+        thread::sleep(CLIPBOARD_FALLBACK_DELAY);
+    }
+    
     /// 發送文字（直接輸入方式）
-    /// TODO: 實作 Unicode 字元輸入
     pub fn send_text_direct(&mut self, text: &str) -> Result<()> {
-        debug!("發送文字（直接輸入）: {}", text);
+        self.send_text_unicode(text)
+    }
+    
+    /// 送出 count 次退格（撤銷上一次送出）
+    pub fn send_backspaces(&mut self, count: usize) -> Result<()> {
+        let key = |flags: KEYBD_EVENT_FLAGS| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 { ki: KEYBDINPUT { wVk: VK_BACK, wScan: 0, dwFlags: flags, time: 0, dwExtraInfo: 0 } },
+        };
+        let inputs: Vec<INPUT> = (0..count).flat_map(|_| [key(KEYBD_EVENT_FLAGS(0)), key(KEYEVENTF_KEYUP)]).collect();
+        if inputs.is_empty() {
+            return Ok(());
+        }
+        let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            bail!("SendInput 只送出 {}/{} 個退格事件（可能被 UIPI 擋下）", sent, inputs.len());
+        }
+        Ok(())
+    }
+    
+    /// 以 Unicode SendInput 送出文字（每個 UTF-16 單元一組按下/放開，擴充平面字元會拆成 surrogate pair）
+    pub fn send_text_unicode(&mut self, text: &str) -> Result<()> {
+        debug!("發送文字（Unicode 輸入）: {}", text);
         
-        // 暫時使用貼上模式
-        // TODO: 實作真正的直接輸入
-        self.send_text_paste(text)
+        let mut inputs = Vec::with_capacity(text.len() * 2);
+        for unit in text.encode_utf16() {
+            for flags in [KEYEVENTF_UNICODE, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP] {
+                inputs.push(INPUT {
+                    r#type: INPUT_KEYBOARD,
+                    Anonymous: INPUT_0 {
+                        ki: KEYBDINPUT {
+                            wVk: VIRTUAL_KEY(0),
+                            wScan: unit,
+                            dwFlags: flags,
+                            time: 0,
+                            dwExtraInfo: UNICODE_INPUT_MARK,
+                        },
+                    },
+                });
+            }
+        }
+        if inputs.is_empty() {
+            return Ok(());
+        }
+        
+        let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            let reason = format!("SendInput 只送出 {}/{} 個事件（可能被 UIPI 擋下）", sent, inputs.len());
+            self.health.record_send_input(Err(reason.clone()));
+            bail!(reason);
+        }
+        self.health.record_send_input(Ok(()));
+        Ok(())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::candidate::is_phrase;
+
+    #[test]
+    fn test_plan_segments_splits_extension_chars() {
+        assert_eq!(plan_segments("你好", SendMode::Auto), vec![(SendBackend::Unicode, "你好")]);
+        assert_eq!(plan_segments("𠀀𠀁", SendMode::Auto), vec![(SendBackend::Paste, "𠀀𠀁")]);
+        assert_eq!(
+            plan_segments("我𠀀你a𪚥", SendMode::Auto),
+            vec![
+                (SendBackend::Unicode, "我"),
+                (SendBackend::Paste, "𠀀"),
+                (SendBackend::Unicode, "你a"),
+                (SendBackend::Paste, "𪚥"),
+            ]
+        );
+        assert!(plan_segments("", SendMode::Auto).is_empty());
+    }
+
+    #[test]
+    fn test_plan_segments_keeps_clusters_together() {
+        // ZWJ 與變體選擇符本身在基本平面，但不能和前後的 emoji 分開送出
+        let family = "👨\u{200D}👩\u{200D}👧";
+        assert_eq!(plan_segments(family, SendMode::Auto), vec![(SendBackend::Paste, family)]);
+        assert_eq!(
+            plan_segments("好❤\u{FE0F}👍\u{1F3FD}", SendMode::Auto),
+            vec![(SendBackend::Unicode, "好❤\u{FE0F}"), (SendBackend::Paste, "👍\u{1F3FD}")]
+        );
+        let lenny = "( ͡° ͜ʖ ͡°)";
+        assert_eq!(plan_segments(lenny, SendMode::Auto), vec![(SendBackend::Unicode, lenny)]);
+    }
+
+    #[test]
+    fn test_plan_commit_keeps_phrase_atomic() {
+        assert!(is_phrase("測試"));
+        assert!(!is_phrase("👨\u{200D}👩\u{200D}👧"));
+        assert_eq!(clusters("好❤\u{FE0F}"), vec!["好", "❤\u{FE0F}"]);
+        // 單字與不用拆段的詞組照常送出，需要拆段的詞組整段貼上
+        assert_eq!(plan_commit("測試", SendMode::Auto), vec![(SendBackend::Unicode, "測試")]);
+        assert_eq!(plan_commit("我𠀀你", SendMode::Auto), vec![(SendBackend::Paste, "我𠀀你")]);
+        assert_eq!(plan_segments("我𠀀你", SendMode::Auto).len(), 3);
+    }
+
+    #[test]
+    fn test_plan_segments_fixed_mode() {
+        assert_eq!(plan_segments("我𠀀", SendMode::Paste), vec![(SendBackend::Paste, "我𠀀")]);
+        assert_eq!(plan_segments("我𠀀", SendMode::Unicode), vec![(SendBackend::Unicode, "我𠀀")]);
+        assert!(plan_segments("", SendMode::Paste).is_empty());
+    }
+
+    #[test]
+    fn test_terminal_action() {
+        assert!(is_terminal_class("CASCADIA_HOSTING_WINDOW_CLASS"));
+        assert!(!is_terminal_class("Notepad"));
+
+        let send = |text: &str, mode| TerminalAction::Send { text: text.to_string(), mode };
+        assert_eq!(terminal_action("ls\r\n", TerminalMode::Off), send("ls\r\n", None));
+        assert_eq!(terminal_action("ls\r\n", TerminalMode::StripNewline), send("ls", None));
+        assert_eq!(terminal_action("ls\n", TerminalMode::Unicode), send("ls", Some(SendMode::Unicode)));
+        assert_eq!(terminal_action("a\nb\n", TerminalMode::Unicode), TerminalAction::Confirm("a\nb".to_string()));
+        assert_eq!(terminal_action("ls\n", TerminalMode::Confirm), TerminalAction::Confirm("ls".to_string()));
+        assert_eq!(terminal_action("ls", TerminalMode::Confirm), send("ls", None));
+
+        assert_eq!(TerminalMode::parse("").unwrap(), TerminalMode::Unicode);
+        assert_eq!(TerminalMode::parse("Strip_Newline").unwrap(), TerminalMode::StripNewline);
+        assert!(TerminalMode::parse("ask").is_err());
+    }
+
+    #[test]
+    fn test_send_mode_parse() {
+        assert_eq!(SendMode::parse("").unwrap(), SendMode::Auto);
+        assert_eq!(SendMode::parse(" Paste ").unwrap(), SendMode::Paste);
+        assert_eq!(SendMode::parse("unicode").unwrap(), SendMode::Unicode);
+        assert!(SendMode::parse("typing").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! 開發者用的即時狀態檢視
+//!
+//! 托盤「狀態檢視（開發者）」只在 debug 建置或開啟 inspector feature（cargo build --release --features inspector）時出現，
+//! 開啟的視窗每秒呼叫 lines 取一次內部指標：
+//! - 輸入處理器的組字狀態；
+//! - 各個共用鎖目前是否被持有（以 try_lock 探測，不會等待，也不會卡住 GUI 執行緒）；
+//! - 待處理的佇列：待送出的文字與退格、送字限制延後的文字、待重繪旗標；
+//! - 鍵盤鉤子回呼耗時統計。
+
+use crate::hook_timing::HookTiming;
+use crate::input_method::InputMethodProcessor;
+use crate::input_simulator::InputSimulator;
+use crate::AppState;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
+
+/// 狀態檢視的資料來源（與 AppState 共用）
+pub struct Inspector {
+    processor: Arc<Mutex<InputMethodProcessor>>,
+    input_simulator: Arc<Mutex<InputSimulator>>,
+    /// 只探測鎖，不讀內容
+    locks: Vec<(&'static str, Arc<dyn LockProbe>)>,
+    pending_paste_text: Arc<Mutex<Option<String>>>,
+    pending_backspaces: Arc<AtomicUsize>,
+    gui_needs_update: Arc<AtomicBool>,
+    hook_timing: Arc<HookTiming>,
+}
+
+/// 可以探測是否被持有的鎖
+trait LockProbe: Send + Sync {
+    fn probe(&self) -> &'static str;
+}
+
+impl<T: Send> LockProbe for Mutex<T> {
+    fn probe(&self) -> &'static str {
+        match self.try_lock() {
+            Ok(_) => "空閒",
+            Err(TryLockError::WouldBlock) => "持有中",
+            Err(TryLockError::Poisoned(_)) => "已中毒（持有者 panic）",
+        }
+    }
+}
+
+impl Inspector {
+    pub fn new(state: &AppState) -> Self {
+        let locks: Vec<(&'static str, Arc<dyn LockProbe>)> = vec![
+            ("輸入處理器", state.input_processor.clone()),
+            ("字典", state.dictionary.clone()),
+            ("輸入模擬器", state.input_simulator.clone()),
+            ("待送出文字", state.pending_paste_text.clone()),
+            ("巨集", state.macros.clone()),
+            ("使用者", state.profile.clone()),
+        ];
+        Self {
+            processor: state.input_processor.clone(),
+            input_simulator: state.input_simulator.clone(),
+            locks,
+            pending_paste_text: state.pending_paste_text.clone(),
+            pending_backspaces: state.pending_backspaces.clone(),
+            gui_needs_update: state.gui_needs_update.clone(),
+            hook_timing: state.hook_timing.clone(),
+        }
+    }
+
+    /// 目前的指標，一行一項（分組標題以【】標出）
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec!["【輸入處理器】".to_string()];
+        // 鍵盤鉤子正在處理按鍵時不等它，下一秒再看
+        match self.processor.try_lock() {
+            Ok(processor) => {
+                let state = processor.get_state();
+                lines.push(format!("字根: '{}'", state.current_code));
+                lines.push(format!("候選: {} 個，從第 {} 個開始顯示", state.candidates.len(), state.candidate_index + 1));
+                lines.push(format!("聯想詞: {} 個", state.suggestions.len()));
+                if let Some(text) = &state.temp_english {
+                    lines.push(format!("暫時英文: '{}'", text));
+                }
+                let composition = processor.composition();
+                if !composition.is_empty() {
+                    lines.push(format!("整句: '{}'", composition));
+                }
+            }
+            Err(_) => lines.push("（鎖被持有中，略過）".to_string()),
+        }
+
+        lines.push("【鎖】".to_string());
+        for (name, lock) in &self.locks {
+            lines.push(format!("{}: {}", name, lock.probe()));
+        }
+
+        lines.push("【佇列】".to_string());
+        let pending_text = match self.pending_paste_text.try_lock() {
+            Ok(text) => text.as_deref().map_or(0, |text| text.chars().count()).to_string(),
+            Err(_) => "?".to_string(),
+        };
+        lines.push(format!("待送出文字: {} 字", pending_text));
+        lines.push(format!("待送出退格: {}", self.pending_backspaces.load(Ordering::Relaxed)));
+        let backlog = match self.input_simulator.try_lock() {
+            Ok(simulator) => if simulator.has_backlog() { "有" } else { "無" },
+            Err(_) => "?",
+        };
+        lines.push(format!("送字限制延後的文字: {}", backlog));
+        lines.push(format!("待重繪: {}", if self.gui_needs_update.load(Ordering::Relaxed) { "是" } else { "否" }));
+
+        lines.push("【鍵盤鉤子】".to_string());
+        // 只列出摘要，耗時分佈看托盤的統計面板
+        lines.extend(self.hook_timing.summary().lines().take_while(|line| !line.is_empty()).map(str::to_string));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_probe() {
+        let lock = Mutex::new(0);
+        assert_eq!(lock.probe(), "空閒");
+        let guard = lock.lock().unwrap();
+        assert_eq!(lock.probe(), "持有中");
+        drop(guard);
+    }
+}
@@ -0,0 +1,63 @@
+//! 開發者用的狀態檢視視窗
+//!
+//! 列出 Inspector 的即時指標（見 inspector 模組），視窗開著時每秒重新填入一次。
+
+use crate::inspector::Inspector;
+use fltk::{browser::Browser, button::Button, enums::Align, frame::Frame, prelude::*, window::Window};
+use std::time::{Duration, Instant};
+
+/// 重新整理的間隔
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 狀態檢視視窗
+pub struct InspectorWindow {
+    window: Window,
+    entries: Browser,
+    inspector: Inspector,
+    /// 上次填入的時間
+    refreshed_at: Instant,
+}
+
+impl InspectorWindow {
+    pub fn new(inspector: Inspector) -> Self {
+        let window = Window::default().with_size(420, 480).with_label("狀態檢視（開發者）").center_screen();
+
+        let mut hint = Frame::new(10, 5, 400, 25, "內部狀態（每秒更新）");
+        hint.set_align(Align::Left | Align::Inside);
+
+        let mut entries = Browser::new(10, 35, 400, 400, "");
+        entries.set_text_size(14);
+        // 字根與文字可能含 @ 這類列表格式碼，不使用格式碼（見 LookupWindow）
+        entries.set_format_char('\u{1}');
+
+        let mut close_button = Button::new(310, 442, 100, 30, "關閉");
+        window.end();
+
+        {
+            let mut window = window.clone();
+            close_button.set_callback(move |_| window.hide());
+        }
+
+        Self { window, entries, inspector, refreshed_at: Instant::now() }
+    }
+
+    pub fn show(&mut self) {
+        self.fill();
+        self.window.show();
+    }
+
+    /// 視窗開著且距離上次填入超過一秒時重新填入列表
+    pub fn refresh(&mut self) {
+        if self.window.shown() && self.refreshed_at.elapsed() >= REFRESH_INTERVAL {
+            self.fill();
+        }
+    }
+
+    fn fill(&mut self) {
+        self.refreshed_at = Instant::now();
+        self.entries.clear();
+        for line in self.inspector.lines() {
+            self.entries.add(&line);
+        }
+    }
+}
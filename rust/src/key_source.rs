@@ -0,0 +1,130 @@
+//! 鍵盤事件來源（測試建置）
+//!
+//! 執行時按鍵由 Windows 低階鍵盤鉤子推進 KeyboardHook 的回呼，整合測試無法產生真實的系統事件。
+//! 這裡把「一串按鍵事件」抽象成 KeyboardSource，由 KeyboardHook::drive 逐一交給和鉤子回呼相同的
+//! process_keyboard_event，走完按鍵判斷、輸入處理器與送字佇列，不需要安裝鉤子。
+//!
+//! ScriptedSource 讓測試以 (vk, 按下/放開, 時間) 的序列描述要按的鍵：
+//!
+//! ```ignore
+//! let mut source = ScriptedSource::new().tap(0x41).tap(0x20); // a、Space
+//! let run = KeyboardHook::drive(&state, &mut source);
+//! assert_eq!(run.sent, ["一"]);
+//! ```
+
+use std::collections::VecDeque;
+
+/// 連續事件之間預設相隔的毫秒數
+const DEFAULT_STEP_MS: u32 = 30;
+
+/// 一個按鍵事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// 虛擬鍵碼
+    pub vk: u32,
+    /// true 為按下，false 為放開
+    pub down: bool,
+    /// 事件時間（毫秒，對應 KBDLLHOOKSTRUCT::time）
+    pub time: u32,
+}
+
+/// 鍵盤事件來源
+pub trait KeyboardSource {
+    /// 下一個事件，沒有了時回傳 None
+    fn next_event(&mut self) -> Option<KeyEvent>;
+}
+
+/// 測試用的腳本事件來源：依序回傳事先排好的事件
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedSource {
+    events: VecDeque<KeyEvent>,
+    /// 下一個事件的時間
+    clock: u32,
+}
+
+impl ScriptedSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 直接以 (vk, 按下/放開, 時間) 序列建立
+    pub fn from_events(events: impl IntoIterator<Item = (u32, bool, u32)>) -> Self {
+        let events: VecDeque<KeyEvent> = events.into_iter().map(|(vk, down, time)| KeyEvent { vk, down, time }).collect();
+        let clock = events.back().map_or(0, |event| event.time + DEFAULT_STEP_MS);
+        Self { events, clock }
+    }
+
+    /// 加上一個事件，時間往後推 DEFAULT_STEP_MS
+    pub fn push(mut self, vk: u32, down: bool) -> Self {
+        self.events.push_back(KeyEvent { vk, down, time: self.clock });
+        self.clock += DEFAULT_STEP_MS;
+        self
+    }
+
+    /// 按下
+    pub fn press(self, vk: u32) -> Self {
+        self.push(vk, true)
+    }
+
+    /// 放開
+    pub fn release(self, vk: u32) -> Self {
+        self.push(vk, false)
+    }
+
+    /// 按一下（按下再放開）
+    pub fn tap(self, vk: u32) -> Self {
+        self.press(vk).release(vk)
+    }
+
+    /// 按著 modifier 按一下 vk，例如 Ctrl+Space
+    pub fn chord(self, modifier: u32, vk: u32) -> Self {
+        self.press(modifier).tap(vk).release(modifier)
+    }
+
+    /// 等待 ms 毫秒再送下一個事件
+    pub fn wait(mut self, ms: u32) -> Self {
+        self.clock += ms;
+        self
+    }
+
+    /// 還沒送出的事件數
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+}
+
+impl KeyboardSource for ScriptedSource {
+    fn next_event(&mut self) -> Option<KeyEvent> {
+        self.events.pop_front()
+    }
+}
+
+/// KeyboardHook::drive 的結果
+#[derive(Debug, Default)]
+pub struct ScriptRun {
+    /// 每個事件是否被攔截（與事件同順序）
+    pub blocked: Vec<bool>,
+    /// 依序排進送字佇列的文字（實際執行時會交給輸入模擬器送出）
+    pub sent: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripted_source() {
+        let mut source = ScriptedSource::new().tap(0x41).wait(100).chord(0x11, 0x20);
+        assert_eq!(source.remaining(), 6);
+        let events: Vec<KeyEvent> = std::iter::from_fn(|| source.next_event()).collect();
+        let keys: Vec<(u32, bool)> = events.iter().map(|event| (event.vk, event.down)).collect();
+        assert_eq!(keys, [(0x41, true), (0x41, false), (0x11, true), (0x20, true), (0x20, false), (0x11, false)]);
+        let times: Vec<u32> = events.iter().map(|event| event.time).collect();
+        assert_eq!(times, [0, 30, 160, 190, 220, 250]);
+        assert_eq!(source.next_event(), None);
+
+        let mut source = ScriptedSource::from_events([(0x41, true, 5), (0x41, false, 9)]).tap(0x20);
+        let times: Vec<u32> = std::iter::from_fn(|| source.next_event()).map(|event| event.time).collect();
+        assert_eq!(times, [5, 9, 39, 69]);
+    }
+}
@@ -0,0 +1,227 @@
+//! 按鍵回放
+//!
+//! 中英混打時不容易看出按鍵被輸入法怎麼處理。鍵盤鉤子每次按下按鍵後記錄一筆：
+//! 按了什麼鍵、處理方式（放行給前景程式、攔截當作字根或選字、轉換成文字送出）與處理後的字根，
+//! 只保留最近 key_trace_len 筆。托盤「按鍵回放」視窗列出這些紀錄，每筆也以 trace 等級寫入日誌
+//! （托盤「日誌等級」選「鍵盤鉤子 trace」即可在主控台看到）。
+//!
+//! 送出的文字由輸入處理器的 Committed 事件得知（訂閱輸入事件），在同一次鉤子回呼裡歸給這個按鍵。
+
+use crate::input_events::{InputEvent, InputObserver};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tracing::trace;
+
+/// 預設保留的按鍵數
+pub const DEFAULT_TRACE_LEN: usize = 30;
+
+/// 按鍵的處理方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyOutcome {
+    /// 放行給前景程式
+    Passed,
+    /// 攔截（當作字根、選字、翻頁等，沒有送出文字）
+    Blocked,
+    /// 攔截並送出文字
+    Converted(String),
+}
+
+/// 一筆按鍵紀錄
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyTraceEntry {
+    /// 按鍵名稱（見 key_label）
+    pub key: String,
+    pub outcome: KeyOutcome,
+    /// 處理後的字根（沒有在組字時為空）
+    pub code: String,
+}
+
+impl KeyTraceEntry {
+    /// 列表與日誌用的一行文字，例如 "a → 攔截（字根 a）"
+    pub fn line(&self) -> String {
+        let outcome = match &self.outcome {
+            KeyOutcome::Passed => "放行".to_string(),
+            KeyOutcome::Blocked => "攔截".to_string(),
+            KeyOutcome::Converted(text) => format!("轉換為「{}」", text),
+        };
+        if self.code.is_empty() {
+            format!("{} → {}", self.key, outcome)
+        } else {
+            format!("{} → {}（字根 {}）", self.key, outcome, self.code)
+        }
+    }
+}
+
+/// 虛擬鍵碼的顯示名稱（依 Shift 狀態區分大小寫與上排符號）
+pub fn key_label(vk: u32, shift: bool) -> String {
+    let shifted = |normal: char, with_shift: char| if shift { with_shift } else { normal }.to_string();
+    match vk {
+        0x41..=0x5A => {
+            let ch = char::from(vk as u8);
+            if shift { ch.to_string() } else { ch.to_ascii_lowercase().to_string() }
+        }
+        0x30..=0x39 if !shift => char::from(vk as u8).to_string(),
+        0x30..=0x39 => {
+            let symbols = [')', '!', '@', '#', '$', '%', '^', '&', '*', '('];
+            symbols[(vk - 0x30) as usize].to_string()
+        }
+        0x60..=0x69 => format!("Num{}", vk - 0x60),
+        0xBA => shifted(';', ':'),
+        0xBB => shifted('=', '+'),
+        0xBC => shifted(',', '<'),
+        0xBD => shifted('-', '_'),
+        0xBE => shifted('.', '>'),
+        0xBF => shifted('/', '?'),
+        0xC0 => shifted('`', '~'),
+        0xDB => shifted('[', '{'),
+        0xDC => shifted('\\', '|'),
+        0xDD => shifted(']', '}'),
+        0xDE => shifted('\'', '"'),
+        0x08 => "Backspace".to_string(),
+        0x09 => "Tab".to_string(),
+        0x0D => "Enter".to_string(),
+        0x10 | 0xA0 | 0xA1 => "Shift".to_string(),
+        0x11 | 0xA2 | 0xA3 => "Ctrl".to_string(),
+        0x12 | 0xA4 | 0xA5 => "Alt".to_string(),
+        0x14 => "CapsLock".to_string(),
+        0x1B => "Esc".to_string(),
+        0x20 => "Space".to_string(),
+        0x21 => "PageUp".to_string(),
+        0x22 => "PageDown".to_string(),
+        0x25 => "←".to_string(),
+        0x26 => "↑".to_string(),
+        0x27 => "→".to_string(),
+        0x28 => "↓".to_string(),
+        0x70..=0x87 => format!("F{}", vk - 0x6F),
+        _ => format!("vk 0x{:02X}", vk),
+    }
+}
+
+/// 最近按鍵的紀錄（鍵盤鉤子寫入，GUI 讀取）
+pub struct KeyTrace {
+    capacity: AtomicUsize,
+    entries: Mutex<VecDeque<KeyTraceEntry>>,
+    /// 目前這個按鍵處理期間送出的文字
+    committed: Mutex<Option<String>>,
+    /// 每記錄一筆加一，視窗據此判斷是否需要重新整理
+    version: AtomicU64,
+}
+
+impl KeyTrace {
+    /// 保留最近 capacity 筆，0 表示不記錄
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: AtomicUsize::new(capacity),
+            entries: Mutex::new(VecDeque::new()),
+            committed: Mutex::new(None),
+            version: AtomicU64::new(0),
+        }
+    }
+
+    /// 更改保留的筆數（設定 key_trace_len），超過的舊紀錄丟掉
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut entries = self.entries.lock().unwrap();
+        let excess = entries.len().saturating_sub(capacity);
+        entries.drain(..excess);
+        self.version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity.load(Ordering::Relaxed) > 0
+    }
+
+    /// 開始處理一個按鍵（清掉按鍵之間由其他路徑送出的文字，例如遊戲模式窗口）
+    pub fn begin_key(&self) {
+        if self.is_enabled() {
+            *self.committed.lock().unwrap() = None;
+        }
+    }
+
+    /// 按鍵處理完畢，記錄一筆
+    pub fn finish_key(&self, key: String, blocked: bool, code: String) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return;
+        }
+        let committed = self.committed.lock().unwrap().take();
+        let outcome = match (blocked, committed) {
+            (false, _) => KeyOutcome::Passed,
+            (true, Some(text)) => KeyOutcome::Converted(text),
+            (true, None) => KeyOutcome::Blocked,
+        };
+        let entry = KeyTraceEntry { key, outcome, code };
+        trace!("按鍵回放: {}", entry.line());
+        {
+            let mut entries = self.entries.lock().unwrap();
+            while entries.len() >= capacity {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+        self.version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 目前的紀錄（由舊到新）
+    pub fn snapshot(&self) -> Vec<KeyTraceEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// 紀錄的版本，有新紀錄時增加
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Relaxed)
+    }
+}
+
+impl InputObserver for KeyTrace {
+    fn on_input_event(&self, event: &InputEvent) {
+        if let InputEvent::Committed(text) = event {
+            if self.is_enabled() {
+                *self.committed.lock().unwrap() = Some(text.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_trace_records_outcomes() {
+        assert_eq!(key_label(0x41, false), "a");
+        assert_eq!(key_label(0x31, true), "!");
+        assert_eq!(key_label(0xBC, false), ",");
+        assert_eq!(key_label(0x20, false), "Space");
+
+        let trace = KeyTrace::new(2);
+        trace.begin_key();
+        trace.finish_key("a".to_string(), true, "a".to_string());
+        trace.begin_key();
+        trace.on_input_event(&InputEvent::Committed("一".to_string()));
+        trace.finish_key("Space".to_string(), true, String::new());
+        // 按鍵之間送出的文字不算在下一個按鍵上
+        trace.on_input_event(&InputEvent::Committed("乙".to_string()));
+        trace.begin_key();
+        trace.finish_key("Ctrl".to_string(), false, String::new());
+
+        let entries = trace.snapshot();
+        assert_eq!(trace.version(), 3);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].outcome, KeyOutcome::Converted("一".to_string()));
+        assert_eq!(entries[0].line(), "Space → 轉換為「一」");
+        assert_eq!(entries[1].line(), "Ctrl → 放行");
+        assert_eq!(
+            KeyTraceEntry { key: "a".into(), outcome: KeyOutcome::Blocked, code: "a".into() }.line(),
+            "a → 攔截（字根 a）"
+        );
+
+        trace.set_capacity(1);
+        assert_eq!(trace.snapshot()[0].line(), "Ctrl → 放行");
+        // 0 表示不記錄
+        trace.set_capacity(0);
+        trace.finish_key("a".to_string(), true, String::new());
+        assert!(trace.snapshot().is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+//! 按鍵回放視窗
+//!
+//! 列出最近的按鍵與處理方式（見 key_trace 模組），最新的在最下面。
+//! 視窗開著時由 GUI 事件循環定期檢查，有新紀錄才重新填入（見 refresh）。
+
+use crate::key_trace::KeyTrace;
+use fltk::{browser::Browser, button::Button, enums::Align, frame::Frame, prelude::*, window::Window};
+use std::sync::Arc;
+
+/// 按鍵回放視窗
+pub struct KeyTraceWindow {
+    window: Window,
+    entries: Browser,
+    trace: Arc<KeyTrace>,
+    /// 上次填入時的紀錄版本
+    shown_version: Option<u64>,
+}
+
+impl KeyTraceWindow {
+    pub fn new(trace: Arc<KeyTrace>) -> Self {
+        let window = Window::default().with_size(420, 400).with_label("按鍵回放").center_screen();
+
+        let mut hint = Frame::new(10, 5, 400, 25, "最近的按鍵與處理方式（最新在最下面）");
+        hint.set_align(Align::Left | Align::Inside);
+
+        let mut entries = Browser::new(10, 35, 400, 320, "");
+        entries.set_text_size(15);
+        // 按鍵名稱可能是 @ 這類列表格式碼，不使用格式碼（見 LookupWindow）
+        entries.set_format_char('\u{1}');
+
+        let mut close_button = Button::new(310, 362, 100, 30, "關閉");
+        window.end();
+
+        {
+            let mut window = window.clone();
+            close_button.set_callback(move |_| window.hide());
+        }
+
+        Self { window, entries, trace, shown_version: None }
+    }
+
+    pub fn show(&mut self) {
+        self.shown_version = None;
+        self.refresh();
+        self.window.show();
+    }
+
+    /// 視窗開著且有新紀錄時重新填入列表
+    pub fn refresh(&mut self) {
+        if !self.window.shown() {
+            return;
+        }
+        let version = self.trace.version();
+        if self.shown_version == Some(version) {
+            return;
+        }
+        self.shown_version = Some(version);
+        self.entries.clear();
+        let entries = self.trace.snapshot();
+        if !self.trace.is_enabled() {
+            self.entries.add("按鍵回放已關閉（設定 key_trace_len 為 0）");
+        } else if entries.is_empty() {
+            self.entries.add("還沒有按鍵紀錄");
+        }
+        for entry in &entries {
+            self.entries.add(&entry.line());
+        }
+        // 捲到最新的一筆
+        let last = self.entries.size();
+        if last > 0 {
+            self.entries.set_bottomline(last);
+        }
+    }
+}
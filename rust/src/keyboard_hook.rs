@@ -1,10 +1,25 @@
 //! Windows 全域鍵盤鉤子模組
 
 use crate::AppState;
+use crate::gui_backend::GuiCommand;
+use crate::input_simulator::{SendOutcome, UNICODE_INPUT_MARK};
+#[cfg(test)]
+use crate::key_source::{KeyboardSource, ScriptRun};
+use crate::key_trace;
+use crate::hook_timing::{TimingEvent, CALLBACK_BUDGET};
+use crate::input_method::ShiftLetter;
+use crate::logging;
+use crate::macros::{self, MacroAction};
+use crate::mode::InputMode;
+use crate::presentation::PresentationAction;
+use crate::rules::LocalTime;
+use crate::schedule::ScheduleTimer;
+use crate::tray::TrayIcon;
 use anyhow::Result;
-use log::{debug, info, warn, error};
+use tracing::{debug, enabled, error, info, warn, Level};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use windows::{
     Win32::Foundation::*,
     Win32::UI::WindowsAndMessaging::*,
@@ -19,8 +34,70 @@ thread_local! {
     static CTRL_PRESSED: std::cell::RefCell<bool> = std::cell::RefCell::new(false);
     static ALT_PRESSED: std::cell::RefCell<bool> = std::cell::RefCell::new(false);
     static SHIFT_PRESSED: std::cell::RefCell<bool> = std::cell::RefCell::new(false);
-    static SHIFT_TOGGLE: std::cell::RefCell<bool> = std::cell::RefCell::new(false); // Shift 切換狀態：false=攔截，true=不攔截
     static SHIFT_USED_WITH_OTHER_KEY: std::cell::RefCell<bool> = std::cell::RefCell::new(false); // Shift 是否與其他鍵組合過
+    static ESCAPE_DETECTOR: std::cell::RefCell<EscapeDetector> = std::cell::RefCell::new(EscapeDetector::default());
+    static PACKET_DECODER: std::cell::RefCell<PacketDecoder> = std::cell::RefCell::new(PacketDecoder::default());
+}
+
+/// 觸發緊急放行需要連按 Esc 的次數
+const EMERGENCY_ESCAPE_COUNT: u32 = 3;
+/// 連按 Esc 時相鄰兩次按下的最大間隔
+const EMERGENCY_ESCAPE_INTERVAL: Duration = Duration::from_millis(500);
+/// 字頻統計與停用字清單定期存檔的間隔
+const PROFILE_DATA_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+/// 檢查排程時段的間隔
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 偵測「連按三次 Esc」的緊急放行熱鍵
+/// 只使用鉤子執行緒的 thread_local 狀態，不需要任何鎖
+#[derive(Default)]
+struct EscapeDetector {
+    count: u32,
+    last_press: Option<Instant>,
+}
+
+impl EscapeDetector {
+    /// 記錄一次按鍵，回傳是否達成連按 Esc 的條件
+    fn press(&mut self, is_escape: bool, now: Instant) -> bool {
+        if !is_escape {
+            self.count = 0;
+            self.last_press = None;
+            return false;
+        }
+
+        let continued = self
+            .last_press
+            .is_some_and(|last| now.duration_since(last) <= EMERGENCY_ESCAPE_INTERVAL);
+        self.count = if continued { self.count + 1 } else { 1 };
+        self.last_press = Some(now);
+
+        if self.count >= EMERGENCY_ESCAPE_COUNT {
+            self.count = 0;
+            self.last_press = None;
+            return true;
+        }
+        false
+    }
+}
+
+/// 把 VK_PACKET 帶的 UTF-16 單元組回字元（擴充平面字元分成兩次 surrogate 送來）
+#[derive(Default)]
+struct PacketDecoder {
+    high_surrogate: Option<u16>,
+}
+
+impl PacketDecoder {
+    /// 送進一個 UTF-16 單元，湊成完整字元時回傳；落單的 surrogate 丟棄
+    fn push(&mut self, unit: u16) -> Option<char> {
+        if (0xD800..0xDC00).contains(&unit) {
+            self.high_surrogate = Some(unit);
+            return None;
+        }
+        match self.high_surrogate.take() {
+            Some(high) => char::decode_utf16([high, unit]).next()?.ok(),
+            None => char::decode_utf16([unit]).next()?.ok(),
+        }
+    }
 }
 
 /// 鍵盤鉤子管理器
@@ -62,10 +139,17 @@ impl KeyboardHook {
         }
     }
     
-    /// 運行訊息循環（整合 fltk 事件處理）
-    pub fn run_with_fltk(&self, _app: &fltk::app::App, state: Arc<AppState>) -> Result<()> {
+    /// 運行鉤子執行緒的訊息循環
+    /// 低階鍵盤鉤子的回呼在安裝鉤子的執行緒上執行，這條執行緒只做鍵盤相關工作，
+    /// GUI 由主執行緒的 fltk 事件循環負責，兩者以 channel / 原子旗標溝通，GUI 卡頓不會拖慢鍵盤處理
+    pub fn run_message_loop(&self, state: Arc<AppState>, tray: &TrayIcon) -> Result<()> {
         unsafe {
             let mut msg = MSG::default();
+            let mut degraded_reported = false;
+            let mut last_profile_data_save = Instant::now();
+            let mut last_backup: Option<Instant> = None;
+            let mut schedule = ScheduleTimer::new(state.schedule.clone());
+            let mut last_schedule_check: Option<Instant> = None;
             
             loop {
                 // 檢查是否應該退出
@@ -75,37 +159,94 @@ impl KeyboardHook {
                     break;
                 }
                 
-                // 處理 fltk 事件（非阻塞）
-                // 使用 app::check() 非阻塞地處理 fltk 事件
-                // 需要定期調用以處理窗口顯示和重繪
-                if fltk::app::check() {
-                    // 如果有 fltk 事件，處理並刷新
-                    fltk::app::flush();
+                // 處理托盤選單（切換使用者等），並讓托盤徽章跟上目前的英/肥模式
+                tray.handle_menu_events();
+                // 模式浮標被點擊（GUI 執行緒只設旗標，模式統一在這裡切換）
+                if state.mode_toggle_request.swap(false, Ordering::Relaxed) {
+                    state.toggle_mode();
                 }
-
-                // 只在有輸入變化時才更新 GUI 主窗口顯示
-                // 注意：這裡不在鍵盤鉤子回呼裡，而是在主迴圈中，避免阻塞鍵盤事件處理
-                if state.gui_needs_update.load(Ordering::Relaxed) {
-                    if let Ok(mut gui_manager) = state.gui_window_manager.lock() {
-                        gui_manager.update_display();
+                tray.set_mode(state.get_mode());
+                
+                // 回呼超時的警告在這裡輸出，不在回呼裡做 I/O
+                Self::report_hook_timing(&state, &mut degraded_reported);
+                
+                // 定期把字頻統計與停用字清單存檔，程式意外結束時不會遺失太多紀錄
+                if last_profile_data_save.elapsed() >= PROFILE_DATA_SAVE_INTERVAL {
+                    last_profile_data_save = Instant::now();
+                    state.save_profile_data();
+                }
+                
+                // 定期備份使用者資料（啟動後第一輪就備份一次，內容沒變時不另建）
+                if let Some(policy) = state.backup {
+                    if last_backup.is_none_or(|backed_up| backed_up.elapsed() >= policy.interval) {
+                        last_backup = Some(Instant::now());
+                        state.backup_profile_data();
                     }
-                    // 清除更新標誌
-                    state.gui_needs_update.store(false, Ordering::Relaxed);
                 }
-
-                // 如果有待貼上的文字，這裡統一送出（避免在鍵盤鉤子回呼裡做耗時的剪貼簿操作）
+                
+                // 進入排程的新時段時切換模式與使用者（啟動後第一輪就檢查一次）
+                if !state.schedule.is_empty()
+                    && last_schedule_check.is_none_or(|checked| checked.elapsed() >= SCHEDULE_CHECK_INTERVAL)
+                {
+                    last_schedule_check = Some(Instant::now());
+                    let now = LocalTime::now();
+                    if let Some(slot) = schedule.poll(now.hour * 60 + now.minute) {
+                        tray.apply_schedule(slot);
+                    }
+                }
+                
+                // 選字確認模式的高亮逾時就取消（鉤子回呼正在用處理器時下一輪再檢查）
+                if let Ok(mut processor) = state.input_processor.try_lock() {
+                    if processor.expire_pending_selection() {
+                        state.gui_needs_update.store(true, Ordering::Relaxed);
+                    }
+                }
+                
+                // 完成緊急放行時沒能立即清空的輸入狀態
+                if state.emergency_reset.load(Ordering::Relaxed) {
+                    Self::try_finish_emergency_reset(&state);
+                }
+                
+                // 撤銷送出的退格（在待送出的文字之前送出）
+                let backspaces = state.pending_backspaces.swap(0, Ordering::Relaxed);
+                if backspaces > 0 {
+                    if let Ok(mut simulator) = state.input_simulator.lock() {
+                        match simulator.send_backspaces(backspaces) {
+                            Ok(()) => info!("撤銷送出: 已送出 {} 次退格", backspaces),
+                            Err(e) => warn!("撤銷送出失敗: {:#}", e),
+                        }
+                    }
+                }
+                
+                // 如果有待送出的文字，這裡統一送出（避免在鍵盤鉤子回呼裡做耗時的剪貼簿/SendInput 操作）
                 if let Ok(mut pending) = state.pending_paste_text.lock() {
                     if let Some(text) = pending.take() {
+                        state.macros.lock().unwrap().record(MacroAction::SendText(text.clone()));
                         if let Ok(mut simulator) = state.input_simulator.lock() {
-                            if let Err(e) = simulator.send_text_paste(&text) {
-                                warn!("發送貼上文字失敗: {}", e);
-                            } else {
-                                info!("已送出候選字（貼上模式）: {}", text);
-                            }
+                            let result = simulator.send_to_foreground(&text);
+                            drop(simulator);
+                            Self::report_send(&state, &text, result);
                         }
                     }
                 }
                 
+                // 因送字限制延後的文字，時間到了再分段送出
+                if let Ok(mut simulator) = state.input_simulator.try_lock() {
+                    if simulator.has_backlog() {
+                        let result = simulator.flush_backlog();
+                        drop(simulator);
+                        Self::report_send(&state, "（延後送出的剩餘文字）", result);
+                    }
+                }
+                
+                // 長文分段送出（Ctrl+Alt+V），時間到了送出下一段
+                if let Ok(mut simulator) = state.input_simulator.try_lock() {
+                    if let Some((chunk, result)) = simulator.flush_long_paste() {
+                        drop(simulator);
+                        Self::report_send(&state, &chunk, result);
+                    }
+                }
+                
                 // 使用 PeekMessageW 非阻塞地檢查 Windows 消息
                 let has_msg = PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool();
                 
@@ -115,20 +256,6 @@ impl KeyboardHook {
                         break;
                     }
                     
-                    // 處理系統托盤菜單項點擊
-                    if msg.message == WM_COMMAND {
-                        let menu_id = msg.wParam.0 as u16;
-                        let notification_code = (msg.wParam.0 >> 16) as u16;
-                        debug!("收到 WM_COMMAND 消息，menu_id: {}, notification_code: {}", menu_id, notification_code);
-                        
-                        if notification_code == 0 && menu_id == 1001 {
-                            info!("✅ 系統托盤退出選項被點擊，準備退出...");
-                            self.should_quit.store(true, Ordering::Relaxed);
-                            PostQuitMessage(0);
-                            break;
-                        }
-                    }
-                    
                     TranslateMessage(&msg);
                     DispatchMessageW(&msg);
                 } else {
@@ -157,15 +284,7 @@ impl KeyboardHook {
             
             APP_STATE.with(|state_opt| {
                 if let Some(state) = state_opt.borrow().as_ref() {
-                    // 解析鍵盤事件
-                    match Self::process_keyboard_event(state, w_param, l_param) {
-                        Ok(handled) => {
-                            should_block = handled;
-                        }
-                        Err(e) => {
-                            debug!("處理鍵盤事件錯誤: {}", e);
-                        }
-                    }
+                    should_block = Self::dispatch(state, w_param, l_param);
                 }
             });
             
@@ -179,6 +298,416 @@ impl KeyboardHook {
         }
     }
     
+    /// 處理一個按鍵事件（鉤子回呼與測試的 drive 共用），回傳是否攔截
+    fn dispatch(state: &AppState, w_param: WPARAM, l_param: LPARAM) -> bool {
+        let started = Instant::now();
+        state.key_trace.begin_key();
+        
+        // 解析鍵盤事件
+        let should_block = match Self::process_keyboard_event(state, w_param, l_param) {
+            Ok(handled) => handled,
+            Err(e) => {
+                debug!("處理鍵盤事件錯誤: {}", e);
+                false
+            }
+        };
+        Self::trace_key(state, w_param, l_param, should_block);
+        if !should_block {
+            Self::forget_undo_on_key(state, w_param, l_param);
+        }
+        
+        // 連續超過耗時預算時降級：鉤子執行緒只輸出 warn 以上的日誌，恢復正常後解除
+        match state.hook_timing.record(started.elapsed()) {
+            TimingEvent::Degraded => logging::set_thread_quiet(true),
+            TimingEvent::Recovered => logging::set_thread_quiet(false),
+            TimingEvent::Normal | TimingEvent::OverBudget => {}
+        }
+        should_block
+    }
+    
+    /// 放行了修飾鍵以外的按鍵：游標可能已經移動，上一次送出不能再撤銷
+    /// 只增加放行計數、不取處理器的鎖，緊急放行後處理器卡住時也不會阻塞鉤子
+    fn forget_undo_on_key(state: &AppState, w_param: WPARAM, l_param: LPARAM) {
+        const WM_KEYDOWN_VALUE: usize = 256;
+        const WM_SYSKEYDOWN_VALUE: usize = 260;
+        if w_param.0 != WM_KEYDOWN_VALUE && w_param.0 != WM_SYSKEYDOWN_VALUE {
+            return;
+        }
+        let kbd_struct = unsafe { *(l_param.0 as *const KBDLLHOOKSTRUCT) };
+        let is_modifier = matches!(kbd_struct.vkCode, 0x10..=0x12 | 0xA0..=0xA5 | 0x5B | 0x5C);
+        if !is_modifier && kbd_struct.flags.0 & LLKHF_INJECTED.0 == 0 {
+            state.passed_keys.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    /// 記錄使用者按下的按鍵與處理結果（按鍵回放，見 key_trace 模組）
+    /// 注入的按鍵（包括本程式送出的文字）不記錄
+    fn trace_key(state: &AppState, w_param: WPARAM, l_param: LPARAM, blocked: bool) {
+        const WM_KEYDOWN_VALUE: usize = 256;
+        const WM_SYSKEYDOWN_VALUE: usize = 260;
+        if !state.key_trace.is_enabled() || (w_param.0 != WM_KEYDOWN_VALUE && w_param.0 != WM_SYSKEYDOWN_VALUE) {
+            return;
+        }
+        let kbd_struct = unsafe { *(l_param.0 as *const KBDLLHOOKSTRUCT) };
+        if kbd_struct.flags.0 & LLKHF_INJECTED.0 != 0 {
+            return;
+        }
+        let shift = SHIFT_PRESSED.with(|p| *p.borrow());
+        // 緊急放行之後也會走到這裡，不能等處理器的鎖；拿不到時記成沒有字根
+        let code = match state.input_processor.try_lock() {
+            Ok(processor) => processor.get_state().current_code.clone(),
+            Err(_) => String::new(),
+        };
+        state.key_trace.finish_key(key_trace::key_label(kbd_struct.vkCode, shift), blocked, code);
+    }
+    
+    /// 輸出回呼超時與降級模式變化的警告（由訊息循環呼叫）
+    fn report_hook_timing(state: &AppState, degraded_reported: &mut bool) {
+        let timing = &state.hook_timing;
+        if let Some((count, last)) = timing.take_new_over_budget() {
+            warn!(
+                "⚠️ 鍵盤鉤子回呼超過 {} ms 預算 {} 次（最近一次 {:.2} ms）",
+                CALLBACK_BUDGET.as_millis(),
+                count,
+                last.as_secs_f64() * 1000.0
+            );
+        }
+        
+        let degraded = timing.is_degraded();
+        if degraded != *degraded_reported {
+            *degraded_reported = degraded;
+            if degraded {
+                warn!("⚠️ 鍵盤鉤子回呼持續過慢，進入降級模式：暫停鉤子執行緒的 debug/info 日誌");
+            } else {
+                warn!("鍵盤鉤子回呼恢復正常，解除降級模式");
+            }
+        }
+    }
+    
+    /// 記錄送出結果；終端機的多行文字交給 GUI 執行緒詢問，不在這裡阻塞鉤子執行緒
+    fn report_send(state: &AppState, text: &str, result: Result<SendOutcome>) {
+        match result {
+            Ok(SendOutcome::Sent) => debug!("已送出候選字: {}", text),
+            Ok(SendOutcome::Deferred) => debug!("超過送字限制或等待按鍵放開，部分文字延後送出: {}", text),
+            Ok(SendOutcome::NeedsConfirm(text)) => {
+                if state.gui_commands.send(GuiCommand::ConfirmTerminalSend(text)).is_err() {
+                    warn!("GUI 執行緒已結束，無法詢問是否送出多行文字");
+                }
+            }
+            Err(e) => warn!("發送文字失敗: {}", e),
+        }
+    }
+    
+    /// 按下選字鍵（數字或方案的其他選字鍵）：選字並排隊送出，一律攔截
+    fn handle_selection(state: &AppState, key: char) -> bool {
+        let mut processor = state.input_processor.lock().unwrap();
+        let candidate_count = processor.get_state().get_current_page_candidates().len();
+        debug!("處理選字鍵 {}: 當前候選字數量={}, 字根='{}'", key, candidate_count, processor.get_state().current_code);
+        
+        // 補碼選字後按選字鍵只是改選，等 Space 再送出
+        if processor.handle_complement_reselect(key) {
+            state.gui_needs_update.store(true, Ordering::Relaxed);
+            return true;
+        }
+        
+        // 選字鍵由輸入法方案決定（嘸蝦米為 1-9、0）
+        if let Some(text) = processor.handle_selection_key(key) {
+            drop(processor);
+            debug!("✅ 選擇候選字 {}: {}（排隊送出）", key, text);
+            Self::queue_text(state, text);
+        } else {
+            // 沒有對應的候選字，攔截並忽略該按鍵
+            debug!("選字鍵 {} 沒有對應的候選字（候選字數量={}），攔截並忽略", key, candidate_count);
+            drop(processor);
+            Self::invalid_key(state, "選字鍵沒有對應的候選");
+        }
+        true
+    }
+    
+    /// 按了無效的鍵（打錯字根、沒有候選可送出或選）：依設定發出提示音或閃一下狀態窗
+    fn invalid_key(state: &AppState, reason: &str) {
+        let feedback = state.invalid_key_feedback;
+        if !feedback.is_enabled() {
+            return;
+        }
+        debug!("無效按鍵提示: {}", reason);
+        if feedback.beep {
+            state.announcer.invalid_key();
+        }
+        if feedback.flash && state.gui_commands.send(GuiCommand::FlashStatus).is_err() {
+            warn!("GUI 執行緒已結束，無法閃爍狀態窗");
+        }
+    }
+    
+    /// 本地查不到的字根交給線上備援查詢（背景執行緒），查到的候選寫入使用者字典
+    fn cloud_lookup(state: &AppState, code: &str) {
+        let Some(lookup) = &state.cloud_lookup else {
+            return;
+        };
+        let Some(app) = APP_STATE.with(|s| s.borrow().clone()) else {
+            return;
+        };
+        lookup.request(code, move |code, texts| app.apply_cloud_candidates(&code, &texts));
+    }
+    
+    /// 緊急放行：切回英模式（不攔截）、重置修飾鍵狀態，並清空輸入
+    /// 只有清空輸入需要鎖，這裡用 try_lock，拿不到鎖就交給訊息循環稍後重試
+    fn emergency_release(state: &AppState) {
+        warn!("⚠️ 連按 {} 次 Esc，緊急放行：清空輸入並切換到英模式", EMERGENCY_ESCAPE_COUNT);
+        
+        // 不經過 AppState::set_mode，那裡會等輸入處理器的鎖
+        if state.mode.set(InputMode::English) != InputMode::English {
+            state.mode_indicator.show(InputMode::English);
+        }
+        CTRL_PRESSED.with(|p| *p.borrow_mut() = false);
+        ALT_PRESSED.with(|p| *p.borrow_mut() = false);
+        SHIFT_PRESSED.with(|p| *p.borrow_mut() = false);
+        SHIFT_USED_WITH_OTHER_KEY.with(|f| *f.borrow_mut() = false);
+        
+        state.emergency_reset.store(true, Ordering::Relaxed);
+        Self::try_finish_emergency_reset(state);
+    }
+    
+    /// 排隊等主迴圈送出文字；整句組字模式（compose_sentence）下改為累積在輸入處理器，按 Enter 才一次送出
+    fn queue_text(state: &AppState, text: String) {
+        if state.input_processor.lock().unwrap().push_composition(&text) {
+            debug!("整句組字: 累積 {}", text);
+            state.gui_needs_update.store(true, Ordering::Relaxed);
+            return;
+        }
+        *state.pending_paste_text.lock().unwrap() = Some(text);
+    }
+    
+    /// 放行清單以外的 Ctrl 組合鍵，回傳是否攔截
+    /// 這些組合留給輸入法自己的 Ctrl 熱鍵；沒有對應的熱鍵時，組字中攔截（不讓前景程式在字根打到一半時收到組合鍵），
+    /// 沒有組字時放行
+    fn handle_ctrl_combo(state: &AppState, vk_value: u32) -> bool {
+        let processor = state.input_processor.lock().unwrap();
+        let composing = !processor.get_state().current_code.is_empty() || processor.is_temp_english();
+        if composing {
+            debug!("組字中按了放行清單以外的 Ctrl 組合鍵，攔截 (vk={})", vk_value);
+        } else {
+            debug!("Ctrl 組合鍵不在放行清單中，沒有組字，讓事件通過 (vk={})", vk_value);
+        }
+        composing
+    }
+    
+    /// 撤銷上一次送出：還原字根，退格交給訊息循環送出；沒有可撤銷的送出時回傳 false（讓按鍵通過）
+    fn undo_commit(state: &AppState) -> bool {
+        if state.gui_has_focus.load(Ordering::Relaxed) {
+            return false;
+        }
+        let (count, buffered) = {
+            let mut processor = state.input_processor.lock().unwrap();
+            let Some(count) = processor.undo_last_commit() else {
+                debug!("沒有可撤銷的送出");
+                return false;
+            };
+            // 整句組字累積的文字還沒送出，從句子拿掉即可
+            (count, processor.pop_composition())
+        };
+        // 還沒送出的文字直接丟掉，不必再退格
+        if !buffered && state.pending_paste_text.lock().unwrap().take().is_none() {
+            state.pending_backspaces.fetch_add(count, Ordering::Relaxed);
+        }
+        state.gui_needs_update.store(true, Ordering::Relaxed);
+        true
+    }
+    
+    /// 開始或停止錄製巨集；停止時把錄到的動作交給 GUI 執行緒詢問名稱與熱鍵
+    fn toggle_macro_recording(state: &AppState) {
+        let mut book = state.macros.lock().unwrap();
+        if !book.is_recording() {
+            book.start_recording();
+            info!("開始錄製巨集（再按一次 Ctrl+Alt+R 停止）");
+            return;
+        }
+        let actions = book.stop_recording().unwrap_or_default();
+        drop(book);
+        if actions.is_empty() {
+            info!("停止錄製巨集：沒有錄到任何動作");
+            return;
+        }
+        info!("停止錄製巨集：錄到 {} 個動作", actions.len());
+        if state.gui_commands.send(GuiCommand::NameMacro(actions)).is_err() {
+            warn!("GUI 執行緒已結束，無法儲存巨集");
+        }
+    }
+    
+    /// 重播綁定在 Ctrl+Alt+slot 的巨集，沒有綁定時回傳 false
+    fn play_macro(state: &AppState, slot: u8) -> bool {
+        let Some(found) = state.macros.lock().unwrap().for_slot(slot).cloned() else {
+            return false;
+        };
+        let simulator = state.input_simulator.clone();
+        let gui_commands = state.gui_commands.clone();
+        macros::play(&found.name, found.actions, move |action| match action {
+            MacroAction::ToggleWindow => {
+                if gui_commands.send(GuiCommand::Toggle).is_err() {
+                    warn!("GUI 執行緒已結束，無法切換遊戲模式窗口");
+                }
+            }
+            MacroAction::SendText(text) => match simulator.lock().unwrap().send_to_foreground(text) {
+                Ok(SendOutcome::Sent | SendOutcome::Deferred) => {}
+                Ok(SendOutcome::NeedsConfirm(text)) => {
+                    if gui_commands.send(GuiCommand::ConfirmTerminalSend(text)).is_err() {
+                        warn!("GUI 執行緒已結束，無法詢問是否送出多行文字");
+                    }
+                }
+                Err(e) => warn!("巨集送出文字失敗: {}", e),
+            },
+        });
+        true
+    }
+    
+    /// 嘗試清空輸入狀態與待貼上文字，成功後清除 emergency_reset 旗標
+    fn try_finish_emergency_reset(state: &AppState) {
+        let Ok(mut processor) = state.input_processor.try_lock() else {
+            debug!("輸入處理器忙碌中，稍後再清空輸入狀態");
+            return;
+        };
+        processor.clear();
+        drop(processor);
+        
+        if let Ok(mut pending) = state.pending_paste_text.try_lock() {
+            pending.take();
+        }
+        if let Ok(mut simulator) = state.input_simulator.try_lock() {
+            let dropped = simulator.clear_backlog() + simulator.cancel_long_paste();
+            if dropped > 0 {
+                info!("緊急放行：丟棄 {} 個延後送出的字", dropped);
+            }
+        }
+        
+        state.emergency_reset.store(false, Ordering::Relaxed);
+        state.gui_needs_update.store(true, Ordering::Relaxed);
+        info!("緊急放行完成，輸入狀態已清空");
+    }
+    
+    /// 其他軟體以 VK_PACKET 送出的字元：設定 accumulate_packet_input 且遊戲模式窗口可見（沒有焦點）時
+    /// 一併加入累積文字；按鍵本身照常交給前景程式
+    fn accumulate_packet(state: &AppState, unit: u16) {
+        if !state.accumulate_packet_input
+            || !state.gui_visible.load(Ordering::Relaxed)
+            || state.gui_has_focus.load(Ordering::Relaxed)
+        {
+            return;
+        }
+        let Some(ch) = PACKET_DECODER.with(|d| d.borrow_mut().push(unit)) else {
+            return;
+        };
+        debug!("VK_PACKET 字元加入累積文字: {}", ch);
+        if state.gui_commands.send(GuiCommand::AppendAccumulated(ch.to_string())).is_err() {
+            error!("GUI 執行緒已結束，無法累積 VK_PACKET 字元");
+        }
+    }
+    
+    /// Alt + 選字鍵：把對應的候選複製到剪貼簿，不送出，字根與候選保持原狀
+    /// 只在肥模式、鉤子處理輸入時有效；沒有對應的候選時回傳 false，讓前景程式照常收到 Alt 組合鍵
+    /// 輸入處理器正被其他執行緒使用時不等待，同樣放行
+    fn copy_candidate(state: &AppState, l_param: LPARAM) -> bool {
+        let kbd_struct = unsafe { *(l_param.0 as *const KBDLLHOOKSTRUCT) };
+        let vk_value = kbd_struct.vkCode;
+        if kbd_struct.flags.0 & LLKHF_INJECTED.0 != 0
+            || !(0x30..=0x39).contains(&vk_value)
+            || state.get_mode() != InputMode::Ucl
+            || state.gui_has_focus.load(Ordering::Relaxed)
+        {
+            return false;
+        }
+        let Ok(processor) = state.input_processor.try_lock() else {
+            debug!("輸入處理器忙碌中，Alt+{} 照常放行", char::from(vk_value as u8));
+            return false;
+        };
+        let Some(text) = processor.peek_selection_key(char::from(vk_value as u8)) else {
+            return false;
+        };
+        drop(processor);
+        debug!("✅ Alt+{}：複製候選到剪貼簿: {}", char::from(vk_value as u8), text);
+        // 剪貼簿交給 GUI 執行緒處理，不在鉤子回呼裡做
+        if state.gui_commands.send(GuiCommand::CopyToClipboard(text)).is_err() {
+            error!("GUI 執行緒已結束，無法複製候選");
+        }
+        Self::mask_alt_release();
+        true
+    }
+    
+    /// 送出一個沒有作用的按鍵（vk 0xE8），避免放開 Alt 時前景程式以為單獨按了 Alt 而打開選單列
+    fn mask_alt_release() {
+        let key = |flags: KEYBD_EVENT_FLAGS| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 { ki: KEYBDINPUT { wVk: VIRTUAL_KEY(0xE8), wScan: 0, dwFlags: flags, time: 0, dwExtraInfo: 0 } },
+        };
+        let inputs = [key(KEYBD_EVENT_FLAGS(0)), key(KEYEVENTF_KEYUP)];
+        unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+    }
+    
+    /// 按鍵（加上目前的 Shift 狀態）在目前的鍵盤配置下是否打出指定字元
+    fn key_types_char(vk_value: u32, ch: char) -> bool {
+        let mut buf = [0u16; 2];
+        let [unit] = ch.encode_utf16(&mut buf) else {
+            return false;
+        };
+        // 低位元組為虛擬鍵碼，高位元組第 0 位為 Shift；-1 表示目前的配置打不出這個字元
+        let scan = unsafe { VkKeyScanW(*unit) };
+        if scan == -1 {
+            return false;
+        }
+        let shift = (scan >> 8) & 1 != 0;
+        (scan & 0xff) as u32 == vk_value && shift == SHIFT_PRESSED.with(|p| *p.borrow())
+    }
+    
+    /// 按鍵（加上目前的 Shift 與 CapsLock 狀態）在目前的鍵盤配置下打出的可見 ASCII 字元
+    fn typed_char(vk_value: u32) -> Option<char> {
+        let mut key_state = [0u8; 256];
+        if SHIFT_PRESSED.with(|p| *p.borrow()) {
+            key_state[VK_SHIFT.0 as usize] = 0x80;
+        }
+        // CapsLock 的切換狀態在最低位元
+        key_state[VK_CAPITAL.0 as usize] = (unsafe { GetKeyState(VK_CAPITAL.0 as i32) } & 1) as u8;
+        let mut buf = [0u16; 4];
+        // 旗標 4：不改變鍵盤的死鍵狀態，前景程式之後照常處理
+        let len = unsafe { ToUnicodeEx(vk_value, 0, &key_state, &mut buf, 4, GetKeyboardLayout(0)) };
+        if len != 1 {
+            return None;
+        }
+        char::from_u32(buf[0] as u32).filter(|ch| ('!'..='~').contains(ch))
+    }
+    
+    /// 暫時英文模式（沒有字根時按 ` 進入，見 InputMethodProcessor::start_temp_english）：
+    /// 可見字元原樣收進緩衝區，Space/Enter 送出，Backspace 刪除，Esc 放棄
+    /// 回傳 None 表示不是暫時英文模式要處理的按鍵（照一般流程處理）
+    fn handle_temp_english(state: &AppState, vk_value: u32) -> Option<bool> {
+        let mut processor = state.input_processor.lock().unwrap();
+        if !processor.is_temp_english() {
+            if !Self::key_types_char(vk_value, '`') || !processor.start_temp_english() {
+                return None;
+            }
+            drop(processor);
+            state.gui_needs_update.store(true, Ordering::Relaxed);
+            return Some(true);
+        }
+        let committed = match vk_value {
+            27 => {
+                processor.clear();
+                None
+            }
+            8 => {
+                processor.temp_english_backspace();
+                None
+            }
+            13 | 32 => processor.commit_temp_english(),
+            _ => processor.push_temp_english(Self::typed_char(vk_value)?),
+        };
+        drop(processor);
+        if let Some(text) = committed {
+            debug!("暫時英文: 排隊送出 {}", text);
+            Self::queue_text(state, text);
+        }
+        state.gui_needs_update.store(true, Ordering::Relaxed);
+        Some(true)
+    }
+    
     /// 處理鍵盤事件
     /// 返回 true 表示應該阻止事件，false 表示讓事件通過
     fn process_keyboard_event(
@@ -190,11 +719,28 @@ impl KeyboardHook {
         // WM_KEYDOWN = 256 (0x0100), WM_KEYUP = 257 (0x0101)
         const WM_KEYDOWN_VALUE: usize = 256;
         const WM_KEYUP_VALUE: usize = 257;
+        // 按住 Alt 時其他鍵以 WM_SYSKEYDOWN = 260 (0x0104) 送出
+        const WM_SYSKEYDOWN_VALUE: usize = 260;
+        
+        const WM_SYSKEYUP_VALUE: usize = 261;
         
         let is_key_down = w_param.0 == WM_KEYDOWN_VALUE;
         let is_key_up = w_param.0 == WM_KEYUP_VALUE;
+        let is_sys_key_down = w_param.0 == WM_SYSKEYDOWN_VALUE;
+        
+        // 回報實體按鍵的狀態給抬手才上屏（注入的事件與 VK_PACKET 不算）
+        unsafe {
+            let kbd_struct = *(l_param.0 as *const KBDLLHOOKSTRUCT);
+            if kbd_struct.flags.0 & LLKHF_INJECTED.0 == 0 && kbd_struct.vkCode != VK_PACKET.0 as u32 {
+                match w_param.0 {
+                    WM_KEYDOWN_VALUE | WM_SYSKEYDOWN_VALUE => state.held_keys.set(kbd_struct.vkCode, true),
+                    WM_KEYUP_VALUE | WM_SYSKEYUP_VALUE => state.held_keys.set(kbd_struct.vkCode, false),
+                    _ => {}
+                }
+            }
+        }
         
-        if !is_key_down && !is_key_up {
+        if !is_key_down && !is_key_up && !is_sys_key_down {
             return Ok(false);
         }
         
@@ -203,11 +749,35 @@ impl KeyboardHook {
         unsafe {
             let kbd_struct = *(l_param.0 as *const KBDLLHOOKSTRUCT);
             
+            // VK_PACKET：巨集軟體、螢幕小鍵盤等以 KEYEVENTF_UNICODE 送出的字元，一律放行，不影響組字狀態
+            if kbd_struct.vkCode == VK_PACKET.0 as u32 {
+                if is_key_down && kbd_struct.dwExtraInfo != UNICODE_INPUT_MARK {
+                    Self::accumulate_packet(state, kbd_struct.scanCode as u16);
+                }
+                return Ok(false);
+            }
+            
             // 檢查是否為注入的事件（避免無限循環）
             if kbd_struct.flags.0 & LLKHF_INJECTED.0 != 0 {
                 debug!("忽略注入的事件");
                 return Ok(false);
             }
+            
+            // 緊急放行熱鍵（連按三次 Esc）：在所有其他邏輯之前檢查，且不取任何鎖，
+            // 確保輸入法狀態卡死時仍然有效（按著 Alt 時的按鍵也會打斷連按）
+            if is_key_down || is_sys_key_down {
+                let is_escape = kbd_struct.vkCode == VK_ESCAPE.0 as u32;
+                let triggered = ESCAPE_DETECTOR.with(|d| d.borrow_mut().press(is_escape, Instant::now()));
+                if triggered {
+                    Self::emergency_release(state);
+                    return Ok(false); // 讓 Esc 照常通過
+                }
+            }
+        }
+        
+        // Alt + 選字鍵複製候選，其他 Alt 組合鍵照常放行
+        if is_sys_key_down {
+            return Ok(Self::copy_candidate(state, l_param));
         }
         
         // 檢查 F4 鍵退出（需要在檢查模式之前，因為退出功能應該在所有模式下都可用）
@@ -320,30 +890,80 @@ impl KeyboardHook {
             if is_key_down && vk_value == 32 && ctrl_pressed {
                 debug!("檢測到 Space 鍵按下，Ctrl: {}", ctrl_pressed);
                 info!("✅ 檢測到 Ctrl+Space 熱鍵，切換遊戲模式窗口");
-                APP_STATE.with(|s| {
-                    if let Some(state) = s.borrow().as_ref() {
-                        info!("獲取 gui_window_manager...");
-                        let mut manager = state.gui_window_manager.lock().unwrap();
-                        let is_visible = manager.is_visible();
-                        info!("當前遊戲模式窗口可見狀態: {}", is_visible);
-                        if is_visible {
-                            info!("隱藏遊戲模式窗口");
-                            manager.hide();
-                        } else {
-                            info!("顯示遊戲模式窗口（調用 manager.show()）");
-                            if let Err(e) = manager.show() {
-                                error!("顯示遊戲模式窗口失敗: {}", e);
-                            } else {
-                                info!("遊戲模式窗口顯示完成");
-                            }
-                        }
-                    } else {
-                        error!("無法獲取 AppState！");
-                    }
-                });
+                // 不在鉤子回呼裡操作 GUI，交給 GUI 執行緒處理
+                if state.gui_commands.send(GuiCommand::Toggle).is_err() {
+                    error!("GUI 執行緒已結束，無法切換遊戲模式窗口");
+                }
+                state.macros.lock().unwrap().record(MacroAction::ToggleWindow);
                 return Ok(true); // 攔截熱鍵，不讓遊戲收到
             }
             
+            // Ctrl + Alt + V/+/-/H/0：展示用版面調整（直橫切換、放大縮小、高對比、還原）
+            // 先按 Alt 時 Ctrl/Alt 會以 WM_SYSKEYDOWN 送出、不會更新上面的旗標，改查實際按鍵狀態
+            let ctrl_down = ctrl_pressed || GetAsyncKeyState(VK_CONTROL.0 as i32) < 0;
+            let alt_down = ALT_PRESSED.with(|p| *p.borrow()) || GetAsyncKeyState(VK_MENU.0 as i32) < 0;
+            if is_key_down && ctrl_down && alt_down {
+                if let Some(action) = PresentationAction::from_vk(vk_value) {
+                    info!("✅ 檢測到展示熱鍵: {:?}", action);
+                    if state.gui_commands.send(GuiCommand::Presentation(action)).is_err() {
+                        error!("GUI 執行緒已結束，無法調整版面");
+                    }
+                    return Ok(true);
+                }
+                
+                // Ctrl + Alt + Z：撤銷上一次送出（刪掉送出的字並還原字根，選錯候選時改選）
+                if vk_value == 0x5A && Self::undo_commit(state) {
+                    return Ok(true);
+                }
+                
+                // Ctrl + Alt + L：查碼（反查剪貼簿中的字，先複製要查的字再按）
+                if vk_value == 0x4C {
+                    if state.gui_commands.send(GuiCommand::LookupClipboard).is_err() {
+                        error!("GUI 執行緒已結束，無法查碼");
+                    }
+                    return Ok(true);
+                }
+                
+                // Ctrl + Alt + K：選取反查（不必先複製，直接反查前景程式選取的字）
+                if vk_value == 0x4B {
+                    if state.gui_commands.send(GuiCommand::LookupSelection).is_err() {
+                        error!("GUI 執行緒已結束，無法選取反查");
+                    }
+                    return Ok(true);
+                }
+                
+                // Ctrl + Alt + V：長文分段送出（剪貼簿的文字一段一段送，避免一次貼上失敗）
+                if vk_value == 0x56 {
+                    if state.gui_commands.send(GuiCommand::LongPaste).is_err() {
+                        error!("GUI 執行緒已結束，無法分段送出");
+                    }
+                    return Ok(true);
+                }
+                
+                // Ctrl + Alt + R：開始/停止錄製巨集
+                if vk_value == 0x52 {
+                    Self::toggle_macro_recording(state);
+                    return Ok(true);
+                }
+                
+                // Ctrl + Alt + 1~9：重播綁定的巨集（沒有綁定時讓按鍵通過）
+                if (0x31..=0x39).contains(&vk_value) && Self::play_macro(state, (vk_value - 0x30) as u8) {
+                    return Ok(true);
+                }
+            }
+            
+            // Ctrl + V：遊戲模式窗口可見但沒有焦點，代表使用者回到遊戲貼上了累積文字
+            // 通知 GUI 清除累積文字（見 clear_after_paste），按鍵照常通過讓遊戲讀取剪貼簿
+            if is_key_down
+                && ctrl_down
+                && !alt_down
+                && vk_value == 0x56
+                && state.gui_visible.load(Ordering::Relaxed)
+                && !state.gui_has_focus.load(Ordering::Relaxed)
+                && state.gui_commands.send(GuiCommand::PastedElsewhere).is_err()
+            {
+                error!("GUI 執行緒已結束，無法清除累積文字");
+            }
         }
         
         // 處理 Shift 鍵的按下和釋放（參考 Python 版邏輯）
@@ -380,26 +1000,8 @@ impl KeyboardHook {
 
                     // 如果沒有與其他鍵組合，視為「單獨按 Shift」→ 切換模式（英/肥）
                     if !used_with_other {
-                    let old_state = SHIFT_TOGGLE.with(|t| *t.borrow());
-                    let new_state = SHIFT_TOGGLE.with(|t| {
-                        let mut toggle = t.borrow_mut();
-                        *toggle = !*toggle;
-                        *toggle
-                    });
-                    
-                    // 清除現有字根輸入
-                    let mut processor = state.input_processor.lock().unwrap();
-                    let state_ref = processor.get_state();
-                    if !state_ref.current_code.is_empty() {
-                        info!("Shift 切換，清除現有字根: {}", state_ref.current_code);
-                        processor.clear();
-                            // 標記需要更新 GUI
-                            state.gui_needs_update.store(true, Ordering::Relaxed);
-                    }
-                    
-                        info!("Shift 單獨按下，切換攔截狀態: {} -> {}", 
-                            if old_state { "不攔截(英)" } else { "攔截(肥)" },
-                            if new_state { "不攔截(英)" } else { "攔截(肥)" });
+                    // 切換時會清除現有字根並更新 GUI
+                    state.toggle_mode();
                 }
                 
                     // Shift Up 事件一律放行，保留原本鍵盤行為
@@ -426,9 +1028,8 @@ impl KeyboardHook {
         }
 
         // 先檢查 Shift 切換狀態（英/肥模式）
-        let shift_toggle = SHIFT_TOGGLE.with(|t| *t.borrow());
         // 如果不攔截模式（英模式），讓所有其他按鍵通過
-        if shift_toggle {
+        if state.get_mode() == InputMode::English {
             // 檢查 CapsLock 狀態（只用於調試日誌）
             unsafe {
                 let caps_lock_state = GetKeyState(20i32); // VK_CAPITAL = 20
@@ -440,12 +1041,16 @@ impl KeyboardHook {
             return Ok(false);
         }
         
-        // 如果 Ctrl 鍵已經按下，讓所有後續按鍵通過（支援 Ctrl+C、Ctrl+V 等組合鍵）
-        // 參考 Python 版本的實現：在攔截模式下，如果 Ctrl 鍵按下，讓所有按鍵通過
+        // Ctrl 組合鍵：白名單內的（Ctrl+C、Ctrl+V 等，設定 ctrl_passthrough）放行，其餘交給 handle_ctrl_combo
         let ctrl_pressed = CTRL_PRESSED.with(|p| *p.borrow());
         if ctrl_pressed && is_key_down {
-            debug!("Ctrl 鍵已按下，讓事件通過（支援 Ctrl+C、Ctrl+V 等組合鍵）");
-            return Ok(false);
+            let vk_value: u32 = unsafe { (*(l_param.0 as *const KBDLLHOOKSTRUCT)).vkCode };
+            let listed = state.ctrl_passthrough.as_ref().is_none_or(|keys| keys.contains(&vk_value));
+            if listed {
+                debug!("Ctrl 組合鍵在放行清單中，讓事件通過 (vk={})", vk_value);
+                return Ok(false);
+            }
+            return Ok(Self::handle_ctrl_combo(state, vk_value));
         }
         
         // 只處理 key down 事件（避免重複處理）
@@ -491,6 +1096,98 @@ impl KeyboardHook {
                 // 不 return，繼續沿用原本攔截邏輯
             }
             
+            // 原樣放行鍵（passthrough_keys，例如遊戲的技能鍵）：在 emoji 前綴與符號處理之前比對，
+            // 依目前的鍵盤配置與 Shift 狀態對應到實際按鍵，字根維持原狀
+            if state.passthrough_keys.iter().any(|&ch| Self::key_types_char(vk_value, ch)) {
+                debug!("原樣放行鍵，讓事件通過 (vk={})", vk_value);
+                return Ok(false);
+            }
+            
+            // 暫時英文模式（` 開頭）：不切換英/肥模式，字元原樣收進緩衝區
+            if let Some(handled) = Self::handle_temp_english(state, vk_value) {
+                return Ok(handled);
+            }
+            
+            // 重送鍵（repeat_output_key）：沒有在組字時重送最近一次送出的文字，其他時候照一般按鍵處理
+            if state.repeat_output_key == Some(vk_value) {
+                let text = state.input_processor.lock().unwrap().repeat_last_output();
+                if let Some(text) = text {
+                    debug!("重送鍵: 排隊送出 {}", text);
+                    Self::queue_text(state, text);
+                    return Ok(true);
+                }
+            }
+            
+            // 重複鍵（repeat_key，預設為 \）：有候選時開始輸入重複次數，沒有候選時照一般按鍵處理
+            {
+                let mut processor = state.input_processor.lock().unwrap();
+                let is_repeat_key = processor.repeat_key().is_some_and(|key| Self::key_types_char(vk_value, key));
+                if is_repeat_key && processor.start_repeat_count() {
+                    debug!("開始輸入重複次數");
+                    state.gui_needs_update.store(true, Ordering::Relaxed);
+                    return Ok(true);
+                }
+            }
+            
+            // 符號選單鍵（symbol_menu_key）：沒有輸入字根時列出符號分類，輸入字根時照一般按鍵處理
+            {
+                let mut processor = state.input_processor.lock().unwrap();
+                let is_menu_key = processor.symbol_menu_key().is_some_and(|key| Self::key_types_char(vk_value, key));
+                if is_menu_key && processor.open_symbol_menu() {
+                    debug!("開啟符號選單");
+                    return Ok(true);
+                }
+            }
+            
+            // emoji 短碼前綴鍵（預設為 :，依目前的鍵盤配置對應到實際按鍵）
+            let emoji = {
+                let mut processor = state.input_processor.lock().unwrap();
+                match processor.emoji_prefix() {
+                    Some(prefix) if Self::key_types_char(vk_value, prefix) => Some(processor.handle_emoji_prefix(prefix)),
+                    _ => None,
+                }
+            };
+            if let Some((true, text)) = emoji {
+                state.gui_needs_update.store(true, Ordering::Relaxed);
+                if let Some(text) = text {
+                    debug!("emoji 短碼: 排隊送出 {}", text);
+                    Self::queue_text(state, text);
+                }
+                return Ok(true);
+            }
+            
+            // 候選翻頁鍵（page_keys）：只在組字時攔截，沒有組字時照常輸入
+            let page_forward = state.page_keys.iter().find_map(|&(prev, next)| {
+                if vk_value == prev {
+                    Some(false)
+                } else if vk_value == next {
+                    Some(true)
+                } else {
+                    None
+                }
+            });
+            if let Some(forward) = page_forward {
+                let mut processor = state.input_processor.lock().unwrap();
+                if processor.page_candidates(forward) {
+                    state.announcer.announce_pages(processor.get_state());
+                    debug!("候選翻到{}頁 (vk={})", if forward { "下一" } else { "上一" }, vk_value);
+                    state.gui_needs_update.store(true, Ordering::Relaxed);
+                    return Ok(true);
+                }
+            }
+            
+            // 方案的選字鍵不是數字（例如 "asd"）時，組字中有候選就先當選字鍵，否則照一般按鍵（字根）處理
+            let selection_key = {
+                let processor = state.input_processor.lock().unwrap();
+                let choosing = !processor.get_state().current_code.is_empty()
+                    && !processor.get_state().get_current_page_candidates().is_empty();
+                let keys = &processor.scheme().selection_keys;
+                keys.chars().filter(|key| !key.is_ascii_digit()).find(|&key| choosing && Self::key_types_char(vk_value, key))
+            };
+            if let Some(key) = selection_key {
+                return Ok(Self::handle_selection(state, key));
+            }
+            
             match vk_value {
                 
                 // Escape (VK_ESCAPE = 27)
@@ -501,14 +1198,30 @@ impl KeyboardHook {
                     let mut processor = state.input_processor.lock().unwrap();
                     let state_ref = processor.get_state();
                     if !state_ref.current_code.is_empty() {
-                        info!("按下 ESC，清除輸入: {}", state_ref.current_code);
+                        debug!("按下 ESC，清除輸入: {}", state_ref.current_code);
                         processor.clear();
                         // 標記需要更新 GUI
                         state.gui_needs_update.store(true, Ordering::Relaxed);
                         // 阻止 ESC 鍵事件傳遞
                         return Ok(true);
                     }
-                    // 沒有輸入，讓 ESC 鍵通過
+                    // 符號選單退回上一層
+                    if processor.close_symbol_menu() {
+                        return Ok(true);
+                    }
+                    // 沒有輸入時清除整句組字累積的文字
+                    if processor.clear_composition() {
+                        info!("按下 ESC，清除整句組字");
+                        processor.clear();
+                        state.gui_needs_update.store(true, Ordering::Relaxed);
+                        return Ok(true);
+                    }
+                    // 沒有輸入，收起聯想詞或同音字並讓 ESC 鍵通過
+                    let state_ref = processor.get_state();
+                    if !state_ref.suggestions.is_empty() || state_ref.homophone_of.is_some() {
+                        processor.clear();
+                        state.gui_needs_update.store(true, Ordering::Relaxed);
+                    }
                     Ok(false)
                 }
                 
@@ -516,7 +1229,10 @@ impl KeyboardHook {
                 8 => {
                     let handled = {
                     let mut processor = state.input_processor.lock().unwrap();
-                        processor.handle_backspace()
+                        // 沒有字根時刪掉整句組字的最後一段
+                        let handled = processor.handle_backspace() || processor.pop_composition();
+                        state.announcer.announce_pages(processor.get_state());
+                        handled
                     };
                     if handled {
                         // 有字根可刪除，阻止事件
@@ -524,10 +1240,18 @@ impl KeyboardHook {
                         state.gui_needs_update.store(true, Ordering::Relaxed);
                         return Ok(true);
                     }
-                    // 沒有字根，讓事件通過
+                    // 沒有字根，讓事件通過（聯想詞已收起，更新顯示）
+                    state.gui_needs_update.store(true, Ordering::Relaxed);
                     Ok(false)
                 }
                 
+                // Shift+Space：切換全形/半形（設定 enable_half_full），不影響目前的字根
+                32 if state.enable_half_full && SHIFT_PRESSED.with(|p| *p.borrow()) => {
+                    state.input_processor.lock().unwrap().toggle_half_width();
+                    state.gui_needs_update.store(true, Ordering::Relaxed);
+                    Ok(true)
+                }
+                
                 // Space (VK_SPACE = 32)
                 32 => {
                     let (has_complement, has_input, text_opt) = {
@@ -536,15 +1260,19 @@ impl KeyboardHook {
                     // 檢查是否有符號選擇（補碼或符號輸入）
                     let has_complement = processor.get_state().complement_selected.is_some();
                     
-                    // 檢查是否有輸入的字根
-                    let has_input = !processor.get_state().current_code.is_empty();
+                    // 檢查是否有輸入的字根（列出同音字或符號選單時也算，Space 選用第一個候選）
+                    let has_input = !processor.get_state().current_code.is_empty()
+                        || processor.get_state().is_listing();
                     
                         let text_opt = if has_complement || has_input {
                         // 嘗試選擇候選字（可能是補碼選擇、符號選擇或第一個候選字）
                             let text = processor.handle_space();
                         
-                        // 確保清除輸入（handle_space() 可能已經清除了，但我們確保總是清除）
-                        processor.clear();
+                        // 沒送出字時確保清除輸入（送出時 handle_space() 已清除，並留下聯想詞；符號選單選了分類時保留）
+                        if text.is_none() && processor.get_state().symbol_menu.is_none() {
+                            processor.clear();
+                            Self::invalid_key(state, "Space 沒有候選可送出");
+                        }
                             
                             text
                         } else {
@@ -560,11 +1288,8 @@ impl KeyboardHook {
                         
                         if let Some(text) = text_opt {
                             // 有候選字，排隊等待主迴圈送出貼上（避免在鉤子回呼裡做耗時操作）
-                            {
-                                let mut pending = state.pending_paste_text.lock().unwrap();
-                                *pending = Some(text.clone());
-                            }
-                            info!("Space: 排隊送出候選字: {}", text);
+                            debug!("Space: 排隊送出候選字: {}", text);
+                            Self::queue_text(state, text);
                             return Ok(true);
                         } else {
                             // 沒有候選字，但已清除輸入，阻止 Space 事件
@@ -588,8 +1313,11 @@ impl KeyboardHook {
                         // 嘗試選擇第一個候選字（與 Space 鍵行為一致）
                             let text = processor.handle_space();
                         
-                        // 確保清除輸入（handle_space() 可能已經清除了，但我們確保總是清除）
-                        processor.clear();
+                        // 沒送出字時確保清除輸入（送出時 handle_space() 已清除，並留下聯想詞）
+                        if text.is_none() {
+                            processor.clear();
+                            Self::invalid_key(state, "Enter 沒有候選可送出");
+                        }
                             
                             text
                         } else {
@@ -605,11 +1333,8 @@ impl KeyboardHook {
                         
                         if let Some(text) = text_opt {
                             // 有候選字，排隊等待主迴圈送出貼上
-                            {
-                                let mut pending = state.pending_paste_text.lock().unwrap();
-                                *pending = Some(text.clone());
-                            }
-                            info!("Enter: 排隊送出候選字: {}", text);
+                            debug!("Enter: 排隊送出候選字: {}", text);
+                            Self::queue_text(state, text);
                             return Ok(true);
                         } else {
                             // 沒有候選字，但已清除輸入，阻止 Enter 事件
@@ -617,32 +1342,58 @@ impl KeyboardHook {
                             return Ok(true);
                         }
                     }
+                    // 沒有輸入時一次送出整句組字累積的文字
+                    let composition = state.input_processor.lock().unwrap().take_composition();
+                    if let Some(text) = composition {
+                        debug!("Enter: 排隊送出整句: {}", text);
+                        *state.pending_paste_text.lock().unwrap() = Some(text);
+                        state.gui_needs_update.store(true, Ordering::Relaxed);
+                        return Ok(true);
+                    }
                     // 沒有輸入，讓 Enter 鍵通過
                     Ok(false)
                 }
                 
+                // 萬用字元 *（Shift+8 或數字鍵盤的 *，VK_MULTIPLY = 106）：只記得部分字根時使用
+                // 沒有字根時讓 * 照常輸入
+                56 | 106 if vk_value == 106 || SHIFT_PRESSED.with(|p| *p.borrow()) => {
+                    let handled = {
+                        let mut processor = state.input_processor.lock().unwrap();
+                        let handled = processor.handle_wildcard_input('*');
+                        state.announcer.announce_pages(processor.get_state());
+                        handled
+                    };
+                    if handled {
+                        debug!("輸入萬用字元 *");
+                        state.gui_needs_update.store(true, Ordering::Relaxed);
+                    }
+                    Ok(handled)
+                }
+                
+                // 萬用字元 ?（美式鍵盤為 Shift+/）：只記得中間少了一碼時使用，例如 a?c
+                // 沒有字根時讓 ? 照常輸入
+                _ if Self::key_types_char(vk_value, '?') => {
+                    let handled = {
+                        let mut processor = state.input_processor.lock().unwrap();
+                        let handled = processor.handle_wildcard_input('?');
+                        state.announcer.announce_pages(processor.get_state());
+                        handled
+                    };
+                    if handled {
+                        debug!("輸入萬用字元 ?");
+                        state.gui_needs_update.store(true, Ordering::Relaxed);
+                    }
+                    Ok(handled)
+                }
+                
                 // 數字鍵 0-9 (VK_0 = 48, VK_9 = 57)
                 48..=57 => {
-                    let num = (vk_value - 48) as u8;
-                    let mut processor = state.input_processor.lock().unwrap();
-                    let state_ref = processor.get_state();
-                    let candidate_count = state_ref.get_current_page_candidates().len();
-                    
-                    debug!("處理數字鍵 {}: 當前候選字數量={}, 字根='{}'", num, candidate_count, state_ref.current_code);
-                    
-                    if let Some(text) = processor.handle_number_selection(num) {
-                        // 選擇了候選字，送出文字並阻止數字鍵事件
-                        {
-                            let mut pending = state.pending_paste_text.lock().unwrap();
-                            *pending = Some(text.clone());
-                        }
-                        info!("✅ 選擇候選字 {}: {}（排隊送出）", num, text);
+                    // 按過重複鍵後數字是重複次數
+                    if state.input_processor.lock().unwrap().push_repeat_digit(char::from(vk_value as u8)) {
+                        state.gui_needs_update.store(true, Ordering::Relaxed);
                         return Ok(true);
-                    } else {
-                        // 沒有對應的候選字，攔截並忽略該按鍵
-                        debug!("數字鍵 {} 沒有對應的候選字（候選字數量={}），攔截並忽略", num, candidate_count);
-                        Ok(true) // 攔截並忽略
                     }
+                    Ok(Self::handle_selection(state, char::from(vk_value as u8)))
                 }
                 
                 // 字母鍵 A-Z (VK_A = 65, VK_Z = 90)
@@ -650,6 +1401,19 @@ impl KeyboardHook {
                     // 若目前 Shift 有按著（不論英/肥模式），讓系統原生處理 Shift+字母
                     // 這樣在肥模式下按住 Shift 也可以直接打出大寫英文（與 Python 版一致）
                     let shift_pressed_now = SHIFT_PRESSED.with(|p| *p.borrow());
+                    // 設定 shift_letter 為 commit/word 時由肥米送出大寫字母（或開始英文單字），不必切換模式
+                    if shift_pressed_now && state.shift_letter != ShiftLetter::Pass {
+                        let committed = {
+                            let mut processor = state.input_processor.lock().unwrap();
+                            processor.handle_shift_letter(state.shift_letter, char::from(vk_value as u8))
+                        };
+                        if let Some(text) = committed {
+                            debug!("Shift+字母: 排隊送出 {}", text);
+                            Self::queue_text(state, text);
+                        }
+                        state.gui_needs_update.store(true, Ordering::Relaxed);
+                        return Ok(true);
+                    }
                     if shift_pressed_now {
                         debug!(
                             "Shift 按下，直接讓英文字母通過，不進入肥米碼表 (vk={}, ch={})",
@@ -664,25 +1428,42 @@ impl KeyboardHook {
                     
                     debug!("處理字母鍵: vk={}, 轉換後={}", vk_value, ch);
                     
-                    let (success, complement_selected) = {
+                    let (success, complement_selected, auto_committed, dead_end_code) = {
                     let mut processor = state.input_processor.lock().unwrap();
-                        processor.handle_code_input(ch)
+                        let (success, complement_selected) = processor.handle_code_input(ch);
+                        // 候選超過一頁時提示頁數（補碼選字後候選不變，不會重複提示）
+                        state.announcer.announce_pages(processor.get_state());
+                        // 提示或線上備援查詢開啟時才查字根是否打錯（要掃過字碼表）
+                        let check = state.invalid_key_feedback.is_enabled() || state.cloud_lookup.is_some();
+                        let dead_end_code = (check && success && processor.is_dead_end()).then(|| processor.get_state().current_code.clone());
+                        (success, complement_selected, processor.take_auto_commit(), dead_end_code)
                     };
                     
+                    // 字根打滿且只有一個候選，已自動送出（auto_commit_unique）
+                    if let Some(text) = auto_committed {
+                        debug!("✅ 自動送出唯一候選: {}（排隊送出）", text);
+                        Self::queue_text(state, text);
+                        state.gui_needs_update.store(true, Ordering::Relaxed);
+                        return Ok(true);
+                    }
+                    
                     if success {
                         // 檢查是否有補碼選擇的候選字
                         if complement_selected.is_some() {
                             // 補碼機制選擇了候選字，但不清除狀態，等待 Space 鍵送出
-                            let (current_code, complement_selected_val) = {
-                                let processor = state.input_processor.lock().unwrap();
-                            let state_ref = processor.get_state();
-                                (state_ref.current_code.clone(), state_ref.complement_selected.clone())
-                            };
-                            info!(
-                                "✅ 補碼選擇候選字（等待 Space 鍵送出）: '{}' -> {:?}",
-                                current_code,
-                                complement_selected_val
-                            );
+                            // 日誌需要再次取鎖，降級模式下略過
+                            if enabled!(Level::INFO) {
+                                let (current_code, complement_selected_val) = {
+                                    let processor = state.input_processor.lock().unwrap();
+                                let state_ref = processor.get_state();
+                                    (state_ref.current_code.clone(), state_ref.complement_selected.clone())
+                                };
+                                debug!(
+                                    "✅ 補碼選擇候選字（等待 Space 鍵送出）: '{}' -> {:?}",
+                                    current_code,
+                                    complement_selected_val
+                                );
+                            }
                             
                             // 標記需要更新 GUI
                             state.gui_needs_update.store(true, Ordering::Relaxed);
@@ -692,17 +1473,24 @@ impl KeyboardHook {
                         }
                         
                         // 成功處理字根輸入，阻止原始按鍵事件
-                        let (current_code, candidates_len, current_page) = {
-                            let processor = state.input_processor.lock().unwrap();
-                        let state_ref = processor.get_state();
-                            (state_ref.current_code.clone(), state_ref.candidates.len(), state_ref.get_current_page_candidates().clone())
-                        };
-                        info!(
-                            "✅ 輸入字根: '{}', 找到 {} 個候選字: {:?}",
-                            current_code,
-                            candidates_len,
-                            current_page
-                        );
+                        if enabled!(Level::INFO) {
+                            let (current_code, candidates_len, current_page) = {
+                                let processor = state.input_processor.lock().unwrap();
+                            let state_ref = processor.get_state();
+                                (state_ref.current_code.clone(), state_ref.candidates.len(), state_ref.get_current_page_candidates().iter().map(|candidate| candidate.text.clone()).collect::<Vec<_>>())
+                            };
+                            debug!(
+                                "✅ 輸入字根: '{}', 找到 {} 個候選字: {:?}",
+                                current_code,
+                                candidates_len,
+                                current_page
+                            );
+                        }
+                        
+                        if let Some(code) = dead_end_code {
+                            Self::invalid_key(state, "字根查不到候選");
+                            Self::cloud_lookup(state, &code);
+                        }
                         
                         // 標記需要更新 GUI
                         state.gui_needs_update.store(true, Ordering::Relaxed);
@@ -795,7 +1583,7 @@ impl KeyboardHook {
                         if symbol_selected.is_some() {
                             // 符號映射找到了候選字，但不清除狀態，等待 Space 鍵送出
                             let state_ref = processor.get_state();
-                            info!(
+                            debug!(
                                 "✅ 符號映射（等待 Space 鍵送出）: '{}' -> {:?}",
                                 state_ref.current_code,
                                 state_ref.complement_selected
@@ -820,7 +1608,7 @@ impl KeyboardHook {
                         if symbol_selected.is_some() {
                             // 符號映射找到了候選字，但不清除狀態，等待 Space 鍵送出
                             let state_ref = processor.get_state();
-                            info!(
+                            debug!(
                                 "✅ 符號映射（等待 Space 鍵送出）: '{}' -> {:?}",
                                 state_ref.current_code,
                                 state_ref.complement_selected
@@ -835,10 +1623,27 @@ impl KeyboardHook {
                     Ok(true)
                 }
                 
+                // 引號鍵 '（VK_OEM_7 = 222）：沒有字根時列出上一個送出字的同音字
+                222 if !SHIFT_PRESSED.with(|p| *p.borrow()) => {
+                    if state.input_processor.lock().unwrap().show_homophones() {
+                        debug!("列出同音字");
+                        state.gui_needs_update.store(true, Ordering::Relaxed);
+                    }
+                    Ok(true)
+                }
+                
                 // 其他所有按鍵：在攔截模式下都應該被攔截
                 // 這包括符號、標點符號等所有可列印字符
                 _ => {
                     debug!("攔截模式：攔截未處理的按鍵 vk={}", vk_value);
+                    // 組字中按了沒有作用的鍵（按鍵被吃掉、字根不變）也要提示
+                    let composing = {
+                        let processor = state.input_processor.lock().unwrap();
+                        !processor.get_state().current_code.is_empty() || processor.get_state().is_listing()
+                    };
+                    if composing {
+                        Self::invalid_key(state, "組字中按了沒有作用的鍵");
+                    }
                     Ok(true) // 攔截所有其他按鍵
                 },
             }
@@ -846,6 +1651,46 @@ impl KeyboardHook {
     }
 }
 
+#[cfg(test)]
+impl KeyboardHook {
+    /// 以事件來源驅動按鍵處理，不需要安裝鉤子（見 key_source 模組）
+    /// 每個事件組成和系統鉤子相同的 KBDLLHOOKSTRUCT 交給 dispatch，
+    /// 之後像訊息循環一樣取出排進送字佇列的文字（只記下，不實際送出）
+    pub fn drive(state: &AppState, source: &mut dyn KeyboardSource) -> ScriptRun {
+        let mut run = ScriptRun::default();
+        let (mut ctrl_held, mut alt_held) = (false, false);
+        while let Some(event) = source.next_event() {
+            let is_alt = matches!(event.vk, 0x12 | 0xA4 | 0xA5);
+            match event.vk {
+                0x11 | 0xA2 | 0xA3 => ctrl_held = event.down,
+                _ if is_alt => alt_held = event.down,
+                _ => {}
+            }
+            // 和 Windows 相同：Alt 本身與按著 Alt（沒有 Ctrl）時的按鍵以 WM_SYSKEYDOWN/WM_SYSKEYUP 送出
+            let sys = !ctrl_held && (alt_held || is_alt);
+            let message = match (event.down, sys) {
+                (true, false) => WM_KEYDOWN,
+                (false, false) => WM_KEYUP,
+                (true, true) => WM_SYSKEYDOWN,
+                (false, true) => WM_SYSKEYUP,
+            };
+            let kbd_struct = KBDLLHOOKSTRUCT {
+                vkCode: event.vk,
+                scanCode: 0,
+                flags: if event.down { KBDLLHOOKSTRUCT_FLAGS(0) } else { LLKHF_UP },
+                time: event.time,
+                dwExtraInfo: 0,
+            };
+            let blocked = Self::dispatch(state, WPARAM(message as usize), LPARAM(&kbd_struct as *const _ as isize));
+            run.blocked.push(blocked);
+            if let Some(text) = state.pending_paste_text.lock().unwrap().take() {
+                run.sent.push(text);
+            }
+        }
+        run
+    }
+}
+
 impl Drop for KeyboardHook {
     fn drop(&mut self) {
         unsafe {
@@ -860,6 +1705,7 @@ mod tests {
     use super::*;
     use crate::input_method::InputMethodProcessor;
     use crate::dictionary::Dictionary;
+    use crate::mode::ModeState;
     use std::collections::HashMap;
 
     #[cfg(test)]
@@ -870,12 +1716,12 @@ mod tests {
         code_map.insert("a".to_string(), vec!["一".to_string(), "乙".to_string()]);
         code_map.insert("ab".to_string(), vec!["二".to_string()]);
         
-        let dictionary = Dictionary {
-            code_to_chars: code_map,
-            pinyi_data: None,
-        };
+        // Dictionary 在 lib 裡，有私有欄位，不能以結構更新語法建立
+        let mut dictionary = Dictionary::default();
+        dictionary.code_to_chars = code_map;
         
         let processor = InputMethodProcessor::new(dictionary.clone());
+        let passed_keys = processor.passed_keys();
         let input_processor = Arc::new(Mutex::new(processor));
         let input_simulator = Arc::new(Mutex::new(crate::input_simulator::InputSimulator::new().unwrap()));
         
@@ -885,11 +1731,13 @@ mod tests {
         let pending_paste_text = Arc::new(Mutex::new(None));
         let gui_visible = Arc::new(AtomicBool::new(false));
         let gui_has_focus = Arc::new(AtomicBool::new(false));
+        let (gui_commands, _gui_command_rx) = std::sync::mpsc::channel();
         
         AppState {
             dictionary: Arc::new(Mutex::new(dictionary)),
             input_simulator: input_simulator.clone(),
             input_processor: input_processor.clone(),
+            passed_keys,
             gui_window_manager: Arc::new(Mutex::new(GuiWindowManager::new(
                 input_processor,
                 input_simulator.clone(),
@@ -900,13 +1748,129 @@ mod tests {
             pending_paste_text,
             gui_visible,
             gui_has_focus,
-            is_ucl_mode: Arc::new(Mutex::new(true)),
-            is_half_mode: Arc::new(Mutex::new(false)),
+            mode: Arc::new(ModeState::default()),
+            enable_half_full: false,
+            shift_letter: ShiftLetter::default(),
+            held_keys: Arc::default(),
             should_quit: Arc::new(AtomicBool::new(false)),
             gui_needs_update,
+            mode_toggle_request: Arc::new(AtomicBool::new(false)),
+            emergency_reset: Arc::new(AtomicBool::new(false)),
+            hook_timing: Arc::new(crate::hook_timing::HookTiming::new()),
+            profile: Arc::new(Mutex::new(crate::profile::Profile {
+                name: "test".to_string(),
+                dir: std::env::temp_dir(),
+            })),
+            gui_commands,
+            announcer: Arc::new(crate::announcer::Announcer::new(crate::announcer::AnnounceMode::Off)),
+            mode_indicator: crate::mode_indicator::ModeIndicator::default(),
+            invalid_key_feedback: crate::announcer::InvalidKeyFeedback::default(),
+            macros: Arc::new(Mutex::new(crate::macros::MacroBook::default())),
+            ctrl_passthrough: None,
+            passthrough_keys: Vec::new(),
+            page_keys: Vec::new(),
+            repeat_output_key: None,
+            key_trace: Arc::new(crate::key_trace::KeyTrace::new(0)),
+            pending_backspaces: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            accumulate_packet_input: false,
+            backup: None,
+            schedule: crate::schedule::Schedule::default(),
+            learning: Arc::new(crate::learning::LearningTracker::default()),
+            main_dictionary: crate::dictionary_update::MainDictionary {
+                scheme_dir: std::env::temp_dir(),
+                dictionary_path: None,
+                strict: false,
+                backend: crate::dictionary::DictionaryBackend::Memory,
+            },
+            dictionary_update: None,
+            cloud_lookup: None,
         }
     }
 
+    #[test]
+    fn test_drive_scripted_keys() {
+        use crate::key_source::ScriptedSource;
+        let state = create_test_state();
+        // a、b、Space：組字後送出第一個候選；放開的事件一律放行
+        let mut source = ScriptedSource::new().tap(0x41).tap(0x42).tap(0x20);
+        let run = KeyboardHook::drive(&state, &mut source);
+        assert_eq!(run.blocked, [true, false, true, false, true, false]);
+        assert_eq!(run.sent, ["二"]);
+
+        // 單獨按 Shift 切到英模式，之後的按鍵全部放行
+        let mut source = ScriptedSource::new().tap(0x10).tap(0x41).tap(0x20);
+        let run = KeyboardHook::drive(&state, &mut source);
+        assert_eq!(state.get_mode(), InputMode::English);
+        assert!(run.blocked.iter().all(|blocked| !blocked));
+        assert!(run.sent.is_empty());
+    }
+
+    #[test]
+    fn test_drive_letter_selection_keys() {
+        use crate::key_source::ScriptedSource;
+        use crate::scheme::InputScheme;
+        let state = create_test_state();
+        let dictionary = state.dictionary.lock().unwrap().clone();
+        let scheme = InputScheme { selection_keys: "asd".to_string(), ..InputScheme::default() };
+        *state.input_processor.lock().unwrap() = InputMethodProcessor::with_scheme(dictionary, scheme);
+        // 沒有組字時 a 是字根；有候選時 s 是選字鍵（選第 2 個候選）
+        let mut source = ScriptedSource::new().tap(0x41).tap(0x53);
+        let run = KeyboardHook::drive(&state, &mut source);
+        assert_eq!(run.sent, ["乙"]);
+        // 組字中有候選時 a 也是選字鍵，不會接成字根 "aa"
+        let mut source = ScriptedSource::new().tap(0x41).tap(0x41);
+        let run = KeyboardHook::drive(&state, &mut source);
+        assert_eq!(run.sent, ["一"]);
+    }
+
+    #[test]
+    fn test_drive_dropped_key_while_composing_is_invalid() {
+        use crate::announcer::InvalidKeyFeedback;
+        use crate::key_source::ScriptedSource;
+        let mut state = create_test_state();
+        let (gui_commands, gui_command_rx) = std::sync::mpsc::channel();
+        state.gui_commands = gui_commands;
+        state.invalid_key_feedback = InvalidKeyFeedback { beep: false, flash: true };
+        // 沒有組字時攔下的鍵不提示；組字中被吃掉的鍵（; 不是字根）閃一下狀態窗，字根不變
+        let mut source = ScriptedSource::new().tap(0xBA).tap(0x41).tap(0xBA);
+        let run = KeyboardHook::drive(&state, &mut source);
+        assert_eq!(run.blocked, [true, false, true, false, true, false]);
+        assert!(matches!(gui_command_rx.try_recv(), Ok(GuiCommand::FlashStatus)));
+        assert!(gui_command_rx.try_recv().is_err());
+        assert_eq!(state.input_processor.lock().unwrap().get_state().current_code, "a");
+    }
+
+    #[test]
+    fn test_drive_alt_digit_copies_without_waiting() {
+        use crate::key_source::ScriptedSource;
+        let mut state = create_test_state();
+        let (gui_commands, gui_command_rx) = std::sync::mpsc::channel();
+        state.gui_commands = gui_commands;
+        // 組字中 Alt+2 複製第 2 個候選，字根不變
+        let mut source = ScriptedSource::new().tap(0x41).chord(0x12, 0x32);
+        let run = KeyboardHook::drive(&state, &mut source);
+        assert_eq!(run.blocked, [true, false, false, true, false, false]);
+        assert!(matches!(gui_command_rx.try_recv(), Ok(GuiCommand::CopyToClipboard(text)) if text == "乙"));
+
+        // 輸入處理器被佔用時不等待，Alt+2 照常放行
+        let processor = state.input_processor.lock().unwrap();
+        let mut source = ScriptedSource::new().chord(0x12, 0x32);
+        let run = KeyboardHook::drive(&state, &mut source);
+        drop(processor);
+        assert!(run.blocked.iter().all(|blocked| !blocked));
+        assert!(gui_command_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_drive_temp_english_types_layout_chars() {
+        use crate::key_source::ScriptedSource;
+        let state = create_test_state();
+        // ` 進入暫時英文，字母、數字與符號依鍵盤配置收進緩衝區，Space 送出
+        let mut source = ScriptedSource::new().tap(0xC0).tap(0x41).tap(0x31).tap(0xBA).tap(0x20);
+        let run = KeyboardHook::drive(&state, &mut source);
+        assert_eq!(run.sent, ["a1;"]);
+    }
+
     #[test]
     fn test_keyboard_hook_creation() {
         let state = Arc::new(create_test_state());
@@ -915,6 +1879,62 @@ mod tests {
         // assert!(hook.hook_handle.0 != 0);
     }
 
+    #[test]
+    fn test_escape_detector_triple_press() {
+        let mut detector = EscapeDetector::default();
+        let start = Instant::now();
+        assert!(!detector.press(true, start));
+        assert!(!detector.press(true, start + Duration::from_millis(100)));
+        assert!(detector.press(true, start + Duration::from_millis(200)));
+        // 觸發後重新計數
+        assert!(!detector.press(true, start + Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn test_escape_detector_resets() {
+        let mut detector = EscapeDetector::default();
+        let start = Instant::now();
+        // 中間夾雜其他按鍵
+        detector.press(true, start);
+        detector.press(true, start);
+        assert!(!detector.press(false, start));
+        assert!(!detector.press(true, start));
+        // 間隔太久
+        let mut detector = EscapeDetector::default();
+        detector.press(true, start);
+        detector.press(true, start + Duration::from_millis(100));
+        assert!(!detector.press(true, start + Duration::from_millis(100) + EMERGENCY_ESCAPE_INTERVAL * 2));
+    }
+
+    #[test]
+    fn test_packet_decoder_joins_surrogates() {
+        let mut decoder = PacketDecoder::default();
+        assert_eq!(decoder.push('字' as u16), Some('字'));
+        // U+20000 分成兩個 surrogate
+        assert_eq!(decoder.push(0xD840), None);
+        assert_eq!(decoder.push(0xDC00), Some('𠀀'));
+        // 落單的 low surrogate 丟棄
+        assert_eq!(decoder.push(0xDC00), None);
+    }
+
+    #[test]
+    fn test_emergency_release_clears_input() {
+        let state = create_test_state();
+        state.input_processor.lock().unwrap().handle_code_input('a');
+        KeyboardHook::emergency_release(&state);
+        assert!(state.input_processor.lock().unwrap().get_state().current_code.is_empty());
+        assert!(!state.emergency_reset.load(Ordering::Relaxed));
+        assert_eq!(state.get_mode(), InputMode::English);
+
+        // 輸入處理器被鎖住時留下旗標，等訊息循環重試
+        let guard = state.input_processor.lock().unwrap();
+        KeyboardHook::emergency_release(&state);
+        assert!(state.emergency_reset.load(Ordering::Relaxed));
+        drop(guard);
+        KeyboardHook::try_finish_emergency_reset(&state);
+        assert!(!state.emergency_reset.load(Ordering::Relaxed));
+    }
+
     #[test]
     fn test_f4_quit_flag() {
         // 測試 F4 鍵退出標誌的設置
@@ -947,20 +1967,19 @@ mod tests {
         // - 單獨按一下 Shift（期間沒有搭配其他鍵）→ 在放開時切換「攔截 / 英模式」，並清除現有字根
         // - Shift + 其他鍵（例如 Shift+1, Shift+A）→ 視為一般組合鍵，不切換模式
         // - 即使在英模式（不攔截）下，只要 Shift 期間有搭配其他鍵，放開 Shift 也不會切換模式
-        SHIFT_TOGGLE.with(|t| {
-            // 初始狀態應該是 false（攔截模式）
-            *t.borrow_mut() = false;
-            assert!(!*t.borrow());
-            
-            // 第一次切換：false -> true（不攔截模式）
-            let mut toggle = t.borrow_mut();
-            *toggle = !*toggle;
-            assert!(*toggle);
-            
-            // 第二次切換：true -> false（攔截模式）
-            *toggle = !*toggle;
-            assert!(!*toggle);
-        });
+        let state = create_test_state();
+        // 初始狀態應該是肥模式（攔截）
+        assert_eq!(state.get_mode(), InputMode::Ucl);
+        
+        // 第一次切換：肥 -> 英（不攔截），同時清除現有字根
+        state.input_processor.lock().unwrap().handle_code_input('a');
+        assert_eq!(state.toggle_mode(), InputMode::English);
+        assert!(state.input_processor.lock().unwrap().get_state().current_code.is_empty());
+        
+        // 第二次切換：英 -> 肥；設定成相同模式時回傳原本的模式
+        assert_eq!(state.toggle_mode(), InputMode::Ucl);
+        assert_eq!(state.set_mode(InputMode::Ucl), InputMode::Ucl);
+        assert_eq!(state.get_mode(), InputMode::Ucl);
     }
 
     #[test]
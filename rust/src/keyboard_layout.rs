@@ -0,0 +1,160 @@
+//! 螢幕鍵盤的佈局描述
+//!
+//! 佈局是一份 JSON，逐列列出鍵位（寬度以標準鍵 1u 為單位），讓 60%、TKL 等不同鍵盤
+//! 都能畫出對應的鍵位圖，社群可以分享佈局檔：
+//!
+//! ```json
+//! { "name": "我的 60%", "rows": [
+//!     [{ "key": "q", "roots": "丿" }, { "key": "w" }, { "label": "Backspace", "width": 2.0 }],
+//!     [{ "label": "Fn", "gap": 0.25 }]
+//! ] }
+//! ```
+//!
+//! - key：按下時輸入的字元（用來對應字根），功能鍵可以省略
+//! - label：鍵帽上的文字，省略時為 key 的大寫
+//! - width：鍵寬（預設 1），gap：左側留白（預設 0）
+//! - roots：鍵上標示的字根，省略時由螢幕鍵盤以字碼表中這一碼的第一個候選代替
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// 內建佈局（標準 60% 鍵盤）
+const BUILTIN_LAYOUT: &str = include_str!("../keyboard_layout.json");
+
+/// 單一鍵位
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct KeyDef {
+    /// 按下時輸入的字元（功能鍵為 None）
+    #[serde(default)]
+    pub key: Option<String>,
+    /// 鍵帽文字
+    #[serde(default)]
+    pub label: Option<String>,
+    /// 鍵寬（1 = 標準鍵）
+    #[serde(default = "default_width")]
+    pub width: f32,
+    /// 左側留白（1 = 標準鍵寬）
+    #[serde(default)]
+    pub gap: f32,
+    /// 鍵上標示的字根
+    #[serde(default)]
+    pub roots: Option<String>,
+}
+
+fn default_width() -> f32 {
+    1.0
+}
+
+impl KeyDef {
+    /// 鍵帽文字（沒有 label 時為 key 的大寫）
+    pub fn caption(&self) -> String {
+        match (&self.label, &self.key) {
+            (Some(label), _) => label.clone(),
+            (None, Some(key)) => key.to_uppercase(),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+/// 鍵位在視窗中的位置（像素）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+/// 鍵盤佈局
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct KeyboardLayout {
+    /// 佈局名稱（顯示在視窗標題）
+    #[serde(default)]
+    pub name: String,
+    /// 由上而下的各列鍵位
+    pub rows: Vec<Vec<KeyDef>>,
+}
+
+impl KeyboardLayout {
+    /// 內建的標準 60% 佈局
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN_LAYOUT).expect("內建鍵盤佈局格式錯誤")
+    }
+
+    /// 從檔案載入佈局
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).with_context(|| format!("無法讀取鍵盤佈局: {:?}", path))?;
+        Self::parse(&content).with_context(|| format!("鍵盤佈局有誤: {:?}", path))
+    }
+
+    /// 從 JSON 字串解析並檢查鍵寬
+    pub fn parse(content: &str) -> Result<Self> {
+        let layout: Self = serde_json::from_str(content).context("無法解析鍵盤佈局 JSON")?;
+        if layout.rows.iter().all(Vec::is_empty) {
+            bail!("鍵盤佈局沒有任何鍵位");
+        }
+        for (row, keys) in layout.rows.iter().enumerate() {
+            for (column, key) in keys.iter().enumerate() {
+                if key.width.is_nan() || key.width <= 0.0 || key.gap.is_nan() || key.gap < 0.0 {
+                    bail!("第 {} 列第 {} 個鍵的寬度或留白不正確", row + 1, column + 1);
+                }
+            }
+        }
+        Ok(layout)
+    }
+
+    /// 每個鍵的位置（unit 為標準鍵的邊長像素），與 rows 攤平後的順序相同
+    pub fn key_rects(&self, unit: i32) -> Vec<KeyRect> {
+        let scale = |u: f32| (u * unit as f32).round() as i32;
+        let mut rects = Vec::new();
+        for (row, keys) in self.rows.iter().enumerate() {
+            let mut x = 0.0;
+            for key in keys {
+                x += key.gap;
+                let left = scale(x);
+                x += key.width;
+                rects.push(KeyRect { x: left, y: row as i32 * unit, w: scale(x) - left, h: unit });
+            }
+        }
+        rects
+    }
+
+    /// 佈局的總寬高（像素）
+    pub fn size(&self, unit: i32) -> (i32, i32) {
+        let width = self.key_rects(unit).iter().map(|rect| rect.x + rect.w).max().unwrap_or(0);
+        (width, self.rows.len() as i32 * unit)
+    }
+
+    /// 所有鍵位（依列攤平）
+    pub fn keys(&self) -> impl Iterator<Item = &KeyDef> {
+        self.rows.iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_layout_and_geometry() {
+        let layout = KeyboardLayout::parse(
+            r#"{ "name": "mini", "rows": [
+                [{ "key": "q", "roots": "丿" }, { "label": "Back", "width": 2.0 }],
+                [{ "key": "a", "gap": 0.5 }]
+            ] }"#,
+        )
+        .unwrap();
+        let rects = layout.key_rects(40);
+        assert_eq!(rects[1], KeyRect { x: 40, y: 0, w: 80, h: 40 });
+        assert_eq!(rects[2], KeyRect { x: 20, y: 40, w: 40, h: 40 });
+        assert_eq!(layout.size(40), (120, 80));
+        let captions: Vec<String> = layout.keys().map(KeyDef::caption).collect();
+        assert_eq!(captions, ["Q", "Back", "A"]);
+
+        assert!(KeyboardLayout::parse(r#"{ "rows": [[{ "key": "q", "width": 0 }]] }"#).is_err());
+        assert!(KeyboardLayout::parse(r#"{ "rows": [] }"#).is_err());
+        assert_eq!(KeyboardLayout::builtin().rows.len(), 5);
+    }
+}
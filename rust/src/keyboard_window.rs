@@ -0,0 +1,74 @@
+//! 螢幕鍵盤視窗
+//!
+//! 依鍵盤佈局（見 keyboard_layout 模組）畫出鍵位圖，每個鍵標出鍵帽與對應的字根，
+//! 佈局沒有標字根的鍵以字碼表中這一碼的第一個候選代替。
+
+use crate::gui_window::escape_label;
+use crate::input_method::InputMethodProcessor;
+use crate::keyboard_layout::KeyboardLayout;
+use fltk::{
+    enums::{Align, Color, FrameType},
+    frame::Frame,
+    prelude::*,
+    window::Window,
+};
+use std::sync::{Arc, Mutex};
+
+/// 標準鍵（1u）的邊長（像素）
+const KEY_UNIT: i32 = 48;
+/// 視窗四周與鍵之間的間距
+const MARGIN: i32 = 4;
+
+/// 螢幕鍵盤視窗
+pub struct KeyboardWindow {
+    window: Window,
+    /// 每個鍵的框（與 layout.keys() 順序相同）
+    frames: Vec<Frame>,
+    layout: KeyboardLayout,
+    processor: Arc<Mutex<InputMethodProcessor>>,
+}
+
+impl KeyboardWindow {
+    pub fn new(layout: KeyboardLayout, processor: Arc<Mutex<InputMethodProcessor>>) -> Self {
+        let (width, height) = layout.size(KEY_UNIT);
+        let title = if layout.name.is_empty() { "螢幕鍵盤".to_string() } else { format!("螢幕鍵盤 - {}", layout.name) };
+        let window = Window::default()
+            .with_size(width + MARGIN * 2, height + MARGIN * 2)
+            .with_label(&title)
+            .center_screen();
+
+        let frames = layout
+            .key_rects(KEY_UNIT)
+            .into_iter()
+            .map(|rect| {
+                let mut frame = Frame::new(rect.x + MARGIN, rect.y + MARGIN, rect.w - MARGIN, rect.h - MARGIN, "");
+                frame.set_frame(FrameType::UpBox);
+                frame.set_color(Color::from_rgb(245, 245, 245));
+                frame.set_align(Align::Center | Align::Inside | Align::Wrap);
+                frame.set_label_size(13);
+                frame
+            })
+            .collect();
+        window.end();
+
+        Self { window, frames, layout, processor }
+    }
+
+    /// 重新標上字根（字碼表或使用者字典可能已變動）後顯示視窗
+    pub fn show(&mut self) {
+        let processor = self.processor.lock().unwrap();
+        for (frame, key) in self.frames.iter_mut().zip(self.layout.keys()) {
+            let roots = key.roots.clone().or_else(|| {
+                let code = key.key.as_deref().filter(|k| !k.trim().is_empty())?.to_lowercase();
+                processor.dictionary().lookup(&code).and_then(|chars| chars.first().cloned())
+            });
+            let caption = key.caption();
+            match roots {
+                Some(roots) => frame.set_label(&format!("{}\n{}", escape_label(&caption), escape_label(&roots))),
+                None => frame.set_label(&escape_label(&caption)),
+            }
+        }
+        drop(processor);
+        self.window.show();
+    }
+}
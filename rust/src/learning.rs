@@ -0,0 +1,238 @@
+//! 學習曲線統計
+//!
+//! 平常打字就是練習：訂閱輸入事件（見 input_events 模組），每天記錄送出的組字次數、
+//! 沒有送出就清掉的次數（打錯字根後放棄）與組字花的時間，算出每日正確率與速度，
+//! 托盤的「學習曲線」面板畫成趨勢圖，也可以匯出 CSV。
+//! 統計依使用者存放在使用者資料目錄的 learning.json，跨次啟動保留。
+
+use crate::input_events::{InputEvent, InputObserver};
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 學習曲線統計檔名（放在使用者資料目錄，見 Profile::data_path）
+pub const LEARNING_FILE: &str = "learning.json";
+
+/// 單次組字最多計入的時間，打到一半離開座位不會把速度拉低
+const MAX_COMPOSITION_TIME: Duration = Duration::from_secs(10);
+
+/// 一天的練習紀錄
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailyRecord {
+    /// 送出的組字次數
+    pub commits: u32,
+    /// 送出的字數
+    pub chars: u32,
+    /// 沒有送出就清除的組字次數
+    pub misses: u32,
+    /// 組字花的時間（毫秒）
+    pub typing_ms: u64,
+}
+
+impl DailyRecord {
+    /// 正確率（0～1），沒有組字時為 None
+    pub fn accuracy(&self) -> Option<f64> {
+        let total = self.commits + self.misses;
+        (total > 0).then(|| self.commits as f64 / total as f64)
+    }
+
+    /// 每分鐘字數，沒有計時資料時為 None
+    pub fn chars_per_minute(&self) -> Option<f64> {
+        (self.typing_ms > 0).then(|| self.chars as f64 * 60_000.0 / self.typing_ms as f64)
+    }
+}
+
+/// 每日練習紀錄（日期 YYYY-MM-DD → 紀錄）
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LearningStats {
+    days: BTreeMap<String, DailyRecord>,
+    /// 上次儲存後是否有新紀錄
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl LearningStats {
+    /// 讀取學習曲線統計，檔案不存在或格式有誤時從空白開始
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<Self>(&content) {
+            Ok(stats) => {
+                info!("載入學習曲線統計: {:?}（{} 天）", path, stats.days.len());
+                stats
+            }
+            Err(e) => {
+                warn!("學習曲線統計 {:?} 格式有誤，重新開始統計: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// 寫入學習曲線統計（先寫暫存檔再改名）
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).with_context(|| format!("無法寫入學習曲線統計: {:?}", tmp_path))?;
+        fs::rename(&tmp_path, path).with_context(|| format!("無法寫入學習曲線統計: {:?}", path))?;
+        Ok(())
+    }
+
+    /// 記錄一次送出
+    pub fn record_commit(&mut self, date: &str, chars: usize, elapsed: Duration) {
+        let record = self.days.entry(date.to_string()).or_default();
+        record.commits = record.commits.saturating_add(1);
+        record.chars = record.chars.saturating_add(chars as u32);
+        record.typing_ms = record.typing_ms.saturating_add(elapsed.min(MAX_COMPOSITION_TIME).as_millis() as u64);
+        self.dirty = true;
+    }
+
+    /// 記錄一次沒有送出就清除的組字
+    pub fn record_miss(&mut self, date: &str) {
+        let record = self.days.entry(date.to_string()).or_default();
+        record.misses = record.misses.saturating_add(1);
+        self.dirty = true;
+    }
+
+    /// 最近 count 天有紀錄的日子（由舊到新）
+    pub fn recent(&self, count: usize) -> Vec<(&str, DailyRecord)> {
+        let skip = self.days.len().saturating_sub(count);
+        self.days.iter().skip(skip).map(|(date, record)| (date.as_str(), *record)).collect()
+    }
+
+    /// 匯出 CSV：日期、組字次數、字數、放棄次數、正確率（%）、每分鐘字數
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("date,commits,chars,misses,accuracy,chars_per_minute\n");
+        for (date, record) in &self.days {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{:.1},{:.1}",
+                date,
+                record.commits,
+                record.chars,
+                record.misses,
+                record.accuracy().unwrap_or(0.0) * 100.0,
+                record.chars_per_minute().unwrap_or(0.0)
+            );
+        }
+        csv
+    }
+
+    /// 上次儲存後是否有新紀錄
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// 標記為已儲存
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+}
+
+/// 今天的日期（本地時間，YYYY-MM-DD）
+fn today() -> String {
+    let time = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+    format!("{:04}-{:02}-{:02}", time.wYear, time.wMonth, time.wDay)
+}
+
+/// 訂閱輸入事件的學習曲線紀錄器
+#[derive(Default)]
+pub struct LearningTracker {
+    inner: Mutex<TrackerState>,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    stats: LearningStats,
+    /// 目前組字開始的時間（沒有在組字時為 None）
+    started: Option<Instant>,
+}
+
+impl LearningTracker {
+    /// 換成另一份統計（切換使用者後呼叫）
+    pub fn set_stats(&self, stats: LearningStats) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.stats = stats;
+        inner.started = None;
+    }
+
+    /// 目前統計的複本
+    pub fn stats(&self) -> LearningStats {
+        self.inner.lock().unwrap().stats.clone()
+    }
+
+    /// 統計有新紀錄時取出複本並標記為已儲存（呼叫端在鎖外寫檔）
+    pub fn take_snapshot(&self) -> Option<LearningStats> {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.stats.is_dirty() {
+            return None;
+        }
+        inner.stats.mark_saved();
+        Some(inner.stats.clone())
+    }
+
+    fn handle(&self, event: &InputEvent, date: impl FnOnce() -> String) {
+        let mut inner = self.inner.lock().unwrap();
+        match event {
+            InputEvent::CompositionStarted => inner.started = Some(Instant::now()),
+            InputEvent::CandidatesUpdated => {}
+            // 沒有組字就送出的（例如聯想詞、符號）不計入
+            InputEvent::Committed(text) => {
+                if let Some(started) = inner.started.take() {
+                    inner.stats.record_commit(&date(), text.chars().count(), started.elapsed());
+                }
+            }
+            InputEvent::Cleared => {
+                if inner.started.take().is_some() {
+                    inner.stats.record_miss(&date());
+                }
+            }
+        }
+    }
+}
+
+impl InputObserver for LearningTracker {
+    fn on_input_event(&self, event: &InputEvent) {
+        self.handle(event, today);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_records_daily_accuracy() {
+        let tracker = LearningTracker::default();
+        let date = || "2024-03-01".to_string();
+        tracker.handle(&InputEvent::CompositionStarted, date);
+        tracker.handle(&InputEvent::CandidatesUpdated, date);
+        tracker.handle(&InputEvent::Committed("輸入".to_string()), date);
+        tracker.handle(&InputEvent::CompositionStarted, date);
+        tracker.handle(&InputEvent::Cleared, date);
+        // 沒有組字的送出不計入
+        tracker.handle(&InputEvent::Committed("，".to_string()), date);
+
+        let stats = tracker.take_snapshot().unwrap();
+        assert!(tracker.take_snapshot().is_none());
+        let record = stats.recent(30)[0].1;
+        assert_eq!((record.commits, record.chars, record.misses), (1, 2, 1));
+        assert_eq!(record.accuracy(), Some(0.5));
+
+        let mut stats = LearningStats::default();
+        stats.record_commit("2024-03-02", 2, Duration::from_secs(3));
+        // 離開座位的那次只計入 MAX_COMPOSITION_TIME
+        stats.record_commit("2024-03-02", 1, Duration::from_secs(600));
+        assert_eq!(stats.recent(1)[0].1.typing_ms, 13_000);
+        assert_eq!(
+            stats.to_csv(),
+            "date,commits,chars,misses,accuracy,chars_per_minute\n2024-03-02,2,3,0,100.0,13.8\n"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! 學習曲線視窗
+//!
+//! 以折線圖畫出最近 CHART_DAYS 天（有打字的日子）的正確率與每分鐘字數，資料來自
+//! learning 模組；「匯出 CSV」把全部紀錄存成 CSV。每次開啟時重新讀取統計。
+
+use crate::learning::{DailyRecord, LearningTracker};
+use fltk::{
+    button::Button,
+    dialog,
+    draw,
+    enums::{Align, Color, Font},
+    frame::Frame,
+    prelude::*,
+    window::Window,
+};
+use tracing::info;
+use std::sync::{Arc, Mutex};
+
+/// 圖上最多畫的天數
+const CHART_DAYS: usize = 30;
+/// 圖表四周留給刻度與圖例的空間
+const CHART_MARGIN: i32 = 40;
+/// 正確率折線的顏色
+const ACCURACY_COLOR: Color = Color::DarkGreen;
+/// 速度折線的顏色
+const SPEED_COLOR: Color = Color::DarkBlue;
+
+/// 一條折線：顏色與每天的數值（0.0 ~ 1.0，沒有資料時為 None）
+type Series<'a> = (Color, &'a dyn Fn(&DailyRecord) -> Option<f64>);
+
+/// 學習曲線視窗
+pub struct LearningWindow {
+    window: Window,
+    chart: Frame,
+    summary: Frame,
+    /// 圖上要畫的紀錄（由舊到新），draw 回呼與 show 共用
+    days: Arc<Mutex<Vec<(String, DailyRecord)>>>,
+    tracker: Arc<LearningTracker>,
+}
+
+impl LearningWindow {
+    pub fn new(tracker: Arc<LearningTracker>) -> Self {
+        let window = Window::default().with_size(640, 420).with_label("學習曲線").center_screen();
+
+        let days: Arc<Mutex<Vec<(String, DailyRecord)>>> = Arc::new(Mutex::new(Vec::new()));
+        let mut chart = Frame::new(0, 0, 640, 340, "");
+        {
+            let days = days.clone();
+            chart.draw(move |frame| draw_chart(frame.x(), frame.y(), frame.w(), frame.h(), &days.lock().unwrap()));
+        }
+
+        let mut summary = Frame::new(10, 345, 620, 30, "");
+        summary.set_align(Align::Left | Align::Inside);
+        summary.set_label_size(14);
+        let mut export_button = Button::new(10, 380, 120, 30, "匯出 CSV");
+        window.end();
+
+        {
+            let tracker = tracker.clone();
+            export_button.set_callback(move |_| export_csv(&tracker));
+        }
+
+        Self { window, chart, summary, days, tracker }
+    }
+
+    /// 重新讀取統計後顯示視窗
+    pub fn show(&mut self) {
+        let stats = self.tracker.stats();
+        let recent: Vec<(String, DailyRecord)> =
+            stats.recent(CHART_DAYS).into_iter().map(|(date, record)| (date.to_string(), record)).collect();
+        let summary = match recent.last() {
+            Some((date, record)) => format!(
+                "最近一天（{}）：正確率 {:.1}%，每分鐘 {:.1} 字，共 {} 字",
+                date,
+                record.accuracy().unwrap_or(0.0) * 100.0,
+                record.chars_per_minute().unwrap_or(0.0),
+                record.chars
+            ),
+            None => "還沒有練習紀錄，在肥模式下打字後再來看看".to_string(),
+        };
+        *self.days.lock().unwrap() = recent;
+        self.summary.set_label(&summary);
+        self.chart.redraw();
+        self.window.show();
+    }
+}
+
+/// 畫出正確率（左軸 0～100%）與每分鐘字數（右軸 0～最大值）的折線圖
+fn draw_chart(x: i32, y: i32, w: i32, h: i32, days: &[(String, DailyRecord)]) {
+    draw::set_draw_color(Color::White);
+    draw::draw_rectf(x, y, w, h);
+
+    let (left, top) = (x + CHART_MARGIN, y + CHART_MARGIN / 2);
+    let (width, height) = (w - CHART_MARGIN * 2, h - CHART_MARGIN * 2);
+    draw::set_draw_color(Color::Dark3);
+    draw::draw_rect(left, top, width, height);
+    draw::set_font(Font::Helvetica, 12);
+    draw::draw_text2("100%", x, top - 6, CHART_MARGIN - 4, 12, Align::Right);
+    draw::draw_text2("0%", x, top + height - 6, CHART_MARGIN - 4, 12, Align::Right);
+
+    if days.is_empty() {
+        return;
+    }
+    let max_speed = days
+        .iter()
+        .filter_map(|(_, record)| record.chars_per_minute())
+        .fold(0.0_f64, f64::max)
+        .max(1.0);
+    draw::draw_text2(&format!("{:.0}", max_speed), left + width + 4, top - 6, CHART_MARGIN, 12, Align::Left);
+    draw::draw_text2("字/分", left + width + 4, top + 8, CHART_MARGIN, 12, Align::Left);
+
+    // 只有一天時畫在正中間
+    let step = if days.len() > 1 { width as f64 / (days.len() - 1) as f64 } else { 0.0 };
+    let point_x = |i: usize| if days.len() > 1 { left + (i as f64 * step) as i32 } else { left + width / 2 };
+    let point_y = |ratio: f64| top + height - (ratio.clamp(0.0, 1.0) * height as f64) as i32;
+
+    draw::set_line_style(draw::LineStyle::Solid, 2);
+    let series: [Series; 2] = [
+        (ACCURACY_COLOR, &|record| record.accuracy()),
+        (SPEED_COLOR, &|record| record.chars_per_minute().map(|speed| speed / max_speed)),
+    ];
+    for (color, value) in series {
+        draw::set_draw_color(color);
+        let points: Vec<(i32, i32)> = days
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (_, record))| value(record).map(|ratio| (point_x(i), point_y(ratio))))
+            .collect();
+        for pair in points.windows(2) {
+            draw::draw_line(pair[0].0, pair[0].1, pair[1].0, pair[1].1);
+        }
+        for &(px, py) in &points {
+            draw::draw_rectf(px - 2, py - 2, 5, 5);
+        }
+    }
+    draw::set_line_style(draw::LineStyle::Solid, 0);
+
+    // 日期刻度：頭尾兩天
+    draw::set_draw_color(Color::Black);
+    let (first, _) = &days[0];
+    let (last, _) = &days[days.len() - 1];
+    draw::draw_text2(first, left, top + height + 4, 80, 12, Align::Left);
+    if days.len() > 1 {
+        draw::draw_text2(last, left + width - 80, top + height + 4, 80, 12, Align::Right);
+    }
+
+    // 圖例
+    draw::set_draw_color(ACCURACY_COLOR);
+    draw::draw_text2("■ 正確率", left, top + height + 18, 100, 12, Align::Left);
+    draw::set_draw_color(SPEED_COLOR);
+    draw::draw_text2("■ 每分鐘字數", left + 100, top + height + 18, 120, 12, Align::Left);
+}
+
+/// 詢問檔名後把全部紀錄匯出成 CSV
+fn export_csv(tracker: &LearningTracker) {
+    let Some(path) = dialog::file_chooser("匯出學習曲線", "*.csv", "learning.csv", false) else {
+        return;
+    };
+    match std::fs::write(&path, tracker.stats().to_csv()) {
+        Ok(()) => info!("已匯出學習曲線: {}", path),
+        Err(e) => dialog::alert_default(&format!("無法匯出學習曲線到 {}：{}", path, e)),
+    }
+}
@@ -0,0 +1,33 @@
+//! 肥米輸入法 - 核心（字碼表與輸入處理）
+//!
+//! 字碼表解析、字典查詢與輸入處理器，不含鍵盤鉤子、GUI、托盤等 Windows 專用的部分。
+//! 輸入法本體（main.rs）與網頁版查碼元件（wasm/）都使用這個 lib，兩邊的查詢結果一致。
+//!
+//! 預設開啟的 app 功能帶入輸入法本體需要的相依套件；只用核心時以 `default-features = false` 關閉，
+//! 這時沒有 SQLite 字典後端（見 sqlite_disabled 模組）、讀不到剪貼簿，本地時間改用 UTC。
+
+pub mod associations;
+pub mod blocklist;
+pub mod candidate;
+pub mod chardefs;
+pub mod charset;
+pub mod dictionary;
+pub mod dictionary_cache;
+pub mod frequency;
+pub mod input_events;
+pub mod input_method;
+pub mod pinyi;
+pub mod rules;
+pub mod scheme;
+pub mod snippets;
+pub mod sorter;
+#[cfg(feature = "app")]
+pub mod sqlite_dictionary;
+#[cfg(not(feature = "app"))]
+#[path = "sqlite_disabled.rs"]
+pub mod sqlite_dictionary;
+pub mod symbols;
+pub mod time_macros;
+pub mod type_sentence;
+pub mod user_dictionary;
+pub mod validation;
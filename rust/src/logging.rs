@@ -0,0 +1,161 @@
+//! 日誌模組
+//!
+//! 使用 tracing：主控台的等級由 EnvFilter 控制，可以分模組設定，例如
+//! `info,uclliu::keyboard_hook=trace` 只開鍵盤鉤子的 trace、其他模組維持 info。
+//! 啟動時取 RUST_LOG（預設 debug），設定 log_filter 或托盤「日誌等級」可以在執行中更換（見 set_filter）。
+//! 另外把 info 以上的訊息寫到執行檔目錄下的 logs/uclliu.log，方便使用者回報問題時附上，
+//! 這一層不受主控台等級影響。每次啟動時把上一次的日誌改名為 uclliu.1.log 保留一份。
+//! 相依套件用 log 輸出的訊息也會轉進來。
+//! 鍵盤鉤子回呼過慢時，鉤子執行緒可以暫時只輸出 warn 以上的日誌（見 set_thread_quiet）。
+
+use anyhow::{anyhow, Context, Result};
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tracing::{Level, Metadata};
+use tracing_subscriber::filter::{filter_fn, EnvFilter, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::reload;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, Layer, Registry};
+
+/// 日誌目錄名稱（相對於執行檔目錄）
+pub const LOG_DIR: &str = "logs";
+/// 本次執行的日誌檔
+const LOG_FILE: &str = "uclliu.log";
+/// 上一次執行的日誌檔
+const PREVIOUS_LOG_FILE: &str = "uclliu.1.log";
+/// 寫入日誌檔的最低等級（debug 會記錄每個按鍵，不寫入檔案）
+/// 日誌檔會打包進回報包，打字的內容（字根、候選、送出的文字）一律只記在 debug 以下
+const FILE_LEVEL: LevelFilter = LevelFilter::INFO;
+/// 沒有設定 RUST_LOG 時的主控台等級（看得到鍵盤事件）
+const DEFAULT_FILTER: &str = "debug";
+
+/// 托盤「日誌等級」列出的常用設定（名稱, 過濾規則）
+pub const FILTER_PRESETS: &[(&str, &str)] = &[
+    ("info", "info"),
+    ("debug", "debug"),
+    ("鍵盤鉤子 trace（其他 info）", "info,uclliu::keyboard_hook=trace"),
+    ("輸入處理 trace（其他 info）", "info,uclliu::input_method=trace,uclliu::dictionary=trace"),
+];
+
+thread_local! {
+    /// 目前執行緒是否只輸出 warn 以上的日誌
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// 主控台過濾規則的重新載入控制
+struct ConsoleFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// 目前的過濾規則（診斷報告與托盤勾選用）
+    directives: Mutex<String>,
+}
+
+static CONSOLE_FILTER: OnceLock<ConsoleFilter> = OnceLock::new();
+
+fn is_quiet(metadata: &Metadata) -> bool {
+    *metadata.level() > Level::WARN && QUIET.with(|q| q.get())
+}
+
+/// 設定目前執行緒是否只輸出 warn 以上的日誌（鍵盤鉤子降級模式使用）
+pub fn set_thread_quiet(quiet: bool) {
+    QUIET.with(|q| q.set(quiet));
+}
+
+/// 解析過濾規則（EnvFilter 語法，例如 "info,uclliu::keyboard_hook=trace"）
+pub fn parse_filter(directives: &str) -> Result<EnvFilter> {
+    EnvFilter::builder()
+        .parse(directives.trim())
+        .with_context(|| format!("日誌過濾規則 '{}' 有誤", directives.trim()))
+}
+
+/// 初始化日誌
+pub fn init() {
+    let directives = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|value| parse_filter(value).is_ok())
+        .unwrap_or_else(|| DEFAULT_FILTER.to_string());
+    let filter = parse_filter(&directives).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let console = fmt::layer().with_target(true).with_filter(filter);
+    let file = open_log_file().map(|file| {
+        fmt::layer()
+            .with_ansi(false)
+            .with_writer(Mutex::new(file))
+            .with_filter(FILE_LEVEL)
+    });
+
+    let initialized = tracing_subscriber::registry()
+        .with(console)
+        .with(file)
+        .with(filter_fn(|metadata| !is_quiet(metadata)))
+        .try_init();
+    if initialized.is_ok() {
+        let _ = CONSOLE_FILTER.set(ConsoleFilter { handle, directives: Mutex::new(directives) });
+    }
+}
+
+/// 執行中更換主控台的過濾規則，日誌檔不受影響
+pub fn set_filter(directives: &str) -> Result<()> {
+    let filter = parse_filter(directives)?;
+    let console = CONSOLE_FILTER.get().ok_or_else(|| anyhow!("日誌尚未初始化"))?;
+    console.handle.reload(filter).context("無法更換日誌過濾規則")?;
+    *console.directives.lock().unwrap() = directives.trim().to_string();
+    tracing::info!("日誌過濾規則改為: {}", directives.trim());
+    Ok(())
+}
+
+/// 目前主控台的過濾規則（給診斷報告與托盤使用）
+pub fn current_filter() -> String {
+    match CONSOLE_FILTER.get() {
+        Some(console) => console.directives.lock().unwrap().clone(),
+        None => DEFAULT_FILTER.to_string(),
+    }
+}
+
+/// 日誌目錄（執行檔目錄下的 logs/）
+pub fn log_dir() -> Option<PathBuf> {
+    let exe_path = std::env::current_exe().ok()?;
+    Some(exe_path.parent()?.join(LOG_DIR))
+}
+
+/// 目前存在的日誌檔（本次與上一次）
+pub fn log_files() -> Vec<PathBuf> {
+    let Some(dir) = log_dir() else {
+        return Vec::new();
+    };
+    [LOG_FILE, PREVIOUS_LOG_FILE]
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// 開啟本次的日誌檔，並保留上一次的日誌；失敗時只輸出到主控台
+fn open_log_file() -> Option<File> {
+    let dir = log_dir()?;
+    fs::create_dir_all(&dir).ok()?;
+
+    let path = dir.join(LOG_FILE);
+    if path.exists() {
+        let _ = fs::rename(&path, dir.join(PREVIOUS_LOG_FILE));
+    }
+    File::create(&path).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filter_per_module() {
+        for (_, directives) in FILTER_PRESETS {
+            assert!(parse_filter(directives).is_ok(), "{}", directives);
+        }
+        let filter = parse_filter(" info,uclliu::keyboard_hook=trace ").unwrap();
+        assert_eq!(filter.max_level_hint(), Some(LevelFilter::TRACE));
+        assert!(parse_filter("info,uclliu::keyboard_hook=loud").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! 長文分段送出
+//!
+//! 把整段筆記一次貼進遊戲郵件常會失敗（超過輸入框一次能接受的長度，或被判定為洗頻）。
+//! 按 Ctrl+Alt+V 時改把剪貼簿的文字切成每段 long_paste_chunk_chars 字，
+//! 每隔 long_paste_delay_ms 毫秒送出一段（第一段也先等一次，讓使用者放開熱鍵），
+//! 進度顯示在「分段送出」視窗，可以中途取消；緊急放行也會取消。
+//!
+//! 這裡只負責切段與排時間，實際送出交給輸入模擬器（見 InputSimulator::flush_long_paste），
+//! 每段仍受送字限制與終端機相容策略約束。段落依字元叢集切開，不會拆開組合字元或 emoji 序列。
+
+use crate::candidate::clusters;
+use std::time::{Duration, Instant};
+
+/// 每段字數的預設值
+pub const DEFAULT_CHUNK_CHARS: usize = 100;
+/// 段間延遲的預設值
+pub const DEFAULT_DELAY: Duration = Duration::from_millis(1000);
+
+/// 進行中的分段送出
+#[derive(Debug, Clone)]
+pub struct LongPaste {
+    /// 還沒送出的文字
+    remaining: String,
+    /// 全部字數
+    total: usize,
+    /// 已送出的字數
+    sent: usize,
+    /// 每段字數
+    chunk_chars: usize,
+    /// 段間延遲
+    delay: Duration,
+    /// 下一段可以送出的時間
+    next_at: Instant,
+}
+
+impl LongPaste {
+    /// 從 now 起算，等 delay 後送出第一段
+    pub fn new(text: &str, chunk_chars: usize, delay: Duration, now: Instant) -> Self {
+        Self {
+            remaining: text.to_string(),
+            total: text.chars().count(),
+            sent: 0,
+            chunk_chars: chunk_chars.max(1),
+            delay,
+            next_at: now + delay,
+        }
+    }
+
+    /// 時間到了就取出下一段（最多 chunk_chars 字，至少一個字元叢集），還沒到或已送完時回傳 None
+    pub fn next_chunk(&mut self, now: Instant) -> Option<String> {
+        if self.remaining.is_empty() || now < self.next_at {
+            return None;
+        }
+        let (mut end, mut count) = (0, 0);
+        for cluster in clusters(&self.remaining) {
+            let chars = cluster.chars().count();
+            if count > 0 && count + chars > self.chunk_chars {
+                break;
+            }
+            end += cluster.len();
+            count += chars;
+        }
+        self.sent += count;
+        self.next_at = now + self.delay;
+        Some(self.remaining.drain(..end).collect())
+    }
+
+    /// 是否已全部送出
+    pub fn is_done(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    /// 已送出的字數與全部字數
+    pub fn progress(&self) -> (usize, usize) {
+        (self.sent, self.total)
+    }
+
+    /// 還沒送出的字數
+    pub fn remaining_chars(&self) -> usize {
+        self.total - self.sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_wait_for_delay() {
+        let start = Instant::now();
+        let delay = Duration::from_millis(500);
+        let mut paste = LongPaste::new("一二三四五👨‍👩‍👧", 2, delay, start);
+        // 第一段也要等一次延遲
+        assert_eq!(paste.next_chunk(start), None);
+        assert_eq!(paste.next_chunk(start + delay).as_deref(), Some("一二"));
+        assert_eq!(paste.next_chunk(start + delay), None);
+        assert_eq!(paste.next_chunk(start + delay * 2).as_deref(), Some("三四"));
+        assert_eq!(paste.progress(), (4, 10));
+        assert_eq!(paste.next_chunk(start + delay * 3).as_deref(), Some("五"));
+        // emoji ZWJ 序列超過每段字數也整個送出
+        assert_eq!(paste.next_chunk(start + delay * 4).as_deref(), Some("👨‍👩‍👧"));
+        assert!(paste.is_done());
+        assert_eq!(paste.remaining_chars(), 0);
+    }
+}
@@ -0,0 +1,78 @@
+//! 長文分段送出的進度視窗
+//!
+//! 分段送出期間（見 long_paste 模組）顯示已送出的字數與進度條，按「取消」丟棄還沒送出的部分。
+//! 文字是送到前景程式的，所以視窗顯示時不能搶走焦點（見 overlay::keep_on_top_without_focus）；
+//! 送完或取消後自動關閉。
+
+use crate::input_simulator::InputSimulator;
+use crate::overlay;
+use fltk::{app, button::Button, enums::Align, frame::Frame, misc::Progress, prelude::*, window::Window};
+use tracing::info;
+use std::sync::{Arc, Mutex};
+use windows::Win32::Foundation::HWND;
+
+/// 分段送出進度視窗
+pub struct LongPasteWindow {
+    window: Window,
+    status: Frame,
+    progress: Progress,
+    simulator: Arc<Mutex<InputSimulator>>,
+}
+
+impl LongPasteWindow {
+    pub fn new(simulator: Arc<Mutex<InputSimulator>>) -> Self {
+        let (screen_w, _) = app::screen_size();
+        let mut window = Window::new(screen_w as i32 - 330, 40, 320, 100, "分段送出");
+        // 工具視窗：不出現在工作列，顯示時不取得焦點
+        window.set_override();
+
+        let mut status = Frame::new(10, 8, 300, 25, "");
+        status.set_align(Align::Left | Align::Inside);
+
+        let mut progress = Progress::new(10, 36, 300, 20, "");
+        progress.set_minimum(0.0);
+
+        let mut cancel_button = Button::new(210, 64, 100, 28, "取消");
+        window.end();
+
+        {
+            let simulator = simulator.clone();
+            let mut window = window.clone();
+            cancel_button.set_callback(move |_| {
+                let dropped = simulator.lock().unwrap().cancel_long_paste();
+                info!("取消分段送出，丟棄 {} 字", dropped);
+                window.hide();
+            });
+        }
+
+        Self { window, status, progress, simulator }
+    }
+
+    pub fn show(&mut self) {
+        self.refresh();
+        if !self.window.shown() {
+            self.window.show();
+            app::flush();
+            overlay::keep_on_top_without_focus(HWND(self.window.raw_handle() as isize));
+        }
+    }
+
+    /// 更新進度；送完或已取消時關閉視窗（鍵盤鉤子正在送字時不等鎖，下次再更新）
+    pub fn refresh(&mut self) {
+        let Ok(simulator) = self.simulator.try_lock() else {
+            return;
+        };
+        match simulator.long_paste_progress() {
+            Some((sent, total)) => {
+                self.status.set_label(&format!("已送出 {} / {} 字", sent, total));
+                self.progress.set_maximum(total as f64);
+                self.progress.set_value(sent as f64);
+            }
+            None => {
+                if self.window.shown() {
+                    self.window.hide();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! 字根查詢視窗
+//!
+//! 輸入字根列出候選字，輸入中文則反查字根；每個候選都附上 Unicode 碼位、區塊與
+//! （可選）字元名稱，選取後可複製碼位或文字本身。
+//! 查碼熱鍵（Ctrl+Alt+L）以剪貼簿的文字、選取反查熱鍵（Ctrl+Alt+K）以選取的文字開啟這個視窗，逐字列出字根（見 show_query）。
+
+use crate::chardefs::is_wildcard;
+use crate::dictionary::Dictionary;
+use crate::input_method::InputMethodProcessor;
+use crate::unicode_info::{self, CharInfo};
+use fltk::{
+    browser::HoldBrowser,
+    button::{Button, CheckButton},
+    enums::CallbackTrigger,
+    input::Input,
+    prelude::*,
+    window::Window,
+};
+use tracing::{debug, warn};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// 查詢結果的一列
+#[derive(Debug, Clone, PartialEq)]
+pub struct LookupRow {
+    /// 字根
+    pub code: String,
+    /// 候選文字
+    pub text: String,
+    /// 候選文字中每個字元的 Unicode 資訊
+    pub infos: Vec<CharInfo>,
+}
+
+impl LookupRow {
+    fn new(code: &str, text: &str, with_name: bool) -> Self {
+        Self {
+            code: code.to_string(),
+            text: text.to_string(),
+            infos: unicode_info::describe(text, with_name),
+        }
+    }
+
+    /// 結果列表中的一行（以 Tab 分欄：文字、字根、碼位、名稱或區塊）
+    fn display_line(&self) -> String {
+        let code_points: Vec<&str> = self.infos.iter().map(|i| i.code_point.as_str()).collect();
+        let details: Vec<&str> = self
+            .infos
+            .iter()
+            .map(|i| i.name.as_deref().unwrap_or(i.block))
+            .collect();
+        // 查不到字根的字也列出來，字根欄以 - 表示
+        let code = if self.code.is_empty() { "-" } else { self.code.as_str() };
+        format!("{}\t{}\t{}\t{}", self.text, code, code_points.join(" "), details.join(" / "))
+    }
+}
+
+/// 查詢字典：英數字串視為字根，列出候選（可用 * 與 ? 萬用字元）；其他文字視為要反查的字，列出所有字根
+/// 整段文字反查不到時（例如從剪貼簿貼上的一句話）改為逐字反查，查不到的字以空字根列出
+pub fn lookup_rows(dictionary: &Dictionary, query: &str, with_name: bool) -> Vec<LookupRow> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    if is_wildcard(query) {
+        // 萬用字元查詢：每列標出實際的字根
+        let pattern = query.to_ascii_lowercase();
+        dictionary
+            .lookup_wildcard(&pattern)
+            .into_iter()
+            .flat_map(|(code, chars)| chars.into_iter().map(move |text| LookupRow::new(&code, &text, with_name)))
+            .collect()
+    } else if query.is_ascii() {
+        let code = query.to_ascii_lowercase();
+        dictionary
+            .lookup(&code)
+            .map(|chars| chars.iter().map(|text| LookupRow::new(&code, text, with_name)).collect())
+            .unwrap_or_default()
+    } else {
+        let rows: Vec<LookupRow> = dictionary
+            .codes_for(query)
+            .into_iter()
+            .map(|code| LookupRow::new(&code, query, with_name))
+            .collect();
+        if !rows.is_empty() || query.chars().nth(1).is_none() {
+            return rows;
+        }
+        let mut rows = Vec::new();
+        let mut seen = Vec::new();
+        for ch in query.chars().filter(|ch| !ch.is_whitespace() && !ch.is_ascii()) {
+            if seen.contains(&ch) {
+                continue;
+            }
+            seen.push(ch);
+            let text = ch.to_string();
+            let codes = dictionary.codes_for(&text);
+            if codes.is_empty() {
+                rows.push(LookupRow::new("", &text, with_name));
+            }
+            rows.extend(codes.iter().map(|code| LookupRow::new(code, &text, with_name)));
+        }
+        rows
+    }
+}
+
+/// 字根查詢視窗
+pub struct LookupWindow {
+    window: Window,
+    input: Input,
+}
+
+impl LookupWindow {
+    /// 建立查詢視窗（with_name 為 Unicode 名稱勾選框的預設值）
+    pub fn new(processor: Arc<Mutex<InputMethodProcessor>>, with_name: bool) -> Self {
+        let window = Window::default().with_size(520, 360).with_label("字根查詢").center_screen();
+
+        let mut input = Input::new(10, 10, 330, 30, "");
+        input.set_tooltip("輸入字根查候選字（可用 * 代表任意字根，例如 a*d），或輸入中文反查字根");
+        input.set_trigger(CallbackTrigger::Changed);
+
+        let mut name_check = CheckButton::new(350, 10, 160, 30, "顯示 Unicode 名稱");
+        name_check.set_value(with_name);
+
+        let mut results = HoldBrowser::new(10, 50, 500, 260, "");
+        results.set_column_char('\t');
+        // 列表預設把行首的 @ 當成格式碼，改用不會出現在文字中的字元，@ 開頭的顏文字才能原樣顯示
+        results.set_format_char('\u{1}');
+        results.set_column_widths(&[60, 70, 140, 220]);
+        results.set_text_size(16);
+
+        let mut copy_code_point = Button::new(10, 320, 120, 30, "複製碼位");
+        let mut copy_text = Button::new(140, 320, 120, 30, "複製文字");
+        window.end();
+
+        let rows: Rc<RefCell<Vec<LookupRow>>> = Rc::new(RefCell::new(Vec::new()));
+
+        // 重新查詢並更新結果列表
+        let refresh = {
+            let rows = rows.clone();
+            let input = input.clone();
+            let name_check = name_check.clone();
+            let results = results.clone();
+            Rc::new(move || {
+                let query = input.value();
+                let found = match processor.lock() {
+                    Ok(processor) => lookup_rows(processor.dictionary(), &query, name_check.value()),
+                    Err(_) => Vec::new(),
+                };
+                debug!("查詢視窗: '{}' 找到 {} 筆", query, found.len());
+
+                // fltk 元件是共享的控制代碼，複製後操作的仍是同一個列表
+                let mut results = results.clone();
+                results.clear();
+                for row in &found {
+                    results.add(&row.display_line());
+                }
+                if !found.is_empty() {
+                    results.select(1);
+                }
+                *rows.borrow_mut() = found;
+            })
+        };
+
+        let on_input = refresh.clone();
+        input.set_callback(move |_| on_input());
+        let on_check = refresh;
+        name_check.set_callback(move |_| on_check());
+
+        // 瀏覽器的行號從 1 開始，0 表示沒有選取
+        let selected_row = {
+            let rows = rows.clone();
+            let results = results.clone();
+            move || {
+                let line = results.value();
+                (line > 0).then(|| rows.borrow().get(line as usize - 1).cloned()).flatten()
+            }
+        };
+
+        let selected = selected_row.clone();
+        copy_code_point.set_callback(move |_| {
+            if let Some(row) = selected() {
+                copy_to_clipboard(&unicode_info::code_points(&row.text));
+            }
+        });
+        copy_text.set_callback(move |_| {
+            if let Some(row) = selected_row() {
+                copy_to_clipboard(&row.text);
+            }
+        });
+
+        Self { window, input }
+    }
+
+    /// 顯示視窗並把焦點放在輸入框
+    pub fn show(&mut self) {
+        self.window.show();
+        let _ = self.input.take_focus();
+    }
+
+    /// 以指定的文字查詢並顯示視窗（查碼熱鍵）
+    pub fn show_query(&mut self, query: &str) {
+        self.input.set_value(query);
+        // 以程式設定的值不會觸發輸入框的回呼，手動觸發一次重新查詢
+        self.input.do_callback();
+        self.show();
+    }
+}
+
+fn copy_to_clipboard(text: &str) {
+    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+        Ok(()) => debug!("已複製到剪貼簿: {}", text),
+        Err(e) => warn!("複製到剪貼簿失敗: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_dictionary() -> Dictionary {
+        Dictionary::from_json(r#"{ "chardefs": { "a": ["一", "乙"], "ab": ["一"], "x": ["𠀀"] } }"#).unwrap()
+    }
+
+    #[test]
+    fn test_lookup_by_code() {
+        let dictionary = create_test_dictionary();
+        let rows = lookup_rows(&dictionary, " A ", false);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].text, "一");
+        assert_eq!(rows[0].display_line(), "一\ta\tU+4E00\t中日韓統一表意文字");
+
+        let rows = lookup_rows(&dictionary, "x", true);
+        assert_eq!(rows[0].display_line(), "𠀀\tx\tU+20000\tCJK UNIFIED IDEOGRAPH-20000");
+        assert!(lookup_rows(&dictionary, "zz", true).is_empty());
+    }
+
+    #[test]
+    fn test_reverse_lookup() {
+        let dictionary = create_test_dictionary();
+        let codes: Vec<String> = lookup_rows(&dictionary, "一", false).into_iter().map(|r| r.code).collect();
+        assert_eq!(codes, vec!["a", "ab"]);
+
+        // 整句反查不到時逐字列出，查不到的字字根為空
+        let rows = lookup_rows(&dictionary, "乙 一囧乙", false);
+        let found: Vec<(&str, &str)> = rows.iter().map(|r| (r.text.as_str(), r.code.as_str())).collect();
+        assert_eq!(found, vec![("乙", "a"), ("一", "a"), ("一", "ab"), ("囧", "")]);
+        assert!(rows[3].display_line().starts_with("囧\t-\t"));
+    }
+}
@@ -0,0 +1,198 @@
+//! 輸入法巨集
+//!
+//! 把「切換輸入窗口 → 打固定句 → 送出 → 切回」這類操作錄成巨集，之後一鍵重播。
+//! 錄的是輸入法層級的動作（切換窗口、送出的文字），不是原始按鍵，重播時不受
+//! 當時的英/肥模式或字根狀態影響。
+//!
+//! - `Ctrl+Alt+R` 開始/停止錄製，停止後狀態窗詢問名稱與要綁定的熱鍵
+//! - `Ctrl+Alt+1`～`Ctrl+Alt+9` 重播綁定的巨集
+//!
+//! 巨集依使用者存放在使用者資料目錄的 macros.json，可以手動編輯：
+//!
+//! ```json
+//! [
+//!   { "name": "打招呼", "slot": 1, "actions": [
+//!       "toggle_window", { "send_text": "大家好" }, "toggle_window"
+//!   ] }
+//! ]
+//! ```
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// 巨集檔名（放在使用者資料目錄，見 Profile::data_path）
+pub const MACROS_FILE: &str = "macros.json";
+
+/// 可綁定的熱鍵數字（Ctrl+Alt+0 已是展示熱鍵的還原版面）
+pub const MACRO_SLOTS: std::ops::RangeInclusive<u8> = 1..=9;
+
+/// 重播時每個動作之間的間隔，讓窗口切換與送字有時間完成
+const MACRO_STEP_DELAY: Duration = Duration::from_millis(100);
+
+/// 巨集中的一個動作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacroAction {
+    /// 切換遊戲模式窗口顯示/隱藏
+    ToggleWindow,
+    /// 把文字送到前景視窗
+    SendText(String),
+}
+
+/// 一個具名的巨集
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Macro {
+    pub name: String,
+    /// 綁定的熱鍵 Ctrl+Alt+數字，None 表示不綁定
+    #[serde(default)]
+    pub slot: Option<u8>,
+    pub actions: Vec<MacroAction>,
+}
+
+/// 目前使用者的巨集與錄製狀態
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MacroBook {
+    macros: Vec<Macro>,
+    /// 錄製中的動作（None 表示沒有在錄製）
+    #[serde(skip)]
+    recording: Option<Vec<MacroAction>>,
+    /// 上次儲存後是否有修改
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl MacroBook {
+    /// 讀取巨集，檔案不存在或格式有誤時視為沒有巨集
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<Self>(&content) {
+            Ok(book) => {
+                info!("載入巨集: {:?}（{} 個）", path, book.macros.len());
+                book
+            }
+            Err(e) => {
+                warn!("巨集檔 {:?} 格式有誤，暫不載入: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// 寫入巨集（先寫暫存檔再改名，中斷時不會弄壞原本的檔案）
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, content).with_context(|| format!("無法寫入巨集: {:?}", tmp_path))?;
+        fs::rename(&tmp_path, path).with_context(|| format!("無法寫入巨集: {:?}", path))?;
+        Ok(())
+    }
+
+    /// 開始錄製（已在錄製時重新開始）
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Vec::new());
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// 錄製中時記下一個動作
+    pub fn record(&mut self, action: MacroAction) {
+        if let Some(actions) = &mut self.recording {
+            actions.push(action);
+        }
+    }
+
+    /// 停止錄製並取出錄到的動作（沒有在錄製時為 None）
+    pub fn stop_recording(&mut self) -> Option<Vec<MacroAction>> {
+        self.recording.take()
+    }
+
+    /// 新增巨集：同名的巨集會被取代，熱鍵被其他巨集佔用時改綁到新的巨集
+    pub fn insert(&mut self, new_macro: Macro) {
+        self.macros.retain(|m| m.name != new_macro.name);
+        if new_macro.slot.is_some() {
+            for m in self.macros.iter_mut().filter(|m| m.slot == new_macro.slot) {
+                m.slot = None;
+            }
+        }
+        self.macros.push(new_macro);
+        self.dirty = true;
+    }
+
+    /// 綁定在 Ctrl+Alt+slot 的巨集
+    pub fn for_slot(&self, slot: u8) -> Option<&Macro> {
+        self.macros.iter().find(|m| m.slot == Some(slot))
+    }
+
+    /// 第一個還沒綁定巨集的熱鍵數字
+    pub fn free_slot(&self) -> Option<u8> {
+        MACRO_SLOTS.into_iter().find(|&slot| self.for_slot(slot).is_none())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+}
+
+/// 在背景執行緒依序執行巨集的動作（不佔用鍵盤鉤子執行緒）
+pub fn play<F>(name: &str, actions: Vec<MacroAction>, mut run: F)
+where
+    F: FnMut(&MacroAction) + Send + 'static,
+{
+    info!("重播巨集 '{}'（{} 個動作）", name, actions.len());
+    let spawned = thread::Builder::new().name("macro".to_string()).spawn(move || {
+        for (i, action) in actions.iter().enumerate() {
+            if i > 0 {
+                thread::sleep(MACRO_STEP_DELAY);
+            }
+            run(action);
+        }
+    });
+    if let Err(e) = spawned {
+        warn!("無法啟動巨集執行緒: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_insert() {
+        let mut book = MacroBook::default();
+        book.record(MacroAction::ToggleWindow);
+        assert!(!book.is_recording());
+
+        book.start_recording();
+        book.record(MacroAction::ToggleWindow);
+        book.record(MacroAction::SendText("大家好".to_string()));
+        let actions = book.stop_recording().unwrap();
+        assert_eq!(actions.len(), 2);
+        assert_eq!(book.stop_recording(), None);
+
+        book.insert(Macro { name: "打招呼".to_string(), slot: Some(1), actions: actions.clone() });
+        book.insert(Macro { name: "道別".to_string(), slot: Some(1), actions });
+        assert!(book.is_dirty());
+        assert_eq!(book.for_slot(1).unwrap().name, "道別");
+        assert_eq!(book.free_slot(), Some(2));
+
+        // 存檔格式可以手動編輯
+        let json = serde_json::to_string(&book).unwrap();
+        assert!(json.contains(r#"["toggle_window",{"send_text":"大家好"}]"#));
+        let loaded: MacroBook = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.for_slot(1).unwrap().name, "道別");
+        assert!(!loaded.is_dirty());
+    }
+}
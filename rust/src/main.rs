@@ -7,31 +7,128 @@
 //! 4. 系統托盤圖示
 
 mod keyboard_hook;
-mod dictionary;
 mod input_simulator;
-mod input_method;
 mod tray;
 mod config;
 mod gui_window;
+mod gui_backend;
 mod game_input_test;
+mod cli;
+mod obs_output;
+mod profile;
+mod icon;
+mod archive;
+mod logging;
+mod report;
+mod hook_timing;
+mod unicode_info;
+mod lookup_window;
+mod blocklist_window;
+mod overlay;
+mod appbar;
+mod presentation;
+mod announcer;
+mod export;
+mod macros;
+mod keyboard_layout;
+mod keyboard_window;
+mod mode;
+mod send_limit;
+mod selection;
+mod backend_health;
+mod long_paste;
+mod long_paste_window;
+mod release_gate;
+mod dictionary_update;
+mod cloud_lookup;
+mod system_layout;
+mod help;
+mod help_window;
+mod mode_indicator;
+mod learning;
+mod learning_window;
+mod backup;
+#[cfg(test)]
+mod key_source;
+mod key_trace;
+mod key_trace_window;
+mod schedule;
+mod inspector;
+mod inspector_window;
+mod symbol_panel;
+mod mini_float;
+mod candidate_window;
+mod mode_badge;
+
+// 字碼表與輸入處理核心在 lib（src/lib.rs），和網頁版查碼元件共用
+use uclliu::{
+    blocklist, candidate, chardefs, charset, dictionary, frequency, input_events, input_method, rules, scheme, snippets,
+    sorter, symbols, time_macros, type_sentence, user_dictionary, validation,
+};
 
 use anyhow::Result;
-use log::{info, error, debug};
+use tracing::{info, error, debug, warn};
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
 
-use dictionary::Dictionary;
+use dictionary::{Dictionary, DictionaryBackend, MergePolicy};
 use keyboard_hook::KeyboardHook;
-use input_simulator::InputSimulator;
-use input_method::InputMethodProcessor;
+use input_simulator::{InputSimulator, SendMode, TerminalMode};
+use release_gate::HeldKeys;
+use announcer::{AnnounceMode, Announcer, InvalidKeyFeedback};
+use mode_indicator::{IndicatorTarget, ModeIndicator};
+use input_method::{InputMethodProcessor, ShiftLetter};
 use tray::TrayIcon;
+use gui_backend::GuiCommand;
 use gui_window::GuiWindowManager;
+use inspector::Inspector;
+use appbar::DockEdge;
+use mini_float::MiniFloatPosition;
+use candidate_window::CandidateWindowPosition;
+use presentation::Presentation;
+use mode_badge::BadgePosition;
+use sorter::TableOrder;
+use hook_timing::HookTiming;
+use scheme::InputScheme;
+use cli::CliArgs;
+use profile::Profile;
+use schedule::Schedule;
+use symbols::SymbolTable;
+use backup::BackupPolicy;
+use user_dictionary::USER_DICTIONARY_FILE;
+use snippets::SNIPPETS_FILE;
+use frequency::{FrequencyStats, FREQUENCY_FILE};
+use blocklist::{Blocklist, BLOCKLIST_FILE};
+use macros::{MacroBook, MACROS_FILE};
+use learning::{LearningStats, LearningTracker, LEARNING_FILE};
+use key_trace::{KeyTrace, DEFAULT_TRACE_LEN};
+use input_events::InputObserver;
+use mode::{InputMode, ModeState};
+use charset::{Charset, CharsetFilter};
+use dictionary_update::{MainDictionary, UpdateSource};
+use cloud_lookup::CloudLookup;
+
+/// 啟動時載入字典的設定：主字碼表與疊在上面的額外層、emoji 短碼表
+struct DictionarySetup<'a> {
+    /// 取代方案字碼表的檔案（設定 dictionary_path 或 --dict），None 表示使用方案的字碼表
+    dictionary_path: Option<&'a std::path::Path>,
+    strict: bool,
+    backend: DictionaryBackend,
+    /// 額外字碼表層（設定 dictionary_layers）
+    layers: &'a [String],
+    /// emoji 短碼表（設定 emoji_layer）
+    emoji_layer: &'a str,
+}
 
 /// 應用程式狀態
 pub struct AppState {
     dictionary: Arc<Mutex<Dictionary>>,
     input_simulator: Arc<Mutex<InputSimulator>>,
     input_processor: Arc<Mutex<InputMethodProcessor>>,
+    /// 鍵盤鉤子放行的按鍵計數（與輸入處理器共用，送出後有變化就不能撤銷）
+    passed_keys: Arc<AtomicU64>,
     gui_window_manager: Arc<Mutex<GuiWindowManager>>,
     /// 待貼上的文字（由鍵盤鉤子產生，由主迴圈送出）
     pending_paste_text: Arc<Mutex<Option<String>>>,
@@ -39,15 +136,86 @@ pub struct AppState {
     gui_visible: Arc<AtomicBool>,
     /// 遊戲模式窗口目前是否有焦點
     gui_has_focus: Arc<AtomicBool>,
-    is_ucl_mode: Arc<Mutex<bool>>,  // 肥/英模式
-    is_half_mode: Arc<Mutex<bool>>, // 半/全模式
+    /// 英/肥模式（鍵盤鉤子、托盤、GUI 共用，透過 get_mode/set_mode 讀寫）
+    mode: Arc<ModeState>,
+    /// 模式浮標被點擊、等待鍵盤鉤子執行緒切換英/肥模式
+    mode_toggle_request: Arc<AtomicBool>,
+    /// 是否允許 Shift+Space 切換全形/半形（設定 enable_half_full，目前的模式記在輸入處理器）
+    enable_half_full: bool,
+    /// 肥模式下按 Shift+字母的行為（設定 shift_letter）
+    shift_letter: ShiftLetter,
+    /// 鍵盤鉤子回報的實體按鍵狀態（抬手才上屏用，送字佇列也持有一份）
+    held_keys: Arc<HeldKeys>,
     should_quit: Arc<AtomicBool>,   // 退出標誌
     gui_needs_update: Arc<AtomicBool>, // GUI 需要更新標誌
+    /// 緊急放行後尚未完成的狀態清空（輸入處理器被鎖住時由鉤子執行緒稍後重試）
+    emergency_reset: Arc<AtomicBool>,
+    /// 鍵盤鉤子回呼耗時統計（托盤統計面板讀取）
+    hook_timing: Arc<HookTiming>,
+    /// 目前的使用者設定檔（托盤可切換）
+    profile: Arc<Mutex<Profile>>,
+    /// 送命令給 GUI 執行緒（鍵盤鉤子不直接操作 GUI）
+    gui_commands: Sender<GuiCommand>,
+    /// 候選頁數提示（嗶聲或語音）
+    announcer: Arc<Announcer>,
+    /// 按了無效的鍵時的提示音與狀態窗閃爍（設定 play_sound_enable、invalid_key_flash）
+    invalid_key_feedback: InvalidKeyFeedback,
+    /// 以鍵盤 LED 或序列埠顯示目前模式
+    mode_indicator: ModeIndicator,
+    /// 目前使用者的巨集與錄製狀態（GUI 執行緒命名新巨集時也會修改）
+    macros: Arc<Mutex<MacroBook>>,
+    /// 肥模式下按住 Ctrl 時放行的組合鍵（虛擬鍵碼，設定 ctrl_passthrough），None 表示一律放行
+    ctrl_passthrough: Option<Vec<u32>>,
+    /// 肥模式下也原樣放行的按鍵字元（設定 passthrough_keys）
+    passthrough_keys: Vec<char>,
+    /// 組字時翻頁候選的按鍵（上一頁, 下一頁的虛擬鍵碼，設定 page_keys）
+    page_keys: Vec<(u32, u32)>,
+    /// 重送最近一次送出文字的按鍵（虛擬鍵碼，設定 repeat_output_key），None 表示不使用
+    repeat_output_key: Option<u32>,
+    /// 撤銷送出時待送出的退格數（由鍵盤鉤子執行緒的訊息循環送出）
+    pending_backspaces: Arc<AtomicUsize>,
+    /// 最近按鍵的處理紀錄（按鍵回放，訂閱輸入處理器的事件）
+    key_trace: Arc<KeyTrace>,
+    /// 其他軟體以 VK_PACKET 送出的字元是否加入遊戲模式窗口的累積文字（設定 accumulate_packet_input）
+    accumulate_packet_input: bool,
+    /// 自動備份使用者資料的間隔與份數（設定 backup_interval_minutes、backup_keep），None 表示不備份
+    backup: Option<BackupPolicy>,
+    /// 依時段自動切換模式與使用者（設定 schedule）
+    schedule: Schedule,
+    /// 每日練習正確率與速度（訂閱輸入處理器的事件）
+    learning: Arc<LearningTracker>,
+    /// 主字碼表的位置與載入方式（線上更新後依此重新載入）
+    main_dictionary: MainDictionary,
+    /// 線上更新字碼表的來源（沒有設定網址時為 None）
+    dictionary_update: Option<UpdateSource>,
+    /// 本地查不到的字根改查線上字碼資料庫（設定 cloud_lookup_url），None 表示不查詢
+    cloud_lookup: Option<Arc<CloudLookup>>,
 }
 
 impl AppState {
-    fn new() -> Result<Self> {
-        let dictionary = Arc::new(Mutex::new(Dictionary::load()?));
+    /// 創建應用狀態，同時回傳 GUI 命令通道的接收端（交給 GUI 執行緒）
+    fn new(
+        scheme: InputScheme,
+        scheme_dir: &std::path::Path,
+        profile: Profile,
+        setup: DictionarySetup,
+    ) -> Result<(Self, Receiver<GuiCommand>)> {
+        let main_dictionary = MainDictionary {
+            scheme_dir: scheme_dir.to_path_buf(),
+            dictionary_path: setup.dictionary_path.map(std::path::Path::to_path_buf),
+            strict: setup.strict,
+            backend: setup.backend,
+        };
+        backup::restore_corrupt(&profile);
+        let mut dictionary = main_dictionary.load(&scheme)?;
+        // 字碼表無法解析、改用上次載入成功的快取版本時，啟動後提示使用者
+        let restored_dictionary = dictionary.restored_from_cache.then(|| main_dictionary.path(&scheme));
+        dictionary.load_layers(scheme_dir, setup.layers, setup.strict);
+        dictionary.load_emoji_layer(scheme_dir, setup.emoji_layer, setup.strict);
+        dictionary.set_user(load_user_dictionary(&profile));
+        dictionary.set_user_path(Some(profile.data_path(USER_DICTIONARY_FILE)));
+        dictionary.set_snippets(load_snippets(&profile));
+        let dictionary = Arc::new(Mutex::new(dictionary));
         let input_simulator = Arc::new(Mutex::new(InputSimulator::new()?));
         let pending_paste_text = Arc::new(Mutex::new(None));
         let gui_visible = Arc::new(AtomicBool::new(false));
@@ -55,42 +223,393 @@ impl AppState {
         
         // 創建輸入法處理器
         let dict_for_processor = dictionary.lock().unwrap();
-        let processor = InputMethodProcessor::new((*dict_for_processor).clone());
+        let mut processor = InputMethodProcessor::with_scheme((*dict_for_processor).clone(), scheme);
+        processor.set_frequency(FrequencyStats::load(&profile.data_path(FREQUENCY_FILE)));
+        processor.set_blocklist(Blocklist::load(&profile.data_path(BLOCKLIST_FILE)));
+        let learning = Arc::new(LearningTracker::default());
+        learning.set_stats(LearningStats::load(&profile.data_path(LEARNING_FILE)));
+        processor.subscribe(&(learning.clone() as Arc<dyn InputObserver>));
+        let key_trace = Arc::new(KeyTrace::new(DEFAULT_TRACE_LEN));
+        processor.subscribe(&(key_trace.clone() as Arc<dyn InputObserver>));
         drop(dict_for_processor);
+        let passed_keys = processor.passed_keys();
         
         let input_processor = Arc::new(Mutex::new(processor));
         
         // 創建 GUI 需要更新標誌
         let gui_needs_update = Arc::new(AtomicBool::new(false));
         
+        // 創建 GUI 命令通道
+        let (gui_commands, gui_command_rx) = mpsc::channel();
+        if let Some(path) = restored_dictionary {
+            let text = format!("字碼表 {:?} 無法解析（可能被其他程式或中斷的下載改壞），已改用上次載入成功的版本。\n請檢查或重新下載字碼表。", path);
+            let _ = gui_commands.send(GuiCommand::ShowMessage("字碼表".to_string(), text));
+        }
+        
+        let macros = Arc::new(Mutex::new(MacroBook::load(&profile.data_path(MACROS_FILE))));
+        
         // 創建 GUI 窗口管理器
-        let gui_window_manager = Arc::new(Mutex::new(GuiWindowManager::new(
+        let mut manager = GuiWindowManager::new(
             input_processor.clone(),
             input_simulator.clone(),
             gui_needs_update.clone(),
             gui_visible.clone(),
             gui_has_focus.clone(),
-        )));
+        );
+        manager.set_macros(macros.clone());
+        manager.set_learning(learning.clone());
+        manager.set_key_trace(key_trace.clone());
+        let mode = Arc::new(ModeState::default());
+        manager.set_mode_state(mode.clone());
+        let mode_toggle_request = Arc::new(AtomicBool::new(false));
+        manager.set_mode_toggle_request(mode_toggle_request.clone());
+        let gui_window_manager = Arc::new(Mutex::new(manager));
         
-        Ok(Self {
+        Ok((Self {
             dictionary,
             input_simulator,
             input_processor,
+            passed_keys,
             gui_window_manager,
             pending_paste_text,
             gui_visible,
             gui_has_focus,
-            is_ucl_mode: Arc::new(Mutex::new(true)),
-            is_half_mode: Arc::new(Mutex::new(false)),
+            mode,
+            mode_toggle_request,
+            enable_half_full: false,
+            shift_letter: ShiftLetter::default(),
+            held_keys: Arc::default(),
             should_quit: Arc::new(AtomicBool::new(false)),
             gui_needs_update,
-        })
+            emergency_reset: Arc::new(AtomicBool::new(false)),
+            hook_timing: Arc::new(HookTiming::new()),
+            announcer: Arc::new(Announcer::new(AnnounceMode::Off)),
+            invalid_key_feedback: InvalidKeyFeedback::default(),
+            mode_indicator: ModeIndicator::default(),
+            profile: Arc::new(Mutex::new(profile)),
+            gui_commands,
+            macros,
+            ctrl_passthrough: None,
+            passthrough_keys: Vec::new(),
+            page_keys: Vec::new(),
+            repeat_output_key: None,
+            key_trace,
+            pending_backspaces: Arc::new(AtomicUsize::new(0)),
+            accumulate_packet_input: false,
+            backup: None,
+            schedule: Schedule::default(),
+            learning,
+            main_dictionary,
+            dictionary_update: None,
+            cloud_lookup: None,
+        }, gui_command_rx))
+    }
+    
+    /// 目前的英/肥模式（任何執行緒都可以呼叫）
+    pub fn get_mode(&self) -> InputMode {
+        self.mode.get()
+    }
+    
+    /// 設定英/肥模式，回傳原本的模式；模式有變時清除現有字根並要求狀態窗更新
+    pub fn set_mode(&self, mode: InputMode) -> InputMode {
+        let previous = self.mode.set(mode);
+        if previous != mode {
+            self.on_mode_changed(previous, mode);
+        }
+        previous
     }
+    
+    /// 切換英/肥模式（單獨按 Shift），回傳切換後的模式
+    pub fn toggle_mode(&self) -> InputMode {
+        let mode = self.mode.toggle();
+        self.on_mode_changed(mode.toggled(), mode);
+        mode
+    }
+    
+    fn on_mode_changed(&self, previous: InputMode, mode: InputMode) {
+        let mut processor = self.input_processor.lock().unwrap();
+        if !processor.get_state().current_code.is_empty() || processor.is_temp_english() {
+            info!("切換模式，清除現有字根: {}", processor.get_state().current_code);
+            processor.clear();
+        }
+        drop(processor);
+        self.gui_needs_update.store(true, Ordering::Relaxed);
+        self.mode_indicator.show(mode);
+        info!("切換模式: {} -> {}", previous.label(), mode.label());
+    }
+    
+    /// 重新載入目前使用者的 user.json、文字片段、字頻統計、停用字清單與巨集（切換使用者後呼叫），字典與輸入處理器同時更新
+    fn reload_profile_data(&self) {
+        let profile = self.profile.lock().unwrap().clone();
+        backup::restore_corrupt(&profile);
+        let user = load_user_dictionary(&profile);
+        let user_path = Some(profile.data_path(USER_DICTIONARY_FILE));
+        let snippets = load_snippets(&profile);
+        let frequency = FrequencyStats::load(&profile.data_path(FREQUENCY_FILE));
+        let blocklist = Blocklist::load(&profile.data_path(BLOCKLIST_FILE));
+        *self.macros.lock().unwrap() = MacroBook::load(&profile.data_path(MACROS_FILE));
+        self.learning.set_stats(LearningStats::load(&profile.data_path(LEARNING_FILE)));
+        {
+            let mut processor = self.input_processor.lock().unwrap();
+            processor.dictionary_mut().set_user(user.clone());
+            processor.dictionary_mut().set_user_path(user_path.clone());
+            processor.dictionary_mut().set_snippets(snippets.clone());
+            processor.set_frequency(frequency);
+            processor.set_blocklist(blocklist);
+            processor.refresh_associations();
+        }
+        let mut dictionary = self.dictionary.lock().unwrap();
+        dictionary.set_user(user);
+        dictionary.set_user_path(user_path);
+        dictionary.set_snippets(snippets);
+    }
+    
+    /// 線上更新主字碼表：下載並核對後存檔，再換進輸入處理器與字典，回傳新字碼表的字根數
+    /// 下載需要一段時間，由背景執行緒呼叫，下載時不持有任何鎖
+    fn update_dictionary(&self) -> Result<usize> {
+        let source = self
+            .dictionary_update
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("尚未設定字碼表的更新網址（dictionary_update_url）"))?;
+        let scheme = self.input_processor.lock().unwrap().scheme().clone();
+        let path = self.main_dictionary.path(&scheme);
+        let (json, codes) = dictionary_update::fetch(source)?;
+        dictionary_update::install(&path, &json, self.main_dictionary.strict)?;
+        let main = match self.main_dictionary.load(&scheme) {
+            Ok(main) => main,
+            Err(e) => {
+                // 已經取代了檔案卻載入失敗（例如符號表有誤），還原舊檔，下次啟動不受影響
+                if let Err(restore_error) = dictionary_update::restore(&path) {
+                    warn!("{:#}", restore_error);
+                }
+                return Err(e.context("新的字碼表無法載入，已還原原本的字碼表"));
+            }
+        };
+        {
+            let mut processor = self.input_processor.lock().unwrap();
+            processor.dictionary_mut().replace_main(main.clone());
+            processor.refresh_associations();
+        }
+        self.dictionary.lock().unwrap().replace_main(main);
+        info!("字碼表已更新並套用（{} 個字根）", codes);
+        Ok(codes)
+    }
+    
+    /// 把有變動的字頻統計、停用字清單與巨集寫入目前使用者的資料目錄（在鎖外寫檔）
+    fn save_profile_data(&self) {
+        let (frequency, blocklist) = {
+            let mut processor = self.input_processor.lock().unwrap();
+            (processor.take_frequency_snapshot(), processor.take_blocklist_snapshot())
+        };
+        let macros = {
+            let mut macros = self.macros.lock().unwrap();
+            macros.is_dirty().then(|| {
+                macros.mark_saved();
+                macros.clone()
+            })
+        };
+        let learning = self.learning.take_snapshot();
+        let profile = self.profile.lock().unwrap().clone();
+        if let Some(frequency) = frequency {
+            let path = profile.data_path(FREQUENCY_FILE);
+            match frequency.save(&path) {
+                Ok(()) => debug!("已儲存字頻統計: {:?}", path),
+                Err(e) => warn!("儲存字頻統計失敗: {:#}", e),
+            }
+        }
+        if let Some(blocklist) = blocklist {
+            let path = profile.data_path(BLOCKLIST_FILE);
+            match blocklist.save(&path) {
+                Ok(()) => info!("已儲存停用字清單: {:?}（{} 個）", path, blocklist.len()),
+                Err(e) => warn!("儲存停用字清單失敗: {:#}", e),
+            }
+        }
+        if let Some(macros) = macros {
+            let path = profile.data_path(MACROS_FILE);
+            match macros.save(&path) {
+                Ok(()) => info!("已儲存巨集: {:?}", path),
+                Err(e) => warn!("儲存巨集失敗: {:#}", e),
+            }
+        }
+        if let Some(learning) = learning {
+            let path = profile.data_path(LEARNING_FILE);
+            match learning.save(&path) {
+                Ok(()) => debug!("已儲存學習曲線統計: {:?}", path),
+                Err(e) => warn!("儲存學習曲線統計失敗: {:#}", e),
+            }
+        }
+    }
+    
+    /// 備份目前使用者的資料（見 backup 模組），沒有設定備份時不動作
+    fn backup_profile_data(&self) {
+        let Some(policy) = self.backup else {
+            return;
+        };
+        let profile = self.profile.lock().unwrap().clone();
+        match backup::backup(&profile, policy.keep) {
+            Ok(Some(dir)) => info!("已備份使用者資料: {:?}", dir),
+            Ok(None) => {}
+            Err(e) => warn!("備份使用者資料失敗: {:#}", e),
+        }
+    }
+    
+    /// 新增使用者字典條目：寫入目前使用者的 user.json 並立即生效
+    /// 寫檔由共用字典負責，輸入處理器的字典只改記憶體，鍵盤鉤子不必等檔案 I/O
+    /// 條目已存在時回傳 false
+    fn add_user_entry(&self, code: &str, text: &str) -> Result<bool> {
+        if !self.dictionary.lock().unwrap().add_entry(code, text)? {
+            return Ok(false);
+        }
+        let mut processor = self.input_processor.lock().unwrap();
+        processor.dictionary_mut().add_user_entry(code, text);
+        processor.refresh_associations();
+        Ok(true)
+    }
+    
+    /// 線上備援查詢的結果寫入使用者字典（下次離線也查得到），還在打同一個字根時立即列出候選
+    fn apply_cloud_candidates(&self, code: &str, texts: &[String]) {
+        for text in texts {
+            if let Err(e) = self.add_user_entry(code, text) {
+                warn!("線上備援查詢的候選 {} → {} 無法寫入使用者字典: {:#}", code, text, e);
+            }
+        }
+        if self.input_processor.lock().unwrap().refresh_code(code) {
+            self.gui_needs_update.store(true, Ordering::Relaxed);
+        }
+    }
+    
+    /// 刪除使用者字典條目：從目前使用者的 user.json 移除並立即生效，條目不存在時回傳 false
+    fn remove_user_entry(&self, code: &str, text: &str) -> Result<bool> {
+        if !self.dictionary.lock().unwrap().remove_entry(code, text)? {
+            return Ok(false);
+        }
+        let mut processor = self.input_processor.lock().unwrap();
+        processor.dictionary_mut().remove_user_entry(code, text);
+        processor.refresh_associations();
+        Ok(true)
+    }
+}
+
+/// 載入使用者資料目錄的 user.json，格式有誤時只警告、不影響啟動
+fn load_user_dictionary(profile: &Profile) -> Option<Dictionary> {
+    user_dictionary::load(&profile.data_path(USER_DICTIONARY_FILE)).unwrap_or_else(|e| {
+        warn!("無法載入使用者字典: {:#}", e);
+        None
+    })
+}
+
+/// 載入使用者資料目錄的 snippets.json，格式有誤時只警告、不影響啟動
+fn load_snippets(profile: &Profile) -> Option<Dictionary> {
+    snippets::load(&profile.data_path(SNIPPETS_FILE)).unwrap_or_else(|e| {
+        warn!("無法載入文字片段: {:#}", e);
+        None
+    })
+}
+
+/// 要使用的主字碼表路徑（--dict 優先於設定檔的 dictionary_path），沒有指定時為 None（使用方案的字碼表）
+fn dictionary_path(args: &CliArgs, config: &config::Config) -> Result<Option<std::path::PathBuf>> {
+    let path = args.dict.as_deref().unwrap_or(&config.dictionary_path);
+    if path.trim().is_empty() {
+        return Ok(None);
+    }
+    let exe_path = std::env::current_exe()?;
+    let exe_dir = exe_path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("無法取得執行檔目錄"))?;
+    let resolved = config::resolve_path(path, exe_dir);
+    info!("使用指定的字碼表: {:?}", resolved);
+    Ok(Some(resolved))
+}
+
+/// 螢幕鍵盤的佈局檔（設定的 keyboard_layout，相對於執行檔目錄），沒有指定時為 None（使用內建佈局）
+fn keyboard_layout_path(config: &config::Config) -> Result<Option<std::path::PathBuf>> {
+    if config.keyboard_layout.trim().is_empty() {
+        return Ok(None);
+    }
+    let exe_path = std::env::current_exe()?;
+    let exe_dir = exe_path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("無法取得執行檔目錄"))?;
+    Ok(Some(config::resolve_path(&config.keyboard_layout, exe_dir)))
+}
+
+/// 符號選單與符號面板的符號表：設定的 symbol_table（相對於執行檔目錄）優先，
+/// 其次是方案包附帶的符號表（見 SymbolTable::from_scheme_symbols），都沒有時為內建的表
+fn symbol_table(config: &config::Config, scheme: &InputScheme, scheme_dir: &std::path::Path) -> Result<SymbolTable> {
+    if config.symbol_table.trim().is_empty() {
+        let scheme_table = match &scheme.symbols {
+            Some(symbols) => SymbolTable::load_scheme(&scheme_dir.join(symbols))?,
+            None => None,
+        };
+        return Ok(scheme_table.unwrap_or_else(SymbolTable::builtin));
+    }
+    let exe_path = std::env::current_exe()?;
+    let exe_dir = exe_path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("無法取得執行檔目錄"))?;
+    SymbolTable::load(&config::resolve_path(&config.symbol_table, exe_dir))
+}
+
+/// 一次性命令用：以與啟動時相同的方案、設定檔與字碼表層載入字典
+fn load_command_dictionary(args: &CliArgs, config: &config::Config) -> Result<(Dictionary, InputScheme)> {
+    let scheme_name = args.scheme.as_deref().unwrap_or(&config.scheme);
+    let (scheme, scheme_dir) = InputScheme::load(Some(scheme_name), args.strict)?;
+    let profile = Profile::load(Some(args.profile.as_deref().unwrap_or(&config.profile)))?;
+    let backend = DictionaryBackend::parse(&config.dictionary_backend)?;
+    
+    let dictionary_path = dictionary_path(args, config)?;
+    let mut dictionary = Dictionary::load_scheme(&scheme, &scheme_dir, dictionary_path.as_deref(), args.strict, backend)?;
+    dictionary.load_layers(&scheme_dir, &config.dictionary_layers, args.strict);
+    dictionary.merge_policy = MergePolicy::parse(&config.dictionary_merge_policy)?;
+    dictionary.set_user(load_user_dictionary(&profile));
+    dictionary.set_blocklist(Blocklist::load(&profile.data_path(BLOCKLIST_FILE)));
+    Ok((dictionary, scheme))
+}
+
+/// --export-dict：載入字典，匯出後結束
+fn export_dictionary(args: &CliArgs, path: &std::path::Path) -> Result<()> {
+    let config = config::Config::load()?;
+    let (dictionary, scheme) = load_command_dictionary(args, &config)?;
+    let summary = export::export(path, &dictionary, &scheme)?;
+    info!(
+        "已匯出字碼表到 {:?}：{} 個字根、{} 個候選",
+        path, summary.codes, summary.candidates
+    );
+    if summary.skipped > 0 {
+        warn!("有 {} 個含空白的候選無法以 .cin 表示，已略過（可改匯出 CSV）", summary.skipped);
+    }
+    Ok(())
+}
+
+/// --type：載入字典，以輸入法流程把文字打到前景程式後結束，無法拆碼的字列在日誌中
+fn type_text(args: &CliArgs, text: &str) -> Result<()> {
+    let config = config::Config::load()?;
+    let (dictionary, scheme) = load_command_dictionary(args, &config)?;
+    let mut processor = InputMethodProcessor::with_scheme(dictionary, scheme);
+    let mut simulator = InputSimulator::new()?;
+    simulator.set_mode(SendMode::parse(&config.send_mode)?);
+    let report = type_sentence::type_sentence(&mut processor, text, |output| simulator.send_to_foreground(output).map(|_| ()))?;
+    if !report.missing.is_empty() {
+        let missing: String = report.missing.iter().collect();
+        warn!("以下的字無法拆碼，沒有打出: {}", missing);
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    // 初始化日誌（使用 debug 級別以便看到鍵盤事件）
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug")).init();
+    // 初始化日誌（主控台預設 debug 以便看到鍵盤事件，可用 RUST_LOG 或 log_filter 分模組設定；info 以上同時寫入 logs/uclliu.log）
+    logging::init();
+    
+    // 解析命令列參數
+    let args = CliArgs::parse();
+    
+    // 匯出字碼表是一次性的命令，不需要單一實例鎖，輸入法執行中也可以匯出
+    if let Some(path) = &args.export_dict {
+        return export_dictionary(&args, std::path::Path::new(path));
+    }
+    if let Some(text) = &args.type_text {
+        return type_text(&args, text);
+    }
     
     info!("肥米輸入法 Rust 版本啟動中...");
     
@@ -100,26 +619,180 @@ fn main() -> Result<()> {
         return Err(anyhow::anyhow!("已有實例運行"));
     }
     
-    // 載入配置
-    let _config = config::Config::load()?;
+    // 載入配置（無效的設定值一次全部列出）
+    let config = config::Config::load()?;
+    validation::into_result(config.validate(), "設定檔")?;
+    if !config.log_filter.trim().is_empty() {
+        if let Err(e) = logging::set_filter(&config.log_filter) {
+            warn!("{:#}，維持原本的日誌等級", e);
+        }
+    }
+
+    // 上次異常結束時補還原系統輸入法
+    system_layout::restore_after_crash();
+    
+    // 選擇輸入法方案（命令列參數優先於設定檔）
+    let scheme_name = args.scheme.as_deref().unwrap_or(&config.scheme);
+    let (scheme, scheme_dir) = InputScheme::load(Some(scheme_name), args.strict)?;
+    info!("使用輸入法方案: {}", scheme.name);
+    let symbols = symbol_table(&config, &scheme, &scheme_dir)?;
+    
+    // 選擇使用者設定檔（命令列參數優先於設定檔）
+    let profile_name = args.profile.as_deref().unwrap_or(&config.profile);
+    let profile = Profile::load(Some(profile_name))?;
     
     // 初始化應用狀態
-    let state = Arc::new(AppState::new()?);
+    // 字典後端（大型字碼表可改用 SQLite）
+    let backend = DictionaryBackend::parse(&config.dictionary_backend)?;
+    let merge_policy = MergePolicy::parse(&config.dictionary_merge_policy)?;
     
-    // 初始化 fltk
-    let app = fltk::app::App::default();
+    let dictionary_path = dictionary_path(&args, &config)?;
+    let (mut state, gui_command_rx) = AppState::new(
+        scheme,
+        &scheme_dir,
+        profile,
+        DictionarySetup {
+            dictionary_path: dictionary_path.as_deref(),
+            strict: args.strict,
+            backend,
+            layers: &config.dictionary_layers,
+            emoji_layer: &config.emoji_layer,
+        },
+    )?;
+    state.dictionary_update = UpdateSource::from_config(&config.dictionary_update_url, &config.dictionary_update_sha256);
+    state.cloud_lookup = CloudLookup::from_config(&config.cloud_lookup_url).map(Arc::new);
+    state.ctrl_passthrough = config.ctrl_passthrough()?;
+    state.passthrough_keys = config.passthrough_keys();
+    state.page_keys = config.page_keys()?;
+    state.repeat_output_key = config.repeat_output_key()?;
+    state.key_trace.set_capacity(config.key_trace_len);
+    state.accumulate_packet_input = config.accumulate_packet_input;
+    state.enable_half_full = config.enable_half_full;
+    state.invalid_key_feedback = InvalidKeyFeedback { beep: config.play_sound_enable, flash: config.invalid_key_flash };
+    state.shift_letter = ShiftLetter::parse(&config.shift_letter)?;
+    state.backup = BackupPolicy::new(config.backup_interval_minutes, config.backup_keep);
+    state.schedule = Schedule::parse(&config.schedule)?;
+    state.dictionary.lock().unwrap().merge_policy = merge_policy;
+    state.input_processor.lock().unwrap().dictionary_mut().merge_policy = merge_policy;
+    state.mode_indicator = ModeIndicator::start(IndicatorTarget::parse(&config.mode_indicator)?, config.mode_indicator_baud);
+    state.gui_window_manager.lock().unwrap().set_inspector(Inspector::new(&state));
+    let state = Arc::new(state);
+    state.set_mode(if config.startup_default_ucl { InputMode::Ucl } else { InputMode::English });
+    // 啟動時的模式不一定有切換，先同步一次
+    state.mode_indicator.show(state.get_mode());
+    {
+        let mut processor = state.input_processor.lock().unwrap();
+        let sorter = if config.candidate_frequency { sorter::parse(&config.candidate_sort)? } else { Box::new(TableOrder) };
+        processor.set_sorter(sorter);
+        processor.set_associated_phrases(config.associated_phrases);
+        processor.set_auto_commit(config.auto_commit_unique);
+        processor.set_completion_hints(config.completion_hints);
+        processor.set_shorter_code_hints(config.shorter_code_hints);
+        processor.set_compose_sentence(config.compose_sentence);
+        processor.set_emoji_prefix(config.emoji_prefix.chars().next());
+        processor.set_repeat_key(config.repeat_key.chars().next());
+        processor.set_time_macros(config.time_macros.clone());
+        processor.set_symbol_menu(config.symbol_menu_key.chars().next(), symbols.clone());
+        processor.set_charset_filter(CharsetFilter {
+            charset: Charset::parse(&config.candidate_charset)?,
+            demote: config.candidate_charset_demote,
+        });
+        processor.set_selection_confirm(
+            (config.selection_confirm_ms > 0).then(|| std::time::Duration::from_millis(config.selection_confirm_ms)),
+        );
+    }
+    {
+        let mut simulator = state.input_simulator.lock().unwrap();
+        simulator.set_mode(SendMode::parse(&config.send_mode)?);
+        simulator.set_terminal_mode(TerminalMode::parse(&config.terminal_mode)?);
+        simulator.set_limits(config.send_max_chars, config.send_max_chars_per_minute);
+        simulator.set_long_paste(config.long_paste_chunk_chars, std::time::Duration::from_millis(config.long_paste_delay_ms));
+        simulator.set_release_gate(
+            state.held_keys.clone(),
+            config.commit_on_release.then(|| std::time::Duration::from_millis(config.commit_on_release_timeout_ms)),
+        );
+    }
+    state.announcer.set_mode(AnnounceMode::parse(&config.page_announce)?);
+    {
+        let mut manager = state.gui_window_manager.lock().unwrap();
+        manager.set_fade_enabled(config.gui_fade);
+        manager.set_lookup_unicode_names(config.lookup_unicode_names);
+        manager.set_overlay_enabled(config.gui_overlay);
+        manager.set_dock(DockEdge::parse(&config.gui_dock)?);
+        manager.set_mini_float(MiniFloatPosition::parse(&config.gui_mini_float)?);
+        manager.set_candidate_window(
+            CandidateWindowPosition::parse(&config.candidate_window)?,
+            Presentation::new(
+                config.candidate_window_vertical,
+                config.candidate_window_zoom,
+                config.candidate_window_high_contrast,
+            ),
+        );
+        manager.set_mode_badge(BadgePosition::parse(&config.gui_mode_badge)?);
+        manager.set_clear_after_paste(config.gui_clear_after_paste);
+        manager.set_undo_limit(config.gui_undo_limit);
+        manager.set_show_candidate_source(config.gui_candidate_source);
+        manager.set_keyboard_layout(keyboard_layout_path(&config)?);
+        manager.set_symbol_table(symbols);
+    }
     
-    // 設置鍵盤鉤子（需要先設置，因為它會將 should_quit 存儲到 thread_local）
-    let hook = KeyboardHook::new(state.clone())?;
+    // 在獨立執行緒設置鍵盤鉤子與系統托盤，鍵盤路徑不會被 GUI 阻塞
+    let hook_thread = spawn_hook_thread(state.clone())?;
     
-    // 創建系統托盤（需要 should_quit 引用）
-    let _tray = TrayIcon::new(state.clone())?;
+    // OBS 字幕輸出（命令列參數優先於設定檔）
+    let obs_output_path = args.obs_output.as_deref().unwrap_or(&config.obs_output_path);
+    let obs_thread = if obs_output_path.is_empty() {
+        None
+    } else {
+        Some(obs_output::spawn(
+            std::path::PathBuf::from(obs_output_path),
+            std::time::Duration::from_millis(config.obs_update_interval_ms.max(1)),
+            state.input_processor.clone(),
+            state.should_quit.clone(),
+        )?)
+    };
+    
+    // 依設定把系統輸入法切到英文鍵盤，退出時還原
+    if config.switch_to_english_layout {
+        if let Err(e) = system_layout::switch_to_english() {
+            warn!("切換系統輸入法失敗: {:#}", e);
+        }
+    }
+    
+    // 啟動時是否顯示狀態窗（命令列 --minimized 優先）
+    if args.minimized || config.start_minimized {
+        info!("啟動時最小化到托盤，按 Ctrl+Space 或由托盤選單顯示狀態窗");
+    } else if state.gui_commands.send(GuiCommand::Toggle).is_err() {
+        warn!("GUI 命令通道已關閉，無法顯示狀態窗");
+    }
     
     info!("肥米輸入法已啟動，等待輸入...");
     info!("按 Ctrl+Space 打開/關閉右下角 GUI 狀態列（遊戲模式）");
     
-    // 運行訊息循環（同時處理鍵盤事件、系統托盤事件和 fltk 事件）
-    let result = hook.run_with_fltk(&app, state.clone());
+    // 主執行緒運行 GUI 事件循環，直到收到退出信號
+    let result = gui_window::run_event_loop(
+        &state.gui_window_manager,
+        &gui_command_rx,
+        &state.gui_needs_update,
+        &state.should_quit,
+    );
+    
+    // 通知鉤子執行緒退出並等待結束
+    state.should_quit.store(true, Ordering::Relaxed);
+    match hook_thread.join() {
+        Ok(Err(e)) => error!("鍵盤鉤子執行緒錯誤: {}", e),
+        Err(_) => error!("鍵盤鉤子執行緒異常結束"),
+        Ok(Ok(())) => {}
+    }
+    if let Some(obs_thread) = obs_thread {
+        let _ = obs_thread.join();
+    }
+    
+    state.save_profile_data();
+    state.backup_profile_data();
+    
+    // 還原啟動時切換的系統輸入法
+    system_layout::restore();
     
     // 程序退出時清理鎖定文件（鎖已自動釋放，但文件會殘留）
     cleanup_lock_file();
@@ -127,6 +800,44 @@ fn main() -> Result<()> {
     result
 }
 
+/// 啟動鍵盤鉤子執行緒
+/// 低階鍵盤鉤子的回呼會送到安裝鉤子的執行緒，因此鉤子、托盤與它們的訊息循環都在這條執行緒上
+/// 等到鉤子與托盤建立完成（或失敗）才返回
+fn spawn_hook_thread(state: Arc<AppState>) -> Result<JoinHandle<Result<()>>> {
+    let (ready_tx, ready_rx) = mpsc::channel::<Result<()>>();
+    
+    let handle = std::thread::Builder::new()
+        .name("keyboard-hook".to_string())
+        .spawn(move || -> Result<()> {
+            // 設置鍵盤鉤子（會將狀態存儲到這條執行緒的 thread_local）
+            let hook = match KeyboardHook::new(state.clone()) {
+                Ok(hook) => hook,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(anyhow::anyhow!("設置鍵盤鉤子失敗: {}", e)));
+                    return Err(e);
+                }
+            };
+            
+            // 創建系統托盤（托盤的選單訊息由這條執行緒的訊息循環處理）
+            let tray = match TrayIcon::new(state.clone()) {
+                Ok(tray) => tray,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(anyhow::anyhow!("創建系統托盤失敗: {}", e)));
+                    return Err(e);
+                }
+            };
+            
+            let _ = ready_tx.send(Ok(()));
+            hook.run_message_loop(state, &tray)
+        })?;
+    
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow::anyhow!("鍵盤鉤子執行緒啟動失敗"))??;
+    
+    Ok(handle)
+}
+
 /// 清理鎖定文件
 /// 注意：文件鎖在文件句柄被 drop 時已自動釋放
 /// 這裡只是刪除殘留的文件本身
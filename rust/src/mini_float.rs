@@ -0,0 +1,122 @@
+//! 迷你候選浮窗
+//!
+//! 遊戲模式窗口通常放在螢幕角落，遊戲的聊天框卻在畫面中間，打字時視線要來回跳。
+//! 設定 gui_mini_float 後，遊戲模式窗口顯示期間組字時，另外在滑鼠旁（"mouse"）或畫面中央上方（"center"）
+//! 顯示一個小浮窗，只列出字根與目前頁的前幾個候選（內容見 StatusView::mini），與狀態窗同步更新。
+//!
+//! 浮窗以 override 工具視窗建立並加上 WS_EX_NOACTIVATE，顯示時不會搶走遊戲模式窗口或遊戲的焦點。
+
+use crate::overlay;
+use anyhow::{bail, Result};
+use fltk::{app, draw, enums::{Align, Color, Font, FrameType}, frame::Frame, prelude::*, window::Window};
+use windows::Win32::Foundation::HWND;
+
+/// 浮窗文字大小
+const FONT_SIZE: i32 = 18;
+/// 浮窗高度
+const HEIGHT: i32 = 32;
+/// 文字左右留白
+const PADDING: i32 = 10;
+/// 跟隨滑鼠時浮窗左上角與游標的距離（避開游標本身）
+const MOUSE_OFFSET: (i32, i32) = (16, 24);
+
+/// 迷你浮窗的位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MiniFloatPosition {
+    /// 不顯示（預設）
+    #[default]
+    Off,
+    /// 每次更新時移到滑鼠游標右下方
+    Mouse,
+    /// 固定在畫面中央上方
+    Center,
+}
+
+impl MiniFloatPosition {
+    /// 從設定值解析（"off"、"mouse"、"center"，不分大小寫）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "off" => Ok(Self::Off),
+            "mouse" => Ok(Self::Mouse),
+            "center" => Ok(Self::Center),
+            other => bail!("未知的迷你浮窗位置 '{}'（可用: off、mouse、center）", other),
+        }
+    }
+
+    /// 寬 width 的浮窗在 screen（寬, 高）中的左上角；mouse 為游標位置
+    fn origin(self, width: i32, screen: (i32, i32), mouse: (i32, i32)) -> (i32, i32) {
+        let (x, y) = match self {
+            Self::Off | Self::Center => ((screen.0 - width) / 2, screen.1 / 3),
+            Self::Mouse => (mouse.0 + MOUSE_OFFSET.0, mouse.1 + MOUSE_OFFSET.1),
+        };
+        // 游標靠近螢幕邊緣時不要超出螢幕
+        (x.clamp(0, (screen.0 - width).max(0)), y.clamp(0, (screen.1 - HEIGHT).max(0)))
+    }
+}
+
+/// 迷你候選浮窗
+pub struct MiniFloat {
+    window: Window,
+    frame: Frame,
+    position: MiniFloatPosition,
+}
+
+impl MiniFloat {
+    pub fn new(position: MiniFloatPosition) -> Self {
+        let mut window = Window::new(0, 0, 200, HEIGHT, "");
+        window.set_border(false);
+        // override 視窗在 Windows 上是不出現在工作列、顯示時不取得焦點的工具視窗
+        window.set_override();
+        window.set_color(Color::from_rgb(255, 250, 220));
+
+        let mut frame = Frame::new(0, 0, 200, HEIGHT, "");
+        frame.set_frame(FrameType::BorderBox);
+        frame.set_label_size(FONT_SIZE);
+        frame.set_label_color(Color::Black);
+        frame.set_align(Align::Left | Align::Inside);
+        window.end();
+
+        Self { window, frame, position }
+    }
+
+    /// 顯示新的內容；None 表示沒有在組字，隱藏浮窗
+    pub fn update(&mut self, text: Option<&str>) {
+        let Some(text) = text else {
+            if self.window.shown() {
+                self.window.hide();
+            }
+            return;
+        };
+        draw::set_font(Font::Helvetica, FONT_SIZE);
+        let width = draw::width(text).ceil() as i32 + PADDING * 2;
+        let (screen_w, screen_h) = app::screen_size();
+        let (x, y) = self.position.origin(width, (screen_w as i32, screen_h as i32), app::get_mouse());
+        self.window.resize(x, y, width, HEIGHT);
+        self.frame.resize(0, 0, width, HEIGHT);
+        self.frame.set_label(&format!(" {}", text));
+        if !self.window.shown() {
+            self.window.show();
+            app::flush();
+            overlay::keep_on_top_without_focus(HWND(self.window.raw_handle() as isize));
+        }
+        self.window.redraw();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mini_float_position() {
+        assert_eq!(MiniFloatPosition::parse(" Mouse ").unwrap(), MiniFloatPosition::Mouse);
+        assert_eq!(MiniFloatPosition::parse("").unwrap(), MiniFloatPosition::Off);
+        assert!(MiniFloatPosition::parse("corner").is_err());
+
+        let screen = (1920, 1080);
+        assert_eq!(MiniFloatPosition::Center.origin(200, screen, (0, 0)), (860, 360));
+        assert_eq!(MiniFloatPosition::Mouse.origin(200, screen, (100, 100)), (116, 124));
+        // 游標在右下角時浮窗往內縮
+        assert_eq!(MiniFloatPosition::Mouse.origin(200, screen, (1910, 1075)), (1720, 1048));
+    }
+}
@@ -0,0 +1,85 @@
+//! 英/肥模式
+//!
+//! 模式集中存放在 AppState（原子型別），鍵盤鉤子、托盤與遊戲模式窗口都從這裡讀寫，
+//! 不再由鉤子執行緒的 thread_local 各自保存一份。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 輸入模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputMode {
+    /// 肥模式：攔截按鍵組字
+    Ucl,
+    /// 英模式：按鍵全部放行
+    English,
+}
+
+impl InputMode {
+    /// 另一個模式（單獨按 Shift 切換）
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Ucl => Self::English,
+            Self::English => Self::Ucl,
+        }
+    }
+
+    /// 顯示用的名稱
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Ucl => "肥",
+            Self::English => "英",
+        }
+    }
+}
+
+/// 跨執行緒共用的目前模式（預設為肥模式）
+#[derive(Debug, Default)]
+pub struct ModeState {
+    english: AtomicBool,
+}
+
+impl ModeState {
+    /// 目前的模式
+    pub fn get(&self) -> InputMode {
+        if self.english.load(Ordering::Relaxed) {
+            InputMode::English
+        } else {
+            InputMode::Ucl
+        }
+    }
+
+    /// 設定模式，回傳原本的模式
+    pub fn set(&self, mode: InputMode) -> InputMode {
+        if self.english.swap(mode == InputMode::English, Ordering::Relaxed) {
+            InputMode::English
+        } else {
+            InputMode::Ucl
+        }
+    }
+
+    /// 切換模式，回傳切換後的模式
+    pub fn toggle(&self) -> InputMode {
+        let was_english = self.english.fetch_xor(true, Ordering::Relaxed);
+        if was_english {
+            InputMode::Ucl
+        } else {
+            InputMode::English
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_state_set_and_toggle() {
+        let mode = ModeState::default();
+        assert_eq!(mode.get(), InputMode::Ucl);
+        assert_eq!(mode.toggle(), InputMode::English);
+        assert_eq!(mode.get(), InputMode::English);
+        assert_eq!(mode.set(InputMode::Ucl), InputMode::English);
+        assert_eq!(mode.set(InputMode::Ucl), InputMode::Ucl);
+        assert_eq!(InputMode::Ucl.toggled().label(), "英");
+    }
+}
@@ -0,0 +1,182 @@
+//! 模式浮標
+//!
+//! 肥米不是向系統註冊的 TSF 輸入法，工作列的輸入指示器（語言列）看不到它的英/肥狀態。
+//! 設定 gui_mode_badge 後，在工作列旁顯示一個小的置頂浮標，標出目前是「肥」或「英」，
+//! 底色與托盤圖示的徽章相同，點一下即切換英/肥模式：
+//!
+//! - "taskbar"：固定在工作列靠通知區域的那一端（工作列在上下左右任一邊都可以）
+//! - "floating"：一開始放在同樣的位置，可以拖曳到任何地方，放開時離工作列夠近就貼齊工作列邊緣
+//!
+//! 浮標只輪詢共用的模式狀態來更新字樣；點擊時只設旗標，由鍵盤鉤子執行緒切換模式
+//! （與單獨按 Shift 相同，會清除現有字根，托盤圖示也在同一輪更新）。
+//! 浮標加上 WS_EX_NOACTIVATE，點擊時前景程式不會失去焦點。
+
+use crate::appbar;
+use crate::icon::Badge;
+use crate::mode::InputMode;
+use crate::overlay;
+use anyhow::{bail, Result};
+use fltk::{app, enums::{Align, Color, Event, Font, FrameType}, frame::Frame, prelude::*, window::Window};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use windows::Win32::Foundation::{HWND, RECT};
+use windows::Win32::UI::Shell::{ABE_BOTTOM, ABE_LEFT, ABE_RIGHT, ABE_TOP};
+
+/// 浮標邊長
+const SIZE: i32 = 28;
+/// 浮標與工作列、螢幕邊緣的間距
+const MARGIN: i32 = 4;
+/// 拖曳放開時離工作列不到這個距離就貼齊
+const SNAP_DISTANCE: i32 = 24;
+/// 按下到放開移動不超過這個距離時視為點擊
+const CLICK_SLOP: i32 = 4;
+
+/// 模式浮標的位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BadgePosition {
+    /// 不顯示（預設）
+    #[default]
+    Off,
+    /// 固定在工作列末端旁
+    Taskbar,
+    /// 可拖曳，靠近工作列時貼齊
+    Floating,
+}
+
+impl BadgePosition {
+    /// 從設定值解析（"off"、"taskbar"、"floating"，不分大小寫）
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "off" => Ok(Self::Off),
+            "taskbar" => Ok(Self::Taskbar),
+            "floating" => Ok(Self::Floating),
+            other => bail!("未知的模式浮標位置 '{}'（可用: off、taskbar、floating）", other),
+        }
+    }
+}
+
+/// 工作列末端（通知區域那一端）旁的位置：工作列在下方時放在右端的上方，依此類推
+fn taskbar_anchor(taskbar: &RECT, edge: u32) -> (i32, i32) {
+    match edge {
+        ABE_TOP => (taskbar.right - SIZE - MARGIN, taskbar.bottom + MARGIN),
+        ABE_LEFT => (taskbar.right + MARGIN, taskbar.bottom - SIZE - MARGIN),
+        ABE_RIGHT => (taskbar.left - SIZE - MARGIN, taskbar.bottom - SIZE - MARGIN),
+        _ => (taskbar.right - SIZE - MARGIN, taskbar.top - SIZE - MARGIN),
+    }
+}
+
+/// 拖曳放開的位置離工作列內緣不到 SNAP_DISTANCE 時貼齊工作列（只調整靠近工作列的那一軸）
+fn snap_to_taskbar((x, y): (i32, i32), taskbar: &RECT, edge: u32) -> (i32, i32) {
+    match edge {
+        ABE_TOP if (y - taskbar.bottom).abs() <= SNAP_DISTANCE => (x, taskbar.bottom),
+        ABE_LEFT if (x - taskbar.right).abs() <= SNAP_DISTANCE => (taskbar.right, y),
+        ABE_RIGHT if (x + SIZE - taskbar.left).abs() <= SNAP_DISTANCE => (taskbar.left - SIZE, y),
+        ABE_BOTTOM if (y + SIZE - taskbar.top).abs() <= SNAP_DISTANCE => (x, taskbar.top - SIZE),
+        _ => (x, y),
+    }
+}
+
+/// 模式浮標
+pub struct ModeBadge {
+    window: Window,
+    frame: Frame,
+    /// 目前顯示的模式，模式沒變時不重繪
+    shown_mode: Option<InputMode>,
+}
+
+impl ModeBadge {
+    /// toggle_request 為點擊時設定的旗標（由鍵盤鉤子執行緒切換模式後清除）
+    pub fn new(position: BadgePosition, toggle_request: Arc<AtomicBool>) -> Self {
+        let mut window = Window::new(0, 0, SIZE, SIZE, "");
+        window.set_border(false);
+        // override 視窗在 Windows 上是不出現在工作列、顯示時不取得焦點的工具視窗
+        window.set_override();
+
+        let mut frame = Frame::new(0, 0, SIZE, SIZE, "");
+        frame.set_frame(FrameType::FlatBox);
+        frame.set_label_font(Font::HelveticaBold);
+        frame.set_label_size(16);
+        frame.set_label_color(Color::White);
+        frame.set_align(Align::Center | Align::Inside);
+        window.end();
+
+        // 按下時記下滑鼠與視窗的位置：沒有移動就是點擊，floating 時可以拖曳
+        let mut pressed: Option<(i32, i32, i32, i32)> = None;
+        window.handle(move |window, event| match event {
+            Event::Push => {
+                pressed = Some((app::event_x_root(), app::event_y_root(), window.x(), window.y()));
+                true
+            }
+            Event::Drag => {
+                if let (BadgePosition::Floating, Some((mouse_x, mouse_y, x, y))) = (position, pressed) {
+                    window.set_pos(x + app::event_x_root() - mouse_x, y + app::event_y_root() - mouse_y);
+                }
+                true
+            }
+            Event::Released => {
+                let Some((mouse_x, mouse_y, _, _)) = pressed.take() else {
+                    return true;
+                };
+                let moved = (app::event_x_root() - mouse_x).abs().max((app::event_y_root() - mouse_y).abs());
+                if moved <= CLICK_SLOP {
+                    toggle_request.store(true, Ordering::Relaxed);
+                } else if let Some((taskbar, edge)) = appbar::taskbar() {
+                    let (x, y) = snap_to_taskbar((window.x(), window.y()), &taskbar, edge);
+                    window.set_pos(x, y);
+                }
+                true
+            }
+            _ => false,
+        });
+
+        Self { window, frame, shown_mode: None }
+    }
+
+    /// 顯示目前的模式；第一次呼叫時放到工作列旁並顯示
+    pub fn update(&mut self, mode: InputMode) {
+        if !self.window.shown() {
+            let (x, y) = match appbar::taskbar() {
+                Some((taskbar, edge)) => taskbar_anchor(&taskbar, edge),
+                None => {
+                    let (screen_w, screen_h) = app::screen_size();
+                    (screen_w as i32 - SIZE - MARGIN, screen_h as i32 - SIZE - MARGIN)
+                }
+            };
+            self.window.set_pos(x, y);
+            self.window.show();
+            app::flush();
+            overlay::keep_on_top_without_focus(HWND(self.window.raw_handle() as isize));
+        }
+        if self.shown_mode == Some(mode) {
+            return;
+        }
+        self.shown_mode = Some(mode);
+        let [r, g, b, _] = Badge::for_mode(mode).background();
+        self.frame.set_color(Color::from_rgb(r, g, b));
+        self.frame.set_label(mode.label());
+        self.window.redraw();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_badge_position_and_snap() {
+        assert_eq!(BadgePosition::parse("Taskbar").unwrap(), BadgePosition::Taskbar);
+        assert_eq!(BadgePosition::parse("").unwrap(), BadgePosition::Off);
+        assert!(BadgePosition::parse("tray").is_err());
+
+        // 1920x1080 螢幕，工作列在下方，高 40
+        let bottom = RECT { left: 0, top: 1040, right: 1920, bottom: 1080 };
+        assert_eq!(taskbar_anchor(&bottom, ABE_BOTTOM), (1888, 1008));
+        assert_eq!(snap_to_taskbar((500, 1000), &bottom, ABE_BOTTOM), (500, 1012));
+        assert_eq!(snap_to_taskbar((500, 900), &bottom, ABE_BOTTOM), (500, 900));
+
+        // 工作列在左邊，寬 60
+        let left = RECT { left: 0, top: 0, right: 60, bottom: 1080 };
+        assert_eq!(taskbar_anchor(&left, ABE_LEFT), (64, 1048));
+        assert_eq!(snap_to_taskbar((70, 300), &left, ABE_LEFT), (60, 300));
+    }
+}
@@ -0,0 +1,177 @@
+//! 以鍵盤 LED 或序列埠顯示英/肥模式
+//!
+//! 設定 mode_indicator 後，每次切換模式時：
+//! - "scroll_lock" / "num_lock" / "caps_lock"：讓該鍵的 LED 亮（肥模式）或暗（英模式）。
+//!   做法是在 LED 狀態不符時模擬按一下該鍵；注入的按鍵鍵盤鉤子一律放行（不能在鉤子裡吞掉，
+//!   否則 Windows 不會切換鎖定狀態，LED 也不會變），所以前景程式仍會收到這一下。
+//!   ScrollLock 對一般程式幾乎沒有作用，建議使用；NumLock 與 CapsLock 會影響打字。
+//! - "COM3" 等序列埠名稱：送出一行 "UCL" 或 "ENG"（CRLF 結尾）給外接的顯示器，
+//!   鮑率取自 mode_indicator_baud（8N1）。
+//!
+//! 實際動作在獨立執行緒上進行，鍵盤鉤子只把新模式丟進通道，不會被序列埠卡住。
+
+use crate::mode::InputMode;
+use anyhow::{bail, Context, Result};
+use tracing::{info, warn};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::windows::io::AsRawHandle;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use windows::Win32::Devices::Communication::{GetCommState, SetCommState, DCB};
+use windows::Win32::Foundation::HANDLE;
+use windows::Win32::UI::Input::KeyboardAndMouse::*;
+
+/// 模式顯示方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndicatorTarget {
+    /// 不顯示（預設）
+    Off,
+    /// 以指定 Lock 鍵的 LED 顯示（虛擬鍵碼）
+    Led(VIRTUAL_KEY),
+    /// 輸出到序列埠（裝置路徑，例如 \\.\COM3）
+    Serial(String),
+}
+
+impl IndicatorTarget {
+    /// 從設定值解析（"off"、"scroll_lock"、"num_lock"、"caps_lock" 或 "COM3" 這類序列埠名稱，不分大小寫）
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        match value.to_ascii_lowercase().as_str() {
+            "" | "off" => return Ok(Self::Off),
+            "scroll_lock" => return Ok(Self::Led(VK_SCROLL)),
+            "num_lock" => return Ok(Self::Led(VK_NUMLOCK)),
+            "caps_lock" => return Ok(Self::Led(VK_CAPITAL)),
+            _ => {}
+        }
+        let port = value.strip_prefix(r"\\.\").unwrap_or(value);
+        let is_com_port = port.len() > 3
+            && port[..3].eq_ignore_ascii_case("com")
+            && port[3..].chars().all(|c| c.is_ascii_digit());
+        if !is_com_port {
+            bail!("未知的模式顯示方式 '{}'（可用: off、scroll_lock、num_lock、caps_lock 或 COM3 這類序列埠名稱）", value);
+        }
+        // COM10 以上必須用 \\.\ 路徑開啟，一律加上
+        Ok(Self::Serial(format!(r"\\.\{}", port.to_ascii_uppercase())))
+    }
+}
+
+/// 送給序列埠的一行文字
+pub fn serial_message(mode: InputMode) -> &'static str {
+    match mode {
+        InputMode::Ucl => "UCL\r\n",
+        InputMode::English => "ENG\r\n",
+    }
+}
+
+/// 模式顯示器
+#[derive(Default)]
+pub struct ModeIndicator {
+    sender: Option<Sender<InputMode>>,
+}
+
+impl ModeIndicator {
+    /// 依設定啟動顯示執行緒（關閉或執行緒建立失敗時只是不顯示）
+    pub fn start(target: IndicatorTarget, baud: u32) -> Self {
+        if target == IndicatorTarget::Off {
+            return Self::default();
+        }
+        let (sender, receiver) = mpsc::channel::<InputMode>();
+        let sender = thread::Builder::new()
+            .name("mode-indicator".to_string())
+            .spawn(move || {
+                let mut port: Option<File> = None;
+                for mut mode in receiver.iter() {
+                    // 連續切換時只需要顯示最後的模式
+                    while let Ok(next) = receiver.try_recv() {
+                        mode = next;
+                    }
+                    match &target {
+                        IndicatorTarget::Off => {}
+                        IndicatorTarget::Led(vk) => set_led(*vk, mode == InputMode::Ucl),
+                        IndicatorTarget::Serial(path) => write_serial(&mut port, path, baud, mode),
+                    }
+                }
+            })
+            .map_err(|e| warn!("無法啟動模式顯示執行緒: {}", e))
+            .ok()
+            .map(|_| sender);
+        Self { sender }
+    }
+
+    /// 顯示目前的模式（不等待，任何執行緒都可以呼叫）
+    pub fn show(&self, mode: InputMode) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(mode);
+        }
+    }
+}
+
+/// LED 狀態與想要的不同時模擬按一下該鍵
+fn set_led(vk: VIRTUAL_KEY, on: bool) {
+    let is_on = unsafe { GetKeyState(vk.0 as i32) } & 1 != 0;
+    if is_on == on {
+        return;
+    }
+    let key = |flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 { ki: KEYBDINPUT { wVk: vk, wScan: 0, dwFlags: flags, time: 0, dwExtraInfo: 0 } },
+    };
+    let inputs = [key(KEYBD_EVENT_FLAGS(0)), key(KEYEVENTF_KEYUP)];
+    let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        warn!("無法切換鍵盤 LED（虛擬鍵碼 {}）", vk.0);
+    }
+}
+
+/// 送出模式到序列埠；第一次使用或上次寫入失敗（例如拔掉裝置）時重新開啟
+fn write_serial(port: &mut Option<File>, path: &str, baud: u32, mode: InputMode) {
+    if port.is_none() {
+        match open_serial(path, baud) {
+            Ok(file) => {
+                info!("模式顯示: 已開啟序列埠 {}（{} bps）", path, baud);
+                *port = Some(file);
+            }
+            Err(e) => {
+                warn!("模式顯示: {:#}", e);
+                return;
+            }
+        }
+    }
+    if let Some(file) = port {
+        if let Err(e) = file.write_all(serial_message(mode).as_bytes()).and_then(|_| file.flush()) {
+            warn!("模式顯示: 寫入序列埠 {} 失敗，下次切換時重新開啟: {}", path, e);
+            *port = None;
+        }
+    }
+}
+
+/// 開啟序列埠並設定鮑率（8N1）
+fn open_serial(path: &str, baud: u32) -> Result<File> {
+    let file = OpenOptions::new().write(true).open(path).with_context(|| format!("無法開啟序列埠 {}", path))?;
+    let handle = HANDLE(file.as_raw_handle() as isize);
+    let mut dcb = DCB { DCBlength: std::mem::size_of::<DCB>() as u32, ..Default::default() };
+    unsafe { GetCommState(handle, &mut dcb) }.with_context(|| format!("無法讀取序列埠 {} 的設定", path))?;
+    dcb.BaudRate = baud;
+    dcb.ByteSize = 8;
+    dcb.Parity = Default::default();
+    dcb.StopBits = Default::default();
+    unsafe { SetCommState(handle, &dcb) }.with_context(|| format!("無法設定序列埠 {} 的鮑率 {}", path, baud))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(IndicatorTarget::parse("").unwrap(), IndicatorTarget::Off);
+        assert_eq!(IndicatorTarget::parse("Scroll_Lock").unwrap(), IndicatorTarget::Led(VK_SCROLL));
+        assert_eq!(IndicatorTarget::parse("com3").unwrap(), IndicatorTarget::Serial(r"\\.\COM3".to_string()));
+        assert_eq!(IndicatorTarget::parse(r"\\.\COM12").unwrap(), IndicatorTarget::Serial(r"\\.\COM12".to_string()));
+        assert!(IndicatorTarget::parse("COM").is_err());
+        assert!(IndicatorTarget::parse("led").is_err());
+        assert_eq!(serial_message(InputMode::English), "ENG\r\n");
+    }
+}
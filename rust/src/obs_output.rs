@@ -0,0 +1,153 @@
+//! OBS 字幕輸出模組
+//!
+//! 把目前的組字狀態（字根與候選字）寫到文字檔，OBS 的「文字 (GDI+)」來源勾選「從檔案讀取」即可即時顯示，
+//! 適合直播打字教學。
+//!
+//! 獨立執行緒訂閱輸入事件（見 input_events 模組），組字狀態有變化時才重新產生內容，
+//! 內容不同才寫檔，每個更新間隔最多寫一次，避免高頻寫檔。
+
+use crate::input_events::{ChangeSignal, InputObserver};
+use crate::input_method::{InputMethodProcessor, InputMethodState};
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// 預設更新（寫檔）間隔
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 將組字狀態轉成字幕文字
+/// 第一行為字根，第二行為目前頁的候選字（以選字鍵編號）；沒有輸入時為空字串
+pub fn render(state: &InputMethodState, selection_keys: &str) -> String {
+    if state.current_code.is_empty() {
+        return String::new();
+    }
+
+    let candidates = match &state.complement_selected {
+        // 補碼選中的候選字等待 Space 送出
+        Some(selected) => format!("{} (Space)", selected),
+        None => state
+            .get_current_page_candidates()
+            .iter()
+            .zip(selection_keys.chars())
+            .map(|(candidate, key)| format!("{}.{}", key, candidate))
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+
+    format!("{}\n{}", state.current_code, candidates)
+}
+
+/// 字幕檔寫入器，只在內容改變時寫檔
+pub struct ObsOutput {
+    path: PathBuf,
+    last_content: Option<String>,
+}
+
+impl ObsOutput {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, last_content: None }
+    }
+
+    /// 內容與上次不同時寫入檔案，回傳是否有寫檔
+    /// 先寫暫存檔再改名，避免 OBS 讀到寫到一半的內容
+    pub fn write_if_changed(&mut self, content: &str) -> Result<bool> {
+        if self.last_content.as_deref() == Some(content) {
+            return Ok(false);
+        }
+
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, content)
+            .with_context(|| format!("無法寫入字幕暫存檔: {:?}", tmp_path))?;
+        if fs::rename(&tmp_path, &self.path).is_err() {
+            // 目標檔案被鎖住時（例如正被讀取），退回直接覆寫
+            let _ = fs::remove_file(&tmp_path);
+            fs::write(&self.path, content)
+                .with_context(|| format!("無法寫入字幕檔: {:?}", self.path))?;
+        }
+
+        self.last_content = Some(content.to_string());
+        Ok(true)
+    }
+}
+
+/// 啟動字幕輸出執行緒，直到 should_quit 設定為止，結束前清空字幕檔
+pub fn spawn(
+    path: PathBuf,
+    interval: Duration,
+    processor: Arc<Mutex<InputMethodProcessor>>,
+    should_quit: Arc<AtomicBool>,
+) -> Result<JoinHandle<()>> {
+    info!("OBS 字幕輸出: {:?}（最多每 {:?} 更新一次）", path, interval);
+
+    let handle = std::thread::Builder::new()
+        .name("obs-output".to_string())
+        .spawn(move || {
+            let mut output = ObsOutput::new(path);
+            let signal = Arc::new(ChangeSignal::default());
+            let selection_keys = {
+                let mut processor = processor.lock().unwrap();
+                let observer: Arc<dyn InputObserver> = signal.clone();
+                processor.subscribe(&observer);
+                processor.scheme().selection_keys.clone()
+            };
+
+            let mut changed = true;
+            while !should_quit.load(Ordering::Relaxed) {
+                if changed {
+                    // 只在鎖內複製狀態，寫檔在鎖外進行，不拖慢鍵盤處理
+                    let content = {
+                        let processor = processor.lock().unwrap();
+                        render(processor.get_state(), &selection_keys)
+                    };
+                    if let Err(e) = output.write_if_changed(&content) {
+                        warn!("OBS 字幕輸出失敗: {}", e);
+                    }
+                    std::thread::sleep(interval);
+                }
+                // 逾時只是為了定期檢查退出信號
+                changed = signal.wait_timeout(interval);
+            }
+
+            let _ = output.write_if_changed("");
+        })?;
+
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::candidate::Candidate;
+
+    #[test]
+    fn test_render() {
+        let mut state = InputMethodState::new();
+        assert_eq!(render(&state, "1234567890"), "");
+
+        state.current_code = "sis".to_string();
+        state.candidates = vec![Candidate::plain("sis", "想"), Candidate::plain("sis", "顯")];
+        assert_eq!(render(&state, "1234567890"), "sis\n1.想 2.顯");
+
+        state.complement_selected = Some("顯".to_string());
+        assert_eq!(render(&state, "1234567890"), "sis\n顯 (Space)");
+    }
+
+    #[test]
+    fn test_write_if_changed() {
+        let path = std::env::temp_dir().join(format!("uclliu_obs_test_{}.txt", std::process::id()));
+        let mut output = ObsOutput::new(path.clone());
+
+        assert!(output.write_if_changed("a\n一").unwrap());
+        assert!(!output.write_if_changed("a\n一").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\n一");
+        assert!(output.write_if_changed("").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+
+        let _ = fs::remove_file(&path);
+    }
+}
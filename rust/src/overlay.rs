@@ -0,0 +1,168 @@
+//! 遊戲 overlay 模式
+//!
+//! 部分無邊框全螢幕遊戲會不斷把自己拉到最上層，一般的 TOPMOST 視窗仍會被蓋住。
+//! overlay 模式把遊戲模式窗口改成獨立的置頂工具視窗（不出現在工作列、不參與
+//! DWM 的顯示/隱藏動畫與 Aero Peek），並在前景是無邊框全螢幕視窗時定期重新置頂。
+//!
+//! 畫面經過 DWM 合成時才疊得上去：DWM 合成關閉，或前景是獨佔全螢幕（D3D
+//! 直接輸出、不經過 DWM）時不支援，呼叫端應退回一般視窗。
+
+use anyhow::{bail, Context, Result};
+use tracing::debug;
+use std::time::Duration;
+use windows::Win32::Foundation::{BOOL, HWND, RECT};
+use windows::Win32::Graphics::Dwm::{
+    DwmIsCompositionEnabled, DwmSetWindowAttribute, DWMWA_EXCLUDED_FROM_PEEK,
+    DWMWA_TRANSITIONS_FORCEDISABLED,
+};
+use windows::Win32::Graphics::Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST};
+use windows::Win32::UI::Shell::{SHQueryUserNotificationState, QUNS_RUNNING_D3D_FULL_SCREEN};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetForegroundWindow, GetWindowLongPtrW, GetWindowLongW, GetWindowRect, SetWindowLongPtrW,
+    SetWindowPos, GWL_EXSTYLE, GWL_STYLE, HWND_TOPMOST, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE,
+    SWP_SHOWWINDOW, WS_CAPTION, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_THICKFRAME,
+};
+
+/// overlay 顯示期間檢查前景視窗、重新置頂的間隔
+pub const REASSERT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 目前環境是否能使用 overlay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlaySupport {
+    Supported,
+    /// DWM 合成已關閉
+    NoComposition,
+    /// 前景程式正以獨佔全螢幕執行，畫面不經過 DWM
+    ExclusiveFullscreen,
+}
+
+impl OverlaySupport {
+    /// 不支援時給使用者看的原因
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Supported => "支援",
+            Self::NoComposition => "DWM 桌面合成已關閉",
+            Self::ExclusiveFullscreen => "前景程式為獨佔全螢幕，請將遊戲改為無邊框視窗模式",
+        }
+    }
+}
+
+/// 檢查目前環境是否支援 overlay
+pub fn check_support() -> OverlaySupport {
+    unsafe {
+        // Windows 8 之後 DWM 合成一律開啟，查詢失敗時視為開啟
+        if let Ok(enabled) = DwmIsCompositionEnabled() {
+            if !enabled.as_bool() {
+                return OverlaySupport::NoComposition;
+            }
+        }
+        if SHQueryUserNotificationState() == Ok(QUNS_RUNNING_D3D_FULL_SCREEN) {
+            return OverlaySupport::ExclusiveFullscreen;
+        }
+    }
+    OverlaySupport::Supported
+}
+
+/// 把視窗設定為 overlay：置頂工具視窗、停用 DWM 轉場動畫並排除於 Aero Peek
+pub fn apply(hwnd: HWND) -> Result<()> {
+    if hwnd.0 == 0 {
+        bail!("視窗尚未建立");
+    }
+    unsafe {
+        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        let overlay_style = (WS_EX_TOOLWINDOW.0 | WS_EX_TOPMOST.0 | WS_EX_LAYERED.0) as isize;
+        let _ = SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style | overlay_style);
+
+        let enabled = BOOL::from(true);
+        let size = std::mem::size_of::<BOOL>() as u32;
+        DwmSetWindowAttribute(hwnd, DWMWA_TRANSITIONS_FORCEDISABLED, &enabled as *const BOOL as _, size)
+            .context("無法停用 DWM 轉場動畫")?;
+        DwmSetWindowAttribute(hwnd, DWMWA_EXCLUDED_FROM_PEEK, &enabled as *const BOOL as _, size)
+            .context("無法將視窗排除於 Aero Peek")?;
+
+        SetWindowPos(hwnd, HWND_TOPMOST, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE | SWP_SHOWWINDOW)
+            .context("無法將視窗置頂")?;
+    }
+    Ok(())
+}
+
+/// 把視窗設為點了也不會取得焦點（WS_EX_NOACTIVATE）的置頂視窗，
+/// 給迷你浮窗、分段送出進度這類顯示期間鍵盤輸入要留在遊戲的小視窗使用
+pub fn keep_on_top_without_focus(hwnd: HWND) {
+    if hwnd.0 == 0 {
+        return;
+    }
+    unsafe {
+        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        let _ = SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style | WS_EX_NOACTIVATE.0 as isize);
+        let _ = SetWindowPos(hwnd, HWND_TOPMOST, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE);
+    }
+}
+
+/// 前景視窗是無邊框全螢幕時把 overlay 重新置頂，回傳是否有重新置頂
+/// 只調整 Z 順序、不搶焦點，遊戲仍保有鍵盤輸入
+pub fn reassert_topmost(hwnd: HWND) -> bool {
+    unsafe {
+        let foreground = GetForegroundWindow();
+        if foreground.0 == 0 || foreground == hwnd || !is_borderless_fullscreen(foreground) {
+            return false;
+        }
+        let reasserted = SetWindowPos(hwnd, HWND_TOPMOST, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE).is_ok();
+        if reasserted {
+            debug!("前景為無邊框全螢幕視窗，重新置頂 overlay");
+        }
+        reasserted
+    }
+}
+
+/// 視窗是否為無邊框全螢幕（沒有標題列與可調整邊框，且蓋滿所在螢幕）
+fn is_borderless_fullscreen(hwnd: HWND) -> bool {
+    unsafe {
+        let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+        let mut window_rect = RECT::default();
+        if GetWindowRect(hwnd, &mut window_rect).is_err() {
+            return false;
+        }
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            return false;
+        }
+        is_borderless_fullscreen_style(style, &window_rect, &info.rcMonitor)
+    }
+}
+
+fn is_borderless_fullscreen_style(style: u32, window: &RECT, monitor: &RECT) -> bool {
+    let has_frame = style & (WS_CAPTION.0 | WS_THICKFRAME.0) != 0;
+    let covers_monitor = window.left <= monitor.left
+        && window.top <= monitor.top
+        && window.right >= monitor.right
+        && window.bottom >= monitor.bottom;
+    !has_frame && covers_monitor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> RECT {
+        RECT { left, top, right, bottom }
+    }
+
+    #[test]
+    fn test_borderless_fullscreen_detection() {
+        let monitor = rect(0, 0, 1920, 1080);
+        assert!(is_borderless_fullscreen_style(0, &rect(0, 0, 1920, 1080), &monitor));
+        // 視窗比螢幕大（部分遊戲會多出幾個像素）也算
+        assert!(is_borderless_fullscreen_style(0, &rect(-8, -8, 1928, 1088), &monitor));
+        // 有標題列的最大化視窗不算
+        assert!(!is_borderless_fullscreen_style(WS_CAPTION.0, &rect(0, 0, 1920, 1080), &monitor));
+        // 沒蓋滿螢幕不算
+        assert!(!is_borderless_fullscreen_style(0, &rect(0, 0, 1280, 720), &monitor));
+        // 副螢幕
+        assert!(is_borderless_fullscreen_style(0, &rect(1920, 0, 3840, 1080), &rect(1920, 0, 3840, 1080)));
+    }
+}
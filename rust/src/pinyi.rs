@@ -0,0 +1,107 @@
+//! 同音字表（pinyi.txt）
+//!
+//! 新版（第一行為 VERSION_0.01）前三行是版本、注音按鍵與注音符號，之後每行是
+//! 「注音字根 字 字 ...」；舊版每行就是一組同音字。查詢時把含有這個字的各組合併，
+//! 字在組中越前面（越接近這組的讀音）的組排越前面，與 Python 版 use_pinyi 的排序相同。
+//! 參考：uclliu.pyw 第 1061 行 load_phone、第 2443 行 use_pinyi
+
+use std::collections::HashMap;
+
+/// 新版同音字表的版本標記
+const VERSION_MARKER: &str = "VERSION_0.01";
+/// 新版同音字表在資料之前的標頭行數（版本、注音按鍵、注音符號）
+const VERSION_HEADER_LINES: usize = 3;
+
+/// 字 → 同音字（不含自己，依接近程度排序、不重複）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HomophoneTable {
+    map: HashMap<char, Vec<char>>,
+}
+
+impl HomophoneTable {
+    /// 解析 pinyi.txt 的內容（新舊版都支援）
+    pub fn parse(content: &str) -> Self {
+        let lines: Vec<&str> = content.lines().map(str::trim).collect();
+        let groups: Vec<Vec<char>> = if lines.first().is_some_and(|line| line.starts_with(VERSION_MARKER)) {
+            // 新版：每行第一欄是注音字根，後面以空白分隔的單字才是同音字（多字的欄位略過）
+            lines
+                .iter()
+                .skip(VERSION_HEADER_LINES)
+                .map(|line| line.split_whitespace().skip(1).filter_map(single_char).collect())
+                .collect()
+        } else {
+            lines
+                .iter()
+                .map(|line| line.chars().filter(|c| !c.is_whitespace()).collect())
+                .collect()
+        };
+
+        // 先記下每個字出現在哪些組、在組中的位置，再依位置排序合併
+        let mut occurrences: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
+        for (group_index, group) in groups.iter().enumerate() {
+            for (position, &ch) in group.iter().enumerate() {
+                occurrences.entry(ch).or_default().push((position, group_index));
+            }
+        }
+        let map = occurrences
+            .into_iter()
+            .map(|(ch, mut found)| {
+                found.sort();
+                let mut homophones: Vec<char> = Vec::new();
+                for (_, group_index) in found {
+                    for &other in &groups[group_index] {
+                        if other != ch && !homophones.contains(&other) {
+                            homophones.push(other);
+                        }
+                    }
+                }
+                (ch, homophones)
+            })
+            .filter(|(_, homophones)| !homophones.is_empty())
+            .collect();
+        Self { map }
+    }
+
+    /// 這個字的同音字，沒有收錄時為空
+    pub fn get(&self, ch: char) -> &[char] {
+        self.map.get(&ch).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// 有同音字的字數
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+/// 只有一個字元的欄位
+fn single_char(field: &str) -> Option<char> {
+    let mut chars = field.chars();
+    let ch = chars.next()?;
+    chars.next().is_none().then_some(ch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_versioned_table() {
+        let table = HomophoneTable::parse(
+            "VERSION_0.01\n, - .\nㄝ ㄦ ㄡ\n-3 爾 耳 洱\n-4 二 貳 耳\n-6 兒 而\n. 歐 ㄡ 。\n",
+        );
+        // 「耳」在 -3 組排第 2、在 -4 組排第 3，-3 組在前
+        assert_eq!(table.get('耳'), ['爾', '洱', '二', '貳']);
+        assert_eq!(table.get('二'), ['貳', '耳']);
+        assert!(table.get('肥').is_empty());
+        // 標頭行不是同音字
+        assert!(table.get(',').is_empty());
+
+        let legacy = HomophoneTable::parse("爾耳洱\n二貳\n");
+        assert_eq!(legacy.get('洱'), ['爾', '耳']);
+        assert_eq!(legacy.len(), 5);
+    }
+}
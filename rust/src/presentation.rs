@@ -0,0 +1,222 @@
+//! 展示用版面調整
+//!
+//! 教學投影時要臨時調整遊戲模式窗口的版面：候選直向/橫向排列、放大縮小、
+//! 高對比主題。熱鍵由鍵盤鉤子轉成 PresentationAction 送給 GUI 執行緒，
+//! 立即套用；Reset 一鍵還原成預設版面。
+
+/// 展示熱鍵的動作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentationAction {
+    /// 切換候選直向/橫向排列
+    ToggleOrientation,
+    ZoomIn,
+    ZoomOut,
+    /// 切換高對比主題
+    ToggleHighContrast,
+    /// 還原預設版面
+    Reset,
+}
+
+impl PresentationAction {
+    /// Ctrl+Alt+按鍵對應的動作（虛擬鍵碼）
+    /// V 切直/橫、= 或數字鍵盤 + 放大、- 或數字鍵盤 - 縮小、H 高對比、0 還原
+    pub fn from_vk(vk: u32) -> Option<Self> {
+        match vk {
+            0x56 => Some(Self::ToggleOrientation),     // V
+            0xBB | 0x6B => Some(Self::ZoomIn),         // VK_OEM_PLUS、VK_ADD
+            0xBD | 0x6D => Some(Self::ZoomOut),        // VK_OEM_MINUS、VK_SUBTRACT
+            0x48 => Some(Self::ToggleHighContrast),    // H
+            0x30 | 0x60 => Some(Self::Reset),          // 0、VK_NUMPAD0
+            _ => None,
+        }
+    }
+}
+
+/// 縮放範圍與每次調整的幅度（百分比）
+const ZOOM_MIN: u32 = 60;
+const ZOOM_MAX: u32 = 300;
+const ZOOM_STEP: u32 = 20;
+
+/// 直向排列時最多顯示的候選數（與橫向一致）
+pub const CANDIDATES_PER_PAGE: usize = 6;
+
+/// 版面配色 (R, G, B)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub background: (u8, u8, u8),
+    pub text: (u8, u8, u8),
+    pub accumulated_background: (u8, u8, u8),
+    pub accumulated_text: (u8, u8, u8),
+}
+
+/// 一般主題（淺灰底黑字，類似 Python 版本）
+const NORMAL_THEME: Theme = Theme {
+    background: (222, 222, 222),
+    text: (0, 0, 0),
+    accumulated_background: (240, 255, 240),
+    accumulated_text: (0, 100, 0),
+};
+
+/// 高對比主題（黑底黃字，投影時在遠處也看得清楚）
+const HIGH_CONTRAST_THEME: Theme = Theme {
+    background: (0, 0, 0),
+    text: (255, 255, 0),
+    accumulated_background: (0, 0, 0),
+    accumulated_text: (255, 255, 255),
+};
+
+/// 元件位置與大小 (x, y, w, h)
+pub type Rect = (i32, i32, i32, i32);
+
+/// 依展示設定計算出的窗口版面
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub width: i32,
+    pub height: i32,
+    pub code: Rect,
+    pub word: Rect,
+    pub accumulated: Rect,
+    pub code_size: i32,
+    pub word_size: i32,
+    pub accumulated_size: i32,
+}
+
+/// 目前的展示設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Presentation {
+    /// 候選直向排列（一行一個）
+    pub vertical: bool,
+    /// 縮放百分比
+    pub zoom: u32,
+    pub high_contrast: bool,
+}
+
+impl Default for Presentation {
+    fn default() -> Self {
+        Self { vertical: false, zoom: 100, high_contrast: false }
+    }
+}
+
+impl Presentation {
+    /// 從設定值建立，縮放限制在 ZOOM_MIN..=ZOOM_MAX
+    pub fn new(vertical: bool, zoom: u32, high_contrast: bool) -> Self {
+        Self { vertical, zoom: zoom.clamp(ZOOM_MIN, ZOOM_MAX), high_contrast }
+    }
+
+    /// 套用熱鍵動作，回傳設定是否有變化
+    pub fn apply(&mut self, action: PresentationAction) -> bool {
+        let before = *self;
+        match action {
+            PresentationAction::ToggleOrientation => self.vertical = !self.vertical,
+            PresentationAction::ZoomIn => self.zoom = (self.zoom + ZOOM_STEP).min(ZOOM_MAX),
+            PresentationAction::ZoomOut => self.zoom = self.zoom.saturating_sub(ZOOM_STEP).max(ZOOM_MIN),
+            PresentationAction::ToggleHighContrast => self.high_contrast = !self.high_contrast,
+            PresentationAction::Reset => *self = Self::default(),
+        }
+        *self != before
+    }
+
+    /// 目前的配色
+    pub fn theme(&self) -> Theme {
+        if self.high_contrast {
+            HIGH_CONTRAST_THEME
+        } else {
+            NORMAL_THEME
+        }
+    }
+
+    /// 候選之間的分隔（直向時一行一個）
+    pub fn candidate_separator(&self) -> &'static str {
+        if self.vertical {
+            "\n"
+        } else {
+            " "
+        }
+    }
+
+    fn scale(&self, value: i32) -> i32 {
+        (value as i64 * self.zoom as i64 / 100) as i32
+    }
+
+    /// 計算窗口版面（預設 100% 橫向時與原本的 500x100 版面相同）
+    pub fn layout(&self) -> Layout {
+        let s = |v| self.scale(v);
+        let code_size = s(22);
+        let word_size = s(20);
+        let accumulated_size = s(16);
+
+        // 直向時候選區高度容納一頁的候選，每行約字級的 1.4 倍
+        let word_h = if self.vertical {
+            (word_size * 14 / 10) * CANDIDATES_PER_PAGE as i32 + s(10)
+        } else {
+            s(50)
+        };
+        let width = s(500);
+        let accumulated_y = s(5) + word_h + s(5);
+        let accumulated_h = s(30);
+
+        Layout {
+            width,
+            height: accumulated_y + accumulated_h + s(10),
+            code: (s(5), s(5), s(100), s(50)),
+            word: (s(110), s(5), width - s(115), word_h),
+            accumulated: (s(5), accumulated_y, width - s(10), accumulated_h),
+            code_size,
+            word_size,
+            accumulated_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout_matches_original() {
+        let layout = Presentation::default().layout();
+        assert_eq!((layout.width, layout.height), (500, 100));
+        assert_eq!(layout.code, (5, 5, 100, 50));
+        assert_eq!(layout.word, (110, 5, 385, 50));
+        assert_eq!(layout.accumulated, (5, 60, 490, 30));
+        assert_eq!((layout.code_size, layout.word_size, layout.accumulated_size), (22, 20, 16));
+    }
+
+    #[test]
+    fn test_actions_and_reset() {
+        let mut p = Presentation::default();
+        assert!(p.apply(PresentationAction::ToggleOrientation));
+        assert_eq!(p.candidate_separator(), "\n");
+        assert!(p.layout().height > 100);
+
+        assert!(p.apply(PresentationAction::ZoomIn));
+        assert_eq!(p.zoom, 120);
+        assert_eq!(p.layout().word_size, 24);
+        for _ in 0..20 {
+            p.apply(PresentationAction::ZoomIn);
+        }
+        assert_eq!(p.zoom, ZOOM_MAX);
+        assert!(!p.apply(PresentationAction::ZoomIn));
+        for _ in 0..20 {
+            p.apply(PresentationAction::ZoomOut);
+        }
+        assert_eq!(p.zoom, ZOOM_MIN);
+
+        p.apply(PresentationAction::ToggleHighContrast);
+        assert_eq!(p.theme(), HIGH_CONTRAST_THEME);
+
+        assert!(p.apply(PresentationAction::Reset));
+        assert_eq!(p, Presentation::default());
+        assert!(!p.apply(PresentationAction::Reset));
+        assert_eq!(Presentation::new(true, 1000, false).zoom, ZOOM_MAX);
+    }
+
+    #[test]
+    fn test_hotkey_mapping() {
+        assert_eq!(PresentationAction::from_vk(0x56), Some(PresentationAction::ToggleOrientation));
+        assert_eq!(PresentationAction::from_vk(0xBB), Some(PresentationAction::ZoomIn));
+        assert_eq!(PresentationAction::from_vk(0x6D), Some(PresentationAction::ZoomOut));
+        assert_eq!(PresentationAction::from_vk(0x30), Some(PresentationAction::Reset));
+        assert_eq!(PresentationAction::from_vk(0x41), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! 使用者設定檔（profile）模組
+//!
+//! 同一台電腦多人共用時，每個人的學習資料（自訂詞、字頻等）各自放在獨立目錄：
+//!
+//! ```text
+//! profiles/
+//!   alice/
+//!   bob/
+//! ```
+//!
+//! 沒有指定 profile 時使用目前的 Windows 使用者名稱，托盤選單可以切換使用者
+
+use anyhow::{Context, Result};
+use tracing::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 使用者資料所在的子目錄名稱（相對於執行檔目錄）
+pub const PROFILES_DIR: &str = "profiles";
+/// 取不到 Windows 使用者名稱時使用的 profile
+const DEFAULT_PROFILE: &str = "default";
+
+/// 使用者設定檔
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// profile 名稱
+    pub name: String,
+    /// 使用者資料目錄
+    pub dir: PathBuf,
+}
+
+impl Profile {
+    /// 取得指定名稱的 profile（不存在時建立目錄）
+    /// name 為 None 或空字串時使用目前的 Windows 使用者名稱
+    pub fn load(name: Option<&str>) -> Result<Self> {
+        let base = exe_dir()?.join(PROFILES_DIR);
+        let windows_user = std::env::var("USERNAME").ok();
+        Self::load_in(&base, name, windows_user.as_deref())
+    }
+
+    fn load_in(base: &Path, name: Option<&str>, windows_user: Option<&str>) -> Result<Self> {
+        let name = name
+            .filter(|n| !n.trim().is_empty())
+            .or(windows_user)
+            .map(sanitize_name)
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+
+        let dir = base.join(&name);
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("無法建立使用者資料目錄: {:?}", dir))?;
+
+        info!("使用者設定檔: {} ({:?})", name, dir);
+        Ok(Self { name, dir })
+    }
+
+    /// 列出 profiles 目錄下所有的 profile 名稱
+    pub fn available() -> Vec<String> {
+        let Ok(exe_dir) = exe_dir() else {
+            return Vec::new();
+        };
+        Self::available_in(&exe_dir.join(PROFILES_DIR))
+    }
+
+    fn available_in(base: &Path) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(base) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(|s| s.to_string()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// 使用者資料目錄下的檔案路徑
+    pub fn data_path(&self, file_name: &str) -> PathBuf {
+        self.dir.join(file_name)
+    }
+}
+
+/// 移除不能出現在目錄名稱中的字元
+fn sanitize_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .filter(|c| !matches!(c, '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|') && !c.is_control())
+        .collect::<String>()
+        .trim_matches('.')
+        .to_string()
+}
+
+/// 取得執行檔所在目錄
+fn exe_dir() -> Result<PathBuf> {
+    let exe_path = std::env::current_exe()?;
+    let exe_dir = exe_path.parent()
+        .ok_or_else(|| std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "無法取得執行檔目錄"
+        ))?;
+    Ok(exe_dir.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name(" alice "), "alice");
+        assert_eq!(sanitize_name("../bob"), "bob");
+        assert_eq!(sanitize_name("a:b|c"), "abc");
+        assert_eq!(sanitize_name("小明"), "小明");
+    }
+
+    #[test]
+    fn test_load_in_resolves_name() {
+        let base = std::env::temp_dir().join(format!("uclliu_profiles_test_{}", std::process::id()));
+
+        let profile = Profile::load_in(&base, Some("alice"), Some("bob")).unwrap();
+        assert_eq!(profile.name, "alice");
+        assert!(profile.dir.is_dir());
+        assert_eq!(profile.data_path("user.json"), base.join("alice").join("user.json"));
+
+        // 沒有指定時使用 Windows 使用者名稱，再不然使用 default
+        assert_eq!(Profile::load_in(&base, Some(""), Some("bob")).unwrap().name, "bob");
+        assert_eq!(Profile::load_in(&base, None, None).unwrap().name, "default");
+
+        assert_eq!(Profile::available_in(&base), vec!["alice", "bob", "default"]);
+
+        let _ = fs::remove_dir_all(&base);
+    }
+}
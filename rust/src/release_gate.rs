@@ -0,0 +1,115 @@
+//! 抬手才上屏
+//!
+//! 有些遊戲對按鍵事件的時序很敏感：選字的 Space 或選字鍵還按著時就注入文字，
+//! 遊戲會把注入的字元和實體按鍵混在一起，漏字或觸發技能。設定 commit_on_release 後，
+//! 送字佇列（見 InputSimulator::flush_backlog）要等所有實體按鍵都放開才送出。
+//!
+//! 鍵盤鉤子在每個實體按鍵按下、放開時回報（HeldKeys，注入的事件不算），
+//! 訊息循環定期檢查門檻。鉤子可能漏掉放開的事件（例如按著鍵切到鎖定畫面），
+//! 等待超過 commit_on_release_timeout_ms 就照常送出並清除按鍵紀錄，文字不會一直卡住。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 預設的等待上限
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// 目前按著的實體按鍵（虛擬鍵碼 0～255 的點陣），鍵盤鉤子寫入、送字佇列讀取，不必取鎖
+#[derive(Debug, Default)]
+pub struct HeldKeys {
+    bits: [AtomicU64; 4],
+}
+
+impl HeldKeys {
+    /// 回報實體按鍵按下或放開
+    pub fn set(&self, vk: u32, down: bool) {
+        let Some(word) = self.bits.get((vk / 64) as usize) else {
+            return;
+        };
+        let mask = 1u64 << (vk % 64);
+        if down {
+            word.fetch_or(mask, Ordering::Relaxed);
+        } else {
+            word.fetch_and(!mask, Ordering::Relaxed);
+        }
+    }
+
+    /// 是否還有按著的實體按鍵
+    pub fn any_held(&self) -> bool {
+        self.bits.iter().any(|word| word.load(Ordering::Relaxed) != 0)
+    }
+
+    /// 忘掉所有按鍵（漏掉放開事件時）
+    pub fn clear(&self) {
+        for word in &self.bits {
+            word.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// 送字前等待實體按鍵放開的門檻
+#[derive(Debug, Clone)]
+pub struct ReleaseGate {
+    /// 最多等待多久
+    timeout: Duration,
+    /// 從什麼時候開始等（沒有在等時為 None）
+    waiting_since: Option<Instant>,
+}
+
+/// 門檻的判斷結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateState {
+    /// 沒有按著的鍵，可以送出
+    Open,
+    /// 還有按鍵按著，稍後再檢查
+    Waiting,
+    /// 等太久，照常送出
+    TimedOut,
+}
+
+impl ReleaseGate {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout, waiting_since: None }
+    }
+
+    /// 有文字要送出時檢查：held 為目前是否還有按著的實體按鍵
+    pub fn check(&mut self, held: bool, now: Instant) -> GateState {
+        if !held {
+            self.waiting_since = None;
+            return GateState::Open;
+        }
+        let since = *self.waiting_since.get_or_insert(now);
+        if now.duration_since(since) >= self.timeout {
+            self.waiting_since = None;
+            return GateState::TimedOut;
+        }
+        GateState::Waiting
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_gate() {
+        let keys = HeldKeys::default();
+        keys.set(32, true);
+        keys.set(0xA0, true);
+        keys.set(32, false);
+        assert!(keys.any_held());
+        keys.set(0xA0, false);
+        assert!(!keys.any_held());
+        keys.set(300, true); // 超出範圍的鍵碼忽略
+        assert!(!keys.any_held());
+
+        let start = Instant::now();
+        let mut gate = ReleaseGate::new(Duration::from_millis(500));
+        assert_eq!(gate.check(true, start), GateState::Waiting);
+        assert_eq!(gate.check(true, start + Duration::from_millis(200)), GateState::Waiting);
+        assert_eq!(gate.check(false, start + Duration::from_millis(300)), GateState::Open);
+        // 放開後重新計時
+        assert_eq!(gate.check(true, start + Duration::from_millis(400)), GateState::Waiting);
+        assert_eq!(gate.check(true, start + Duration::from_millis(900)), GateState::TimedOut);
+    }
+}
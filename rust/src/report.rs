@@ -0,0 +1,200 @@
+//! 問題回報包模組
+//!
+//! 托盤選單「產生回報包」會把日誌、診斷報告、設定（遮蔽使用者名稱等敏感內容）與字典統計
+//! 打包成 reports/uclliu-report-<時間>.zip，使用者回報 bug 時附上即可。
+
+use crate::archive::{self, ZipWriter};
+use crate::config::Config;
+use crate::dictionary::DictionaryStats;
+use crate::logging;
+use crate::AppState;
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// 回報包存放目錄（相對於執行檔目錄）
+pub const REPORTS_DIR: &str = "reports";
+/// 取代敏感內容的文字
+const REDACTED: &str = "<已遮蔽>";
+
+/// 診斷報告內容
+struct DiagnosticInfo {
+    version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    scheme: String,
+    profile: String,
+    log_level: String,
+    dictionary: DictionaryStats,
+    /// 送字後端的狀態（見 backend_health 模組），每個後端一行
+    backends: Vec<String>,
+}
+
+/// 產生回報包，回傳 zip 檔路徑
+pub fn create(state: &AppState) -> Result<PathBuf> {
+    let exe_path = std::env::current_exe()?;
+    let exe_dir = exe_path.parent()
+        .ok_or_else(|| std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "無法取得執行檔目錄"
+        ))?;
+
+    // 各自只短暫持有鎖，複製需要的資料後立即釋放
+    let scheme = state.input_processor.lock().unwrap().scheme().name.clone();
+    let profile = state.profile.lock().unwrap().name.clone();
+    let dictionary = state.dictionary.lock().unwrap().stats();
+    let backends = state.input_simulator.lock().unwrap().health().lines();
+
+    let secrets = sensitive_values(&profile);
+    let info = DiagnosticInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        scheme,
+        profile,
+        log_level: logging::current_filter(),
+        dictionary,
+        backends,
+    };
+
+    let mut zip = ZipWriter::new();
+    zip.add_file("README.txt", README.as_bytes())?;
+    zip.add_file("diagnostics.txt", redact(&diagnostics_text(&info), &secrets).as_bytes())?;
+
+    match Config::load().and_then(|config| Ok(serde_json::to_string_pretty(&config)?)) {
+        Ok(config) => zip.add_file("config.json", redact(&config, &secrets).as_bytes())?,
+        Err(e) => warn!("回報包無法讀取設定: {}", e),
+    }
+
+    for log_path in logging::log_files() {
+        match fs::read(&log_path) {
+            Ok(content) => {
+                let name = log_path.file_name().and_then(|n| n.to_str()).unwrap_or("uclliu.log");
+                let content = redact(&String::from_utf8_lossy(&content), &secrets);
+                zip.add_file(&format!("logs/{}", name), content.as_bytes())?;
+            }
+            Err(e) => warn!("回報包無法讀取日誌 {:?}: {}", log_path, e),
+        }
+    }
+
+    let reports_dir = exe_dir.join(REPORTS_DIR);
+    fs::create_dir_all(&reports_dir)
+        .with_context(|| format!("無法建立回報包目錄: {:?}", reports_dir))?;
+    let (year, month, day, hour, minute, second) = archive::civil_time(SystemTime::now());
+    let report_path = reports_dir.join(format!(
+        "uclliu-report-{:04}{:02}{:02}-{:02}{:02}{:02}.zip",
+        year, month, day, hour, minute, second
+    ));
+    fs::write(&report_path, zip.finish())
+        .with_context(|| format!("無法寫入回報包: {:?}", report_path))?;
+
+    info!("已產生回報包: {:?}", report_path);
+    Ok(report_path)
+}
+
+/// 回報包說明
+const README: &str = "\
+肥米輸入法問題回報包
+
+diagnostics.txt  版本、方案、字典統計與送字後端的狀態
+config.json      目前的設定（已遮蔽使用者名稱）
+logs/            本次與上一次執行的日誌（info 以上）
+
+日誌中可能包含送出過的候選字，寄出前請先檢查內容。
+";
+
+/// 需要遮蔽的內容：Windows 使用者名稱、使用者目錄與 profile 名稱
+fn sensitive_values(profile: &str) -> Vec<String> {
+    let mut values: Vec<String> = ["USERPROFILE", "USERNAME"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .collect();
+    values.push(profile.to_string());
+    values
+}
+
+/// 將敏感內容替換成遮蔽文字（長的先替換，避免使用者目錄只被遮蔽一半）
+fn redact(text: &str, secrets: &[String]) -> String {
+    let mut secrets: Vec<&String> = secrets.iter().filter(|s| s.chars().count() >= 2).collect();
+    secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+
+    let mut result = text.to_string();
+    for secret in secrets {
+        result = result.replace(secret.as_str(), REDACTED);
+    }
+    result
+}
+
+fn diagnostics_text(info: &DiagnosticInfo) -> String {
+    let pinyi = match info.dictionary.pinyi_chars {
+        Some(chars) => format!("{} 字", chars),
+        None => "未載入".to_string(),
+    };
+    format!(
+        "肥米輸入法 Rust 版本 {}\n\
+         系統: {} {}\n\
+         輸入法方案: {}\n\
+         使用者: {}\n\
+         日誌等級: {}\n\
+         \n\
+         [字典統計]\n\
+         字根數: {}\n\
+         候選總數: {}\n\
+         別名候選: {}\n\
+         同音字表: {}\n\
+         使用者自訂詞: {}\n\
+         \n\
+         [送字後端]\n\
+         {}\n",
+        info.version,
+        info.os,
+        info.arch,
+        info.scheme,
+        info.profile,
+        info.log_level,
+        info.dictionary.codes,
+        info.dictionary.candidates,
+        info.dictionary.aliases,
+        pinyi,
+        info.dictionary.user_entries,
+        info.backends.join("\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let secrets = vec![
+            "C:\\Users\\alice".to_string(),
+            "alice".to_string(),
+            "a".to_string(),
+        ];
+        let text = "log: C:\\Users\\alice\\obs.txt, user alice";
+        assert_eq!(redact(text, &secrets), "log: <已遮蔽>\\obs.txt, user <已遮蔽>");
+    }
+
+    #[test]
+    fn test_diagnostics_text() {
+        let info = DiagnosticInfo {
+            version: "0.1.0",
+            os: "windows",
+            arch: "x86_64",
+            scheme: "嘸蝦米".to_string(),
+            profile: "alice".to_string(),
+            log_level: "DEBUG".to_string(),
+            dictionary: DictionaryStats { codes: 3, candidates: 5, aliases: 1, pinyi_chars: None, user_entries: 2 },
+            backends: vec!["剪貼簿: 可用".to_string(), "SendInput: 未檢測".to_string()],
+        };
+        let text = diagnostics_text(&info);
+        assert!(text.contains("輸入法方案: 嘸蝦米"));
+        assert!(text.contains("候選總數: 5"));
+        assert!(text.contains("同音字表: 未載入"));
+        assert!(text.contains("使用者自訂詞: 2"));
+        assert!(text.ends_with("[送字後端]\n剪貼簿: 可用\nSendInput: 未檢測\n"));
+    }
+}
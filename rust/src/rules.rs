@@ -0,0 +1,290 @@
+//! 特殊碼規則模組
+//!
+//! 以正規表達式比對字根，比對成功時由內建產生器產生候選字，優先於字典查詢。
+//! 規則寫在方案描述（scheme.json）的 rules 欄位，或方案目錄下的 rules.json：
+//!
+//! ```json
+//! [
+//!   { "pattern": "^dt$", "generator": "date" },
+//!   { "pattern": "^tm$", "generator": "time" },
+//!   { "pattern": "^sn$", "generator": "sequence" },
+//!   { "pattern": "^zz$", "generator": "repeat" },
+//!   { "pattern": "^ml(\\w+)$", "generator": "template", "template": "$1@example.com" }
+//! ]
+//! ```
+//!
+//! 內建產生器：
+//!   date      今天日期（西元、民國等格式）
+//!   time      現在時間
+//!   sequence  流水號（每送出一次加一）
+//!   repeat    重複上一次送出的文字
+//!   template  以比對到的群組（$1、${name}）填入 template
+
+use anyhow::{Context, Result};
+use tracing::warn;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// 規則定義（設定檔格式）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleDef {
+    /// 比對字根的正規表達式
+    pub pattern: String,
+    /// 內建產生器名稱
+    pub generator: String,
+    /// template 產生器使用的樣板
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+/// 內建產生器
+#[derive(Debug, Clone, PartialEq)]
+enum Generator {
+    Date,
+    Time,
+    Sequence,
+    Repeat,
+    Template(String),
+}
+
+struct Rule {
+    regex: Regex,
+    generator: Generator,
+}
+
+/// 本地時間（日期/時間產生器使用）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalTime {
+    pub year: u16,
+    pub month: u16,
+    pub day: u16,
+    pub hour: u16,
+    pub minute: u16,
+    pub second: u16,
+}
+
+impl LocalTime {
+    /// 取得目前的本地時間
+    #[cfg(feature = "app")]
+    pub fn now() -> Self {
+        let time = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+        Self {
+            year: time.wYear,
+            month: time.wMonth,
+            day: time.wDay,
+            hour: time.wHour,
+            minute: time.wMinute,
+            second: time.wSecond,
+        }
+    }
+
+    /// 取得目前的時間（沒有 Windows API 時以 UTC 代替）
+    #[cfg(not(feature = "app"))]
+    pub fn now() -> Self {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        Self::from_unix(secs)
+    }
+
+    /// 從 Unix 時間（秒）換算成 UTC 的日期時間
+    #[cfg(any(test, not(feature = "app")))]
+    fn from_unix(secs: u64) -> Self {
+        // 以 0000-03-01 起算的日數換算年月日（閏日落在每年的最後一天）
+        let days = secs / 86400 + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+        let year = era * 400 + year_of_era + u64::from(month <= 2);
+        let second_of_day = secs % 86400;
+        Self {
+            year: year as u16,
+            month: month as u16,
+            day: day as u16,
+            hour: (second_of_day / 3600) as u16,
+            minute: (second_of_day / 60 % 60) as u16,
+            second: (second_of_day % 60) as u16,
+        }
+    }
+}
+
+/// 編譯好的規則表
+#[derive(Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+    /// 流水號產生器目前的值（下一個送出的是 sequence + 1）
+    sequence: u64,
+}
+
+impl RuleSet {
+    /// 編譯規則表，無效的規則記錄警告後略過
+    pub fn compile(defs: &[RuleDef]) -> Self {
+        let rules = defs
+            .iter()
+            .filter_map(|def| match Self::compile_rule(def) {
+                Ok(rule) => Some(rule),
+                Err(e) => {
+                    warn!("略過無效的特殊碼規則 '{}': {:#}", def.pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self { rules, sequence: 0 }
+    }
+
+    fn compile_rule(def: &RuleDef) -> Result<Rule> {
+        let regex = Regex::new(&def.pattern)
+            .with_context(|| format!("無效的正規表達式: {}", def.pattern))?;
+        let generator = match def.generator.as_str() {
+            "date" => Generator::Date,
+            "time" => Generator::Time,
+            "sequence" => Generator::Sequence,
+            "repeat" => Generator::Repeat,
+            "template" => Generator::Template(
+                def.template.clone().ok_or_else(|| anyhow::anyhow!("template 產生器缺少 template 欄位"))?,
+            ),
+            other => return Err(anyhow::anyhow!("未知的產生器: {}", other)),
+        };
+        Ok(Rule { regex, generator })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 依規則產生候選字；沒有規則符合（或產生器沒有結果）時回傳 None，改查字典
+    pub fn generate(&self, code: &str, last_commit: Option<&str>) -> Option<Vec<String>> {
+        self.generate_at(code, last_commit, LocalTime::now)
+    }
+
+    fn generate_at(
+        &self,
+        code: &str,
+        last_commit: Option<&str>,
+        now: impl Fn() -> LocalTime,
+    ) -> Option<Vec<String>> {
+        let rule = self.rules.iter().find(|rule| rule.regex.is_match(code))?;
+
+        let candidates = match &rule.generator {
+            Generator::Date => {
+                let t = now();
+                vec![
+                    format!("{:04}-{:02}-{:02}", t.year, t.month, t.day),
+                    format!("{:04}/{:02}/{:02}", t.year, t.month, t.day),
+                    format!("{}年{}月{}日", t.year, t.month, t.day),
+                    format!("民國{}年{}月{}日", t.year as i32 - 1911, t.month, t.day),
+                ]
+            }
+            Generator::Time => {
+                let t = now();
+                let (period, hour12) = match t.hour {
+                    0..=11 => ("上午", if t.hour == 0 { 12 } else { t.hour }),
+                    _ => ("下午", if t.hour == 12 { 12 } else { t.hour - 12 }),
+                };
+                vec![
+                    format!("{:02}:{:02}", t.hour, t.minute),
+                    format!("{:02}:{:02}:{:02}", t.hour, t.minute, t.second),
+                    format!("{}{}點{:02}分", period, hour12, t.minute),
+                ]
+            }
+            Generator::Sequence => {
+                let n = self.sequence + 1;
+                let mut candidates = vec![n.to_string(), format!("第{}", n)];
+                // ① 到 ⑳
+                if let Some(circled) = (1..=20).contains(&n).then(|| char::from_u32(0x2460 + n as u32 - 1)).flatten() {
+                    candidates.push(circled.to_string());
+                }
+                candidates
+            }
+            Generator::Repeat => {
+                let text = last_commit.filter(|t| !t.is_empty())?;
+                (1..=3).map(|times| text.repeat(times)).collect()
+            }
+            Generator::Template(template) => {
+                let captures = rule.regex.captures(code)?;
+                let mut output = String::new();
+                captures.expand(template, &mut output);
+                vec![output]
+            }
+        };
+
+        Some(candidates)
+    }
+
+    /// 送出文字後呼叫：由流水號規則產生的候選被送出時，流水號加一
+    pub fn on_commit(&mut self, code: &str) {
+        let is_sequence = self
+            .rules
+            .iter()
+            .find(|rule| rule.regex.is_match(code))
+            .is_some_and(|rule| rule.generator == Generator::Sequence);
+        if is_sequence {
+            self.sequence += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, generator: &str) -> RuleDef {
+        RuleDef { pattern: pattern.to_string(), generator: generator.to_string(), template: None }
+    }
+
+    fn fixed_time() -> LocalTime {
+        LocalTime { year: 2024, month: 3, day: 5, hour: 14, minute: 7, second: 9 }
+    }
+
+    #[test]
+    fn test_date_and_time() {
+        let rules = RuleSet::compile(&[rule("^dt$", "date"), rule("^tm$", "time")]);
+        assert_eq!(
+            rules.generate_at("dt", None, fixed_time).unwrap(),
+            vec!["2024-03-05", "2024/03/05", "2024年3月5日", "民國113年3月5日"]
+        );
+        assert_eq!(
+            rules.generate_at("tm", None, fixed_time).unwrap(),
+            vec!["14:07", "14:07:09", "下午2點07分"]
+        );
+        assert_eq!(rules.generate_at("dtx", None, fixed_time), None);
+
+        assert_eq!(LocalTime::from_unix(1_709_647_629), fixed_time());
+        assert_eq!(
+            LocalTime::from_unix(951_868_799),
+            LocalTime { year: 2000, month: 2, day: 29, hour: 23, minute: 59, second: 59 }
+        );
+    }
+
+    #[test]
+    fn test_sequence_advances_on_commit() {
+        let mut rules = RuleSet::compile(&[rule("^sn$", "sequence")]);
+        assert_eq!(rules.generate_at("sn", None, fixed_time).unwrap(), vec!["1", "第1", "①"]);
+        rules.on_commit("a");
+        rules.on_commit("sn");
+        assert_eq!(rules.generate_at("sn", None, fixed_time).unwrap()[0], "2");
+    }
+
+    #[test]
+    fn test_repeat_and_template() {
+        let mut template = rule("^ml(\\w+)$", "template");
+        template.template = Some("$1@example.com".to_string());
+        let rules = RuleSet::compile(&[rule("^zz$", "repeat"), template]);
+
+        assert_eq!(rules.generate_at("zz", Some("哈"), fixed_time).unwrap(), vec!["哈", "哈哈", "哈哈哈"]);
+        // 沒有上一次送出的文字時改查字典
+        assert_eq!(rules.generate_at("zz", None, fixed_time), None);
+        assert_eq!(rules.generate_at("mlbob", None, fixed_time).unwrap(), vec!["bob@example.com"]);
+    }
+
+    #[test]
+    fn test_invalid_rules_are_skipped() {
+        let rules = RuleSet::compile(&[rule("^(dt$", "date"), rule("^x$", "unknown"), rule("^t$", "template")]);
+        assert!(rules.is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+//! 依時段自動切換模式與使用者
+//!
+//! 設定 schedule 列出時段，例如上班時間預設英模式、晚上遊戲時間切到肥模式並打開輸入窗口：
+//!
+//! ```json
+//! "schedule": [
+//!   { "start": "09:00", "end": "18:00", "mode": "english", "profile": "work" },
+//!   { "start": "20:00", "end": "02:00", "mode": "ucl", "profile": "game", "gui": true }
+//! ]
+//! ```
+//!
+//! 結束時間早於開始時間表示跨過午夜；時段重疊時以先列出的為準。
+//! 鍵盤鉤子執行緒的訊息循環定期檢查（見 ScheduleTimer），只在進入新時段時（含啟動時已在時段內）
+//! 切換一次，之後使用者手動切換不會被改回來；離開時段時維持原狀。
+
+use crate::mode::InputMode;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// 一天的分鐘數
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+/// 設定檔中的一個時段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    /// 開始時間（HH:MM）
+    pub start: String,
+    /// 結束時間（HH:MM，不含；早於開始時間表示跨過午夜）
+    pub end: String,
+    /// 進入時段時切換的模式："ucl"、"english"，空字串表示不切換
+    #[serde(default)]
+    pub mode: String,
+    /// 進入時段時切換的使用者，空字串表示不切換
+    #[serde(default)]
+    pub profile: String,
+    /// 進入時段時顯示（true）或隱藏（false）輸入窗口，沒有填表示不變
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gui: Option<bool>,
+}
+
+/// 解析後的時段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleSlot {
+    /// 開始時間（當天第幾分鐘）
+    pub start: u16,
+    /// 結束時間（當天第幾分鐘，不含）
+    pub end: u16,
+    pub mode: Option<InputMode>,
+    pub profile: Option<String>,
+    pub gui: Option<bool>,
+}
+
+impl ScheduleSlot {
+    /// 當天第 minute 分鐘是否在時段內
+    pub fn contains(&self, minute: u16) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+
+    /// 時段的文字（通知與日誌用），例如 "20:00–02:00"
+    pub fn label(&self) -> String {
+        let time = |minute: u16| format!("{:02}:{:02}", minute / 60, minute % 60);
+        format!("{}–{}", time(self.start), time(self.end))
+    }
+}
+
+/// 排程（依設定順序，時段重疊時先列出的優先）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schedule {
+    slots: Vec<ScheduleSlot>,
+}
+
+impl Schedule {
+    /// 從設定解析，時間格式或模式有誤時回傳錯誤
+    pub fn parse(entries: &[ScheduleEntry]) -> Result<Self> {
+        let slots = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| parse_entry(entry).with_context(|| format!("排程第 {} 個時段有誤", i + 1)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { slots })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// 第 minute 分鐘所在的時段（索引）
+    fn active(&self, minute: u16) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.contains(minute))
+    }
+}
+
+fn parse_entry(entry: &ScheduleEntry) -> Result<ScheduleSlot> {
+    let start = parse_time(&entry.start)?;
+    let end = parse_time(&entry.end)?;
+    if start % MINUTES_PER_DAY == end % MINUTES_PER_DAY {
+        bail!("開始與結束時間相同（{}）", entry.start.trim());
+    }
+    let mode = match entry.mode.trim().to_ascii_lowercase().as_str() {
+        "" => None,
+        "ucl" => Some(InputMode::Ucl),
+        "english" => Some(InputMode::English),
+        other => bail!("未知的模式 '{}'（可用: ucl、english）", other),
+    };
+    let profile = Some(entry.profile.trim()).filter(|name| !name.is_empty()).map(str::to_string);
+    // 24:00 換算成隔天 00:00
+    Ok(ScheduleSlot { start: start % MINUTES_PER_DAY, end: end % MINUTES_PER_DAY, mode, profile, gui: entry.gui })
+}
+
+/// 解析 HH:MM（允許 24:00），回傳當天第幾分鐘
+fn parse_time(value: &str) -> Result<u16> {
+    let value = value.trim();
+    let parsed = value
+        .split_once(':')
+        .and_then(|(hour, minute)| Some((hour.parse::<u16>().ok()?, minute.parse::<u16>().ok()?)))
+        .filter(|&(hour, minute)| minute < 60 && (hour < 24 || (hour == 24 && minute == 0)));
+    match parsed {
+        Some((hour, minute)) => Ok(hour * 60 + minute),
+        None => bail!("時間 '{}' 格式有誤（應為 HH:MM）", value),
+    }
+}
+
+/// 排程計時器：記住目前所在的時段，進入新時段時回傳一次
+pub struct ScheduleTimer {
+    schedule: Schedule,
+    /// 上次檢查時所在的時段，None 表示還沒檢查過
+    current: Option<Option<usize>>,
+}
+
+impl ScheduleTimer {
+    pub fn new(schedule: Schedule) -> Self {
+        Self { schedule, current: None }
+    }
+
+    /// 以目前時間（當天第幾分鐘）檢查，剛進入某個時段時回傳該時段
+    pub fn poll(&mut self, minute: u16) -> Option<&ScheduleSlot> {
+        let active = self.schedule.active(minute);
+        if self.current == Some(active) {
+            return None;
+        }
+        self.current = Some(active);
+        active.map(|i| &self.schedule.slots[i])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(start: &str, end: &str, mode: &str, profile: &str) -> ScheduleEntry {
+        ScheduleEntry { start: start.into(), end: end.into(), mode: mode.into(), profile: profile.into(), gui: None }
+    }
+
+    #[test]
+    fn test_schedule_switches_on_entering_slot() {
+        let schedule = Schedule::parse(&[
+            entry("09:00", "18:00", "english", "work"),
+            entry("20:00", "2:00", "UCL", ""),
+        ])
+        .unwrap();
+        let at = |hour: u16, minute: u16| hour * 60 + minute;
+
+        let mut timer = ScheduleTimer::new(schedule);
+        // 啟動時已在時段內也套用一次
+        let slot = timer.poll(at(10, 0)).unwrap();
+        assert_eq!((slot.mode, slot.profile.as_deref()), (Some(InputMode::English), Some("work")));
+        assert_eq!(timer.poll(at(17, 59)), None);
+        // 離開時段維持原狀
+        assert_eq!(timer.poll(at(18, 0)), None);
+        let slot = timer.poll(at(20, 0)).unwrap();
+        assert_eq!((slot.mode, slot.profile.as_deref()), (Some(InputMode::Ucl), None));
+        assert_eq!(slot.label(), "20:00–02:00");
+        // 跨過午夜仍是同一個時段
+        assert_eq!(timer.poll(at(1, 30)), None);
+        assert_eq!(timer.poll(at(2, 0)), None);
+
+        assert!(Schedule::parse(&[entry("9:00", "24:00", "", "")]).is_ok());
+        assert!(Schedule::parse(&[entry("25:00", "18:00", "", "")]).is_err());
+        assert!(Schedule::parse(&[entry("09:00", "09:00", "", "")]).is_err());
+        assert!(Schedule::parse(&[entry("09:00", "18:00", "game", "")]).is_err());
+    }
+}
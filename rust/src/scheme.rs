@@ -0,0 +1,236 @@
+//! 輸入法方案包模組
+//!
+//! 方案包 = 字典 + 補碼規則 + 選字鍵 + 符號表，打包成一個目錄：
+//!
+//! ```text
+//! schemes/
+//!   array30/
+//!     scheme.json   方案描述（本模組的 InputScheme）
+//!     array30.json  字典（chardefs 格式，與 liu.json 相同）
+//!     symbols.json  符號表（可選，chardefs 格式，會合併進字典；可另帶 categories 供符號面板與符號選單使用）
+//!     rules.json    特殊碼規則（可選，見 rules 模組）
+//! ```
+//!
+//! 沒有指定方案時使用內建的嘸蝦米方案（執行檔目錄下的 liu.json）
+
+use crate::rules::RuleDef;
+use crate::validation;
+use anyhow::{Context, Result};
+use tracing::info;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 方案包所在的子目錄名稱（相對於執行檔目錄）
+pub const SCHEMES_DIR: &str = "schemes";
+/// 方案描述檔名稱
+pub const SCHEME_FILE: &str = "scheme.json";
+/// 特殊碼規則檔名稱（方案目錄下，內建方案為執行檔目錄）
+pub const RULES_FILE: &str = "rules.json";
+
+/// 輸入法方案
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputScheme {
+    /// 方案名稱（顯示用）
+    pub name: String,
+    /// 字典檔名（相對於方案目錄）
+    pub dictionary: String,
+    /// 符號表檔名（相對於方案目錄，可選）
+    pub symbols: Option<String>,
+    /// 字根最大長度
+    pub max_code_length: usize,
+    /// 補碼鍵：第 i 個字元選擇第 i+2 個候選字
+    /// 例如嘸蝦米的 "vrsfw"：v → 候選2、r → 候選3、s → 候選4、f → 候選5、w → 候選6
+    pub complement_keys: String,
+    /// 選字鍵：第 i 個字元選擇當前頁的第 i+1 個候選字
+    pub selection_keys: String,
+    /// 特殊碼規則（優先於字典查詢）
+    pub rules: Vec<RuleDef>,
+}
+
+impl Default for InputScheme {
+    /// 內建嘸蝦米方案（與原本寫死的行為一致）
+    fn default() -> Self {
+        Self {
+            name: "嘸蝦米".to_string(),
+            dictionary: "liu.json".to_string(),
+            symbols: None,
+            max_code_length: 5,
+            complement_keys: "vrsfw".to_string(),
+            selection_keys: "1234567890".to_string(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl InputScheme {
+    /// 載入方案
+    /// name 為 None 時回傳內建嘸蝦米方案與執行檔目錄
+    /// 否則讀取 schemes/<name>/scheme.json，回傳方案與方案目錄
+    /// strict 為 true 時依內建 schema 嚴格驗證方案描述
+    pub fn load(name: Option<&str>, strict: bool) -> Result<(Self, PathBuf)> {
+        let exe_dir = exe_dir()?;
+
+        let name = match name {
+            Some(name) if !name.is_empty() => name,
+            _ => {
+                let mut scheme = Self::default();
+                scheme.rules.extend(load_rules_file(&exe_dir.join(RULES_FILE), strict)?);
+                return Ok((scheme, exe_dir));
+            }
+        };
+
+        let scheme_dir = exe_dir.join(SCHEMES_DIR).join(name);
+        let scheme_path = scheme_dir.join(SCHEME_FILE);
+        if !scheme_path.exists() {
+            return Err(anyhow::anyhow!(
+                "找不到方案 '{}'\n請確認 {:?} 存在\n可用方案: {:?}",
+                name,
+                scheme_path,
+                Self::available()
+            ));
+        }
+
+        info!("載入輸入法方案: {:?}", scheme_path);
+        let content = fs::read_to_string(&scheme_path)
+            .with_context(|| format!("無法讀取方案描述: {:?}", scheme_path))?;
+        let mut scheme = Self::parse(&content, strict)
+            .with_context(|| format!("方案描述 {:?} 有誤", scheme_path))?;
+        scheme.rules.extend(load_rules_file(&scheme_dir.join(RULES_FILE), strict)?);
+
+        Ok((scheme, scheme_dir))
+    }
+
+    /// 從 JSON 字串解析方案描述（未填寫的欄位沿用嘸蝦米預設值）
+    pub fn from_json(content: &str) -> Result<Self> {
+        Self::parse(content, false)
+    }
+
+    /// 從 JSON 字串解析方案描述，strict 為 true 時拒絕未知欄位與空字串
+    pub fn parse(content: &str, strict: bool) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(content)
+            .with_context(|| "無法解析方案描述 JSON 格式")?;
+        validation::into_result(validation::validate_scheme(&value, strict), "方案描述")?;
+        let scheme: Self = serde_json::from_value(value)
+            .with_context(|| "無法解析方案描述 JSON 格式")?;
+        if scheme.max_code_length == 0 {
+            return Err(anyhow::anyhow!("方案 '{}' 的 max_code_length 必須大於 0", scheme.name));
+        }
+        Ok(scheme)
+    }
+
+    /// 列出 schemes 目錄下所有可用的方案名稱
+    pub fn available() -> Vec<String> {
+        let Ok(exe_dir) = exe_dir() else {
+            return Vec::new();
+        };
+        Self::available_in(&exe_dir.join(SCHEMES_DIR))
+    }
+
+    fn available_in(dir: &Path) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().join(SCHEME_FILE).exists())
+            .filter_map(|e| e.file_name().to_str().map(|s| s.to_string()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// 補碼鍵對應的候選字索引（0 起算），不是補碼鍵時回傳 None
+    pub fn complement_index(&self, ch: char) -> Option<usize> {
+        self.complement_keys.chars().position(|c| c == ch).map(|i| i + 1)
+    }
+
+    /// 選字鍵對應的候選字索引（0 起算），不是選字鍵時回傳 None
+    pub fn selection_index(&self, ch: char) -> Option<usize> {
+        self.selection_keys.chars().position(|c| c == ch)
+    }
+}
+
+/// 讀取特殊碼規則檔，檔案不存在時回傳空列表
+fn load_rules_file(path: &Path, strict: bool) -> Result<Vec<RuleDef>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    info!("載入特殊碼規則: {:?}", path);
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("無法讀取特殊碼規則: {:?}", path))?;
+    parse_rules(&content, strict).with_context(|| format!("特殊碼規則 {:?} 有誤", path))
+}
+
+fn parse_rules(content: &str, strict: bool) -> Result<Vec<RuleDef>> {
+    let value: serde_json::Value = serde_json::from_str(content)
+        .with_context(|| "無法解析特殊碼規則 JSON 格式")?;
+    validation::into_result(validation::validate_rules(&value, strict), "特殊碼規則")?;
+    Ok(serde_json::from_value(value)?)
+}
+
+/// 取得執行檔所在目錄
+fn exe_dir() -> Result<PathBuf> {
+    let exe_path = std::env::current_exe()?;
+    let exe_dir = exe_path.parent()
+        .ok_or_else(|| std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "無法取得執行檔目錄"
+        ))?;
+    Ok(exe_dir.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_scheme_matches_boshiamy() {
+        let scheme = InputScheme::default();
+        assert_eq!(scheme.complement_index('v'), Some(1));
+        assert_eq!(scheme.complement_index('w'), Some(5));
+        assert_eq!(scheme.complement_index('a'), None);
+        assert_eq!(scheme.selection_index('1'), Some(0));
+        assert_eq!(scheme.selection_index('0'), Some(9));
+        assert_eq!(scheme.max_code_length, 5);
+    }
+
+    #[test]
+    fn test_from_json_partial() {
+        // 行列 30：沒有補碼、字根最多 4 碼
+        let scheme = InputScheme::from_json(
+            r#"{ "name": "行列30", "dictionary": "array30.json", "max_code_length": 4, "complement_keys": "" }"#,
+        ).unwrap();
+        assert_eq!(scheme.name, "行列30");
+        assert_eq!(scheme.dictionary, "array30.json");
+        assert_eq!(scheme.max_code_length, 4);
+        assert_eq!(scheme.complement_index('v'), None);
+        // 未填寫的欄位沿用預設值
+        assert_eq!(scheme.selection_keys, "1234567890");
+    }
+
+    #[test]
+    fn test_from_json_rejects_zero_length() {
+        assert!(InputScheme::from_json(r#"{ "max_code_length": 0 }"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_strict_rejects_unknown_field() {
+        let content = r#"{ "name": "行列30", "max_lenght": 4 }"#;
+        assert!(InputScheme::parse(content, false).is_ok());
+        let error = InputScheme::parse(content, true).unwrap_err();
+        assert!(error.to_string().contains("max_lenght"));
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let scheme = InputScheme::from_json(r#"{ "rules": [{ "pattern": "^dt$", "generator": "date" }] }"#).unwrap();
+        assert_eq!(scheme.rules[0].generator, "date");
+
+        let rules = parse_rules(r#"[{ "pattern": "^zz$", "generator": "repeat" }]"#, true).unwrap();
+        assert_eq!(rules.len(), 1);
+        assert!(parse_rules(r#"[{ "generator": "repeat" }]"#, false).is_err());
+    }
+}
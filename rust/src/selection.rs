@@ -0,0 +1,114 @@
+//! 取得選取的文字
+//!
+//! 選取反查熱鍵（Ctrl+Alt+K）不必先手動複製：這裡模擬 Ctrl+C 讓前景程式把選取的文字放進剪貼簿，
+//! 讀出來之後再把剪貼簿還原成原本的文字，使用者原本複製的東西不會被蓋掉。
+//!
+//! 熱鍵的 Ctrl、Alt 還按著時送出 Ctrl+C 會變成 Ctrl+Alt+C，所以先等修飾鍵都放開。
+//! 前景程式沒有選取文字時多半不會動剪貼簿，以剪貼簿序號判斷有沒有複製到東西，不會拿舊的內容去查。
+//! 剪貼簿原本不是文字（例如圖片）時無法還原，只記一筆警告。
+
+use anyhow::{bail, Result};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use windows::Win32::System::DataExchange::GetClipboardSequenceNumber;
+use windows::Win32::UI::Input::KeyboardAndMouse::*;
+
+/// 等待使用者放開熱鍵修飾鍵的上限
+const MODIFIER_RELEASE_TIMEOUT: Duration = Duration::from_millis(1000);
+/// 等待前景程式把選取的文字放進剪貼簿的上限
+const COPY_TIMEOUT: Duration = Duration::from_millis(500);
+/// 輪詢間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+/// 無法取得剪貼簿序號時的固定等待
+const COPY_FALLBACK_DELAY: Duration = Duration::from_millis(150);
+
+/// 會影響 Ctrl+C 的修飾鍵
+const MODIFIER_KEYS: [VIRTUAL_KEY; 5] = [VK_CONTROL, VK_MENU, VK_SHIFT, VK_LWIN, VK_RWIN];
+
+/// 模擬 Ctrl+C 取得前景程式選取的文字，讀完後還原剪貼簿；沒有選取文字時回傳 None
+pub fn copy_selection() -> Result<Option<String>> {
+    if !wait_until(MODIFIER_RELEASE_TIMEOUT, POLL_INTERVAL, || !MODIFIER_KEYS.iter().any(|&vk| is_key_down(vk))) {
+        bail!("修飾鍵一直沒有放開，略過選取反查");
+    }
+
+    let mut clipboard = arboard::Clipboard::new()?;
+    let backup = clipboard.get_text().ok();
+    let seq_before = unsafe { GetClipboardSequenceNumber() };
+    send_ctrl_c()?;
+
+    let copied = if seq_before == 0 {
+        // 沒有 WINSTA_ACCESSCLIPBOARD 權限時拿不到序號，退回固定等待，內容和原本一樣就當作沒有複製到
+        thread::sleep(COPY_FALLBACK_DELAY);
+        clipboard.get_text().ok().filter(|text| Some(text) != backup.as_ref())
+    } else if wait_until(COPY_TIMEOUT, POLL_INTERVAL, || unsafe { GetClipboardSequenceNumber() } != seq_before) {
+        clipboard.get_text().ok()
+    } else {
+        debug!("剪貼簿沒有變化，前景程式沒有選取文字");
+        return Ok(None);
+    };
+
+    match &backup {
+        Some(text) => {
+            if let Err(e) = clipboard.set_text(text.as_str()) {
+                warn!("還原剪貼簿失敗: {}", e);
+            }
+        }
+        None => warn!("剪貼簿原本不是文字，無法還原"),
+    }
+    info!("已取得選取的文字（{} 字）", copied.as_deref().map_or(0, |text| text.chars().count()));
+    Ok(copied.filter(|text| !text.trim().is_empty()))
+}
+
+fn is_key_down(vk: VIRTUAL_KEY) -> bool {
+    unsafe { GetAsyncKeyState(vk.0 as i32) < 0 }
+}
+
+/// 送出 Ctrl+C
+fn send_ctrl_c() -> Result<()> {
+    let key = |vk: VIRTUAL_KEY, flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 { ki: KEYBDINPUT { wVk: vk, wScan: 0, dwFlags: flags, time: 0, dwExtraInfo: 0 } },
+    };
+    let inputs = [
+        key(VK_CONTROL, KEYBD_EVENT_FLAGS(0)),
+        key(VK_C, KEYBD_EVENT_FLAGS(0)),
+        key(VK_C, KEYEVENTF_KEYUP),
+        key(VK_CONTROL, KEYEVENTF_KEYUP),
+    ];
+    let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        bail!("SendInput 只送出 {}/{} 個 Ctrl+C 事件（可能被 UIPI 擋下）", sent, inputs.len());
+    }
+    Ok(())
+}
+
+/// 每隔 poll 檢查一次 done，成立時回傳 true，超過 timeout 仍不成立時回傳 false
+fn wait_until(timeout: Duration, poll: Duration, mut done: impl FnMut() -> bool) -> bool {
+    let start = Instant::now();
+    loop {
+        if done() {
+            return true;
+        }
+        if start.elapsed() >= timeout {
+            return false;
+        }
+        thread::sleep(poll);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_until() {
+        let mut calls = 0;
+        assert!(wait_until(Duration::from_millis(100), Duration::ZERO, || {
+            calls += 1;
+            calls == 3
+        }));
+        assert_eq!(calls, 3);
+        assert!(!wait_until(Duration::from_millis(10), Duration::from_millis(1), || false));
+    }
+}
@@ -0,0 +1,88 @@
+//! 送字的長度與速率限制
+//!
+//! 一次貼上超長文字或短時間內送出大量文字，可能被遊戲判定為洗頻。設定單次送出的字數上限
+//! 與每分鐘的字數上限後，超過的部分留在佇列中，之後由訊息循環分段送出
+//! （見 InputSimulator::flush_backlog）。兩個上限都是 0 時不限制（預設）。
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 每分鐘上限的計算區間
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// 超過單次上限而分段送出時，兩段之間至少間隔的時間
+const CHUNK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 送字限制與最近的送出紀錄
+#[derive(Debug, Clone, Default)]
+pub struct SendLimiter {
+    /// 單次送出的字數上限（0 表示不限）
+    max_chars: usize,
+    /// 每分鐘送出的字數上限（0 表示不限）
+    max_chars_per_minute: usize,
+    /// 最近一分鐘內每次送出的時間與字數
+    history: VecDeque<(Instant, usize)>,
+    /// 上一次分段送出的時間（文字沒有被切開時為 None）
+    last_chunk: Option<Instant>,
+}
+
+impl SendLimiter {
+    pub fn new(max_chars: usize, max_chars_per_minute: usize) -> Self {
+        Self { max_chars, max_chars_per_minute, ..Default::default() }
+    }
+
+    /// 是否有設定任何上限
+    pub fn is_enabled(&self) -> bool {
+        self.max_chars > 0 || self.max_chars_per_minute > 0
+    }
+
+    /// 現在最多可以送出幾個字（usize::MAX 表示不限），0 表示要等一下再送
+    pub fn allowance(&mut self, now: Instant) -> usize {
+        if self.last_chunk.is_some_and(|last| now.duration_since(last) < CHUNK_INTERVAL) {
+            return 0;
+        }
+        let mut allowance = if self.max_chars > 0 { self.max_chars } else { usize::MAX };
+        if self.max_chars_per_minute > 0 {
+            while self.history.front().is_some_and(|(time, _)| now.duration_since(*time) >= RATE_WINDOW) {
+                self.history.pop_front();
+            }
+            let sent: usize = self.history.iter().map(|(_, chars)| chars).sum();
+            allowance = allowance.min(self.max_chars_per_minute.saturating_sub(sent));
+        }
+        allowance
+    }
+
+    /// 記錄一次送出；split 表示文字超過上限被切開，下一段要間隔一下再送
+    pub fn record(&mut self, now: Instant, chars: usize, split: bool) {
+        if self.max_chars_per_minute > 0 {
+            self.history.push_back((now, chars));
+        }
+        self.last_chunk = split.then_some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowance_limits_length_and_rate() {
+        let start = Instant::now();
+        assert!(!SendLimiter::default().is_enabled());
+        assert_eq!(SendLimiter::default().allowance(start), usize::MAX);
+
+        let mut limiter = SendLimiter::new(10, 25);
+        assert_eq!(limiter.allowance(start), 10);
+        limiter.record(start, 10, true);
+        // 分段送出後要間隔一下
+        assert_eq!(limiter.allowance(start + Duration::from_millis(100)), 0);
+        let later = start + CHUNK_INTERVAL;
+        assert_eq!(limiter.allowance(later), 10);
+        limiter.record(later, 10, false);
+        // 一分鐘內只剩 5 字
+        assert_eq!(limiter.allowance(later), 5);
+        limiter.record(later, 5, false);
+        assert_eq!(limiter.allowance(later + Duration::from_secs(30)), 0);
+        // 最早那筆過了一分鐘後釋出
+        assert_eq!(limiter.allowance(start + RATE_WINDOW), 10);
+    }
+}
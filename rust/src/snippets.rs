@@ -0,0 +1,146 @@
+//! 文字片段（snippets.json）
+//!
+//! 地址、email 簽名檔、遊戲指令之類常打的長字串，可以在使用者資料目錄的 snippets.json 定義成
+//! 「字根 → 任意文字」，一個字根可以有多段文字：
+//!
+//! ```json
+//! {
+//!   "addr": "台北市中正區重慶南路一段 122 號",
+//!   "sig": ["王小明\nxiaoming@example.com", "王小明 敬上 {date}"],
+//!   "gg": "/party invite {clipboard}"
+//! }
+//! ```
+//!
+//! （錄製的按鍵巨集已經使用 macros.json，文字片段另外存放在 snippets.json。）
+//! 片段以最高優先順序的字碼表層載入（見 Dictionary::set_snippets），同一字根的候選排在主字碼表之前；
+//! 候選列只顯示開頭幾個字的預覽，送出時才送出全文，並展開其中的佔位符：
+//!
+//! - {date}：目前日期（%Y-%m-%d）
+//! - {time}：目前時間（%H:%M）
+//! - {clipboard}：剪貼簿中的文字（讀不到時為空字串）
+//!
+//! 其他大括號原樣輸出。
+
+use crate::dictionary::Dictionary;
+use crate::rules::LocalTime;
+use crate::time_macros;
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// 文字片段檔名（放在使用者資料目錄，見 Profile::data_path）
+pub const SNIPPETS_FILE: &str = "snippets.json";
+
+/// 候選列預覽的最多字數
+const PREVIEW_CHARS: usize = 12;
+
+/// 載入文字片段，檔案不存在時回傳 None
+pub fn load(path: &Path) -> Result<Option<Dictionary>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).with_context(|| format!("無法讀取文字片段: {:?}", path))?;
+    let dictionary = parse(&content).with_context(|| format!("文字片段 {:?} 有誤", path))?;
+    info!("載入文字片段: {:?}（{} 個字根）", path, dictionary.code_to_chars.len());
+    Ok(Some(dictionary))
+}
+
+/// 解析文字片段，轉成以預覽顯示、送出全文的字碼表（見 Dictionary::from_json 的 display/output 格式）
+pub fn parse(content: &str) -> Result<Dictionary> {
+    let root: Map<String, Value> = serde_json::from_str(content).context("格式應為 { \"字根\": \"文字\" }")?;
+    let mut chardefs = Map::new();
+    for (code, value) in root {
+        let code = code.trim().to_lowercase();
+        if code.is_empty() || code.chars().any(char::is_whitespace) {
+            bail!("字根 '{}' 不可為空或包含空白", code);
+        }
+        let texts: Vec<&str> = match &value {
+            Value::String(text) => vec![text.as_str()],
+            Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+            _ => bail!("字根 '{}' 的內容應為字串或字串陣列", code),
+        };
+        let mut entries: Vec<Value> = Vec::new();
+        let mut previews: Vec<String> = Vec::new();
+        for text in texts.into_iter().filter(|text| !text.is_empty()) {
+            // 同一字根下預覽相同時加上編號，送出時才找得到各自的全文
+            let mut display = preview(text);
+            if previews.contains(&display) {
+                display = format!("{}#{}", display, previews.len() + 1);
+            }
+            previews.push(display.clone());
+            entries.push(json!({ "display": display, "output": text }));
+        }
+        if entries.is_empty() {
+            bail!("字根 '{}' 沒有任何文字", code);
+        }
+        chardefs.insert(code, Value::Array(entries));
+    }
+    Dictionary::from_json(&json!({ "chardefs": chardefs }).to_string())
+}
+
+/// 候選列顯示的預覽：換行顯示成 ⏎，超過 PREVIEW_CHARS 字時截斷
+fn preview(text: &str) -> String {
+    let flat = text.trim().replace("\r\n", "\n").replace('\n', "⏎");
+    if flat.chars().count() <= PREVIEW_CHARS {
+        return flat;
+    }
+    let mut short: String = flat.chars().take(PREVIEW_CHARS).collect();
+    short.push('…');
+    short
+}
+
+/// 展開片段中的佔位符；clipboard 只在用到 {clipboard} 時才呼叫
+pub fn expand(text: &str, now: LocalTime, clipboard: impl FnOnce() -> Option<String>) -> String {
+    let mut output = text
+        .replace("{date}", &time_macros::expand("%Y-%m-%d", now))
+        .replace("{time}", &time_macros::expand("%H:%M", now));
+    if output.contains("{clipboard}") {
+        output = output.replace("{clipboard}", &clipboard().unwrap_or_default());
+    }
+    output
+}
+
+/// 讀取剪貼簿中的文字（{clipboard} 用），讀不到時回傳 None
+#[cfg(feature = "app")]
+pub fn clipboard_text() -> Option<String> {
+    arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()).ok()
+}
+
+/// 沒有剪貼簿時 {clipboard} 一律展開成空字串
+#[cfg(not(feature = "app"))]
+pub fn clipboard_text() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::SNIPPETS_LAYER;
+
+    #[test]
+    fn test_snippets() {
+        let dictionary = parse(r#"{ "Addr": "台北市中正區重慶南路一段 122 號", "sig": ["王小明\n{date} {time}", "王小明\n{date} {time}"] }"#)
+            .unwrap();
+        assert_eq!(dictionary.lookup("addr").unwrap().to_vec(), ["台北市中正區重慶南路一段…"]);
+        assert_eq!(dictionary.lookup("sig").unwrap().to_vec(), ["王小明⏎{date} {…", "王小明⏎{date} {…#2"]);
+        assert_eq!(dictionary.output_for("sig", "王小明⏎{date} {…#2").as_deref(), Some("王小明\n{date} {time}"));
+        assert!(parse(r#"{ "a b": "x" }"#).is_err());
+
+        // 片段層排在主字碼表之前，重新套用時不會重複
+        let mut main = Dictionary::from_json(r#"{ "chardefs": { "addr": ["甲"] } }"#).unwrap();
+        main.set_snippets(Some(dictionary.clone()));
+        main.set_snippets(Some(dictionary));
+        assert_eq!(main.lookup("addr").unwrap().to_vec(), ["台北市中正區重慶南路一段…", "甲"]);
+        assert_eq!(main.layer_of("addr", "台北市中正區重慶南路一段…"), Some(SNIPPETS_LAYER));
+        main.set_snippets(None);
+        assert_eq!(main.lookup("addr").unwrap().to_vec(), ["甲"]);
+        assert!(parse(r#"{ "a": [] }"#).is_err());
+
+        let t = LocalTime { year: 2024, month: 3, day: 5, hour: 14, minute: 7, second: 9 };
+        assert_eq!(expand("{date} {time} {x}", t, || panic!("不應讀取剪貼簿")), "2024-03-05 14:07 {x}");
+        assert_eq!(expand("/invite {clipboard}", t, || Some("小明".to_string())), "/invite 小明");
+        assert_eq!(expand("[{clipboard}]", t, || None), "[]");
+    }
+}
@@ -0,0 +1,124 @@
+//! 候選排序策略
+//!
+//! 同一字根查出的候選怎麼排，由設定 candidate_sort 選擇：
+//!
+//! - "table"：字碼表順序（含字碼表的權重），每次都一樣，方便背位置（預設）
+//! - "frequency"：依選用次數由多到少
+//! - "recency"：最近選用過的排前面
+//!
+//! 排序在 InputMethodState::lookup_candidates（與補碼、符號組合的查詢）裡套用，
+//! 使用紀錄一律由 FrequencyStats 記錄，切換策略不會遺失紀錄。補碼鍵（v/r/s/f）與數字鍵依排序後的位置選字，
+//! 依使用紀錄排序時位置會跟著變，所以預設用字碼表順序，其他策略需要自行開啟。
+//! 要加新的策略時實作 CandidateSorter 並加進 parse 即可。
+
+use crate::candidate::{Candidate, CandidateSource};
+use crate::frequency::FrequencyStats;
+use anyhow::{bail, Result};
+use std::cmp::Reverse;
+
+/// 候選排序策略
+pub trait CandidateSorter: Send {
+    /// 設定值名稱（見 parse）
+    fn name(&self) -> &'static str;
+
+    /// 排序同一字根查出的候選；sort_by_key 是穩定排序，權重相同時保持字碼表順序
+    fn sort(&self, code: &str, candidates: &mut [Candidate], usage: &FrequencyStats);
+
+    /// 是否依使用紀錄調整順序（候選據此帶上使用次數，並標出學習來源）
+    fn is_adaptive(&self) -> bool {
+        true
+    }
+
+    /// 記下使用紀錄後排序
+    fn arrange(&self, code: &str, candidates: &mut [Candidate], usage: &FrequencyStats) {
+        if self.is_adaptive() {
+            tag_usage(candidates, usage);
+        }
+        self.sort(code, candidates, usage);
+    }
+}
+
+/// 字碼表順序
+pub struct TableOrder;
+
+impl CandidateSorter for TableOrder {
+    fn name(&self) -> &'static str {
+        "table"
+    }
+
+    fn sort(&self, _code: &str, _candidates: &mut [Candidate], _usage: &FrequencyStats) {}
+
+    fn is_adaptive(&self) -> bool {
+        false
+    }
+}
+
+/// 依選用次數
+pub struct ByFrequency;
+
+impl CandidateSorter for ByFrequency {
+    fn name(&self) -> &'static str {
+        "frequency"
+    }
+
+    fn sort(&self, code: &str, candidates: &mut [Candidate], usage: &FrequencyStats) {
+        usage.sort(code, candidates);
+    }
+}
+
+/// 依最後一次選用的先後
+pub struct ByRecency;
+
+impl CandidateSorter for ByRecency {
+    fn name(&self) -> &'static str {
+        "recency"
+    }
+
+    fn sort(&self, code: &str, candidates: &mut [Candidate], usage: &FrequencyStats) {
+        candidates.sort_by_key(|candidate| Reverse(usage.last_used(code, &candidate.text)));
+    }
+}
+
+/// 從設定值解析（"table"、"frequency"、"recency"，不分大小寫）
+pub fn parse(value: &str) -> Result<Box<dyn CandidateSorter>> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "" | "table" => Ok(Box::new(TableOrder)),
+        "frequency" => Ok(Box::new(ByFrequency)),
+        "recency" => Ok(Box::new(ByRecency)),
+        other => bail!("未知的候選排序方式 '{}'（可用: table、frequency、recency）", other),
+    }
+}
+
+/// 記下每個候選的使用次數（排序權重），有使用紀錄的候選來源標為學習
+/// 萬用字元查詢的候選各有各的字根，不排序，只標出使用紀錄
+pub fn tag_usage(candidates: &mut [Candidate], usage: &FrequencyStats) {
+    for candidate in candidates {
+        candidate.weight = usage.count(&candidate.code, &candidate.text);
+        if candidate.weight > 0 {
+            candidate.source = Some(CandidateSource::Learned);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorters() {
+        let mut usage = FrequencyStats::new();
+        usage.record("a", "乙");
+        usage.record("a", "乙");
+        usage.record("a", "丫");
+        let order = |sorter: &dyn CandidateSorter| {
+            let mut candidates: Vec<Candidate> = ["一", "乙", "丫"].iter().map(|text| Candidate::plain("a", *text)).collect();
+            sorter.arrange("a", &mut candidates, &usage);
+            candidates.into_iter().map(|candidate| candidate.text).collect::<Vec<_>>()
+        };
+        assert_eq!(order(parse("").unwrap().as_ref()), ["一", "乙", "丫"]);
+        assert_eq!(order(parse("frequency").unwrap().as_ref()), ["乙", "丫", "一"]);
+        assert_eq!(order(parse("Recency").unwrap().as_ref()), ["丫", "乙", "一"]);
+        assert_eq!(parse("recency").unwrap().name(), "recency");
+        assert!(parse("random").is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! SQLite 字典後端
+//!
+//! 大型字碼表（數十萬字根加上詞庫）整份載入 HashMap 既慢又佔記憶體。
+//! 改用 SQLite 後端時，第一次啟動會把字碼表 JSON 轉成同目錄下的 .sqlite 檔
+//! （例如 liu.json → liu.sqlite），之後啟動只需開啟資料庫，查詢走索引。
+//! 字碼表 JSON 的大小或修改時間變了會自動重建。
+
+use crate::dictionary::Dictionary;
+use crate::dictionary_cache::source_stamp;
+use anyhow::{Context, Result};
+use tracing::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 資料庫格式版本，格式改變時遞增以觸發重建
+const FORMAT_VERSION: &str = "1";
+
+const SCHEMA: &str = "
+    CREATE TABLE chardefs (
+        code    TEXT NOT NULL,
+        seq     INTEGER NOT NULL,
+        display TEXT NOT NULL,
+        output  TEXT,
+        PRIMARY KEY (code, seq)
+    ) WITHOUT ROWID;
+    CREATE INDEX chardefs_display ON chardefs (display);
+    CREATE TABLE meta (
+        key   TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+/// SQLite 字典資料庫
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// 開啟字碼表對應的資料庫，不存在或已過期時先從 JSON 重建
+    pub fn open_or_build(json_path: &Path, strict: bool) -> Result<Self> {
+        let db_path = Self::db_path(json_path);
+        let source = source_stamp(json_path)?;
+
+        if db_path.exists() {
+            let conn = Connection::open(&db_path)
+                .with_context(|| format!("無法開啟字典資料庫: {:?}", db_path))?;
+            if Self::read_meta(&conn, "source").ok().flatten().as_deref() == Some(source.as_str())
+                && Self::read_meta(&conn, "format").ok().flatten().as_deref() == Some(FORMAT_VERSION)
+            {
+                info!("開啟字典資料庫: {:?}", db_path);
+                return Ok(Self { conn: Mutex::new(conn) });
+            }
+            drop(conn);
+            info!("字碼表已變更，重建字典資料庫: {:?}", db_path);
+            fs::remove_file(&db_path)
+                .with_context(|| format!("無法刪除過期的字典資料庫: {:?}", db_path))?;
+        }
+
+        info!("從 {:?} 建立字典資料庫（只在第一次或字碼表變更時執行）", json_path);
+        let dictionary = Dictionary::read_file(json_path, strict)?;
+
+        // 先寫到暫存檔再改名，建到一半被中斷時不會留下不完整的資料庫
+        let tmp_path = db_path.with_extension("sqlite.tmp");
+        let _ = fs::remove_file(&tmp_path);
+        let mut conn = Connection::open(&tmp_path)
+            .with_context(|| format!("無法建立字典資料庫: {:?}", tmp_path))?;
+        Self::import(&mut conn, &dictionary, &source)?;
+        drop(conn);
+        fs::rename(&tmp_path, &db_path)
+            .with_context(|| format!("無法寫入字典資料庫: {:?}", db_path))?;
+
+        let conn = Connection::open(&db_path)
+            .with_context(|| format!("無法開啟字典資料庫: {:?}", db_path))?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// 以記憶體資料庫匯入字典（測試用）
+    #[cfg(test)]
+    pub fn in_memory(dictionary: &Dictionary) -> Result<Self> {
+        let mut conn = Connection::open_in_memory()?;
+        Self::import(&mut conn, dictionary, "memory")?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    /// 字碼表對應的資料庫路徑（同目錄、副檔名改為 .sqlite）
+    pub fn db_path(json_path: &Path) -> PathBuf {
+        json_path.with_extension("sqlite")
+    }
+
+    fn import(conn: &mut Connection, dictionary: &Dictionary, source: &str) -> Result<()> {
+        conn.execute_batch(SCHEMA)?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO chardefs (code, seq, display, output) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (code, chars) in &dictionary.code_to_chars {
+                for (seq, display) in chars.iter().enumerate() {
+                    let output = dictionary.output_for(code, display);
+                    insert.execute(params![code, seq as i64, display, output])?;
+                }
+            }
+            let mut meta = tx.prepare("INSERT INTO meta (key, value) VALUES (?1, ?2)")?;
+            meta.execute(params!["source", source])?;
+            meta.execute(params!["format", FORMAT_VERSION])?;
+        }
+        tx.commit()?;
+        info!("字典資料庫已建立（{} 個字根）", dictionary.code_to_chars.len());
+        Ok(())
+    }
+
+    fn read_meta(conn: &Connection, key: &str) -> Result<Option<String>> {
+        Ok(conn
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?)
+    }
+
+    /// 查詢字根的候選字（依字碼表順序）
+    pub fn lookup(&self, code: &str) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached("SELECT display FROM chardefs WHERE code = ?1 ORDER BY seq")?;
+        let rows = stmt.query_map([code], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+    }
+
+    /// 是否有比 prefix 更長、且以 prefix 開頭的字根
+    pub fn has_longer_code(&self, prefix: &str) -> Result<bool> {
+        // TEXT 以 UTF-8 位元組比較，prefix 後接最大碼位即為所有以 prefix 開頭字串的上界
+        let upper = format!("{}\u{10FFFF}", prefix);
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached("SELECT 1 FROM chardefs WHERE code > ?1 AND code < ?2 LIMIT 1")?;
+        Ok(stmt.exists(params![prefix, upper])?)
+    }
+
+    /// 以 prefix 開頭的更長字根中，緊接在 prefix 後面的字根（補全提示用，依字母排序）
+    pub fn next_letters(&self, prefix: &str) -> Result<Vec<char>> {
+        let upper = format!("{}\u{10FFFF}", prefix);
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(
+            "SELECT DISTINCT substr(code, ?3, 1) AS next FROM chardefs WHERE code > ?1 AND code < ?2 ORDER BY next",
+        )?;
+        let rows = stmt.query_map(params![prefix, upper, prefix.chars().count() as i64 + 1], |row| row.get::<_, String>(0))?;
+        let mut letters = Vec::new();
+        for row in rows {
+            letters.extend(row?.chars().next());
+        }
+        Ok(letters)
+    }
+
+    /// 符合萬用字元樣式（* 代表任意長度，? 代表剛好一個字根）的字根，短的字根在前，最多 limit 個
+    pub fn codes_matching(&self, pattern: &str, limit: usize) -> Result<Vec<String>> {
+        // * 與 ? 和 GLOB 的意義相同；[ 另有意義，字根本身含有 [ 時要跳脫
+        let glob = pattern.replace('[', "[[]");
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(
+            "SELECT DISTINCT code FROM chardefs WHERE code GLOB ?1 ORDER BY length(code), code LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![glob, limit as i64], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+    }
+
+    /// 所有字根（依字母排序）
+    pub fn codes(&self) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached("SELECT DISTINCT code FROM chardefs ORDER BY code")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+    }
+
+    /// 候選的別名輸出
+    pub fn output_for(&self, code: &str, display: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(
+            "SELECT output FROM chardefs WHERE code = ?1 AND display = ?2 AND output IS NOT NULL LIMIT 1",
+        )?;
+        Ok(stmt.query_row(params![code, display], |row| row.get(0)).optional()?)
+    }
+
+    /// 反查：候選中包含指定文字的字根
+    pub fn codes_for(&self, text: &str) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached("SELECT DISTINCT code FROM chardefs WHERE display = ?1")?;
+        let rows = stmt.query_map([text], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
+    }
+
+    /// (字根數, 候選總數, 別名候選數)
+    pub fn counts(&self) -> Result<(usize, usize, usize)> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row(
+            "SELECT COUNT(DISTINCT code), COUNT(*), COUNT(output) FROM chardefs",
+            [],
+            |row| Ok((row.get::<_, i64>(0)? as usize, row.get::<_, i64>(1)? as usize, row.get::<_, i64>(2)? as usize)),
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_store() -> SqliteStore {
+        let dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "a": ["一", "乙"], "ab": ["二"], "xo": [{ "display": "笑", "output": "😂" }, "一"] } }"#,
+        ).unwrap();
+        SqliteStore::in_memory(&dictionary).unwrap()
+    }
+
+    #[test]
+    fn test_lookup_keeps_order() {
+        let store = create_test_store();
+        assert_eq!(store.lookup("a").unwrap(), vec!["一", "乙"]);
+        assert!(store.lookup("zz").unwrap().is_empty());
+        assert_eq!(store.output_for("xo", "笑").unwrap().as_deref(), Some("😂"));
+        assert_eq!(store.output_for("xo", "一").unwrap(), None);
+    }
+
+    #[test]
+    fn test_has_longer_code() {
+        let store = create_test_store();
+        assert!(store.has_longer_code("a").unwrap());
+        assert!(!store.has_longer_code("ab").unwrap());
+        assert!(!store.has_longer_code("b").unwrap());
+        assert_eq!(store.next_letters("a").unwrap(), ['b']);
+        assert_eq!(store.next_letters("").unwrap(), ['a', 'x']);
+        assert!(store.next_letters("ab").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_codes_matching() {
+        let store = create_test_store();
+        assert_eq!(store.codes_matching("a*", 10).unwrap(), vec!["a", "ab"]);
+        assert_eq!(store.codes_matching("*o", 10).unwrap(), vec!["xo"]);
+        assert_eq!(store.codes_matching("a*", 1).unwrap(), vec!["a"]);
+        assert_eq!(store.codes_matching("a?", 10).unwrap(), vec!["ab"]);
+        assert!(store.codes_matching("?", 10).unwrap().iter().all(|code| code.len() == 1));
+    }
+
+    #[test]
+    fn test_codes_for_and_counts() {
+        let store = create_test_store();
+        let mut codes = store.codes_for("一").unwrap();
+        codes.sort();
+        assert_eq!(codes, vec!["a", "xo"]);
+        assert_eq!(store.counts().unwrap(), (3, 5, 1));
+        assert_eq!(store.codes().unwrap(), vec!["a", "ab", "xo"]);
+    }
+
+    #[test]
+    fn test_open_or_build_rebuilds_when_source_changes() {
+        let dir = std::env::temp_dir().join(format!("uclliu-sqlite-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let json_path = dir.join("liu.json");
+
+        fs::write(&json_path, r#"{ "chardefs": { "a": ["一"] } }"#).unwrap();
+        let store = SqliteStore::open_or_build(&json_path, false).unwrap();
+        assert_eq!(store.lookup("a").unwrap(), vec!["一"]);
+        drop(store);
+
+        // 再次開啟直接使用既有資料庫
+        assert_eq!(SqliteStore::open_or_build(&json_path, false).unwrap().lookup("a").unwrap(), vec!["一"]);
+
+        // 字碼表內容（大小）改變後重建
+        fs::write(&json_path, r#"{ "chardefs": { "a": ["一", "乙"] } }"#).unwrap();
+        let store = SqliteStore::open_or_build(&json_path, false).unwrap();
+        assert_eq!(store.lookup("a").unwrap(), vec!["一", "乙"]);
+        drop(store);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
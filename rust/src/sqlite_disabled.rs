@@ -0,0 +1,49 @@
+//! SQLite 字典後端（未啟用）
+//!
+//! 關閉 app 功能編譯 lib 核心時（例如 wasm 查碼元件）沒有 rusqlite，以這個模組代替 sqlite_dictionary：
+//! SqliteStore 無法建立，設定 dictionary_backend = "sqlite" 時載入字典會回報錯誤，其餘查詢一律走記憶體。
+
+use anyhow::{bail, Result};
+use std::path::Path;
+
+/// SQLite 字典資料庫（這個版本無法建立）
+pub enum SqliteStore {}
+
+impl SqliteStore {
+    /// 回報這個版本沒有 SQLite 後端
+    pub fn open_or_build(json_path: &Path, _strict: bool) -> Result<Self> {
+        bail!("這個版本沒有 SQLite 字典後端，無法開啟 {:?}", json_path)
+    }
+
+    pub fn lookup(&self, _code: &str) -> Result<Vec<String>> {
+        match *self {}
+    }
+
+    pub fn has_longer_code(&self, _prefix: &str) -> Result<bool> {
+        match *self {}
+    }
+
+    pub fn next_letters(&self, _prefix: &str) -> Result<Vec<char>> {
+        match *self {}
+    }
+
+    pub fn codes_matching(&self, _pattern: &str, _limit: usize) -> Result<Vec<String>> {
+        match *self {}
+    }
+
+    pub fn codes(&self) -> Result<Vec<String>> {
+        match *self {}
+    }
+
+    pub fn output_for(&self, _code: &str, _display: &str) -> Result<Option<String>> {
+        match *self {}
+    }
+
+    pub fn codes_for(&self, _text: &str) -> Result<Vec<String>> {
+        match *self {}
+    }
+
+    pub fn counts(&self) -> Result<(usize, usize, usize)> {
+        match *self {}
+    }
+}
@@ -0,0 +1,103 @@
+//! 符號面板
+//!
+//! 托盤「符號面板」開啟一個小的置頂面板，上方是符號表的分類標籤，每個符號一個按鈕，
+//! 點一下即送到前景程式（和選字一樣經過送字佇列，受送字限制與抬手才上屏約束）。
+//! 符號表與符號選單相同（見 symbols 模組：symbol_table 設定、方案包附帶的符號表或內建的表）。
+//!
+//! 面板加上 WS_EX_NOACTIVATE，點擊時前景程式不會失去焦點。
+
+use crate::gui_window::escape_label;
+use crate::input_simulator::{InputSimulator, SendOutcome};
+use crate::overlay;
+use crate::symbols::SymbolTable;
+use fltk::{app, button::Button, enums::Align, frame::Frame, group::{Group, Tabs}, prelude::*, window::Window};
+use tracing::{debug, warn};
+use std::sync::{Arc, Mutex};
+use windows::Win32::Foundation::HWND;
+
+/// 每列幾個符號
+const COLUMNS: i32 = 12;
+/// 符號按鈕的邊長
+const CELL: i32 = 32;
+/// 四周的間距
+const MARGIN: i32 = 6;
+/// 標題列與分類標籤的高度
+const BAR_HEIGHT: i32 = 24;
+
+/// 符號面板
+pub struct SymbolPanel {
+    window: Window,
+}
+
+impl SymbolPanel {
+    pub fn new(table: &SymbolTable, simulator: Arc<Mutex<InputSimulator>>) -> Self {
+        let rows = table
+            .categories
+            .iter()
+            .map(|category| (category.symbols.len() as i32 + COLUMNS - 1) / COLUMNS)
+            .max()
+            .unwrap_or(1);
+        let width = COLUMNS * CELL + MARGIN * 2;
+        let height = BAR_HEIGHT * 2 + rows * CELL + MARGIN * 3;
+        let (screen_w, screen_h) = app::screen_size();
+        let mut window = Window::new(
+            screen_w as i32 - width - 20,
+            screen_h as i32 - height - 80,
+            width,
+            height,
+            "符號面板",
+        );
+        window.set_border(false);
+        // override 視窗在 Windows 上是不出現在工作列、顯示時不取得焦點的工具視窗，沒有標題列，自己畫一條
+        window.set_override();
+
+        let mut title = Frame::new(MARGIN, MARGIN, width - MARGIN * 2 - BAR_HEIGHT, BAR_HEIGHT, "符號面板");
+        title.set_align(Align::Left | Align::Inside);
+        let mut close_button = Button::new(width - MARGIN - BAR_HEIGHT, MARGIN, BAR_HEIGHT, BAR_HEIGHT, "×");
+
+        let tabs_y = MARGIN * 2 + BAR_HEIGHT;
+        let tabs = Tabs::new(MARGIN, tabs_y, width - MARGIN * 2, height - tabs_y - MARGIN, "");
+        let body_y = tabs_y + BAR_HEIGHT;
+        for category in &table.categories {
+            let group = Group::new(MARGIN, body_y, width - MARGIN * 2, rows * CELL + MARGIN, None)
+                .with_label(&escape_label(&category.name));
+            for (i, symbol) in category.symbols.iter().enumerate() {
+                let (row, column) = (i as i32 / COLUMNS, i as i32 % COLUMNS);
+                let mut button = Button::new(MARGIN + column * CELL, body_y + MARGIN + row * CELL, CELL, CELL, None)
+                    .with_label(&escape_label(symbol));
+                button.set_tooltip(symbol);
+                let simulator = simulator.clone();
+                let symbol = symbol.clone();
+                button.set_callback(move |_| send_symbol(&simulator, &symbol));
+            }
+            group.end();
+        }
+        tabs.end();
+        window.end();
+
+        {
+            let mut window = window.clone();
+            close_button.set_callback(move |_| window.hide());
+        }
+
+        Self { window }
+    }
+
+    pub fn show(&mut self) {
+        if !self.window.shown() {
+            self.window.show();
+            app::flush();
+            overlay::keep_on_top_without_focus(HWND(self.window.raw_handle() as isize));
+        }
+    }
+}
+
+/// 送出面板上點選的符號
+fn send_symbol(simulator: &Mutex<InputSimulator>, symbol: &str) {
+    match simulator.lock().unwrap().send_to_foreground(symbol) {
+        Ok(SendOutcome::Sent) => debug!("符號面板: 已送出 {}", symbol),
+        Ok(SendOutcome::Deferred) => debug!("符號面板: {} 排入送字佇列", symbol),
+        Ok(SendOutcome::NeedsConfirm(_)) => warn!("符號面板: 前景為終端機，略過 {}", symbol),
+        Err(e) => warn!("符號面板: 送出 {} 失敗: {:#}", symbol, e),
+    }
+}
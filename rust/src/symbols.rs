@@ -0,0 +1,164 @@
+//! 符號選單的符號表
+//!
+//! 按下符號選單鍵（設定 symbol_menu_key）時，候選區先列出分類，選了分類再列出該分類的符號，
+//! 選字鍵或 Space 選用、翻頁鍵翻頁，和一般候選一樣。符號表是一份 JSON，可以自行增減：
+//!
+//! ```json
+//! { "categories": [
+//!     { "name": "標點", "symbols": ["，", "、", "。"] },
+//!     { "name": "箭頭", "symbols": ["←", "↑", "→", "↓"] }
+//! ] }
+//! ```
+//!
+//! 沒有指定 symbol_table 時使用方案包附帶的符號表（見 scheme_table），再沒有時使用內建的表（標點、數學、箭頭、框線）。
+//! 同一份符號表也列在符號面板（托盤「符號面板」）的分類標籤中，點一下即送出。
+//!
+//! 方案包的 symbols.json（scheme.json 的 symbols）是 chardefs 格式的直達標點映射，會合併進字典；
+//! 同一個檔案可以再加上 categories 給符號選單與面板使用，兩者共用一份資料：
+//!
+//! ```json
+//! { "chardefs": { ",": ["，"], ".": ["。"] },
+//!   "categories": [{ "name": "注音符號", "symbols": ["ㄅ", "ㄆ", "ㄇ"] }] }
+//! ```
+//!
+//! 沒有 categories 時，把 chardefs 的符號依字根順序整理成「方案符號」分類，排在內建分類之前。
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// 方案的直達標點映射整理成的分類名稱
+const SCHEME_CATEGORY: &str = "方案符號";
+
+/// 內建符號表
+const BUILTIN_SYMBOLS: &str = include_str!("../symbols.json");
+
+/// 一個符號分類
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SymbolCategory {
+    /// 分類名稱（列在第一層選單）
+    pub name: String,
+    /// 分類中的符號（依序列為候選）
+    pub symbols: Vec<String>,
+}
+
+/// 符號表
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct SymbolTable {
+    pub categories: Vec<SymbolCategory>,
+}
+
+/// 符號選單目前列出的層級
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolMenu {
+    /// 列出分類
+    Categories,
+    /// 列出第幾個分類的符號
+    Category(usize),
+}
+
+impl SymbolTable {
+    /// 內建的符號表
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN_SYMBOLS).expect("內建符號表格式錯誤")
+    }
+
+    /// 從檔案載入符號表
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).with_context(|| format!("無法讀取符號表: {:?}", path))?;
+        Self::parse(&content).with_context(|| format!("符號表有誤: {:?}", path))
+    }
+
+    /// 從 JSON 字串解析，略過空白的符號並檢查每個分類都有符號
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut table: Self = serde_json::from_str(content).context("無法解析符號表 JSON")?;
+        if table.categories.is_empty() {
+            bail!("符號表沒有任何分類");
+        }
+        for category in &mut table.categories {
+            category.symbols.retain(|symbol| !symbol.trim().is_empty());
+            if category.name.trim().is_empty() || category.symbols.is_empty() {
+                bail!("符號表的分類「{}」沒有名稱或沒有符號", category.name);
+            }
+        }
+        Ok(table)
+    }
+
+    /// 方案包附帶的符號表：有 categories 時直接使用，只有 chardefs 時整理成「方案符號」分類接在內建的表之前
+    /// 兩者都沒有（或沒有任何單純字串的符號）時回傳 None
+    pub fn from_scheme_symbols(content: &str) -> Result<Option<Self>> {
+        let value: Value = serde_json::from_str(content).context("無法解析符號表 JSON")?;
+        if value.get("categories").is_some() {
+            return Self::parse(content).map(Some);
+        }
+        let Some(chardefs) = value.get("chardefs").and_then(Value::as_object) else {
+            return Ok(None);
+        };
+        // 依字根排序，同樣的字根長度下標點鍵的順序固定；display/output 物件的候選略過
+        let sorted: BTreeMap<&String, &Value> = chardefs.iter().collect();
+        let mut symbols: Vec<String> = Vec::new();
+        for symbol in sorted.values().filter_map(|chars| chars.as_array()).flatten().filter_map(Value::as_str) {
+            if !symbol.trim().is_empty() && !symbols.iter().any(|s| s == symbol) {
+                symbols.push(symbol.to_string());
+            }
+        }
+        if symbols.is_empty() {
+            return Ok(None);
+        }
+        let mut table = Self::builtin();
+        table.categories.insert(0, SymbolCategory { name: SCHEME_CATEGORY.to_string(), symbols });
+        Ok(Some(table))
+    }
+
+    /// 讀取方案包附帶的符號表（見 from_scheme_symbols），檔案不存在時回傳 None
+    pub fn load_scheme(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path).with_context(|| format!("無法讀取符號表: {:?}", path))?;
+        Self::from_scheme_symbols(&content).with_context(|| format!("符號表有誤: {:?}", path))
+    }
+
+    /// 所有分類的名稱（第一層選單的候選）
+    pub fn category_names(&self) -> Vec<String> {
+        self.categories.iter().map(|category| category.name.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_symbol_table() {
+        let table = SymbolTable::parse(r#"{ "categories": [{ "name": "箭頭", "symbols": ["←", " ", "→"] }] }"#).unwrap();
+        assert_eq!(table.categories[0].symbols, ["←", "→"]);
+        assert_eq!(table.category_names(), ["箭頭"]);
+
+        assert!(SymbolTable::parse(r#"{ "categories": [] }"#).is_err());
+        assert!(SymbolTable::parse(r#"{ "categories": [{ "name": "空", "symbols": [] }] }"#).is_err());
+        assert_eq!(SymbolTable::builtin().category_names(), ["標點", "數學", "箭頭", "框線"]);
+    }
+
+    #[test]
+    fn test_scheme_symbols() {
+        // 只有直達標點映射：整理成方案符號分類，排在內建分類之前
+        let table = SymbolTable::from_scheme_symbols(r#"{ "chardefs": { ".": ["。"], ",": ["，", "、"], "..": ["。"] } }"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(table.categories[0].symbols, ["，", "、", "。"]);
+        assert_eq!(table.category_names(), ["方案符號", "標點", "數學", "箭頭", "框線"]);
+
+        // 有 categories 時直接使用
+        let table = SymbolTable::from_scheme_symbols(
+            r#"{ "chardefs": { ",": ["，"] }, "categories": [{ "name": "注音", "symbols": ["ㄅ", "ㄆ"] }] }"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(table.category_names(), ["注音"]);
+        assert_eq!(SymbolTable::from_scheme_symbols(r#"{ "chardefs": {} }"#).unwrap(), None);
+    }
+}
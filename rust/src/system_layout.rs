@@ -0,0 +1,171 @@
+//! 系統預設輸入法的切換與還原
+//!
+//! 開啟 switch_to_english_layout 時，啟動時把系統預設輸入法切到英文（美式）鍵盤，
+//! 避免與系統的中文輸入法搶按鍵，退出時再還原。切換前原本的設定記在登錄檔
+//! HKCU\Software\UCLLIU 的 SavedInputLanguage，正常還原後刪除；程式異常結束時
+//! 紀錄會留下來，下次啟動時先補還原。
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{LPARAM, WPARAM};
+use windows::Win32::System::Registry::{
+    RegDeleteKeyValueW, RegGetValueW, RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ, RRF_RT_REG_SZ,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{LoadKeyboardLayoutW, KLF_ACTIVATE};
+use windows::Win32::UI::TextServices::HKL;
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetForegroundWindow, PostMessageW, SystemParametersInfoW, SPIF_SENDCHANGE, SPI_GETDEFAULTINPUTLANG,
+    SPI_SETDEFAULTINPUTLANG, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, WM_INPUTLANGCHANGEREQUEST,
+};
+
+/// 記錄原本設定的登錄檔機碼（HKEY_CURRENT_USER 之下）
+const REGISTRY_KEY: &str = "Software\\UCLLIU";
+/// 記錄原本設定的值名稱
+const REGISTRY_VALUE: &str = "SavedInputLanguage";
+/// 英文（美式）鍵盤的配置代碼
+const ENGLISH_LAYOUT_ID: &str = "00000409";
+
+/// 上次異常結束沒有還原時，把記錄的輸入法設回去並刪除紀錄
+pub fn restore_after_crash() {
+    match read_saved() {
+        Ok(Some(hkl)) => {
+            warn!("上次沒有正常還原系統輸入法，補還原為 {}", format_hkl(hkl));
+            restore_to(hkl);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("讀取系統輸入法紀錄失敗: {:#}", e),
+    }
+}
+
+/// 記錄目前的系統預設輸入法並切到英文鍵盤（已經是英文鍵盤時不動作）
+pub fn switch_to_english() -> Result<()> {
+    let english = unsafe { LoadKeyboardLayoutW(&HSTRING::from(ENGLISH_LAYOUT_ID), KLF_ACTIVATE) }
+        .context("無法載入英文鍵盤配置")?;
+    let current = default_input_language()?;
+    if current == english {
+        return Ok(());
+    }
+    save(current)?;
+    apply(english)?;
+    info!("系統預設輸入法已切到英文鍵盤（原本為 {}，退出時還原）", format_hkl(current));
+    Ok(())
+}
+
+/// 還原啟動時記錄的系統預設輸入法（沒有紀錄時不動作）
+pub fn restore() {
+    match read_saved() {
+        Ok(Some(hkl)) => {
+            restore_to(hkl);
+            info!("已還原系統預設輸入法: {}", format_hkl(hkl));
+        }
+        Ok(None) => {}
+        Err(e) => warn!("讀取系統輸入法紀錄失敗: {:#}", e),
+    }
+}
+
+fn restore_to(hkl: HKL) {
+    if let Err(e) = apply(hkl) {
+        warn!("還原系統輸入法失敗: {:#}", e);
+        return;
+    }
+    let deleted = unsafe {
+        RegDeleteKeyValueW(HKEY_CURRENT_USER, &HSTRING::from(REGISTRY_KEY), &HSTRING::from(REGISTRY_VALUE))
+    };
+    if let Err(e) = deleted {
+        warn!("刪除系統輸入法紀錄失敗: {}", e);
+    }
+}
+
+/// 目前的系統預設輸入法
+fn default_input_language() -> Result<HKL> {
+    let mut hkl = HKL::default();
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETDEFAULTINPUTLANG,
+            0,
+            Some(&mut hkl as *mut HKL as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    }
+    .context("無法取得系統預設輸入法")?;
+    Ok(hkl)
+}
+
+/// 設定系統預設輸入法，並請前景視窗一併切換（其他視窗在下次取得焦點時套用）
+fn apply(hkl: HKL) -> Result<()> {
+    let mut value = hkl;
+    unsafe { SystemParametersInfoW(SPI_SETDEFAULTINPUTLANG, 0, Some(&mut value as *mut HKL as *mut _), SPIF_SENDCHANGE) }
+        .context("無法設定系統預設輸入法")?;
+    unsafe {
+        let _ = PostMessageW(GetForegroundWindow(), WM_INPUTLANGCHANGEREQUEST, WPARAM(0), LPARAM(hkl.0));
+    }
+    Ok(())
+}
+
+fn save(hkl: HKL) -> Result<()> {
+    let text = HSTRING::from(format_hkl(hkl));
+    let bytes = (text.len() + 1) * std::mem::size_of::<u16>();
+    unsafe {
+        RegSetKeyValueW(
+            HKEY_CURRENT_USER,
+            &HSTRING::from(REGISTRY_KEY),
+            &HSTRING::from(REGISTRY_VALUE),
+            REG_SZ.0,
+            Some(text.as_ptr() as *const _),
+            bytes as u32,
+        )
+    }
+    .context("無法在登錄檔記錄系統輸入法")
+}
+
+/// 讀取記錄的輸入法，沒有紀錄時為 None
+fn read_saved() -> Result<Option<HKL>> {
+    let mut buffer = [0u16; 32];
+    let mut size = std::mem::size_of_val(&buffer) as u32;
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &HSTRING::from(REGISTRY_KEY),
+            &HSTRING::from(REGISTRY_VALUE),
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr() as *mut _),
+            Some(&mut size),
+        )
+    };
+    if result.is_err() {
+        // 沒有紀錄（機碼或值不存在）
+        return Ok(None);
+    }
+    let len = buffer.iter().position(|&unit| unit == 0).unwrap_or(buffer.len());
+    let text = String::from_utf16_lossy(&buffer[..len]);
+    parse_hkl(&text)
+        .map(|value| Some(HKL(value)))
+        .with_context(|| format!("登錄檔中的輸入法紀錄格式錯誤: {}", text))
+}
+
+fn format_hkl(hkl: HKL) -> String {
+    format!("{:08X}", hkl.0)
+}
+
+/// 解析十六進位的輸入法代碼（可加 0x 前綴）
+fn parse_hkl(text: &str) -> Option<isize> {
+    let digits = text.trim();
+    let digits = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")).unwrap_or(digits);
+    u64::from_str_radix(digits, 16).ok().map(|value| value as isize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hkl_round_trip() {
+        assert_eq!(format_hkl(HKL(0x0404_0404)), "04040404");
+        assert_eq!(parse_hkl("04040404"), Some(0x0404_0404));
+        assert_eq!(parse_hkl(" 0xF0040404 "), Some(0xF004_0404));
+        assert_eq!(parse_hkl(&format_hkl(HKL(-0x0FFB_FBFC))), Some(-0x0FFB_FBFC));
+        assert_eq!(parse_hkl("zh-TW"), None);
+    }
+}
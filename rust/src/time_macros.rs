@@ -0,0 +1,77 @@
+//! 日期/時間巨集碼
+//!
+//! 設定 time_macros 列出特殊碼與展開格式（預設是空清單，不會蓋過字碼表的字根），例如：
+//!
+//! ```json
+//! "time_macros": [
+//!   { "code": "date", "format": "%Y-%m-%d" },
+//!   { "code": "time", "format": "%H:%M" },
+//!   { "code": "now", "format": "%Y-%m-%d %H:%M" }
+//! ]
+//! ```
+//!
+//! 字根完全符合特殊碼時，候選是依格式展開的目前日期/時間（預覽），優先於特殊碼規則與字典查詢，不計入字頻；
+//! 送出時輸入處理器再以當下的時間重新展開，候選擺了一陣子才送出也不會送出過時的時間。
+//!
+//! 和特殊碼規則（rules 模組）的 date/time 產生器的差別：規則寫在方案裡、以正規表達式比對，
+//! 候選是幾種固定格式、查詢當下就定了；巨集碼寫在使用者設定裡，格式由使用者自訂，送出時才展開。
+//!
+//! 格式：%Y 西元年、%R 民國年、%m 月、%d 日（兩位數）、%H 時（24 小時制）、%I 時（12 小時制）、
+//! %p 上午/下午、%M 分、%S 秒、%% 百分號；其他字元原樣輸出。
+
+use crate::rules::LocalTime;
+use serde::{Deserialize, Serialize};
+
+/// 一個日期/時間巨集碼
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeMacro {
+    /// 觸發的字根（完全符合才展開）
+    pub code: String,
+    /// 展開格式（見模組說明）
+    pub format: String,
+}
+
+/// 依格式展開時間
+pub fn expand(format: &str, t: LocalTime) -> String {
+    let mut output = String::new();
+    let mut chars = format.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            output.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => output.push_str(&format!("{:04}", t.year)),
+            Some('R') => output.push_str(&(t.year as i32 - 1911).to_string()),
+            Some('m') => output.push_str(&format!("{:02}", t.month)),
+            Some('d') => output.push_str(&format!("{:02}", t.day)),
+            Some('H') => output.push_str(&format!("{:02}", t.hour)),
+            Some('I') => output.push_str(&format!("{:02}", match t.hour % 12 { 0 => 12, hour => hour })),
+            Some('p') => output.push_str(if t.hour < 12 { "上午" } else { "下午" }),
+            Some('M') => output.push_str(&format!("{:02}", t.minute)),
+            Some('S') => output.push_str(&format!("{:02}", t.second)),
+            Some('%') => output.push('%'),
+            // 不認得的格式碼原樣輸出
+            Some(other) => {
+                output.push('%');
+                output.push(other);
+            }
+            None => output.push('%'),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_formats() {
+        let t = LocalTime { year: 2024, month: 3, day: 5, hour: 14, minute: 7, second: 9 };
+        let formats: Vec<String> = ["%Y-%m-%d", "%H:%M", "%Y-%m-%d %H:%M"].iter().map(|format| expand(format, t)).collect();
+        assert_eq!(formats, ["2024-03-05", "14:07", "2024-03-05 14:07"]);
+        assert_eq!(expand("民國%R年%m月%d日 %p%I:%M:%S", t), "民國113年03月05日 下午02:07:09");
+        assert_eq!(expand("100%% %q%", t), "100% %q%");
+    }
+}
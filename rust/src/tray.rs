@@ -1,47 +1,463 @@
 //! 系統托盤模組
 
+use crate::gui_backend::GuiCommand;
+use crate::icon::{self, Badge, RgbaImage};
+use crate::mode::InputMode;
+use crate::profile::Profile;
+use crate::logging;
+use crate::report;
+use crate::schedule::ScheduleSlot;
 use crate::AppState;
 use anyhow::Result;
-use log::info;
+use tracing::{info, warn};
+use std::cell::Cell;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use tray_icon::{
-    menu::{Menu, MenuItem},
-    TrayIconBuilder,
+    menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu},
+    Icon, TrayIconBuilder,
 };
+use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_CXSMICON};
+
+/// 托盤圖示檔名（與執行檔放在同一目錄）
+const ICON_FILE: &str = "icon.ico";
 
 /// 系統托盤圖示
 pub struct TrayIcon {
-    _tray_icon: tray_icon::TrayIcon,
-    _state: Arc<AppState>,
+    tray_icon: tray_icon::TrayIcon,
+    state: Arc<AppState>,
+    /// 「使用者」子選單中的各個 profile 選項
+    profile_items: Vec<(CheckMenuItem, String)>,
+    /// 「日誌等級」子選單中的常用過濾規則（見 logging::FILTER_PRESETS）
+    log_filter_items: Vec<(CheckMenuItem, &'static str)>,
+    /// 「日誌等級」子選單的「自訂…」選項
+    log_filter_custom_item: MenuItem,
+    /// 「顯示/隱藏狀態窗」選項
+    toggle_item: MenuItem,
+    /// 「字根查詢」選項
+    lookup_item: MenuItem,
+    /// 「停用字管理」選項
+    blocklist_item: MenuItem,
+    /// 「螢幕鍵盤」選項
+    keyboard_item: MenuItem,
+    /// 「符號面板」選項
+    symbol_panel_item: MenuItem,
+    /// 「說明」選項
+    help_item: MenuItem,
+    /// 「更新字碼表」選項
+    update_item: MenuItem,
+    /// 「產生回報包」選項
+    report_item: MenuItem,
+    /// 「按鍵延遲統計」選項
+    timing_item: MenuItem,
+    /// 「學習曲線」選項
+    learning_item: MenuItem,
+    /// 「按鍵回放」選項
+    key_trace_item: MenuItem,
+    /// 「狀態檢視（開發者）」選項（只在 debug 建置或開啟 inspector feature 時出現）
+    inspector_item: Option<MenuItem>,
+    /// 「退出」選項
+    quit_item: MenuItem,
+    /// 未加徽章的基本圖示（已縮放到目前 DPI 的托盤圖示尺寸）
+    base_icon: RgbaImage,
+    /// 目前顯示的模式徽章
+    badge: Cell<Badge>,
 }
 
 impl TrayIcon {
     pub fn new(state: Arc<AppState>) -> Result<Self> {
-        // 載入圖示（暫時使用預設）
-        // TODO: 載入實際的 icon.ico
-        
+        // 載入圖示，預設為肥模式（鍵盤鉤子啟動時為攔截狀態）
+        let base_icon = Self::load_base_icon();
+        let badge = Badge::Ucl;
+
         let menu = Menu::new();
-        
-        // 創建退出選項
-        // tray-icon 0.10 使用 Windows 消息循環處理菜單項點擊
-        // 退出選項會自動發送 WM_COMMAND 消息，我們在 keyboard_hook.rs 中處理
-        // 注意：MenuItem::new 的第三個參數是 Accelerator（快捷鍵），不是回調函數
-        let quit_i = MenuItem::new("退出", true, None);
-        menu.append(&quit_i)?;
-        
+
+        // 使用者子選單：列出 profiles 目錄下的所有使用者，勾選目前使用者
+        let current_profile = state.profile.lock().unwrap().name.clone();
+        let mut profile_names = Profile::available();
+        if !profile_names.contains(&current_profile) {
+            profile_names.push(current_profile.clone());
+        }
+        let profile_menu = Submenu::new("使用者", true);
+        let mut profile_items = Vec::with_capacity(profile_names.len());
+        for name in profile_names {
+            let item = CheckMenuItem::new(&name, true, name == current_profile, None);
+            profile_menu.append(&item)?;
+            profile_items.push((item, name));
+        }
+        menu.append(&profile_menu)?;
+
+        // 顯示/隱藏狀態窗（與 Ctrl+Space 相同，最小化啟動時由這裡叫出）
+        let toggle_item = MenuItem::new("顯示/隱藏狀態窗", true, None);
+        menu.append(&toggle_item)?;
+
+        // 字根查詢視窗（候選字附 Unicode 碼位與名稱）
+        let lookup_item = MenuItem::new("字根查詢", true, None);
+        menu.append(&lookup_item)?;
+
+        // 停用字管理視窗（GUI 右鍵「隱藏」的字也在這裡恢復）
+        let blocklist_item = MenuItem::new("停用字管理", true, None);
+        menu.append(&blocklist_item)?;
+
+        // 螢幕鍵盤（鍵位圖與字根，佈局可由設定的 keyboard_layout 指定）
+        let keyboard_item = MenuItem::new("螢幕鍵盤", true, None);
+        menu.append(&keyboard_item)?;
+
+        // 符號面板（分類標籤 + 點擊送出，與符號選單同一份符號表）
+        let symbol_panel_item = MenuItem::new("符號面板", true, None);
+        menu.append(&symbol_panel_item)?;
+
+        // 說明視窗（依目前設定列出熱鍵、選字鍵與補碼鍵）
+        let help_item = MenuItem::new("說明（快捷鍵一覽）", true, None);
+        menu.append(&help_item)?;
+
+        // 從設定的網址下載新版字碼表，核對校驗碼後直接套用
+        let update_item = MenuItem::new("更新字碼表", true, None);
+        menu.append(&update_item)?;
+
+        // 產生回報包（日誌、診斷報告、設定、字典統計打包成 zip）
+        let report_item = MenuItem::new("產生回報包", true, None);
+        menu.append(&report_item)?;
+
+        // 鍵盤鉤子回呼耗時分佈
+        let timing_item = MenuItem::new("按鍵延遲統計", true, None);
+        menu.append(&timing_item)?;
+        // 每日正確率與速度的趨勢圖
+        let learning_item = MenuItem::new("學習曲線", true, None);
+        menu.append(&learning_item)?;
+        let key_trace_item = MenuItem::new("按鍵回放", true, None);
+        menu.append(&key_trace_item)?;
+        // 開發者用：每秒更新的內部狀態（組字狀態、鎖、待送出佇列）
+        let inspector_item = if cfg!(any(debug_assertions, feature = "inspector")) {
+            let item = MenuItem::new("狀態檢視（開發者）", true, None);
+            menu.append(&item)?;
+            Some(item)
+        } else {
+            None
+        };
+
+        // 日誌等級子選單：執行中更換主控台的過濾規則（日誌檔固定記錄 info 以上）
+        let current_filter = logging::current_filter();
+        let log_menu = Submenu::new("日誌等級", true);
+        let mut log_filter_items = Vec::with_capacity(logging::FILTER_PRESETS.len());
+        for &(label, directives) in logging::FILTER_PRESETS {
+            let item = CheckMenuItem::new(label, true, directives == current_filter, None);
+            log_menu.append(&item)?;
+            log_filter_items.push((item, directives));
+        }
+        let log_filter_custom_item = MenuItem::new("自訂…", true, None);
+        log_menu.append(&log_filter_custom_item)?;
+        menu.append(&log_menu)?;
+        menu.append(&PredefinedMenuItem::separator())?;
+
+        // 退出選項（與其他選項一樣由 handle_menu_events 依 MenuId 處理）
+        let quit_item = MenuItem::new("退出", true, None);
+        menu.append(&quit_item)?;
+
         let tray_icon = TrayIconBuilder::new()
             .with_menu(Box::new(menu))
-            .with_tooltip("肥米輸入法")
+            .with_tooltip(Self::tooltip(&current_profile))
+            .with_icon(Self::compose_icon(&base_icon, badge)?)
             .build()?;
-        
+
         info!("系統托盤圖示已創建");
-        
+
         Ok(Self {
-            _tray_icon: tray_icon,
-            _state: state,
+            tray_icon,
+            state,
+            profile_items,
+            log_filter_items,
+            log_filter_custom_item,
+            toggle_item,
+            lookup_item,
+            blocklist_item,
+            keyboard_item,
+            symbol_panel_item,
+            help_item,
+            update_item,
+            report_item,
+            timing_item,
+            learning_item,
+            key_trace_item,
+            inspector_item,
+            quit_item,
+            base_icon,
+            badge: Cell::new(badge),
         })
     }
-    
+
+    /// 更新模式徽章（英/肥），模式沒變時不重建圖示
+    pub fn set_mode(&self, mode: InputMode) {
+        let badge = Badge::for_mode(mode);
+        if self.badge.get() == badge {
+            return;
+        }
+        self.badge.set(badge);
+
+        match Self::compose_icon(&self.base_icon, badge) {
+            Ok(icon) => {
+                if let Err(e) = self.tray_icon.set_icon(Some(icon)) {
+                    warn!("更新托盤圖示失敗: {}", e);
+                }
+            }
+            Err(e) => warn!("建立托盤圖示失敗: {}", e),
+        }
+    }
+
+    /// 載入執行檔目錄下的 icon.ico，依目前 DPI 挑選尺寸；找不到或無法解析時使用內建圖示
+    fn load_base_icon() -> RgbaImage {
+        let size = Self::icon_size();
+        let icon_path = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join(ICON_FILE)));
+
+        let loaded = icon_path.as_ref().filter(|path| path.exists()).map(|path| {
+            std::fs::read(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| icon::parse_ico(&bytes))
+        });
+
+        match loaded {
+            Some(Ok(images)) => {
+                info!("載入托盤圖示: {:?}（{} 種尺寸，使用 {}px）", icon_path, images.len(), size);
+                icon::select_size(&images, size).unwrap_or_else(|| icon::fallback_icon(size))
+            }
+            Some(Err(e)) => {
+                warn!("無法載入托盤圖示 {:?}: {}，使用內建圖示", icon_path, e);
+                icon::fallback_icon(size)
+            }
+            None => icon::fallback_icon(size),
+        }
+    }
+
+    /// 目前 DPI 下的托盤圖示尺寸（100% 為 16px，150% 為 24px，以此類推）
+    fn icon_size() -> u32 {
+        let size = unsafe { GetSystemMetrics(SM_CXSMICON) };
+        if size > 0 { size as u32 } else { 16 }
+    }
+
+    fn compose_icon(base: &RgbaImage, badge: Badge) -> Result<Icon> {
+        let mut image = base.clone();
+        icon::draw_badge(&mut image, badge);
+        Ok(Icon::from_rgba(image.pixels, image.width, image.height)?)
+    }
+
+    /// 處理托盤選單事件（由鍵盤鉤子執行緒的訊息循環呼叫）
+    pub fn handle_menu_events(&self) {
+        while let Ok(event) = MenuEvent::receiver().try_recv() {
+            if event.id == self.quit_item.id() {
+                info!("✅ 系統托盤退出選項被點擊，準備退出...");
+                // 訊息循環下一輪看到旗標就結束
+                self.state.should_quit.store(true, Ordering::Relaxed);
+                return;
+            }
+            if event.id == self.toggle_item.id() {
+                if self.state.gui_commands.send(GuiCommand::Toggle).is_err() {
+                    warn!("GUI 執行緒已結束，無法切換狀態窗");
+                }
+                continue;
+            }
+            if event.id == self.lookup_item.id() {
+                if self.state.gui_commands.send(GuiCommand::ShowLookup).is_err() {
+                    warn!("GUI 執行緒已結束，無法開啟字根查詢視窗");
+                }
+                continue;
+            }
+            if event.id == self.blocklist_item.id() {
+                if self.state.gui_commands.send(GuiCommand::ShowBlocklist).is_err() {
+                    warn!("GUI 執行緒已結束，無法開啟停用字管理視窗");
+                }
+                continue;
+            }
+            if event.id == self.keyboard_item.id() {
+                if self.state.gui_commands.send(GuiCommand::ShowKeyboard).is_err() {
+                    warn!("GUI 執行緒已結束，無法開啟螢幕鍵盤");
+                }
+                continue;
+            }
+            if event.id == self.symbol_panel_item.id() {
+                if self.state.gui_commands.send(GuiCommand::ShowSymbolPanel).is_err() {
+                    warn!("GUI 執行緒已結束，無法開啟符號面板");
+                }
+                continue;
+            }
+            if event.id == self.help_item.id() {
+                if self.state.gui_commands.send(GuiCommand::ShowHelp).is_err() {
+                    warn!("GUI 執行緒已結束，無法開啟說明視窗");
+                }
+                continue;
+            }
+            if event.id == self.update_item.id() {
+                self.update_dictionary();
+                continue;
+            }
+            if event.id == self.report_item.id() {
+                self.create_report();
+                continue;
+            }
+            if event.id == self.timing_item.id() {
+                self.show_hook_timing();
+                continue;
+            }
+            if event.id == self.learning_item.id() {
+                if self.state.gui_commands.send(GuiCommand::ShowLearning).is_err() {
+                    warn!("GUI 執行緒已結束，無法開啟學習曲線");
+                }
+                continue;
+            }
+            if event.id == self.key_trace_item.id() {
+                if self.state.gui_commands.send(GuiCommand::ShowKeyTrace).is_err() {
+                    warn!("GUI 執行緒已結束，無法開啟按鍵回放");
+                }
+                continue;
+            }
+            if self.inspector_item.as_ref().is_some_and(|item| event.id == item.id()) {
+                if self.state.gui_commands.send(GuiCommand::ShowInspector).is_err() {
+                    warn!("GUI 執行緒已結束，無法開啟狀態檢視");
+                }
+                continue;
+            }
+            if event.id == self.log_filter_custom_item.id() {
+                if self.state.gui_commands.send(GuiCommand::EditLogFilter).is_err() {
+                    warn!("GUI 執行緒已結束，無法更換日誌等級");
+                }
+                continue;
+            }
+            let log_filter = self
+                .log_filter_items
+                .iter()
+                .find(|(item, _)| item.id() == event.id)
+                .map(|&(_, directives)| directives);
+            if let Some(directives) = log_filter {
+                if let Err(e) = logging::set_filter(directives) {
+                    warn!("{:#}", e);
+                }
+                // 點擊時選單會自動切換勾選狀態，這裡統一改成只勾選目前的規則
+                let current = logging::current_filter();
+                for (item, item_directives) in &self.log_filter_items {
+                    item.set_checked(*item_directives == current);
+                }
+                continue;
+            }
+
+            let selected = self
+                .profile_items
+                .iter()
+                .find(|(item, _)| item.id() == event.id)
+                .map(|(_, name)| name.clone());
+            if let Some(name) = selected {
+                self.switch_profile(&name);
+            }
+        }
+    }
+
+    /// 切換使用者設定檔
+    fn switch_profile(&self, name: &str) {
+        match Profile::load(Some(name)) {
+            Ok(profile) => {
+                info!("切換使用者: {}", profile.name);
+                // 先把目前使用者的字頻與停用字存檔，再換成新使用者的資料
+                self.state.save_profile_data();
+                *self.state.profile.lock().unwrap() = profile;
+                self.state.reload_profile_data();
+                let _ = self.tray_icon.set_tooltip(Some(Self::tooltip(name)));
+            }
+            Err(e) => warn!("切換使用者 {} 失敗: {}", name, e),
+        }
+
+        // 點擊時選單會自動切換勾選狀態，這裡統一改成只勾選目前使用者
+        let current = self.state.profile.lock().unwrap().name.clone();
+        for (item, item_name) in &self.profile_items {
+            item.set_checked(*item_name == current);
+        }
+    }
+
+    /// 進入排程時段：切換使用者、模式與輸入窗口，並跳出通知
+    pub fn apply_schedule(&self, slot: &ScheduleSlot) {
+        let mut changes = Vec::new();
+        if let Some(name) = &slot.profile {
+            if *name != self.state.profile.lock().unwrap().name {
+                self.switch_profile(name);
+                changes.push(format!("使用者 {}", name));
+            }
+        }
+        if let Some(mode) = slot.mode {
+            if self.state.set_mode(mode) != mode {
+                changes.push(format!("{}模式", mode.label()));
+            }
+        }
+        if let Some(show) = slot.gui {
+            if self.state.gui_visible.load(Ordering::Relaxed) != show {
+                if self.state.gui_commands.send(GuiCommand::Toggle).is_err() {
+                    warn!("GUI 執行緒已結束，無法切換狀態窗");
+                }
+                changes.push(if show { "顯示輸入窗口" } else { "隱藏輸入窗口" }.to_string());
+            }
+        }
+        // 已經是時段要的狀態時不打擾使用者
+        if changes.is_empty() {
+            return;
+        }
+        let text = format!("進入排程時段 {}：{}", slot.label(), changes.join("、"));
+        info!("{}", text);
+        if self.state.gui_commands.send(GuiCommand::ShowMessage("排程".to_string(), text)).is_err() {
+            warn!("GUI 執行緒已結束，無法顯示排程通知");
+        }
+    }
+
+    /// 在背景執行緒產生回報包（避免阻塞鍵盤鉤子），完成後在檔案總管中選取
+    fn create_report(&self) {
+        let state = self.state.clone();
+        let spawned = std::thread::Builder::new()
+            .name("report".to_string())
+            .spawn(move || match report::create(&state) {
+                Ok(path) => {
+                    let _ = std::process::Command::new("explorer")
+                        .arg(format!("/select,{}", path.display()))
+                        .spawn();
+                }
+                Err(e) => warn!("產生回報包失敗: {}", e),
+            });
+        if let Err(e) = spawned {
+            warn!("無法啟動回報包執行緒: {}", e);
+        }
+    }
+
+    /// 在背景執行緒更新字碼表（下載不阻塞鍵盤鉤子），完成或失敗時顯示訊息
+    fn update_dictionary(&self) {
+        let state = self.state.clone();
+        let spawned = std::thread::Builder::new()
+            .name("dictionary-update".to_string())
+            .spawn(move || {
+                let text = match state.update_dictionary() {
+                    Ok(codes) => format!("字碼表已更新（{} 個字根），不需重新啟動。", codes),
+                    Err(e) => {
+                        warn!("更新字碼表失敗: {:#}", e);
+                        format!("更新字碼表失敗：{:#}", e)
+                    }
+                };
+                let command = GuiCommand::ShowMessage("更新字碼表".to_string(), text);
+                if state.gui_commands.send(command).is_err() {
+                    warn!("GUI 執行緒已結束，無法顯示字碼表更新結果");
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("無法啟動字碼表更新執行緒: {}", e);
+        }
+    }
+
+    /// 顯示鍵盤鉤子回呼耗時統計（訊息視窗由 GUI 執行緒顯示，不阻塞鍵盤鉤子）
+    fn show_hook_timing(&self) {
+        let command = GuiCommand::ShowMessage("按鍵延遲統計".to_string(), self.state.hook_timing.summary());
+        if self.state.gui_commands.send(command).is_err() {
+            warn!("GUI 執行緒已結束，無法顯示按鍵延遲統計");
+        }
+    }
+
+    fn tooltip(profile_name: &str) -> String {
+        format!("肥米輸入法（{}）", profile_name)
+    }
+
     /// 獲取托盤圖示的窗口句柄（用於調試）
     pub fn _get_hwnd(&self) -> Option<windows::Win32::Foundation::HWND> {
         // tray-icon 0.10 可能不直接暴露窗口句柄
@@ -49,4 +465,3 @@ impl TrayIcon {
         None
     }
 }
-
@@ -0,0 +1,115 @@
+//! 模擬輸入一段中文（自動拆碼）
+//!
+//! 給自動化測試與教學工具使用：指定一段文字，逐字以反查取得字根，
+//! 依序把字根、翻頁與選字鍵交給輸入處理器，處理器送出的文字交給呼叫端（輸入法本體交給輸入模擬器送到前景程式），
+//! 和使用者實際打字走同一套流程（字頻、聯想詞、別名輸出都會生效）。
+//! ASCII 字元（英數、空白、半形標點）不經輸入處理器直接送出；反查不到字根的字略過並回報。
+//!
+//! 輸入法本體的命令列以 --type <文字> 使用（見 cli 模組）。
+
+use crate::input_method::InputMethodProcessor;
+use anyhow::Result;
+use tracing::{debug, info, warn};
+
+/// 模擬輸入的結果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeReport {
+    /// 實際送出的文字
+    pub typed: String,
+    /// 無法拆碼（反查不到字根，或打出字根後找不到這個字）而略過的字
+    pub missing: Vec<char>,
+}
+
+/// 以輸入法流程打出 text，送出的文字交給 send（輸入法本體送到前景程式，測試時可以改成收集起來）
+pub fn type_sentence(
+    processor: &mut InputMethodProcessor,
+    text: &str,
+    mut send: impl FnMut(&str) -> Result<()>,
+) -> Result<TypeReport> {
+    let mut report = TypeReport::default();
+    processor.clear();
+    for ch in text.chars() {
+        let output = if ch.is_ascii() && !ch.is_ascii_control() {
+            Some(ch.to_string())
+        } else {
+            type_char(processor, ch)
+        };
+        match output {
+            Some(output) => {
+                send(&output)?;
+                report.typed.push_str(&output);
+            }
+            None => {
+                warn!("模擬輸入: 「{}」無法拆碼，略過", ch);
+                report.missing.push(ch);
+            }
+        }
+    }
+    info!("模擬輸入: 送出「{}」，無法拆碼 {} 個字", report.typed, report.missing.len());
+    Ok(report)
+}
+
+/// 依反查到的字根（短的在前）逐一嘗試：打出字根、翻到該字所在的頁、按選字鍵，回傳處理器送出的文字
+fn type_char(processor: &mut InputMethodProcessor, ch: char) -> Option<String> {
+    let target = ch.to_string();
+    let codes = processor.dictionary().codes_for(&target);
+    for code in codes.iter().filter(|code| code.chars().all(|c| c.is_ascii_lowercase())) {
+        processor.clear();
+        let mut typed = true;
+        for key in code.chars() {
+            // 補碼選出其他候選時表示這組字根打不出來（例如被較短的字根搶先）
+            let (handled, selected) = processor.handle_code_input(key);
+            if !handled || selected.is_some() {
+                typed = false;
+                break;
+            }
+            // 開啟自動送出時字根打滿就送出了
+            if let Some(output) = processor.take_auto_commit() {
+                debug!("模擬輸入: 「{}」以字根 {} 自動送出", ch, code);
+                return Some(output);
+            }
+        }
+        if !typed {
+            continue;
+        }
+        loop {
+            let state = processor.get_state();
+            let position = state.get_current_page_candidates().iter().position(|candidate| candidate.text == target);
+            let key = position.and_then(|position| processor.scheme().selection_keys.chars().nth(position));
+            if let Some(key) = key {
+                debug!("模擬輸入: 「{}」字根 {} 選字鍵 {}", ch, code, key);
+                return processor.handle_selection_key(key);
+            }
+            if !state.has_next_page() {
+                break;
+            }
+            processor.page_candidates(true);
+        }
+    }
+    processor.clear();
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::Dictionary;
+
+    #[test]
+    fn test_type_sentence_reports_missing() {
+        let dictionary = Dictionary::from_json(
+            r#"{ "chardefs": { "a": ["一", "乙", "丁", "七", "九", "了", "二"], "ab": ["乙"], "b": ["人"] } }"#,
+        )
+        .unwrap();
+        let mut processor = InputMethodProcessor::new(dictionary);
+        let mut sent = Vec::new();
+        let report = type_sentence(&mut processor, "乙二 人x囧", |text| {
+            sent.push(text.to_string());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(report, TypeReport { typed: "乙二 人x".to_string(), missing: vec!['囧'] });
+        assert_eq!(sent, ["乙", "二", " ", "人", "x"]);
+        assert!(processor.get_state().current_code.is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+//! 候選字的 Unicode 資訊模組
+//!
+//! 查罕用字、擴充區字或造字區字時，查詢視窗會列出每個字的碼位（U+XXXX）、
+//! 所在區塊，以及（可選）Unicode 字元名稱。
+
+/// 單一字元的 Unicode 資訊
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharInfo {
+    pub ch: char,
+    /// 碼位，例如 U+4E00
+    pub code_point: String,
+    /// 所在區塊（中文名稱）
+    pub block: &'static str,
+    /// Unicode 字元名稱，未要求或沒有名稱時為 None
+    pub name: Option<String>,
+}
+
+/// 與中文輸入相關的 Unicode 區塊（起, 迄, 名稱）
+const BLOCKS: &[(u32, u32, &str)] = &[
+    (0x0000, 0x007F, "基本拉丁字母"),
+    (0x2000, 0x206F, "一般標點"),
+    (0x2460, 0x24FF, "圍繞字母數字"),
+    (0x2E80, 0x2EFF, "中日韓部首補充"),
+    (0x2F00, 0x2FDF, "康熙部首"),
+    (0x3000, 0x303F, "中日韓符號和標點"),
+    (0x3100, 0x312F, "注音符號"),
+    (0x31A0, 0x31BF, "注音符號擴充"),
+    (0x31C0, 0x31EF, "中日韓筆畫"),
+    (0x3200, 0x32FF, "中日韓圍繞字元及月份"),
+    (0x3300, 0x33FF, "中日韓相容字元"),
+    (0x3400, 0x4DBF, "中日韓統一表意文字擴充 A"),
+    (0x4E00, 0x9FFF, "中日韓統一表意文字"),
+    (0xE000, 0xF8FF, "私人使用區（造字）"),
+    (0xF900, 0xFAFF, "中日韓相容表意文字"),
+    (0xFE30, 0xFE4F, "中日韓相容形式"),
+    (0xFF00, 0xFFEF, "半形及全形字元"),
+    (0x1F300, 0x1FAFF, "表情符號"),
+    (0x20000, 0x2A6DF, "中日韓統一表意文字擴充 B"),
+    (0x2A700, 0x2B73F, "中日韓統一表意文字擴充 C"),
+    (0x2B740, 0x2B81F, "中日韓統一表意文字擴充 D"),
+    (0x2B820, 0x2CEAF, "中日韓統一表意文字擴充 E"),
+    (0x2CEB0, 0x2EBEF, "中日韓統一表意文字擴充 F"),
+    (0x2EBF0, 0x2EE5F, "中日韓統一表意文字擴充 I"),
+    (0x2F800, 0x2FA1F, "中日韓相容表意文字補充"),
+    (0x30000, 0x3134F, "中日韓統一表意文字擴充 G"),
+    (0x31350, 0x323AF, "中日韓統一表意文字擴充 H"),
+    (0xF0000, 0x10FFFF, "補充私人使用區"),
+];
+
+/// 字元的碼位，例如 '一' → "U+4E00"
+pub fn code_point(ch: char) -> String {
+    format!("U+{:04X}", ch as u32)
+}
+
+/// 文字中每個字元的碼位，以空白分隔（給「複製碼位」使用）
+pub fn code_points(text: &str) -> String {
+    text.chars().map(code_point).collect::<Vec<_>>().join(" ")
+}
+
+/// 字元所在的區塊名稱
+pub fn block_name(ch: char) -> &'static str {
+    let cp = ch as u32;
+    BLOCKS
+        .iter()
+        .find(|(start, end, _)| (*start..=*end).contains(&cp))
+        .map(|(_, _, name)| *name)
+        .unwrap_or("其他")
+}
+
+/// 取得字元的 Unicode 資訊，with_name 為 true 時附上字元名稱
+pub fn char_info(ch: char, with_name: bool) -> CharInfo {
+    CharInfo {
+        ch,
+        code_point: code_point(ch),
+        block: block_name(ch),
+        name: if with_name { unicode_names2::name(ch).map(|name| name.to_string()) } else { None },
+    }
+}
+
+/// 文字中每個字元的 Unicode 資訊
+pub fn describe(text: &str, with_name: bool) -> Vec<CharInfo> {
+    text.chars().map(|ch| char_info(ch, with_name)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_point_and_block() {
+        assert_eq!(code_point('一'), "U+4E00");
+        assert_eq!(code_point('a'), "U+0061");
+        assert_eq!(code_point('𠀀'), "U+20000");
+        assert_eq!(code_points("一二"), "U+4E00 U+4E8C");
+
+        assert_eq!(block_name('一'), "中日韓統一表意文字");
+        assert_eq!(block_name('𠀀'), "中日韓統一表意文字擴充 B");
+        assert_eq!(block_name('，'), "半形及全形字元");
+        assert_eq!(block_name('\u{E000}'), "私人使用區（造字）");
+    }
+
+    #[test]
+    fn test_char_info_name_is_optional() {
+        let info = char_info('一', true);
+        assert_eq!(info.name.as_deref(), Some("CJK UNIFIED IDEOGRAPH-4E00"));
+        assert_eq!(char_info('一', false).name, None);
+
+        let infos = describe("a。", true);
+        assert_eq!(infos[0].name.as_deref(), Some("LATIN SMALL LETTER A"));
+        assert_eq!(infos[1].name.as_deref(), Some("IDEOGRAPHIC FULL STOP"));
+    }
+}
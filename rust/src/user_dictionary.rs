@@ -0,0 +1,153 @@
+//! 使用者字典（user.json）
+//!
+//! 個人新增的字根放在使用者資料目錄的 user.json，與主字碼表 liu.json 分開，
+//! 更新字碼表時不會蓋掉。格式與字碼表相同：
+//!
+//! ```json
+//! { "chardefs": { "uclliu": ["肥米"], "xo": [{ "display": "笑", "output": "😂" }] } }
+//! ```
+//!
+//! 載入後套用到字典（見 Dictionary::set_user），同一字根的候選排在主字碼表之前。
+
+use crate::dictionary::Dictionary;
+use anyhow::{bail, Context, Result};
+use tracing::info;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// 使用者字典檔名（放在使用者資料目錄，見 Profile::data_path）
+pub const USER_DICTIONARY_FILE: &str = "user.json";
+
+/// 載入使用者字典，檔案不存在時回傳 None
+pub fn load(path: &Path) -> Result<Option<Dictionary>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("無法讀取使用者字典: {:?}", path))?;
+    let dictionary = Dictionary::parse(&content, false)
+        .with_context(|| format!("使用者字典 {:?} 有誤", path))?;
+    info!("載入使用者字典: {:?}（{} 個字根）", path, dictionary.code_to_chars.len());
+    Ok(Some(dictionary))
+}
+
+/// 新增一筆條目並寫回 user.json（檔案不存在時建立）
+/// 字根一律轉小寫；條目已存在時不修改檔案並回傳 false
+pub fn add_entry(path: &Path, code: &str, text: &str) -> Result<bool> {
+    let code = code.trim().to_lowercase();
+    if code.is_empty() || code.chars().any(char::is_whitespace) {
+        bail!("字根 '{}' 不可為空或包含空白", code);
+    }
+    if text.is_empty() {
+        bail!("字根 '{}' 的候選文字不可為空", code);
+    }
+
+    let mut root = read_root(path)?;
+    let Some(chardefs) = root.get_mut("chardefs").and_then(Value::as_object_mut) else {
+        bail!("使用者字典 {:?} 缺少 chardefs", path);
+    };
+    let Some(entries) = chardefs.entry(code.clone()).or_insert_with(|| json!([])).as_array_mut() else {
+        bail!("使用者字典 {:?} 的字根 '{}' 不是陣列", path, code);
+    };
+    if entries.iter().any(|entry| entry_matches(entry, text)) {
+        return Ok(false);
+    }
+    entries.push(Value::String(text.to_string()));
+
+    write_root(path, &root)?;
+    info!("已新增使用者字典條目: {} → {}", code, text);
+    Ok(true)
+}
+
+/// 從 user.json 刪除一筆條目（別名格式以 display 比對），字根沒有候選時整個移除
+/// 條目不存在（或檔案不存在）時不修改檔案並回傳 false
+pub fn remove_entry(path: &Path, code: &str, text: &str) -> Result<bool> {
+    let code = code.trim().to_lowercase();
+    if !path.exists() {
+        return Ok(false);
+    }
+    let mut root = read_root(path)?;
+    let Some(chardefs) = root.get_mut("chardefs").and_then(Value::as_object_mut) else {
+        bail!("使用者字典 {:?} 缺少 chardefs", path);
+    };
+    let Some(entries) = chardefs.get_mut(&code).and_then(Value::as_array_mut) else {
+        return Ok(false);
+    };
+    let before = entries.len();
+    entries.retain(|entry| !entry_matches(entry, text));
+    if entries.len() == before {
+        return Ok(false);
+    }
+    if entries.is_empty() {
+        chardefs.remove(&code);
+    }
+
+    write_root(path, &root)?;
+    info!("已刪除使用者字典條目: {} → {}", code, text);
+    Ok(true)
+}
+
+/// 讀取 user.json 的 JSON 內容，檔案不存在時為空的字碼表
+fn read_root(path: &Path) -> Result<Value> {
+    if !path.exists() {
+        return Ok(json!({ "chardefs": {} }));
+    }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("無法讀取使用者字典: {:?}", path))?;
+    serde_json::from_str(&content).with_context(|| format!("使用者字典 {:?} 不是有效的 JSON", path))
+}
+
+/// 寫回 user.json：先寫到暫存檔再改名，寫到一半被中斷時不會弄壞原本的檔案
+fn write_root(path: &Path, root: &Value) -> Result<()> {
+    let content = serde_json::to_string_pretty(root)?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content).with_context(|| format!("無法寫入使用者字典: {:?}", tmp_path))?;
+    fs::rename(&tmp_path, path).with_context(|| format!("無法寫入使用者字典: {:?}", path))
+}
+
+/// 條目是否為指定文字（字串條目直接比對，別名條目比對 display）
+fn entry_matches(entry: &Value, text: &str) -> bool {
+    match entry {
+        Value::String(s) => s == text,
+        other => other.get("display").and_then(Value::as_str) == Some(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_entry_creates_and_appends() {
+        let dir = std::env::temp_dir().join(format!("uclliu-user-dict-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(USER_DICTIONARY_FILE);
+        let _ = fs::remove_file(&path);
+
+        assert!(load(&path).unwrap().is_none());
+        assert!(add_entry(&path, "UCL", "肥米").unwrap());
+        assert!(add_entry(&path, "ucl", "肥米輸入法").unwrap());
+        assert!(!add_entry(&path, "ucl", "肥米").unwrap());
+        assert!(add_entry(&path, "a b", "x").is_err());
+
+        let user = load(&path).unwrap().unwrap();
+        assert_eq!(user.lookup("ucl").as_deref(), Some(&["肥米".to_string(), "肥米輸入法".to_string()][..]));
+
+        // 保留手動寫入的別名格式
+        fs::write(&path, r#"{ "chardefs": { "xo": [{ "display": "笑", "output": "😂" }] } }"#).unwrap();
+        assert!(!add_entry(&path, "xo", "笑").unwrap());
+        assert!(add_entry(&path, "xo", "哭").unwrap());
+        let user = load(&path).unwrap().unwrap();
+        assert_eq!(user.output_for("xo", "笑").as_deref(), Some("😂"));
+        assert_eq!(user.lookup("xo").unwrap().len(), 2);
+
+        // 刪除別名條目後字根還有候選；刪光時整個字根移除
+        assert!(remove_entry(&path, "XO", "笑").unwrap());
+        assert!(!remove_entry(&path, "xo", "笑").unwrap());
+        assert!(remove_entry(&path, "xo", "哭").unwrap());
+        assert!(load(&path).unwrap().unwrap().lookup("xo").is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,334 @@
+//! 字典、方案描述與設定的驗證模組
+//!
+//! 內建一份簡化的 JSON schema，載入字碼表（liu.json、符號表）與方案描述（scheme.json）時
+//! 檢查結構與型別，錯誤訊息帶 JSON 路徑（例如 `chardefs.xxx[2]`），方便表格作者除錯。
+//!
+//! 一般模式只在 serde 解析失敗時才做驗證以產生較好讀的錯誤訊息；
+//! 嚴格模式（--strict）一律驗證，並額外拒絕未知欄位與空字串。
+//!
+//! 設定（UCLLIU.ini）的各個值由各自的解析函式檢查，以 check_value 收集成同樣的錯誤清單
+//! （路徑為設定名稱，見 Config::validate），啟動時一次列出所有無效的設定。
+
+use anyhow::Result;
+use serde_json::Value;
+use std::fmt;
+
+/// 錯誤訊息最多列出的筆數
+const MAX_REPORTED_ERRORS: usize = 20;
+
+/// 內建 schema 的節點
+enum Schema {
+    /// 字串
+    String,
+    /// 字串（嚴格模式下不可為空）
+    NonEmptyString,
+    /// 正整數
+    PositiveInteger,
+    /// 整數（可為負）
+    Integer,
+    /// 可為 null
+    Nullable(&'static Schema),
+    /// 陣列，每個元素符合同一 schema
+    Array(&'static Schema),
+    /// 任意鍵的物件，每個值符合同一 schema（鍵在嚴格模式下不可為空）
+    Map(&'static Schema),
+    /// 固定欄位的物件
+    Object {
+        fields: &'static [Field],
+        /// 嚴格模式下是否仍允許未知欄位
+        allow_unknown: bool,
+    },
+    /// 符合其中任一 schema
+    AnyOf(&'static [Schema], &'static str),
+}
+
+/// 物件欄位
+struct Field {
+    name: &'static str,
+    schema: Schema,
+    required: bool,
+}
+
+/// 字碼表候選：純字串或 { "display", "output" } 別名
+const CANDIDATE_SCHEMA: Schema = Schema::AnyOf(
+    &[
+        Schema::NonEmptyString,
+        Schema::Object {
+            fields: &[
+                Field { name: "display", schema: Schema::NonEmptyString, required: true },
+                Field { name: "output", schema: Schema::NonEmptyString, required: true },
+            ],
+            allow_unknown: false,
+        },
+    ],
+    "字串或 { \"display\": 字串, \"output\": 字串 } 物件",
+);
+
+/// 字碼表：{ "chardefs": { "字根": [候選, ...] }, "weights": { "字根": { "候選": 權重 } } }
+/// 由 .cin 轉出的字碼表常帶有 cname、selkey 等其他欄位，因此頂層允許未知欄位
+const DICTIONARY_SCHEMA: Schema = Schema::Object {
+    fields: &[
+        Field { name: "chardefs", schema: Schema::Map(&Schema::Array(&CANDIDATE_SCHEMA)), required: true },
+        Field { name: "weights", schema: Schema::Map(&Schema::Map(&Schema::Integer)), required: false },
+    ],
+    allow_unknown: true,
+};
+
+/// 特殊碼規則：{ "pattern", "generator", "template" }
+const RULE_SCHEMA: Schema = Schema::Object {
+    fields: &[
+        Field { name: "pattern", schema: Schema::NonEmptyString, required: true },
+        Field { name: "generator", schema: Schema::NonEmptyString, required: true },
+        Field { name: "template", schema: Schema::Nullable(&Schema::String), required: false },
+    ],
+    allow_unknown: false,
+};
+
+/// 特殊碼規則表（rules.json）
+const RULES_SCHEMA: Schema = Schema::Array(&RULE_SCHEMA);
+
+/// 方案描述（scheme.json），所有欄位皆可省略
+const SCHEME_SCHEMA: Schema = Schema::Object {
+    fields: &[
+        Field { name: "name", schema: Schema::NonEmptyString, required: false },
+        Field { name: "dictionary", schema: Schema::NonEmptyString, required: false },
+        Field { name: "symbols", schema: Schema::Nullable(&Schema::NonEmptyString), required: false },
+        Field { name: "max_code_length", schema: Schema::PositiveInteger, required: false },
+        Field { name: "complement_keys", schema: Schema::String, required: false },
+        Field { name: "selection_keys", schema: Schema::String, required: false },
+        Field { name: "rules", schema: Schema::Array(&RULE_SCHEMA), required: false },
+    ],
+    allow_unknown: false,
+};
+
+/// 單筆驗證錯誤
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// JSON 路徑，例如 chardefs.xxx[2]
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "(根): {}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// 驗證字碼表結構
+pub fn validate_dictionary(value: &Value, strict: bool) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    validate(value, &DICTIONARY_SCHEMA, "", strict, &mut errors);
+    errors
+}
+
+/// 驗證方案描述結構
+pub fn validate_scheme(value: &Value, strict: bool) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    validate(value, &SCHEME_SCHEMA, "", strict, &mut errors);
+    errors
+}
+
+/// 驗證特殊碼規則表結構
+pub fn validate_rules(value: &Value, strict: bool) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    validate(value, &RULES_SCHEMA, "", strict, &mut errors);
+    errors
+}
+
+/// 記下一個設定值的解析結果：無法解析時以設定名稱為路徑加入錯誤清單
+pub fn check_value<T>(errors: &mut Vec<ValidationError>, name: &str, result: Result<T>) {
+    if let Err(e) = result {
+        errors.push(ValidationError { path: name.to_string(), message: format!("{:#}", e) });
+    }
+}
+
+/// 將驗證結果轉為 Result，有錯誤時列出前幾筆
+pub fn into_result(errors: Vec<ValidationError>, what: &str) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = format!("{}格式錯誤（共 {} 處）:", what, errors.len());
+    for error in errors.iter().take(MAX_REPORTED_ERRORS) {
+        message.push_str("\n  ");
+        message.push_str(&error.to_string());
+    }
+    if errors.len() > MAX_REPORTED_ERRORS {
+        message.push_str(&format!("\n  ...（其餘 {} 處省略）", errors.len() - MAX_REPORTED_ERRORS));
+    }
+    Err(anyhow::anyhow!(message))
+}
+
+fn validate(value: &Value, schema: &Schema, path: &str, strict: bool, errors: &mut Vec<ValidationError>) {
+    let push = |errors: &mut Vec<ValidationError>, message: String| {
+        errors.push(ValidationError { path: path.to_string(), message });
+    };
+
+    match schema {
+        Schema::String => {
+            if !value.is_string() {
+                push(errors, format!("應為字串，實際為{}", type_name(value)));
+            }
+        }
+        Schema::NonEmptyString => match value {
+            Value::String(s) if strict && s.is_empty() => push(errors, "不可為空字串".to_string()),
+            Value::String(_) => {}
+            other => push(errors, format!("應為字串，實際為{}", type_name(other))),
+        },
+        Schema::PositiveInteger => match value.as_u64() {
+            Some(n) if n > 0 => {}
+            _ => push(errors, format!("應為正整數，實際為 {}", value)),
+        },
+        Schema::Integer => {
+            if value.as_i64().is_none() {
+                push(errors, format!("應為整數，實際為 {}", value));
+            }
+        }
+        Schema::Nullable(inner) => {
+            if !value.is_null() {
+                validate(value, inner, path, strict, errors);
+            }
+        }
+        Schema::Array(item) => match value {
+            Value::Array(items) => {
+                for (i, v) in items.iter().enumerate() {
+                    validate(v, item, &format!("{}[{}]", path, i), strict, errors);
+                }
+            }
+            other => push(errors, format!("應為陣列，實際為{}", type_name(other))),
+        },
+        Schema::Map(item) => match value {
+            Value::Object(map) => {
+                for (key, v) in map {
+                    let child = join_path(path, key);
+                    if strict && key.is_empty() {
+                        errors.push(ValidationError {
+                            path: child.clone(),
+                            message: "鍵不可為空字串".to_string(),
+                        });
+                    }
+                    validate(v, item, &child, strict, errors);
+                }
+            }
+            other => push(errors, format!("應為物件，實際為{}", type_name(other))),
+        },
+        Schema::Object { fields, allow_unknown } => {
+            let Value::Object(map) = value else {
+                push(errors, format!("應為物件，實際為{}", type_name(value)));
+                return;
+            };
+            for field in fields.iter() {
+                match map.get(field.name) {
+                    Some(v) => validate(v, &field.schema, &join_path(path, field.name), strict, errors),
+                    None if field.required => push(errors, format!("缺少必要欄位 \"{}\"", field.name)),
+                    None => {}
+                }
+            }
+            if strict && !allow_unknown {
+                for key in map.keys() {
+                    if !fields.iter().any(|f| f.name == key) {
+                        errors.push(ValidationError {
+                            path: join_path(path, key),
+                            message: "未知欄位".to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        Schema::AnyOf(options, description) => {
+            let matched = options.iter().any(|option| {
+                let mut option_errors = Vec::new();
+                validate(value, option, path, strict, &mut option_errors);
+                option_errors.is_empty()
+            });
+            if !matched {
+                push(errors, format!("應為{}，實際為 {}", description, value));
+            }
+        }
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "布林值",
+        Value::Number(_) => "數字",
+        Value::String(_) => "字串",
+        Value::Array(_) => "陣列",
+        Value::Object(_) => "物件",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_valid_dictionary() {
+        let value = json!({
+            "cname": "嘸蝦米",
+            "chardefs": { "a": ["一", { "display": "笑", "output": "😂" }] },
+            "weights": { "a": { "笑": 5, "一": -1 } }
+        });
+        assert!(validate_dictionary(&value, true).is_empty());
+        let errors = validate_dictionary(&json!({ "chardefs": {}, "weights": { "a": { "一": "高" } } }), true);
+        assert_eq!(errors[0].path, "weights.a.一");
+    }
+
+    #[test]
+    fn test_dictionary_errors_have_paths() {
+        let value = json!({ "chardefs": { "xxx": ["一", "二", 3], "yy": "四" } });
+        let errors = validate_dictionary(&value, false);
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["chardefs.xxx[2]", "chardefs.yy"]);
+
+        let errors = validate_dictionary(&json!({ "defs": {} }), false);
+        assert_eq!(errors[0].to_string(), "(根): 缺少必要欄位 \"chardefs\"");
+    }
+
+    #[test]
+    fn test_strict_rejects_empty_and_unknown() {
+        let value = json!({ "chardefs": { "a": ["", { "display": "笑", "output": "😂", "note": 1 }] } });
+        assert!(validate_dictionary(&value, false).is_empty());
+        assert_eq!(validate_dictionary(&value, true).len(), 2);
+
+        let scheme = json!({ "name": "行列30", "complement_keys": "", "max_lenght": 4 });
+        assert!(validate_scheme(&scheme, false).is_empty());
+        let errors = validate_scheme(&scheme, true);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "max_lenght");
+    }
+
+    #[test]
+    fn test_rules_errors_have_paths() {
+        let rules = json!([{ "pattern": "^dt$", "generator": "date" }, { "pattern": "^x$" }]);
+        let errors = validate_rules(&rules, false);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "[1]");
+
+        let scheme = json!({ "rules": [{ "pattern": "^ml(\\w+)$", "generator": "template", "template": 1 }] });
+        assert_eq!(validate_scheme(&scheme, false)[0].path, "rules[0].template");
+    }
+
+    #[test]
+    fn test_into_result_message() {
+        let errors = validate_scheme(&json!({ "max_code_length": "5" }), false);
+        let message = into_result(errors, "方案描述").unwrap_err().to_string();
+        assert!(message.contains("max_code_length: 應為正整數"));
+        assert!(into_result(Vec::new(), "方案描述").is_ok());
+    }
+}
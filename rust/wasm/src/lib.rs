@@ -0,0 +1,110 @@
+//! 肥米輸入法 - 網頁版查碼元件
+//!
+//! 把輸入法的字碼表解析（uclliu lib 的 chardefs 模組）編成 WebAssembly，給官網與教學頁
+//! 查字根、反查字使用，和輸入法本體讀同一份 liu.json，結果一致。
+//!
+//! ```js
+//! import init, { LiuDictionary } from "uclliu-wasm";
+//! await init();
+//! const dict = new LiuDictionary(await (await fetch("liu.json")).text());
+//! dict.lookup("ucl");          // ["肥", ...]
+//! dict.lookup("a*d");          // 萬用字元查詢（? 代表剛好一碼，例如 a?c）
+//! dict.reverseLookup("肥");    // ["ucl", ...]
+//! ```
+
+use uclliu::chardefs::{codes_containing, is_wildcard, sort_codes, wildcard_match, CharDefs};
+use uclliu::dictionary::WILDCARD_MAX_CODES;
+use wasm_bindgen::prelude::*;
+
+/// 載入後的字碼表
+#[wasm_bindgen]
+pub struct LiuDictionary {
+    defs: CharDefs,
+}
+
+#[wasm_bindgen]
+impl LiuDictionary {
+    /// 從 liu.json 的內容建立（格式錯誤時丟出例外）
+    #[wasm_bindgen(constructor)]
+    pub fn new(json: &str) -> Result<LiuDictionary, JsError> {
+        Self::parse(json).map_err(|e| JsError::new(&e))
+    }
+
+    /// 查字根的候選；字根含 * 或 ? 時為萬用字元查詢（短的字根在前），查不到時為空陣列
+    pub fn lookup(&self, code: &str) -> Vec<String> {
+        let code = code.to_lowercase();
+        if !is_wildcard(&code) {
+            return self.defs.code_to_chars.get(&code).cloned().unwrap_or_default();
+        }
+        if code.chars().all(|c| c == '*' || c == '?') {
+            return Vec::new();
+        }
+        let mut codes: Vec<String> = self
+            .defs
+            .code_to_chars
+            .keys()
+            .filter(|candidate| wildcard_match(&code, candidate))
+            .cloned()
+            .collect();
+        sort_codes(&mut codes);
+        let mut chars: Vec<String> = Vec::new();
+        for code in codes.iter().take(WILDCARD_MAX_CODES) {
+            for ch in &self.defs.code_to_chars[code] {
+                if !chars.contains(ch) {
+                    chars.push(ch.clone());
+                }
+            }
+        }
+        chars
+    }
+
+    /// 反查：打出這個字（或詞）的所有字根，短的字根在前
+    #[wasm_bindgen(js_name = reverseLookup)]
+    pub fn reverse_lookup(&self, text: &str) -> Vec<String> {
+        let mut codes = codes_containing(&self.defs.code_to_chars, text);
+        sort_codes(&mut codes);
+        codes
+    }
+
+    /// 候選實際送出的文字（display/output 別名），沒有別名時為 undefined
+    #[wasm_bindgen(js_name = outputFor)]
+    pub fn output_for(&self, code: &str, display: &str) -> Option<String> {
+        self.defs.candidate_outputs.get(&code.to_lowercase())?.get(display).cloned()
+    }
+
+    /// 字根數
+    #[wasm_bindgen(getter, js_name = codeCount)]
+    pub fn code_count(&self) -> usize {
+        self.defs.code_to_chars.len()
+    }
+}
+
+impl LiuDictionary {
+    /// 解析字碼表（錯誤訊息給 JavaScript 顯示）
+    fn parse(json: &str) -> Result<Self, String> {
+        CharDefs::parse(json)
+            .map(|defs| Self { defs })
+            .map_err(|e| format!("無法解析字碼表: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_and_reverse_lookup() {
+        let dict = LiuDictionary::parse(
+            r#"{ "chardefs": { "ad": ["丁"], "abd": ["甲"], "ucl": ["肥", "甲"], "xo": [{ "display": "笑", "output": "😂" }] } }"#,
+        )
+        .unwrap();
+        assert_eq!(dict.lookup("UCL"), vec!["肥", "甲"]);
+        assert!(dict.lookup("zz").is_empty());
+        assert_eq!(dict.lookup("a*d"), vec!["丁", "甲"]);
+        assert!(dict.lookup("*").is_empty());
+        assert_eq!(dict.reverse_lookup("甲"), vec!["abd", "ucl"]);
+        assert_eq!(dict.output_for("xo", "笑").as_deref(), Some("😂"));
+        assert_eq!(dict.code_count(), 4);
+        assert!(LiuDictionary::parse("[]").is_err());
+    }
+}